@@ -9,7 +9,7 @@ use libp2p::plaintext::PlainText2Config;
 use libp2p::swarm::{SwarmBuilder, SwarmEvent};
 use libp2p::{dns, tcp, yamux, Multiaddr, PeerId, Swarm, Transport};
 
-use libp2p_pubsub_core::{Behaviour, Config, IdentTopic, Message};
+use libp2p_pubsub_core::{Behaviour, Config, IdentTopic, Identity, Message};
 use libp2p_pubsub_floodsub::Protocol as Floodsub;
 
 /// Set up a DNS-enabled TCP transport over the Yamux protocol.
@@ -33,10 +33,21 @@ fn new_dns_tcp_transport(keypair: &Keypair) -> Boxed<(PeerId, StreamMuxerBox)> {
 fn new_floodsub_node(keypair: &Keypair) -> Swarm<Behaviour<Floodsub>> {
     let peer_id = PeerId::from(keypair.public());
     let transport = new_dns_tcp_transport(keypair);
-    let behaviour = Behaviour::new(Config::default(), Floodsub);
+    let behaviour = Behaviour::new_with_identity(
+        Config::default(),
+        Floodsub,
+        Identity::Keypair(keypair.clone()),
+    );
     SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
 }
 
+/// Log the node preflight check warnings, once subscribed to the topic.
+fn log_preflight_warnings(node: &str, swarm: &Swarm<Behaviour<Floodsub>>) {
+    for warning in swarm.behaviour().preflight() {
+        println!("{node} > Preflight warning: {warning}");
+    }
+}
+
 /// Set up a subscriber node.
 fn setup_subscriber(sub: char, keypair: &Keypair, listen_addr: &str) -> Swarm<Behaviour<Floodsub>> {
     let mut subscriber = new_floodsub_node(keypair);
@@ -78,6 +89,20 @@ async fn new_subscriber_task(sub: char, mut subscriber: Swarm<Behaviour<Floodsub
                     );
                     return;
                 }
+                libp2p_pubsub_core::Event::Subscribed { topic } => {
+                    println!("SUBSCRIBER {sub} > Subscribed to topic: {}", topic);
+                    log_preflight_warnings(&format!("SUBSCRIBER {sub}"), &subscriber);
+                }
+                libp2p_pubsub_core::Event::Unsubscribed { topic } => {
+                    println!("SUBSCRIBER {sub} > Unsubscribed from topic: {}", topic);
+                }
+                libp2p_pubsub_core::Event::PeerMisbehaving { peer, reason, .. } => {
+                    println!(
+                        "SUBSCRIBER {sub} > Peer misbehaving: {} (reason: {:?})",
+                        peer, reason
+                    );
+                }
+                _ => {}
             },
             _ => {}
         }
@@ -197,6 +222,9 @@ async fn main() {
                     SwarmEvent::ConnectionEstablished { peer_id, connection_id, .. } => {
                         println!("PUBLISHER > Connection established with: {} (connection: {})", peer_id, connection_id);
                     },
+                    SwarmEvent::Behaviour(libp2p_pubsub_core::Event::Subscribed { .. }) => {
+                        log_preflight_warnings("PUBLISHER", &publisher);
+                    },
                     _ => {},
                 }
             }
@@ -229,6 +257,17 @@ async fn main() {
                             msg_data, message.topic
                         );
                     }
+                    libp2p_pubsub_core::Event::Subscribed { topic } => {
+                        println!("RELAY > Subscribed to topic: {}", topic);
+                        log_preflight_warnings("RELAY", &relay);
+                    }
+                    libp2p_pubsub_core::Event::Unsubscribed { topic } => {
+                        println!("RELAY > Unsubscribed from topic: {}", topic);
+                    }
+                    libp2p_pubsub_core::Event::PeerMisbehaving { peer, reason, .. } => {
+                        println!("RELAY > Peer misbehaving: {} (reason: {:?})", peer, reason);
+                    }
+                    _ => {}
                 },
                 _ => {}
             }
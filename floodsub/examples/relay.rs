@@ -0,0 +1,441 @@
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::Parser;
+use futures::{FutureExt, StreamExt};
+use libp2p::core::muxing::StreamMuxerBox;
+use libp2p::core::transport::Boxed;
+use libp2p::core::upgrade;
+use libp2p::identity::Keypair;
+use libp2p::multiaddr::Protocol;
+use libp2p::swarm::{self, SwarmEvent, THandlerErr};
+use libp2p::{dns, plaintext, tcp, yamux, Multiaddr, PeerId, Swarm, Transport};
+
+use libp2p_pubsub_core::{Behaviour, Config, Event, IdentTopic, Identity};
+use libp2p_pubsub_floodsub::Protocol as Floodsub;
+
+/// The time to wait for the unsubscriptions to be flushed to the connected peers on shutdown.
+const UNSUBSCRIBE_FLUSH_DEADLINE: Duration = Duration::from_secs(5);
+
+/// A long-running floodsub relay node.
+///
+/// The relay subscribes to the given topics, forwarding the messages received on them, and keeps
+/// its connections to the bootstrap peers alive. The per-topic forwarding counters are logged
+/// periodically. On SIGINT, the relay unsubscribes from its topics before exiting.
+#[derive(Debug, Parser)]
+struct Args {
+    /// The address to listen on.
+    #[arg(long, default_value = "/ip4/0.0.0.0/tcp/0")]
+    listen: Multiaddr,
+
+    /// A topic to relay. Can be repeated.
+    #[arg(long = "topic", required = true)]
+    topics: Vec<String>,
+
+    /// The address of a bootstrap peer, ending with its peer ID (e.g.,
+    /// `/dns4/localhost/tcp/60200/p2p/<PEER_ID>`). Can be repeated.
+    #[arg(long = "bootstrap")]
+    bootstrap: Vec<BootstrapPeer>,
+
+    /// The interval, in seconds, between two forwarding counters reports.
+    #[arg(long, default_value = "10")]
+    stats_interval: u64,
+}
+
+/// A bootstrap peer, parsed from its address ending with a `/p2p/<PEER_ID>` component.
+#[derive(Debug, Clone)]
+struct BootstrapPeer {
+    peer_id: PeerId,
+    addr: Multiaddr,
+}
+
+impl FromStr for BootstrapPeer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut addr = s.parse::<Multiaddr>().map_err(|err| err.to_string())?;
+        match addr.pop() {
+            Some(Protocol::P2p(peer_id)) => Ok(Self { peer_id, addr }),
+            _ => Err(format!("missing peer ID in bootstrap address: {s}")),
+        }
+    }
+}
+
+/// Set up a DNS-enabled TCP transport over the Yamux protocol.
+fn new_dns_tcp_transport(keypair: &Keypair) -> Boxed<(PeerId, StreamMuxerBox)> {
+    let transport = dns::tokio::Transport::system(tcp::tokio::Transport::new(
+        tcp::Config::default().nodelay(true),
+    ))
+    .expect("Failed to create DNS/-enabled TCP transport");
+
+    transport
+        .upgrade(upgrade::Version::V1)
+        .authenticate(plaintext::Config::new(keypair))
+        .multiplex(yamux::Config::default())
+        .timeout(Duration::from_secs(20))
+        .boxed()
+}
+
+/// Create a new Floodsub node with the given keypair, over the given transport.
+fn new_floodsub_node(
+    keypair: &Keypair,
+    transport: Boxed<(PeerId, StreamMuxerBox)>,
+) -> Swarm<Behaviour<Floodsub>> {
+    let peer_id = PeerId::from(keypair.public());
+    let behaviour = Behaviour::new_with_identity(
+        Config::default(),
+        Floodsub,
+        Identity::Keypair(keypair.clone()),
+    );
+    Swarm::new(
+        transport,
+        behaviour,
+        peer_id,
+        swarm::Config::with_tokio_executor(),
+    )
+}
+
+/// The relay logic, driving a Floodsub node.
+struct Relay {
+    /// The relay node.
+    swarm: Swarm<Behaviour<Floodsub>>,
+    /// The relayed topics.
+    topics: Vec<IdentTopic>,
+    /// The bootstrap peers the relay keeps connected to.
+    bootstrap: Vec<BootstrapPeer>,
+    /// The interval between two forwarding counters reports.
+    stats_interval: Duration,
+}
+
+impl Relay {
+    /// Create a new relay, subscribed to the `topics`, and dialing the `bootstrap` peers.
+    ///
+    /// The bootstrap peers are added to the node known peers, so they are re-dialed, with an
+    /// exponential backoff, whenever disconnected.
+    fn new(
+        mut swarm: Swarm<Behaviour<Floodsub>>,
+        topics: Vec<IdentTopic>,
+        bootstrap: Vec<BootstrapPeer>,
+        stats_interval: Duration,
+    ) -> Self {
+        for topic in &topics {
+            swarm
+                .behaviour_mut()
+                .subscribe(topic.clone())
+                .expect("Failed to subscribe to topic");
+        }
+
+        for peer in &bootstrap {
+            swarm
+                .behaviour_mut()
+                .add_known_peer(peer.peer_id, vec![peer.addr.clone()]);
+        }
+
+        Self {
+            swarm,
+            topics,
+            bootstrap,
+            stats_interval,
+        }
+    }
+
+    /// Run the relay until the `shutdown` future resolves. Then, unsubscribe from the relayed
+    /// topics, waiting for the unsubscriptions to be flushed to the connected peers.
+    async fn run(mut self, shutdown: impl Future<Output = ()>) {
+        let shutdown = shutdown.fuse();
+        futures::pin_mut!(shutdown);
+
+        let mut stats = tokio::time::interval(self.stats_interval);
+        loop {
+            tokio::select! {
+                event = self.swarm.select_next_some() => self.on_swarm_event(event),
+                _ = stats.tick() => self.log_forwarding_counters(),
+                _ = &mut shutdown => break,
+            }
+        }
+
+        self.unsubscribe_and_flush().await;
+    }
+
+    /// Handle a relay node swarm event.
+    fn on_swarm_event(&mut self, event: SwarmEvent<Event, THandlerErr<Behaviour<Floodsub>>>) {
+        match event {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                println!("RELAY > Listen address: {address}");
+            }
+            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                println!("RELAY > Connection established with: {peer_id}");
+            }
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established: 0,
+                ..
+            } => {
+                println!("RELAY > Disconnected from: {peer_id}");
+
+                // The known peers are pruned after repeated dial failures. Add the bootstrap
+                // peer back, so it is re-dialed until it is reachable again.
+                let pruned = !self
+                    .swarm
+                    .behaviour()
+                    .known_peers()
+                    .any(|(peer, _)| *peer == peer_id);
+                if let Some(peer) = self.bootstrap.iter().find(|p| p.peer_id == peer_id) {
+                    if pruned {
+                        self.swarm
+                            .behaviour_mut()
+                            .add_known_peer(peer.peer_id, vec![peer.addr.clone()]);
+                    }
+                }
+            }
+            SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
+                println!("RELAY > Dial failure (peer: {peer_id:?}): {error}");
+            }
+            SwarmEvent::Behaviour(Event::MessageReceived { src, message, .. }) => {
+                tracing::debug!(%src, topic = %message.topic, "Message relayed");
+            }
+            // Check the relay configuration once subscribed to all the relayed topics.
+            SwarmEvent::Behaviour(Event::Subscribed { .. })
+                if self.swarm.behaviour().subscriptions().len() == self.topics.len() =>
+            {
+                for warning in self.swarm.behaviour().preflight() {
+                    tracing::warn!(code = %warning.code, "Preflight warning: {}", warning);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Log the forwarding counters of each relayed topic.
+    fn log_forwarding_counters(&self) {
+        for topic in &self.topics {
+            let traffic = self
+                .swarm
+                .behaviour()
+                .topic_traffic(&topic.hash())
+                .unwrap_or_default();
+            println!(
+                "RELAY > Topic {}: received {} messages, forwarded {} messages ({} bytes)",
+                topic,
+                traffic.received_messages,
+                traffic.forwarded_messages,
+                traffic.forwarded_bytes
+            );
+        }
+    }
+
+    /// Unsubscribe from the relayed topics, and wait for the unsubscriptions to be flushed to the
+    /// connected peers, or the flush deadline.
+    async fn unsubscribe_and_flush(&mut self) {
+        println!("RELAY > Unsubscribing from the relayed topics");
+
+        let flush_id = self.swarm.behaviour_mut().unsubscribe_many_and_flush(
+            self.topics.iter().map(|topic| topic.hash()),
+            UNSUBSCRIBE_FLUSH_DEADLINE,
+        );
+
+        loop {
+            match self.swarm.select_next_some().await {
+                SwarmEvent::Behaviour(Event::UnsubscriptionFlushed {
+                    flush_id: id,
+                    report,
+                    ..
+                }) if id == flush_id => {
+                    println!(
+                        "RELAY > Unsubscriptions flushed to {} peers ({} failed)",
+                        report.flushed.len(),
+                        report.failed.len()
+                    );
+                    return;
+                }
+                event => self.on_swarm_event(event),
+            }
+        }
+    }
+}
+
+/// A long-running relay node. See `--help` for the command line arguments.
+///
+/// For example, relaying the messages of the `/examples/relay-topic` topic published by another
+/// node:
+/// ```text
+/// cargo run --example relay -- --listen /ip4/0.0.0.0/tcp/60201 \
+///     --topic /examples/relay-topic \
+///     --bootstrap /dns4/localhost/tcp/60200/p2p/<PEER_ID>
+/// ```
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    let keypair = Keypair::generate_ed25519();
+    let mut swarm = new_floodsub_node(&keypair, new_dns_tcp_transport(&keypair));
+
+    println!("RELAY > Peer ID: {}", swarm.local_peer_id());
+
+    swarm
+        .listen_on(args.listen)
+        .expect("Failed to listen on address");
+
+    let topics = args.topics.into_iter().map(IdentTopic::new).collect();
+    let relay = Relay::new(
+        swarm,
+        topics,
+        args.bootstrap,
+        Duration::from_secs(args.stats_interval),
+    );
+
+    relay
+        .run(async {
+            tokio::signal::ctrl_c()
+                .await
+                .expect("Failed to listen for SIGINT");
+            println!("RELAY > SIGINT received, shutting down");
+        })
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p_pubsub_core::Message;
+    use testlib::any_memory_addr;
+
+    use super::*;
+
+    /// Create a new Floodsub node over the in-memory test transport.
+    fn new_test_node() -> Swarm<Behaviour<Floodsub>> {
+        let keypair = Keypair::generate_secp256k1();
+        let transport = testlib::test_transport(&keypair);
+        new_floodsub_node(&keypair, transport)
+    }
+
+    /// Listen on a new in-memory address and return it, along with the node peer ID.
+    async fn listen(swarm: &mut Swarm<Behaviour<Floodsub>>) -> BootstrapPeer {
+        testlib::swarm::should_listen_on_address(swarm, any_memory_addr());
+        let addr = testlib::swarm::wait_for_new_listen_addr(swarm).await;
+        BootstrapPeer {
+            peer_id: *swarm.local_peer_id(),
+            addr,
+        }
+    }
+
+    /// Whether the node knows the peer is subscribed to the topic.
+    fn is_peer_subscribed(
+        swarm: &Swarm<Behaviour<Floodsub>>,
+        peer: &PeerId,
+        topic: &IdentTopic,
+    ) -> bool {
+        swarm
+            .behaviour()
+            .peer_subscriptions(peer)
+            .map_or(false, |topics| topics.contains(&topic.hash()))
+    }
+
+    /// A message published at one end of a relay chain reaches the other end in 3 hops:
+    /// ```text
+    ///   ┌───────────┐     ┌─────────┐     ┌─────────┐     ┌────────────┐
+    ///   │ PUBLISHER │◄───►│ RELAY A │◄───►│ RELAY B │◄───►│ SUBSCRIBER │
+    ///   └───────────┘     └─────────┘     └─────────┘     └────────────┘
+    /// ```
+    #[tokio::test]
+    async fn message_flows_end_to_end_through_a_relay_chain() {
+        testlib::init_logger();
+
+        //// Given
+        let topic = IdentTopic::new("/examples/relay-topic");
+
+        let mut publisher = new_test_node();
+        let publisher_addr = listen(&mut publisher).await;
+        publisher
+            .behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("Failed to subscribe to topic");
+
+        let mut relay_a = new_test_node();
+        let relay_a_addr = listen(&mut relay_a).await;
+        let relay_a = Relay::new(
+            relay_a,
+            vec![topic.clone()],
+            vec![publisher_addr.clone()],
+            Duration::from_secs(60),
+        );
+
+        let mut relay_b = new_test_node();
+        let relay_b_addr = listen(&mut relay_b).await;
+        let relay_b = Relay::new(
+            relay_b,
+            vec![topic.clone()],
+            vec![relay_a_addr.clone()],
+            Duration::from_secs(60),
+        );
+
+        let mut subscriber = new_test_node();
+        subscriber
+            .behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("Failed to subscribe to topic");
+        testlib::swarm::should_dial_address(&mut subscriber, relay_b_addr.addr.clone());
+
+        let message = Message::new(topic.clone(), b"Hello World!".to_vec());
+
+        //// When
+        let relays = futures::future::join(
+            relay_a.run(futures::future::pending()),
+            relay_b.run(futures::future::pending()),
+        );
+        let received = async {
+            // Wait for the relays subscriptions to propagate along the chain.
+            let ready = testlib::swarm::poll_mesh_until(
+                &mut [&mut publisher, &mut subscriber],
+                |swarms| {
+                    is_peer_subscribed(swarms[0], &relay_a_addr.peer_id, &topic)
+                        && is_peer_subscribed(swarms[1], &relay_b_addr.peer_id, &topic)
+                },
+                Duration::from_secs(5),
+            )
+            .await;
+            assert!(ready, "The relay chain should be established");
+            testlib::swarm::poll_mesh(Duration::from_millis(100), &mut publisher, &mut subscriber)
+                .await;
+
+            publisher
+                .behaviour_mut()
+                .publish(message.clone())
+                .expect("Failed to publish message");
+
+            tokio::select! {
+                event = testlib::swarm::wait_for_message(
+                    &mut subscriber,
+                    |ev| matches!(ev, Event::MessageReceived { .. }),
+                    Duration::from_secs(5),
+                ) => event,
+                _ = testlib::swarm::poll(&mut publisher) => unreachable!(),
+            }
+        };
+
+        let received = tokio::select! {
+            received = received => received,
+            _ = relays => unreachable!("The relays should run until shut down"),
+        };
+
+        //// Then
+        let Some(Event::MessageReceived {
+            src,
+            message: received,
+            ..
+        }) = received
+        else {
+            panic!("The subscriber should receive the message");
+        };
+        assert_eq!(
+            src, relay_b_addr.peer_id,
+            "The message should be propagated by the last relay"
+        );
+        assert_eq!(received.from, Some(publisher_addr.peer_id));
+        assert_eq!(received.data, message.data);
+        assert!(
+            !subscriber.is_connected(&publisher_addr.peer_id),
+            "The subscriber should not be directly connected to the publisher"
+        );
+    }
+}
@@ -9,7 +9,7 @@ use libp2p::plaintext::PlainText2Config;
 use libp2p::swarm::{SwarmBuilder, SwarmEvent};
 use libp2p::{dns, tcp, yamux, Multiaddr, PeerId, Swarm, Transport};
 
-use libp2p_pubsub_core::{Behaviour, Config, IdentTopic, Message};
+use libp2p_pubsub_core::{Behaviour, Config, IdentTopic, Identity, Message};
 use libp2p_pubsub_floodsub::Protocol as Floodsub;
 
 /// Set up a DNS-enabled TCP transport over the Yamux protocol.
@@ -33,10 +33,21 @@ fn new_dns_tcp_transport(keypair: &Keypair) -> Boxed<(PeerId, StreamMuxerBox)> {
 fn new_floodsub_node(keypair: &Keypair) -> Swarm<Behaviour<Floodsub>> {
     let peer_id = PeerId::from(keypair.public());
     let transport = new_dns_tcp_transport(keypair);
-    let behaviour = Behaviour::new(Config::default(), Floodsub);
+    let behaviour = Behaviour::new_with_identity(
+        Config::default(),
+        Floodsub,
+        Identity::Keypair(keypair.clone()),
+    );
     SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build()
 }
 
+/// Log the node preflight check warnings, once subscribed to the topic.
+fn log_preflight_warnings(node: &str, swarm: &Swarm<Behaviour<Floodsub>>) {
+    for warning in swarm.behaviour().preflight() {
+        println!("{node} > Preflight warning: {warning}");
+    }
+}
+
 /// The topic to publish/subscribe to.
 const PUBSUB_TOPIC: &str = "/examples/simple-topic";
 
@@ -121,6 +132,9 @@ async fn main() {
                     SwarmEvent::ConnectionEstablished { peer_id, connection_id, .. } => {
                         println!("PUBLISHER > Connection established with: {} (connection: {})", peer_id, connection_id);
                     },
+                    SwarmEvent::Behaviour(libp2p_pubsub_core::Event::Subscribed { .. }) => {
+                        log_preflight_warnings("PUBLISHER", &publisher);
+                    },
                     _ => {},
                 }
             }
@@ -164,6 +178,20 @@ async fn main() {
                         );
                         return;
                     }
+                    libp2p_pubsub_core::Event::Subscribed { topic } => {
+                        println!("SUBSCRIBER > Subscribed to topic: {}", topic);
+                        log_preflight_warnings("SUBSCRIBER", &subscriber);
+                    }
+                    libp2p_pubsub_core::Event::Unsubscribed { topic } => {
+                        println!("SUBSCRIBER > Unsubscribed from topic: {}", topic);
+                    }
+                    libp2p_pubsub_core::Event::PeerMisbehaving { peer, reason, .. } => {
+                        println!(
+                            "SUBSCRIBER > Peer misbehaving: {} (reason: {:?})",
+                            peer, reason
+                        );
+                    }
+                    _ => {}
                 },
                 _ => {}
             }
@@ -0,0 +1,540 @@
+//! A compatibility layer mimicking the `libp2p::gossipsub` behaviour API.
+//!
+//! The [`Behaviour`] wraps a floodsub [`pubsub::Behaviour`](PubsubBehaviour), and mirrors the
+//! method signatures and the events of the upstream gossipsub behaviour where the semantics allow
+//! it. An application written against `libp2p::gossipsub` can be ported by changing its import
+//! path:
+//!
+//! ```ignore
+//! // use libp2p::gossipsub;
+//! use libp2p_pubsub_floodsub::compat as gossipsub;
+//! ```
+//!
+//! Only the subset of the upstream API with a floodsub equivalent is provided. The differences
+//! with the upstream behaviour are documented on each method. Notably:
+//!
+//! - The subscriptions are processed asynchronously, on a later poll of the behaviour, and
+//!   publishing fails until then.
+//! - Publishing requires the local node to be subscribed to the topic.
+//! - The messages are flooded to every connected peer subscribed to the topic, as the floodsub
+//!   protocol does not maintain a mesh.
+//! - Only the [`Event::Message`], [`Event::Subscribed`] and [`Event::Unsubscribed`] events are
+//!   emitted. The other events of the pubsub behaviour are dropped.
+//!
+//! For the features without an upstream equivalent, use the wrapped pubsub behaviour (see
+//! [`Behaviour::inner_mut`]).
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use libp2p::core::{Endpoint, Multiaddr};
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::behaviour::ConnectionEstablished;
+use libp2p::swarm::dial_opts::DialOpts;
+use libp2p::swarm::{
+    ConnectionClosed, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, PollParameters,
+    THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, ConnectionGateDenied, Event as PubsubEvent, Identity,
+    Message as PubsubMessage,
+};
+pub use libp2p_pubsub_core::{
+    Config, Hasher, IdentTopic, IdentityHash, MessageId, PublishError, Sha256Hash, Sha256Topic,
+    Topic, TopicHash,
+};
+
+use crate::protocol::Protocol as Floodsub;
+
+/// The authenticity of the published messages, i.e., the author the local node stamps them with.
+#[derive(Clone)]
+pub enum MessageAuthenticity {
+    /// The messages are authored by the keypair peer id.
+    Signed(Keypair),
+    /// The messages are authored by the given peer id.
+    Author(PeerId),
+    /// Not supported: [`Behaviour::new`] fails.
+    RandomAuthor,
+    /// Not supported: [`Behaviour::new`] fails, as the local peer id is required. Create the
+    /// pubsub behaviour with an anonymous [`Identity`] instead, and wrap it (see
+    /// [`Behaviour::from`]).
+    Anonymous,
+}
+
+/// A builder mirroring the upstream gossipsub configuration builder.
+///
+/// Only the options with a pubsub equivalent are provided. Use the pubsub configuration builder
+/// (see [`libp2p_pubsub_core::ConfigBuilder`]) for the others.
+#[derive(Debug, Default, Clone)]
+pub struct ConfigBuilder {
+    config: libp2p_pubsub_core::ConfigBuilder,
+}
+
+impl ConfigBuilder {
+    /// The time between each heartbeat (see [`Config::heartbeat_interval`]).
+    pub fn heartbeat_interval(&mut self, interval: Duration) -> &mut Self {
+        self.config.heartbeat_interval(interval);
+        self
+    }
+
+    /// The maximum byte size for each frame (see [`Config::max_frame_size`]).
+    pub fn max_transmit_size(&mut self, max_size: usize) -> &mut Self {
+        self.config.max_frame_size(max_size);
+        self
+    }
+
+    /// The time a message id is kept to deduplicate the received messages (see
+    /// [`Config::message_cache_ttl`]).
+    pub fn duplicate_cache_time(&mut self, cache_time: Duration) -> &mut Self {
+        self.config.message_cache_ttl(cache_time);
+        self
+    }
+
+    /// Build the configuration.
+    ///
+    /// Unlike upstream, building the configuration never fails. The result is kept for source
+    /// compatibility.
+    pub fn build(&self) -> Result<Config, &'static str> {
+        Ok(self.config.build())
+    }
+}
+
+/// A received message, shaped as the upstream gossipsub message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    /// The author of the message, if any.
+    pub source: Option<PeerId>,
+    /// The data of the message.
+    pub data: Vec<u8>,
+    /// The sequence number of the message, if any.
+    ///
+    /// The sequence numbers that are not 8 bytes long, as set by some implementations, are
+    /// reported as missing.
+    pub sequence_number: Option<u64>,
+    /// The topic the message was published to.
+    pub topic: TopicHash,
+}
+
+impl From<PubsubMessage> for Message {
+    fn from(message: PubsubMessage) -> Self {
+        Self {
+            source: message.from,
+            sequence_number: message
+                .sequence_number
+                .as_deref()
+                .and_then(|seqno| seqno.try_into().ok())
+                .map(u64::from_be_bytes),
+            data: message.data,
+            topic: message.topic,
+        }
+    }
+}
+
+/// The events emitted by the compatibility [`Behaviour`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Event {
+    /// A message has been received on a topic the local node is subscribed to.
+    Message {
+        /// The peer that propagated the message.
+        propagation_source: PeerId,
+        /// The message id.
+        message_id: MessageId,
+        /// The message itself.
+        message: Message,
+    },
+    /// A remote peer subscribed to a topic.
+    Subscribed {
+        /// The subscribed remote peer.
+        peer_id: PeerId,
+        /// The topic the peer subscribed to.
+        topic: TopicHash,
+    },
+    /// A remote peer unsubscribed from a topic.
+    Unsubscribed {
+        /// The unsubscribed remote peer.
+        peer_id: PeerId,
+        /// The topic the peer unsubscribed from.
+        topic: TopicHash,
+    },
+}
+
+/// Errors that can occur when subscribing to a topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SubscriptionError {
+    /// The subscription to the topic is not allowed, e.g., the topic fails the topic validation
+    /// (see [`Config::topic_validation`]).
+    NotAllowed(String),
+}
+
+impl fmt::Display for SubscriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubscriptionError::NotAllowed(reason) => {
+                write!(f, "subscription not allowed: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SubscriptionError {}
+
+/// A floodsub behaviour mirroring the upstream gossipsub behaviour API.
+pub struct Behaviour {
+    /// The wrapped pubsub behaviour.
+    inner: PubsubBehaviour<Floodsub>,
+    /// The peers the local node keeps connections with.
+    explicit_peers: HashSet<PeerId>,
+    /// The peers whose connections are denied.
+    blacklisted_peers: HashSet<PeerId>,
+    /// The topics each connected peer is known to be subscribed to, as last reported.
+    peer_topics: HashMap<PeerId, BTreeSet<TopicHash>>,
+    /// The actions to return to the swarm on the next poll.
+    pending_actions: VecDeque<ToSwarm<Event, THandlerInEvent<PubsubBehaviour<Floodsub>>>>,
+    /// The waker of the last poll, woken when an action is queued.
+    waker: Option<Waker>,
+}
+
+impl Behaviour {
+    /// Create a new compatibility behaviour.
+    ///
+    /// Differences with upstream: the [`MessageAuthenticity::RandomAuthor`] and
+    /// [`MessageAuthenticity::Anonymous`] authenticities are not supported.
+    pub fn new(privacy: MessageAuthenticity, config: Config) -> Result<Self, &'static str> {
+        let identity = match privacy {
+            MessageAuthenticity::Signed(keypair) => Identity::Keypair(keypair),
+            MessageAuthenticity::Author(peer_id) => Identity::PeerId(peer_id),
+            MessageAuthenticity::RandomAuthor => {
+                return Err("random author message authenticity is not supported");
+            }
+            MessageAuthenticity::Anonymous => {
+                return Err("anonymous message authenticity requires the local peer id");
+            }
+        };
+
+        Ok(PubsubBehaviour::new_with_identity(config, Floodsub, identity).into())
+    }
+
+    /// The wrapped pubsub behaviour.
+    pub fn inner(&self) -> &PubsubBehaviour<Floodsub> {
+        &self.inner
+    }
+
+    /// The wrapped pubsub behaviour, e.g., to use the features without an upstream equivalent.
+    ///
+    /// The events emitted by the wrapped behaviour are not reported by this behaviour, besides the
+    /// ones mapped to [`Event`] variants.
+    pub fn inner_mut(&mut self) -> &mut PubsubBehaviour<Floodsub> {
+        &mut self.inner
+    }
+
+    /// Subscribe to a topic.
+    ///
+    /// Returns `Ok(true)` if the subscription request was accepted, `Ok(false)` if the local node
+    /// was already subscribed to the topic.
+    ///
+    /// Differences with upstream: the subscription is processed asynchronously, on a later poll of
+    /// the behaviour (see [`PubsubBehaviour::subscribe`]). Until then, publishing to the topic
+    /// fails. No subscription filter is supported, the topic is checked against the topic
+    /// validation instead (see [`Config::topic_validation`]).
+    pub fn subscribe<H: Hasher>(&mut self, topic: &Topic<H>) -> Result<bool, SubscriptionError> {
+        self.inner
+            .subscribe(topic.hash())
+            .map_err(|err| SubscriptionError::NotAllowed(err.to_string()))
+    }
+
+    /// Unsubscribe from a topic.
+    ///
+    /// Returns `Ok(true)` if the unsubscription request was accepted, `Ok(false)` if the local
+    /// node was not subscribed to the topic.
+    ///
+    /// Differences with upstream: the unsubscription is processed asynchronously, on a later poll
+    /// of the behaviour (see [`PubsubBehaviour::unsubscribe`]). It never fails: the result is kept
+    /// for source compatibility.
+    pub fn unsubscribe<H: Hasher>(&mut self, topic: &Topic<H>) -> Result<bool, PublishError> {
+        // The pubsub behaviour unsubscription is infallible.
+        Ok(self.inner.unsubscribe(topic).unwrap_or(false))
+    }
+
+    /// Publish a message to the network, and return its message id.
+    ///
+    /// Differences with upstream: publishing requires the local node to be subscribed to the
+    /// topic, as there is no fanout, and fails until the subscription is processed. It fails with
+    /// [`PublishError::NoActiveConnections`] if the local node has no connected peers, instead of
+    /// failing if no connected peer is subscribed to the topic. Publishing an already published
+    /// message does not fail: the duplicate is dropped by the receivers. See
+    /// [`PubsubBehaviour::publish`] for the other failures.
+    pub fn publish(
+        &mut self,
+        topic: impl Into<TopicHash>,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<MessageId, PublishError> {
+        self.inner.publish_with_id(PubsubMessage::new(topic, data))
+    }
+
+    /// Add a peer the local node keeps a connection with.
+    ///
+    /// The peer is dialed if not connected, and redialed once its last connection is closed.
+    ///
+    /// Differences with upstream: as floodsub floods the messages to every connected peer
+    /// subscribed to their topic, an explicit peer is treated as any other connected peer once
+    /// connected.
+    pub fn add_explicit_peer(&mut self, peer_id: &PeerId) {
+        if !self.explicit_peers.insert(*peer_id) {
+            return;
+        }
+
+        if !self.peer_topics.contains_key(peer_id) {
+            self.dial(*peer_id);
+        }
+    }
+
+    /// Remove a peer from the explicit peers. The connections with the peer are kept.
+    pub fn remove_explicit_peer(&mut self, peer_id: &PeerId) {
+        self.explicit_peers.remove(peer_id);
+    }
+
+    /// Blacklist a peer.
+    ///
+    /// The messages propagated by the peer are no longer reported.
+    ///
+    /// Differences with upstream: the connections with the peer are closed, and the new
+    /// connections are denied, so the other behaviours of the swarm cannot use them either. The
+    /// messages authored by the peer and propagated by other peers are still reported.
+    pub fn blacklist_peer(&mut self, peer_id: &PeerId) {
+        if !self.blacklisted_peers.insert(*peer_id) {
+            return;
+        }
+
+        if self.peer_topics.contains_key(peer_id) {
+            self.queue_action(ToSwarm::CloseConnection {
+                peer_id: *peer_id,
+                connection: Default::default(),
+            });
+        }
+    }
+
+    /// Remove a peer from the blacklist. The peer connections are allowed again.
+    pub fn remove_blacklisted_peer(&mut self, peer_id: &PeerId) {
+        self.blacklisted_peers.remove(peer_id);
+    }
+
+    /// Queue a dial to the peer.
+    fn dial(&mut self, peer_id: PeerId) {
+        self.queue_action(ToSwarm::Dial {
+            opts: DialOpts::peer_id(peer_id).build(),
+        });
+    }
+
+    /// Queue an action to return to the swarm, and wake the behaviour up.
+    fn queue_action(&mut self, action: ToSwarm<Event, THandlerInEvent<PubsubBehaviour<Floodsub>>>) {
+        self.pending_actions.push_back(action);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Deny the connections with the blacklisted peers.
+    fn check_blacklist(&self, peer_id: PeerId) -> Result<(), ConnectionDenied> {
+        if self.blacklisted_peers.contains(&peer_id) {
+            return Err(ConnectionDenied::new(ConnectionGateDenied {
+                peer: peer_id,
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Compare the connected peers subscriptions with the last reported ones, and queue the
+    /// subscription events of the changed ones.
+    ///
+    /// The pubsub behaviour does not report the remote peers subscriptions changes, so they are
+    /// compared on every poll of the pubsub behaviour returning pending.
+    fn report_peer_subscriptions(&mut self) {
+        for (peer_id, reported) in self.peer_topics.iter_mut() {
+            let Some(current) = self.inner.peer_subscriptions(peer_id) else {
+                continue;
+            };
+            if reported == current {
+                continue;
+            }
+
+            for topic in current.difference(reported) {
+                self.pending_actions
+                    .push_back(ToSwarm::GenerateEvent(Event::Subscribed {
+                        peer_id: *peer_id,
+                        topic: topic.clone(),
+                    }));
+            }
+            for topic in reported.difference(current) {
+                self.pending_actions
+                    .push_back(ToSwarm::GenerateEvent(Event::Unsubscribed {
+                        peer_id: *peer_id,
+                        topic: topic.clone(),
+                    }));
+            }
+
+            *reported = current.clone();
+        }
+    }
+
+    /// Map the pubsub behaviour event to the compatibility event, if any.
+    fn map_event(&self, event: PubsubEvent) -> Option<Event> {
+        match event {
+            PubsubEvent::MessageReceived {
+                src,
+                message,
+                message_id,
+                ..
+            } if !self.blacklisted_peers.contains(&src) => Some(Event::Message {
+                propagation_source: src,
+                message_id,
+                message: message.into(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl From<PubsubBehaviour<Floodsub>> for Behaviour {
+    /// Wrap the pubsub behaviour, e.g., created with an [`Identity`] not supported by
+    /// [`Behaviour::new`].
+    fn from(inner: PubsubBehaviour<Floodsub>) -> Self {
+        Self {
+            inner,
+            explicit_peers: Default::default(),
+            blacklisted_peers: Default::default(),
+            peer_topics: Default::default(),
+            pending_actions: Default::default(),
+            waker: None,
+        }
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = THandler<PubsubBehaviour<Floodsub>>;
+    type ToSwarm = Event;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check_blacklist(peer)?;
+        self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        if let Some(peer) = maybe_peer {
+            self.check_blacklist(peer)?;
+        }
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check_blacklist(peer)?;
+        self.inner
+            .handle_established_outbound_connection(connection_id, peer, addr, role_override)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm<Self::ConnectionHandler>) {
+        match &event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished { peer_id, .. }) => {
+                self.peer_topics.entry(*peer_id).or_default();
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed {
+                peer_id,
+                remaining_established: 0,
+                ..
+            }) => {
+                self.peer_topics.remove(peer_id);
+                if self.explicit_peers.contains(peer_id)
+                    && !self.blacklisted_peers.contains(peer_id)
+                {
+                    self.dial(*peer_id);
+                }
+            }
+            _ => {}
+        }
+
+        self.inner.on_swarm_event(event);
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+        params: &mut impl PollParameters,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        loop {
+            if let Some(action) = self.pending_actions.pop_front() {
+                return Poll::Ready(action);
+            }
+
+            match self.inner.poll(cx, params) {
+                Poll::Ready(ToSwarm::GenerateEvent(event)) => {
+                    if let Some(event) = self.map_event(event) {
+                        return Poll::Ready(ToSwarm::GenerateEvent(event));
+                    }
+                }
+                Poll::Ready(action) => {
+                    return Poll::Ready(action.map_out(|_| unreachable!("event already mapped")));
+                }
+                Poll::Pending => {
+                    self.report_peer_subscriptions();
+                    if self.pending_actions.is_empty() {
+                        self.waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
 pub use protocol::{Protocol, PROTOCOL_ID};
 pub use router::Router;
 
+#[cfg(feature = "compat")]
+pub mod compat;
 mod protocol;
 mod router;
@@ -1,3 +1,5 @@
+use libp2p::identity::PeerId;
+
 use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
 
 use crate::router::Router;
@@ -17,7 +19,7 @@ impl libp2p_pubsub_core::protocol::Protocol for Protocol {
         SimpleProtocolUpgrade::new(PROTOCOL_ID)
     }
 
-    fn router(&self) -> Self::RouterService {
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
         Default::default()
     }
 }
@@ -1,4 +1,5 @@
 use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
 
 use libp2p::identity::PeerId;
 
@@ -7,7 +8,7 @@ use libp2p_pubsub_core::protocol::{
     ProtocolRouterConnectionEvent, ProtocolRouterInEvent, ProtocolRouterMessageEvent,
     ProtocolRouterOutEvent, ProtocolRouterSubscriptionEvent,
 };
-use libp2p_pubsub_core::TopicHash;
+use libp2p_pubsub_core::{BackoffTracker, ControlMessage, ForwardFanout, TopicHash};
 
 /// The `Router` struct is the implementation of the [`ProtocolRouter`](
 /// libp2p_pubsub_core::protocol::ProtocolRouter) trait for the floodsub protocol.
@@ -22,9 +23,30 @@ pub struct Router {
     /// Peers are added to this map when they send the router a message with a topic they are
     /// subscribed to. They are removed on disconnection.
     routing_table: HashMap<TopicHash, BTreeSet<PeerId>>,
+
+    /// The peers backed off on a topic by a `Prune` control message.
+    ///
+    /// Backed off peers are excluded from the topic's message forwarding until the backoff
+    /// expires.
+    backoffs: BackoffTracker,
+
+    /// The forwarded and published messages destination peers selector.
+    ///
+    /// By default, the messages are forwarded to all the subscribed peers.
+    fanout: ForwardFanout,
 }
 
 impl Router {
+    /// Create a new router applying the given backoff to the `Prune` control messages that carry
+    /// no backoff.
+    #[must_use]
+    pub fn with_default_prune_backoff(backoff: Duration) -> Self {
+        Self {
+            backoffs: BackoffTracker::new(backoff),
+            ..Default::default()
+        }
+    }
+
     /// Track the local node subscription to a topic.
     fn add_subscription(&mut self, topic: TopicHash) -> bool {
         self.subscriptions.insert(topic)
@@ -79,11 +101,34 @@ impl Router {
         }
     }
 
-    /// Get the peers subscribed to a topic.
+    /// Replace the local node subscriptions and the routing table with the given state.
+    ///
+    /// The peers backoffs are kept, as they are not tracked by the behaviour.
+    fn sync_state(
+        &mut self,
+        local_topics: BTreeSet<TopicHash>,
+        peers: Vec<(PeerId, BTreeSet<TopicHash>)>,
+    ) {
+        self.subscriptions = local_topics;
+        self.routing_table.clear();
+        for (peer, topics) in peers {
+            for topic in topics {
+                self.add_peer_subscription(peer, topic);
+            }
+        }
+    }
+
+    /// Get the peers subscribed to a topic and not backed off on it.
     ///
-    /// Returns a reference to the set of peers subscribed to the topic, if any.
-    fn get_peers_subscribed(&self, topic: &TopicHash) -> Option<&BTreeSet<PeerId>> {
-        self.routing_table.get(topic)
+    /// Returns `None` if no peers are subscribed to the topic.
+    fn get_peers_subscribed(&self, topic: &TopicHash) -> Option<Vec<PeerId>> {
+        self.routing_table.get(topic).map(|peers| {
+            peers
+                .iter()
+                .filter(|peer| !self.backoffs.is_backed_off(peer, topic))
+                .cloned()
+                .collect()
+        })
     }
 }
 
@@ -97,11 +142,20 @@ impl EventHandler for Router {
         ev: Self::InEvent,
     ) {
         match ev {
-            ProtocolRouterInEvent::ConnectionEvent(conn_ev) => {
-                if let ProtocolRouterConnectionEvent::PeerDisconnected(peer) = conn_ev {
+            ProtocolRouterInEvent::ConnectionEvent(conn_ev) => match conn_ev {
+                ProtocolRouterConnectionEvent::PeerDisconnected(peer) => {
                     self.remove_peer(&peer);
+                    self.backoffs.remove_peer(&peer);
+                    self.fanout.set_deprioritized(peer, false);
                 }
-            }
+                ProtocolRouterConnectionEvent::PeerDeprioritized {
+                    peer,
+                    deprioritized,
+                } => {
+                    self.fanout.set_deprioritized(peer, deprioritized);
+                }
+                _ => {}
+            },
             ProtocolRouterInEvent::SubscriptionEvent(sub_ev) => match sub_ev {
                 ProtocolRouterSubscriptionEvent::Subscribed(sub) => {
                     self.add_subscription(sub.topic);
@@ -115,11 +169,23 @@ impl EventHandler for Router {
                 ProtocolRouterSubscriptionEvent::PeerUnsubscribed { peer, topic } => {
                     self.remove_peer_subscription(&peer, &topic);
                 }
+                ProtocolRouterSubscriptionEvent::PeerSubscribedMany { peer, topics } => {
+                    for topic in topics {
+                        self.add_peer_subscription(peer, topic);
+                    }
+                }
+                ProtocolRouterSubscriptionEvent::PeerUnsubscribedMany { peer, topics } => {
+                    for topic in &topics {
+                        self.remove_peer_subscription(&peer, topic);
+                    }
+                }
+                _ => {}
             },
             ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessageReceived {
                 src,
                 message,
-                ..
+                message_id,
+                exclude,
             }) => {
                 let topic = message.topic();
                 if !self.is_subscribed(&topic) {
@@ -127,11 +193,8 @@ impl EventHandler for Router {
                 }
 
                 if let Some(peers) = self.get_peers_subscribed(&topic) {
-                    let peers = peers
-                        .iter()
-                        .filter(|p| **p != src)
-                        .cloned()
-                        .collect::<Vec<_>>();
+                    let peers = peers.into_iter().filter(|peer| !exclude.contains(peer));
+                    let peers = self.fanout.select_forward(&message_id, &src, peers);
                     if peers.is_empty() {
                         return;
                     }
@@ -144,15 +207,29 @@ impl EventHandler for Router {
             }
             ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessagePublished {
                 message,
-                ..
+                message_id,
+                dest,
             }) => {
                 let topic = message.topic();
                 if !self.is_subscribed(&topic) {
                     return;
                 }
 
+                // Send the round-robin topic message to the selected peer only.
+                if let Some(dest) = dest {
+                    svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage {
+                        dest: vec![dest],
+                        message,
+                    });
+                    return;
+                }
+
                 if let Some(peers) = self.get_peers_subscribed(&topic) {
-                    let peers = peers.iter().cloned().collect::<Vec<_>>();
+                    let peers = self.fanout.select_publish(&message_id, peers);
+                    if peers.is_empty() {
+                        return;
+                    }
+
                     svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage {
                         dest: peers,
                         message,
@@ -161,9 +238,24 @@ impl EventHandler for Router {
                     tracing::debug!("No peers subscribed to topic: {:?}", topic);
                 }
             }
-            ProtocolRouterInEvent::ControlEvent(_) => {
-                // No-op
+            ProtocolRouterInEvent::ControlEvent(ctrl_ev) => {
+                if let ControlMessage::Prune(prune) = &ctrl_ev.message {
+                    self.backoffs.on_prune(ctrl_ev.src, prune);
+                }
+            }
+            ProtocolRouterInEvent::Heartbeat => {
+                self.backoffs.heartbeat();
+            }
+            ProtocolRouterInEvent::ForwardFanout(fanout) => {
+                self.fanout = fanout;
+            }
+            ProtocolRouterInEvent::StateSync {
+                local_topics,
+                peers,
+            } => {
+                self.sync_state(local_topics, peers);
             }
+            _ => {}
         }
     }
 }
@@ -1,14 +1,18 @@
 use std::rc::Rc;
+use std::time::Duration;
 
 use assert_matches::assert_matches;
 use libp2p::identity::PeerId;
 use rand::random;
 
+use libp2p_pubsub_common::service::BufferedContext;
 use libp2p_pubsub_core::protocol::{
-    ProtocolRouterConnectionEvent, ProtocolRouterInEvent, ProtocolRouterMessageEvent,
-    ProtocolRouterOutEvent, ProtocolRouterSubscriptionEvent,
+    ProtocolRouterConnectionEvent, ProtocolRouterControlEvent, ProtocolRouterInEvent,
+    ProtocolRouterMessageEvent, ProtocolRouterOutEvent, ProtocolRouterSubscriptionEvent,
+};
+use libp2p_pubsub_core::{
+    ControlMessage, ForwardFanout, FrameMessage, MessageId, PruneControlMessage, TopicHash,
 };
-use libp2p_pubsub_core::{FrameMessage, MessageId, TopicHash};
 use testlib::service::noop_context;
 
 use super::Router;
@@ -43,6 +47,7 @@ fn new_received_message_seq(
             src,
             message: Rc::new(new_test_message(topic)),
             message_id: new_test_message_id(),
+            exclude: Default::default(),
         },
     )]
 }
@@ -53,6 +58,7 @@ fn new_published_message_seq(topic: TopicHash) -> impl IntoIterator<Item = Proto
         ProtocolRouterMessageEvent::MessagePublished {
             message: Rc::new(new_test_message(topic)),
             message_id: new_test_message_id(),
+            dest: None,
         },
     )]
 }
@@ -98,6 +104,108 @@ fn new_peer_unsubscribed_seq(
     )]
 }
 
+/// Create a new batched peer subscription sequence for the given peer and topics.
+fn new_peer_subscribed_many_seq(
+    peer: PeerId,
+    topics: Vec<TopicHash>,
+) -> impl IntoIterator<Item = ProtocolRouterInEvent> {
+    [ProtocolRouterInEvent::SubscriptionEvent(
+        ProtocolRouterSubscriptionEvent::PeerSubscribedMany { peer, topics },
+    )]
+}
+
+/// Create a new batched peer unsubscription sequence for the given peer and topics.
+fn new_peer_unsubscribed_many_seq(
+    peer: PeerId,
+    topics: Vec<TopicHash>,
+) -> impl IntoIterator<Item = ProtocolRouterInEvent> {
+    [ProtocolRouterInEvent::SubscriptionEvent(
+        ProtocolRouterSubscriptionEvent::PeerUnsubscribedMany { peer, topics },
+    )]
+}
+
+/// Create a new prune control message received sequence for the given peer and topic.
+fn new_prune_received_seq(
+    src: PeerId,
+    topic: TopicHash,
+) -> impl IntoIterator<Item = ProtocolRouterInEvent> {
+    [ProtocolRouterInEvent::ControlEvent(
+        ProtocolRouterControlEvent::new(
+            src,
+            ControlMessage::Prune(PruneControlMessage {
+                topic_hash: topic,
+                peers: Vec::new(),
+                backoff: None,
+            }),
+        ),
+    )]
+}
+
+/// Create a new forward fan-out sequence with the given seed and caps.
+fn new_forward_fanout_seq(
+    seed: u64,
+    max_forward: Option<usize>,
+    max_publish: Option<usize>,
+) -> impl IntoIterator<Item = ProtocolRouterInEvent> {
+    [ProtocolRouterInEvent::ForwardFanout(ForwardFanout::new(
+        seed,
+        max_forward,
+        max_publish,
+    ))]
+}
+
+/// Create a new message received sequence for the given message.
+fn new_received_message_with_id_seq(
+    src: PeerId,
+    message: Rc<FrameMessage>,
+    message_id: MessageId,
+) -> impl IntoIterator<Item = ProtocolRouterInEvent> {
+    [ProtocolRouterInEvent::MessageEvent(
+        ProtocolRouterMessageEvent::MessageReceived {
+            src,
+            message,
+            message_id,
+            exclude: Default::default(),
+        },
+    )]
+}
+
+/// Create a new router state sync sequence with the given local node and peers subscriptions.
+fn new_state_sync_seq(
+    local_topics: impl IntoIterator<Item = TopicHash>,
+    peers: impl IntoIterator<Item = (PeerId, Vec<TopicHash>)>,
+) -> impl IntoIterator<Item = ProtocolRouterInEvent> {
+    [ProtocolRouterInEvent::StateSync {
+        local_topics: local_topics.into_iter().collect(),
+        peers: peers
+            .into_iter()
+            .map(|(peer, topics)| (peer, topics.into_iter().collect()))
+            .collect(),
+    }]
+}
+
+/// Create a router service with the given fan-out, the local node subscribed to the topic and
+/// the given peers subscribed to it.
+fn new_test_fanout_service(
+    fanout: impl IntoIterator<Item = ProtocolRouterInEvent>,
+    topic: &TopicHash,
+    peers: &[PeerId],
+) -> BufferedContext<Router> {
+    let mut service = testlib::service::default_test_service::<Router>();
+
+    let input_events = itertools::chain!(
+        fanout,
+        new_subscribe_seq(topic.clone()),
+        peers
+            .iter()
+            .flat_map(|peer| new_peer_subscribed_seq(*peer, topic.clone())),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    service
+}
+
 #[test]
 fn do_not_forward_a_message_if_not_subscribed() {
     //// Given
@@ -293,6 +401,44 @@ fn publish_a_message_to_all_peers_subscribed() {
     });
 }
 
+#[test]
+fn publish_a_round_robin_message_to_the_selected_peer_only() {
+    //// Given
+    let topic = new_test_topic();
+    let remote_peer_a = new_test_peer_id();
+    let remote_peer_b = new_test_peer_id();
+
+    let mut service = testlib::service::default_test_service::<Router>();
+
+    // Simulate the local node and peers subscriptions
+    let input_events = itertools::chain!(
+        new_subscribe_seq(topic.clone()),
+        new_peer_subscribed_seq(remote_peer_a, topic.clone()),
+        new_peer_subscribed_seq(remote_peer_b, topic.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    // Simulate the publication of a message sent to peer B only
+    let input_events = [ProtocolRouterInEvent::MessageEvent(
+        ProtocolRouterMessageEvent::MessagePublished {
+            message: Rc::new(new_test_message(topic.clone())),
+            message_id: new_test_message_id(),
+            dest: Some(remote_peer_b),
+        },
+    )];
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events.len(), 1, "A message should be forwarded");
+    assert_matches!(&output_events[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+        assert_eq!(dest, &[remote_peer_b], "The message should be forwarded to peer B only");
+    });
+}
+
 #[test]
 fn forward_a_message_to_all_peers_subscribed_except_the_sender() {
     //// Given
@@ -337,6 +483,55 @@ fn forward_a_message_to_all_peers_subscribed_except_the_sender() {
     });
 }
 
+#[test]
+fn forward_a_message_to_all_peers_subscribed_except_the_excluded_peers() {
+    //// Given
+    let topic = new_test_topic();
+    let local_peer = new_test_peer_id();
+    let remote_peer_a = new_test_peer_id();
+    let remote_peer_b = new_test_peer_id();
+    let remote_peer_c = new_test_peer_id();
+
+    let mut service = testlib::service::default_test_service::<Router>();
+
+    // Simulate the local node and peers subscriptions
+    let input_events = itertools::chain!(
+        new_subscribe_seq(topic.clone()),
+        new_peer_subscribed_seq(remote_peer_a, topic.clone()),
+        new_peer_subscribed_seq(remote_peer_b, topic.clone()),
+        new_peer_subscribed_seq(remote_peer_c, topic.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    // Simulate the local node forwarding a message, excluding the remote peer B
+    let input_events = [ProtocolRouterInEvent::MessageEvent(
+        ProtocolRouterMessageEvent::MessageReceived {
+            src: local_peer,
+            message: Rc::new(new_test_message(topic.clone())),
+            message_id: new_test_message_id(),
+            exclude: [remote_peer_b].into(),
+        },
+    )];
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        output_events.len(),
+        1,
+        "A message forward event should be emitted"
+    );
+    assert_matches!(&output_events[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+        assert_eq!(dest.len(), 2, "The message should be forwarded to 2 peers");
+        assert!(dest.contains(&remote_peer_a), "The message should be forwarded to peer A");
+        assert!(!dest.contains(&remote_peer_b), "The message should not be forwarded to peer B");
+        assert!(dest.contains(&remote_peer_c), "The message should be forwarded to peer C");
+    });
+}
+
 #[test]
 fn topic_should_be_removed_from_routing_table_if_no_remaining_peers() {
     //// Given
@@ -490,3 +685,618 @@ fn peer_should_be_removed_from_routing_table_on_disconnect() {
         assert_eq!(&message.topic(), &topic_b, "The message should be on topic");
     });
 }
+
+#[test]
+fn batched_peer_subscriptions_produce_the_same_routing_state() {
+    //// Given
+    let topics = [new_test_topic(), new_test_topic(), new_test_topic()];
+    let remote_peer_a = new_test_peer_id();
+    let remote_peer_b = new_test_peer_id();
+
+    let mut singular_service = testlib::service::default_test_service::<Router>();
+    let mut batched_service = testlib::service::default_test_service::<Router>();
+
+    let local_subscriptions = topics
+        .iter()
+        .cloned()
+        .flat_map(new_subscribe_seq)
+        .collect::<Vec<_>>();
+    testlib::service::inject_events(&mut singular_service, local_subscriptions.clone());
+    testlib::service::inject_events(&mut batched_service, local_subscriptions);
+
+    //// When
+    // Peer A subscribes to all the topics and unsubscribes from the second one, and Peer B
+    // subscribes to the first two topics.
+    let singular_events = itertools::chain!(
+        topics
+            .iter()
+            .flat_map(|topic| new_peer_subscribed_seq(remote_peer_a, topic.clone())),
+        new_peer_subscribed_seq(remote_peer_b, topics[0].clone()),
+        new_peer_subscribed_seq(remote_peer_b, topics[1].clone()),
+        new_peer_unsubscribed_seq(remote_peer_a, topics[1].clone()),
+    );
+    testlib::service::inject_events(&mut singular_service, singular_events);
+
+    let batched_events = itertools::chain!(
+        new_peer_subscribed_many_seq(remote_peer_a, topics.to_vec()),
+        new_peer_subscribed_many_seq(remote_peer_b, topics[0..2].to_vec()),
+        new_peer_unsubscribed_many_seq(remote_peer_a, vec![topics[1].clone()]),
+    );
+    testlib::service::inject_events(&mut batched_service, batched_events);
+
+    let published_messages = topics
+        .iter()
+        .cloned()
+        .flat_map(new_published_message_seq)
+        .collect::<Vec<_>>();
+    testlib::service::inject_events(&mut singular_service, published_messages.clone());
+    testlib::service::inject_events(&mut batched_service, published_messages);
+
+    let singular_output =
+        testlib::service::collect_events(&mut singular_service, &mut noop_context());
+    let batched_output =
+        testlib::service::collect_events(&mut batched_service, &mut noop_context());
+
+    //// Then
+    let forwarded = |events: Vec<ProtocolRouterOutEvent>| {
+        events
+            .into_iter()
+            .map(|ev| {
+                assert_matches!(ev, ProtocolRouterOutEvent::ForwardMessage { mut dest, message } => {
+                    dest.sort();
+                    (message.topic(), dest)
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let singular_forwarded = forwarded(singular_output);
+    let batched_forwarded = forwarded(batched_output);
+
+    assert_eq!(
+        singular_forwarded.len(),
+        3,
+        "A message per topic should be forwarded"
+    );
+    assert_eq!(batched_forwarded, singular_forwarded);
+
+    let mut both_peers = vec![remote_peer_a, remote_peer_b];
+    both_peers.sort();
+    assert_eq!(batched_forwarded[0], (topics[0].clone(), both_peers));
+    assert_eq!(
+        batched_forwarded[1],
+        (topics[1].clone(), vec![remote_peer_b])
+    );
+    assert_eq!(
+        batched_forwarded[2],
+        (topics[2].clone(), vec![remote_peer_a])
+    );
+}
+
+#[tokio::test]
+async fn do_not_forward_a_message_to_a_backed_off_peer_until_expiry() {
+    //// Given
+    let topic = new_test_topic();
+    let remote_peer_a = new_test_peer_id();
+    let remote_peer_b = new_test_peer_id();
+
+    let mut service = BufferedContext::new(Router::with_default_prune_backoff(
+        Duration::from_millis(50),
+    ));
+
+    // Simulate the local node and peers subscriptions
+    let input_events = itertools::chain!(
+        new_subscribe_seq(topic.clone()),
+        new_peer_subscribed_seq(remote_peer_a, topic.clone()),
+        new_peer_subscribed_seq(remote_peer_b, topic.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    // Simulate the reception of a prune control message from the remote peer A
+    let input_events = itertools::chain!(
+        new_prune_received_seq(remote_peer_a, topic.clone()),
+        new_published_message_seq(topic.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events_backed_off =
+        testlib::service::collect_events(&mut service, &mut noop_context());
+
+    // Wait for the backoff to expire
+    tokio::time::sleep(Duration::from_millis(60)).await;
+
+    let input_events = new_published_message_seq(topic.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events_expired = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events_backed_off.len(), 1);
+    assert_matches!(&output_events_backed_off[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+        assert_eq!(dest, &vec![remote_peer_b], "The message should only be forwarded to peer B");
+    });
+
+    assert_eq!(output_events_expired.len(), 1);
+    assert_matches!(&output_events_expired[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+        assert_eq!(dest.len(), 2, "The message should be forwarded to 2 peers");
+        assert!(dest.contains(&remote_peer_a), "The message should be forwarded to peer A");
+    });
+}
+
+#[test]
+fn forwarded_message_destinations_are_capped_to_the_max_forward_fanout() {
+    //// Given
+    let topic = new_test_topic();
+    let src = new_test_peer_id();
+    let peers = (0..32).map(|_| new_test_peer_id()).collect::<Vec<_>>();
+
+    let mut service = new_test_fanout_service(
+        new_forward_fanout_seq(42, Some(4), None),
+        &topic,
+        &[&peers[..], &[src]].concat(),
+    );
+
+    //// When
+    let input_events = new_received_message_seq(src, topic.clone());
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        output_events.len(),
+        1,
+        "A message forward event should be emitted"
+    );
+    assert_matches!(&output_events[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+        assert_eq!(dest.len(), 4, "The message should be forwarded to 4 peers");
+        assert!(!dest.contains(&src), "The message should not be forwarded to the sender");
+        assert!(dest.iter().all(|peer| peers.contains(peer)), "The message should be forwarded to subscribed peers");
+    });
+}
+
+#[test]
+fn published_message_destinations_are_capped_to_the_max_publish_fanout() {
+    //// Given
+    let topic = new_test_topic();
+    let peers = (0..32).map(|_| new_test_peer_id()).collect::<Vec<_>>();
+
+    let mut service =
+        new_test_fanout_service(new_forward_fanout_seq(42, Some(4), Some(8)), &topic, &peers);
+
+    //// When
+    let input_events = new_published_message_seq(topic.clone());
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        output_events.len(),
+        1,
+        "A message forward event should be emitted"
+    );
+    assert_matches!(&output_events[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+        assert_eq!(dest.len(), 8, "The message should be sent to 8 peers");
+    });
+}
+
+#[test]
+fn forward_fanout_selection_is_deterministic_for_a_seed_and_message() {
+    //// Given
+    let topic = new_test_topic();
+    let src = new_test_peer_id();
+    let peers = (0..32).map(|_| new_test_peer_id()).collect::<Vec<_>>();
+    let message = Rc::new(new_test_message(topic.clone()));
+    let message_id = new_test_message_id();
+
+    let mut service_a =
+        new_test_fanout_service(new_forward_fanout_seq(42, Some(4), None), &topic, &peers);
+    let mut service_b =
+        new_test_fanout_service(new_forward_fanout_seq(42, Some(4), None), &topic, &peers);
+    let mut service_c =
+        new_test_fanout_service(new_forward_fanout_seq(7, Some(4), None), &topic, &peers);
+
+    //// When
+    let forward = |service: &mut BufferedContext<Router>| {
+        let input_events =
+            new_received_message_with_id_seq(src, message.clone(), message_id.clone());
+        testlib::service::inject_events(service, input_events);
+        testlib::service::collect_events(service, &mut noop_context())
+    };
+    let output_events_a = forward(&mut service_a);
+    let output_events_b = forward(&mut service_b);
+    let output_events_c = forward(&mut service_c);
+
+    //// Then
+    assert_matches!(
+        (&output_events_a[..], &output_events_b[..], &output_events_c[..]),
+        (
+            [ProtocolRouterOutEvent::ForwardMessage { dest: dest_a, .. }],
+            [ProtocolRouterOutEvent::ForwardMessage { dest: dest_b, .. }],
+            [ProtocolRouterOutEvent::ForwardMessage { dest: dest_c, .. }],
+        ) => {
+            assert_eq!(dest_a, dest_b, "The same seed should select the same peers");
+            assert_ne!(dest_a, dest_c, "A different seed should select different peers");
+        }
+    );
+}
+
+#[test]
+fn forward_fanout_below_the_cap_forwards_to_all_peers_except_the_sender() {
+    //// Given
+    let topic = new_test_topic();
+    let src = new_test_peer_id();
+    let remote_peer_a = new_test_peer_id();
+    let remote_peer_b = new_test_peer_id();
+
+    let mut service = new_test_fanout_service(
+        new_forward_fanout_seq(42, Some(4), None),
+        &topic,
+        &[src, remote_peer_a, remote_peer_b],
+    );
+
+    //// When
+    let input_events = new_received_message_seq(src, topic.clone());
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        output_events.len(),
+        1,
+        "A message forward event should be emitted"
+    );
+    assert_matches!(&output_events[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+        assert_eq!(dest.len(), 2, "The message should be forwarded to 2 peers");
+        assert!(!dest.contains(&src), "The message should not be forwarded to the sender");
+    });
+}
+
+/// Create a new peer deprioritization sequence for the given peer.
+fn new_peer_deprioritized_seq(
+    peer: PeerId,
+    deprioritized: bool,
+) -> impl IntoIterator<Item = ProtocolRouterInEvent> {
+    [ProtocolRouterInEvent::ConnectionEvent(
+        ProtocolRouterConnectionEvent::PeerDeprioritized {
+            peer,
+            deprioritized,
+        },
+    )]
+}
+
+#[test]
+fn capped_forward_fanout_picks_the_deprioritized_peers_last() {
+    //// Given
+    let topic = new_test_topic();
+    let src = new_test_peer_id();
+    let healthy_peers = (0..4).map(|_| new_test_peer_id()).collect::<Vec<_>>();
+    let deprioritized_peers = (0..4).map(|_| new_test_peer_id()).collect::<Vec<_>>();
+
+    let mut service = new_test_fanout_service(
+        new_forward_fanout_seq(42, Some(4), None),
+        &topic,
+        &[&healthy_peers[..], &deprioritized_peers[..], &[src]].concat(),
+    );
+
+    let input_events = deprioritized_peers
+        .iter()
+        .flat_map(|peer| new_peer_deprioritized_seq(*peer, true));
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    let input_events = new_received_message_seq(src, topic.clone());
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_matches!(&output_events[..], [ProtocolRouterOutEvent::ForwardMessage { dest, .. }] => {
+        assert_eq!(dest.len(), 4, "The message should be forwarded to 4 peers");
+        assert!(dest.iter().all(|peer| healthy_peers.contains(peer)), "The message should be forwarded to the non-deprioritized peers");
+    });
+}
+
+#[test]
+fn restored_peers_are_no_longer_deprioritized() {
+    //// Given
+    let topic = new_test_topic();
+    let src = new_test_peer_id();
+    let peers = (0..8).map(|_| new_test_peer_id()).collect::<Vec<_>>();
+    let message = Rc::new(new_test_message(topic.clone()));
+    let message_id = new_test_message_id();
+
+    let mut service_a = new_test_fanout_service(
+        new_forward_fanout_seq(42, Some(4), None),
+        &topic,
+        &[&peers[..], &[src]].concat(),
+    );
+    let mut service_b = new_test_fanout_service(
+        new_forward_fanout_seq(42, Some(4), None),
+        &topic,
+        &[&peers[..], &[src]].concat(),
+    );
+
+    // Deprioritize, then restore, all the peers of the service A.
+    let input_events = itertools::chain!(
+        peers
+            .iter()
+            .flat_map(|peer| new_peer_deprioritized_seq(*peer, true)),
+        peers
+            .iter()
+            .flat_map(|peer| new_peer_deprioritized_seq(*peer, false)),
+    );
+    testlib::service::inject_events(&mut service_a, input_events);
+    testlib::service::poll(&mut service_a, &mut noop_context());
+
+    //// When
+    let forward = |service: &mut BufferedContext<Router>| {
+        let input_events =
+            new_received_message_with_id_seq(src, message.clone(), message_id.clone());
+        testlib::service::inject_events(service, input_events);
+        testlib::service::collect_events(service, &mut noop_context())
+    };
+    let output_events_a = forward(&mut service_a);
+    let output_events_b = forward(&mut service_b);
+
+    //// Then
+    assert_matches!(
+        (&output_events_a[..], &output_events_b[..]),
+        (
+            [ProtocolRouterOutEvent::ForwardMessage { dest: dest_a, .. }],
+            [ProtocolRouterOutEvent::ForwardMessage { dest: dest_b, .. }],
+        ) => {
+            assert_eq!(dest_a, dest_b, "The restored peers should be selected as if never deprioritized");
+        }
+    );
+}
+
+mod composite {
+    use libp2p_pubsub_core::protocol::CompositeRouter;
+    use libp2p_pubsub_core::Subscription;
+
+    use super::*;
+
+    const PROTOCOL_A: &str = "/floodsub-a/1.0.0";
+    const PROTOCOL_B: &str = "/floodsub-b/1.0.0";
+
+    /// Create a composite router hosting two floodsub routers.
+    fn new_test_composite_service() -> BufferedContext<CompositeRouter<Router, Router>> {
+        BufferedContext::new(CompositeRouter::new(
+            Router::default(),
+            vec![PROTOCOL_A],
+            Router::default(),
+            vec![PROTOCOL_B],
+        ))
+    }
+
+    /// Create a new peer connection sequence for the given peer and negotiated protocol.
+    fn new_peer_connected_seq(
+        peer: PeerId,
+        protocol: &str,
+    ) -> impl IntoIterator<Item = ProtocolRouterInEvent> {
+        [
+            ProtocolRouterInEvent::ConnectionEvent(ProtocolRouterConnectionEvent::PeerConnected(
+                peer,
+            )),
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerProtocolNegotiated {
+                    peer,
+                    protocol: protocol.to_string(),
+                },
+            ),
+        ]
+    }
+
+    /// Create a new subscription sequence for the given topic, restricted to the given protocol.
+    fn new_subscribe_with_hint_seq(
+        topic: TopicHash,
+        protocol: &str,
+    ) -> impl IntoIterator<Item = ProtocolRouterInEvent> {
+        let mut subscription = Subscription::from(topic);
+        subscription.protocol_hint = Some(protocol.to_string());
+
+        [ProtocolRouterInEvent::SubscriptionEvent(
+            ProtocolRouterSubscriptionEvent::Subscribed(subscription),
+        )]
+    }
+
+    #[test]
+    fn publish_a_message_to_the_peers_of_each_protocol() {
+        //// Given
+        let topic = new_test_topic();
+        let remote_peer_a = new_test_peer_id();
+        let remote_peer_b = new_test_peer_id();
+
+        let mut service = new_test_composite_service();
+
+        // Simulate the local node and peers subscriptions
+        let input_events = itertools::chain!(
+            new_subscribe_seq(topic.clone()),
+            new_peer_connected_seq(remote_peer_a, PROTOCOL_A),
+            new_peer_connected_seq(remote_peer_b, PROTOCOL_B),
+            new_peer_subscribed_seq(remote_peer_a, topic.clone()),
+            new_peer_subscribed_seq(remote_peer_b, topic.clone()),
+        );
+        testlib::service::inject_events(&mut service, input_events);
+        testlib::service::poll(&mut service, &mut noop_context());
+
+        //// When
+        let input_events = new_published_message_seq(topic.clone());
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(
+            output_events.len(),
+            2,
+            "Each protocol router should forward the message"
+        );
+        assert_matches!(&output_events[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+            assert_eq!(dest, &vec![remote_peer_a], "The message should be forwarded to peer A");
+        });
+        assert_matches!(&output_events[1], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+            assert_eq!(dest, &vec![remote_peer_b], "The message should be forwarded to peer B");
+        });
+    }
+
+    #[test]
+    fn publish_a_message_only_to_the_hinted_protocol_peers() {
+        //// Given
+        let topic = new_test_topic();
+        let remote_peer_a = new_test_peer_id();
+        let remote_peer_b = new_test_peer_id();
+
+        let mut service = new_test_composite_service();
+
+        // Simulate the local node and peers subscriptions
+        let input_events = itertools::chain!(
+            new_subscribe_with_hint_seq(topic.clone(), PROTOCOL_B),
+            new_peer_connected_seq(remote_peer_a, PROTOCOL_A),
+            new_peer_connected_seq(remote_peer_b, PROTOCOL_B),
+            new_peer_subscribed_seq(remote_peer_a, topic.clone()),
+            new_peer_subscribed_seq(remote_peer_b, topic.clone()),
+        );
+        testlib::service::inject_events(&mut service, input_events);
+        testlib::service::poll(&mut service, &mut noop_context());
+
+        //// When
+        let input_events = new_published_message_seq(topic.clone());
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(
+            output_events.len(),
+            1,
+            "Only the hinted protocol router should forward the message"
+        );
+        assert_matches!(&output_events[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+            assert_eq!(dest, &vec![remote_peer_b], "The message should be forwarded to peer B");
+        });
+    }
+
+    #[test]
+    fn forward_a_received_message_only_to_the_sender_protocol_peers() {
+        //// Given
+        let topic = new_test_topic();
+        let remote_peer_a1 = new_test_peer_id();
+        let remote_peer_a2 = new_test_peer_id();
+        let remote_peer_b = new_test_peer_id();
+
+        let mut service = new_test_composite_service();
+
+        // Simulate the local node and peers subscriptions
+        let input_events = itertools::chain!(
+            new_subscribe_seq(topic.clone()),
+            new_peer_connected_seq(remote_peer_a1, PROTOCOL_A),
+            new_peer_connected_seq(remote_peer_a2, PROTOCOL_A),
+            new_peer_connected_seq(remote_peer_b, PROTOCOL_B),
+            new_peer_subscribed_seq(remote_peer_a1, topic.clone()),
+            new_peer_subscribed_seq(remote_peer_a2, topic.clone()),
+            new_peer_subscribed_seq(remote_peer_b, topic.clone()),
+        );
+        testlib::service::inject_events(&mut service, input_events);
+        testlib::service::poll(&mut service, &mut noop_context());
+
+        //// When
+        // Simulate the reception of a message from the remote peer A1
+        let input_events = new_received_message_seq(remote_peer_a1, topic.clone());
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(
+            output_events.len(),
+            1,
+            "Only the sender protocol router should forward the message"
+        );
+        assert_matches!(&output_events[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+            assert_eq!(dest, &vec![remote_peer_a2], "The message should be forwarded to peer A2");
+        });
+    }
+}
+
+#[test]
+fn state_sync_replaces_a_desynchronized_routing_table() {
+    //// Given
+    let topic = new_test_topic();
+    let remote_peer_a = new_test_peer_id(); // Stale, unsubscribed for the behaviour
+    let remote_peer_b = new_test_peer_id(); // Missed, subscribed for the behaviour
+
+    let mut service = testlib::service::default_test_service::<Router>();
+
+    // Desynchronize the router: it only knows about peer A's stale subscription
+    let input_events = itertools::chain!(
+        new_subscribe_seq(topic.clone()),
+        new_peer_subscribed_seq(remote_peer_a, topic.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    let input_events = itertools::chain!(
+        new_state_sync_seq([topic.clone()], [(remote_peer_b, vec![topic.clone()])]),
+        new_published_message_seq(topic.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        output_events.len(),
+        1,
+        "A message forward event should be emitted"
+    );
+    assert_matches!(&output_events[0], ProtocolRouterOutEvent::ForwardMessage { dest, .. } => {
+        assert_eq!(dest, &[remote_peer_b], "The message should be forwarded to peer B only");
+    });
+}
+
+#[test]
+fn state_sync_replaces_the_local_node_subscriptions() {
+    //// Given
+    let topic_a = new_test_topic(); // Stale, unsubscribed for the behaviour
+    let topic_b = new_test_topic(); // Missed, subscribed for the behaviour
+    let remote_peer = new_test_peer_id();
+
+    let mut service = testlib::service::default_test_service::<Router>();
+
+    // Desynchronize the router: it only knows about the stale topic A subscription
+    let input_events = itertools::chain!(
+        new_subscribe_seq(topic_a.clone()),
+        new_peer_subscribed_seq(remote_peer, topic_a.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    let input_events = itertools::chain!(
+        new_state_sync_seq(
+            [topic_b.clone()],
+            [(remote_peer, vec![topic_a.clone(), topic_b.clone()])]
+        ),
+        new_published_message_seq(topic_a.clone()),
+        new_published_message_seq(topic_b.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        output_events.len(),
+        1,
+        "Only the topic B message forward event should be emitted"
+    );
+    assert_matches!(&output_events[0], ProtocolRouterOutEvent::ForwardMessage { dest, message } => {
+        assert_eq!(dest, &[remote_peer], "The message should be forwarded to the peer");
+        assert_eq!(&message.topic(), &topic_b, "The message should be on topic B");
+    });
+}
@@ -0,0 +1,124 @@
+//! The upstream `libp2p::gossipsub` usage, as in its chat example, ported to the compatibility
+//! layer by only changing the `gossipsub` import path.
+//!
+//! The same example is instantiated against both, checking that it compiles and behaves the same.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::{self, Swarm};
+use tracing_futures::Instrument;
+
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+/// The time to wait for each step of the example.
+const STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Instantiate the example with the given `gossipsub` module path.
+macro_rules! gossipsub_example {
+    ($name:ident, $($gossipsub:ident)::+) => {
+        /// Two nodes subscribe to the same topic. Once the publisher sees the subscriber
+        /// subscription, it publishes a message, and the subscriber receives it.
+        #[tokio::test]
+        async fn $name() {
+            use $($gossipsub)::+ as gossipsub;
+
+            testlib::init_logger();
+
+            /// Create a new gossipsub node with the given keypair.
+            fn new_node(keypair: &Keypair) -> Swarm<gossipsub::Behaviour> {
+                let peer_id = PeerId::from(keypair.public());
+                let transport = testlib::test_transport(keypair);
+
+                // Set a custom gossipsub configuration
+                let gossipsub_config = gossipsub::ConfigBuilder::default()
+                    .heartbeat_interval(Duration::from_millis(100))
+                    .build()
+                    .expect("valid gossipsub configuration");
+
+                // Build a gossipsub network behaviour
+                let gossipsub = gossipsub::Behaviour::new(
+                    gossipsub::MessageAuthenticity::Signed(keypair.clone()),
+                    gossipsub_config,
+                )
+                .expect("valid gossipsub behaviour");
+
+                let config = swarm::Config::with_executor(
+                    |fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+                        tokio::spawn(fut.in_current_span());
+                    },
+                );
+                Swarm::new(transport, gossipsub, peer_id, config)
+            }
+
+            //// Given
+            let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+            let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+            let publisher_id = PeerId::from(publisher_key.public());
+            let subscriber_id = PeerId::from(subscriber_key.public());
+
+            let mut publisher = new_node(&publisher_key);
+            let mut subscriber = new_node(&subscriber_key);
+
+            // Create a Gossipsub topic
+            let topic = gossipsub::IdentTopic::new("test-net");
+
+            // Subscribes to our topic
+            assert!(publisher.behaviour_mut().subscribe(&topic).expect("subscribe to topic"));
+            assert!(subscriber.behaviour_mut().subscribe(&topic).expect("subscribe to topic"));
+
+            testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+            let subscriber_addr = tokio::time::timeout(
+                STEP_TIMEOUT,
+                testlib::swarm::wait_for_new_listen_addr(&mut subscriber),
+            )
+            .await
+            .expect("listening to start");
+            testlib::swarm::should_dial_address(&mut publisher, subscriber_addr);
+
+            let subscribed = tokio::select! {
+                event = testlib::swarm::wait_for_message(
+                    &mut publisher,
+                    |event| matches!(event, gossipsub::Event::Subscribed { .. }),
+                    STEP_TIMEOUT,
+                ) => event,
+                _ = testlib::swarm::poll(&mut subscriber) => unreachable!(),
+            };
+            assert_matches!(subscribed, Some(gossipsub::Event::Subscribed { peer_id, topic: subscribed_topic }) => {
+                assert_eq!(peer_id, subscriber_id);
+                assert_eq!(subscribed_topic, topic.hash());
+            });
+
+            //// When
+            let message_id = publisher
+                .behaviour_mut()
+                .publish(topic.clone(), "Hello World!".as_bytes())
+                .expect("publish message");
+
+            let received = tokio::select! {
+                event = testlib::swarm::wait_for_message(
+                    &mut subscriber,
+                    |event| matches!(event, gossipsub::Event::Message { .. }),
+                    STEP_TIMEOUT,
+                ) => event,
+                _ = testlib::swarm::poll(&mut publisher) => unreachable!(),
+            };
+
+            //// Then
+            assert_matches!(received, Some(gossipsub::Event::Message { propagation_source, message_id: id, message }) => {
+                assert_eq!(propagation_source, publisher_id);
+                assert_eq!(id, message_id);
+                assert_eq!(message.source, Some(publisher_id));
+                assert_eq!(message.data, b"Hello World!");
+                assert_eq!(message.topic, topic.hash());
+            });
+        }
+    };
+}
+
+gossipsub_example!(upstream_gossipsub_example, libp2p::gossipsub);
+gossipsub_example!(compat_gossipsub_example, libp2p_pubsub_floodsub::compat);
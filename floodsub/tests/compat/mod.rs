@@ -0,0 +1 @@
+mod gossipsub_migration;
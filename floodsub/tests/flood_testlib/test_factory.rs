@@ -6,7 +6,7 @@ use rand::Rng;
 use libp2p::identity::{Keypair, PeerId};
 use libp2p::swarm::SwarmBuilder;
 use libp2p::Swarm;
-use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, IdentTopic};
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, IdentTopic, Identity, TopicHash};
 use libp2p_pubsub_floodsub::Protocol as Floodsub;
 use tracing_futures::Instrument;
 
@@ -24,11 +24,31 @@ pub fn new_test_topic() -> IdentTopic {
 /// Creates a new Node with the given key-pair, default Config and default
 /// Protocol.
 pub fn new_test_node(keypair: &Keypair) -> Swarm<Behaviour> {
+    new_test_node_with_config(keypair, Config::default())
+}
+
+/// Creates a new Node with the given key-pair and Config, and default Protocol.
+pub fn new_test_node_with_config(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
     let peer_id = PeerId::from(keypair.public());
-    let transport = testlib::test_transport(keypair);
+    let protocol = Default::default();
+    let behaviour = Behaviour::new(peer_id, config, protocol);
+    new_test_swarm(keypair, behaviour)
+}
+
+/// Creates a new Node with the given key-pair as its identity, default Config and default
+/// Protocol. The node publishes messages authored by the key-pair peer id.
+pub fn new_test_node_with_keypair_identity(keypair: &Keypair) -> Swarm<Behaviour> {
     let config = Config::default();
     let protocol = Default::default();
-    let behaviour = Behaviour::new(config.clone(), protocol);
+    let behaviour =
+        Behaviour::new_with_identity(config, protocol, Identity::Keypair(keypair.clone()));
+    new_test_swarm(keypair, behaviour)
+}
+
+/// Creates a new Swarm with the given key-pair and behaviour over the test transport.
+fn new_test_swarm(keypair: &Keypair, behaviour: Behaviour) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair);
     SwarmBuilder::with_executor(
         transport,
         behaviour,
@@ -39,3 +59,10 @@ pub fn new_test_node(keypair: &Keypair) -> Swarm<Behaviour> {
     )
     .build()
 }
+
+/// Checks if the node is aware of the peer subscription to the topic.
+pub fn is_peer_subscribed(node: &Swarm<Behaviour>, peer: &PeerId, topic: &TopicHash) -> bool {
+    node.behaviour()
+        .peer_subscriptions(peer)
+        .map_or(false, |subscriptions| subscriptions.contains(topic))
+}
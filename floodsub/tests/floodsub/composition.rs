@@ -0,0 +1,122 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::kad::{self, store::MemoryStore};
+use libp2p::swarm::{
+    self, NetworkBehaviour, SwarmEvent, THandler, THandlerInEvent, THandlerOutEvent,
+};
+use libp2p::Swarm;
+use tokio::time::timeout;
+use tracing_futures::Instrument;
+
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, Event, Message};
+use libp2p_pubsub_floodsub::Protocol as Floodsub;
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+use crate::flood_testlib::*;
+
+type Behaviour = PubsubBehaviour<Floodsub>;
+
+/// A behaviour composing the pubsub behaviour with another libp2p behaviour, as an application
+/// would.
+#[derive(NetworkBehaviour)]
+struct ComposedBehaviour {
+    kad: kad::Behaviour<MemoryStore>,
+    pubsub: Behaviour,
+}
+
+fn new_composed_node(keypair: &Keypair) -> Swarm<ComposedBehaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair);
+    let behaviour = ComposedBehaviour {
+        kad: kad::Behaviour::new(peer_id, MemoryStore::new(peer_id)),
+        pubsub: Behaviour::new(peer_id, Config::default(), Default::default()),
+    };
+    let config = swarm::Config::with_executor(|fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+        tokio::spawn(fut.in_current_span());
+    });
+    Swarm::new(transport, behaviour, peer_id, config)
+}
+
+fn assert_send<T: Send>() {}
+
+/// The connection handlers and the events exchanged with them are moved to the connection tasks,
+/// so the composed behaviours require them to be `Send`.
+#[test]
+fn pubsub_handler_and_event_types_are_send() {
+    assert_send::<THandler<Behaviour>>();
+    assert_send::<THandlerInEvent<Behaviour>>();
+    assert_send::<THandlerOutEvent<Behaviour>>();
+    assert_send::<Event>();
+}
+
+#[tokio::test]
+async fn composed_behaviour_nodes_exchange_messages() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let mut node_a = new_composed_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_A));
+    let mut node_b = new_composed_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_B));
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .pubsub
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    testlib::swarm::should_dial_address(&mut node_b, node_a_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .pubsub
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish the message");
+
+    let (node_a_events, _) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    let messages = node_a_events
+        .into_iter()
+        .filter_map(|ev| match ev {
+            SwarmEvent::Behaviour(ComposedBehaviourEvent::Pubsub(
+                event @ Event::MessageReceived { .. },
+            )) => Some(event),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(messages.len(), 1, "Node A should receive the message");
+    assert_matches!(&messages[0], Event::MessageReceived { message, .. } => {
+        assert_eq!(message.topic, topic.hash());
+        assert_eq!(message.data, b"test-payload");
+    });
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Duration;
+
+use libp2p::identity::PeerId;
+use libp2p::Swarm;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, DeliveryMode, IdentTopic, Message, SubscriptionBuilder,
+};
+use libp2p_pubsub_floodsub::Protocol as Floodsub;
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C, TEST_KEYPAIR_D};
+
+use crate::flood_testlib::*;
+
+type Behaviour = PubsubBehaviour<Floodsub>;
+
+/// Create a publisher node, publishing on the topic in round-robin, and a worker node per
+/// key-pair, subscribed to the topic without forwarding, connected to the publisher.
+async fn new_work_queue(
+    topic: &IdentTopic,
+    worker_keys: &[&str],
+) -> (Swarm<Behaviour>, Vec<Swarm<Behaviour>>) {
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let mut publisher = new_test_node(&publisher_key);
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+    let publisher_addr = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_new_listen_addr(&mut publisher),
+    )
+    .await
+    .expect("listening to start");
+
+    let mut subscription = SubscriptionBuilder::new(topic.clone());
+    subscription.delivery_mode(DeliveryMode::RoundRobinPublish);
+    publisher
+        .behaviour_mut()
+        .subscribe(subscription.build())
+        .expect("subscribe to topic");
+
+    let mut workers = Vec::new();
+    for key in worker_keys {
+        let mut worker = new_test_node(&testlib::secp256k1_keypair(key));
+
+        let mut subscription = SubscriptionBuilder::new(topic.clone());
+        subscription.forwarding(false);
+        worker
+            .behaviour_mut()
+            .subscribe(subscription.build())
+            .expect("subscribe to topic");
+
+        testlib::swarm::should_dial_address(&mut worker, publisher_addr.clone());
+        timeout(
+            Duration::from_secs(5),
+            testlib::swarm::wait_for_connection_establishment(&mut worker, &mut publisher),
+        )
+        .await
+        .expect("worker to connect to publisher");
+
+        workers.push(worker);
+    }
+
+    // Wait for the publisher to learn the workers subscriptions.
+    let worker_ids = workers
+        .iter()
+        .map(|worker| *worker.local_peer_id())
+        .collect::<Vec<_>>();
+    let mut nodes = std::iter::once(&mut publisher)
+        .chain(workers.iter_mut())
+        .collect::<Vec<_>>();
+    let subscribed = testlib::swarm::poll_mesh_until(
+        &mut nodes,
+        |nodes| {
+            worker_ids
+                .iter()
+                .all(|worker| is_peer_subscribed(nodes[0], worker, &topic.hash()))
+        },
+        Duration::from_secs(5),
+    )
+    .await;
+    assert!(
+        subscribed,
+        "Publisher should learn the workers subscriptions"
+    );
+
+    (publisher, workers)
+}
+
+/// Publish a message per sequence number and return the peer each message was sent to.
+fn publish_messages(
+    publisher: &mut Swarm<Behaviour>,
+    topic: &IdentTopic,
+    seqnos: Range<u64>,
+) -> Vec<PeerId> {
+    seqnos
+        .map(|seqno| {
+            let message = Message::new_with_sequence_number(
+                topic.clone(),
+                format!("job-{seqno}").into_bytes(),
+                seqno.to_be_bytes(),
+            );
+            let receipt = publisher
+                .behaviour_mut()
+                .publish_with_receipt(message)
+                .expect("publish the message");
+            receipt.peer.expect("a worker should be selected")
+        })
+        .collect()
+}
+
+/// Poll the nodes until every worker received the expected number of messages.
+async fn wait_for_received_messages(
+    publisher: &mut Swarm<Behaviour>,
+    workers: &mut [Swarm<Behaviour>],
+    topic: &IdentTopic,
+    expected: &HashMap<PeerId, u64>,
+) -> HashMap<PeerId, u64> {
+    let received = |worker: &Swarm<Behaviour>| {
+        worker
+            .behaviour()
+            .topic_traffic(&topic.hash())
+            .map_or(0, |traffic| traffic.received_messages)
+    };
+
+    let mut nodes = std::iter::once(publisher)
+        .chain(workers.iter_mut())
+        .collect::<Vec<_>>();
+    testlib::swarm::poll_mesh_until(
+        &mut nodes,
+        |nodes| {
+            nodes[1..].iter().all(|worker| {
+                received(worker) >= expected.get(worker.local_peer_id()).copied().unwrap_or(0)
+            })
+        },
+        Duration::from_secs(5),
+    )
+    .await;
+
+    workers
+        .iter()
+        .map(|worker| (*worker.local_peer_id(), received(worker)))
+        .collect()
+}
+
+/// Count the messages sent to each peer.
+fn count_by_peer(selected: &[PeerId]) -> HashMap<PeerId, u64> {
+    let mut counts = HashMap::new();
+    for peer in selected {
+        *counts.entry(*peer).or_default() += 1;
+    }
+    counts
+}
+
+#[tokio::test]
+async fn round_robin_messages_are_sent_to_one_worker_in_rotation() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut publisher, mut workers) =
+        new_work_queue(&topic, &[TEST_KEYPAIR_B, TEST_KEYPAIR_C, TEST_KEYPAIR_D]).await;
+
+    //// When
+    let selected = publish_messages(&mut publisher, &topic, 0..6);
+    let expected = count_by_peer(&selected);
+    let received =
+        wait_for_received_messages(&mut publisher, &mut workers, &topic, &expected).await;
+
+    //// Then
+    assert_eq!(
+        selected[..3],
+        selected[3..],
+        "The rotation should repeat in the same order"
+    );
+    for worker in &workers {
+        assert_eq!(
+            expected.get(worker.local_peer_id()),
+            Some(&2),
+            "Every worker should be selected twice"
+        );
+    }
+    assert_eq!(
+        received, expected,
+        "Every message should be received by the selected worker only"
+    );
+}
+
+#[tokio::test]
+async fn disconnected_worker_is_skipped_mid_rotation() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut publisher, mut workers) =
+        new_work_queue(&topic, &[TEST_KEYPAIR_B, TEST_KEYPAIR_C, TEST_KEYPAIR_D]).await;
+
+    let first = publish_messages(&mut publisher, &topic, 0..1)[0];
+
+    // The worker next in the rotation disconnects.
+    let mut rotation = workers
+        .iter()
+        .map(|worker| *worker.local_peer_id())
+        .collect::<Vec<_>>();
+    rotation.sort();
+    let first_idx = rotation.iter().position(|peer| *peer == first).unwrap();
+    let disconnected = rotation[(first_idx + 1) % rotation.len()];
+
+    publisher
+        .disconnect_peer_id(disconnected)
+        .expect("disconnect the worker");
+    let mut nodes = std::iter::once(&mut publisher)
+        .chain(workers.iter_mut())
+        .collect::<Vec<_>>();
+    let disconnected_processed = testlib::swarm::poll_mesh_until(
+        &mut nodes,
+        |nodes| {
+            !nodes[0]
+                .behaviour()
+                .connections()
+                .active_peers()
+                .contains(&disconnected)
+        },
+        Duration::from_secs(5),
+    )
+    .await;
+    assert!(disconnected_processed, "Worker should be disconnected");
+
+    //// When
+    let selected = publish_messages(&mut publisher, &topic, 1..5);
+
+    //// Then
+    let remaining = rotation
+        .iter()
+        .copied()
+        .filter(|peer| *peer != disconnected)
+        .collect::<Vec<_>>();
+    let after_first = remaining
+        .iter()
+        .copied()
+        .find(|peer| *peer > first)
+        .unwrap_or(remaining[0]);
+
+    assert!(
+        !selected.contains(&disconnected),
+        "The disconnected worker should not be selected"
+    );
+    assert_eq!(
+        selected[0], after_first,
+        "The rotation should resume after the last selected worker"
+    );
+    assert_ne!(selected[0], selected[1]);
+    assert_eq!(selected[..2], selected[2..]);
+}
+
+#[tokio::test]
+async fn single_worker_receives_every_message() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut publisher, mut workers) = new_work_queue(&topic, &[TEST_KEYPAIR_B]).await;
+    let worker = *workers[0].local_peer_id();
+
+    //// When
+    let selected = publish_messages(&mut publisher, &topic, 0..3);
+    let expected = count_by_peer(&selected);
+    let received =
+        wait_for_received_messages(&mut publisher, &mut workers, &topic, &expected).await;
+
+    //// Then
+    assert_eq!(selected, [worker; 3]);
+    assert_eq!(received.get(&worker), Some(&3));
+}
@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use futures::StreamExt;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, ConfigBuilder, Event, Message};
+use libp2p_pubsub_floodsub::Protocol as Floodsub;
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+
+use crate::flood_testlib::*;
+
+type Behaviour = PubsubBehaviour<Floodsub>;
+
+/// Creates a new node with the echo detection enabled.
+fn new_echo_detecting_node(keypair: &libp2p::identity::Keypair) -> Swarm<Behaviour> {
+    new_test_node_with_config(keypair, ConfigBuilder::new().detect_echo(true).build())
+}
+
+/// Poll the nodes until the first node emits a published message echo event, or the `timeout`
+/// elapses.
+async fn wait_for_echo(
+    node: &mut Swarm<Behaviour>,
+    others: &mut [&mut Swarm<Behaviour>],
+    timeout: Duration,
+) -> Option<Event> {
+    let wait = async {
+        loop {
+            let others = futures::future::select_all(
+                others.iter_mut().map(|other| other.select_next_some()),
+            );
+            tokio::select! {
+                event = node.select_next_some() => {
+                    if let SwarmEvent::Behaviour(event @ Event::PublishedMessageEchoed { .. }) = event {
+                        return event;
+                    }
+                },
+                _ = others => {},
+            }
+        }
+    };
+
+    tokio::time::timeout(timeout, wait).await.ok()
+}
+
+#[tokio::test]
+async fn published_message_echo_is_detected() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let mut node_a = new_echo_detecting_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_A));
+    let mut node_b = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_B));
+    let mut node_c = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_C));
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+    testlib::swarm::should_listen_on_address(&mut node_c, any_memory_addr());
+
+    let (node_b_addr, node_c_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_b, &mut node_c),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    // A <-> B <-> C, and A <-> C
+    testlib::swarm::should_dial_address(&mut node_a, node_b_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("node A to connect to node B");
+
+    testlib::swarm::should_dial_address(&mut node_b, node_c_addr.clone());
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_c),
+    )
+    .await
+    .expect("node B to connect to node C");
+
+    testlib::swarm::should_dial_address(&mut node_a, node_c_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_a, &mut node_c),
+    )
+    .await
+    .expect("node A to connect to node C");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    // Node C subscribes while node A is not polled: node A is not aware of node C subscription
+    // yet, so node A publishes to node B only, and the message reaches node C via node B.
+    node_c
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_b, &mut node_c).await;
+
+    assert!(is_peer_subscribed(
+        &node_b,
+        node_c.local_peer_id(),
+        &topic.hash()
+    ));
+    assert!(!is_peer_subscribed(
+        &node_a,
+        node_c.local_peer_id(),
+        &topic.hash()
+    ));
+
+    //// When
+    node_a
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish to topic");
+
+    let echo = wait_for_echo(
+        &mut node_a,
+        &mut [&mut node_b, &mut node_c],
+        Duration::from_secs(5),
+    )
+    .await;
+
+    //// Then
+    assert_matches!(echo, Some(Event::PublishedMessageEchoed { echoed_by, .. }) => {
+        assert_eq!(&echoed_by, node_c.local_peer_id(), "The message should be echoed by node C");
+    });
+}
+
+#[tokio::test]
+async fn published_message_without_echo_path_is_not_detected() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let mut node_a = new_echo_detecting_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_A));
+    let mut node_b = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_B));
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let node_b_addr = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_new_listen_addr(&mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    testlib::swarm::should_dial_address(&mut node_a, node_b_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("node A to connect to node B");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// When
+    node_a
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish to topic");
+
+    // Wait for longer than the time the message takes to propagate to node B and back.
+    let echo = wait_for_echo(&mut node_a, &mut [&mut node_b], Duration::from_secs(2)).await;
+
+    //// Then
+    assert!(echo.is_none(), "No echo event should be emitted");
+}
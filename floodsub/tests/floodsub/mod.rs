@@ -1,3 +1,7 @@
+mod composition;
 mod connections;
+mod delivery;
+mod echo;
 mod routing;
 mod subscriptions;
+mod traffic;
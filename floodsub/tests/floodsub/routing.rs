@@ -105,3 +105,212 @@ async fn publish_to_topic() {
         assert_eq!(message.data, message_payload[..]);
     });
 }
+
+#[tokio::test]
+async fn publish_to_topic_with_keypair_identity_stamps_the_author() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let message_payload = b"test-payload";
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    //// Setup
+    let mut publisher = new_test_node_with_keypair_identity(&publisher_key);
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key);
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    should_subscribe_to_topic(&mut publisher, topic.clone());
+    should_subscribe_to_topic(&mut subscriber, topic.clone());
+
+    // Dial the publisher node
+    testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+    )
+    .await
+    .expect("subscriber to connect to publisher");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    //// When
+    should_publish_to_topic(
+        &mut publisher,
+        Message::new(topic.clone(), *message_payload),
+    );
+    should_publish_to_topic(
+        &mut publisher,
+        Message::new(topic.clone(), *message_payload),
+    );
+
+    let (_, sub_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        sub_events.len(),
+        2,
+        "Both messages should be received, as their sequence numbers differ"
+    );
+    for event in &sub_events {
+        assert_matches!(event, SwarmEvent::Behaviour(Event::MessageReceived { message, .. }) => {
+            assert_eq!(message.from.as_ref(), Some(publisher.local_peer_id()), "The message should be authored by the publisher");
+            assert!(message.sequence_number.is_some(), "The message should have a sequence number");
+        });
+    }
+}
+
+#[tokio::test]
+async fn publish_chunked_message_to_topic() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let message_payload = (0..=255u8).cycle().take(10_000).collect::<Vec<_>>();
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    //// Setup
+    let mut publisher = new_test_node(&publisher_key);
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key);
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    should_subscribe_to_topic(&mut publisher, topic.clone());
+    should_subscribe_to_topic(&mut subscriber, topic.clone());
+
+    // Dial the publisher node
+    testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+    )
+    .await
+    .expect("subscriber to connect to publisher");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    //// When
+    let message = Message::new_with_sequence_number(
+        topic.clone(),
+        message_payload.clone(),
+        b"seq-1".to_vec(),
+    );
+    let result = publisher.behaviour_mut().publish_chunked(message, 1024);
+    assert_matches!(result, Ok(_), "publish chunked message should succeed");
+
+    let (_, sub_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        sub_events.len(),
+        1,
+        "Only 1 message event should be emitted"
+    );
+    assert_matches!(&sub_events[0], SwarmEvent::Behaviour(Event::MessageReceived { src, message, .. }) => {
+        assert_eq!(src, publisher.local_peer_id(), "The message should be propagated by the publisher");
+        assert_eq!(message.topic.as_str(), topic.hash().as_str());
+        assert_eq!(message.data, message_payload);
+    });
+}
+
+#[tokio::test]
+async fn self_authored_message_is_not_delivered() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    //// Setup
+    let mut publisher = new_test_node(&publisher_key);
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key);
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    should_subscribe_to_topic(&mut publisher, topic.clone());
+    should_subscribe_to_topic(&mut subscriber, topic.clone());
+
+    // Dial the publisher node
+    testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+    )
+    .await
+    .expect("subscriber to connect to publisher");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    //// When
+    // A message authored by the subscriber, reflected back by the publisher.
+    let mut reflected_message = Message::new(topic.clone(), b"reflected-payload".to_vec());
+    reflected_message.from = Some(*subscriber.local_peer_id());
+    should_publish_to_topic(&mut publisher, reflected_message);
+
+    let message = Message::new(topic.clone(), b"test-payload".to_vec());
+    should_publish_to_topic(&mut publisher, message);
+
+    let (_, sub_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        sub_events.len(),
+        1,
+        "Only 1 message event should be emitted"
+    );
+    assert_matches!(&sub_events[0], SwarmEvent::Behaviour(Event::MessageReceived { message, .. }) => {
+        assert_eq!(message.data, b"test-payload");
+    });
+}
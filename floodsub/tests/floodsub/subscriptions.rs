@@ -165,14 +165,25 @@ async fn send_subscriptions_on_connection_established() {
     .await
     .expect("Node A to connect to Node B");
 
-    // Wait for pub-sub network to establish
-    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
-
     //// Then
     let topic_a = topic_a.hash();
     let topic_b = topic_b.hash();
     let topic_c = topic_c.hash();
 
+    // Wait for the subscriptions to be exchanged
+    let node_a_id = *node_a.local_peer_id();
+    let node_b_id = *node_b.local_peer_id();
+    let exchanged = testlib::swarm::poll_mesh_until(
+        &mut [&mut node_a, &mut node_b],
+        |nodes| {
+            is_peer_subscribed(nodes[0], &node_b_id, &topic_c)
+                && is_peer_subscribed(nodes[1], &node_a_id, &topic_b)
+        },
+        Duration::from_secs(5),
+    )
+    .await;
+    assert!(exchanged, "Subscriptions should be exchanged");
+
     assert_matches!(
         node_a.behaviour().peer_subscriptions(node_b.local_peer_id()),
         Some(subscriptions) => {
@@ -226,21 +237,25 @@ async fn send_subscriptions_on_subscribe() {
     .await
     .expect("Node B to connect to Node A");
 
-    // Poll the network for a short period of time to allow the subscriptions to be processed and exchanged.
-    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
-
     //// When
     node_b
         .behaviour_mut()
         .subscribe(topic_a.clone())
         .expect("subscribe to topic");
 
-    // Poll the network for a short period of time to allow the subscriptions to be processed and exchanged.
-    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
-
     //// Then
     let topic_a = topic_a.hash();
 
+    // Wait for the subscription to be propagated
+    let node_b_id = *node_b.local_peer_id();
+    let propagated = testlib::swarm::poll_mesh_until(
+        &mut [&mut node_a, &mut node_b],
+        |nodes| is_peer_subscribed(nodes[0], &node_b_id, &topic_a),
+        Duration::from_secs(5),
+    )
+    .await;
+    assert!(propagated, "Node B subscription should be propagated");
+
     assert_matches!(
         node_a.behaviour().peer_subscriptions(node_b.local_peer_id()),
         Some(subscriptions) => {
@@ -303,8 +318,19 @@ async fn send_subscriptions_on_unsubscribe() {
     .await
     .expect("Node B to connect to Node A");
 
-    // Poll the network for a short period of time to allow the subscriptions to be processed and exchanged.
-    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+    // Wait for the subscriptions to be exchanged
+    let node_a_id = *node_a.local_peer_id();
+    let node_b_id = *node_b.local_peer_id();
+    let exchanged = testlib::swarm::poll_mesh_until(
+        &mut [&mut node_a, &mut node_b],
+        |nodes| {
+            is_peer_subscribed(nodes[0], &node_b_id, &topic_a.hash())
+                && is_peer_subscribed(nodes[1], &node_a_id, &topic_a.hash())
+        },
+        Duration::from_secs(5),
+    )
+    .await;
+    assert!(exchanged, "Subscriptions should be exchanged");
 
     //// When
     node_a
@@ -316,14 +342,23 @@ async fn send_subscriptions_on_unsubscribe() {
         .unsubscribe(&topic_a)
         .expect("unsubscribe from topic");
 
-    // Poll the network for a short period of time to allow the subscriptions to be processed.
-    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
-
     //// Then
     let topic_a = topic_a.hash();
     let topic_b = topic_b.hash();
     let topic_c = topic_c.hash();
 
+    // Wait for the unsubscriptions to be propagated
+    let propagated = testlib::swarm::poll_mesh_until(
+        &mut [&mut node_a, &mut node_b],
+        |nodes| {
+            !is_peer_subscribed(nodes[0], &node_b_id, &topic_a)
+                && !is_peer_subscribed(nodes[1], &node_a_id, &topic_a)
+        },
+        Duration::from_secs(5),
+    )
+    .await;
+    assert!(propagated, "Unsubscriptions should be propagated");
+
     assert_matches!(
         node_a.behaviour().peer_subscriptions(node_b.local_peer_id()),
         Some(subscriptions) => {
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{FrameMessage, Message};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+
+use crate::flood_testlib::*;
+
+#[tokio::test]
+async fn published_and_received_traffic_is_accounted() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let messages = [
+        Message::new(topic.clone(), b"test-payload-1".to_vec()),
+        Message::new(topic.clone(), vec![0x42; 512]),
+    ];
+    let messages_len = messages
+        .iter()
+        .map(|message| FrameMessage::from(message.clone()).encoded_len() as u64)
+        .sum::<u64>();
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut publisher = new_test_node(&publisher_key);
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key);
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    publisher
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    subscriber
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    testlib::swarm::should_dial_address(&mut subscriber, publisher_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut publisher),
+    )
+    .await
+    .expect("subscriber to connect to publisher");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    //// When
+    for message in messages {
+        publisher
+            .behaviour_mut()
+            .publish(message)
+            .expect("publish to topic");
+    }
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut publisher, &mut subscriber).await;
+
+    //// Then
+    let topic = topic.hash();
+
+    assert_matches!(publisher.behaviour().topic_traffic(&topic), Some(traffic) => {
+        assert_eq!(traffic.published_messages, 2);
+        assert_eq!(traffic.published_bytes, messages_len);
+        assert_eq!(traffic.forwarded_messages, 2);
+        assert_eq!(traffic.forwarded_bytes, messages_len);
+        assert_eq!(traffic.received_messages, 0);
+        assert_eq!(traffic.size_histogram, [1, 0, 1, 0, 0, 0, 0]);
+    });
+    assert_matches!(subscriber.behaviour().topic_traffic(&topic), Some(traffic) => {
+        assert_eq!(traffic.received_messages, 2);
+        assert_eq!(traffic.received_bytes, messages_len);
+        assert_eq!(traffic.published_messages, 0);
+        assert_eq!(traffic.forwarded_messages, 0, "The publisher is the only peer subscribed");
+        assert_eq!(traffic.size_histogram, [1, 0, 1, 0, 0, 0, 0]);
+    });
+    assert_eq!(
+        Some(subscriber.behaviour().traffic_totals()),
+        subscriber.behaviour().topic_traffic(&topic),
+        "All the traffic should be accounted to the topic"
+    );
+}
+
+#[tokio::test]
+async fn relayed_traffic_is_accounted_as_forwarded() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let message = Message::new(topic.clone(), b"test-payload".to_vec());
+    let message_len = FrameMessage::from(message.clone()).encoded_len() as u64;
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let relay_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    let mut publisher = new_test_node(&publisher_key);
+    let mut relay = new_test_node(&relay_key);
+    testlib::swarm::should_listen_on_address(&mut relay, any_memory_addr());
+    let mut subscriber = new_test_node(&subscriber_key);
+
+    let relay_addr = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_new_listen_addr(&mut relay),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut publisher, &mut relay, &mut subscriber] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    // Publisher <-> Relay <-> Subscriber
+    testlib::swarm::should_dial_address(&mut publisher, relay_addr.clone());
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut publisher, &mut relay),
+    )
+    .await
+    .expect("publisher to connect to relay");
+
+    testlib::swarm::should_dial_address(&mut subscriber, relay_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut relay),
+    )
+    .await
+    .expect("subscriber to connect to relay");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut relay,
+        &mut subscriber,
+    )
+    .await;
+
+    //// When
+    publisher
+        .behaviour_mut()
+        .publish(message)
+        .expect("publish to topic");
+
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut relay,
+        &mut subscriber,
+    )
+    .await;
+
+    //// Then
+    let topic = topic.hash();
+
+    assert_matches!(publisher.behaviour().topic_traffic(&topic), Some(traffic) => {
+        assert_eq!(traffic.published_messages, 1);
+        assert_eq!(traffic.forwarded_messages, 1);
+        assert_eq!(traffic.forwarded_bytes, message_len);
+    });
+    assert_matches!(relay.behaviour().topic_traffic(&topic), Some(traffic) => {
+        assert_eq!(traffic.published_messages, 0);
+        assert_eq!(traffic.received_messages, 1);
+        assert_eq!(traffic.received_bytes, message_len);
+        assert_eq!(traffic.forwarded_messages, 1, "The message should be relayed to the subscriber only");
+        assert_eq!(traffic.forwarded_bytes, message_len);
+    });
+    assert_matches!(subscriber.behaviour().topic_traffic(&topic), Some(traffic) => {
+        assert_eq!(traffic.received_messages, 1);
+        assert_eq!(traffic.received_bytes, message_len);
+        assert_eq!(traffic.forwarded_messages, 0, "The relay is the message source");
+    });
+}
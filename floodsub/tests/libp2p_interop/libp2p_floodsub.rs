@@ -5,13 +5,12 @@ use std::time::Duration;
 
 use assert_matches::assert_matches;
 use futures::StreamExt;
-use libp2p::floodsub::protocol::CodecError;
 use libp2p::floodsub::{
     Floodsub as Libp2pFloodsubBehaviour, FloodsubEvent as Libp2pFloodsubEvent,
     Topic as Libp2pFloodsubTopic,
 };
 use libp2p::identity::{Keypair, PeerId};
-use libp2p::swarm::{StreamUpgradeError, Swarm, SwarmBuilder, SwarmEvent};
+use libp2p::swarm::{Swarm, SwarmBuilder, SwarmEvent};
 use rand::random;
 use tokio::time::timeout;
 use tracing_futures::Instrument;
@@ -42,7 +41,7 @@ fn new_libp2p_topic(raw: &str) -> Libp2pFloodsubTopic {
 fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
     let peer_id = PeerId::from(keypair.public());
     let transport = testlib::test_transport(keypair);
-    let behaviour = Behaviour::new(config, Default::default());
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
     SwarmBuilder::with_executor(
         transport,
         behaviour,
@@ -91,38 +90,6 @@ async fn wait_for_message_event(
     events
 }
 
-async fn wait_for_libp2p_gossipsub_message_event(
-    swarm: &mut Swarm<Libp2pFloodsubBehaviour>,
-) -> Vec<SwarmEvent<Libp2pFloodsubEvent, StreamUpgradeError<CodecError>>> {
-    let mut events = Vec::new();
-
-    loop {
-        let event = swarm.select_next_some().await;
-        tracing::trace!(?event, "Event emitted");
-        events.push(event);
-
-        if matches!(
-            events.last(),
-            Some(SwarmEvent::Behaviour(Libp2pFloodsubEvent::Message { .. }))
-        ) {
-            break;
-        }
-    }
-
-    events
-}
-
-async fn wait_mesh_message_propagation(
-    duration: Duration,
-    swarm1: &mut Swarm<Behaviour>,
-    swarm2: &mut Swarm<Libp2pFloodsubBehaviour>,
-) -> Vec<SwarmEvent<Libp2pFloodsubEvent, StreamUpgradeError<CodecError>>> {
-    tokio::select! {
-        _ = timeout(duration, testlib::swarm::poll(swarm1)) => panic!("timeout reached"),
-        res = wait_for_libp2p_gossipsub_message_event(swarm2) => res,
-    }
-}
-
 async fn wait_mesh_libp2p_gossipsub_message_propagation(
     duration: Duration,
     swarm1: &mut Swarm<Libp2pFloodsubBehaviour>,
@@ -199,12 +166,24 @@ async fn floodsub_node_publish_and_libp2p_floodsub_node_subscribes() {
     .await
     .expect("publisher to dial the subscriber");
 
-    testlib::swarm::poll_mesh(
-        Duration::from_millis(50),
-        &mut publisher,
-        &mut libp2p_subscriber,
-    )
-    .await;
+    // Wait for the publisher to be aware of the subscriber's subscription
+    let subscriber_id = *libp2p_subscriber.local_peer_id();
+    let subscribed = tokio::select! {
+        _ = testlib::swarm::poll(&mut libp2p_subscriber) => unreachable!(),
+        subscribed = testlib::swarm::wait_for_peer_subscription(
+            &mut publisher,
+            |behaviour| {
+                behaviour
+                    .peer_subscriptions(&subscriber_id)
+                    .map_or(false, |subscriptions| subscriptions.contains(&topic.hash()))
+            },
+            Duration::from_secs(5),
+        ) => subscribed,
+    };
+    assert!(
+        subscribed,
+        "publisher should be aware of the subscriber's subscription"
+    );
 
     //// When
     // Libp2p's floodsub implementation requires the messages to have present the sequence number
@@ -220,16 +199,17 @@ async fn floodsub_node_publish_and_libp2p_floodsub_node_subscribes() {
         .publish(message)
         .expect("publish the message");
 
-    let sub_events = wait_mesh_message_propagation(
-        Duration::from_millis(50),
-        &mut publisher,
-        &mut libp2p_subscriber,
-    )
-    .await;
+    let sub_event = tokio::select! {
+        _ = testlib::swarm::poll(&mut publisher) => unreachable!(),
+        event = testlib::swarm::wait_for_message(
+            &mut libp2p_subscriber,
+            |event| matches!(event, Libp2pFloodsubEvent::Message(_)),
+            Duration::from_secs(5),
+        ) => event,
+    };
 
     //// Then
-    let last_event = sub_events.last().expect("at least one event");
-    assert_matches!(last_event, SwarmEvent::Behaviour(Libp2pFloodsubEvent::Message(message)) => {
+    assert_matches!(sub_event, Some(Libp2pFloodsubEvent::Message(message)) => {
         assert!(!message.sequence_number.is_empty());
         assert_eq!(message.source, *publisher.local_peer_id());
         assert!(message.topics.contains(&libp2p_topic));
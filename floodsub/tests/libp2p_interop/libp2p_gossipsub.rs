@@ -19,13 +19,26 @@ use tokio::time::timeout;
 use tracing_futures::Instrument;
 use void::Void;
 
-use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, Event, IdentTopic, Message};
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, IdentTopic, Message,
+};
 use libp2p_pubsub_floodsub::Protocol as Floodsub;
 use testlib::any_memory_addr;
-use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
 
 type Behaviour = PubsubBehaviour<Floodsub>;
 
+/// A 2048-bit RSA test keypair, PKCS#8 DER encoded.
+///
+/// The RSA public keys cannot be inlined into the peer ID, so the messages signed with this
+/// keypair carry the ~300 bytes protobuf encoded public key in their key field.
+const TEST_RSA_KEYPAIR: &[u8] = include_bytes!("rsa_2048_keypair.pk8");
+
+/// Create the RSA test keypair.
+fn rsa_keypair() -> Keypair {
+    Keypair::rsa_from_pkcs8(&mut TEST_RSA_KEYPAIR.to_vec()).expect("valid RSA keypair")
+}
+
 /// Create a new test topic with a random name.
 fn new_test_topic() -> IdentTopic {
     IdentTopic::new(format!(
@@ -43,7 +56,7 @@ fn new_libp2p_topic(raw: &str) -> Libp2pGossipsubIdentTopic {
 fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
     let peer_id = PeerId::from(keypair.public());
     let transport = testlib::test_transport(keypair);
-    let behaviour = Behaviour::new(config, Default::default());
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
     SwarmBuilder::with_executor(
         transport,
         behaviour,
@@ -240,6 +253,107 @@ async fn floodsub_node_publish_and_gossipsub_node_subscribes() {
     });
 }
 
+/// Interoperability test where a Floodsub node, with the frame diagnostics enabled, acts publisher
+/// and a Libp2p Gosssipsub Node (with Floodsub support enabled) acts as subscriber.
+///
+/// The frames sent by the publisher, subscriptions and message, carry the checksum trailer field.
+/// The subscriber asserts the frames are decoded, ignoring the trailer field, and the message is
+/// received.
+#[tokio::test]
+async fn floodsub_node_with_frame_diagnostics_publish_and_gossipsub_node_subscribes() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let libp2p_topic = new_libp2p_topic(topic.hash().as_str());
+
+    let message_payload = b"test-payload";
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let publisher_config = ConfigBuilder::default().frame_diagnostics(true).build();
+    let subscriber_config = Libp2pGossipsubConfigBuilder::default()
+        .validation_mode(Libp2pGossipsubValidationMode::Permissive)
+        .support_floodsub()
+        .build()
+        .expect("valid gossipsub configuration");
+
+    let mut publisher = new_test_node(&publisher_key, publisher_config.clone());
+    testlib::swarm::should_listen_on_address(&mut publisher, any_memory_addr());
+
+    let mut libp2p_subscriber = new_libp2p_gossipsub_node(
+        &subscriber_key,
+        Libp2pGossipsubMessageAuthenticity::Anonymous,
+        subscriber_config.clone(),
+    );
+    testlib::swarm::should_listen_on_address(&mut libp2p_subscriber, any_memory_addr());
+
+    let (_publisher_addr, subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut publisher, &mut libp2p_subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    publisher
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    libp2p_subscriber
+        .behaviour_mut()
+        .subscribe(&libp2p_topic)
+        .expect("subscribe to topic");
+
+    // Poll the pub-sub network to process the subscriptions
+    testlib::swarm::poll_mesh(
+        Duration::from_micros(10),
+        &mut publisher,
+        &mut libp2p_subscriber,
+    )
+    .await;
+
+    // Dial the publisher node
+    testlib::swarm::should_dial_address(&mut publisher, subscriber_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut publisher, &mut libp2p_subscriber),
+    )
+    .await
+    .expect("publisher to dial the subscriber");
+
+    testlib::swarm::poll_mesh(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut libp2p_subscriber,
+    )
+    .await;
+
+    //// When
+    let message = Message::new(topic.clone(), *message_payload);
+    publisher
+        .behaviour_mut()
+        .publish(message)
+        .expect("publish the message");
+
+    let sub_events = wait_mesh_message_propagation(
+        Duration::from_millis(50),
+        &mut publisher,
+        &mut libp2p_subscriber,
+    )
+    .await;
+
+    //// Then
+    let last_event = sub_events.last().expect("at least one event");
+    assert_matches!(last_event, SwarmEvent::Behaviour(Libp2pGossipsubEvent::Message { message, .. }) => {
+        assert!(message.sequence_number.is_none());
+        assert!(message.source.is_none());
+        assert_eq!(message.topic.as_str(), topic.hash().as_str());
+        assert_eq!(message.data[..], message_payload[..]);
+    });
+}
+
 /// Interoperability test where a Libp2p Gossipsub node (with Floodsub support enabled) acts
 /// publisher and a Floodsub node acts as subscriber.
 ///
@@ -337,3 +451,191 @@ async fn gossipsub_node_publish_and_floodsub_node_subscribes() {
         assert_eq!(message.data, message_payload[..]);
     });
 }
+
+/// Interoperability test where a Libp2p Gossipsub node (with Floodsub support enabled), with an
+/// RSA identity, acts as publisher and a Floodsub node acts as subscriber.
+///
+/// The publisher signs the message and attaches its ~300 bytes public key to the message key
+/// field, the subscriber asserts the reception of the identical key bytes.
+#[tokio::test]
+async fn gossipsub_node_publish_signed_message_key_and_floodsub_node_subscribes() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let libp2p_topic = new_libp2p_topic(topic.hash().as_str());
+
+    let publisher_key = rsa_keypair();
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let libp2p_publisher_config = Libp2pGossipsubConfigBuilder::default()
+        .support_floodsub()
+        .build()
+        .expect("valid gossipsub configuration");
+
+    let mut libp2p_publisher = new_libp2p_gossipsub_node(
+        &publisher_key,
+        Libp2pGossipsubMessageAuthenticity::Signed(publisher_key.clone()),
+        libp2p_publisher_config,
+    );
+    testlib::swarm::should_listen_on_address(&mut libp2p_publisher, any_memory_addr());
+
+    let mut subscriber = new_test_node(&subscriber_key, Config::default());
+    testlib::swarm::should_listen_on_address(&mut subscriber, any_memory_addr());
+
+    let (libp2p_publisher_addr, _subscriber_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut libp2p_publisher, &mut subscriber),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    libp2p_publisher
+        .behaviour_mut()
+        .subscribe(&libp2p_topic)
+        .expect("subscribe to topic");
+    subscriber
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    // Dial the publisher node
+    testlib::swarm::should_dial_address(&mut subscriber, libp2p_publisher_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut libp2p_publisher),
+    )
+    .await
+    .expect("subscriber to dial the publisher");
+
+    testlib::swarm::poll_mesh(
+        Duration::from_millis(50),
+        &mut subscriber,
+        &mut libp2p_publisher,
+    )
+    .await;
+
+    //// When
+    libp2p_publisher
+        .behaviour_mut()
+        .publish(libp2p_topic.hash(), *b"test-payload")
+        .expect("publish the message");
+
+    let sub_events = wait_mesh_libp2p_gossipsub_message_propagation(
+        Duration::from_millis(50),
+        &mut libp2p_publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    //// Then
+    let expected_key = publisher_key.public().encode_protobuf();
+    assert!(expected_key.len() >= 290, "The key should be ~300 bytes");
+    assert_matches!(sub_events.last(), Some(SwarmEvent::Behaviour(Event::MessageReceived { message, .. })) => {
+        assert_eq!(message.from, Some(*libp2p_publisher.local_peer_id()));
+        assert_eq!(message.key.as_deref(), Some(expected_key.as_slice()));
+    });
+}
+
+/// Interoperability test where a Floodsub node publishes a message with a 300 bytes key, relayed
+/// by a Libp2p Gossipsub node (with Floodsub support enabled) to another Floodsub node.
+///
+/// The second Floodsub node is an explicit peer of the relay, so the relay forwards the received
+/// messages to it. The subscriber asserts the reception of the identical key bytes.
+#[tokio::test]
+async fn floodsub_node_publish_message_key_relayed_by_gossipsub_node() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let libp2p_topic = new_libp2p_topic(topic.hash().as_str());
+
+    let publisher_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let relay_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let subscriber_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    let relay_config = Libp2pGossipsubConfigBuilder::default()
+        .validation_mode(Libp2pGossipsubValidationMode::Permissive)
+        .support_floodsub()
+        .build()
+        .expect("valid gossipsub configuration");
+
+    let mut publisher = new_test_node(&publisher_key, Config::default());
+    let mut subscriber = new_test_node(&subscriber_key, Config::default());
+
+    let mut libp2p_relay = new_libp2p_gossipsub_node(
+        &relay_key,
+        Libp2pGossipsubMessageAuthenticity::Anonymous,
+        relay_config,
+    );
+    testlib::swarm::should_listen_on_address(&mut libp2p_relay, any_memory_addr());
+    let relay_addr = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_new_listen_addr(&mut libp2p_relay),
+    )
+    .await
+    .expect("listening to start");
+
+    // Subscribe to the topic
+    libp2p_relay
+        .behaviour_mut()
+        .subscribe(&libp2p_topic)
+        .expect("subscribe to topic");
+    libp2p_relay
+        .behaviour_mut()
+        .add_explicit_peer(subscriber.local_peer_id());
+    for node in [&mut publisher, &mut subscriber] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    // Dial the relay node: Publisher -> Relay <- Subscriber
+    testlib::swarm::should_dial_address(&mut publisher, relay_addr.clone());
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut publisher, &mut libp2p_relay),
+    )
+    .await
+    .expect("publisher to dial the relay");
+
+    testlib::swarm::should_dial_address(&mut subscriber, relay_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut subscriber, &mut libp2p_relay),
+    )
+    .await
+    .expect("subscriber to dial the relay");
+
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut libp2p_relay,
+        &mut publisher,
+        &mut subscriber,
+    )
+    .await;
+
+    //// When
+    let key = (0..=255).cycle().take(300).collect::<Vec<u8>>();
+    let mut message = Message::new(topic.clone(), *b"test-payload");
+    message.set_key(key.clone());
+    publisher
+        .behaviour_mut()
+        .publish(message)
+        .expect("publish the message");
+
+    let sub_events = tokio::select! {
+        _ = timeout(Duration::from_millis(100), async {
+            tokio::join!(testlib::swarm::poll(&mut publisher), testlib::swarm::poll(&mut libp2p_relay))
+        }) => panic!("timeout reached"),
+        res = wait_for_message_event(&mut subscriber) => res,
+    };
+
+    //// Then
+    assert_matches!(sub_events.last(), Some(SwarmEvent::Behaviour(Event::MessageReceived { src, message, .. })) => {
+        assert_eq!(src, libp2p_relay.local_peer_id(), "The message should be relayed");
+        assert_eq!(message.data, b"test-payload");
+        assert_eq!(message.key.as_deref(), Some(key.as_slice()));
+    });
+}
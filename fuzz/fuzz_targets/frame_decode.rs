@@ -0,0 +1,15 @@
+//! Raw bytes → `FrameProto::decode` → validated frame parts.
+//!
+//! Processing an arbitrary byte buffer received from a remote peer must never panic, invalid
+//! frames and frame parts must be discarded.
+
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+
+use libp2p_pubsub_core::fuzzing::process_raw_frame;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = process_raw_frame(Bytes::copy_from_slice(data));
+});
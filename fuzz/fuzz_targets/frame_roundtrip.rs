@@ -0,0 +1,47 @@
+//! Arbitrary valid messages → multi-message frame encoding → frame decoding.
+//!
+//! Every valid message encoded into a frame must survive the decode and validation round trip.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use libp2p_pubsub_core::fuzzing::{encode_messages_frame, process_raw_frame};
+use libp2p_pubsub_core::FrameMessage;
+
+/// An arbitrary valid message.
+#[derive(Debug, Arbitrary)]
+struct ArbitraryMessage {
+    topic: String,
+    data: Vec<u8>,
+    seqno: Option<Vec<u8>>,
+}
+
+fuzz_target!(|messages: Vec<ArbitraryMessage>| {
+    let messages = messages
+        .into_iter()
+        .filter(|msg| !msg.topic.is_empty())
+        // A payload starting with the time-to-live envelope magic bytes may carry an expiry time
+        // that has already passed, and the message would be dropped.
+        .filter(|msg| !msg.data.starts_with(b"PSTL"))
+        .map(|msg| {
+            let mut message = FrameMessage::new(msg.topic, msg.data);
+            message.set_seqno(msg.seqno);
+            message
+        })
+        .collect::<Vec<_>>();
+    if messages.is_empty() {
+        return;
+    }
+
+    let count = messages.len();
+    let processed = process_raw_frame(encode_messages_frame(messages));
+
+    assert_eq!(
+        processed.messages, count,
+        "all valid messages must be decoded"
+    );
+    assert_eq!(processed.subscriptions, 0);
+    assert_eq!(processed.control, 0);
+});
@@ -0,0 +1,46 @@
+//! Arbitrary `MessageProto` → `TryFrom<MessageProto> for Message`.
+//!
+//! The conversion must never panic and, if it succeeds, the resulting message accessors must not
+//! panic either.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+
+use libp2p_pubsub_core::{FrameMessage, Message};
+use libp2p_pubsub_proto::pubsub::MessageProto;
+
+/// An arbitrary [`MessageProto`].
+///
+/// The protobuf generated types do not implement [`Arbitrary`].
+#[derive(Debug, Arbitrary)]
+struct ArbitraryMessageProto {
+    from: Option<Vec<u8>>,
+    data: Option<Vec<u8>>,
+    seqno: Option<Vec<u8>>,
+    topic: String,
+    signature: Option<Vec<u8>>,
+    key: Option<Vec<u8>>,
+}
+
+impl From<ArbitraryMessageProto> for MessageProto {
+    fn from(msg: ArbitraryMessageProto) -> Self {
+        Self {
+            from: msg.from.map(Bytes::from),
+            data: msg.data.map(Bytes::from),
+            seqno: msg.seqno.map(Bytes::from),
+            topic: msg.topic,
+            signature: msg.signature.map(Bytes::from),
+            key: msg.key.map(Bytes::from),
+        }
+    }
+}
+
+fuzz_target!(|msg: ArbitraryMessageProto| {
+    let proto: MessageProto = msg.into();
+    if let Ok(msg) = FrameMessage::try_from(proto) {
+        let _ = Message::from(msg);
+    }
+});
@@ -0,0 +1,70 @@
+//! Benchmarks of the time-to-live cache expired entries sweep.
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, SamplingMode};
+
+use libp2p_pubsub_common::ttl_cache::Cache;
+
+/// The number of entries in the benchmarked cache.
+const CACHE_SIZE: u64 = 100_000;
+
+/// The benchmarked cache entries time-to-live.
+///
+/// It must be long enough for the non-expired entries not to expire while the cache is filled
+/// and the benchmark runs.
+const TTL: Duration = Duration::from_millis(200);
+
+/// Create a cache with `CACHE_SIZE` entries, where `expired_pct` percent of them are expired.
+fn new_cache(expired_pct: u64) -> Cache<u64, u64> {
+    let mut cache = Cache::with_capacity_and_ttl(CACHE_SIZE as usize, TTL);
+
+    let expired = CACHE_SIZE * expired_pct / 100;
+    for key in 0..expired {
+        cache.put(key, key);
+    }
+
+    // Wait for the inserted entries to expire
+    if expired > 0 {
+        sleep(TTL + Duration::from_millis(10));
+    }
+
+    for key in expired..CACHE_SIZE {
+        cache.put(key, key);
+    }
+
+    cache
+}
+
+fn bench_clear_expired_entries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ttl_cache/clear_expired_entries");
+
+    // The cache setup dominates the iteration time (it waits for the entries to expire), so
+    // keep the number of iterations to the minimum.
+    group
+        .sampling_mode(SamplingMode::Flat)
+        .sample_size(10)
+        .warm_up_time(Duration::from_millis(1))
+        .measurement_time(Duration::from_millis(1));
+
+    for expired_pct in [0, 10] {
+        // The sweep is destructive, so each iteration requires a fresh cache.
+        group.bench_with_input(
+            BenchmarkId::new("expired_pct", expired_pct),
+            &expired_pct,
+            |b, &expired_pct| {
+                b.iter_batched_ref(
+                    || new_cache(expired_pct),
+                    |cache| cache.clear_expired_entries(),
+                    BatchSize::PerIteration,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_clear_expired_entries);
+criterion_main!(benches);
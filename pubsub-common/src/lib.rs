@@ -2,4 +2,6 @@ pub mod heartbeat;
 
 /// A stateful entity that can process and produce events.
 pub mod service;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 pub mod ttl_cache;
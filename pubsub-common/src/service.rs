@@ -16,9 +16,11 @@ pub use context::ServiceContext;
 pub use context_handles::{InCtx, OutCtx};
 pub use event_handler_trait::{EventHandler, OnEventCtx};
 pub use service_trait::{InEvent, JointCtx, OutEvent, PollCtx, Service};
+pub use spawned_context::SpawnedContext;
 
 mod buffered_context;
 mod context;
 mod context_handles;
 mod event_handler_trait;
 mod service_trait;
+mod spawned_context;
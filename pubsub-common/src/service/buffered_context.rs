@@ -123,6 +123,18 @@ impl<S: Service> BufferedContext<S> {
     pub fn service(&self) -> &S {
         &self.service
     }
+
+    /// Get the number of events queued in the service input mailbox, not yet processed by the
+    /// service.
+    pub fn inbox_len(&self) -> usize {
+        self.inbox.len()
+    }
+
+    /// Get the number of events queued in the service output mailbox, not yet returned by
+    /// [`BufferedContext::poll`].
+    pub fn outbox_len(&self) -> usize {
+        self.outbox.len()
+    }
 }
 
 impl<S: Service + Default> Default for BufferedContext<S> {
@@ -0,0 +1,272 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::task::{Context, Poll};
+
+use futures::channel::mpsc;
+use futures::future::{self, BoxFuture};
+use futures::StreamExt;
+
+use super::buffered_context::BufferedContext;
+use super::context::ServiceContext;
+use super::service_trait::Service;
+
+/// A spawned service context.
+///
+/// This [`ServiceContext`] implementation moves the service into a [`BufferedContext`] driven by a
+/// separate task, spawned with the given executor. The service input and output events are bridged
+/// over bounded channels, so a service doing heavy work per event does not block the task polling
+/// the context.
+///
+/// The events sent while the input channel is full are buffered by the context, and forwarded to
+/// the service task in order, as soon as the channel has capacity again (see
+/// [`pending_len`](#method.pending_len)). No event is dropped.
+///
+/// The service task stops when the context is dropped.
+pub struct SpawnedContext<S: Service> {
+    /// The service task input channel.
+    in_tx: mpsc::Sender<S::InEvent>,
+    /// The service task output channel.
+    out_rx: mpsc::Receiver<S::OutEvent>,
+    /// The events pending to be sent to the service task, waiting for input channel capacity.
+    pending: VecDeque<S::InEvent>,
+    _service: PhantomData<fn() -> S>,
+}
+
+/// Public API.
+impl<S> SpawnedContext<S>
+where
+    S: Service + Send,
+    S::InEvent: Send,
+    S::OutEvent: Send,
+{
+    /// Create a new service context, spawning the service task with the given `executor`.
+    ///
+    /// The `capacity` is the number of events buffered by each of the input and output channels.
+    ///
+    /// ```ignore
+    /// let context = SpawnedContext::new(service, 64, |fut| {
+    ///     tokio::spawn(fut);
+    /// });
+    /// ```
+    pub fn new(service: S, capacity: usize, executor: impl FnOnce(BoxFuture<'static, ()>)) -> Self {
+        let (in_tx, in_rx) = mpsc::channel(capacity);
+        let (out_tx, out_rx) = mpsc::channel(capacity);
+
+        executor(Box::pin(run_service(
+            BufferedContext::new(service),
+            in_rx,
+            out_tx,
+        )));
+
+        Self {
+            in_tx,
+            out_rx,
+            pending: VecDeque::new(),
+            _service: PhantomData,
+        }
+    }
+}
+
+impl<S: Service> SpawnedContext<S> {
+    /// Get the number of events pending to be sent to the service task.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Forward the pending events to the service task, in order, until the input channel is full.
+    ///
+    /// If a task context is given, the current task is woken up once the input channel has
+    /// capacity again.
+    fn flush_pending(&mut self, mut cx: Option<&mut Context<'_>>) {
+        while !self.pending.is_empty() {
+            if let Some(cx) = cx.as_mut() {
+                match self.in_tx.poll_ready(cx) {
+                    Poll::Ready(Ok(())) => {}
+                    Poll::Ready(Err(_)) => {
+                        // The service task stopped, drop the pending events.
+                        self.pending.clear();
+                        return;
+                    }
+                    Poll::Pending => return,
+                }
+            }
+
+            let Some(ev) = self.pending.pop_front() else {
+                return;
+            };
+            if let Err(err) = self.in_tx.try_send(ev) {
+                if err.is_disconnected() {
+                    self.pending.clear();
+                    return;
+                }
+
+                // The input channel is full, keep the event at the front of the queue.
+                self.pending.push_front(err.into_inner());
+                return;
+            }
+        }
+    }
+}
+
+/// Drive the `service` until its input channel is closed, forwarding the input channel events to
+/// the service and the service output events to the output channel.
+async fn run_service<S: Service>(
+    mut service: BufferedContext<S>,
+    mut in_rx: mpsc::Receiver<S::InEvent>,
+    mut out_tx: mpsc::Sender<S::OutEvent>,
+) {
+    let mut pending_out: Option<S::OutEvent> = None;
+
+    future::poll_fn(move |cx| loop {
+        // Wait for output channel capacity before emitting any other event.
+        if let Some(ev) = pending_out.take() {
+            match out_tx.poll_ready(cx) {
+                Poll::Ready(Ok(())) => {
+                    if out_tx.start_send(ev).is_err() {
+                        return Poll::Ready(());
+                    }
+                }
+                Poll::Ready(Err(_)) => return Poll::Ready(()),
+                Poll::Pending => {
+                    pending_out = Some(ev);
+                    return Poll::Pending;
+                }
+            }
+        }
+
+        // Forward the received events to the service.
+        loop {
+            match in_rx.poll_next_unpin(cx) {
+                Poll::Ready(Some(ev)) => service.do_send(ev),
+                Poll::Ready(None) => return Poll::Ready(()),
+                Poll::Pending => break,
+            }
+        }
+
+        match service.poll(cx) {
+            Poll::Ready(ev) => pending_out = Some(ev),
+            Poll::Pending => return Poll::Pending,
+        }
+    })
+    .await
+}
+
+impl<S: Service> ServiceContext for SpawnedContext<S> {
+    type InEvent = S::InEvent;
+    type OutEvent = S::OutEvent;
+
+    /// Sends a message unconditionally, ignoring any potential errors.
+    ///
+    /// The event is forwarded to the service task if the input channel has capacity. Otherwise,
+    /// it is buffered until the next [`SpawnedContext::poll`] call finds capacity.
+    fn do_send(&mut self, ev: S::InEvent) {
+        self.pending.push_back(ev);
+        self.flush_pending(None);
+    }
+
+    /// Poll the service task for events.
+    ///
+    /// The polling process consists of the following steps:
+    ///  1. Forward the pending events to the service task, registering the current task to be
+    ///     woken up once the input channel has capacity, if full.
+    ///  2. Return the next service output event, if any.
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<S::OutEvent> {
+        self.flush_pending(Some(cx));
+
+        match self.out_rx.poll_next_unpin(cx) {
+            Poll::Ready(Some(ev)) => Poll::Ready(ev),
+            // The service task stopped.
+            Poll::Ready(None) => Poll::Pending,
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::service::{InCtx, OutCtx, PollCtx};
+
+    use super::*;
+
+    /// A service echoing its input events, sleeping the given delay per event.
+    struct EchoService {
+        delay: Duration,
+    }
+
+    impl Service for EchoService {
+        type InEvent = u64;
+        type OutEvent = u64;
+
+        fn poll<'a>(
+            &mut self,
+            svc_cx: impl PollCtx<'a, Self::InEvent, Self::OutEvent>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Self::OutEvent> {
+            let (mut in_cx, mut out_cx) = svc_cx.split();
+            while let Some(ev) = in_cx.pop_next() {
+                std::thread::sleep(self.delay);
+                out_cx.emit(ev);
+            }
+            Poll::Pending
+        }
+    }
+
+    fn new_test_context(delay: Duration, capacity: usize) -> SpawnedContext<EchoService> {
+        SpawnedContext::new(EchoService { delay }, capacity, |fut| {
+            tokio::spawn(fut);
+        })
+    }
+
+    async fn next_event(context: &mut SpawnedContext<EchoService>) -> u64 {
+        future::poll_fn(|cx| context.poll(cx)).await
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn slow_service_does_not_block_the_polling_task() {
+        //// Given
+        let mut context = new_test_context(Duration::from_millis(200), 8);
+
+        //// When
+        let start = Instant::now();
+        context.do_send(1);
+        let polled = future::poll_fn(|cx| Poll::Ready(context.poll(cx))).await;
+        let elapsed = start.elapsed();
+
+        let event = next_event(&mut context).await;
+
+        //// Then
+        assert!(polled.is_pending(), "No event should be ready yet");
+        assert!(
+            elapsed < Duration::from_millis(100),
+            "Sending and polling should not wait for the service"
+        );
+        assert_eq!(event, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn events_order_is_preserved_when_the_input_channel_is_full() {
+        //// Given
+        let mut context = new_test_context(Duration::ZERO, 2);
+
+        //// When
+        for ev in 0..256 {
+            context.do_send(ev);
+        }
+        let pending = context.pending_len();
+
+        let mut events = Vec::new();
+        while events.len() < 256 {
+            events.push(next_event(&mut context).await);
+        }
+
+        //// Then
+        assert!(
+            pending > 0,
+            "Events should be buffered when the channel is full"
+        );
+        assert_eq!(events, (0..256).collect::<Vec<_>>());
+        assert_eq!(context.pending_len(), 0);
+    }
+}
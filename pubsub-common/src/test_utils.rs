@@ -0,0 +1,233 @@
+//! Test utilities for the services and event handlers, e.g., the protocol routers.
+//!
+//! The helpers drive any [`Service`] wrapped in a [`BufferedContext`]: inject input events into
+//! the service input mailbox, poll the service, and collect the emitted output events. The
+//! synchronous helpers take a task [`Context`], e.g., a [`noop_context`], and the `async_*`
+//! helpers poll the service with the current task context, so the service timers wake the test
+//! task.
+//!
+//! This module requires the `test-utils` feature.
+//!
+//! # Example
+//!
+//! A minimal test of a router, forwarding the received messages to all the connected peers but
+//! the message source:
+//!
+//! ```
+//! use std::collections::BTreeSet;
+//!
+//! use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+//! use libp2p_pubsub_common::test_utils::{self, EventSequence};
+//!
+//! enum RouterIn {
+//!     PeerConnected(u32),
+//!     MessageReceived { src: u32, message: &'static str },
+//! }
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum RouterOut {
+//!     ForwardMessage { dest: Vec<u32>, message: &'static str },
+//! }
+//!
+//! #[derive(Default)]
+//! struct Router {
+//!     peers: BTreeSet<u32>,
+//! }
+//!
+//! impl EventHandler for Router {
+//!     type InEvent = RouterIn;
+//!     type OutEvent = RouterOut;
+//!
+//!     fn on_event<'a>(&mut self, svc_cx: &mut impl OnEventCtx<'a, RouterOut>, ev: RouterIn) {
+//!         match ev {
+//!             RouterIn::PeerConnected(peer) => {
+//!                 self.peers.insert(peer);
+//!             }
+//!             RouterIn::MessageReceived { src, message } => {
+//!                 let dest = self.peers.iter().copied().filter(|p| *p != src).collect();
+//!                 svc_cx.emit(RouterOut::ForwardMessage { dest, message });
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! //// Given
+//! let mut router = test_utils::default_test_service::<Router>();
+//!
+//! //// When
+//! let input_events = EventSequence::new()
+//!     .then_all((1..=3).map(RouterIn::PeerConnected))
+//!     .then(RouterIn::MessageReceived { src: 2, message: "hello" });
+//! test_utils::inject_events(&mut router, input_events);
+//!
+//! let output_events = test_utils::collect_events(&mut router, &mut test_utils::noop_context());
+//!
+//! //// Then
+//! assert_eq!(
+//!     output_events,
+//!     [RouterOut::ForwardMessage { dest: vec![1, 3], message: "hello" }]
+//! );
+//! ```
+
+use std::future::poll_fn;
+use std::task::{Context, Poll};
+
+use futures::task::noop_waker_ref;
+
+use crate::service::{BufferedContext, Service, ServiceContext};
+
+/// Create a no-op task [`Context`] for testing.
+///
+/// The context waker does nothing when woken, so the services are only polled when the test
+/// polls them.
+pub fn noop_context() -> Context<'static> {
+    Context::from_waker(noop_waker_ref())
+}
+
+/// Create a new [`Service`] with default values wrapped in a [`BufferedContext`].
+pub fn default_test_service<S: Service + Default>() -> BufferedContext<S> {
+    BufferedContext::default()
+}
+
+/// Inject the events into the [`Service`] input mailbox.
+///
+/// The events are processed on the next poll of the service.
+pub fn inject_events<S>(
+    service: &mut BufferedContext<S>,
+    events: impl IntoIterator<Item = S::InEvent>,
+) where
+    S: Service,
+{
+    for event in events {
+        service.do_send(event);
+    }
+}
+
+/// Poll a [`Service`] until it returns `Poll::Pending`.
+///
+/// All events emitted by the service are discarded. See [`collect_events`] to collect all events
+/// emitted by the service.
+pub fn poll<S>(service: &mut BufferedContext<S>, cx: &mut Context<'_>)
+where
+    S: Service,
+{
+    while service.poll(cx).is_ready() {}
+}
+
+/// Poll a [`Service`] and collect all events from it.
+///
+/// This function polls the service until it returns `Poll::Pending`. Returns a `Vec` of all events
+/// emitted by the service.
+pub fn collect_events<S>(service: &mut BufferedContext<S>, cx: &mut Context<'_>) -> Vec<S::OutEvent>
+where
+    S: Service,
+{
+    let mut events = Vec::new();
+    while let Poll::Ready(event) = service.poll(cx) {
+        events.push(event);
+    }
+    events
+}
+
+/// Poll asynchronously a [`Service`] until it returns `Poll::Pending`.
+///
+/// All events emitted by the service are discarded. See [`async_collect_events`] to collect all
+/// events emitted by the service.
+pub async fn async_poll<S>(service: &mut BufferedContext<S>)
+where
+    S: Service,
+{
+    poll_fn(|cx| {
+        while service.poll(cx).is_ready() {}
+        Poll::Ready(())
+    })
+    .await
+}
+
+/// Poll asynchronously a [`Service`] and collect all events from it.
+///
+/// This function polls the service until it returns `Poll::Pending`. Returns a `Vec` of all events
+/// emitted by the service.
+pub async fn async_collect_events<S>(service: &mut BufferedContext<S>) -> Vec<S::OutEvent>
+where
+    S: Service,
+{
+    poll_fn(|cx| {
+        let mut events = Vec::new();
+        while let Poll::Ready(event) = service.poll(cx) {
+            events.push(event);
+        }
+        Poll::Ready(events)
+    })
+    .await
+}
+
+/// A builder of input event sequences, to inject into a service with [`inject_events`].
+///
+/// ```
+/// use libp2p_pubsub_common::test_utils::EventSequence;
+///
+/// let seq = EventSequence::new().then(1).then_all([2, 3]).then(4);
+/// assert_eq!(seq.into_iter().collect::<Vec<_>>(), [1, 2, 3, 4]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventSequence<E> {
+    events: Vec<E>,
+}
+
+impl<E> Default for EventSequence<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> EventSequence<E> {
+    /// Create a new empty event sequence.
+    #[must_use]
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+
+    /// Append an event to the sequence.
+    #[must_use]
+    pub fn then(mut self, event: E) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    /// Append the events, e.g., another sequence, to the sequence.
+    #[must_use]
+    pub fn then_all(mut self, events: impl IntoIterator<Item = E>) -> Self {
+        self.events.extend(events);
+        self
+    }
+
+    /// The number of events in the sequence.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether the sequence is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl<E> IntoIterator for EventSequence<E> {
+    type Item = E;
+    type IntoIter = std::vec::IntoIter<E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.into_iter()
+    }
+}
+
+impl<E> FromIterator<E> for EventSequence<E> {
+    fn from_iter<I: IntoIterator<Item = E>>(iter: I) -> Self {
+        Self {
+            events: iter.into_iter().collect(),
+        }
+    }
+}
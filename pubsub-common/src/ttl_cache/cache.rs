@@ -9,6 +9,8 @@ struct CacheEntry<M> {
     pub timestamp: Instant,
     /// The message.
     pub message: M,
+    /// The entry cost in bytes, accounted against the cache byte limit.
+    pub cost: usize,
 }
 
 /// Cache of messages that we have already seen.
@@ -21,11 +23,18 @@ pub struct Cache<K, V> {
     /// Time-to-live of messages in the cache.
     ttl: Duration,
 
+    /// Maximum total cost, in bytes, of the messages in the cache. `None` if unlimited.
+    max_bytes: Option<usize>,
+
+    /// Total cost, in bytes, of the entries in the cache (expired and not-expired).
+    bytes: usize,
+
     /// The internal cache data structure.
     ///
     /// A `LinkedHashMap` is used to keep track of the insertion order of the messages. The
     /// oldest insertions are at the front of the map, and the newest insertions are at the back of
-    /// the map.
+    /// the map. As refreshed entries are moved to the back of the map, the map is also ordered by
+    /// expiration time: the expired entries are always a prefix of the map.
     cache: LinkedHashMap<K, CacheEntry<V>>,
 }
 
@@ -42,14 +51,64 @@ impl<K, V> Cache<K, V> {
     /// Creates a new empty cache with the given capacity and time-to-live.
     #[must_use]
     pub fn with_capacity_and_ttl(capacity: usize, ttl: Duration) -> Self {
+        Self::with_capacity_ttl_and_byte_limit(capacity, ttl, None)
+    }
+
+    /// Creates a new empty cache with the given capacity, time-to-live and byte limit.
+    ///
+    /// Once the total cost of the entries (see [`Cache::put_with_cost`]) exceeds the byte limit,
+    /// the oldest entries are evicted until the cache is under the limit again. The newest entry
+    /// is never evicted, even if its cost alone exceeds the limit. A `None`, or zero, byte limit
+    /// disables the byte accounting eviction.
+    #[must_use]
+    pub fn with_capacity_ttl_and_byte_limit(
+        capacity: usize,
+        ttl: Duration,
+        max_bytes: Option<usize>,
+    ) -> Self {
         Self {
             capacity,
             ttl,
+            max_bytes: max_bytes.filter(|max_bytes| *max_bytes > 0),
+            bytes: 0,
             cache: LinkedHashMap::with_capacity(capacity),
         }
     }
 }
 
+impl<K, V> Cache<K, V> {
+    /// Returns `true` if the entry has expired at the given instant.
+    fn is_expired(&self, entry: &CacheEntry<V>, now: Instant) -> bool {
+        now.saturating_duration_since(entry.timestamp) > self.ttl
+    }
+
+    /// Returns the number of expired entries still present in the cache.
+    ///
+    /// As the expired entries are a prefix of the map, only the expired entries (and the first
+    /// non-expired entry) are visited.
+    fn expired_count(&self) -> usize {
+        let now = Instant::now();
+        self.cache
+            .values()
+            .take_while(|entry| self.is_expired(entry, now))
+            .count()
+    }
+
+    /// Returns `true` if the cache exceeds its byte limit.
+    fn is_over_byte_limit(&self) -> bool {
+        matches!(self.max_bytes, Some(max_bytes) if self.bytes > max_bytes)
+    }
+
+    /// Returns the total cost, in bytes, of the entries in the cache.
+    ///
+    /// The expired entries still present in the cache, i.e., not cleared yet (see
+    /// [`Cache::clear_expired_entries`]), are accounted.
+    #[must_use]
+    pub fn byte_usage(&self) -> usize {
+        self.bytes
+    }
+}
+
 impl<K, V> Cache<K, V>
 where
     K: Eq + Hash + Clone,
@@ -61,6 +120,17 @@ where
     ///
     /// If the source is `None`, then the message is assumed to have been sent by us.
     pub fn put(&mut self, id: K, message: V) -> bool {
+        self.put_with_cost(id, message, 0)
+    }
+
+    /// Inserts a message in the cache, accounting the given cost, in bytes, against the cache
+    /// byte limit.
+    ///
+    /// If the message was already in the cache, its entry is refreshed and keeps its original
+    /// message and cost.
+    ///
+    /// See [`Cache::put`] for the return value.
+    pub fn put_with_cost(&mut self, id: K, message: V, cost: usize) -> bool {
         let result = match self.cache.raw_entry_mut().from_key(&id) {
             RawEntryMut::Occupied(mut entry) => {
                 // If the entry has expired but it is still present, update the timestamp
@@ -76,7 +146,15 @@ where
             }
             RawEntryMut::Vacant(entry) => {
                 let timestamp = Instant::now();
-                entry.insert(id, CacheEntry { timestamp, message });
+                entry.insert(
+                    id,
+                    CacheEntry {
+                        timestamp,
+                        message,
+                        cost,
+                    },
+                );
+                self.bytes += cost;
 
                 true
             }
@@ -84,12 +162,24 @@ where
 
         // If the cache is full, remove the oldest message.
         if self.cache.len() > self.capacity {
-            self.cache.pop_front();
+            self.pop_front();
+        }
+
+        // If the cache exceeds the byte limit, remove the oldest messages, but the newest one.
+        while self.cache.len() > 1 && self.is_over_byte_limit() {
+            self.pop_front();
         }
 
         result
     }
 
+    /// Removes the oldest entry of the cache, updating the byte usage.
+    fn pop_front(&mut self) -> Option<CacheEntry<V>> {
+        let (_, entry) = self.cache.pop_front()?;
+        self.bytes -= entry.cost;
+        Some(entry)
+    }
+
     /// Returns an iterator over all the entries of the cache (expired and not-expired).
     #[cfg(test)]
     pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
@@ -99,10 +189,7 @@ where
     /// Returns the number of non-expired messages in the cache.
     #[must_use]
     pub fn len(&self) -> usize {
-        self.cache
-            .iter()
-            .skip_while(|(_, entry)| entry.timestamp.elapsed() > self.ttl)
-            .count()
+        self.cache.len() - self.expired_count()
     }
 
     /// Returns if the cache is empty.
@@ -130,12 +217,26 @@ where
             .map(|entry| &entry.message)
     }
 
+    /// Returns a mutable reference to the message with the given ID, if it exists in the cache and
+    /// has not expired.
+    ///
+    /// The entry insertion time is not updated.
+    #[must_use]
+    pub fn get_mut(&mut self, id: &K) -> Option<&mut V> {
+        let ttl = self.ttl;
+        self.cache
+            .get_mut(id)
+            .filter(|entry| entry.timestamp.elapsed() <= ttl)
+            .map(|entry| &mut entry.message)
+    }
+
     /// Removes the message with the given ID from the cache.
     ///
     /// Returns the removed cache entry, if it existed in the cache and had not expired.
     pub fn remove(&mut self, id: &K) -> Option<V> {
-        self.cache
-            .remove(id)
+        let entry = self.cache.remove(id)?;
+        self.bytes -= entry.cost;
+        Some(entry)
             .filter(|entry| entry.timestamp.elapsed() <= self.ttl)
             .map(|entry| entry.message)
     }
@@ -144,19 +245,17 @@ where
     ///
     /// An entry is considered expired if the elapsed time since the insertion of the entry is
     /// greater than the time-to-live of the cache, then the entry is considered expired.
+    ///
+    /// The expired entries are popped from the front of the map, so the sweep cost is proportional
+    /// to the number of expired entries, not to the cache size.
     pub fn clear_expired_entries(&mut self) {
-        let mut to_remove = Vec::new();
-
-        for (id, entry) in self.cache.iter() {
-            if entry.timestamp.elapsed() <= self.ttl {
+        let now = Instant::now();
+        while let Some((_, entry)) = self.cache.front() {
+            if !self.is_expired(entry, now) {
                 break;
             }
 
-            to_remove.push(id.clone());
-        }
-
-        for id in to_remove {
-            self.cache.remove(&id);
+            self.pop_front();
         }
     }
 }
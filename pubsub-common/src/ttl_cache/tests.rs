@@ -212,6 +212,38 @@ fn get_a_cache_entry_by_id() {
     assert_matches!(expired_entry, None);
 }
 
+#[test]
+fn get_a_mutable_cache_entry_by_id() {
+    //// Given
+    let (id1, msg1) = test_message(b"test-message1");
+    let (id2, msg2) = test_message(b"test-message2");
+    let (_, msg3) = test_message(b"test-message3");
+
+    // Set cache TTL to 100ms
+    let capacity = 1024;
+    let ttl = Duration::from_millis(100);
+    let mut cache = Cache::with_capacity_and_ttl(capacity, ttl);
+
+    cache.put(id1.clone(), msg1.clone());
+
+    // Insert messages 120ms apart (so the ones inserted first expire)
+    sleep(ttl + Duration::from_millis(20));
+
+    cache.put(id2.clone(), msg2.clone());
+
+    //// When
+    if let Some(msg) = cache.get_mut(&id2) {
+        *msg = msg3.clone();
+    }
+    let expired_entry = cache.get_mut(&id1);
+
+    //// Then
+    assert_matches!(expired_entry, None);
+    assert_matches!(cache.get(&id2), Some(msg) => {
+        assert_eq!(msg, &msg3, "message 2 should have been updated");
+    });
+}
+
 #[test]
 fn remove_a_cache_entry_by_id() {
     //// Given
@@ -330,3 +362,174 @@ fn insert_an_already_expired_message_should_update_the_timestamp() {
         ]
     );
 }
+
+#[test]
+fn refreshed_entries_survive_the_expired_entries_sweep() {
+    //// Given
+    let (id1, msg1) = test_message(b"test-message1");
+    let (id2, msg2) = test_message(b"test-message2");
+    let (id3, msg3) = test_message(b"test-message3");
+
+    // Set cache TTL to 100ms
+    let capacity = 1024;
+    let ttl = Duration::from_millis(100);
+    let mut cache = Cache::with_capacity_and_ttl(capacity, ttl);
+
+    cache.put(id1.clone(), msg1.clone());
+    cache.put(id2.clone(), msg2);
+
+    // Refresh message 1 half way through its time-to-live
+    sleep(ttl / 2);
+    cache.put(id1.clone(), msg1);
+    cache.put(id3.clone(), msg3);
+
+    // Wait for message 2 (but not message 1) to expire
+    sleep(ttl / 2 + Duration::from_millis(20));
+
+    let len_before = cache.len();
+
+    //// When
+    cache.clear_expired_entries();
+
+    //// Then
+    assert_eq!(len_before, 2, "Only messages 1 and 3 should be valid");
+    assert_eq!(cache.len(), 2);
+
+    let cache_content_ids = cache.iter().map(|(id, _)| id).collect::<Vec<_>>();
+    assert_eq!(cache_content_ids, vec![&id1, &id3]);
+    assert!(!cache.contains_key(&id2), "message 2 should have expired");
+}
+
+/// A helper function to create a test message with a `size` bytes payload.
+fn test_sized_message(seq: u8, size: usize) -> (MessageId, Message) {
+    let mut payload = vec![0; size.max(1)];
+    payload[0] = seq;
+    let message = Message {
+        topic: String::from("test-topic"),
+        payload,
+    };
+    let id = message_id(&message);
+    (id, message)
+}
+
+/// Insert the message into the cache, with its payload length as cost.
+fn put_sized(cache: &mut Cache<MessageId, Message>, (id, msg): (MessageId, Message)) -> MessageId {
+    let cost = msg.payload.len();
+    cache.put_with_cost(id.clone(), msg, cost);
+    id
+}
+
+#[test]
+fn large_messages_evict_proportionally_more_entries_than_small_ones() {
+    //// Given
+    let mut cache =
+        Cache::with_capacity_ttl_and_byte_limit(1024, Duration::from_secs(5), Some(1000));
+
+    // Fill the cache with 10 small messages, 100 bytes each
+    let small_ids = (0..10)
+        .map(|seq| put_sized(&mut cache, test_sized_message(seq, 100)))
+        .collect::<Vec<_>>();
+
+    //// When
+    let large_id = put_sized(&mut cache, test_sized_message(100, 500));
+    let len_after_large = cache.len();
+
+    let small_id = put_sized(&mut cache, test_sized_message(101, 100));
+    let len_after_small = cache.len();
+
+    //// Then
+    // The large message evicted the 5 oldest small messages
+    assert_eq!(len_after_large, 6);
+    for id in &small_ids[..5] {
+        assert!(
+            !cache.contains_key(id),
+            "old small messages should be evicted"
+        );
+    }
+
+    // The small message evicted a single small message
+    assert_eq!(len_after_small, 6);
+    assert!(!cache.contains_key(&small_ids[5]));
+    assert!(cache.contains_key(&large_id));
+    assert!(cache.contains_key(&small_id));
+    assert_eq!(cache.byte_usage(), 1000);
+}
+
+#[test]
+fn newest_message_is_kept_even_if_over_the_byte_limit() {
+    //// Given
+    let mut cache =
+        Cache::with_capacity_ttl_and_byte_limit(1024, Duration::from_secs(5), Some(100));
+    let small_id = put_sized(&mut cache, test_sized_message(0, 50));
+
+    //// When
+    let large_id = put_sized(&mut cache, test_sized_message(1, 500));
+
+    //// Then
+    assert!(!cache.contains_key(&small_id));
+    assert!(cache.contains_key(&large_id));
+    assert_eq!(cache.byte_usage(), 500);
+}
+
+#[test]
+fn byte_usage_is_consistent_across_put_remove_and_expiry() {
+    //// Given
+    let ttl = Duration::from_millis(100);
+    let mut cache = Cache::with_capacity_ttl_and_byte_limit(1024, ttl, Some(10_000));
+
+    //// When
+    let id1 = put_sized(&mut cache, test_sized_message(1, 100));
+    let id2 = put_sized(&mut cache, test_sized_message(2, 200));
+    let usage_after_put = cache.byte_usage();
+
+    // Refreshing an entry does not account its cost twice
+    put_sized(&mut cache, test_sized_message(1, 100));
+    let usage_after_refresh = cache.byte_usage();
+
+    cache.remove(&id1);
+    let usage_after_remove = cache.byte_usage();
+
+    sleep(ttl + Duration::from_millis(20));
+    let usage_before_sweep = cache.byte_usage();
+    put_sized(&mut cache, test_sized_message(3, 300));
+    cache.clear_expired_entries();
+    let usage_after_sweep = cache.byte_usage();
+
+    // Removing an expired entry still releases its cost
+    put_sized(&mut cache, test_sized_message(4, 400));
+    sleep(ttl + Duration::from_millis(20));
+    let removed = cache.remove(&id2);
+
+    //// Then
+    assert_eq!(usage_after_put, 300);
+    assert_eq!(usage_after_refresh, 300);
+    assert_eq!(usage_after_remove, 200);
+    assert_eq!(
+        usage_before_sweep, 200,
+        "expired entries are accounted until swept"
+    );
+    assert_eq!(usage_after_sweep, 300);
+    assert_eq!(removed, None);
+    cache.clear_expired_entries();
+    assert_eq!(cache.byte_usage(), 0);
+}
+
+#[test]
+fn no_byte_limit_preserves_the_entry_capacity_eviction() {
+    for max_bytes in [None, Some(0)] {
+        //// Given
+        let mut cache =
+            Cache::with_capacity_ttl_and_byte_limit(3, Duration::from_secs(5), max_bytes);
+
+        //// When
+        let ids = (0..5)
+            .map(|seq| put_sized(&mut cache, test_sized_message(seq, 10_000)))
+            .collect::<Vec<_>>();
+
+        //// Then
+        assert_eq!(cache.len(), 3, "only the capacity should bound the cache");
+        let cache_content_ids = cache.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>();
+        assert_eq!(cache_content_ids, ids[2..]);
+        assert_eq!(cache.byte_usage(), 30_000);
+    }
+}
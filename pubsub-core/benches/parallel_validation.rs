@@ -0,0 +1,49 @@
+//! Benchmarks of the received frames messages validation, serial and in parallel, for a frame
+//! carrying many messages.
+//!
+//! Run with `cargo bench -p libp2p-pubsub-core --features fuzzing,parallel`. Without the
+//! `parallel` feature, both benchmarks validate the frame messages serially.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_core::fuzzing::{
+    encode_messages_frame, process_raw_frame_with_parallel_validation_threshold,
+};
+use libp2p_pubsub_core::FrameMessage;
+
+/// The number of messages carried by the benchmark frame.
+const MESSAGE_COUNT: usize = 500;
+
+/// Create the benchmark frame: a frame carrying `MESSAGE_COUNT` distinct messages.
+fn new_frame() -> Bytes {
+    let author = PeerId::random();
+    encode_messages_frame((0..MESSAGE_COUNT).map(|i| {
+        FrameMessage::new_with_seq_no_and_from(
+            "/pubsub/2/bench/parallel-validation",
+            vec![0; 256],
+            (i as u64).to_be_bytes(),
+            author,
+        )
+    }))
+}
+
+fn bench_parallel_validation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel_validation");
+    group.throughput(Throughput::Elements(MESSAGE_COUNT as u64));
+
+    let frame = new_frame();
+    for (name, threshold) in [("serial", usize::MAX), ("parallel", 0)] {
+        group.bench_with_input(BenchmarkId::new(name, MESSAGE_COUNT), &frame, |b, frame| {
+            b.iter(|| {
+                process_raw_frame_with_parallel_validation_threshold(frame.clone(), threshold)
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parallel_validation);
+criterion_main!(benches);
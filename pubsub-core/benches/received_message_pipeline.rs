@@ -0,0 +1,73 @@
+//! Benchmarks of the received messages pipeline: from the frame decoding to the messages release
+//! for delivery, through the upstream framing, message id, message cache and ordering services.
+//!
+//! Run with `cargo bench -p libp2p-pubsub-core --features fuzzing`.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_core::fuzzing::{encode_messages_frame, ReceivedMessagePipeline};
+use libp2p_pubsub_core::FrameMessage;
+
+/// The number of messages processed per benchmark iteration.
+const MESSAGE_COUNT: usize = 50_000;
+
+/// The number of distinct topics in the synthetic workload.
+const TOPIC_COUNT: usize = 100;
+
+/// Create the synthetic workload frames: frames carrying `messages_per_frame` distinct messages,
+/// cycling through all the topics.
+fn new_frames(messages_per_frame: usize) -> Vec<Bytes> {
+    let author = PeerId::random();
+    let messages = (0..MESSAGE_COUNT)
+        .map(|i| {
+            FrameMessage::new_with_seq_no_and_from(
+                format!(
+                    "/pubsub/2/bench/received-message-pipeline-{:04}",
+                    i % TOPIC_COUNT
+                ),
+                b"payload".to_vec(),
+                (i as u64).to_be_bytes(),
+                author,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    messages
+        .chunks(messages_per_frame)
+        .map(|chunk| encode_messages_frame(chunk.iter().cloned()))
+        .collect()
+}
+
+fn bench_received_message_pipeline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("received_message_pipeline");
+    group.throughput(Throughput::Elements(MESSAGE_COUNT as u64));
+    group.sample_size(20);
+
+    for messages_per_frame in [1, 100] {
+        let frames = new_frames(messages_per_frame);
+        group.bench_with_input(
+            BenchmarkId::new("messages_per_frame", messages_per_frame),
+            &frames,
+            |b, frames| {
+                // A new pipeline per iteration, so the messages are not deduplicated.
+                b.iter_batched(
+                    ReceivedMessagePipeline::new,
+                    |mut pipeline| {
+                        frames
+                            .iter()
+                            .map(|frame| pipeline.process(frame.clone()))
+                            .sum::<usize>()
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_received_message_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+//! Benchmarks of the received frames decode and routing with and without topic hash interning.
+//!
+//! Run with `cargo bench -p libp2p-pubsub-core --features fuzzing`.
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use libp2p_pubsub_core::fuzzing::{encode_messages_frame, UpstreamFraming};
+use libp2p_pubsub_core::{FrameMessage, TopicHash};
+
+/// The number of distinct topics in the synthetic workload.
+const TOPIC_COUNT: usize = 10_000;
+
+/// The number of frames decoded per benchmark iteration.
+const FRAME_COUNT: usize = 20_000;
+
+/// The topic hash of the i-th synthetic workload topic.
+///
+/// Long topic strings, as hashed topics are, make the topic hash allocations and comparisons
+/// significant.
+fn topic(index: usize) -> String {
+    format!("/pubsub/2/bench/topic-interning/synthetic-workload-topic-{index:08}")
+}
+
+/// Create the synthetic workload frames: single-message frames cycling through all the topics.
+fn new_frames() -> Vec<Bytes> {
+    (0..FRAME_COUNT)
+        .map(|i| {
+            let message = FrameMessage::new(topic(i % TOPIC_COUNT), b"payload".to_vec());
+            encode_messages_frame([message])
+        })
+        .collect()
+}
+
+/// Create the routing table of the synthetic workload: all the topics are subscribed.
+fn new_routes() -> HashMap<TopicHash, usize> {
+    (0..TOPIC_COUNT)
+        .map(|i| (TopicHash::from_raw(topic(i)), i))
+        .collect()
+}
+
+fn bench_decode_and_route(c: &mut Criterion) {
+    let frames = new_frames();
+    let routes = new_routes();
+
+    let mut group = c.benchmark_group("topic_interning/decode_and_route");
+    group.throughput(Throughput::Elements(FRAME_COUNT as u64));
+
+    // A capacity of zero disables the interning, i.e., the baseline.
+    for max_interned_topics in [0, 2 * TOPIC_COUNT] {
+        group.bench_with_input(
+            BenchmarkId::new("max_interned_topics", max_interned_topics),
+            &max_interned_topics,
+            |b, &max_interned_topics| {
+                let mut framing = UpstreamFraming::new(max_interned_topics);
+                b.iter(|| {
+                    let mut routed = 0;
+                    for frame in &frames {
+                        for topic in framing.received_message_topics(frame.clone()) {
+                            routed += routes.get(&topic).copied().unwrap_or_default();
+                        }
+                    }
+                    routed
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode_and_route);
+criterion_main!(benches);
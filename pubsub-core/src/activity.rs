@@ -0,0 +1,282 @@
+//! Periodic per-topic activity summaries (see
+//! [`Config::activity_summaries`](crate::Config::activity_summaries)).
+//!
+//! Unlike the [traffic accounting](crate::Behaviour::topic_traffic) cumulative counters, the
+//! activity counters are reset at the end of every summary window, so the summaries report the
+//! topic rates directly.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::topic::TopicHash;
+
+/// The message activity of a locally subscribed topic over a summary window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct TopicActivity {
+    /// The number of messages received from the remote peers, including duplicates.
+    pub received: u64,
+    /// The number of received messages delivered to the local node application.
+    pub delivered: u64,
+    /// The number of messages sent to the remote peers. A message sent to `n` peers counts `n`
+    /// times.
+    pub forwarded: u64,
+    /// The number of duplicate messages received from the remote peers.
+    pub duplicates: u64,
+    /// The bytes of the messages received from the remote peers, including duplicates.
+    pub bytes: u64,
+}
+
+impl TopicActivity {
+    /// Whether no activity was accounted.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Add the other activity counters to these counters.
+    fn merge(&mut self, other: &TopicActivity) {
+        self.received = self.received.saturating_add(other.received);
+        self.delivered = self.delivered.saturating_add(other.delivered);
+        self.forwarded = self.forwarded.saturating_add(other.forwarded);
+        self.duplicates = self.duplicates.saturating_add(other.duplicates);
+        self.bytes = self.bytes.saturating_add(other.bytes);
+    }
+}
+
+/// A topic activity summary, covering a single summary window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ActivitySummary {
+    /// The summary window duration.
+    pub(crate) window: Duration,
+    /// The activity of the most active topics, most active first.
+    pub(crate) per_topic: Vec<(TopicHash, TopicActivity)>,
+    /// The rolled up activity of the topics beyond the summary entries cap, if any.
+    pub(crate) others: Option<TopicActivity>,
+}
+
+/// Keeps the per-topic activity counters of the current summary window.
+///
+/// Only the locally subscribed topics are accounted.
+#[derive(Debug)]
+pub(crate) struct ActivityAccounting {
+    /// The maximum number of topics reported individually in a summary.
+    max_topics: usize,
+    /// The current window start time.
+    window_start: Instant,
+    /// The current window counters, by locally subscribed topic.
+    topics: HashMap<TopicHash, TopicActivity>,
+}
+
+impl ActivityAccounting {
+    /// Create a new activity accounting, starting the first window at `now`.
+    pub(crate) fn new(max_topics: usize, now: Instant) -> Self {
+        Self {
+            max_topics,
+            window_start: now,
+            topics: Default::default(),
+        }
+    }
+
+    /// Start accounting the topic activity.
+    pub(crate) fn track_topic(&mut self, topic: TopicHash) {
+        self.topics.entry(topic).or_default();
+    }
+
+    /// Stop accounting the topic activity, dropping its current window counters.
+    pub(crate) fn untrack_topic(&mut self, topic: &TopicHash) {
+        self.topics.remove(topic);
+    }
+
+    /// Account a message received from a remote peer.
+    pub(crate) fn record_received(&mut self, topic: &TopicHash, size: usize) {
+        if let Some(activity) = self.topics.get_mut(topic) {
+            activity.received = activity.received.saturating_add(1);
+            activity.bytes = activity.bytes.saturating_add(size as u64);
+        }
+    }
+
+    /// Account a received message delivered to the local node application.
+    pub(crate) fn record_delivered(&mut self, topic: &TopicHash) {
+        if let Some(activity) = self.topics.get_mut(topic) {
+            activity.delivered = activity.delivered.saturating_add(1);
+        }
+    }
+
+    /// Account a message sent to a remote peer.
+    pub(crate) fn record_forwarded(&mut self, topic: &TopicHash) {
+        if let Some(activity) = self.topics.get_mut(topic) {
+            activity.forwarded = activity.forwarded.saturating_add(1);
+        }
+    }
+
+    /// Account a duplicate message received from a remote peer.
+    pub(crate) fn record_duplicate(&mut self, topic: &TopicHash) {
+        if let Some(activity) = self.topics.get_mut(topic) {
+            activity.duplicates = activity.duplicates.saturating_add(1);
+        }
+    }
+
+    /// Close the current window at `now`, returning its summary, and reset the counters.
+    ///
+    /// The topics without activity are omitted. If more than the maximum number of topics were
+    /// active, the least active ones, by received messages, are rolled up into the summary
+    /// `others` entry.
+    pub(crate) fn take_summary(&mut self, now: Instant) -> ActivitySummary {
+        let window = now.saturating_duration_since(self.window_start);
+        self.window_start = now;
+
+        let mut per_topic = self
+            .topics
+            .iter_mut()
+            .map(|(topic, activity)| (topic.clone(), std::mem::take(activity)))
+            .filter(|(_, activity)| !activity.is_empty())
+            .collect::<Vec<_>>();
+        per_topic.sort_by(|(topic_a, a), (topic_b, b)| {
+            (b.received, b.forwarded)
+                .cmp(&(a.received, a.forwarded))
+                .then_with(|| topic_a.cmp(topic_b))
+        });
+
+        let others = (per_topic.len() > self.max_topics).then(|| {
+            per_topic.drain(self.max_topics..).fold(
+                TopicActivity::default(),
+                |mut others, (_, activity)| {
+                    others.merge(&activity);
+                    others
+                },
+            )
+        });
+
+        ActivitySummary {
+            window,
+            per_topic,
+            others,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_topic(name: &str) -> TopicHash {
+        TopicHash::from_raw(format!("/test/{name}"))
+    }
+
+    #[test]
+    fn summary_covers_the_window_and_resets_the_counters() {
+        //// Given
+        let topic = new_test_topic("topic");
+        let start = Instant::now();
+
+        let mut accounting = ActivityAccounting::new(10, start);
+        accounting.track_topic(topic.clone());
+
+        accounting.record_received(&topic, 100);
+        accounting.record_received(&topic, 100);
+        accounting.record_duplicate(&topic);
+        accounting.record_delivered(&topic);
+        accounting.record_forwarded(&topic);
+
+        //// When
+        let first = accounting.take_summary(start + Duration::from_secs(5));
+        accounting.record_received(&topic, 10);
+        let second = accounting.take_summary(start + Duration::from_secs(15));
+
+        //// Then
+        assert_eq!(first.window, Duration::from_secs(5));
+        assert_eq!(
+            first.per_topic,
+            [(
+                topic.clone(),
+                TopicActivity {
+                    received: 2,
+                    delivered: 1,
+                    forwarded: 1,
+                    duplicates: 1,
+                    bytes: 200,
+                }
+            )]
+        );
+        assert_eq!(first.others, None);
+
+        assert_eq!(second.window, Duration::from_secs(10));
+        assert_eq!(
+            second.per_topic,
+            [(
+                topic,
+                TopicActivity {
+                    received: 1,
+                    bytes: 10,
+                    ..Default::default()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn idle_and_untracked_topics_are_omitted() {
+        //// Given
+        let active = new_test_topic("active");
+        let idle = new_test_topic("idle");
+        let untracked = new_test_topic("untracked");
+        let start = Instant::now();
+
+        let mut accounting = ActivityAccounting::new(10, start);
+        accounting.track_topic(active.clone());
+        accounting.track_topic(idle);
+
+        //// When
+        accounting.record_received(&active, 1);
+        accounting.record_received(&untracked, 1);
+        let summary = accounting.take_summary(start + Duration::from_secs(1));
+        let idle_summary = accounting.take_summary(start + Duration::from_secs(2));
+
+        //// Then
+        assert_eq!(summary.per_topic.len(), 1);
+        assert_eq!(summary.per_topic[0].0, active);
+        assert!(idle_summary.per_topic.is_empty());
+        assert_eq!(idle_summary.others, None);
+    }
+
+    #[test]
+    fn least_active_topics_beyond_the_cap_are_rolled_up() {
+        //// Given
+        let topics = (0..4)
+            .map(|idx| new_test_topic(&format!("topic-{idx}")))
+            .collect::<Vec<_>>();
+        let start = Instant::now();
+
+        let mut accounting = ActivityAccounting::new(2, start);
+        for topic in &topics {
+            accounting.track_topic(topic.clone());
+        }
+
+        //// When
+        // The topic `i` receives `i + 1` messages.
+        for (idx, topic) in topics.iter().enumerate() {
+            for _ in 0..=idx {
+                accounting.record_received(topic, 10);
+            }
+        }
+        let summary = accounting.take_summary(start + Duration::from_secs(1));
+
+        //// Then
+        let reported = summary
+            .per_topic
+            .iter()
+            .map(|(topic, _)| topic.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(reported, [topics[3].clone(), topics[2].clone()]);
+        assert_eq!(
+            summary.others,
+            Some(TopicActivity {
+                received: 3,
+                bytes: 30,
+                ..Default::default()
+            })
+        );
+    }
+}
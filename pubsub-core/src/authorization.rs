@@ -0,0 +1,275 @@
+//! Topic authorization (see [`Config::topic_authorizer`](crate::Config::topic_authorizer)).
+//!
+//! In a permissioned network, only the approved peers may take part in some topics. The topic
+//! authorizer is consulted when a remote peer announces a topic subscription, and when a message
+//! is received, for its propagation source and its claimed author. The announcements and messages
+//! from the peers not authorized are dropped.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use libp2p::identity::PeerId;
+
+use crate::topic::TopicHash;
+
+/// The action a peer takes part in a topic with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TopicAction {
+    /// Subscribe to the topic.
+    Subscribe,
+    /// Publish, or propagate, messages on the topic.
+    Publish,
+}
+
+// NOTE: Use `trait_set` crate as `trait_alias` is not yet stable.
+//       https://github.com/rust-lang/rust/issues/41517
+trait_set::trait_set! {
+    /// The topic authorizer function type.
+    ///
+    /// Returns `true` if the peer is authorized to take the action on the topic. The authorizer is
+    /// called on the hot path, once per subscription announcement and up to twice per received
+    /// message, so it must be cheap, e.g., a lookup in a prebuilt [`StaticAllowlist`].
+    pub trait TopicAuthorizer = Fn(&PeerId, &TopicHash, TopicAction) -> bool;
+}
+
+/// A shared topic authorizer, as set in the configuration.
+#[derive(Clone)]
+pub(crate) struct SharedTopicAuthorizer(pub(crate) Rc<dyn TopicAuthorizer<Output = bool>>);
+
+impl fmt::Debug for SharedTopicAuthorizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TopicAuthorizer(..)")
+    }
+}
+
+/// A static topic allowlist: the peers authorized to subscribe, or publish, to each restricted
+/// topic.
+///
+/// The topics not restricted for an action are open to all peers for this action, e.g., a topic
+/// only restricted for publishing can be subscribed to by any peer.
+///
+/// ```
+/// # use libp2p::identity::PeerId;
+/// # use libp2p_pubsub_core::{ConfigBuilder, IdentTopic, StaticAllowlist, TopicAction};
+/// let topic = IdentTopic::new("/private/announcements");
+/// let admin = PeerId::random();
+///
+/// let mut allowlist = StaticAllowlist::new();
+/// allowlist.allow(topic.hash(), TopicAction::Publish, [admin]);
+///
+/// let config = ConfigBuilder::default()
+///     .topic_authorizer(Some(allowlist.into_authorizer()))
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StaticAllowlist {
+    /// The authorized peers, by restricted topic.
+    topics: HashMap<TopicHash, TopicAllowlist>,
+}
+
+/// The authorized peers of a restricted topic, by action. If `None`, the action is not restricted.
+#[derive(Debug, Clone, Default)]
+struct TopicAllowlist {
+    /// The peers authorized to subscribe to the topic.
+    subscribers: Option<HashSet<PeerId>>,
+    /// The peers authorized to publish on the topic.
+    publishers: Option<HashSet<PeerId>>,
+}
+
+impl StaticAllowlist {
+    /// Create a new, empty, allowlist. No topic is restricted.
+    #[must_use]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Restrict the action on the topic to the allowlisted peers, and add the given peers to them.
+    pub fn allow(
+        &mut self,
+        topic: impl Into<TopicHash>,
+        action: TopicAction,
+        peers: impl IntoIterator<Item = PeerId>,
+    ) -> &mut Self {
+        let allowlist = self.topics.entry(topic.into()).or_default();
+        let allowed = match action {
+            TopicAction::Subscribe => &mut allowlist.subscribers,
+            TopicAction::Publish => &mut allowlist.publishers,
+        };
+        allowed.get_or_insert_with(Default::default).extend(peers);
+        self
+    }
+
+    /// Returns `true` if the peer is authorized to take the action on the topic, i.e., the action
+    /// on the topic is not restricted, or the peer is allowlisted.
+    #[must_use]
+    pub fn is_allowed(&self, peer: &PeerId, topic: &TopicHash, action: TopicAction) -> bool {
+        let Some(allowlist) = self.topics.get(topic) else {
+            return true;
+        };
+
+        let allowed = match action {
+            TopicAction::Subscribe => &allowlist.subscribers,
+            TopicAction::Publish => &allowlist.publishers,
+        };
+        allowed.as_ref().map_or(true, |peers| peers.contains(peer))
+    }
+
+    /// Convert the allowlist into a topic authorizer (see
+    /// [`ConfigBuilder::topic_authorizer`](crate::ConfigBuilder::topic_authorizer)).
+    #[must_use]
+    pub fn into_authorizer(self) -> Rc<dyn TopicAuthorizer<Output = bool>> {
+        Rc::new(
+            move |peer: &PeerId, topic: &TopicHash, action: TopicAction| {
+                self.is_allowed(peer, topic, action)
+            },
+        )
+    }
+}
+
+/// Accounts the unauthorized subscriptions and messages, and limits the unauthorized messages
+/// notifications to one per interval, peer and topic.
+#[derive(Debug)]
+pub(crate) struct UnauthorizedTracker {
+    /// The minimum time between two notifications for the same peer and topic.
+    interval: Duration,
+    /// The time of the last notification, by peer and topic.
+    notified: HashMap<PeerId, HashMap<TopicHash, Instant>>,
+    /// The number of unauthorized subscription announcements.
+    subscriptions: u64,
+    /// The number of unauthorized messages.
+    messages: u64,
+}
+
+impl UnauthorizedTracker {
+    /// Create a new tracker with the given notifications interval.
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            notified: Default::default(),
+            subscriptions: 0,
+            messages: 0,
+        }
+    }
+
+    /// The number of unauthorized subscription announcements.
+    pub(crate) fn subscriptions(&self) -> u64 {
+        self.subscriptions
+    }
+
+    /// The number of unauthorized messages.
+    pub(crate) fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// Record an unauthorized subscription announcement.
+    pub(crate) fn record_subscription(&mut self) {
+        self.subscriptions += 1;
+    }
+
+    /// Record an unauthorized message from the peer on the topic.
+    ///
+    /// Returns `true` if the message must be notified, i.e., no unauthorized message from the peer
+    /// on the topic was notified during the last interval.
+    pub(crate) fn record_message(&mut self, peer: PeerId, topic: &TopicHash, now: Instant) -> bool {
+        self.messages += 1;
+
+        let notified = self.notified.entry(peer).or_default();
+        match notified.get_mut(topic) {
+            Some(last) if now.saturating_duration_since(*last) < self.interval => false,
+            Some(last) => {
+                *last = now;
+                true
+            }
+            None => {
+                notified.insert(topic.clone(), now);
+                true
+            }
+        }
+    }
+
+    /// Forget the notifications of the disconnected peer.
+    pub(crate) fn peer_disconnected(&mut self, peer: &PeerId) {
+        self.notified.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unrestricted_topics_are_open_to_all_peers() {
+        //// Given
+        let mut allowlist = StaticAllowlist::new();
+        let restricted = TopicHash::from_raw("/test/restricted");
+        let open = TopicHash::from_raw("/test/open");
+        let (member, stranger) = (PeerId::random(), PeerId::random());
+
+        allowlist.allow(restricted.clone(), TopicAction::Publish, [member]);
+
+        //// Then
+        assert!(allowlist.is_allowed(&member, &restricted, TopicAction::Publish));
+        assert!(!allowlist.is_allowed(&stranger, &restricted, TopicAction::Publish));
+
+        // The topic is only restricted for publishing.
+        assert!(allowlist.is_allowed(&stranger, &restricted, TopicAction::Subscribe));
+        assert!(allowlist.is_allowed(&stranger, &open, TopicAction::Publish));
+        assert!(allowlist.is_allowed(&stranger, &open, TopicAction::Subscribe));
+    }
+
+    #[test]
+    fn allowlist_authorizer_checks_the_allowlist() {
+        //// Given
+        let topic = TopicHash::from_raw("/test/restricted");
+        let (member, stranger) = (PeerId::random(), PeerId::random());
+
+        let mut allowlist = StaticAllowlist::new();
+        allowlist
+            .allow(topic.clone(), TopicAction::Subscribe, [member])
+            .allow(topic.clone(), TopicAction::Subscribe, [])
+            .allow(topic.clone(), TopicAction::Publish, []);
+
+        //// When
+        let authorizer = allowlist.into_authorizer();
+
+        //// Then
+        assert!(authorizer(&member, &topic, TopicAction::Subscribe));
+        assert!(!authorizer(&stranger, &topic, TopicAction::Subscribe));
+        assert!(
+            !authorizer(&member, &topic, TopicAction::Publish),
+            "No peer should be allowed to publish"
+        );
+    }
+
+    #[test]
+    fn unauthorized_messages_are_notified_once_per_interval() {
+        //// Given
+        let mut tracker = UnauthorizedTracker::new(Duration::from_secs(60));
+        let (topic_a, topic_b) = (
+            TopicHash::from_raw("/test/topic-a"),
+            TopicHash::from_raw("/test/topic-b"),
+        );
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        //// When
+        let first = tracker.record_message(peer, &topic_a, now);
+        let within_interval = tracker.record_message(peer, &topic_a, now + Duration::from_secs(30));
+        let other_topic = tracker.record_message(peer, &topic_b, now + Duration::from_secs(30));
+        let after_interval = tracker.record_message(peer, &topic_a, now + Duration::from_secs(60));
+
+        //// Then
+        assert!(first);
+        assert!(!within_interval);
+        assert!(other_topic);
+        assert!(after_interval);
+        assert_eq!(
+            tracker.messages(),
+            4,
+            "Every unauthorized message should be counted"
+        );
+    }
+}
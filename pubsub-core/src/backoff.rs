@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use libp2p::identity::PeerId;
+
+use crate::framing::PruneControlMessage;
+use crate::topic::TopicHash;
+
+/// The default backoff applied when a `Prune` control message carries no backoff.
+pub const DEFAULT_PRUNE_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Tracks the per-peer, per-topic backoffs requested by the remote peers' `Prune` control
+/// messages.
+///
+/// While a peer is backed off on a topic, the protocol router should neither graft the peer on the
+/// topic nor include it in the topic's mesh/forward decisions.
+///
+/// The expired backoffs are not removed on lookup. The owner of the tracker is expected to call
+/// [`heartbeat`](BackoffTracker::heartbeat) periodically to remove them.
+#[derive(Debug, Clone)]
+pub struct BackoffTracker {
+    /// The backoff applied when a `Prune` control message carries no backoff.
+    default_backoff: Duration,
+
+    /// The backoff expiration instant of each (peer, topic) pair.
+    backoffs: HashMap<(PeerId, TopicHash), Instant>,
+}
+
+impl Default for BackoffTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_PRUNE_BACKOFF)
+    }
+}
+
+impl BackoffTracker {
+    /// Create a new backoff tracker with the given default backoff.
+    #[must_use]
+    pub fn new(default_backoff: Duration) -> Self {
+        Self {
+            default_backoff,
+            backoffs: Default::default(),
+        }
+    }
+
+    /// Record the backoff requested by a `Prune` control message received from the `src` peer.
+    ///
+    /// If the message carries no backoff, the default backoff is applied.
+    pub fn on_prune(&mut self, src: PeerId, prune: &PruneControlMessage) {
+        let backoff = prune
+            .backoff
+            .map(Duration::from_secs)
+            .unwrap_or(self.default_backoff);
+        self.backoff(src, prune.topic_hash.clone(), backoff);
+    }
+
+    /// Back off the peer on the topic for the given duration.
+    ///
+    /// An existing backoff is only extended, never shortened.
+    pub fn backoff(&mut self, peer: PeerId, topic: TopicHash, backoff: Duration) {
+        let until = Instant::now() + backoff;
+        self.backoffs
+            .entry((peer, topic))
+            .and_modify(|current| *current = (*current).max(until))
+            .or_insert(until);
+    }
+
+    /// Check if the peer is backed off on the topic.
+    #[must_use]
+    pub fn is_backed_off(&self, peer: &PeerId, topic: &TopicHash) -> bool {
+        self.backoffs
+            .get(&(*peer, topic.clone()))
+            .map_or(false, |until| *until > Instant::now())
+    }
+
+    /// Remove all the backoffs of the peer, e.g., on disconnection.
+    pub fn remove_peer(&mut self, peer: &PeerId) {
+        self.backoffs.retain(|(p, _), _| p != peer);
+    }
+
+    /// Remove the expired backoffs.
+    pub fn heartbeat(&mut self) {
+        let now = Instant::now();
+        self.backoffs.retain(|_, until| *until > now);
+    }
+
+    /// The number of tracked backoffs, including the expired ones not yet removed.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.backoffs.len()
+    }
+
+    /// Returns `true` if no backoffs are tracked.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.backoffs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_prune(topic: TopicHash, backoff: Option<u64>) -> PruneControlMessage {
+        PruneControlMessage {
+            topic_hash: topic,
+            peers: Vec::new(),
+            backoff,
+        }
+    }
+
+    #[test]
+    fn peer_is_backed_off_until_expiry() {
+        //// Given
+        let peer = PeerId::random();
+        let topic = TopicHash::from_raw("/test/topic");
+        let mut tracker = BackoffTracker::default();
+
+        //// When
+        tracker.backoff(peer, topic.clone(), Duration::from_millis(20));
+        let backed_off = tracker.is_backed_off(&peer, &topic);
+
+        std::thread::sleep(Duration::from_millis(30));
+        let backed_off_after_expiry = tracker.is_backed_off(&peer, &topic);
+
+        //// Then
+        assert!(backed_off, "Peer should be backed off");
+        assert!(
+            !backed_off_after_expiry,
+            "Peer should not be backed off after expiry"
+        );
+    }
+
+    #[test]
+    fn prune_without_backoff_applies_default_backoff() {
+        //// Given
+        let peer = PeerId::random();
+        let topic_a = TopicHash::from_raw("/test/topic-a");
+        let topic_b = TopicHash::from_raw("/test/topic-b");
+        let mut tracker = BackoffTracker::new(Duration::from_millis(20));
+
+        //// When
+        tracker.on_prune(peer, &new_test_prune(topic_a.clone(), None));
+        tracker.on_prune(peer, &new_test_prune(topic_b.clone(), Some(60)));
+
+        std::thread::sleep(Duration::from_millis(30));
+
+        //// Then
+        assert!(
+            !tracker.is_backed_off(&peer, &topic_a),
+            "Default backoff should have expired"
+        );
+        assert!(
+            tracker.is_backed_off(&peer, &topic_b),
+            "Prune backoff should not have expired"
+        );
+    }
+
+    #[test]
+    fn heartbeat_removes_expired_backoffs() {
+        //// Given
+        let peer_a = PeerId::random();
+        let peer_b = PeerId::random();
+        let topic = TopicHash::from_raw("/test/topic");
+        let mut tracker = BackoffTracker::default();
+
+        tracker.backoff(peer_a, topic.clone(), Duration::from_millis(10));
+        tracker.backoff(peer_b, topic.clone(), Duration::from_secs(60));
+
+        //// When
+        std::thread::sleep(Duration::from_millis(20));
+        let len_before = tracker.len();
+        tracker.heartbeat();
+
+        //// Then
+        assert_eq!(len_before, 2, "Expired backoffs are kept until heartbeat");
+        assert_eq!(tracker.len(), 1, "Expired backoff should be removed");
+        assert!(tracker.is_backed_off(&peer_b, &topic));
+    }
+}
@@ -1,52 +1,142 @@
-use std::collections::{BTreeSet, VecDeque};
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::io;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::task::{Context, Poll};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 use bytes::Bytes;
+use futures::StreamExt;
 use libp2p::core::Endpoint;
 use libp2p::identity::PeerId;
 use libp2p::swarm::behaviour::ConnectionEstablished;
+use libp2p::swarm::dial_opts::DialOpts;
 use libp2p::swarm::{
-    AddressChange, ConnectionClosed, ConnectionDenied, ConnectionHandler, ConnectionId,
-    DialFailure, FromSwarm, ListenFailure, NetworkBehaviour, NotifyHandler, PollParameters,
-    THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    AddressChange, CloseConnection, ConnectionClosed, ConnectionDenied, ConnectionHandler,
+    ConnectionId, DialFailure, FromSwarm, ListenFailure, NetworkBehaviour, NotifyHandler,
+    PollParameters, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 use libp2p::Multiaddr;
+use prost::Message as _;
 
+use libp2p_pubsub_common::heartbeat::Heartbeat;
 use libp2p_pubsub_common::service::{BufferedContext, ServiceContext};
-
+use libp2p_pubsub_proto::pubsub::FrameProto;
+
+use crate::activity::ActivityAccounting;
+use crate::authorization::{TopicAction, UnauthorizedTracker};
+use crate::builder::BehaviourBuilder;
+#[cfg(feature = "chaos")]
+use crate::chaos::{ChaosPolicy, ChaosStats};
+use crate::chunk::{split_into_chunks, ChunkHeader};
 use crate::config::Config;
+use crate::confirmation::SubscriptionConfirmations;
 use crate::conn_handler::{Command as HandlerCommand, Event as HandlerEvent, Handler};
-use crate::event::Event;
-use crate::framing::{Message as FrameMessage, SubscriptionAction};
-use crate::message::Message;
+use crate::consumer::{ConsumerHandle, ConsumerRegistry, ConsumerTag};
+use crate::dead_letter::{DeadLetter, DeadLetterBuffer, DeadLetterStage};
+use crate::delivery::{DeliveryMode, RoundRobinRotations};
+use crate::dispatch::dispatch;
+use crate::drops::DropStats;
+use crate::egress::{self, BytesPerSecond, EgressPacer, EgressStats};
+use crate::event::{Event, MisbehaviourReason};
+use crate::fanout::ForwardFanout;
+use crate::flush::{FlushId, PendingFlushes};
+use crate::forward::ForwardError;
+use crate::frame_limit::PeerFrameLimits;
+use crate::framing::{ControlMessage, Frame, Message as FrameMessage, SubscriptionAction};
+use crate::gate::{ConnectionGateContext, ConnectionGateDenied, GateDecision};
+use crate::identity::Identity;
+use crate::iwant::IWantQuotas;
+use crate::lifecycle::{MessageContext, MessageStage};
+use crate::message::{Message, Priority};
+use crate::message_id::{MessageId, MessageIdFn};
+use crate::pending::PendingCounts;
+use crate::preflight::{PreflightCode, PreflightWarning};
 use crate::protocol::{
-    Protocol, ProtocolRouterConnectionEvent, ProtocolRouterControlEvent, ProtocolRouterInEvent,
-    ProtocolRouterMessageEvent, ProtocolRouterOutEvent, ProtocolRouterSubscriptionEvent,
+    Protocol, ProtocolRouterConnectionEvent, ProtocolRouterInEvent, ProtocolRouterMessageEvent,
+    ProtocolRouterOutEvent, ProtocolRouterSubscriptionEvent,
 };
+use crate::provenance::{Provenance, ProvenanceStats, ProvenanceTracking};
+use crate::publish::{PublishError, PublishReceipt};
+use crate::refresh::SubscriptionRefreshes;
+use crate::reqres::{self, PendingRequests, RequestEnvelope, RequestId, ResponseEnvelope};
+use crate::sampling::{FrameDirection, FrameSampler, SampledFrame};
+use crate::scheduler::ForwardScheduler;
+use crate::send_health::{SendHealthChange, SendHealthTracker};
 use crate::services::connections::{
     ConnectionsInEvent, ConnectionsOutEvent, ConnectionsService, ConnectionsSwarmEvent,
 };
+use crate::services::dialer::{DialerInEvent, DialerOutEvent, DialerService};
 use crate::services::framing::{
-    FramingDownstreamInEvent, FramingDownstreamOutEvent, FramingInEvent, FramingOutEvent,
-    FramingServiceContext, FramingUpstreamInEvent, FramingUpstreamOutEvent,
+    FrameFailureClass, FramingDownstreamInEvent, FramingDownstreamOutEvent, FramingInEvent,
+    FramingOutEvent, FramingServiceContext, FramingStatsParams, FramingUpstreamInEvent,
+    FramingUpstreamOutEvent, NegativeCacheParams, PeerFramingStats, UpstreamFramingParams,
 };
 use crate::services::message_cache::{
-    MessageCacheInEvent, MessageCacheMessageEvent, MessageCacheService,
+    MessageCacheInEvent, MessageCacheMessageEvent, MessageCacheOutEvent, MessageCacheService,
 };
 use crate::services::message_id::{
     MessageIdInEvent, MessageIdMessageEvent, MessageIdOutEvent, MessageIdService,
     MessageIdSubscriptionEvent,
 };
+use crate::services::ordering::{OrderingInEvent, OrderingOutEvent, OrderingService};
+use crate::services::reassembly::{ReassemblyInEvent, ReassemblyOutEvent, ReassemblyService};
+use crate::services::subscription_announce::{
+    SubscriptionAnnounceInEvent, SubscriptionAnnounceOutEvent, SubscriptionAnnounceService,
+};
+use crate::services::subscription_sync::{
+    SubscriptionSyncInEvent, SubscriptionSyncOutEvent, SubscriptionSyncService,
+};
 use crate::services::subscriptions::{
-    SubscriptionsInEvent, SubscriptionsOutEvent, SubscriptionsPeerConnectionEvent,
-    SubscriptionsService,
+    ChurnParams, ChurnStats, SubscriptionsDebounceService, SubscriptionsInEvent,
+    SubscriptionsOutEvent, SubscriptionsPeerConnectionEvent, SubscriptionsService,
 };
-use crate::subscription::Subscription;
-use crate::topic::{Hasher, Topic, TopicHash};
-
+use crate::silence::SilenceWatchdog;
+use crate::subscription::{
+    PeerSubscriptionInfo, Subscription, SubscriptionError, SubscriptionInfo,
+    TopicHashMismatchNotifications,
+};
+use crate::topic::{Hasher, Sha256Hash, Topic, TopicHash, TopicValidationError};
+use crate::topology::{PeerTopology, TopologySnapshot};
+use crate::traffic::{TopicTraffic, TrafficAccounting};
+use crate::ttl;
+use crate::warmup::ForwardWarmup;
+
+/// A message forward held by the forwarding scheduler: the destination peer connection, if any,
+/// the message, its lifecycle context, if any, and its local scheduling priority.
+type ScheduledForward = (
+    Option<ConnectionId>,
+    Rc<FrameMessage>,
+    Option<MessageContext>,
+    Priority,
+);
+
+/// The pubsub network behaviour, routing the messages with the `P` protocol.
+///
+/// # Composition
+///
+/// The behaviour can be composed with other behaviours with the libp2p
+/// `#[derive(NetworkBehaviour)]` macro (libp2p `0.52`). The derive requires:
+///
+///  - The [`ToSwarm`](NetworkBehaviour::ToSwarm) event, [`Event`], to implement `Debug`, as the
+///    generated event enum derives it.
+///  - The [`ConnectionHandler`](NetworkBehaviour::ConnectionHandler), its `Error` type
+///    (`Infallible`) and the events exchanged with the behaviour to be `Send + 'static`, as they
+///    are moved to the connection tasks.
+///
+/// The behaviour itself is not `Send`: the swarm must be polled from the task, or thread, it was
+/// created on.
 pub struct Behaviour<P: Protocol> {
+    /// The local node peer ID.
+    local_peer_id: PeerId,
+
+    /// The local node identity, stamped as author of the published messages.
+    identity: Identity,
+
+    /// The sequence number stamped in the next authored message published without one.
+    next_sequence_number: u64,
+
     /// The behaviour's configuration.
     config: Config,
 
@@ -56,23 +146,172 @@ pub struct Behaviour<P: Protocol> {
     /// Peer subscriptions tracking and management service.
     subscriptions_service: BufferedContext<SubscriptionsService>,
 
+    /// Local subscription updates debounce service.
+    subscriptions_debounce_service: BufferedContext<SubscriptionsDebounceService>,
+
+    /// Failed subscription syncs retry service.
+    subscription_sync_service: BufferedContext<SubscriptionSyncService>,
+
+    /// Subscriptions announcement pacing service.
+    subscription_announce_service: BufferedContext<SubscriptionAnnounceService>,
+
+    /// Known peers dialing service.
+    dialer_service: BufferedContext<DialerService>,
+
     /// Message ID service.
     message_id_service: BufferedContext<MessageIdService>,
 
     /// Message cache and deduplication service.
     message_cache_service: BufferedContext<MessageCacheService>,
 
+    /// Chunked transfers reassembly service.
+    reassembly_service: BufferedContext<ReassemblyService>,
+
+    /// Ordered delivery service.
+    ordering_service: BufferedContext<OrderingService>,
+
     /// The pubsub protocol router service.
     protocol_router_service: BufferedContext<P::RouterService>,
 
     /// The frame encoder and decoder service.
     framing_service: FramingServiceContext,
 
+    /// The protocol router's heartbeat.
+    heartbeat: Heartbeat,
+
+    /// The per-topic message traffic counters.
+    traffic: TrafficAccounting,
+
+    /// The per-topic delivered messages provenance statistics.
+    provenance: ProvenanceTracking,
+
+    /// The per-topic activity counters of the current summary window.
+    activity: ActivityAccounting,
+
+    /// The topic activity summaries timer, if enabled.
+    activity_summary_timer: Option<Heartbeat>,
+
+    /// The last time the protocol router state was synced with the behaviour.
+    last_router_sync: Instant,
+
+    /// The subscribed topics silence watchdog, if enabled.
+    silence_watchdog: Option<SilenceWatchdog>,
+
+    /// The IWANT requests serving quotas.
+    iwant_quotas: IWantQuotas,
+
+    /// The local subscriptions the connected peers confirmed knowing about.
+    subscription_confirmations: SubscriptionConfirmations,
+
+    /// The peers flagged as duplicate flooders and the instant their cooldown expires.
+    ///
+    /// The frames received from these peers are dropped until the cooldown expires.
+    flood_cooldowns: HashMap<PeerId, Instant>,
+
+    /// The number of frames dropped because their destination peer was no longer connected.
+    dropped_frames_disconnected: u64,
+
+    /// The unauthorized subscriptions and messages counters, and the unauthorized messages
+    /// notifications, limited per peer and topic.
+    unauthorized: UnauthorizedTracker,
+
+    /// The topic hash mismatch notifications, limited per received topic.
+    topic_hash_mismatch_notifications: TopicHashMismatchNotifications,
+
+    /// The recorded dead letters, if enabled (see [`Config::dead_letter`]).
+    dead_letters: DeadLetterBuffer,
+
+    /// The raw frames sampler, if enabled (see [`Config::frame_sampling`]).
+    frame_sampler: Option<FrameSampler>,
+
+    /// The egress pacer, if enabled (see [`Config::egress_budget`]).
+    egress_pacer: Option<EgressPacer<ToSwarm<Event, HandlerCommand>>>,
+
+    /// The egress pacer refill timer, ticking several times per heartbeat interval, if enabled.
+    egress_timer: Option<Heartbeat>,
+
+    /// The peers whose disconnection was requested by the protocol router, until disconnected.
+    disconnecting_peers: HashSet<PeerId>,
+
+    /// The received messages forwarding warm-up (see [`Config::forward_warmup`]).
+    forward_warmup: ForwardWarmup,
+
+    /// The message forwards pending to be released to the framing service, by destination peer
+    /// (see [`Config::max_forwards_per_poll`]).
+    forward_scheduler: ForwardScheduler<ScheduledForward>,
+
+    /// The messages handed to the protocol router and their lifecycle context, by message
+    /// address, until the router processed them.
+    routed_messages: HashMap<*const FrameMessage, (Rc<FrameMessage>, MessageContext)>,
+
+    /// The latency between the last delivered message decoding and its delivery.
+    last_delivery_latency: Option<Duration>,
+
+    /// The latency between the last forwarded message decoding, or publication, and its hand-off
+    /// to the framing service.
+    last_forward_latency: Option<Duration>,
+
+    /// The requests sent by the local node, awaiting a response (see
+    /// [`Behaviour::send_request`]).
+    pending_requests: PendingRequests,
+
+    /// The request/response messages waiting for the local subscription to a reply topic to be
+    /// processed before being published, by reply topic.
+    pending_reqres_messages: HashMap<TopicHash, Vec<Message>>,
+
+    /// The subscription refreshes exchanged with the remote peers (see
+    /// [`Behaviour::request_subscription_refresh`]).
+    subscription_refreshes: SubscriptionRefreshes,
+
+    /// The frame size limits suspected for the remote peers (see [`Behaviour::peer_frame_limit`]).
+    peer_frame_limits: PeerFrameLimits,
+
+    /// The remote peers outbound send health (see [`Behaviour::is_connectivity_asymmetric`]).
+    send_health: SendHealthTracker,
+
+    /// The in-flight flushed unsubscriptions (see [`Behaviour::unsubscribe_many_and_flush`]).
+    pending_flushes: PendingFlushes,
+
+    /// The paused topics (see [`Behaviour::pause_topic`]).
+    paused_topics: BTreeSet<TopicHash>,
+
+    /// Whether the local node is in listen-only mode (see [`Behaviour::set_listen_only`]).
+    listen_only: bool,
+
+    /// The subscribed topics whose received messages are not forwarded (see
+    /// [`SubscriptionBuilder::forwarding`](crate::SubscriptionBuilder::forwarding)).
+    non_forwarding_topics: HashSet<TopicHash>,
+
+    /// The publish rotations of the topics published in round-robin (see
+    /// [`SubscriptionBuilder::delivery_mode`](crate::SubscriptionBuilder::delivery_mode)).
+    round_robin_rotations: RoundRobinRotations,
+
+    /// The peer selected for each published round-robin message, by message address, until the
+    /// message is handed to the protocol router.
+    round_robin_destinations: HashMap<*const FrameMessage, PeerId>,
+
+    /// The connections allowed disabled by the connection gate, ignored by pubsub (see
+    /// [`Config::connection_gate`]).
+    gated_connections: HashSet<ConnectionId>,
+
+    /// The local consumers registered on each topic (see [`Behaviour::register_consumer`]).
+    consumers: ConsumerRegistry,
+
     /// Connection handler's mailbox.
     ///
     /// It should only contain [`ToSwarm::NotifyHandler`] events to send to the connection handler.
     conn_handler_mailbox: VecDeque<ToSwarm<Event, HandlerCommand>>,
 
+    /// Dial requests mailbox.
+    ///
+    /// It should only contain [`ToSwarm::Dial`] events to send to the swarm.
+    dial_mailbox: VecDeque<ToSwarm<Event, HandlerCommand>>,
+
+    /// Close connection requests mailbox.
+    ///
+    /// It should only contain [`ToSwarm::CloseConnection`] events to send to the swarm.
+    close_mailbox: VecDeque<ToSwarm<Event, HandlerCommand>>,
+
     /// Behaviour output events mailbox.
     ///
     /// It should only contain [`ToSwarm::GenerateEvent`] events to send out of the behaviour, to
@@ -82,29 +321,248 @@ pub struct Behaviour<P: Protocol> {
 
 /// Public API.
 impl<P: Protocol> Behaviour<P> {
-    /// Creates a new `Behaviour` from the given configuration and protocol.
-    pub fn new(config: Config, protocol: P) -> Self {
-        let message_cache_service = BufferedContext::new(MessageCacheService::new(
+    /// Creates a new `Behaviour` for the local node, identified by `local_peer_id`, from the given
+    /// configuration and protocol.
+    ///
+    /// The published messages are not stamped with an author (see [`Identity::Anonymous`]).
+    pub fn new(local_peer_id: PeerId, config: Config, protocol: P) -> Self {
+        Self::new_with_identity(config, protocol, Identity::Anonymous(local_peer_id))
+    }
+
+    /// Creates a new `Behaviour` for the local node, identified by its `identity`, from the given
+    /// configuration and protocol.
+    ///
+    /// Unless the identity is anonymous, the messages published without a
+    /// [`from`](Message::from) field are authored by the local node peer id. The authored
+    /// messages published without a [sequence number](Message::sequence_number) are also stamped
+    /// with a monotonically increasing one, so their [default message
+    /// ids](crate::default_message_id_fn) are unique.
+    pub fn new_with_identity(config: Config, protocol: P, identity: Identity) -> Self {
+        let mut message_cache_service = Self::new_message_cache_service(&config);
+        if let Some(persistence) = config.dedup_persistence() {
+            message_cache_service.enable_dedup_persistence(persistence.clone());
+        }
+        Self::new_with_message_cache(config, protocol, identity, message_cache_service)
+    }
+
+    /// Creates a new [`BehaviourBuilder`] for the given protocol.
+    ///
+    /// Unlike [`Behaviour::new_with_identity`], the builder fails if an optional subsystem cannot
+    /// be initialized, e.g., if the dedup store cannot be opened.
+    pub fn builder(protocol: P) -> BehaviourBuilder<P> {
+        BehaviourBuilder::new(protocol)
+    }
+
+    /// Creates the message cache service from the given configuration, without the dedup store.
+    pub(crate) fn new_message_cache_service(config: &Config) -> MessageCacheService {
+        MessageCacheService::new(
             config.message_cache_capacity(),
             config.message_cache_ttl(),
+            config.dedup_scope(),
+            config.max_duplicate_resends(),
+            config.history_length(),
             config.heartbeat_interval(),
             Duration::from_secs(0),
+        )
+        .with_byte_limit(config.message_cache_max_bytes())
+        .with_message_lookup(config.max_iwant_served_per_peer_per_heartbeat() > 0)
+    }
+
+    /// Creates a new `Behaviour` with the given, already initialized, message cache service.
+    pub(crate) fn new_with_message_cache(
+        config: Config,
+        protocol: P,
+        identity: Identity,
+        message_cache_service: MessageCacheService,
+    ) -> Self {
+        let local_peer_id = identity.peer_id();
+        let message_cache_service = BufferedContext::new(message_cache_service);
+        let subscriptions_service = BufferedContext::new(SubscriptionsService::new(
+            config.max_known_remote_peers(),
+            ChurnParams {
+                window: config.subscription_churn_window(),
+                heartbeat_interval: config.heartbeat_interval(),
+                flapping_threshold: config.subscription_flapping_threshold(),
+            },
+        ));
+        let subscriptions_debounce_service = BufferedContext::new(
+            SubscriptionsDebounceService::new(config.subscription_debounce()),
+        );
+        let dialer_service = BufferedContext::new(DialerService::new(
+            config.target_peer_count(),
+            config.max_dial_attempts(),
+            config.dial_backoff(),
+        ));
+        let subscription_sync_service = BufferedContext::new(SubscriptionSyncService::new(
+            config.subscription_sync_max_attempts(),
+            config.subscription_sync_retry_interval(),
+        ));
+        let subscription_announce_service = BufferedContext::new(SubscriptionAnnounceService::new(
+            config.subscription_announce_batch(),
+            config.subscription_announce_interval(),
+        ));
+        let message_id_service =
+            BufferedContext::new(MessageIdService::new(config.default_message_id_fn()));
+        let reassembly_service = BufferedContext::new(ReassemblyService::new(
+            config.max_concurrent_transfers(),
+            config.max_transfer_size(),
+            config.transfer_timeout(),
+        ));
+        let ordering_service = BufferedContext::new(OrderingService::new(
+            config.ordering_max_delay(),
+            config.ordering_max_held_messages(),
         ));
-        let protocol_router_service = BufferedContext::new(protocol.router());
+        let mut protocol_router_service = BufferedContext::new(protocol.router(local_peer_id));
+        protocol_router_service.do_send(ProtocolRouterInEvent::ForwardFanout(ForwardFanout::new(
+            ForwardFanout::seed_from_peer_id(&local_peer_id),
+            config.max_forward_fanout(),
+            config.max_publish_fanout(),
+        )));
+        let framing_service = FramingServiceContext::new(
+            config.max_frame_size(),
+            config.publish_batch_window(),
+            UpstreamFramingParams {
+                message_ttl_clock_skew: config.message_ttl_clock_skew(),
+                max_interned_topics: config.max_interned_topics(),
+                max_key_size: config.max_key_size(),
+                topic_validation: *config.topic_validation(),
+                stats: FramingStatsParams {
+                    window: config.framing_stats_window(),
+                    inefficient_threshold: config.inefficient_framing_threshold(),
+                    min_message_rate: config.inefficient_framing_min_message_rate(),
+                    notify_interval: config.inefficient_framing_event_interval(),
+                },
+                frame_diagnostics: config.frame_diagnostics(),
+                limits: config.frame_limits(),
+                negative_cache: NegativeCacheParams {
+                    capacity: config.negative_validation_cache_capacity(),
+                    ttl: config.negative_validation_cache_ttl(),
+                },
+                parallel_validation_threshold: config.parallel_validation_threshold(),
+            },
+        );
+        let heartbeat = Heartbeat::new(config.heartbeat_interval(), config.heartbeat_interval());
+        let dead_letters = DeadLetterBuffer::new(config.dead_letter_capacity());
+        let frame_sampler = config.frame_sampling().cloned().map(FrameSampler::new);
+        let egress_pacer = config
+            .egress_budget()
+            .map(|budget| EgressPacer::new(budget, Instant::now()));
+        let egress_timer = config.egress_budget().map(|_| {
+            let interval = egress::refill_interval(config.heartbeat_interval());
+            Heartbeat::new(interval, interval)
+        });
+        let forward_warmup = ForwardWarmup::new(config.forward_warmup());
+        let unauthorized = UnauthorizedTracker::new(config.unauthorized_message_event_interval());
+        let topic_hash_mismatch_notifications =
+            TopicHashMismatchNotifications::new(config.topic_hash_mismatch_event_interval());
+        let forward_scheduler = ForwardScheduler::new(
+            config.max_forwards_per_poll(),
+            config.max_peer_forwards_per_poll(),
+        );
+        let subscription_refreshes = SubscriptionRefreshes::new(
+            config.subscription_refresh_cooldown(),
+            config.subscription_refresh_window(),
+        );
+        let peer_frame_limits = PeerFrameLimits::new(config.peer_frame_limit_decay());
+        let send_health = SendHealthTracker::new(
+            config.send_health_window(),
+            config.asymmetric_connectivity_threshold(),
+            config.asymmetric_connectivity_min_sends(),
+        );
+        let provenance = ProvenanceTracking::new(config.provenance_stats_window());
+        let activity =
+            ActivityAccounting::new(config.activity_summary_max_topics(), Instant::now());
+        let activity_summary_timer = config
+            .activity_summaries()
+            .map(|interval| Heartbeat::new(interval, interval));
+        let silence_watchdog = config.silence_watchdog().map(SilenceWatchdog::new);
+        let iwant_quotas = IWantQuotas::new(
+            config.max_iwant_served_per_peer_per_heartbeat(),
+            config.max_iwant_serves_per_message(),
+        );
+
+        // Start the sequence numbers from the current time, so they are not reused across restarts.
+        let next_sequence_number = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
 
         Self {
+            local_peer_id,
+            identity,
+            next_sequence_number,
             config,
             connections_service: Default::default(),
-            subscriptions_service: Default::default(),
-            message_id_service: Default::default(),
+            subscriptions_service,
+            subscriptions_debounce_service,
+            subscription_sync_service,
+            subscription_announce_service,
+            dialer_service,
+            message_id_service,
             message_cache_service,
+            reassembly_service,
+            ordering_service,
             protocol_router_service,
-            framing_service: Default::default(),
+            framing_service,
+            heartbeat,
+            traffic: Default::default(),
+            provenance,
+            activity,
+            activity_summary_timer,
+            last_router_sync: Instant::now(),
+            silence_watchdog,
+            iwant_quotas,
+            subscription_confirmations: Default::default(),
+            flood_cooldowns: Default::default(),
+            dropped_frames_disconnected: 0,
+            unauthorized,
+            topic_hash_mismatch_notifications,
+            dead_letters,
+            frame_sampler,
+            egress_pacer,
+            egress_timer,
+            disconnecting_peers: Default::default(),
+            forward_warmup,
+            forward_scheduler,
+            routed_messages: Default::default(),
+            last_delivery_latency: None,
+            last_forward_latency: None,
+            pending_requests: Default::default(),
+            pending_reqres_messages: Default::default(),
+            subscription_refreshes,
+            peer_frame_limits,
+            send_health,
+            pending_flushes: Default::default(),
+            paused_topics: Default::default(),
+            listen_only: false,
+            non_forwarding_topics: Default::default(),
+            round_robin_rotations: Default::default(),
+            round_robin_destinations: Default::default(),
+            gated_connections: Default::default(),
+            consumers: Default::default(),
             conn_handler_mailbox: Default::default(),
+            dial_mailbox: Default::default(),
+            close_mailbox: Default::default(),
             behaviour_output_mailbox: Default::default(),
         }
     }
 
+    /// Get the paused topics (see [`Behaviour::pause_topic`]).
+    pub fn paused_topics(&self) -> &BTreeSet<TopicHash> {
+        &self.paused_topics
+    }
+
+    /// Returns `true` if the local node is in listen-only mode (see
+    /// [`Behaviour::set_listen_only`]).
+    pub fn is_listen_only(&self) -> bool {
+        self.listen_only
+    }
+
+    /// Get the tags of the local consumers registered on the given topic, in registration order
+    /// (see [`Behaviour::register_consumer`]).
+    pub fn consumers(&self, topic: &TopicHash) -> impl Iterator<Item = &ConsumerTag> {
+        self.consumers.consumers(topic)
+    }
+
     /// Get a reference to the connections service.
     pub fn connections(&self) -> &ConnectionsService {
         &self.connections_service
@@ -120,94 +578,2938 @@ impl<P: Protocol> Behaviour<P> {
         self.subscriptions_service.peer_subscriptions(peer_id)
     }
 
-    /// Subscribe to topic.
+    /// Get the metadata of the local subscription to a topic: its owner, if any (see
+    /// [`SubscriptionBuilder::owner`](crate::SubscriptionBuilder::owner)), and the time it was
+    /// processed.
     ///
-    /// Returns `Ok(true)` if the subscription was successful, `Ok(false)` if we were already
-    /// subscribed to the topic.
-    pub fn subscribe(&mut self, sub: impl Into<Subscription>) -> anyhow::Result<bool> {
-        let sub = sub.into();
+    /// Returns `None` if the node is not subscribed to the topic. See
+    /// [`Behaviour::unsubscribe_with_info`] to get the metadata of the subscription removed.
+    pub fn subscription_info(&self, topic: &TopicHash) -> Option<&SubscriptionInfo> {
+        self.subscriptions_service.subscription_info(topic)
+    }
 
-        tracing::debug!(?sub, "Subscribing to topic");
+    /// Get the metadata of a peer subscription to a topic: the time it was first announced, and
+    /// the time it was last announced again.
+    ///
+    /// Returns `None` if the peer is not connected, or not subscribed to the topic.
+    pub fn peer_subscription_info(
+        &self,
+        peer_id: &PeerId,
+        topic: &TopicHash,
+    ) -> Option<&PeerSubscriptionInfo> {
+        self.subscriptions_service
+            .peer_subscription_info(peer_id, topic)
+    }
 
-        if self.subscriptions_service.is_subscribed(&sub.topic) {
-            return Ok(false);
-        }
+    /// Get the local node topic subscriptions the peer confirmed knowing about, i.e., the peer
+    /// forwarded a message on the topic, or grafted the local node into the topic mesh.
+    ///
+    /// The confirmations are best-effort, and reset when the peer disconnects, or the local node
+    /// unsubscribes from the topic (see [`Event::PeerConfirmedSubscription`]).
+    pub fn peer_confirmed_topics(&self, peer_id: &PeerId) -> BTreeSet<TopicHash> {
+        self.subscription_confirmations.confirmed_topics(peer_id)
+    }
 
-        // Notify the subscriptions service of the subscription request.
+    /// Get the frame size limit suspected for a peer, if any.
+    ///
+    /// The pubsub protocol has no handshake to learn the maximum frame size of a peer. Instead, the
+    /// limit is inferred from the outbound substreams failing after sending large frames: each
+    /// failure lowers the suspected limit below the size of the largest frame sent over the
+    /// substream. The frames sent to the peer are then batched and coalesced up to the suspected
+    /// limit. The suspicion is dropped after the peer goes without send failures for a while (see
+    /// [`Config::peer_frame_limit_decay`]).
+    pub fn peer_frame_limit(&self, peer_id: &PeerId) -> Option<usize> {
+        self.peer_frame_limits.limit(peer_id)
+    }
+
+    /// Check if a peer is suspected of asymmetric connectivity.
+    ///
+    /// A peer is suspected when frames are still received from it, while most of the frames sent
+    /// to it over the send health window fail (see [`Config::asymmetric_connectivity_threshold`]).
+    /// The suspected peers are deprioritized as message forward destinations, unless disabled (see
+    /// [`Config::deprioritize_asymmetric_peers`]).
+    pub fn is_connectivity_asymmetric(&self, peer_id: &PeerId) -> bool {
+        self.send_health.is_suspected(peer_id)
+    }
+
+    /// Get the topic subscriptions of a peer connection.
+    ///
+    /// Returns `None` unless the peer subscriptions are tracked per connection (see
+    /// [`Config::connection_scoped_subscriptions`]) and the connection announced a subscription.
+    pub fn connection_subscriptions(
+        &self,
+        peer_id: &PeerId,
+        connection_id: &ConnectionId,
+    ) -> Option<&BTreeSet<TopicHash>> {
         self.subscriptions_service
-            .do_send(SubscriptionsInEvent::SubscriptionRequest(sub));
+            .connection_subscriptions(peer_id, connection_id)
+    }
 
-        Ok(true)
+    /// Get the topic subscriptions of a peer the local node is not connected to, as learned from
+    /// third parties (see [`Config::max_known_remote_peers`]).
+    pub fn known_peer_subscriptions(&self, peer_id: &PeerId) -> Option<&BTreeSet<TopicHash>> {
+        self.subscriptions_service.known_peer_subscriptions(peer_id)
     }
 
-    /// Unsubscribe from topic.
+    /// Get the progress of the in-flight local subscriptions announcement to a peer, as the number
+    /// of topics announced and the total number of topics to announce (see
+    /// [`Config::subscription_announce_batch`]).
     ///
-    /// Returns `Ok(true)` if the unsubscription was successful, `Ok(false)` if we were not
-    /// subscribed to the topic.
-    pub fn unsubscribe<H: Hasher>(&mut self, topic: &Topic<H>) -> anyhow::Result<bool> {
-        tracing::debug!(sub = %topic, "Unsubscribing from topic");
+    /// Returns `None` if no announcement to the peer is in flight.
+    pub fn announcement_progress(&self, peer_id: &PeerId) -> Option<(usize, usize)> {
+        self.subscription_announce_service.progress(peer_id)
+    }
 
-        let topic = topic.hash();
+    /// Check the configuration and the local subscriptions consistency, e.g., once subscribed to
+    /// the topics, before joining the network.
+    ///
+    /// In addition to the [configuration checks](Config::preflight), the checks depending on the
+    /// local subscriptions are performed: the subscriptions announcement frame size against the
+    /// maximum frame size, the backfill maximum age against the message cache TTL if a
+    /// subscription enables the backfill, the same topic strings hashed differently, and the topic
+    /// hashes accepted by several subscriptions.
+    ///
+    /// The subscription requests are processed asynchronously, only the subscriptions whose
+    /// [`Event::Subscribed`] event was emitted are checked.
+    #[must_use]
+    pub fn preflight(&self) -> Vec<PreflightWarning> {
+        let mut warnings = self.config.preflight();
+        let subscriptions = self.subscriptions_service.subscriptions();
+
+        if subscriptions.is_empty() {
+            warnings.push(PreflightWarning::new(
+                PreflightCode::NoSubscriptions,
+                "no topic subscribed, no message can be published nor relayed",
+                Vec::new(),
+            ));
+        }
 
-        if !self.subscriptions_service.is_subscribed(&topic) {
-            return Ok(false);
+        // The subscriptions are announced to each connecting peer, in batches if enabled. Every
+        // batch frame must fit the maximum frame size.
+        let topics = subscriptions.iter().cloned().collect::<Vec<_>>();
+        let batch_size = self
+            .config
+            .subscription_announce_batch()
+            .unwrap_or(topics.len())
+            .max(1);
+        let announcement_size = topics
+            .chunks(batch_size)
+            .map(|batch| {
+                let actions = batch.iter().cloned().map(SubscriptionAction::Subscribe);
+                FrameProto::from(Frame::new_with_subscriptions(actions)).encoded_len()
+            })
+            .max()
+            .unwrap_or(0);
+        if announcement_size > self.config.max_frame_size() {
+            warnings.push(PreflightWarning::new(
+                PreflightCode::AnnouncementExceedsMaxFrameSize,
+                "the subscriptions announcement frame is larger than the maximum frame size, the \
+                 subscriptions sync fails",
+                vec![
+                    ("announcement_frame_size", announcement_size.to_string()),
+                    ("max_frame_size", self.config.max_frame_size().to_string()),
+                    ("subscriptions", topics.len().to_string()),
+                ],
+            ));
         }
 
-        // Notify the subscriptions service of the unsubscription request.
-        self.subscriptions_service
-            .do_send(SubscriptionsInEvent::UnsubscriptionRequest(topic));
+        let backfill_topics = subscriptions
+            .iter()
+            .filter(|topic| self.message_cache_service.is_backfill_enabled(topic))
+            .count();
+        if backfill_topics > 0 && self.config.backfill_max_age() > self.config.message_cache_ttl() {
+            warnings.push(PreflightWarning::new(
+                PreflightCode::BackfillMaxAgeExceedsCacheTtl,
+                "the backfill maximum age is longer than the message cache TTL, the older \
+                 messages are never backfilled",
+                vec![
+                    (
+                        "backfill_max_age",
+                        format!("{:?}", self.config.backfill_max_age()),
+                    ),
+                    (
+                        "message_cache_ttl",
+                        format!("{:?}", self.config.message_cache_ttl()),
+                    ),
+                    ("backfill_subscriptions", backfill_topics.to_string()),
+                ],
+            ));
+        }
 
-        Ok(true)
+        for topic in subscriptions {
+            let sha256_topic = Sha256Hash::hash(topic.as_str().to_owned());
+            if subscriptions.contains(&sha256_topic) {
+                warnings.push(PreflightWarning::new(
+                    PreflightCode::MixedTopicHashing,
+                    "the same topic string is subscribed with different hash functions",
+                    vec![
+                        ("identity_topic", topic.to_string()),
+                        ("sha256_topic", sha256_topic.to_string()),
+                    ],
+                ));
+            }
+        }
+
+        for (hash, topics) in self.subscriptions_service.duplicate_topic_hashes() {
+            let topics = topics
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            warnings.push(PreflightWarning::new(
+                PreflightCode::DuplicateTopicHash,
+                "the topic hash is accepted by several subscriptions",
+                vec![("topic_hash", hash.to_string()), ("subscriptions", topics)],
+            ));
+        }
+
+        warnings
     }
 
-    /// Publish a message to the network.
-    pub fn publish(&mut self, message: Message) -> anyhow::Result<()> {
-        let topic = message.topic.clone();
+    /// Get an owned copy of the local node topic subscriptions.
+    pub fn subscriptions_owned(&self) -> BTreeSet<TopicHash> {
+        self.subscriptions().clone()
+    }
 
-        tracing::debug!(%topic, "Publishing message");
+    /// Get an owned copy of the peer topic subscriptions.
+    pub fn peer_subscriptions_owned(&self, peer_id: &PeerId) -> Option<BTreeSet<TopicHash>> {
+        self.peer_subscriptions(peer_id).cloned()
+    }
 
-        // Check if we are subscribed to the topic.
-        if !self.subscriptions_service.is_subscribed(&topic) {
-            return Err(anyhow::anyhow!("Not subscribed to topic"));
-        }
+    /// Get a snapshot of the local node subscriptions, the connected peers, their topic
+    /// subscriptions and connections count.
+    pub fn topology_snapshot(&self) -> TopologySnapshot {
+        let peers = self
+            .connections_service
+            .active_peers()
+            .into_iter()
+            .map(|peer| {
+                let topology = PeerTopology {
+                    connections: self.connections_service.peer_connections_count(&peer),
+                    subscriptions: self.peer_subscriptions_owned(&peer).unwrap_or_default(),
+                };
+                (peer, topology)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        TopologySnapshot::new(self.subscriptions_owned(), peers)
+    }
 
-        // Check if we have connections to publish the message.
-        if self.connections_service.active_peers_count() == 0 {
-            return Err(anyhow::anyhow!("No active connections"));
+    /// Publish a message to the network, split into chunks of, at most, `chunk_size` payload
+    /// bytes.
+    ///
+    /// Each chunk is published as an individual message on the same topic. The chunk message
+    /// payload is the chunk bytes prefixed with a 53 bytes header:
+    ///
+    /// ```text
+    /// magic ("PSCK", 4 bytes) || version (1, 1 byte) || transfer id (sha256(payload), 32 bytes)
+    ///   || chunk index (u32 BE) || chunks total (u32 BE) || payload length (u64 BE)
+    /// ```
+    ///
+    /// The receiving nodes reassemble the payload and emit a single
+    /// [`MessageReceived`](Event::MessageReceived) event, whose message id is the transfer id. Nodes
+    /// not supporting chunking see the chunks as opaque messages.
+    ///
+    /// The signature of the message is not propagated. If the message has a sequence number, each
+    /// chunk sequence number is the message sequence number followed by the chunk index (u32 BE),
+    /// so chunks are not deduplicated by the [default message id
+    /// function](crate::default_message_id_fn). Chunks of anonymous messages require a
+    /// content-addressed message id function, e.g., [`sha256_message_id_fn`](crate::sha256_message_id_fn).
+    ///
+    /// Messages with a [time-to-live](Message::set_ttl) are rejected with a
+    /// [`PublishError::ChunkedTtl`] error. Besides the [`publish`](Self::publish) errors, a
+    /// [`PublishError::ChunkTooLarge`] error is returned, and no chunk published, if a chunk does
+    /// not fit in a frame.
+    pub fn publish_chunked(
+        &mut self,
+        message: Message,
+        chunk_size: usize,
+    ) -> Result<(), PublishError> {
+        if message.ttl.is_some() {
+            return Err(PublishError::ChunkedTtl);
         }
 
-        let message = FrameMessage::from(message);
+        let chunks = split_into_chunks(&message.data, chunk_size)
+            .ok_or(PublishError::InvalidChunkSize(chunk_size))?;
 
-        // Notify the message id service of the published message.
-        self.message_id_service
-            .do_send(MessageIdInEvent::MessageEvent(
-                MessageIdMessageEvent::Published(Rc::new(message)),
-            ));
+        tracing::debug!(topic = %message.topic, chunks = chunks.len(), "Publishing chunked message");
+
+        // The chunks of a round-robin topic message are all sent to the peer selected for the
+        // first chunk.
+        let mut peer = None;
+        for (index, data) in (0u32..).zip(chunks) {
+            let sequence_number = message.sequence_number.as_ref().map(|seqno| {
+                let mut seqno = seqno.to_vec();
+                seqno.extend_from_slice(&index.to_be_bytes());
+                Bytes::from(seqno)
+            });
+
+            let chunk = Message {
+                from: message.from,
+                data,
+                sequence_number,
+                topic: message.topic.clone(),
+                signature: None,
+                key: message.key.clone(),
+                ttl: None,
+                priority: message.priority,
+            };
+
+            // Check the chunk fits in a frame before publishing any chunk.
+            if index == 0 {
+                let frame = FrameProto::from(Frame::new_with_messages([chunk.clone().into()]));
+                let max_size = self.config.max_frame_size();
+                if frame.encoded_len() > max_size {
+                    return Err(PublishError::ChunkTooLarge {
+                        size: frame.encoded_len(),
+                        max_size,
+                    });
+                }
+            }
+
+            let receipt = self.publish_to(chunk, peer, false, None)?;
+            peer = peer.or(receipt.peer);
+        }
 
         Ok(())
     }
-}
 
-/// Internal API.
-impl<P: Protocol> Behaviour<P> {
-    /// Send a pubsub frame to a `dst` peer.
-    ///
-    /// This method checks if the frame size is within the allowed limits and queues a connection
-    /// handler event to send the frame to the peer.
-    fn send_frame(&mut self, dest: PeerId, frame: Bytes) {
-        tracing::trace!(%dest, "Sending frame");
+    /// Get the message traffic counters of a locally subscribed topic.
+    ///
+    /// Returns `None` if the local node is not subscribed to the topic. The traffic of the topics
+    /// the local node is not subscribed to is only accounted in the [traffic
+    /// totals](Behaviour::traffic_totals).
+    pub fn topic_traffic(&self, topic: &TopicHash) -> Option<TopicTraffic> {
+        self.traffic.topic(topic).cloned()
+    }
+
+    /// Get the provenance statistics of the messages delivered on a locally subscribed topic, over
+    /// the sliding window (see [`Config::provenance_stats_window`]).
+    ///
+    /// Returns `None` if the local node is not subscribed to the topic.
+    pub fn topic_provenance_stats(&self, topic: &TopicHash) -> Option<ProvenanceStats> {
+        self.provenance.stats(topic, Instant::now())
+    }
+
+    /// Get the number of frames dropped because their destination peer was no longer connected.
+    pub fn dropped_frames_disconnected(&self) -> u64 {
+        self.dropped_frames_disconnected
+    }
+
+    /// Set the chaos policy, injecting faults in the received and sent frames, for resilience
+    /// testing. Only available with the `chaos` feature enabled.
+    ///
+    /// The received frames faults are injected before the frames are decoded, simulating an
+    /// inbound loss, and the sent frames faults once the frames are encoded, simulating an
+    /// outbound loss. The duplicated frames go through the normal path, e.g., their messages are
+    /// deduplicated, and the delayed frames are released once due. The frames reported once
+    /// flushed, i.e., the unsubscription flushes (see [`Behaviour::unsubscribe_many_and_flush`]), are
+    /// left untouched. See [`Behaviour::chaos_stats`] for the injected faults counters.
+    #[cfg(feature = "chaos")]
+    pub fn set_chaos_policy(&mut self, policy: ChaosPolicy) {
+        tracing::warn!(?policy, "Setting chaos policy");
+        self.framing_service.set_chaos_policy(Some(policy));
+    }
+
+    /// Clear the chaos policy (see [`Behaviour::set_chaos_policy`]). The frames already delayed
+    /// are still released once due.
+    #[cfg(feature = "chaos")]
+    pub fn clear_chaos_policy(&mut self) {
+        tracing::debug!("Clearing chaos policy");
+        self.framing_service.set_chaos_policy(None);
+    }
+
+    /// Get the number of faults injected by the chaos policy (see
+    /// [`Behaviour::set_chaos_policy`]).
+    #[cfg(feature = "chaos")]
+    pub fn chaos_stats(&self) -> ChaosStats {
+        self.framing_service.chaos_stats()
+    }
+
+    /// Get the egress pacer state and counters, if an egress budget is set (see
+    /// [`Config::egress_budget`]).
+    pub fn egress_stats(&self) -> Option<EgressStats> {
+        self.egress_pacer.as_ref().map(EgressPacer::stats)
+    }
+
+    /// Get the number of message forwards held back by the forwarding scheduler, pending to be
+    /// sent on the next poll cycles (see [`Config::max_forwards_per_poll`]).
+    pub fn pending_forwards(&self) -> usize {
+        self.forward_scheduler.len()
+    }
+
+    /// Get the number of message forwards toward the peer held back by the forwarding scheduler
+    /// (see [`Config::max_peer_forwards_per_poll`]).
+    pub fn pending_peer_forwards(&self, peer: &PeerId) -> usize {
+        self.forward_scheduler.peer_len(peer)
+    }
+
+    /// Get the number of events queued in the behaviour, by service and by mailbox.
+    ///
+    /// The events queued when the behaviour is dropped are lost, e.g., when the swarm is torn
+    /// down. See [`Behaviour::drain_to_completion`] to process them.
+    pub fn pending_event_counts(&self) -> PendingCounts {
+        PendingCounts {
+            connections: self.connections_service.inbox_len()
+                + self.connections_service.outbox_len(),
+            subscriptions: self.subscriptions_service.inbox_len()
+                + self.subscriptions_service.outbox_len(),
+            subscriptions_debounce: self.subscriptions_debounce_service.inbox_len()
+                + self.subscriptions_debounce_service.outbox_len(),
+            subscription_sync: self.subscription_sync_service.inbox_len()
+                + self.subscription_sync_service.outbox_len(),
+            subscription_announce: self.subscription_announce_service.inbox_len()
+                + self.subscription_announce_service.outbox_len(),
+            dialer: self.dialer_service.inbox_len() + self.dialer_service.outbox_len(),
+            message_id: self.message_id_service.inbox_len() + self.message_id_service.outbox_len(),
+            message_cache: self.message_cache_service.inbox_len()
+                + self.message_cache_service.outbox_len(),
+            reassembly: self.reassembly_service.inbox_len() + self.reassembly_service.outbox_len(),
+            ordering: self.ordering_service.inbox_len() + self.ordering_service.outbox_len(),
+            protocol_router: self.protocol_router_service.inbox_len()
+                + self.protocol_router_service.outbox_len(),
+            framing: self.framing_service.inbox_len() + self.framing_service.outbox_len(),
+            scheduled_forwards: self.forward_scheduler.len(),
+            paced_frames: self.egress_pacer.as_ref().map_or(0, EgressPacer::len),
+            conn_handler_mailbox: self.conn_handler_mailbox.len(),
+            dial_mailbox: self.dial_mailbox.len(),
+            close_mailbox: self.close_mailbox.len(),
+            behaviour_output_mailbox: self.behaviour_output_mailbox.len(),
+        }
+    }
+
+    /// Process the events queued in the behaviour's services until all the services queues are
+    /// empty, or the `budget` of processing rounds is spent.
+    ///
+    /// Every processing round polls the services once, as the swarm does on every behaviour poll,
+    /// so the events emitted by a service and dispatched to an already polled service are
+    /// processed on the next round. The resulting commands, requests and events are left in the
+    /// mailboxes, to be taken by the swarm on its next poll, so no event is lost when the behaviour
+    /// is owned by a swarm.
+    ///
+    /// Returns `true` if the services queues were drained within the budget. Useful in tests, to
+    /// wait for the local operations to be processed instead of polling the swarm for a fixed
+    /// period of time.
+    pub fn drain_to_completion(&mut self, cx: &mut Context<'_>, budget: usize) -> bool {
+        for _ in 0..budget {
+            if self.pending_event_counts().services() == 0 {
+                return true;
+            }
+
+            self.poll_services(cx);
+            self.forward_scheduler.end_cycle();
+        }
+
+        self.pending_event_counts().services() == 0
+    }
+
+    /// Get the number of frames, messages and requests dropped, by cause.
+    pub fn drop_stats(&self) -> DropStats {
+        DropStats {
+            frames_expired: self.send_health.frames_expired(),
+            frames_cancelled: self.send_health.frames_cancelled(),
+            unauthorized_subscriptions: self.unauthorized.subscriptions(),
+            unauthorized_messages: self.unauthorized.messages(),
+            iwant_unserved: self.iwant_quotas.unserved(),
+            listen_only_forwards: self.forward_scheduler.discarded(),
+        }
+    }
+
+    /// Get the number of received message ids computed with the [default message id
+    /// function](Config::default_message_id_fn), as the message topic's message id function
+    /// panicked, or returned an empty or oversized message id.
+    pub fn message_id_fallbacks(&self) -> u64 {
+        self.message_id_service.fallbacks()
+    }
+
+    /// Get the number of subscription actions received from the remote peers that were dropped
+    /// for failing the [topic validation](Config::topic_validation).
+    pub fn invalid_topic_announcements(&self) -> u64 {
+        self.framing_service.invalid_topic_announcements()
+    }
+
+    /// Get the number of frames, or frame parts, received from the remote peers that were dropped
+    /// for failing the validation, of the given class.
+    ///
+    /// The [transport corruption](FrameFailureClass::TransportCorruption) failures are only
+    /// detected if the [frame diagnostics](Config::frame_diagnostics) are enabled.
+    pub fn frame_failures(&self, class: FrameFailureClass) -> u64 {
+        self.framing_service.frame_failures(class)
+    }
+
+    /// Get the number of frames, or messages, received from the remote peers that were dropped
+    /// without being validated again, as their validation failure was cached (see
+    /// [`Config::negative_validation_cache_capacity`]).
+    ///
+    /// The dropped frames and messages are still accounted as validation failures (see
+    /// [`Behaviour::frame_failures`]).
+    pub fn negative_validation_cache_hits(&self) -> u64 {
+        self.framing_service.negative_cache_hits()
+    }
+
+    /// Get the number of frames, or messages, received from the remote peers that were not found
+    /// in the negative validation cache, and were validated (see
+    /// [`Config::negative_validation_cache_capacity`]).
+    pub fn negative_validation_cache_misses(&self) -> u64 {
+        self.framing_service.negative_cache_misses()
+    }
+
+    /// Get the recorded dead letters, oldest first.
+    ///
+    /// The dead letters are only recorded if enabled (see [`Config::dead_letter`]), and up to the
+    /// [dead letter capacity](Config::dead_letter_capacity).
+    pub fn dead_letters(&self) -> impl Iterator<Item = &DeadLetter> {
+        self.dead_letters.iter()
+    }
+
+    /// Get the sampled raw frames, oldest first.
+    ///
+    /// The frames are only sampled if enabled (see [`Config::frame_sampling`]), and up to the
+    /// [maximum number of sampled frames](crate::SamplingConfig::max_stored). See
+    /// [`SampledFrame::decode_report`] to describe a sampled frame contents.
+    pub fn sampled_frames(&self) -> impl Iterator<Item = &SampledFrame> {
+        self.frame_sampler.iter().flat_map(FrameSampler::iter)
+    }
+
+    /// Drop the sampled raw frames (see [`Behaviour::sampled_frames`]).
+    pub fn clear_sampled_frames(&mut self) {
+        if let Some(sampler) = self.frame_sampler.as_mut() {
+            sampler.clear();
+        }
+    }
+
+    /// Get the message traffic counters of all topics.
+    pub fn traffic_totals(&self) -> TopicTraffic {
+        self.traffic.totals().clone()
+    }
+
+    /// Get the peer a message was first received from, i.e., the peer that delivered it.
+    ///
+    /// Returns `None` if the message is no longer in the message cache, it was published by the
+    /// local node, or the peer disconnected since.
+    pub fn message_first_seen_source(
+        &self,
+        topic: &TopicHash,
+        message_id: &MessageId,
+    ) -> Option<PeerId> {
+        self.message_cache_service
+            .first_seen_source(topic, message_id)
+    }
+
+    /// Get the total encoded size, in bytes, of the messages in the message cache (see
+    /// [`Config::message_cache_max_bytes`]).
+    pub fn message_cache_byte_usage(&self) -> usize {
+        self.message_cache_service.byte_usage()
+    }
+
+    /// Get the framing statistics of the frames received from a connected peer over the
+    /// [framing statistics window](Config::framing_stats_window).
+    ///
+    /// Returns `None` if no frame carrying messages was received from the peer since it connected.
+    pub fn peer_framing_stats(&self, peer: &PeerId) -> Option<PeerFramingStats> {
+        self.framing_service.peer_framing_stats(peer)
+    }
+
+    /// Get the subscription churn statistics of a connected peer over the [subscription churn
+    /// window](Config::subscription_churn_window).
+    ///
+    /// If the peer is not connected, or its subscriptions did not change during the window, the
+    /// statistics are empty.
+    pub fn peer_subscription_churn(&self, peer: &PeerId) -> ChurnStats {
+        self.subscriptions_service.churn(peer)
+    }
+
+    /// Whether the received messages forwarding is held back by the [forwarding
+    /// warm-up](Config::forward_warmup).
+    ///
+    /// The warm-up starts with the first connection and, once completed, an
+    /// [`Event::WarmupCompleted`] event is emitted.
+    pub fn is_warming_up(&self) -> bool {
+        self.forward_warmup.is_warming_up()
+    }
+
+    /// Get the latency between the decoding of the last message delivered to the application, and
+    /// its delivery.
+    ///
+    /// Returns `None` if no message was delivered yet. The chunked transfers are not accounted.
+    pub fn last_delivery_latency(&self) -> Option<Duration> {
+        self.last_delivery_latency
+    }
+
+    /// Get the latency between the decoding, or the publication, of the last message forwarded to
+    /// the connected peers, and its hand-off to the framing service.
+    ///
+    /// Returns `None` if no message was forwarded yet.
+    pub fn last_forward_latency(&self) -> Option<Duration> {
+        self.last_forward_latency
+    }
+
+    /// Get the known peers and their addresses.
+    ///
+    /// The known peers are the peers added via [`Behaviour::add_known_peer`] that were not pruned
+    /// after repeated dial failures.
+    pub fn known_peers(&self) -> impl Iterator<Item = (&PeerId, &[Multiaddr])> {
+        self.dialer_service.known_peers()
+    }
+
+    /// Add a peer, discovered out-of-band, and its addresses to the known peers.
+    ///
+    /// The known peers are dialed automatically while the local node is subscribed to at least
+    /// one topic and the number of connected peers is below the [target peer
+    /// count](Config::target_peer_count). A failed dial is retried with an exponential backoff,
+    /// and the peer is pruned after the [maximum number of dial
+    /// attempts](Config::max_dial_attempts).
+    ///
+    /// If the peer is already known, the addresses are added to the peer's known addresses.
+    pub fn add_known_peer(&mut self, peer: PeerId, addrs: Vec<Multiaddr>) {
+        tracing::debug!(%peer, ?addrs, "Adding known peer");
+
+        // Notify the dialer service of the known peer.
+        self.dialer_service
+            .do_send(DialerInEvent::KnownPeerAdded { peer, addrs });
+    }
+
+    /// Pause a topic, without tearing down its subscription.
+    ///
+    /// While paused, the messages received on the topic are inserted into the message cache, so
+    /// their duplicates are still suppressed once resumed, but they are neither notified to the
+    /// application nor forwarded. Publishing to the topic fails with
+    /// [`PublishError::TopicPaused`]. The subscription to the topic is still announced to the
+    /// connected peers, so their subscriptions state does not change.
+    ///
+    /// The topic stays paused, even if unsubscribed, until [resumed](Behaviour::resume_topic).
+    ///
+    /// Returns `true` if the topic was not paused.
+    pub fn pause_topic(&mut self, topic: &TopicHash) -> bool {
+        tracing::debug!(%topic, "Pausing topic");
+        self.paused_topics.insert(topic.clone())
+    }
+
+    /// Resume a topic paused with [`Behaviour::pause_topic`].
+    ///
+    /// The messages received once resumed are notified to the application and forwarded as usual.
+    /// The messages received while paused are not.
+    ///
+    /// Returns `true` if the topic was paused.
+    pub fn resume_topic(&mut self, topic: &TopicHash) -> bool {
+        tracing::debug!(%topic, "Resuming topic");
+        self.paused_topics.remove(topic)
+    }
+
+    /// Enable, or disable, the listen-only mode.
+    ///
+    /// In listen-only mode, the local node receives the messages on its subscribed topics, but
+    /// neither forwards nor publishes any message: the protocol router message forwards are
+    /// dropped and counted (see [`DropStats::listen_only_forwards`]), as are the forwards
+    /// still pending when the mode is enabled, and publishing fails with
+    /// [`PublishError::ListenOnly`]. No message is queued while in listen-only mode, so disabling
+    /// it does not send any message received, or published, in the meantime.
+    ///
+    /// The subscription announcements are still sent to the connected peers, and the control
+    /// messages of the protocol router too: a node announcing no subscriptions would receive no
+    /// messages at all. So the subscriptions are kept as is, and the node resumes its normal
+    /// operation once the mode is disabled, without re-subscribing.
+    ///
+    /// An [`Event::ListenOnlyChanged`] event is emitted if the mode changes.
+    pub fn set_listen_only(&mut self, enabled: bool) {
+        if self.listen_only == enabled {
+            return;
+        }
+
+        tracing::debug!(enabled, "Setting listen-only mode");
+        self.listen_only = enabled;
+
+        // Drop the forwards scheduled before entering the listen-only mode.
+        if enabled {
+            self.forward_scheduler.clear();
+        }
+
+        self.behaviour_output_mailbox
+            .push_back(ToSwarm::GenerateEvent(Event::listen_only_changed(enabled)));
+    }
+
+    /// Register a local consumer of the messages received on the given topic.
+    ///
+    /// Every message received on the topic is notified once per registered consumer, as an
+    /// [`Event::MessageForConsumer`] event tagged with the consumer `tag`. The node subscribes to
+    /// the topic, if not subscribed yet, and stays subscribed until the last consumer is
+    /// unregistered and the topic explicit subscription, if any, is removed (see
+    /// [`Behaviour::unsubscribe`]).
+    ///
+    /// The consumer is unregistered when the returned handle is dropped, or with
+    /// [`Behaviour::unregister_consumer`].
+    ///
+    /// Returns a [`TopicValidationError`](crate::TopicValidationError) error if the topic fails
+    /// the [topic validation](Config::topic_validation).
+    pub fn register_consumer(
+        &mut self,
+        topic: TopicHash,
+        tag: ConsumerTag,
+    ) -> anyhow::Result<ConsumerHandle> {
+        tracing::debug!(%topic, %tag, "Registering consumer");
+
+        self.config.topic_validation().validate(topic.as_str())?;
+
+        let (handle, subscribe) = self.consumers.register(topic.clone(), tag);
+        if subscribe {
+            // Notify the subscriptions service of the subscription request.
+            self.subscriptions_service
+                .do_send(SubscriptionsInEvent::SubscriptionRequest(topic.into()));
+        }
+
+        Ok(handle)
+    }
+
+    /// Unregister a local consumer registered with [`Behaviour::register_consumer`], without
+    /// waiting for its handle to be dropped.
+    ///
+    /// If it was the last consumer registered on a topic not explicitly subscribed to, the node
+    /// unsubscribes from the topic.
+    pub fn unregister_consumer(&mut self, handle: ConsumerHandle) {
+        tracing::debug!(topic = %handle.topic(), tag = %handle.tag(), "Unregistering consumer");
+
+        let topic = handle.topic().clone();
+        if self.consumers.unregister(handle) {
+            self.release_consumers_topic(topic);
+        }
+    }
+
+    /// Subscribe to topic.
+    ///
+    /// Returns `Ok(true)` if the subscription request was accepted, `Ok(false)` if we were already
+    /// subscribed to the topic.
+    ///
+    /// The explicit subscription to a topic is independent of the local consumers registered on it
+    /// (see [`Behaviour::register_consumer`]): the node stays subscribed to the topic until
+    /// unsubscribed, even if all the consumers are unregistered.
+    ///
+    /// The subscription is processed asynchronously, during a later poll of the behaviour. Until
+    /// then, the node is not subscribed to the topic and [`Behaviour::publish`] fails. Once the
+    /// subscription is processed, and its announcement to the connected peers queued, an
+    /// [`Event::Subscribed`] event is emitted. Applications should wait for that event before
+    /// publishing to the topic.
+    ///
+    /// Returns a [`TopicValidationError`](crate::TopicValidationError) error if the topic fails
+    /// the [topic validation](Config::topic_validation).
+    pub fn subscribe(&mut self, sub: impl Into<Subscription>) -> anyhow::Result<bool> {
+        Ok(self.request_subscription(sub.into())?)
+    }
+
+    /// Unsubscribe from topic.
+    ///
+    /// Returns `Ok(true)` if the unsubscription request was accepted, `Ok(false)` if we were not
+    /// subscribed to the topic.
+    ///
+    /// As with [`Behaviour::subscribe`], the unsubscription is processed asynchronously. Once it
+    /// is processed, an [`Event::Unsubscribed`] event is emitted.
+    ///
+    /// If local consumers are registered on the topic (see [`Behaviour::register_consumer`]), only
+    /// the explicit subscription is removed, and the node stays subscribed to the topic until the
+    /// last consumer is unregistered.
+    pub fn unsubscribe<H: Hasher>(&mut self, topic: &Topic<H>) -> anyhow::Result<bool> {
+        tracing::debug!(sub = %topic, "Unsubscribing from topic");
+
+        Ok(self.request_unsubscription(topic.hash()).is_some())
+    }
+
+    /// Unsubscribe from topic, and return the metadata of the subscription removed (see
+    /// [`Behaviour::subscription_info`]), e.g., to log its owner.
+    ///
+    /// Returns `Ok(Some(info))` if the unsubscription request was accepted, `Ok(None)` if we were
+    /// not subscribed to the topic, or if the topic is retained by local consumers. The
+    /// unsubscription is processed as with [`Behaviour::unsubscribe`].
+    pub fn unsubscribe_with_info<H: Hasher>(
+        &mut self,
+        topic: &Topic<H>,
+    ) -> anyhow::Result<Option<SubscriptionInfo>> {
+        tracing::debug!(sub = %topic, "Unsubscribing from topic");
+
+        let topic = topic.hash();
+        let info = self
+            .subscriptions_service
+            .subscription_info(&topic)
+            .cloned();
+        match self.request_unsubscription(topic) {
+            Some(true) => Ok(info),
+            _ => Ok(None),
+        }
+    }
+
+    /// Unsubscribe from several topics, and report when the unsubscription has been flushed to
+    /// the connected peers.
+    ///
+    /// The topics are unsubscribed as with [`Behaviour::unsubscribe`]. Once processed, their
+    /// unsubscription is sent to every active peer in a single frame, bypassing the [subscription
+    /// debounce window](Config::subscription_debounce). An [`Event::UnsubscriptionFlushed`] event,
+    /// carrying the returned flush id, is emitted once the connection handlers report the frame
+    /// flushed to the substream of every peer, or once the `deadline` passes. The peers the frame
+    /// was not flushed to by then, or that disconnected in the meantime, are reported as failed.
+    /// The deadline is checked on every poll of the behaviour, at least once per heartbeat (see
+    /// [`Config::heartbeat_interval`]).
+    ///
+    /// The topics the node is not subscribed to, or retained by local consumers, are skipped. If
+    /// no topic is unsubscribed, the flush completes with an empty report.
+    pub fn unsubscribe_many_and_flush(
+        &mut self,
+        topics: impl IntoIterator<Item = TopicHash>,
+        deadline: Duration,
+    ) -> FlushId {
+        let topics = topics
+            .into_iter()
+            .filter(|topic| self.request_unsubscription(topic.clone()) == Some(true))
+            .collect::<BTreeSet<_>>();
+
+        let flush_id = self
+            .pending_flushes
+            .insert(topics.clone(), Instant::now() + deadline);
+        tracing::debug!(%flush_id, ?topics, "Unsubscribing from topics with flush");
+
+        flush_id
+    }
+
+    /// Remove the explicit subscription to the topic and, unless the topic is retained by local
+    /// consumers, request the unsubscription to the subscriptions service.
+    ///
+    /// Returns `None` if the node is not subscribed to the topic, otherwise whether the
+    /// unsubscription was requested.
+    fn request_unsubscription(&mut self, topic: TopicHash) -> Option<bool> {
+        self.consumers.remove_explicit_subscription(&topic);
+
+        if !self.subscriptions_service.is_subscribed(&topic) {
+            self.record_dead_letter(
+                DeadLetterStage::SubscriptionFilter,
+                "not subscribed",
+                || format!("unsubscription request for topic {topic}"),
+            );
+            return None;
+        }
+
+        if self.consumers.has_consumers(&topic) {
+            tracing::debug!(%topic, "Topic retained by the registered consumers");
+            return Some(false);
+        }
+
+        // Notify the subscriptions service of the unsubscription request.
+        self.subscriptions_service
+            .do_send(SubscriptionsInEvent::UnsubscriptionRequest(topic));
+
+        Some(true)
+    }
+
+    /// Replace the message id function of a subscribed topic, without unsubscribing from it.
+    ///
+    /// If `message_id_fn` is `None`, the [default message id
+    /// function](Config::default_message_id_fn) is used. The messages published and received
+    /// afterwards get their message id from the new function. The message cache entries keep
+    /// the ids computed by the previous function, so a message seen before the swap is only
+    /// deduplicated after it if both functions compute the same id for it.
+    ///
+    /// Returns [`SubscriptionError::NotSubscribed`] if the node is not subscribed to the topic.
+    pub fn set_message_id_fn(
+        &mut self,
+        topic: &TopicHash,
+        message_id_fn: Option<Rc<dyn MessageIdFn<Output = MessageId>>>,
+    ) -> Result<(), SubscriptionError> {
+        tracing::debug!(%topic, "Changing topic message id function");
+
+        if !self.subscriptions_service.is_subscribed(topic) {
+            return Err(SubscriptionError::NotSubscribed(topic.clone()));
+        }
+
+        // Notify the message id service of the message id function change.
+        self.message_id_service
+            .do_send(MessageIdInEvent::SubscriptionEvent(
+                MessageIdSubscriptionEvent::MessageIdFnChanged {
+                    topic: topic.clone(),
+                    message_id_fn,
+                },
+            ));
+
+        Ok(())
+    }
+
+    /// Ask a connected peer to re-announce its subscriptions, e.g., when the peer subscriptions
+    /// seem out of sync after a missed announcement.
+    ///
+    /// There is no refresh request in the wire protocol. Instead, the local subscriptions are
+    /// re-sent to the peer in full, and the peers running this implementation answer the
+    /// re-announcement of already known subscriptions with their own subscriptions. This is a
+    /// heuristic: other implementations may ignore the re-announcement, and nodes with no
+    /// subscriptions have nothing to re-announce.
+    ///
+    /// The topics the peer is newly found subscribed to within the [refresh
+    /// window](Config::subscription_refresh_window) are notified as an
+    /// [`Event::SubscriptionRefreshCompleted`] event.
+    ///
+    /// Returns `false`, and sends nothing, if the peer is not connected, the local node has no
+    /// subscriptions, or the peer was sent the local subscriptions less than a [refresh
+    /// cooldown](Config::subscription_refresh_cooldown) ago.
+    pub fn request_subscription_refresh(&mut self, peer: PeerId) -> bool {
+        if self.connections_service.peer_connections_count(&peer) == 0
+            || self.subscriptions_service.subscriptions().is_empty()
+        {
+            tracing::debug!(%peer, "Subscription refresh skipped");
+            return false;
+        }
+
+        if !self.subscription_refreshes.request(peer, Instant::now()) {
+            tracing::debug!(%peer, "Subscription refresh in cooldown");
+            return false;
+        }
+
+        tracing::debug!(%peer, "Requesting subscription refresh");
+        self.announce_subscriptions(peer);
+        true
+    }
+
+    /// Ask all the connected peers to re-announce their subscriptions (see
+    /// [`Behaviour::request_subscription_refresh`]).
+    ///
+    /// Returns the number of peers the local subscriptions were re-sent to.
+    pub fn refresh_all_peers(&mut self) -> usize {
+        self.connections_service
+            .active_peers()
+            .into_iter()
+            .filter(|peer| self.request_subscription_refresh(*peer))
+            .count()
+    }
+
+    /// Replace the protocol router subscriptions and routing tables with the local node
+    /// subscriptions and the connected peers subscriptions, as tracked by the behaviour.
+    ///
+    /// The protocol router only learns the subscriptions incrementally, so its state may diverge
+    /// from the behaviour view, e.g., after a panic recovered in the router. The sync reconciles
+    /// them (see [`ProtocolRouterInEvent::StateSync`]). It is also performed periodically if
+    /// [`Config::router_sync_interval`] is enabled.
+    pub fn force_router_sync(&mut self) {
+        let local_topics = self.subscriptions_owned();
+        let peers = self
+            .connections_service
+            .active_peers()
+            .into_iter()
+            .map(|peer| {
+                let topics = self.peer_subscriptions_owned(&peer).unwrap_or_default();
+                (peer, topics)
+            })
+            .collect::<Vec<_>>();
+
+        tracing::debug!(peers = peers.len(), "Syncing the protocol router state");
+
+        self.last_router_sync = Instant::now();
+        self.protocol_router_service
+            .do_send(ProtocolRouterInEvent::StateSync {
+                local_topics,
+                peers,
+            });
+    }
+
+    /// Update the maximum byte size of the frames sent to the remote peers (see
+    /// [`Config::max_frame_size`]).
+    ///
+    /// The failed subscription syncs pending a retry are retried with the new limit. The
+    /// connection handlers of the already established connections keep coalescing the queued
+    /// frames up to the previous limit.
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.config.set_max_frame_size(max_frame_size);
+        self.framing_service.do_send(FramingInEvent::Downstream(
+            FramingDownstreamInEvent::MaxFrameSizeChanged(max_frame_size),
+        ));
+    }
+
+    /// Update the egress bandwidth budget (see [`Config::egress_budget`]).
+    ///
+    /// The frames held back by the egress pacer are released at the new budget rate, and all at
+    /// once if the budget is removed. The pacer counters are reset when the budget is removed.
+    pub fn set_egress_budget(&mut self, budget: Option<BytesPerSecond>) {
+        tracing::debug!(?budget, "Setting egress budget");
+        self.config.set_egress_budget(budget);
+
+        let Some(budget) = budget else {
+            self.egress_timer = None;
+            if let Some(mut pacer) = self.egress_pacer.take() {
+                for (_, event) in pacer.drain() {
+                    self.conn_handler_mailbox.push_back(event);
+                }
+            }
+            return;
+        };
+
+        match self.egress_pacer.as_mut() {
+            Some(pacer) => pacer.set_budget(budget, Instant::now()),
+            None => {
+                let interval = egress::refill_interval(self.config.heartbeat_interval());
+                self.egress_pacer = Some(EgressPacer::new(budget, Instant::now()));
+                self.egress_timer = Some(Heartbeat::new(interval, interval));
+            }
+        }
+    }
+
+    /// Publish a message to the network.
+    ///
+    /// The node must be subscribed to the message topic, i.e., the [`Event::Subscribed`] event
+    /// for the topic must have been emitted. Otherwise, an error is returned.
+    ///
+    /// If no connected peer is subscribed to the topic, the message is published anyway, and an
+    /// [`Event::PublishedWithoutSubscribers`] event is emitted (see
+    /// [`Config::warn_on_no_subscribers`]).
+    ///
+    /// If the message has a [time-to-live](Message::set_ttl), its absolute expiry time is stamped
+    /// into the message payload.
+    ///
+    /// Publishing to a [paused topic](Behaviour::pause_topic), or in [listen-only
+    /// mode](Behaviour::set_listen_only), fails.
+    ///
+    /// Publishing fails if the topic's message id function panics, or returns an empty or
+    /// oversized message id (see [`MessageIdFn`]).
+    ///
+    /// Publishing fails if the message encoded size exceeds the maximum publish size (see
+    /// [`Config::max_publish_size`]).
+    ///
+    /// The message is routed against the peer subscriptions at the time it is accepted: a peer
+    /// unsubscription received but not yet processed when this method is called does not prevent
+    /// the peer from receiving the message. The frames queued for a peer are only dropped on its
+    /// disconnection, not on its unsubscription from the message topic.
+    pub fn publish(&mut self, message: Message) -> Result<(), PublishError> {
+        self.publish_with_receipt(message).map(|_| ())
+    }
+
+    /// Publish a message to the network, and return its message id.
+    ///
+    /// The message is published as with [`Behaviour::publish`]. The returned message id is the
+    /// one computed by the topic's message id function, once the message is stamped with the
+    /// local node as author, i.e., the id the other nodes compute for the message.
+    pub fn publish_with_id(&mut self, message: Message) -> Result<MessageId, PublishError> {
+        self.publish_with_receipt(message)
+            .map(|receipt| receipt.message_id)
+    }
+
+    /// Publish a message to the network, and return its message id and, for the topics
+    /// published in round-robin, the peer the message was sent to.
+    ///
+    /// The message is published as with [`Behaviour::publish`]. If the topic is published in
+    /// round-robin (see [`SubscriptionBuilder::delivery_mode`](crate::SubscriptionBuilder::delivery_mode)),
+    /// the message is sent to the next connected subscribed peer of the topic rotation only. If
+    /// no connected peer is subscribed to the topic, no peer is selected and the message is
+    /// published as usual.
+    pub fn publish_with_receipt(
+        &mut self,
+        message: Message,
+    ) -> Result<PublishReceipt, PublishError> {
+        self.publish_to(message, None, true, None)
+    }
+
+    /// Publish a message to the network, along with local metadata delivered to the local node.
+    ///
+    /// The message is published as with [`Behaviour::publish`]. If the locally published
+    /// messages are delivered to the local node (see [`Config::deliver_own_messages`]), the
+    /// [`Event::MessageReceived`] event of the message carries the `metadata`, e.g., the parsed
+    /// message payload or a trace context, so the local consumers need not derive it again. The
+    /// metadata is never sent on the wire.
+    pub fn publish_with_metadata(
+        &mut self,
+        message: Message,
+        metadata: Arc<dyn Any + Send + Sync>,
+    ) -> Result<(), PublishError> {
+        self.publish_to(message, None, true, Some(metadata))
+            .map(|_| ())
+    }
+
+    /// Forward a message, as is, to the connected peers.
+    ///
+    /// The message is handed to the protocol router as a message received from the local node,
+    /// so it is forwarded as any received message, but to none of the `exclude` peers. Unlike
+    /// [`publish`](Self::publish), the message author and sequence number are left untouched, so
+    /// the other nodes deduplicate it as usual. This allows the application to re-propagate the
+    /// messages it validated out-of-band.
+    ///
+    /// The message is presumed already seen: it is neither inserted into the message cache nor
+    /// notified to the application. The forwarding warm-up (see [`Config::forward_warmup`]) does
+    /// not apply.
+    ///
+    /// Returns the number of connected peers subscribed to the message topic, not excluded, the
+    /// message was queued to. The protocol router may select fewer destination peers, e.g., if
+    /// the forwarding fan-out is capped (see [`Config::max_forward_fanout`]).
+    pub fn forward_message(
+        &mut self,
+        message: FrameMessage,
+        exclude: &[PeerId],
+    ) -> Result<usize, ForwardError> {
+        let topic = message.topic();
+
+        tracing::debug!(%topic, "Forwarding message");
+
+        // Check if we are subscribed to the topic.
+        if !self.subscriptions_service.is_subscribed(&topic) {
+            return Err(ForwardError::NotSubscribed(topic));
+        }
+
+        // Check if the local node is in listen-only mode.
+        if self.listen_only {
+            return Err(ForwardError::ListenOnly);
+        }
+
+        // Check if the message expired.
+        if ttl::is_expired(
+            &message.data(),
+            SystemTime::now(),
+            self.config.message_ttl_clock_skew(),
+        ) {
+            return Err(ForwardError::Expired);
+        }
+
+        let exclude = exclude.iter().copied().collect::<BTreeSet<_>>();
+        let queued = self
+            .connections_service
+            .active_peers()
+            .into_iter()
+            .filter(|peer| {
+                !exclude.contains(peer)
+                    && self.subscriptions_service.is_peer_subscribed(peer, &topic)
+            })
+            .count();
+
+        let message_id = self.message_id_service.message_id(None, &message);
+        let context = MessageContext::forwarded(&topic);
+        context.record_message_id(&message_id);
+
+        // Notify the protocol's service of the message to forward.
+        let message = Rc::new(message);
+        self.track_routed_message(&message, context);
+        self.protocol_router_service
+            .do_send(ProtocolRouterInEvent::MessageEvent(
+                ProtocolRouterMessageEvent::MessageReceived {
+                    src: self.local_peer_id,
+                    message,
+                    message_id,
+                    exclude,
+                },
+            ));
+
+        Ok(queued)
+    }
+
+    /// Send a request to the nodes subscribed to the given topic (see [`reqres`](crate::reqres)).
+    ///
+    /// The request is published wrapped in a request envelope, carrying a new request id and the
+    /// local node reply topic. The local node subscribes to its reply topic on the first request,
+    /// and the request is published once the subscription is processed.
+    ///
+    /// The first response received is notified with an [`Event::ResponseReceived`] event. If no
+    /// response is received before the `timeout`, an [`Event::RequestTimedOut`] event is emitted
+    /// instead. The timeouts are checked on every heartbeat (see [`Config::heartbeat_interval`]).
+    ///
+    /// As with [`publish`](Self::publish), the local node must be subscribed to the topic and have
+    /// active connections, or a [`PublishError::NotSubscribed`] or
+    /// [`PublishError::NoActiveConnections`] error is returned.
+    pub fn send_request(
+        &mut self,
+        topic: impl Into<TopicHash>,
+        payload: impl AsRef<[u8]>,
+        timeout: Duration,
+    ) -> Result<RequestId, PublishError> {
+        let topic = topic.into();
+
+        if !self.subscriptions_service.is_subscribed(&topic) {
+            return Err(PublishError::NotSubscribed(topic));
+        }
+
+        if self.connections_service.active_peers_count() == 0 {
+            return Err(PublishError::NoActiveConnections);
+        }
+
+        let reply_to = reqres::reply_topic(&self.local_peer_id);
+        let request_id = self.pending_requests.insert(Instant::now() + timeout);
+        let Some(data) = reqres::encode_request(request_id, &reply_to, payload.as_ref()) else {
+            self.pending_requests.complete(&request_id);
+            return Err(PublishError::ReplyTopicTooLong(reply_to));
+        };
+
+        tracing::debug!(%topic, %request_id, "Sending request");
+
+        let mut message = Message::new(topic, data);
+        message.sequence_number = Some(self.next_sequence_number());
+        if let Err(err) = self.publish_on_reply_topic_subscription(reply_to, message) {
+            self.pending_requests.complete(&request_id);
+            return Err(err);
+        }
+
+        Ok(request_id)
+    }
+
+    /// Send a response to a request received from another node (see [`reqres`](crate::reqres)).
+    ///
+    /// The response is published to the request reply topic, wrapped in a response envelope
+    /// carrying the request id. Publishing to a topic requires a subscription, so the local node
+    /// subscribes to the reply topic on the first response to the requester, and the response is
+    /// published once the subscription is processed. The other responses published to the reply
+    /// topic are not notified to the application.
+    ///
+    /// As with [`publish`](Self::publish), the local node must have active connections, or a
+    /// [`PublishError::NoActiveConnections`] error is returned.
+    pub fn send_response(
+        &mut self,
+        request: &RequestEnvelope,
+        payload: impl AsRef<[u8]>,
+    ) -> Result<(), PublishError> {
+        if self.connections_service.active_peers_count() == 0 {
+            return Err(PublishError::NoActiveConnections);
+        }
+
+        tracing::debug!(reply_to = %request.reply_to, request_id = %request.request_id, "Sending response");
+
+        let data = reqres::encode_response(request.request_id, payload.as_ref());
+        let mut message = Message::new(request.reply_to.clone(), data);
+        message.sequence_number = Some(self.next_sequence_number());
+        self.publish_on_reply_topic_subscription(request.reply_to.clone(), message)
+    }
+}
+
+/// Internal API.
+impl<P: Protocol> Behaviour<P> {
+    /// Request the subscription to a topic, once validated (see [`Behaviour::subscribe`]).
+    fn request_subscription(&mut self, sub: Subscription) -> Result<bool, TopicValidationError> {
+        tracing::debug!(?sub, "Subscribing to topic");
+
+        self.config
+            .topic_validation()
+            .validate(sub.topic.as_str())?;
+
+        self.consumers.add_explicit_subscription(sub.topic.clone());
+
+        if self.subscriptions_service.is_subscribed(&sub.topic) {
+            self.record_dead_letter(
+                DeadLetterStage::SubscriptionFilter,
+                "already subscribed",
+                || format!("subscription request for topic {}", sub.topic),
+            );
+            return Ok(false);
+        }
+
+        // Notify the subscriptions service of the subscription request.
+        self.subscriptions_service
+            .do_send(SubscriptionsInEvent::SubscriptionRequest(sub));
+
+        Ok(true)
+    }
+
+    /// Track the lifecycle context of a message handed to the protocol router, so it follows the
+    /// message to the framing service if the router forwards it.
+    fn track_routed_message(&mut self, message: &Rc<FrameMessage>, mut context: MessageContext) {
+        context.record_stage(MessageStage::Routed);
+        self.routed_messages
+            .insert(Rc::as_ptr(message), (message.clone(), context));
+    }
+
+    /// Unsubscribe from a topic no longer retained by any local consumer nor explicitly subscribed
+    /// to.
+    fn release_consumers_topic(&mut self, topic: TopicHash) {
+        tracing::debug!(%topic, "Last consumer unregistered, unsubscribing from topic");
+
+        // Notify the subscriptions service of the unsubscription request.
+        self.subscriptions_service
+            .do_send(SubscriptionsInEvent::UnsubscriptionRequest(topic));
+    }
+
+    /// Notify the application of a received message: once per local consumer registered on the
+    /// message topic, and as an [`Event::MessageReceived`] event if the topic is explicitly
+    /// subscribed to, or has no consumers.
+    fn notify_message_received(&mut self, src: PeerId, message: Message, message_id: MessageId) {
+        // Account the delivered message provenance.
+        self.provenance.record(
+            &message.topic,
+            Provenance::new(&src, message.from.as_ref()),
+            Instant::now(),
+        );
+        self.activity.record_delivered(&message.topic);
+
+        for tag in self.consumers.consumers(&message.topic) {
+            self.behaviour_output_mailbox
+                .push_back(ToSwarm::GenerateEvent(Event::message_for_consumer(
+                    tag.clone(),
+                    message.clone(),
+                    message_id.clone(),
+                )));
+        }
+
+        if self.consumers.is_explicitly_subscribed(&message.topic)
+            || !self.consumers.has_consumers(&message.topic)
+        {
+            self.behaviour_output_mailbox
+                .push_back(ToSwarm::GenerateEvent(Event::message_received(
+                    src, message, message_id,
+                )));
+        }
+    }
+
+    /// Returns `true` if the peer is authorized to take the action on the topic (see
+    /// [`Config::topic_authorizer`]).
+    fn is_authorized(&self, peer: &PeerId, topic: &TopicHash, action: TopicAction) -> bool {
+        self.config
+            .topic_authorizer()
+            .map_or(true, |authorizer| authorizer(peer, topic, action))
+    }
+
+    /// Consult the connection gate, if any, for the connection established with the peer (see
+    /// [`Config::connection_gate`]).
+    ///
+    /// Returns an error if the connection is denied, and the disabled connection handler if the
+    /// connection is allowed disabled. The allowed disabled connections are ignored from now on.
+    fn gate_connection(
+        &mut self,
+        peer_id: PeerId,
+        context: ConnectionGateContext,
+    ) -> Result<Option<Handler<P::Upgrade>>, ConnectionDenied> {
+        let Some(gate) = self.config.connection_gate() else {
+            return Ok(None);
+        };
+
+        let connection_id = context.connection_id;
+        match gate(&peer_id, context) {
+            GateDecision::Allow => Ok(None),
+            GateDecision::Deny => {
+                tracing::debug!(peer = %peer_id, "Connection denied by the connection gate");
+                Err(ConnectionDenied::new(ConnectionGateDenied {
+                    peer: peer_id,
+                }))
+            }
+            GateDecision::AllowDisabled => {
+                tracing::debug!(peer = %peer_id, "Connection disabled by the connection gate");
+                self.gated_connections.insert(connection_id);
+                Ok(Some(Handler::new_disabled(
+                    P::upgrade(),
+                    self.config.max_frame_size(),
+                )))
+            }
+        }
+    }
+
+    /// Record an intentionally dropped input as a dead letter, if enabled (see
+    /// [`Config::dead_letter`]).
+    ///
+    /// The dropped input `summary` is only formatted if the dead letters are enabled.
+    fn record_dead_letter(
+        &mut self,
+        stage: DeadLetterStage,
+        reason: &'static str,
+        summary: impl FnOnce() -> String,
+    ) {
+        if !self.config.dead_letter() {
+            return;
+        }
+
+        let letter = DeadLetter::new(stage, reason, summary());
+        if self.config.dead_letter_events() {
+            self.behaviour_output_mailbox
+                .push_back(ToSwarm::GenerateEvent(Event::DeadLetter(letter.clone())));
+        }
+        self.dead_letters.push(letter);
+    }
+
+    /// Publish a request/response message once the local node is subscribed to the `reply_to`
+    /// topic, subscribing to it if needed.
+    fn publish_on_reply_topic_subscription(
+        &mut self,
+        reply_to: TopicHash,
+        message: Message,
+    ) -> Result<(), PublishError> {
+        if self.subscriptions_service.is_subscribed(&reply_to) {
+            return self.publish(message);
+        }
+
+        // Do not queue the message, to be published once out of the listen-only mode.
+        if self.listen_only {
+            return Err(PublishError::ListenOnly);
+        }
+
+        if !self.pending_reqres_messages.contains_key(&reply_to) {
+            self.request_subscription(reply_to.clone().into())
+                .map_err(PublishError::InvalidReplyTopic)?;
+        }
+        self.pending_reqres_messages
+            .entry(reply_to)
+            .or_default()
+            .push(message);
+
+        Ok(())
+    }
+
+    /// Get the next authored message sequence number, as a 8-byte big-endian integer.
+    fn next_sequence_number(&mut self) -> Bytes {
+        let sequence_number = self.next_sequence_number;
+        self.next_sequence_number = self.next_sequence_number.wrapping_add(1);
+        Bytes::copy_from_slice(&sequence_number.to_be_bytes())
+    }
+
+    /// Send a pubsub frame to a `dst` peer, over the given `connection` or any of the peer
+    /// connections if `None`.
+    ///
+    /// This method checks if the frame size is within the allowed limits and the peer is still
+    /// connected, and queues a connection handler event to send the frame to the peer. If the
+    /// egress budget is exhausted, the frame is held back by the egress pacer, unless a `control`
+    /// frame (see [`Config::egress_budget`]).
+    fn send_frame(
+        &mut self,
+        dest: PeerId,
+        connection: Option<ConnectionId>,
+        frame: Bytes,
+        tag: Option<u64>,
+        priority: Priority,
+        control: bool,
+    ) {
+        tracing::trace!(%dest, "Sending frame");
+
+        // Check if the frame size exceeds the maximum allowed size. If so, drop the frame.
+        if frame.len() > self.config.max_frame_size() {
+            tracing::warn!(%dest, "Frame size exceeds maximum allowed size");
+            self.record_dead_letter(DeadLetterStage::SizeLimit, "frame too large", || {
+                format!("{} bytes frame to {dest}", frame.len())
+            });
+            if let Some(tag) = tag {
+                self.pending_flushes.failed(&FlushId::new(tag), dest);
+            }
+            return;
+        }
+
+        // Check if the peer is still connected. If not, drop the frame.
+        if !self.connections_service.is_active(&dest) {
+            tracing::debug!(%dest, "Peer not connected, dropping frame");
+            self.dropped_frames_disconnected += 1;
+            if let Some(tag) = tag {
+                self.pending_flushes.failed(&FlushId::new(tag), dest);
+            }
+            return;
+        }
+
+        // Sample the sent frame, if enabled.
+        if let Some(sampler) = self.frame_sampler.as_mut() {
+            sampler.sample(FrameDirection::Sent, dest, &frame);
+        }
+
+        // The tagged frames are reported by the connection handler once flushed.
+        let len = frame.len() as u64;
+        let event = match tag {
+            Some(tag) => HandlerCommand::SendTaggedFrame { frame, tag },
+            None => HandlerCommand::SendFrame {
+                frame,
+                expires_at: None,
+                priority,
+            },
+        };
+        let event = ToSwarm::NotifyHandler {
+            peer_id: dest,
+            handler: connection.map_or(NotifyHandler::Any, NotifyHandler::One),
+            event,
+        };
+
+        // Hold back the frame if the egress budget is exhausted.
+        let event = match self.egress_pacer.as_mut() {
+            Some(pacer) => pacer.submit(dest, len, priority, control, event, Instant::now()),
+            None => Some(event),
+        };
+        match event {
+            Some(event) => self.conn_handler_mailbox.push_back(event),
+            None => tracing::trace!(%dest, "Egress budget exhausted, holding back frame"),
+        }
+    }
+
+    /// The local scheduling priority a message is forwarded with: the highest of the message
+    /// priority and its topic priority (see
+    /// [`SubscriptionBuilder::priority`](crate::SubscriptionBuilder::priority)).
+    ///
+    /// The received messages are of normal priority, as the priority is not propagated.
+    fn forward_priority(&self, message: &FrameMessage) -> Priority {
+        message
+            .priority()
+            .max(self.subscriptions_service.topic_priority(&message.topic))
+    }
+
+    /// The connections of the `dest` peer a message of the `topic` is forwarded to.
+    ///
+    /// If the peer subscriptions are tracked per connection (see
+    /// [`Config::connection_scoped_subscriptions`]), the message is sent to each of the peer
+    /// connections subscribed to the topic. Otherwise, or if none of its connections is
+    /// subscribed, the message is sent to any of the peer connections (`None`).
+    fn forward_connections(&self, dest: &PeerId, topic: &TopicHash) -> Vec<Option<ConnectionId>> {
+        if !self.config.connection_scoped_subscriptions() {
+            return vec![None];
+        }
+
+        match self
+            .subscriptions_service
+            .subscribed_connections(dest, topic)
+        {
+            Some(connections) if !connections.is_empty() => {
+                connections.into_iter().map(Some).collect()
+            }
+            _ => vec![None],
+        }
+    }
+
+    /// Announce the current local subscriptions to the peer, in full.
+    fn announce_subscriptions(&mut self, dest: PeerId) {
+        let topics = self
+            .subscriptions_service
+            .subscriptions()
+            .iter()
+            .cloned()
+            .collect();
+        self.subscription_announce_service
+            .do_send(SubscriptionAnnounceInEvent::AnnounceRequested { dest, topics });
+    }
+
+    /// Send the local subscriptions to a `dest` peer, and notify the subscription sync service of
+    /// the outcome.
+    ///
+    /// The subscription sync fails if the subscriptions frame exceeds the maximum frame size, or
+    /// if the peer has no enabled connection. The failed syncs are retried by the subscription
+    /// sync service.
+    fn sync_subscriptions(&mut self, dest: PeerId, topics: Vec<TopicHash>) {
+        // Skip the peers disconnected in the meantime.
+        if self.connections_service.peer_connections_count(&dest) == 0 {
+            return;
+        }
+
+        let actions = topics
+            .into_iter()
+            .map(SubscriptionAction::Subscribe)
+            .collect::<Vec<_>>();
+
+        let frame = FrameProto::from(Frame::new_with_subscriptions(actions.clone()));
+        if frame.encoded_len() > self.config.max_frame_size() {
+            tracing::warn!(%dest, "Subscriptions frame size exceeds maximum allowed size");
+            self.subscription_sync_service
+                .do_send(SubscriptionSyncInEvent::SyncFailed(dest));
+            return;
+        }
+
+        if !self.connections_service.is_active(&dest) {
+            tracing::debug!(%dest, "Peer connections disabled, subscriptions sync failed");
+            self.subscription_sync_service
+                .do_send(SubscriptionSyncInEvent::SyncFailed(dest));
+            return;
+        }
+
+        self.framing_service.do_send(FramingInEvent::Downstream(
+            FramingDownstreamInEvent::SendSubscriptionRequest {
+                dest,
+                actions,
+                tag: None,
+            },
+        ));
+        self.subscription_sync_service
+            .do_send(SubscriptionSyncInEvent::SyncSucceeded(dest));
+    }
+
+    /// Send the recently cached messages of the topic to the peer that subscribed to it, if the
+    /// topic backfill is enabled (see [`SubscriptionBuilder::backfill_on_peer_subscribe`]).
+    ///
+    /// [`SubscriptionBuilder::backfill_on_peer_subscribe`]: crate::SubscriptionBuilder::backfill_on_peer_subscribe
+    fn backfill_messages(&mut self, peer: PeerId, topic: TopicHash) {
+        let messages = self.message_cache_service.recent_messages(
+            &topic,
+            self.config.backfill_max_age(),
+            self.config.backfill_max_messages(),
+        );
+        if messages.is_empty() || self.listen_only || !self.connections_service.is_active(&peer) {
+            return;
+        }
+
+        tracing::debug!(%peer, %topic, count = messages.len(), "Backfilling messages");
+
+        let count = messages.len();
+        for message in messages {
+            let priority = self.forward_priority(&message);
+            for connection in self.forward_connections(&peer, &topic) {
+                // Account the forwarded message traffic.
+                self.traffic.record_forwarded(&topic, message.encoded_len());
+                self.activity.record_forwarded(&topic);
+
+                self.forward_scheduler.schedule(
+                    peer,
+                    priority,
+                    (connection, message.clone(), None, priority),
+                );
+            }
+        }
+
+        self.behaviour_output_mailbox
+            .push_back(ToSwarm::GenerateEvent(Event::backfill_sent(
+                peer, topic, count,
+            )));
+    }
+
+    /// Record the peer's confirmation of the local subscription to the topic, and notify the
+    /// application of the first confirmation, if enabled (see
+    /// [`Config::subscription_confirmation_events`]).
+    fn confirm_peer_subscription(&mut self, peer: PeerId, topic: &TopicHash) {
+        if !self.subscription_confirmations.confirm(peer, topic) {
+            return;
+        }
+
+        tracing::trace!(%peer, %topic, "Peer confirmed the subscription");
+        if !self.config.subscription_confirmation_events() {
+            return;
+        }
+
+        self.behaviour_output_mailbox
+            .push_back(ToSwarm::GenerateEvent(Event::peer_confirmed_subscription(
+                peer,
+                topic.clone(),
+            )));
+    }
+
+    /// Send the cached messages requested by the peer's IWANT control message, within the peer's
+    /// serving quotas (see [`Config::max_iwant_served_per_peer_per_heartbeat`]).
+    ///
+    /// The ids of the messages not in the message cache are silently ignored.
+    fn serve_iwant(&mut self, peer: PeerId, message_ids: &[MessageId]) {
+        if self.config.max_iwant_served_per_peer_per_heartbeat() == 0
+            || self.listen_only
+            || !self.connections_service.is_active(&peer)
+        {
+            return;
+        }
+
+        for message_id in message_ids {
+            let Some(message) = self.message_cache_service.message(message_id) else {
+                continue;
+            };
+            if !self.iwant_quotas.try_serve(peer, message_id) {
+                continue;
+            }
+
+            tracing::trace!(%peer, %message_id, "Serving IWANT request");
+
+            let topic = message.topic();
+            let priority = self.forward_priority(&message);
+            for connection in self.forward_connections(&peer, &topic) {
+                // Account the forwarded message traffic.
+                self.traffic.record_forwarded(&topic, message.encoded_len());
+                self.activity.record_forwarded(&topic);
+
+                self.forward_scheduler.schedule(
+                    peer,
+                    priority,
+                    (connection, message.clone(), None, priority),
+                );
+            }
+        }
+    }
+
+    /// Close all the connections of a `peer`, as requested by the protocol router.
+    ///
+    /// The requests for peers not connected, e.g., issued by a router reacting to a peer
+    /// disconnection, or already being disconnected, are ignored.
+    fn disconnect_peer(&mut self, peer: PeerId, reason: String) {
+        if self.connections_service.peer_connections_count(&peer) == 0 {
+            tracing::debug!(%peer, "Peer not connected, ignoring disconnect request");
+            return;
+        }
+
+        if !self.disconnecting_peers.insert(peer) {
+            tracing::trace!(%peer, "Peer already disconnecting, ignoring disconnect request");
+            return;
+        }
+
+        tracing::debug!(%peer, %reason, "Disconnecting peer");
+
+        self.close_mailbox.push_back(ToSwarm::CloseConnection {
+            peer_id: peer,
+            connection: CloseConnection::All,
+        });
+        self.behaviour_output_mailbox
+            .push_back(ToSwarm::GenerateEvent(Event::peer_disconnect_requested(
+                peer, reason,
+            )));
+    }
+
+    /// Record a frame send attempt to the peer in the peer send health.
+    fn record_send(&mut self, peer: PeerId, failed: bool) {
+        if let Some(change) = self.send_health.record_send(peer, failed, Instant::now()) {
+            self.on_send_health_change(peer, change);
+        }
+    }
+
+    /// Notify the application and the protocol's router service of a peer send health change.
+    fn on_send_health_change(&mut self, peer: PeerId, change: SendHealthChange) {
+        let deprioritized = match change {
+            SendHealthChange::Suspected { fail_ratio } => {
+                tracing::debug!(%peer, fail_ratio, "Asymmetric connectivity suspected");
+                self.behaviour_output_mailbox
+                    .push_back(ToSwarm::GenerateEvent(
+                        Event::asymmetric_connectivity_suspected(peer, fail_ratio),
+                    ));
+                true
+            }
+            SendHealthChange::Recovered => {
+                tracing::debug!(%peer, "Asymmetric connectivity no longer suspected");
+                false
+            }
+        };
+
+        // Pick the suspected peer last as a message forward destination.
+        if self.config.deprioritize_asymmetric_peers() {
+            self.protocol_router_service
+                .do_send(ProtocolRouterInEvent::ConnectionEvent(
+                    ProtocolRouterConnectionEvent::PeerDeprioritized {
+                        peer,
+                        deprioritized,
+                    },
+                ));
+        }
+    }
+
+    /// Publish a message to the network (see [`Behaviour::publish_with_receipt`]).
+    ///
+    /// If the topic is published in round-robin, the message is sent to the `peer`, if any, instead
+    /// of to the next peer of the topic rotation, e.g., so all the chunks of a chunked message are
+    /// sent to the same peer.
+    ///
+    /// If `deliver_locally`, the message is delivered to the local node too, along with the
+    /// `local_metadata`, if enabled (see [`Config::deliver_own_messages`]).
+    fn publish_to(
+        &mut self,
+        message: Message,
+        peer: Option<PeerId>,
+        deliver_locally: bool,
+        local_metadata: Option<Arc<dyn Any + Send + Sync>>,
+    ) -> Result<PublishReceipt, PublishError> {
+        let topic = message.topic.clone();
+
+        tracing::debug!(%topic, "Publishing message");
+
+        // Check if we are subscribed to the topic.
+        if !self.subscriptions_service.is_subscribed(&topic) {
+            return Err(PublishError::NotSubscribed(topic));
+        }
+
+        // Check if the topic is paused.
+        if self.paused_topics.contains(&topic) {
+            return Err(PublishError::TopicPaused(topic));
+        }
+
+        // Check if the local node is in listen-only mode.
+        if self.listen_only {
+            return Err(PublishError::ListenOnly);
+        }
+
+        // Check if we have connections to publish the message.
+        if self.connections_service.active_peers_count() == 0 {
+            return Err(PublishError::NoActiveConnections);
+        }
+
+        // Stamp the local node as the message author, unless anonymous.
+        let mut message = message;
+        if message.from.is_none() {
+            if let Some(author) = self.identity.author() {
+                message.from = Some(author);
+                if message.sequence_number.is_none() {
+                    message.sequence_number = Some(self.next_sequence_number());
+                }
+            }
+        }
+
+        // Stamp the message absolute expiry time, if any.
+        if let Some(ttl) = message.ttl.take() {
+            message.data = ttl::encode(SystemTime::now() + ttl, &message.data);
+        }
+
+        let message = FrameMessage::from(message);
+
+        // Check the message, as sent on the wire, fits the publish size limit.
+        if let Some(max_size) = self.config.max_publish_size() {
+            let size = message.encoded_len();
+            if size > max_size {
+                return Err(PublishError::MessageTooLarge { size, max_size });
+            }
+        }
+
+        // Check the topic's message id function computes the message id.
+        let message_id = match self.message_id_service.try_message_id(None, &message) {
+            Ok(message_id) => message_id,
+            Err(err) => return Err(PublishError::MessageIdComputation(err)),
+        };
+
+        // Select the peer the round-robin topic message is sent to.
+        let peer = if self.round_robin_rotations.is_tracked(&topic) {
+            let candidates = self
+                .connections_service
+                .active_peers()
+                .into_iter()
+                .filter(|peer| self.subscriptions_service.is_peer_subscribed(peer, &topic))
+                .collect::<Vec<_>>();
+            match peer {
+                Some(peer) if candidates.contains(&peer) => Some(peer),
+                _ => self.round_robin_rotations.next_peer(&topic, candidates),
+            }
+        } else {
+            None
+        };
+        let message = Rc::new(message);
+        if let Some(peer) = peer {
+            tracing::debug!(%topic, %peer, "Publishing round-robin message");
+            self.round_robin_destinations
+                .insert(Rc::as_ptr(&message), peer);
+        }
+
+        // Account the published message traffic.
+        self.traffic.record_published(&topic, message.encoded_len());
+
+        // Deliver the message to the local node, if enabled, unless already seen, e.g.,
+        // re-published while still cached.
+        if deliver_locally
+            && self.config.deliver_own_messages()
+            && !self.message_cache_service.contains(&topic, &message_id)
+        {
+            self.behaviour_output_mailbox
+                .push_back(ToSwarm::GenerateEvent(Event::local_message_received(
+                    self.local_peer_id,
+                    Message::from((*message).clone()),
+                    message_id.clone(),
+                    local_metadata,
+                )));
+        }
+
+        // Notify the message id service of the published message.
+        self.message_id_service
+            .do_send(MessageIdInEvent::MessageEvent(
+                MessageIdMessageEvent::Published {
+                    message,
+                    context: MessageContext::published(&topic),
+                },
+            ));
+
+        Ok(PublishReceipt::new(message_id, peer))
+    }
+
+    /// Check the subscribed topics silence watchdog, if enabled, and notify the application if it
+    /// fired.
+    fn check_silence_watchdog(&mut self) {
+        let Some(watchdog) = self.silence_watchdog.as_mut() else {
+            return;
+        };
+
+        let active_peers = self.connections_service.active_peers();
+        let watched = self
+            .subscriptions_service
+            .subscriptions()
+            .iter()
+            .map(|topic| {
+                let peers = active_peers
+                    .iter()
+                    .filter(|peer| self.subscriptions_service.is_peer_subscribed(peer, topic))
+                    .copied()
+                    .collect();
+                (topic.clone(), peers)
+            })
+            .collect();
+
+        if let Some(alert) = watchdog.check(watched, Instant::now()) {
+            tracing::warn!(
+                topics = ?alert.topics,
+                peers = alert.peers.len(),
+                silent_for = ?alert.silent_for,
+                "Subscribed topics silent"
+            );
+            self.behaviour_output_mailbox
+                .push_back(ToSwarm::GenerateEvent(Event::subscribed_but_silent(
+                    alert.topics,
+                    alert.peers,
+                    alert.silent_for,
+                )));
+        }
+    }
+
+    /// Remove the frames queued in the connection handler mailbox for the given peer.
+    fn purge_queued_frames(&mut self, peer: &PeerId) {
+        let queued = self.conn_handler_mailbox.len();
+        self.conn_handler_mailbox
+            .retain(|ev| !matches!(ev, ToSwarm::NotifyHandler { peer_id, .. } if peer_id == peer));
+
+        let purged = queued - self.conn_handler_mailbox.len();
+        if purged > 0 {
+            tracing::debug!(%peer, purged, "Peer disconnected, dropping queued frames");
+            self.dropped_frames_disconnected += purged as u64;
+        }
+    }
+
+    /// Poll the heartbeat, the timers and the services, dispatching the services events and
+    /// filling the mailboxes.
+    fn poll_services(&mut self, cx: &mut Context<'_>) {
+        // Unregister the local consumers whose handle was dropped.
+        for topic in self.consumers.unregister_released() {
+            self.release_consumers_topic(topic);
+        }
+
+        // Poll the heartbeat and notify the protocol's router service, along with the recently
+        // seen messages history.
+        while self.heartbeat.poll_next_unpin(cx).is_ready() {
+            if self.config.history_gossip() > 0 {
+                let history = self
+                    .subscriptions_service
+                    .subscriptions()
+                    .iter()
+                    .map(|topic| {
+                        let ids = self
+                            .message_cache_service
+                            .history(topic, self.config.history_gossip());
+                        (topic.clone(), ids)
+                    })
+                    .collect();
+                self.protocol_router_service
+                    .do_send(ProtocolRouterInEvent::MessageHistory(history));
+            }
+
+            // Sync the protocol router state, if due.
+            if let Some(interval) = self.config.router_sync_interval() {
+                if self.last_router_sync.elapsed() >= interval {
+                    self.force_router_sync();
+                }
+            }
+
+            self.protocol_router_service
+                .do_send(ProtocolRouterInEvent::Heartbeat);
+            self.subscriptions_service
+                .do_send(SubscriptionsInEvent::Heartbeat);
+
+            // Notify the application of the requests that timed out.
+            for request_id in self.pending_requests.expire(Instant::now()) {
+                tracing::debug!(%request_id, "Request timed out");
+                self.behaviour_output_mailbox
+                    .push_back(ToSwarm::GenerateEvent(Event::request_timed_out(request_id)));
+            }
+
+            // Notify the application of the subscription refreshes whose window closed.
+            for (peer, topics_learned) in self.subscription_refreshes.complete(Instant::now()) {
+                tracing::debug!(%peer, ?topics_learned, "Subscription refresh completed");
+                self.behaviour_output_mailbox
+                    .push_back(ToSwarm::GenerateEvent(
+                        Event::subscription_refresh_completed(peer, topics_learned),
+                    ));
+            }
+
+            // Notify the application if the subscribed topics fell silent.
+            self.check_silence_watchdog();
+
+            // Reset the IWANT serving quotas, and drop the serve counts of the evicted messages.
+            let message_cache = &self.message_cache_service;
+            self.iwant_quotas
+                .heartbeat(|message_id| message_cache.message(message_id).is_some());
+
+            // Drop the asymmetric connectivity suspicions that no longer hold.
+            for (peer, change) in self.send_health.heartbeat(Instant::now()) {
+                self.on_send_health_change(peer, change);
+            }
+
+            // Stop clamping the frames sent to the peers whose suspected frame size limit decayed.
+            for (peer, connections) in self.peer_frame_limits.expire(Instant::now()) {
+                tracing::debug!(%peer, "Suspected frame size limit decayed");
+                self.framing_service.do_send(FramingInEvent::Downstream(
+                    FramingDownstreamInEvent::PeerFrameLimitChanged { peer, limit: None },
+                ));
+                for connection in connections {
+                    self.conn_handler_mailbox.push_back(ToSwarm::NotifyHandler {
+                        peer_id: peer,
+                        handler: NotifyHandler::One(connection),
+                        event: HandlerCommand::SetFrameLimit(None),
+                    });
+                }
+            }
+        }
+
+        // Poll the activity summaries timer and notify the application of the elapsed window
+        // topics activity.
+        if let Some(timer) = self.activity_summary_timer.as_mut() {
+            while timer.poll_next_unpin(cx).is_ready() {
+                let summary = self.activity.take_summary(Instant::now());
+                self.behaviour_output_mailbox
+                    .push_back(ToSwarm::GenerateEvent(Event::topic_activity_summary(
+                        summary.window,
+                        summary.per_topic,
+                        summary.others,
+                    )));
+            }
+        }
+
+        // Poll the egress pacer refill timer, and release the held back frames the refilled
+        // budget allows.
+        if let Some(timer) = self.egress_timer.as_mut() {
+            while timer.poll_next_unpin(cx).is_ready() {}
+        }
+        if let Some(pacer) = self.egress_pacer.as_mut() {
+            for (_, event) in pacer.release(Instant::now()) {
+                self.conn_handler_mailbox.push_back(event);
+            }
+        }
+
+        // Poll the connections service.
+        while let Poll::Ready(conn_event) = self.connections_service.poll(cx) {
+            // Drop the frames still queued for a disconnected peer, its subscription refreshes and
+            // its suspected frame size limit.
+            if let ConnectionsOutEvent::PeerDisconnected(peer) = &conn_event {
+                self.disconnecting_peers.remove(peer);
+                self.subscription_refreshes.peer_disconnected(peer);
+                self.pending_flushes.peer_disconnected(peer);
+                self.send_health.peer_disconnected(peer);
+                self.iwant_quotas.peer_disconnected(peer);
+                self.subscription_confirmations.peer_disconnected(peer);
+                if self.peer_frame_limits.limit(peer).is_some() {
+                    self.peer_frame_limits.peer_disconnected(peer);
+                    self.framing_service.do_send(FramingInEvent::Downstream(
+                        FramingDownstreamInEvent::PeerFrameLimitChanged {
+                            peer: *peer,
+                            limit: None,
+                        },
+                    ));
+                }
+                self.purge_queued_frames(peer);
+                if let Some(pacer) = self.egress_pacer.as_mut() {
+                    let dropped = pacer.peer_disconnected(peer);
+                    if dropped > 0 {
+                        tracing::debug!(%peer, dropped, "Peer disconnected, dropping paced frames");
+                        self.dropped_frames_disconnected += dropped as u64;
+                    }
+                }
+                self.unauthorized.peer_disconnected(peer);
+
+                let dropped = self.forward_scheduler.peer_disconnected(peer);
+                if dropped > 0 {
+                    tracing::debug!(%peer, dropped, "Peer disconnected, dropping pending forwards");
+                }
+            }
+
+            // Start the forwarding warm-up on the first connection.
+            if let ConnectionsOutEvent::NewPeerConnected { .. } = &conn_event {
+                self.forward_warmup.start();
+            }
+
+            // Notify the services of the connection event, e.g., so they drop the disconnected
+            // peer's pending subscription sync retries, in-flight subscriptions announcement,
+            // framing statistics and message cache state.
+            dispatch!(conn_event =>
+                self.subscriptions_service,
+                self.subscription_sync_service,
+                self.subscription_announce_service,
+                self.framing_service,
+                self.message_cache_service,
+                self.dialer_service,
+                self.protocol_router_service,
+            );
+        }
+
+        // Poll the forwarding warm-up timer.
+        if self.forward_warmup.poll_completed(cx).is_ready() {
+            self.behaviour_output_mailbox
+                .push_back(ToSwarm::GenerateEvent(Event::WarmupCompleted));
+        }
+
+        // The peer unsubscriptions processed in this polling round. The protocol router is notified
+        // of them once the messages published before they were processed are routed (see
+        // `Behaviour::publish`).
+        let mut peer_unsubscriptions = Vec::new();
+
+        // Poll the subscriptions service.
+        while let Poll::Ready(sub_event) = self.subscriptions_service.poll(cx) {
+            // Notify the services of the subscriptions event.
+            dispatch!(sub_event =>
+                self.ordering_service,
+                self.message_cache_service,
+                self.message_id_service,
+                self.protocol_router_service,
+                self.subscriptions_debounce_service,
+                self.subscription_announce_service,
+                self.dialer_service,
+            );
+
+            match sub_event {
+                SubscriptionsOutEvent::Subscribed(sub) => {
+                    // Start accounting the topic traffic.
+                    self.traffic.track_topic(sub.topic.clone());
+                    self.provenance.track_topic(sub.topic.clone());
+                    self.activity.track_topic(sub.topic.clone());
+
+                    // Stop forwarding the topic received messages, if disabled.
+                    if !sub.forwarding {
+                        self.non_forwarding_topics.insert(sub.topic.clone());
+                    }
+
+                    // Start the topic publish rotation, if published in round-robin.
+                    if sub.delivery_mode == DeliveryMode::RoundRobinPublish {
+                        self.round_robin_rotations.track_topic(sub.topic.clone());
+                    }
+
+                    // Publish the request/response messages waiting for the reply topic
+                    // subscription.
+                    for message in self
+                        .pending_reqres_messages
+                        .remove(&sub.topic)
+                        .unwrap_or_default()
+                    {
+                        if let Err(err) = self.publish(message) {
+                            tracing::debug!(topic = %sub.topic, "Request/response message not published: {err}");
+                        }
+                    }
+
+                    // Notify the application of the processed subscription. The event is emitted
+                    // after the announcement frames are queued (see the mailboxes order below).
+                    self.behaviour_output_mailbox
+                        .push_back(ToSwarm::GenerateEvent(Event::subscribed(sub.topic)));
+                }
+                SubscriptionsOutEvent::Unsubscribed { topic, info } => {
+                    tracing::debug!(%topic, owner = ?info.owner, "Unsubscribed from topic");
+
+                    // Stop accounting the topic traffic individually.
+                    self.traffic.untrack_topic(&topic);
+                    self.provenance.untrack_topic(&topic);
+                    self.activity.untrack_topic(&topic);
+                    if let Some(watchdog) = self.silence_watchdog.as_mut() {
+                        watchdog.untrack_topic(&topic);
+                    }
+                    self.subscription_confirmations.topic_unsubscribed(&topic);
+                    self.non_forwarding_topics.remove(&topic);
+                    self.round_robin_rotations.untrack_topic(&topic);
+
+                    // Notify the debounce service of the subscription update, unless the
+                    // unsubscription is flushed to the peers on its own.
+                    if !self.pending_flushes.topic_unsubscribed(&topic) {
+                        self.subscriptions_debounce_service
+                            .do_send(SubscriptionAction::Unsubscribe(topic.clone()));
+                    }
+
+                    // Notify the dialer service of the local subscriptions change.
+                    self.dialer_service
+                        .do_send(DialerInEvent::LocalSubscriptionsChanged {
+                            subscribed: !self.subscriptions_service.subscriptions().is_empty(),
+                        });
+
+                    // Notify the application of the processed unsubscription.
+                    self.behaviour_output_mailbox
+                        .push_back(ToSwarm::GenerateEvent(Event::unsubscribed(topic)));
+                }
+                SubscriptionsOutEvent::PeerSubscribed { peer, topic } => {
+                    tracing::debug!(src = %peer, %topic, "Peer subscribed");
+
+                    // Attribute the topic to the peer subscription refresh, if pending.
+                    self.subscription_refreshes.learned(&peer, [topic.clone()]);
+
+                    // Send the recently cached topic messages to the peer, if enabled.
+                    self.backfill_messages(peer, topic);
+                }
+                SubscriptionsOutEvent::PeerUnsubscribed { peer, topic } => {
+                    tracing::debug!(src = %peer, %topic, "Peer unsubscribed");
+
+                    // Hold the peer unsubscription event back until the published messages are
+                    // routed.
+                    peer_unsubscriptions
+                        .push(ProtocolRouterSubscriptionEvent::PeerUnsubscribed { peer, topic });
+                }
+                SubscriptionsOutEvent::PeerSubscribedMany { peer, topics } => {
+                    tracing::debug!(src = %peer, ?topics, "Peer subscribed");
+
+                    // Attribute the topics to the peer subscription refresh, if pending.
+                    self.subscription_refreshes
+                        .learned(&peer, topics.iter().cloned());
+
+                    // Send the recently cached topics messages to the peer, if enabled.
+                    for topic in topics {
+                        self.backfill_messages(peer, topic);
+                    }
+                }
+                SubscriptionsOutEvent::PeerUnsubscribedMany { peer, topics } => {
+                    tracing::debug!(src = %peer, ?topics, "Peer unsubscribed");
+
+                    // Hold the peer unsubscriptions event back until the published messages are
+                    // routed.
+                    peer_unsubscriptions.push(
+                        ProtocolRouterSubscriptionEvent::PeerUnsubscribedMany { peer, topics },
+                    );
+                }
+                SubscriptionsOutEvent::KnownPeerSubscribed { peer, topic } => {
+                    tracing::trace!(%peer, %topic, "Known remote peer subscribed");
+                }
+                SubscriptionsOutEvent::KnownPeerForgotten(_) => {}
+                SubscriptionsOutEvent::PeerFlapping {
+                    peer,
+                    topic,
+                    transitions_per_min,
+                } => {
+                    tracing::debug!(%peer, %topic, transitions_per_min, "Peer flapping subscription");
+                    self.behaviour_output_mailbox
+                        .push_back(ToSwarm::GenerateEvent(Event::peer_flapping(
+                            peer,
+                            topic,
+                            transitions_per_min,
+                        )));
+                }
+                SubscriptionsOutEvent::SendSubscriptions { dest, topics } => {
+                    // The subscriptions are announced to the peer by the announce service.
+                    tracing::debug!(%dest, ?topics, "Announcing subscriptions");
+                }
+            }
+        }
+
+        // Send the flushed unsubscriptions whose topics were all unsubscribed to the active peers,
+        // tagged with the flush id.
+        for (flush_id, topics) in self.pending_flushes.ready() {
+            let peers = if topics.is_empty() {
+                Vec::new()
+            } else {
+                self.connections_service.active_peers()
+            };
+
+            tracing::debug!(%flush_id, ?topics, "Sending flushed unsubscription");
+
+            let actions = topics
+                .into_iter()
+                .map(SubscriptionAction::Unsubscribe)
+                .collect::<Vec<_>>();
+            for dest in &peers {
+                self.framing_service.do_send(FramingInEvent::Downstream(
+                    FramingDownstreamInEvent::SendSubscriptionRequest {
+                        dest: *dest,
+                        actions: actions.clone(),
+                        tag: Some(flush_id.as_u64()),
+                    },
+                ));
+            }
+            self.pending_flushes.sent(&flush_id, peers);
+        }
+
+        // Poll the subscriptions debounce service.
+        while let Poll::Ready(sub_action) = self.subscriptions_debounce_service.poll(cx) {
+            // Send the subscription update to all active peers.
+            tracing::debug!(?sub_action, "Sending subscription update");
+
+            for dest in self.connections_service.active_peers() {
+                // Notify the framing service of the subscription update request.
+                self.framing_service.do_send(FramingInEvent::Downstream(
+                    FramingDownstreamInEvent::SendSubscriptionRequest {
+                        dest,
+                        actions: vec![sub_action.clone()],
+                        tag: None,
+                    },
+                ));
+            }
+        }
+
+        // Poll the subscription announce and sync services. The subscriptions batches sync
+        // outcome is notified to the sync service, and the retried syncs are announced again, so
+        // both services are polled until no sync is retried.
+        loop {
+            // Poll the subscription announce service.
+            while let Poll::Ready(announce_event) = self.subscription_announce_service.poll(cx) {
+                match announce_event {
+                    SubscriptionAnnounceOutEvent::SendSubscriptions { dest, topics } => {
+                        // Send the subscriptions batch to the peer.
+                        tracing::debug!(%dest, ?topics, "Sending subscriptions");
+                        self.sync_subscriptions(dest, topics);
+                    }
+                }
+            }
+
+            // Poll the subscription sync service.
+            let mut retried = false;
+            while let Poll::Ready(sync_event) = self.subscription_sync_service.poll(cx) {
+                match sync_event {
+                    SubscriptionSyncOutEvent::RetrySync(dest) => {
+                        // Announce the current local subscriptions to the peer again.
+                        tracing::debug!(%dest, "Retrying subscriptions sync");
+                        self.announce_subscriptions(dest);
+                        retried = true;
+                    }
+                    SubscriptionSyncOutEvent::SyncFailed(peer) => {
+                        tracing::warn!(%peer, "Subscriptions sync failed");
+
+                        self.behaviour_output_mailbox
+                            .push_back(ToSwarm::GenerateEvent(Event::subscription_sync_failed(
+                                peer,
+                            )));
+                    }
+                }
+            }
+
+            if !retried {
+                break;
+            }
+        }
+
+        // Poll the dialer service.
+        while let Poll::Ready(event) = self.dialer_service.poll(cx) {
+            match event {
+                DialerOutEvent::Dial { peer, addrs } => {
+                    tracing::debug!(%peer, ?addrs, "Dialing known peer");
+
+                    self.dial_mailbox.push_back(ToSwarm::Dial {
+                        opts: DialOpts::peer_id(peer).addresses(addrs).build(),
+                    });
+                }
+            }
+        }
+
+        // Poll the message id service.
+        while let Poll::Ready(event) = self.message_id_service.poll(cx) {
+            match event {
+                MessageIdOutEvent::MessagePublished {
+                    message,
+                    message_id,
+                    mut context,
+                } => {
+                    let dest = self.round_robin_destinations.remove(&Rc::as_ptr(&message));
+
+                    // If message has already seen before, drop it.
+                    let topic = message.topic();
+                    if self.message_cache_service.contains(&topic, &message_id) {
+                        self.record_dead_letter(
+                            DeadLetterStage::CacheDuplicate,
+                            "published message already seen",
+                            || format!("published message {message_id}"),
+                        );
+                        continue;
+                    }
+                    context.record_stage(MessageStage::Deduplicated);
+
+                    // Notify the application if no connected peer is subscribed to the topic.
+                    if self.config.warn_on_no_subscribers()
+                        && self.subscriptions_service.topic_subscribers_count(&topic) == 0
+                    {
+                        tracing::warn!(%topic, "Message published without subscribed peers");
+                        self.behaviour_output_mailbox
+                            .push_back(ToSwarm::GenerateEvent(
+                                Event::published_without_subscribers(topic, message_id.clone()),
+                            ));
+                    }
+
+                    // Notify the message cache service of the published message.
+                    self.message_cache_service
+                        .do_send(MessageCacheInEvent::MessageEvent(
+                            MessageCacheMessageEvent::MessagePublished {
+                                message: message.clone(),
+                                message_id: message_id.clone(),
+                            },
+                        ));
+
+                    // Notify the protocol's service of the published message.
+                    self.track_routed_message(&message, context);
+                    self.protocol_router_service
+                        .do_send(ProtocolRouterInEvent::MessageEvent(
+                            ProtocolRouterMessageEvent::MessagePublished {
+                                message,
+                                message_id,
+                                dest,
+                            },
+                        ));
+                }
+                MessageIdOutEvent::MessageReceived(mut ctx) => {
+                    let src = ctx.src();
+                    let topic = ctx.topic();
+                    let message_id = ctx.message_id();
+
+                    // If message has already seen before, notify the message cache service of
+                    // the duplicate and drop it.
+                    if self.message_cache_service.contains(topic, message_id) {
+                        self.record_dead_letter(
+                            DeadLetterStage::CacheDuplicate,
+                            "received message already seen",
+                            || format!("message {message_id} from {src}"),
+                        );
+                        self.activity.record_duplicate(topic);
+                        self.message_cache_service
+                            .do_send(MessageCacheInEvent::MessageEvent(
+                                MessageCacheMessageEvent::DuplicateMessageReceived {
+                                    src,
+                                    topic: topic.clone(),
+                                    message_id: message_id.clone(),
+                                },
+                            ));
+                        continue;
+                    }
+
+                    // Drop our own messages no longer in the seen cache.
+                    if ctx.message().author() == Some(self.local_peer_id) {
+                        tracing::debug!(%src, "Dropping self-authored message");
+                        continue;
+                    }
+
+                    ctx.record_stage(MessageStage::Deduplicated);
+
+                    // Notify the message cache service of the received message.
+                    self.message_cache_service
+                        .do_send(MessageCacheInEvent::MessageEvent(
+                            MessageCacheMessageEvent::MessageReceived(ctx.clone()),
+                        ));
+
+                    // Drop the messages received on a paused topic, once cached, so they are
+                    // neither delivered nor forwarded.
+                    if self.paused_topics.contains(ctx.topic()) {
+                        self.record_dead_letter(
+                            DeadLetterStage::TopicPaused,
+                            "received message topic paused",
+                            || format!("message {} from {src}", ctx.message_id()),
+                        );
+                        continue;
+                    }
+
+                    if let Some((header, _)) = ChunkHeader::decode(&ctx.message().data()) {
+                        // Notify the reassembly service of the received chunk. The chunk is not
+                        // notified to the application.
+                        self.reassembly_service
+                            .do_send(ReassemblyInEvent::ChunkReceived {
+                                src,
+                                message: ctx.message().clone(),
+                                header,
+                            });
+                    } else {
+                        // Notify the ordering service of the received message. The message is
+                        // notified to the application once released.
+                        self.ordering_service
+                            .do_send(OrderingInEvent::MessageReceived(ctx.clone()));
+                    }
+
+                    // Do not notify the protocol's service of the received message while warming
+                    // up, so it is not forwarded.
+                    if self.forward_warmup.is_warming_up() {
+                        tracing::trace!(%src, message_id = %ctx.message_id(), "Message not forwarded, warming up");
+                        continue;
+                    }
+
+                    // Do not notify the protocol's service of the messages received on a topic
+                    // with the forwarding disabled, so they are not relayed.
+                    if self.non_forwarding_topics.contains(ctx.topic()) {
+                        tracing::trace!(%src, message_id = %ctx.message_id(), "Message not forwarded, topic forwarding disabled");
+                        continue;
+                    }
+
+                    // Notify the protocol's service of the received message.
+                    let (src, message, message_id, context) = ctx.into_parts();
+                    self.track_routed_message(&message, context);
+                    self.protocol_router_service
+                        .do_send(ProtocolRouterInEvent::MessageEvent(
+                            ProtocolRouterMessageEvent::MessageReceived {
+                                src,
+                                message,
+                                message_id,
+                                exclude: Default::default(),
+                            },
+                        ));
+                }
+            }
+        }
+
+        // Notify the protocol's service of the peer unsubscriptions, now that the messages
+        // published before they were processed are handed to it.
+        for event in peer_unsubscriptions {
+            self.protocol_router_service
+                .do_send(ProtocolRouterInEvent::SubscriptionEvent(event));
+        }
+
+        // Poll the message cache service.
+        while let Poll::Ready(event) = self.message_cache_service.poll(cx) {
+            match event {
+                MessageCacheOutEvent::DuplicateFloodDetected { peer, message_id } => {
+                    tracing::debug!(%peer, "Peer flagged as duplicate flooder");
+
+                    // Start the peer's cooldown period, if enabled.
+                    if let Some(cooldown) = self.config.duplicate_flood_cooldown() {
+                        self.flood_cooldowns.insert(peer, Instant::now() + cooldown);
+                    }
+
+                    // Notify the behaviour output mailbox of the misbehaving peer.
+                    self.behaviour_output_mailbox
+                        .push_back(ToSwarm::GenerateEvent(Event::peer_misbehaving(
+                            peer,
+                            MisbehaviourReason::duplicate_flood(message_id),
+                        )));
+                }
+                MessageCacheOutEvent::PublishedMessageEchoed {
+                    message_id,
+                    echoed_by,
+                } => {
+                    if !self.config.detect_echo() {
+                        continue;
+                    }
+
+                    tracing::debug!(%echoed_by, %message_id, "Published message echoed");
+
+                    // Notify the behaviour output mailbox of the published message echo.
+                    self.behaviour_output_mailbox
+                        .push_back(ToSwarm::GenerateEvent(Event::published_message_echoed(
+                            message_id, echoed_by,
+                        )));
+                }
+                MessageCacheOutEvent::DedupPersistenceFailed { error } => {
+                    tracing::warn!("Dedup store failed: {}", error);
+
+                    // Notify the behaviour output mailbox of the dedup store failure.
+                    let error = Rc::try_unwrap(error)
+                        .unwrap_or_else(|error| io::Error::new(error.kind(), error.to_string()));
+                    self.behaviour_output_mailbox
+                        .push_back(ToSwarm::GenerateEvent(Event::dedup_persistence_failed(
+                            error,
+                        )));
+                }
+            }
+        }
+
+        // Poll the reassembly service.
+        while let Poll::Ready(event) = self.reassembly_service.poll(cx) {
+            match event {
+                ReassemblyOutEvent::TransferCompleted {
+                    src,
+                    message,
+                    transfer_id,
+                } => {
+                    tracing::debug!(%src, "Chunked transfer completed");
+
+                    // Notify the application of the reassembled message.
+                    self.notify_message_received(
+                        src,
+                        message.into(),
+                        MessageId::new_from_slice(&transfer_id),
+                    );
+                }
+            }
+        }
+
+        // Poll the ordering service.
+        while let Poll::Ready(event) = self.ordering_service.poll(cx) {
+            match event {
+                OrderingOutEvent::MessageReleased(mut ctx) => {
+                    self.last_delivery_latency = Some(ctx.record_stage(MessageStage::Delivered));
+                    let (src, message, message_id, _) = ctx.into_parts();
+
+                    // Drop the messages held back before their topic was paused.
+                    if self.paused_topics.contains(&message.topic()) {
+                        self.record_dead_letter(
+                            DeadLetterStage::TopicPaused,
+                            "released message topic paused",
+                            || format!("message {message_id} from {src}"),
+                        );
+                        continue;
+                    }
+
+                    let message = Message::from((*message).clone());
+
+                    // Notify the responses to the local node requests, and drop the other
+                    // responses.
+                    if let Some(response) = ResponseEnvelope::decode(&message.data) {
+                        if message.topic == reqres::reply_topic(&self.local_peer_id)
+                            && self.pending_requests.complete(&response.request_id)
+                        {
+                            self.behaviour_output_mailbox
+                                .push_back(ToSwarm::GenerateEvent(Event::response_received(
+                                    response.request_id,
+                                    message.from.unwrap_or(src),
+                                    response.payload,
+                                )));
+                        } else {
+                            tracing::trace!(topic = %message.topic, request_id = %response.request_id, "Dropping response");
+                        }
+                        continue;
+                    }
+
+                    // Notify the application of the received message.
+                    self.notify_message_received(src, message, message_id);
+                }
+                OrderingOutEvent::GapSkipped {
+                    topic,
+                    source,
+                    missing,
+                } => {
+                    tracing::debug!(%topic, %source, ?missing, "Skipping missing messages");
+
+                    // Notify the behaviour output mailbox of the skipped messages.
+                    self.behaviour_output_mailbox
+                        .push_back(ToSwarm::GenerateEvent(Event::message_gap(
+                            topic, source, missing,
+                        )));
+                }
+            }
+        }
+
+        // Poll the protocol service.
+        while let Poll::Ready(event) = self.protocol_router_service.poll(cx) {
+            // Notify the framing service of the router event, e.g., the control messages to send.
+            dispatch!(event => self.framing_service);
+
+            match event {
+                ProtocolRouterOutEvent::ForwardMessage { message, dest } => {
+                    // Do not forward the messages that expired since they were accepted.
+                    if ttl::is_expired(
+                        &message.data(),
+                        SystemTime::now(),
+                        self.config.message_ttl_clock_skew(),
+                    ) {
+                        tracing::debug!("Dropping expired message");
+                        continue;
+                    }
+
+                    // Do not forward any message in listen-only mode.
+                    if self.listen_only {
+                        tracing::trace!("Listen-only mode, dropping message forward");
+                        self.forward_scheduler.discard(dest.len());
+                        continue;
+                    }
+
+                    let topic = message.topic();
+                    let message_len = message.encoded_len();
+                    let priority = self.forward_priority(&message);
+
+                    // Follow the message lifecycle context, if the message was handed to the
+                    // router by the behaviour.
+                    let mut context = self
+                        .routed_messages
+                        .get(&Rc::as_ptr(&message))
+                        .map(|(_, context)| context.clone());
+                    if let Some(context) = context.as_mut() {
+                        self.last_forward_latency =
+                            Some(context.record_stage(MessageStage::Forwarded));
+                    }
+
+                    for dest in dest {
+                        // Skip the peers without usable connections, e.g., disabled connections.
+                        if !self.connections_service.is_active(&dest) {
+                            tracing::trace!(%dest, "Peer not active, skipping forward");
+                            continue;
+                        }
+
+                        for connection in self.forward_connections(&dest, &topic) {
+                            // Account the forwarded message traffic.
+                            self.traffic.record_forwarded(&topic, message_len);
+                            self.activity.record_forwarded(&topic);
+
+                            // Schedule the message forward, released to the framing service below.
+                            self.forward_scheduler.schedule(
+                                dest,
+                                priority,
+                                (connection, message.clone(), context.clone(), priority),
+                            );
+                        }
+                    }
+                }
+                ProtocolRouterOutEvent::SendControlMessage { dest, .. } => {
+                    // The control message is sent to the peer by the framing service.
+                    tracing::trace!(%dest, "Sending control message");
+                }
+                ProtocolRouterOutEvent::DisconnectPeer { peer, reason } => {
+                    self.disconnect_peer(peer, reason);
+                }
+            }
+        }
+
+        // The router processed all the messages handed to it, drop their lifecycle contexts.
+        self.routed_messages.clear();
+
+        // Release the scheduled message forwards, up to the poll cycle limits, to the framing
+        // service.
+        for (dest, (connection, message, context, priority)) in self.forward_scheduler.release() {
+            self.framing_service.do_send(FramingInEvent::Downstream(
+                FramingDownstreamInEvent::ForwardMessage {
+                    dest,
+                    connection,
+                    message,
+                    context,
+                    priority,
+                },
+            ));
+        }
+
+        // Poll the framing service.
+        while let Poll::Ready(event) = self.framing_service.poll(cx) {
+            // Notify the protocol's router service of the framing event, e.g., the received
+            // control messages.
+            dispatch!(event => self.protocol_router_service);
+
+            match event {
+                FramingOutEvent::Downstream(FramingDownstreamOutEvent::SendFrame {
+                    dest,
+                    connection,
+                    frame,
+                    tag,
+                    priority,
+                    control,
+                }) => {
+                    // Send the frame to the peer.
+                    self.send_frame(dest, connection, frame, tag, priority, control);
+                }
+                FramingOutEvent::Upstream(ev) => match ev {
+                    FramingUpstreamOutEvent::MessageReceived(mut ctx) => {
+                        // Handle the messages received on an alias of a local subscription as
+                        // messages of the subscription topic.
+                        if !self.subscriptions_service.is_subscribed(ctx.topic()) {
+                            if let Some(topic) =
+                                self.subscriptions_service.alias_topic(ctx.topic()).cloned()
+                            {
+                                ctx.set_topic(topic);
+                            }
+                        }
+
+                        let src = ctx.src();
+                        let message = ctx.message();
+                        let topic = ctx.topic();
+
+                        // Account the received message traffic.
+                        self.traffic.record_received(topic, message.encoded_len());
+                        self.activity.record_received(topic, message.encoded_len());
+
+                        // Skip the message if we are not subscribed to the topic.
+                        if !self.subscriptions_service.is_subscribed(topic) {
+                            self.record_dead_letter(
+                                DeadLetterStage::NotSubscribed,
+                                "topic not subscribed",
+                                || format!("message on topic {topic} from {src}"),
+                            );
+
+                            // Notify the peers publishing on a local subscription topic string
+                            // hashed differently.
+                            if let Some(local_topic) =
+                                self.subscriptions_service.hash_counterpart(topic)
+                            {
+                                if self
+                                    .topic_hash_mismatch_notifications
+                                    .notify(topic, Instant::now())
+                                {
+                                    tracing::debug!(%src, %topic, %local_topic, "Suspected topic hash mismatch");
+                                    self.behaviour_output_mailbox.push_back(
+                                        ToSwarm::GenerateEvent(
+                                            Event::topic_hash_mismatch_suspected(
+                                                topic.clone(),
+                                                local_topic.clone(),
+                                            ),
+                                        ),
+                                    );
+                                }
+                            }
+                            continue;
+                        }
+
+                        // Re-arm the silence watchdog.
+                        if let Some(watchdog) = self.silence_watchdog.as_mut() {
+                            watchdog.record_received(topic, Instant::now());
+                        }
+
+                        // The peer forwarding the message knows about the local subscription.
+                        self.confirm_peer_subscription(src, topic);
+
+                        // Drop the message if its propagation source, or its claimed author, is not
+                        // authorized to publish on the topic.
+                        let unauthorized = [Some(src), message.author()]
+                            .into_iter()
+                            .flatten()
+                            .find(|peer| !self.is_authorized(peer, topic, TopicAction::Publish));
+                        if let Some(peer) = unauthorized {
+                            tracing::debug!(%src, %peer, %topic, "Dropping unauthorized message");
+                            self.record_dead_letter(
+                                DeadLetterStage::Unauthorized,
+                                "peer not authorized to publish",
+                                || format!("message on topic {topic} from {src} by {peer}"),
+                            );
+                            if self
+                                .unauthorized
+                                .record_message(peer, topic, Instant::now())
+                            {
+                                self.behaviour_output_mailbox
+                                    .push_back(ToSwarm::GenerateEvent(
+                                        Event::unauthorized_message(peer, topic.clone()),
+                                    ));
+                            }
+                            continue;
+                        }
+
+                        // Skip our own messages reflected back by a remote peer, unless the echo
+                        // detection is enabled. The echoes are detected as duplicates of the
+                        // published messages.
+                        if message.author() == Some(self.local_peer_id)
+                            && !self.config.detect_echo()
+                        {
+                            tracing::debug!(%src, "Dropping self-authored message");
+                            continue;
+                        }
+
+                        // Flag the unsigned messages claiming to be authored by a directly
+                        // connected peer, but propagated by another peer.
+                        if self.config.verify_from_matches_signature() {
+                            let claimed_from = message.author().filter(|author| {
+                                *author != src
+                                    && message.signature().is_none()
+                                    && self.connections_service.is_active(author)
+                            });
+                            if let Some(claimed_from) = claimed_from {
+                                let message_id =
+                                    self.message_id_service.message_id(Some(&src), message);
+                                tracing::debug!(
+                                    %claimed_from,
+                                    %src,
+                                    %message_id,
+                                    "Suspected spoofed message"
+                                );
+                                self.behaviour_output_mailbox
+                                    .push_back(ToSwarm::GenerateEvent(
+                                        Event::suspected_spoofed_message(
+                                            claimed_from,
+                                            src,
+                                            message_id.clone(),
+                                        ),
+                                    ));
+
+                                if self.config.drop_suspected_spoofed() {
+                                    self.record_dead_letter(
+                                        DeadLetterStage::SuspectedSpoofed,
+                                        "message author suspected to be spoofed",
+                                        || {
+                                            format!(
+                                                "message {message_id} from {claimed_from} \
+                                                 propagated by {src}"
+                                            )
+                                        },
+                                    );
+                                    continue;
+                                }
+                            }
+                        }
+
+                        // Notify the message id service of the received message.
+                        self.message_id_service
+                            .do_send(MessageIdInEvent::MessageEvent(
+                                MessageIdMessageEvent::Received(ctx),
+                            ));
+                    }
+                    FramingUpstreamOutEvent::SubscriptionRequestReceived {
+                        src,
+                        connection,
+                        action,
+                    } => {
+                        // Track the subscriptions per connection, if enabled.
+                        let connection = self
+                            .config
+                            .connection_scoped_subscriptions()
+                            .then_some(connection);
+                        let subscribed = |topic| match &connection {
+                            Some(connection) => self
+                                .subscriptions_service
+                                .is_connection_subscribed(&src, connection, topic),
+                            None => self.subscriptions_service.is_peer_subscribed(&src, topic),
+                        };
+
+                        // Ignore the subscription announcements of the peers not authorized to
+                        // subscribe to the topic.
+                        if let SubscriptionAction::Subscribe(topic) = &action {
+                            if !self.is_authorized(&src, topic, TopicAction::Subscribe) {
+                                tracing::debug!(%src, %topic, "Ignoring unauthorized subscription");
+                                self.unauthorized.record_subscription();
+                                self.record_dead_letter(
+                                    DeadLetterStage::Unauthorized,
+                                    "peer not authorized to subscribe",
+                                    || format!("{action:?} from {src}"),
+                                );
+                                continue;
+                            }
+                        }
+
+                        match &action {
+                            SubscriptionAction::Subscribe(topic) if !subscribed(topic) => {
+                                // Notify the subscriptions service of the subscription request.
+                                self.subscriptions_service.do_send(
+                                    SubscriptionsInEvent::PeerSubscriptionRequest {
+                                        src,
+                                        connection,
+                                        action,
+                                    },
+                                );
+                            }
+                            SubscriptionAction::Unsubscribe(topic) if subscribed(topic) => {
+                                // Notify the subscriptions service of the unsubscription request.
+                                self.subscriptions_service.do_send(
+                                    SubscriptionsInEvent::PeerSubscriptionRequest {
+                                        src,
+                                        connection,
+                                        action,
+                                    },
+                                );
+                            }
+                            SubscriptionAction::Subscribe(_) => {
+                                self.record_dead_letter(
+                                    DeadLetterStage::SubscriptionFilter,
+                                    "peer subscription unchanged",
+                                    || format!("{action:?} from {src}"),
+                                );
+
+                                // The peer re-announced a known subscription, e.g., to refresh
+                                // its subscriptions. Answer with the local subscriptions, unless
+                                // recently sent, so the peer can refresh ours in turn.
+                                if !self.subscriptions_service.subscriptions().is_empty()
+                                    && self.subscription_refreshes.answer(src, Instant::now())
+                                {
+                                    tracing::debug!(%src, "Answering subscriptions re-announcement");
+                                    self.announce_subscriptions(src);
+                                }
+                            }
+                            SubscriptionAction::Unsubscribe(_) => {
+                                self.record_dead_letter(
+                                    DeadLetterStage::SubscriptionFilter,
+                                    "peer subscription unchanged",
+                                    || format!("{action:?} from {src}"),
+                                );
+                            }
+                        }
+                    }
+                    FramingUpstreamOutEvent::ValidationFailed { src, error, class } => {
+                        let reason = match class {
+                            FrameFailureClass::TransportCorruption => "corrupt frame",
+                            FrameFailureClass::ProtocolError => "invalid frame",
+                        };
+                        self.record_dead_letter(DeadLetterStage::FramingValidation, reason, || {
+                            format!("{error} from {src}")
+                        });
+                    }
+                    FramingUpstreamOutEvent::InefficientFraming {
+                        src,
+                        avg_msgs_per_frame,
+                    } => {
+                        self.behaviour_output_mailbox
+                            .push_back(ToSwarm::GenerateEvent(Event::inefficient_peer_framing(
+                                src,
+                                avg_msgs_per_frame,
+                            )));
+                    }
+                    FramingUpstreamOutEvent::ControlMessageReceived { src, message } => {
+                        // The peers proposed by a `Prune` peer exchange are subscribed to the
+                        // pruned topic.
+                        if let ControlMessage::Prune(prune) = &message {
+                            for peer in prune.peers.iter().filter(|p| **p != self.local_peer_id) {
+                                self.subscriptions_service.do_send(
+                                    SubscriptionsInEvent::PeerInfoLearned {
+                                        peer: *peer,
+                                        topics: vec![prune.topic_hash.clone()],
+                                    },
+                                );
+                            }
+                        }
+
+                        // The peer grafting the local node into a subscribed topic mesh knows
+                        // about the local subscription.
+                        if let ControlMessage::Graft(graft) = &message {
+                            if self.subscriptions_service.is_subscribed(&graft.topic_hash) {
+                                self.confirm_peer_subscription(src, &graft.topic_hash);
+                            }
+                        }
 
-        // Check if the frame size exceeds the maximum allowed size. If so, drop the frame.
-        if frame.len() > self.config.max_frame_size() {
-            tracing::warn!(%dest, "Frame size exceeds maximum allowed size");
-            return;
+                        // Serve the messages requested by an `IWant` from the message cache.
+                        if let ControlMessage::IWant(iwant) = &message {
+                            self.serve_iwant(src, &iwant.message_ids);
+                        }
+                    }
+                },
+            }
         }
 
-        self.conn_handler_mailbox.push_back(ToSwarm::NotifyHandler {
-            peer_id: dest,
-            handler: NotifyHandler::Any,
-            event: HandlerCommand::SendFrame(frame),
-        });
+        // Notify the application of the flushed unsubscriptions complete, or past their deadline.
+        for (flush_id, report) in self.pending_flushes.complete(Instant::now()) {
+            tracing::debug!(%flush_id, ?report, "Unsubscription flush completed");
+            self.behaviour_output_mailbox
+                .push_back(ToSwarm::GenerateEvent(Event::unsubscription_flushed(
+                    flush_id, report,
+                )));
+        }
     }
 }
 
@@ -225,6 +3527,18 @@ where
         local_addr: &Multiaddr,
         remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
+        // Consult the connection gate before creating the connection handler.
+        if let Some(handler) = self.gate_connection(
+            peer_id,
+            ConnectionGateContext::new_inbound(
+                connection_id,
+                local_addr.clone(),
+                remote_addr.clone(),
+            ),
+        )? {
+            return Ok(handler);
+        }
+
         // Emit an event to the connections service.
         self.connections_service
             .do_send(ConnectionsInEvent::EstablishedInboundConnection {
@@ -242,6 +3556,22 @@ where
         ))
     }
 
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _addresses: &[Multiaddr],
+        _effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        // Supply the known peer addresses, if any.
+        let addrs = maybe_peer
+            .and_then(|peer| self.dialer_service.known_peer_addrs(&peer))
+            .map(|addrs| addrs.to_vec())
+            .unwrap_or_default();
+
+        Ok(addrs)
+    }
+
     fn handle_established_outbound_connection(
         &mut self,
         connection_id: ConnectionId,
@@ -249,6 +3579,14 @@ where
         remote_addr: &Multiaddr,
         _role_override: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
+        // Consult the connection gate before creating the connection handler.
+        if let Some(handler) = self.gate_connection(
+            peer_id,
+            ConnectionGateContext::new_outbound(connection_id, remote_addr.clone()),
+        )? {
+            return Ok(handler);
+        }
+
         // Emit an event to the connections service.
         self.connections_service
             .do_send(ConnectionsInEvent::EstablishedOutboundConnection {
@@ -267,11 +3605,39 @@ where
 
     fn on_swarm_event(&mut self, event: FromSwarm<Self::ConnectionHandler>) {
         match event {
+            // Ignore the connections allowed disabled by the connection gate.
+            FromSwarm::ConnectionEstablished(ConnectionEstablished { connection_id, .. })
+            | FromSwarm::AddressChange(AddressChange { connection_id, .. })
+                if self.gated_connections.contains(&connection_id) => {}
+            FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. })
+                if self.gated_connections.contains(&connection_id) =>
+            {
+                self.gated_connections.remove(&connection_id);
+            }
             FromSwarm::ConnectionEstablished(ev) => {
+                // Retry the failed subscription sync over the new connection.
+                if ev.other_established > 0 {
+                    self.subscription_sync_service
+                        .do_send(SubscriptionSyncInEvent::ConnectionEstablished(ev.peer_id));
+                }
+
                 self.connections_service
                     .do_send(ConnectionsInEvent::from_swarm_event(ev));
             }
             FromSwarm::ConnectionClosed(ev) => {
+                // Drop the closed connection scoped subscriptions. The last connection
+                // subscriptions are dropped once the peer disconnects.
+                if ev.remaining_established > 0 {
+                    self.subscriptions_service.do_send(
+                        SubscriptionsInEvent::from_peer_connection_event(
+                            SubscriptionsPeerConnectionEvent::ConnectionClosed {
+                                peer: ev.peer_id,
+                                connection: ev.connection_id,
+                            },
+                        ),
+                    );
+                }
+
                 self.connections_service
                     .do_send(ConnectionsInEvent::from_swarm_event(ev));
             }
@@ -280,6 +3646,12 @@ where
                     .do_send(ConnectionsInEvent::from_swarm_event(ev));
             }
             FromSwarm::DialFailure(ev) => {
+                // Notify the dialer service of the failed dial to a known peer.
+                if let Some(peer) = ev.peer_id {
+                    self.dialer_service
+                        .do_send(DialerInEvent::DialFailure(peer));
+                }
+
                 self.connections_service
                     .do_send(ConnectionsInEvent::from_swarm_event(ev));
             }
@@ -294,310 +3666,196 @@ where
     fn on_connection_handler_event(
         &mut self,
         peer_id: PeerId,
-        _connection_id: ConnectionId,
+        connection_id: ConnectionId,
         event: THandlerOutEvent<Self>,
     ) {
+        // Ignore the connections allowed disabled by the connection gate.
+        if self.gated_connections.contains(&connection_id) {
+            return;
+        }
+
         match event {
             HandlerEvent::FrameReceived(frame) => {
+                // Sample the received frame, before any decoding, if enabled.
+                if let Some(sampler) = self.frame_sampler.as_mut() {
+                    sampler.sample(FrameDirection::Received, peer_id, &frame);
+                }
+
+                self.send_health.record_received(peer_id, Instant::now());
+
+                // Drop the frame if the peer is in a duplicate flood cooldown period.
+                if let Some(until) = self.flood_cooldowns.get(&peer_id) {
+                    if Instant::now() < *until {
+                        tracing::trace!(src = %peer_id, "Dropping frame from flooding peer");
+                        return;
+                    }
+
+                    self.flood_cooldowns.remove(&peer_id);
+                }
+
                 // Notify the framing service of the received frame handler event.
                 self.framing_service.do_send(FramingInEvent::Upstream(
                     FramingUpstreamInEvent::RawFrameReceived {
                         src: peer_id,
+                        connection: connection_id,
                         frame,
                     },
                 ));
             }
-            HandlerEvent::FrameSent => {}
-        }
-    }
-
-    fn poll(
-        &mut self,
-        cx: &mut Context<'_>,
-        _params: &mut impl PollParameters,
-    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
-        // Poll the connections service.
-        while let Poll::Ready(conn_event) = self.connections_service.poll(cx) {
-            // Notify the subscriptions service of the connection event.
-            self.subscriptions_service
-                .do_send(SubscriptionsInEvent::from_peer_connection_event(
-                    conn_event.clone(),
-                ));
-
-            // Notify the protocol's routing service of the connection event.
-            self.protocol_router_service.do_send(match conn_event {
-                ConnectionsOutEvent::NewPeerConnected(peer) => {
-                    ProtocolRouterInEvent::ConnectionEvent(
-                        ProtocolRouterConnectionEvent::PeerConnected(peer),
-                    )
-                }
-                ConnectionsOutEvent::PeerDisconnected(peer) => {
-                    ProtocolRouterInEvent::ConnectionEvent(
-                        ProtocolRouterConnectionEvent::PeerDisconnected(peer),
-                    )
-                }
-            });
-        }
-
-        // Poll the subscriptions service.
-        while let Poll::Ready(sub_event) = self.subscriptions_service.poll(cx) {
-            match sub_event {
-                SubscriptionsOutEvent::Subscribed(sub) => {
-                    // Notify the message id service of the subscription.
-                    self.message_id_service
-                        .do_send(MessageIdInEvent::SubscriptionEvent(
-                            MessageIdSubscriptionEvent::Subscribed {
-                                topic: sub.topic.clone(),
-                                message_id_fn: sub.message_id_fn.clone(),
-                            },
-                        ));
-
-                    // Notify the protocol's routing service of the subscription event.
-                    self.protocol_router_service
-                        .do_send(ProtocolRouterInEvent::SubscriptionEvent(
-                            ProtocolRouterSubscriptionEvent::Subscribed(sub.clone()),
-                        ));
-
-                    // Send the subscription update to all active peers.
-                    tracing::debug!(topic = %sub.topic, "Sending subscription update");
-
-                    let sub_action = SubscriptionAction::Subscribe(sub.topic);
-                    for dest in self.connections_service.active_peers() {
-                        // Notify the framing service of the subscription update request.
-                        self.framing_service.do_send(FramingInEvent::Downstream(
-                            FramingDownstreamInEvent::SendSubscriptionRequest {
-                                dest,
-                                actions: vec![sub_action.clone()],
-                            },
-                        ));
-                    }
-                }
-                SubscriptionsOutEvent::Unsubscribed(topic) => {
-                    // Notify the message id service of the unsubscription.
-                    self.message_id_service
-                        .do_send(MessageIdInEvent::SubscriptionEvent(
-                            MessageIdSubscriptionEvent::Unsubscribed(topic.clone()),
-                        ));
-
-                    // Notify the protocol's service of the unsubscription event.
-                    self.protocol_router_service
-                        .do_send(ProtocolRouterInEvent::SubscriptionEvent(
-                            ProtocolRouterSubscriptionEvent::Unsubscribed(topic.clone()),
-                        ));
-
-                    // Send the subscription updates to all active peers.
-                    tracing::debug!(%topic, "Sending subscription update");
-
-                    let sub_action = SubscriptionAction::Unsubscribe(topic);
-                    for dest in self.connections_service.active_peers() {
-                        // Notify the framing service of the subscription update request.
-                        self.framing_service.do_send(FramingInEvent::Downstream(
-                            FramingDownstreamInEvent::SendSubscriptionRequest {
-                                dest,
-                                actions: vec![sub_action.clone()],
-                            },
-                        ));
-                    }
-                }
-                SubscriptionsOutEvent::PeerSubscribed { peer, topic } => {
-                    tracing::debug!(src = %peer, %topic, "Peer subscribed");
-
-                    // Notify the protocol's service of the peer subscription event.
-                    self.protocol_router_service
-                        .do_send(ProtocolRouterInEvent::SubscriptionEvent(
-                            ProtocolRouterSubscriptionEvent::PeerSubscribed { peer, topic },
-                        ));
-                }
-                SubscriptionsOutEvent::PeerUnsubscribed { peer, topic } => {
-                    tracing::debug!(src = %peer, %topic, "Peer unsubscribed");
-
-                    // Notify the protocol's service of the peer unsubscription event.
-                    self.protocol_router_service
-                        .do_send(ProtocolRouterInEvent::SubscriptionEvent(
-                            ProtocolRouterSubscriptionEvent::PeerUnsubscribed { peer, topic },
-                        ));
-                }
-                SubscriptionsOutEvent::SendSubscriptions { dest, topics } => {
-                    // Send the subscriptions to the peer.
-                    tracing::debug!(%dest, ?topics, "Sending subscriptions");
-
-                    let actions = topics
-                        .into_iter()
-                        .map(SubscriptionAction::Subscribe)
-                        .collect::<Vec<_>>();
+            HandlerEvent::FrameSent => {
+                self.record_send(peer_id, false);
+            }
+            HandlerEvent::TaggedFrameSent { tag } => {
+                // Record the flushed unsubscription frame.
+                self.pending_flushes.flushed(&FlushId::new(tag), peer_id);
+            }
+            HandlerEvent::FramesDropped { expired, cancelled } => {
+                tracing::debug!(
+                    peer = %peer_id,
+                    expired,
+                    cancelled,
+                    "Connection handler dropped queued frames"
+                );
+                self.send_health.record_dropped(expired, cancelled);
+            }
+            HandlerEvent::FrameSendFailed { frame_size } => {
+                self.record_send(peer_id, true);
+
+                let previous = self.peer_frame_limits.limit(&peer_id);
+                let Some(limit) = self.peer_frame_limits.record_send_failure(
+                    peer_id,
+                    connection_id,
+                    frame_size,
+                    Instant::now(),
+                ) else {
+                    tracing::debug!(peer = %peer_id, frame_size, "Frame send failed");
+                    return;
+                };
+
+                tracing::debug!(
+                    peer = %peer_id,
+                    frame_size,
+                    limit,
+                    "Frame send failed, clamping the frames sent to the peer"
+                );
+
+                // Batch the messages sent to the peer up to the suspected limit.
+                if previous != Some(limit) {
                     self.framing_service.do_send(FramingInEvent::Downstream(
-                        FramingDownstreamInEvent::SendSubscriptionRequest { dest, actions },
+                        FramingDownstreamInEvent::PeerFrameLimitChanged {
+                            peer: peer_id,
+                            limit: Some(limit),
+                        },
                     ));
                 }
-            }
-        }
-
-        // Poll the message id service.
-        while let Poll::Ready(event) = self.message_id_service.poll(cx) {
-            match event {
-                MessageIdOutEvent::MessagePublished {
-                    message,
-                    message_id,
-                } => {
-                    // If message has already seen before, drop it.
-                    if self.message_cache_service.contains(&message_id) {
-                        continue;
-                    }
-
-                    // Notify the message cache service of the published message.
-                    self.message_cache_service
-                        .do_send(MessageCacheInEvent::MessageEvent(
-                            MessageCacheMessageEvent::MessagePublished {
-                                message: message.clone(),
-                                message_id: message_id.clone(),
-                            },
-                        ));
-
-                    // Notify the protocol's service of the published message.
-                    self.protocol_router_service
-                        .do_send(ProtocolRouterInEvent::MessageEvent(
-                            ProtocolRouterMessageEvent::MessagePublished {
-                                message,
-                                message_id,
-                            },
-                        ));
-                }
-                MessageIdOutEvent::MessageReceived {
-                    src,
-                    message,
-                    message_id,
-                } => {
-                    // If message has already seen before, drop it.
-                    if self.message_cache_service.contains(&message_id) {
-                        continue;
-                    }
-
-                    // Notify the message cache service of the received message.
-                    self.message_cache_service
-                        .do_send(MessageCacheInEvent::MessageEvent(
-                            MessageCacheMessageEvent::MessageReceived {
-                                src,
-                                message: message.clone(),
-                                message_id: message_id.clone(),
-                            },
-                        ));
-
-                    // Notify the behaviour output mailbox of the received message.
-                    self.behaviour_output_mailbox
-                        .push_back(ToSwarm::GenerateEvent(Event::MessageReceived {
-                            src,
-                            message: (*message).clone().into(),
-                            message_id: message_id.clone(),
-                        }));
 
-                    // Notify the protocol's service of the received message.
-                    self.protocol_router_service
-                        .do_send(ProtocolRouterInEvent::MessageEvent(
-                            ProtocolRouterMessageEvent::MessageReceived {
-                                src,
-                                message,
-                                message_id,
-                            },
-                        ));
-                }
+                // Coalesce the frames queued on the connection up to the suspected limit.
+                self.conn_handler_mailbox.push_back(ToSwarm::NotifyHandler {
+                    peer_id,
+                    handler: NotifyHandler::One(connection_id),
+                    event: HandlerCommand::SetFrameLimit(Some(limit)),
+                });
             }
-        }
-
-        // Poll the message cache service.
-        let _ = self.message_cache_service.poll(cx);
+            HandlerEvent::Disabled { reason } => {
+                tracing::debug!(peer = %peer_id, ?reason, "Connection handler disabled");
 
-        // Poll the protocol service.
-        while let Poll::Ready(event) = self.protocol_router_service.poll(cx) {
-            match event {
-                ProtocolRouterOutEvent::ForwardMessage { message, dest } => {
-                    for dest in dest {
-                        // Notify the framing service of the message to send.
-                        self.framing_service.do_send(FramingInEvent::Downstream(
-                            FramingDownstreamInEvent::ForwardMessage {
-                                dest,
-                                message: message.clone(),
-                            },
-                        ));
-                    }
-                }
-                ProtocolRouterOutEvent::SendControlMessage { message, dest } => {
-                    // Notify the framing service of the control message to send.
-                    self.framing_service.do_send(FramingInEvent::Downstream(
-                        FramingDownstreamInEvent::SendControlMessage { dest, message },
+                // Stop using the connection to send frames.
+                self.connections_service
+                    .do_send(ConnectionsInEvent::ConnectionDisabled {
+                        connection_id,
+                        peer_id,
+                    });
+
+                // Notify the application of the disabled connection.
+                self.behaviour_output_mailbox
+                    .push_back(ToSwarm::GenerateEvent(Event::connection_disabled(
+                        peer_id,
+                        connection_id,
+                        reason,
+                    )));
+            }
+            HandlerEvent::ProtocolNegotiated(protocol) => {
+                tracing::debug!(peer = %peer_id, %protocol, "Protocol negotiated");
+
+                // Notify the protocol's router service of the negotiated protocol.
+                self.protocol_router_service
+                    .do_send(ProtocolRouterInEvent::ConnectionEvent(
+                        ProtocolRouterConnectionEvent::PeerProtocolNegotiated {
+                            peer: peer_id,
+                            protocol,
+                        },
                     ));
-                }
             }
         }
+    }
 
-        // Poll the framing service.
-        while let Poll::Ready(event) = self.framing_service.poll(cx) {
-            match event {
-                FramingOutEvent::Downstream(FramingDownstreamOutEvent::SendFrame {
-                    dest,
-                    frame,
-                }) => {
-                    // Send the frame to the peer.
-                    self.send_frame(dest, frame);
-                }
-                FramingOutEvent::Upstream(ev) => match ev {
-                    FramingUpstreamOutEvent::MessageReceived { src, message } => {
-                        // Skip the message if we are not subscribed to the topic.
-                        if !self.subscriptions_service.is_subscribed(&message.topic()) {
-                            continue;
-                        }
-
-                        // Notify the message id service of the received message.
-                        self.message_id_service
-                            .do_send(MessageIdInEvent::MessageEvent(
-                                MessageIdMessageEvent::Received { src, message },
-                            ));
-                    }
-                    FramingUpstreamOutEvent::SubscriptionRequestReceived { src, action } => {
-                        match &action {
-                            SubscriptionAction::Subscribe(topic)
-                                if !self.subscriptions_service.is_peer_subscribed(&src, topic) =>
-                            {
-                                // Notify the subscriptions service of the subscription request.
-                                self.subscriptions_service.do_send(
-                                    SubscriptionsInEvent::PeerSubscriptionRequest { src, action },
-                                );
-                            }
-                            SubscriptionAction::Unsubscribe(topic)
-                                if self.subscriptions_service.is_peer_subscribed(&src, topic) =>
-                            {
-                                // Notify the subscriptions service of the unsubscription request.
-                                self.subscriptions_service.do_send(
-                                    SubscriptionsInEvent::PeerSubscriptionRequest { src, action },
-                                );
-                            }
-                            _ => {}
-                        }
-                    }
-                    FramingUpstreamOutEvent::ControlMessageReceived { src, message } => {
-                        // Notify the protocol's router service of the control message.
-                        self.protocol_router_service
-                            .do_send(ProtocolRouterInEvent::ControlEvent(
-                                ProtocolRouterControlEvent { src, message },
-                            ));
-                    }
-                },
-            }
-        }
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+        _params: &mut impl PollParameters,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        // Process the services queued events, filling the mailboxes.
+        self.poll_services(cx);
 
         // Process the connection handler mailbox.
         if let Some(event) = self.conn_handler_mailbox.pop_front() {
             return Poll::Ready(event);
         }
 
+        // Process the dial requests mailbox.
+        if let Some(event) = self.dial_mailbox.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        // Process the close connection requests mailbox.
+        if let Some(event) = self.close_mailbox.pop_front() {
+            return Poll::Ready(event);
+        }
+
         // Process the behaviour output events mailbox.
         if let Some(event) = self.behaviour_output_mailbox.pop_front() {
             return Poll::Ready(event);
         }
 
+        // The poll cycle is over. If forwards are held back, schedule the next poll cycle right
+        // away to release them.
+        self.forward_scheduler.end_cycle();
+        if !self.forward_scheduler.is_empty() {
+            cx.waker().wake_by_ref();
+        }
+
         Poll::Pending
     }
 }
 
+/// Report the events queued when the behaviour is dropped, e.g., when the swarm is torn down
+/// mid-operation. Only checked with debug assertions enabled.
+///
+/// A warning is logged, unless the `strict-drop` feature is enabled, in which case the drop panics.
+#[cfg(debug_assertions)]
+impl<P: Protocol> Drop for Behaviour<P> {
+    fn drop(&mut self) {
+        let pending = self.pending_event_counts();
+        if pending.is_empty() || std::thread::panicking() {
+            return;
+        }
+
+        #[cfg(feature = "strict-drop")]
+        panic!(
+            "Behaviour dropped with {} queued events: {pending:?}",
+            pending.total()
+        );
+
+        #[cfg(not(feature = "strict-drop"))]
+        tracing::warn!(
+            local_peer_id = %self.local_peer_id,
+            queued = pending.total(),
+            ?pending,
+            "Behaviour dropped with queued events"
+        );
+    }
+}
+
 impl From<ConnectionEstablished<'_>> for ConnectionsSwarmEvent {
     fn from(ev: ConnectionEstablished) -> Self {
         Self::ConnectionEstablished {
@@ -648,39 +3906,43 @@ impl From<ListenFailure<'_>> for ConnectionsSwarmEvent {
     }
 }
 
-impl From<ConnectionsOutEvent> for SubscriptionsPeerConnectionEvent {
-    fn from(ev: ConnectionsOutEvent) -> Self {
-        match ev {
-            ConnectionsOutEvent::NewPeerConnected(peer) => {
-                SubscriptionsPeerConnectionEvent::NewPeerConnected(peer)
-            }
-            ConnectionsOutEvent::PeerDisconnected(peer) => {
-                SubscriptionsPeerConnectionEvent::PeerDisconnected(peer)
-            }
-        }
-    }
-}
-
 impl From<Message> for FrameMessage {
     fn from(message: Message) -> Self {
         let mut msg = Self::new(message.topic, message.data);
         msg.set_seqno(message.sequence_number);
-        msg.set_key(message.key);
+        msg.set_key_bytes(message.key);
         msg.set_author(message.from);
         msg.set_signature(message.signature);
+        msg.set_priority(message.priority);
         msg
     }
 }
 
 impl From<FrameMessage> for Message {
     fn from(message: FrameMessage) -> Self {
+        // Unwrap the message expiry envelope, if any.
+        let data = message.data();
+        let (data, ttl) = match ttl::decode(&data) {
+            Some((expiry, payload)) => (
+                payload.to_vec(),
+                Some(
+                    expiry
+                        .duration_since(SystemTime::now())
+                        .unwrap_or(Duration::ZERO),
+                ),
+            ),
+            None => (data.to_vec(), None),
+        };
+
         Self {
             topic: message.topic(),
-            data: message.data().to_vec(),
+            data,
             sequence_number: message.seqno(),
             key: message.key(),
             from: message.author(),
             signature: message.signature(),
+            ttl,
+            priority: message.priority(),
         }
     }
 }
@@ -0,0 +1,267 @@
+use std::collections::BTreeSet;
+use std::io;
+use std::path::PathBuf;
+
+use crate::behaviour::Behaviour;
+use crate::config::Config;
+use crate::identity::Identity;
+use crate::preflight::PreflightWarning;
+use crate::protocol::Protocol;
+use crate::services::message_cache::DedupPersistenceConfig;
+use crate::topic::{TopicHash, TopicValidationError};
+use crate::topology::TopologySnapshot;
+use crate::upgrade::protocol_ids;
+
+/// Errors that can occur when building a behaviour.
+///
+/// See [`BehaviourBuilder::build`] for more details.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// No local node identity was set (see [`BehaviourBuilder::identity`]).
+    #[error("missing local node identity")]
+    MissingIdentity,
+
+    /// The protocol upgrade advertises no protocol id.
+    #[error("no protocol id registered")]
+    NoProtocolIds,
+
+    /// A protocol id advertised by the protocol upgrade does not start with a `/`.
+    #[error("invalid protocol id: {0}")]
+    InvalidProtocolId(String),
+
+    /// A protocol id is advertised more than once by the protocol upgrade, e.g., by both inner
+    /// protocols of a [`CompositeProtocol`](crate::protocol::CompositeProtocol).
+    #[error("duplicate protocol id: {0}")]
+    DuplicateProtocolId(String),
+
+    /// An imported subscription topic fails the [topic validation](Config::topic_validation).
+    #[error("invalid imported subscription {topic}: {source}")]
+    InvalidSubscription {
+        /// The imported subscription topic.
+        topic: TopicHash,
+        /// The topic validation error.
+        source: TopicValidationError,
+    },
+
+    /// The preflight checks reported warnings, while denied (see
+    /// [`BehaviourBuilder::deny_preflight_warnings`]).
+    #[error("{} preflight warning(s)", .0.len())]
+    Preflight(Vec<PreflightWarning>),
+
+    /// The dedup store file cannot be read, is corrupt, or cannot be rewritten.
+    #[error("dedup store open failed: {0}")]
+    DedupStore(#[source] io::Error),
+}
+
+/// A builder for a [`Behaviour`] whose optional subsystems are initialized eagerly.
+///
+/// Unlike [`Behaviour::new_with_identity`], which falls back to a degraded mode when an optional
+/// subsystem fails to initialize, e.g., to a memory-only message cache if the dedup store cannot
+/// be opened, the builder fails with a [`BuildError`].
+///
+/// ```ignore
+/// let behaviour = Behaviour::builder(protocol)
+///     .config(config)
+///     .identity(Identity::Keypair(keypair))
+///     .import_subscriptions(&snapshot)
+///     .dedup_store("/var/lib/node/dedup")
+///     .build()
+///     .await?;
+/// ```
+pub struct BehaviourBuilder<P: Protocol> {
+    protocol: P,
+    config: Config,
+    identity: Option<Identity>,
+    subscriptions: BTreeSet<TopicHash>,
+    dedup_store: Option<DedupPersistenceConfig>,
+    deny_preflight_warnings: bool,
+}
+
+impl<P: Protocol> BehaviourBuilder<P> {
+    /// Create a new behaviour builder for the given protocol, with the default configuration.
+    pub(crate) fn new(protocol: P) -> Self {
+        Self {
+            protocol,
+            config: Default::default(),
+            identity: None,
+            subscriptions: Default::default(),
+            dedup_store: None,
+            deny_preflight_warnings: false,
+        }
+    }
+
+    /// The behaviour configuration.
+    ///
+    /// Default is [`Config::default`].
+    #[must_use]
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// The local node identity (see [`Behaviour::new_with_identity`]).
+    ///
+    /// Required.
+    #[must_use]
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Subscribe to the local node subscriptions of the given topology snapshot, e.g., taken
+    /// before the node restart (see [`Behaviour::topology_snapshot`]).
+    ///
+    /// The subscriptions are requested with the default subscription options. As with
+    /// [`Behaviour::subscribe`], they are processed during the first polls of the behaviour.
+    #[must_use]
+    pub fn import_subscriptions(mut self, snapshot: &TopologySnapshot) -> Self {
+        self.subscriptions
+            .extend(snapshot.subscriptions().iter().cloned());
+        self
+    }
+
+    /// Persist the seen messages ids to the dedup store file at the given path, flushed every 5
+    /// seconds (see [`DedupPersistenceConfig::new`]).
+    ///
+    /// Overrides the [`Config::dedup_persistence`] configuration. Unlike the configuration, the
+    /// build fails if the file cannot be read, or is corrupt.
+    #[must_use]
+    pub fn dedup_store(mut self, path: impl Into<PathBuf>) -> Self {
+        self.dedup_store = Some(DedupPersistenceConfig::new(path));
+        self
+    }
+
+    /// Whether the build fails if the configuration preflight checks report warnings (see
+    /// [`Config::preflight`]). Otherwise, the warnings are logged.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn deny_preflight_warnings(mut self, deny: bool) -> Self {
+        self.deny_preflight_warnings = deny;
+        self
+    }
+
+    /// Build the behaviour.
+    ///
+    /// The identity, the protocol ids, the imported subscriptions and the preflight checks are
+    /// validated first, so no file is touched if the build fails validation. The dedup store,
+    /// either set with [`BehaviourBuilder::dedup_store`] or configured, is then opened and its
+    /// live records loaded into the message cache.
+    pub async fn build(self) -> Result<Behaviour<P>, BuildError> {
+        let identity = self.identity.ok_or(BuildError::MissingIdentity)?;
+
+        validate_protocol_ids(protocol_ids(&P::upgrade()))?;
+
+        for topic in &self.subscriptions {
+            self.config
+                .topic_validation()
+                .validate(topic.as_str())
+                .map_err(|source| BuildError::InvalidSubscription {
+                    topic: topic.clone(),
+                    source,
+                })?;
+        }
+
+        let warnings = self.config.preflight();
+        if self.deny_preflight_warnings && !warnings.is_empty() {
+            return Err(BuildError::Preflight(warnings));
+        }
+        for warning in &warnings {
+            tracing::warn!(code = %warning.code, values = ?warning.values, "{}", warning.message);
+        }
+
+        let mut message_cache_service = Behaviour::<P>::new_message_cache_service(&self.config);
+        let dedup_store = self
+            .dedup_store
+            .or_else(|| self.config.dedup_persistence().cloned());
+        if let Some(dedup_store) = dedup_store {
+            message_cache_service
+                .try_enable_dedup_persistence(dedup_store)
+                .map_err(BuildError::DedupStore)?;
+        }
+
+        let mut behaviour = Behaviour::new_with_message_cache(
+            self.config,
+            self.protocol,
+            identity,
+            message_cache_service,
+        );
+        for topic in self.subscriptions {
+            // The topics were validated, so the subscription requests are accepted.
+            let _ = behaviour.subscribe(topic);
+        }
+
+        Ok(behaviour)
+    }
+}
+
+/// Check the protocol ids are not empty, start with a `/`, and are unique.
+fn validate_protocol_ids(ids: Vec<String>) -> Result<(), BuildError> {
+    if ids.is_empty() {
+        return Err(BuildError::NoProtocolIds);
+    }
+
+    let mut seen = BTreeSet::new();
+    for id in ids {
+        if !id.starts_with('/') {
+            return Err(BuildError::InvalidProtocolId(id));
+        }
+        if !seen.insert(id.clone()) {
+            return Err(BuildError::DuplicateProtocolId(id));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn valid_protocol_ids_pass() {
+        //// When
+        let result = validate_protocol_ids(ids(&["/floodsub/1.0.0", "/meshsub/1.1.0"]));
+
+        //// Then
+        assert_matches!(result, Ok(()));
+    }
+
+    #[test]
+    fn empty_protocol_ids_fail() {
+        //// When
+        let result = validate_protocol_ids(Vec::new());
+
+        //// Then
+        assert_matches!(result, Err(BuildError::NoProtocolIds));
+    }
+
+    #[test]
+    fn protocol_id_without_leading_slash_fails() {
+        //// When
+        let result = validate_protocol_ids(ids(&["/floodsub/1.0.0", "meshsub/1.1.0"]));
+
+        //// Then
+        assert_matches!(result, Err(BuildError::InvalidProtocolId(id)) => {
+            assert_eq!(id, "meshsub/1.1.0");
+        });
+    }
+
+    #[test]
+    fn duplicate_protocol_id_fails() {
+        //// When
+        let result = validate_protocol_ids(ids(&["/floodsub/1.0.0", "/floodsub/1.0.0"]));
+
+        //// Then
+        assert_matches!(result, Err(BuildError::DuplicateProtocolId(id)) => {
+            assert_eq!(id, "/floodsub/1.0.0");
+        });
+    }
+}
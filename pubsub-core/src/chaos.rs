@@ -0,0 +1,403 @@
+//! Fault injection in the received and sent frames, for resilience testing (see
+//! [`Behaviour::set_chaos_policy`](crate::Behaviour::set_chaos_policy)).
+//!
+//! Only available with the `chaos` feature enabled. Not meant for production use.
+
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// The frames a chaos policy applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChaosDirection {
+    /// The frames received from the remote peers, before decoding.
+    Inbound,
+    /// The frames sent to the remote peers, once encoded.
+    Outbound,
+    /// Both the received and sent frames.
+    Both,
+}
+
+impl ChaosDirection {
+    /// Returns `true` if the received frames are in scope.
+    fn inbound(self) -> bool {
+        matches!(self, ChaosDirection::Inbound | ChaosDirection::Both)
+    }
+
+    /// Returns `true` if the sent frames are in scope.
+    fn outbound(self) -> bool {
+        matches!(self, ChaosDirection::Outbound | ChaosDirection::Both)
+    }
+}
+
+/// The faults injected in the frames (see
+/// [`Behaviour::set_chaos_policy`](crate::Behaviour::set_chaos_policy)).
+///
+/// Every frame in scope is first dropped with the drop rate probability. The frames not dropped
+/// are duplicated with the duplicate rate probability, and every resulting copy is delayed by a
+/// uniformly random duration within the delay range, if set. Otherwise, the duplicate is released
+/// right after the original, as a late re-delivery. The faults are drawn from a random
+/// number generator seeded with the policy seed, so the same frames sequence gets the same faults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosPolicy {
+    /// The probability, between `0.0` and `1.0`, a frame is dropped.
+    pub drop_rate: f64,
+    /// The probability, between `0.0` and `1.0`, a frame is duplicated.
+    pub duplicate_rate: f64,
+    /// The range, `(min, max)`, of the delay the frames are held back for. If `None`, the frames
+    /// are not delayed.
+    pub delay: Option<(Duration, Duration)>,
+    /// The frames the policy applies to.
+    pub scope: ChaosDirection,
+    /// The fault draws random number generator seed.
+    pub seed: u64,
+}
+
+impl ChaosPolicy {
+    /// Create a new chaos policy on the given frames, injecting no fault.
+    #[must_use]
+    pub fn new(scope: ChaosDirection) -> Self {
+        Self {
+            drop_rate: 0.0,
+            duplicate_rate: 0.0,
+            delay: None,
+            scope,
+            seed: 0,
+        }
+    }
+}
+
+/// The number of faults injected in one direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChaosCounters {
+    /// The number of frames dropped.
+    pub dropped: u64,
+    /// The number of frames duplicated.
+    pub duplicated: u64,
+    /// The number of frame copies delayed.
+    pub delayed: u64,
+}
+
+/// The number of faults injected, by direction (see
+/// [`Behaviour::chaos_stats`](crate::Behaviour::chaos_stats)).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChaosStats {
+    /// The faults injected in the received frames.
+    pub inbound: ChaosCounters,
+    /// The faults injected in the sent frames.
+    pub outbound: ChaosCounters,
+}
+
+/// The faults injected in the frames of one direction, and the delayed frames.
+struct DirectionState<T> {
+    /// The injected faults counters.
+    counters: ChaosCounters,
+    /// The delayed frames, with their due time, in insertion order.
+    delayed: Vec<(Instant, T)>,
+}
+
+impl<T> Default for DirectionState<T> {
+    fn default() -> Self {
+        Self {
+            counters: Default::default(),
+            delayed: Vec::new(),
+        }
+    }
+}
+
+impl<T> DirectionState<T> {
+    /// Take the delayed frames due at the given time, in insertion order.
+    fn take_due(&mut self, now: Instant) -> Vec<T> {
+        let (due, pending) = std::mem::take(&mut self.delayed)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(due_at, _)| *due_at <= now);
+        self.delayed = pending;
+        due.into_iter().map(|(_, frame)| frame).collect()
+    }
+
+    /// The time the next delayed frame is due, if any.
+    fn next_due(&self) -> Option<Instant> {
+        self.delayed.iter().map(|(due_at, _)| *due_at).min()
+    }
+}
+
+/// Injects the chaos policy faults in the received `I` frames and the sent `O` frames, holding
+/// back the delayed frames until due.
+pub(crate) struct ChaosLayer<I, O> {
+    /// The chaos policy, if set.
+    policy: Option<ChaosPolicy>,
+    /// The fault draws random number generator.
+    rng: StdRng,
+    /// The received frames faults.
+    inbound: DirectionState<I>,
+    /// The sent frames faults.
+    outbound: DirectionState<O>,
+    /// The timer waking the task when the next delayed frame is due.
+    timer: Option<Delay>,
+}
+
+impl<I: Clone, O: Clone> Default for ChaosLayer<I, O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Clone, O: Clone> ChaosLayer<I, O> {
+    /// Create a new chaos layer, injecting no fault until a policy is set.
+    pub(crate) fn new() -> Self {
+        Self {
+            policy: None,
+            rng: StdRng::seed_from_u64(0),
+            inbound: Default::default(),
+            outbound: Default::default(),
+            timer: None,
+        }
+    }
+
+    /// Set, or clear, the chaos policy, re-seeding the fault draws. The frames already delayed are
+    /// still released when due.
+    pub(crate) fn set_policy(&mut self, policy: Option<ChaosPolicy>) {
+        if let Some(policy) = &policy {
+            self.rng = StdRng::seed_from_u64(policy.seed);
+        }
+        self.policy = policy;
+    }
+
+    /// Get the injected faults counters.
+    pub(crate) fn stats(&self) -> ChaosStats {
+        ChaosStats {
+            inbound: self.inbound.counters,
+            outbound: self.outbound.counters,
+        }
+    }
+
+    /// Inject the faults in a received frame, and return the frames to process right away.
+    pub(crate) fn inbound(&mut self, frame: I, now: Instant) -> Vec<I> {
+        match &self.policy {
+            Some(policy) if policy.scope.inbound() => {
+                inject(policy, &mut self.rng, &mut self.inbound, frame, now)
+            }
+            _ => vec![frame],
+        }
+    }
+
+    /// Inject the faults in a sent frame, and return the frames to send right away.
+    pub(crate) fn outbound(&mut self, frame: O, now: Instant) -> Vec<O> {
+        match &self.policy {
+            Some(policy) if policy.scope.outbound() => {
+                inject(policy, &mut self.rng, &mut self.outbound, frame, now)
+            }
+            _ => vec![frame],
+        }
+    }
+
+    /// Take the delayed received and sent frames due at the given time, in insertion order.
+    pub(crate) fn take_due(&mut self, now: Instant) -> (Vec<I>, Vec<O>) {
+        (self.inbound.take_due(now), self.outbound.take_due(now))
+    }
+
+    /// Poll the delayed frames timer, and take the delayed frames due, if any.
+    ///
+    /// The timer is re-armed for the next delayed frame, if any, so the task is woken up when due.
+    pub(crate) fn poll_due(&mut self, cx: &mut Context<'_>) -> (Vec<I>, Vec<O>) {
+        let due = self.take_due(Instant::now());
+
+        let next_due = match (self.inbound.next_due(), self.outbound.next_due()) {
+            (Some(inbound), Some(outbound)) => Some(inbound.min(outbound)),
+            (inbound, outbound) => inbound.or(outbound),
+        };
+        self.timer = next_due.map(|next_due| {
+            let mut timer = Delay::new(next_due.saturating_duration_since(Instant::now()));
+            // Register the waker. A timer already elapsed wakes the task right away.
+            if timer.poll_unpin(cx) == Poll::Ready(()) {
+                cx.waker().wake_by_ref();
+            }
+            timer
+        });
+
+        due
+    }
+}
+
+/// Inject the policy faults in a frame, and return the frames to process right away.
+///
+/// The frame is first dropped with the drop rate probability. The frames not dropped are
+/// duplicated with the duplicate rate probability, and the copies are delayed, if the policy
+/// delays the frames. Otherwise, the duplicate is held back until the next release.
+fn inject<T: Clone>(
+    policy: &ChaosPolicy,
+    rng: &mut StdRng,
+    state: &mut DirectionState<T>,
+    frame: T,
+    now: Instant,
+) -> Vec<T> {
+    if draw(rng, policy.drop_rate) {
+        state.counters.dropped += 1;
+        return Vec::new();
+    }
+
+    let duplicate = draw(rng, policy.duplicate_rate);
+    let copies = if duplicate {
+        state.counters.duplicated += 1;
+        vec![frame.clone(), frame]
+    } else {
+        vec![frame]
+    };
+
+    let Some((min, max)) = policy.delay else {
+        let mut copies = copies;
+        // Hold the duplicate back until the next delayed frames release, so it is processed after
+        // the original, as a late re-delivery, rather than within the same batch.
+        if duplicate {
+            state.delayed.extend(copies.pop().map(|copy| (now, copy)));
+        }
+        return copies;
+    };
+    for copy in copies {
+        let delay = if max > min {
+            rng.gen_range(min..=max)
+        } else {
+            min
+        };
+        state.counters.delayed += 1;
+        state.delayed.push((now + delay, copy));
+    }
+    Vec::new()
+}
+
+/// Draw a boolean with the given probability. A NaN, or non-positive, probability draws `false`.
+fn draw(rng: &mut StdRng, probability: f64) -> bool {
+    if probability.is_nan() || probability <= 0.0 {
+        return false;
+    }
+    rng.gen_bool(probability.min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestLayer = ChaosLayer<u32, u32>;
+
+    fn new_test_layer(policy: ChaosPolicy) -> TestLayer {
+        let mut layer = TestLayer::new();
+        layer.set_policy(Some(policy));
+        layer
+    }
+
+    /// Inject the faults in the received frames `0..count`, and return the frames passed through.
+    fn inject_inbound(layer: &mut TestLayer, count: u32, now: Instant) -> Vec<u32> {
+        (0..count)
+            .flat_map(|frame| layer.inbound(frame, now))
+            .collect()
+    }
+
+    #[test]
+    fn frames_are_dropped_and_duplicated_with_the_policy_rates() {
+        //// Given
+        let now = Instant::now();
+        let mut layer = new_test_layer(ChaosPolicy {
+            drop_rate: 0.2,
+            duplicate_rate: 0.1,
+            ..ChaosPolicy::new(ChaosDirection::Inbound)
+        });
+
+        //// When
+        let passed = inject_inbound(&mut layer, 10_000, now);
+        let (duplicates, _) = layer.take_due(now);
+        let sent = layer.outbound(7, now);
+
+        //// Then
+        let stats = layer.stats();
+        assert!(
+            (1_700..=2_300).contains(&stats.inbound.dropped),
+            "The dropped count should be close to the rate, got {}",
+            stats.inbound.dropped
+        );
+        assert!(
+            (600..=1_000).contains(&stats.inbound.duplicated),
+            "The duplicated count should be close to the rate, got {}",
+            stats.inbound.duplicated
+        );
+        assert_eq!(
+            passed.len() as u64,
+            10_000 - stats.inbound.dropped,
+            "The duplicates should be held back until the next release"
+        );
+        assert_eq!(duplicates.len() as u64, stats.inbound.duplicated);
+        assert_eq!(stats.inbound.delayed, 0);
+        assert_eq!(sent, [7], "The sent frames should be out of scope");
+        assert_eq!(stats.outbound, ChaosCounters::default());
+    }
+
+    #[test]
+    fn faults_are_reproducible_with_the_same_seed() {
+        //// Given
+        let now = Instant::now();
+        let policy = ChaosPolicy {
+            drop_rate: 0.3,
+            duplicate_rate: 0.3,
+            seed: 42,
+            ..ChaosPolicy::new(ChaosDirection::Both)
+        };
+        let mut layer_a = new_test_layer(policy.clone());
+        let mut layer_b = new_test_layer(policy.clone());
+        let mut layer_c = new_test_layer(ChaosPolicy { seed: 7, ..policy });
+
+        //// When
+        let passed_a = inject_inbound(&mut layer_a, 100, now);
+        let passed_b = inject_inbound(&mut layer_b, 100, now);
+        let passed_c = inject_inbound(&mut layer_c, 100, now);
+
+        //// Then
+        assert_eq!(passed_a, passed_b);
+        assert_ne!(passed_a, passed_c);
+    }
+
+    #[test]
+    fn delayed_frames_are_released_when_due() {
+        //// Given
+        let now = Instant::now();
+        let mut layer = new_test_layer(ChaosPolicy {
+            delay: Some((Duration::from_millis(10), Duration::from_millis(20))),
+            ..ChaosPolicy::new(ChaosDirection::Outbound)
+        });
+
+        //// When
+        let sent = (0..10)
+            .flat_map(|frame| layer.outbound(frame, now))
+            .collect::<Vec<_>>();
+        let (_, due_early) = layer.take_due(now + Duration::from_millis(5));
+        let (_, due) = layer.take_due(now + Duration::from_millis(20));
+
+        //// Then
+        assert!(sent.is_empty(), "All the frames should be delayed");
+        assert!(due_early.is_empty());
+        assert_eq!(due, (0..10).collect::<Vec<_>>());
+        assert_eq!(layer.stats().outbound.delayed, 10);
+    }
+
+    #[test]
+    fn cleared_policy_injects_no_fault() {
+        //// Given
+        let now = Instant::now();
+        let mut layer = new_test_layer(ChaosPolicy {
+            drop_rate: 1.0,
+            ..ChaosPolicy::new(ChaosDirection::Both)
+        });
+        layer.set_policy(None);
+
+        //// When
+        let passed = inject_inbound(&mut layer, 10, now);
+
+        //// Then
+        assert_eq!(passed, (0..10).collect::<Vec<_>>());
+        assert_eq!(layer.stats(), ChaosStats::default());
+    }
+}
@@ -0,0 +1,175 @@
+//! Application-level message chunking.
+//!
+//! A payload larger than the maximum frame size can be split into chunks, published as
+//! individual messages on the same topic (see
+//! [`Behaviour::publish_chunked`](crate::Behaviour::publish_chunked)). Each chunk message payload
+//! starts with a fixed-size header followed by the chunk bytes:
+//!
+//! ```text
+//! +-------+---------+-------------+-------+-------+-------------+-------------+
+//! | magic | version | transfer id | index | total | payload len | chunk bytes |
+//! +-------+---------+-------------+-------+-------+-------------+-------------+
+//!     4        1          32          4       4          8         variable
+//! ```
+//!
+//!  - `magic`: The `PSCK` ASCII bytes.
+//!  - `version`: The header format version. Currently, `1`.
+//!  - `transfer id`: The SHA-256 digest of the full payload.
+//!  - `index`: The zero-based chunk index, big-endian encoded.
+//!  - `total`: The total number of chunks, big-endian encoded.
+//!  - `payload len`: The full payload length in bytes, big-endian encoded.
+//!
+//! Nodes not supporting chunking see the chunk messages as opaque messages.
+
+use sha2::{Digest, Sha256};
+
+/// The chunk header magic bytes.
+const CHUNK_MAGIC: [u8; 4] = *b"PSCK";
+
+/// The chunk header format version.
+const CHUNK_VERSION: u8 = 1;
+
+/// The chunk header length in bytes.
+pub(crate) const CHUNK_HEADER_LEN: usize = 4 + 1 + 32 + 4 + 4 + 8;
+
+/// A chunked transfer identifier: the SHA-256 digest of the full payload.
+pub(crate) type TransferId = [u8; 32];
+
+/// The header prepended to each chunk message payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ChunkHeader {
+    /// The transfer identifier.
+    pub(crate) transfer_id: TransferId,
+    /// The zero-based chunk index.
+    pub(crate) index: u32,
+    /// The total number of chunks.
+    pub(crate) total: u32,
+    /// The full payload length in bytes.
+    pub(crate) payload_len: u64,
+}
+
+impl ChunkHeader {
+    /// Encode the header into the given buffer.
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&CHUNK_MAGIC);
+        buf.push(CHUNK_VERSION);
+        buf.extend_from_slice(&self.transfer_id);
+        buf.extend_from_slice(&self.index.to_be_bytes());
+        buf.extend_from_slice(&self.total.to_be_bytes());
+        buf.extend_from_slice(&self.payload_len.to_be_bytes());
+    }
+
+    /// Decode a chunk message payload into its header and the chunk bytes.
+    ///
+    /// Returns `None` if the payload is not a valid chunk message payload.
+    pub(crate) fn decode(data: &[u8]) -> Option<(Self, &[u8])> {
+        if data.len() < CHUNK_HEADER_LEN {
+            return None;
+        }
+
+        let (header, chunk) = data.split_at(CHUNK_HEADER_LEN);
+        let (magic, header) = header.split_at(CHUNK_MAGIC.len());
+        let (version, header) = header.split_at(1);
+        if magic != CHUNK_MAGIC || version[0] != CHUNK_VERSION {
+            return None;
+        }
+
+        let (transfer_id, header) = header.split_at(32);
+        let (index, header) = header.split_at(4);
+        let (total, payload_len) = header.split_at(4);
+
+        let header = Self {
+            transfer_id: transfer_id.try_into().ok()?,
+            index: u32::from_be_bytes(index.try_into().ok()?),
+            total: u32::from_be_bytes(total.try_into().ok()?),
+            payload_len: u64::from_be_bytes(payload_len.try_into().ok()?),
+        };
+        if header.total == 0 || header.index >= header.total {
+            return None;
+        }
+
+        Some((header, chunk))
+    }
+}
+
+/// Compute the transfer identifier of a payload.
+pub(crate) fn transfer_id(payload: &[u8]) -> TransferId {
+    Sha256::digest(payload).into()
+}
+
+/// Split the payload into chunk message payloads of, at most, `chunk_size` payload bytes each
+/// (plus the chunk header).
+///
+/// Returns `None` if the chunk size is zero or the number of chunks does not fit in a `u32`.
+pub(crate) fn split_into_chunks(payload: &[u8], chunk_size: usize) -> Option<Vec<Vec<u8>>> {
+    if chunk_size == 0 {
+        return None;
+    }
+
+    let transfer_id = transfer_id(payload);
+    let total = u32::try_from(((payload.len() + chunk_size - 1) / chunk_size).max(1)).ok()?;
+
+    let chunks = (0..total)
+        .map(|index| {
+            let start = (index as usize * chunk_size).min(payload.len());
+            let end = (start + chunk_size).min(payload.len());
+
+            let header = ChunkHeader {
+                transfer_id,
+                index,
+                total,
+                payload_len: payload.len() as u64,
+            };
+
+            let mut chunk = Vec::with_capacity(CHUNK_HEADER_LEN + end - start);
+            header.encode_into(&mut chunk);
+            chunk.extend_from_slice(&payload[start..end]);
+            chunk
+        })
+        .collect();
+
+    Some(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_chunks_round_trip() {
+        //// Given
+        let payload = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+
+        //// When
+        let chunks = split_into_chunks(&payload, 300).expect("valid chunk size");
+
+        //// Then
+        assert_eq!(chunks.len(), 4);
+
+        let mut reassembled = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let (header, bytes) = ChunkHeader::decode(chunk).expect("valid chunk");
+            assert_eq!(header.transfer_id, transfer_id(&payload));
+            assert_eq!(header.index, i as u32);
+            assert_eq!(header.total, 4);
+            assert_eq!(header.payload_len, 1000);
+            reassembled.extend_from_slice(bytes);
+        }
+        assert_eq!(reassembled, payload);
+    }
+
+    #[test]
+    fn decode_rejects_non_chunk_payloads() {
+        //// Given
+        let short = b"PSCK".to_vec();
+        let opaque = vec![0u8; CHUNK_HEADER_LEN + 10];
+
+        let mut out_of_range = split_into_chunks(b"payload", 4).unwrap().remove(0);
+        out_of_range[37..41].copy_from_slice(&7u32.to_be_bytes());
+
+        //// Then
+        assert!(ChunkHeader::decode(&short).is_none());
+        assert!(ChunkHeader::decode(&opaque).is_none());
+        assert!(ChunkHeader::decode(&out_of_range).is_none());
+    }
+}
@@ -1,10 +1,34 @@
+use std::fmt;
+use std::rc::Rc;
 use std::time::Duration;
 
+use libp2p::identity::PeerId;
+
+use crate::authorization::{SharedTopicAuthorizer, TopicAuthorizer};
+use crate::egress::BytesPerSecond;
+use crate::gate::{ConnectionGate, GateDecision, SharedConnectionGate};
+use crate::message_id::{default_message_id_fn, DedupScope, MessageId, MessageRef};
+use crate::preflight::{PreflightCode, PreflightWarning};
+use crate::sampling::SamplingConfig;
+use crate::services::framing::{FrameLimitPolicy, FrameLimits};
+use crate::services::message_cache::DedupPersistenceConfig;
+use crate::topic::TopicValidation;
+
+/// The default maximum byte size for each pubsub frame (see [`Config::max_frame_size`]).
+pub(crate) const DEFAULT_MAX_FRAME_SIZE: usize = 65537;
+
+/// The pubsub network behaviour configuration.
+///
+/// Two configurations compare equal if all their fields are equal, the topic authorizer and the
+/// connection gate being compared by presence only (see [`Config::diff`]).
 #[derive(Debug, Clone)]
 pub struct Config {
     /// The maximum size of a RPC frame.
     max_frame_size: usize,
 
+    /// The maximum encoded size of a message published by the local node.
+    max_publish_size: Option<usize>,
+
     /// The idle timeout of a connection.
     connection_idle_timeout: Duration,
 
@@ -20,22 +44,548 @@ pub struct Config {
 
     /// Message cache entries Time-To-Live.
     message_cache_ttl: Duration,
+
+    /// Message cache total messages size limit, in bytes.
+    message_cache_max_bytes: Option<usize>,
+
+    /// The maximum number of times a peer can re-send an already seen message before being
+    /// flagged as misbehaving.
+    max_duplicate_resends: usize,
+
+    /// The time during which the frames received from a peer flagged as duplicate flooder are
+    /// ignored.
+    duplicate_flood_cooldown: Option<Duration>,
+
+    /// Whether to notify the first echo of the locally published messages.
+    detect_echo: bool,
+
+    /// Whether to deliver the locally published messages to the local node too.
+    deliver_own_messages: bool,
+
+    /// Whether to notify the messages published while no connected peer is subscribed to their
+    /// topic.
+    warn_on_no_subscribers: bool,
+
+    /// The time window during which the messages destined to a peer are batched into a single
+    /// frame.
+    publish_batch_window: Option<Duration>,
+
+    /// The time window during which the local subscription changes are held before being
+    /// announced to the remote peers.
+    subscription_debounce: Option<Duration>,
+
+    /// The number of connected peers up to which the known peers are dialed.
+    target_peer_count: usize,
+
+    /// The maximum number of consecutive failed dial attempts before a known peer is pruned.
+    max_dial_attempts: usize,
+
+    /// The time to wait before retrying a failed dial to a known peer.
+    dial_backoff: Duration,
+
+    /// The maximum number of chunked transfers reassembled concurrently.
+    max_concurrent_transfers: usize,
+
+    /// The maximum payload size of a chunked transfer.
+    max_transfer_size: usize,
+
+    /// The time to wait for all the chunks of a chunked transfer to be received.
+    transfer_timeout: Duration,
+
+    /// The maximum number of known remote peers whose subscriptions are tracked.
+    max_known_remote_peers: usize,
+
+    /// The clock skew tolerated when checking the expiry time of the received messages.
+    message_ttl_clock_skew: Duration,
+
+    /// The maximum number of interned topic hashes.
+    max_interned_topics: usize,
+
+    /// The maximum size of the received messages key field.
+    max_key_size: usize,
+
+    /// The topic strings validation rules.
+    topic_validation: TopicValidation,
+
+    /// The maximum time a message is held back on the ordered delivery topics.
+    ordering_max_delay: Duration,
+
+    /// The maximum number of held back messages per ordered delivery topic and author.
+    ordering_max_held_messages: usize,
+
+    /// The number of heartbeat intervals the message cache history spans.
+    history_length: usize,
+
+    /// The number of heartbeat intervals of message history notified to the protocol routers.
+    history_gossip: usize,
+
+    /// The maximum number of attempts to send the local subscriptions to a connected peer.
+    subscription_sync_max_attempts: usize,
+
+    /// The time to wait before retrying a failed subscription sync with a connected peer.
+    subscription_sync_retry_interval: Duration,
+
+    /// The maximum number of topics announced to a newly connected peer per subscriptions frame.
+    subscription_announce_batch: Option<usize>,
+
+    /// The time between two subscriptions frames of an announcement to a newly connected peer.
+    subscription_announce_interval: Duration,
+
+    /// The maximum number of peers a received message is forwarded to.
+    max_forward_fanout: Option<usize>,
+
+    /// The maximum number of peers a locally published message is sent to.
+    max_publish_fanout: Option<usize>,
+
+    /// Whether to record the intentionally dropped inputs as dead letters.
+    dead_letter: bool,
+
+    /// The maximum number of dead letters kept.
+    dead_letter_capacity: usize,
+
+    /// Whether to emit the recorded dead letters as behaviour events.
+    dead_letter_events: bool,
+
+    /// The per-peer framing statistics sliding window duration.
+    framing_stats_window: Duration,
+
+    /// The average number of messages per frame below which a peer framing is inefficient.
+    inefficient_framing_threshold: f64,
+
+    /// The message rate, in messages per second, above which an inefficient peer framing is
+    /// notified.
+    inefficient_framing_min_message_rate: f64,
+
+    /// The minimum time between two inefficient framing notifications of the same peer.
+    inefficient_framing_event_interval: Duration,
+
+    /// The time after the first connection during which the received messages are not forwarded.
+    forward_warmup: Option<Duration>,
+
+    /// Whether to track the remote peer subscriptions per connection.
+    connection_scoped_subscriptions: bool,
+
+    /// The scope the seen messages are deduplicated in.
+    dedup_scope: DedupScope,
+
+    /// The message id function used for the topics subscribed without a message id function.
+    default_message_id_fn: fn(Option<&PeerId>, &MessageRef) -> MessageId,
+
+    /// The minimum time between two re-sendings of the local subscriptions to the same peer.
+    subscription_refresh_cooldown: Duration,
+
+    /// The time after a subscription refresh request during which the peer announced topics are
+    /// attributed to the refresh.
+    subscription_refresh_window: Duration,
+
+    /// The time without send failures after which a peer suspected frame size limit is dropped.
+    peer_frame_limit_decay: Duration,
+
+    /// The per-peer subscription churn sliding window duration.
+    subscription_churn_window: Duration,
+
+    /// The subscription transitions rate, in transitions per minute, from which a peer is
+    /// notified as flapping a topic subscription.
+    subscription_flapping_threshold: f64,
+
+    /// Whether to flag the unsigned received messages claiming to be authored by a directly
+    /// connected peer, but propagated by another peer.
+    verify_from_matches_signature: bool,
+
+    /// Whether to drop the received messages suspected to be spoofed.
+    drop_suspected_spoofed: bool,
+
+    /// The maximum age of the cached messages backfilled to a newly subscribed peer.
+    backfill_max_age: Duration,
+
+    /// The maximum number of messages backfilled to a newly subscribed peer, per topic.
+    backfill_max_messages: usize,
+
+    /// The maximum number of message forwards released per behaviour poll cycle.
+    max_forwards_per_poll: usize,
+
+    /// The maximum number of message forwards released to a single peer per behaviour poll cycle.
+    max_peer_forwards_per_poll: usize,
+
+    /// The authorizer consulted on the peer subscription announcements and received messages.
+    topic_authorizer: Option<SharedTopicAuthorizer>,
+
+    /// The minimum time between two unauthorized message events for the same peer and topic.
+    unauthorized_message_event_interval: Duration,
+
+    /// The persistent message dedup store configuration. If `None`, the message cache is
+    /// memory-only.
+    dedup_persistence: Option<DedupPersistenceConfig>,
+
+    /// The gate consulted when a connection is established, before its handler is created.
+    connection_gate: Option<SharedConnectionGate>,
+
+    /// Whether the sent frames carry a checksum trailer, and the received frames checksum
+    /// trailers are verified.
+    frame_diagnostics: bool,
+
+    /// The sliding window over which the peers outbound send health is tracked.
+    send_health_window: Duration,
+
+    /// The failed to attempted sends ratio above which a peer is suspected of asymmetric
+    /// connectivity.
+    asymmetric_connectivity_threshold: f64,
+
+    /// The minimum number of sends to a peer over the send health window to suspect it of
+    /// asymmetric connectivity.
+    asymmetric_connectivity_min_sends: usize,
+
+    /// Whether the peers suspected of asymmetric connectivity are deprioritized as message forward
+    /// destinations.
+    deprioritize_asymmetric_peers: bool,
+
+    /// The minimum time between two topic hash mismatch events for the same received topic.
+    topic_hash_mismatch_event_interval: Duration,
+
+    /// The maximum number of control messages per received frame.
+    max_control_entries_per_frame: usize,
+
+    /// The maximum number of message ids per received IHAVE control message.
+    max_ihave_ids_per_message: usize,
+
+    /// The maximum number of IWANT message ids per received frame.
+    max_iwant_ids_per_frame: usize,
+
+    /// The maximum number of subscription actions per received frame.
+    max_subscriptions_per_frame: usize,
+
+    /// How the received frames exceeding a limit are handled.
+    frame_limit_policy: FrameLimitPolicy,
+
+    /// The maximum number of cached received frames and messages validation failures.
+    negative_validation_cache_capacity: usize,
+
+    /// The time a received frame or message validation failure is cached.
+    negative_validation_cache_ttl: Duration,
+
+    /// The sliding window over which the delivered messages provenance is accounted.
+    provenance_stats_window: Duration,
+
+    /// The interval at which a topic activity summary event is emitted, if enabled.
+    activity_summaries: Option<Duration>,
+
+    /// The maximum number of topics reported individually in a topic activity summary.
+    activity_summary_max_topics: usize,
+
+    /// The interval at which the protocol router state is synced with the behaviour, if enabled.
+    router_sync_interval: Option<Duration>,
+
+    /// The silence duration after which the subscribed topics silence watchdog fires, if enabled.
+    silence_watchdog: Option<Duration>,
+
+    /// The maximum number of messages served to a peer's IWANT requests per heartbeat interval.
+    max_iwant_served_per_peer_per_heartbeat: usize,
+
+    /// The maximum number of times the same message is served to the same peer's IWANT requests.
+    max_iwant_serves_per_message: usize,
+
+    /// The number of messages of a received frame above which the frame messages are validated in
+    /// parallel.
+    parallel_validation_threshold: usize,
+
+    /// The raw frames sampling configuration. If `None`, no frame is sampled.
+    frame_sampling: Option<SamplingConfig>,
+
+    /// The egress bandwidth budget of the frames sent to the remote peers. If `None`, the frames
+    /// are not paced.
+    egress_budget: Option<BytesPerSecond>,
+
+    /// Whether to emit the peers' first confirmation of the local subscriptions as behaviour
+    /// events.
+    subscription_confirmation_events: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            max_frame_size: 65537,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_publish_size: None,
             connection_idle_timeout: Duration::from_secs(120),
             max_connection_send_retry_attempts: 2,
             heartbeat_interval: Duration::from_secs(1),
             message_cache_capacity: 1024,
             message_cache_ttl: Duration::from_secs(5),
+            message_cache_max_bytes: None,
+            max_duplicate_resends: 16,
+            duplicate_flood_cooldown: None,
+            detect_echo: false,
+            deliver_own_messages: false,
+            warn_on_no_subscribers: true,
+            publish_batch_window: None,
+            subscription_debounce: None,
+            target_peer_count: 8,
+            max_dial_attempts: 5,
+            dial_backoff: Duration::from_secs(1),
+            max_concurrent_transfers: 8,
+            max_transfer_size: 16 * 1024 * 1024,
+            transfer_timeout: Duration::from_secs(60),
+            max_known_remote_peers: 1024,
+            message_ttl_clock_skew: Duration::from_secs(1),
+            max_interned_topics: 4096,
+            max_key_size: 2048,
+            topic_validation: TopicValidation::default(),
+            ordering_max_delay: Duration::from_secs(1),
+            ordering_max_held_messages: 64,
+            history_length: 5,
+            history_gossip: 3,
+            subscription_sync_max_attempts: 3,
+            subscription_sync_retry_interval: Duration::from_secs(1),
+            subscription_announce_batch: None,
+            subscription_announce_interval: Duration::from_millis(100),
+            max_forward_fanout: None,
+            max_publish_fanout: None,
+            dead_letter: false,
+            dead_letter_capacity: 256,
+            dead_letter_events: false,
+            framing_stats_window: Duration::from_secs(10),
+            inefficient_framing_threshold: 1.5,
+            inefficient_framing_min_message_rate: 100.0,
+            inefficient_framing_event_interval: Duration::from_secs(60),
+            forward_warmup: None,
+            connection_scoped_subscriptions: false,
+            dedup_scope: DedupScope::Global,
+            default_message_id_fn,
+            subscription_refresh_cooldown: Duration::from_secs(30),
+            subscription_refresh_window: Duration::from_secs(2),
+            peer_frame_limit_decay: Duration::from_secs(300),
+            subscription_churn_window: Duration::from_secs(60),
+            subscription_flapping_threshold: 10.0,
+            verify_from_matches_signature: false,
+            drop_suspected_spoofed: false,
+            backfill_max_age: Duration::from_secs(5),
+            backfill_max_messages: 16,
+            max_forwards_per_poll: 1024,
+            max_peer_forwards_per_poll: 128,
+            topic_authorizer: None,
+            unauthorized_message_event_interval: Duration::from_secs(60),
+            dedup_persistence: None,
+            connection_gate: None,
+            frame_diagnostics: false,
+            send_health_window: Duration::from_secs(30),
+            asymmetric_connectivity_threshold: 0.5,
+            asymmetric_connectivity_min_sends: 8,
+            deprioritize_asymmetric_peers: true,
+            topic_hash_mismatch_event_interval: Duration::from_secs(60),
+            max_control_entries_per_frame: 1024,
+            max_ihave_ids_per_message: 5000,
+            max_iwant_ids_per_frame: 5000,
+            max_subscriptions_per_frame: 5000,
+            frame_limit_policy: FrameLimitPolicy::Truncate,
+            negative_validation_cache_capacity: 256,
+            negative_validation_cache_ttl: Duration::from_secs(60),
+            provenance_stats_window: Duration::from_secs(60),
+            activity_summaries: None,
+            activity_summary_max_topics: 64,
+            router_sync_interval: None,
+            silence_watchdog: None,
+            max_iwant_served_per_peer_per_heartbeat: 0,
+            max_iwant_serves_per_message: 3,
+            parallel_validation_threshold: 128,
+            frame_sampling: None,
+            egress_budget: None,
+            subscription_confirmation_events: false,
         }
     }
 }
 
+impl PartialEq for Config {
+    fn eq(&self, other: &Self) -> bool {
+        self.diff(other).is_empty()
+    }
+}
+
+/// A field differing between two configurations (see [`Config::diff`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConfigDiff {
+    /// The field name.
+    pub field: &'static str,
+    /// The field value in the configuration compared.
+    pub left: String,
+    /// The field value in the other configuration.
+    pub right: String,
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.left, self.right)
+    }
+}
+
 impl Config {
+    /// Create a new config builder with the default configuration values.
+    #[must_use]
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+
+    /// List the fields differing from the other configuration, with both values formatted, e.g.,
+    /// to log the effective configuration differences between nodes.
+    ///
+    /// The topic authorizer and the connection gate can not be compared, their values are only
+    /// told apart by presence (`Some(..)` or `None`).
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> Vec<ConfigDiff> {
+        self.fields()
+            .into_iter()
+            .zip(other.fields())
+            .filter(|((_, left), (_, right))| left != right)
+            .map(|((field, left), (_, right))| ConfigDiff { field, left, right })
+            .collect()
+    }
+
+    /// Check the configuration values consistency, e.g., before the node joins the network.
+    ///
+    /// Only the checks not depending on the local subscriptions are performed. See
+    /// [`Behaviour::preflight`](crate::Behaviour::preflight) for the complete checks.
+    #[must_use]
+    pub fn preflight(&self) -> Vec<PreflightWarning> {
+        let mut warnings = Vec::new();
+
+        if self.heartbeat_interval > self.message_cache_ttl {
+            warnings.push(PreflightWarning::new(
+                PreflightCode::HeartbeatExceedsCacheTtl,
+                "the heartbeat interval is longer than the message cache TTL, the seen messages \
+                 outlive the TTL",
+                vec![
+                    (
+                        "heartbeat_interval",
+                        format!("{:?}", self.heartbeat_interval),
+                    ),
+                    ("message_cache_ttl", format!("{:?}", self.message_cache_ttl)),
+                ],
+            ));
+        }
+
+        if self.history_gossip > self.history_length {
+            warnings.push(PreflightWarning::new(
+                PreflightCode::HistoryGossipExceedsHistoryLength,
+                "the notified message history is longer than the message cache history",
+                vec![
+                    ("history_gossip", self.history_gossip.to_string()),
+                    ("history_length", self.history_length.to_string()),
+                ],
+            ));
+        }
+
+        warnings
+    }
+
+    /// The configuration fields names and formatted values.
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        macro_rules! fields {
+            ($($field:ident),* $(,)?) => {
+                vec![$((stringify!($field), format!("{:?}", self.$field))),*]
+            };
+        }
+
+        /// The presence marker of a non-comparable field.
+        fn presence<T>(value: &Option<T>) -> String {
+            match value {
+                Some(_) => "Some(..)".to_string(),
+                None => "None".to_string(),
+            }
+        }
+
+        let mut fields = fields![
+            max_frame_size,
+            max_publish_size,
+            connection_idle_timeout,
+            max_connection_send_retry_attempts,
+            heartbeat_interval,
+            message_cache_capacity,
+            message_cache_ttl,
+            message_cache_max_bytes,
+            max_duplicate_resends,
+            duplicate_flood_cooldown,
+            detect_echo,
+            deliver_own_messages,
+            warn_on_no_subscribers,
+            publish_batch_window,
+            subscription_debounce,
+            target_peer_count,
+            max_dial_attempts,
+            dial_backoff,
+            max_concurrent_transfers,
+            max_transfer_size,
+            transfer_timeout,
+            max_known_remote_peers,
+            message_ttl_clock_skew,
+            max_interned_topics,
+            max_key_size,
+            topic_validation,
+            ordering_max_delay,
+            ordering_max_held_messages,
+            history_length,
+            history_gossip,
+            subscription_sync_max_attempts,
+            subscription_sync_retry_interval,
+            subscription_announce_batch,
+            subscription_announce_interval,
+            max_forward_fanout,
+            max_publish_fanout,
+            dead_letter,
+            dead_letter_capacity,
+            dead_letter_events,
+            framing_stats_window,
+            inefficient_framing_threshold,
+            inefficient_framing_min_message_rate,
+            inefficient_framing_event_interval,
+            forward_warmup,
+            connection_scoped_subscriptions,
+            dedup_scope,
+            subscription_refresh_cooldown,
+            subscription_refresh_window,
+            peer_frame_limit_decay,
+            subscription_churn_window,
+            subscription_flapping_threshold,
+            verify_from_matches_signature,
+            drop_suspected_spoofed,
+            backfill_max_age,
+            backfill_max_messages,
+            max_forwards_per_poll,
+            max_peer_forwards_per_poll,
+            unauthorized_message_event_interval,
+            dedup_persistence,
+            frame_diagnostics,
+            send_health_window,
+            asymmetric_connectivity_threshold,
+            asymmetric_connectivity_min_sends,
+            deprioritize_asymmetric_peers,
+            topic_hash_mismatch_event_interval,
+            max_control_entries_per_frame,
+            max_ihave_ids_per_message,
+            max_iwant_ids_per_frame,
+            max_subscriptions_per_frame,
+            frame_limit_policy,
+            negative_validation_cache_capacity,
+            negative_validation_cache_ttl,
+            provenance_stats_window,
+            activity_summaries,
+            activity_summary_max_topics,
+            router_sync_interval,
+            silence_watchdog,
+            max_iwant_served_per_peer_per_heartbeat,
+            max_iwant_serves_per_message,
+            parallel_validation_threshold,
+            frame_sampling,
+            egress_budget,
+            subscription_confirmation_events,
+        ];
+        fields.push((
+            "default_message_id_fn",
+            format!("{:p}", self.default_message_id_fn as *const ()),
+        ));
+        fields.push(("topic_authorizer", presence(&self.topic_authorizer)));
+        fields.push(("connection_gate", presence(&self.connection_gate)));
+        fields
+    }
+
     /// The maximum byte size for each pubsub frame (default is 65536 bytes).
     ///
     /// This represents the maximum size of the entire protobuf payload. It must be at least
@@ -46,6 +596,25 @@ impl Config {
         self.max_frame_size
     }
 
+    /// The maximum encoded size, in bytes, of a message published by the local node, including
+    /// its topic, key and signature. Larger messages are rejected by
+    /// [`Behaviour::publish`](crate::Behaviour::publish) with a
+    /// [`PublishError::MessageTooLarge`](crate::PublishError::MessageTooLarge) error.
+    ///
+    /// This is a local publishing policy, independent of the [maximum frame
+    /// size](Config::max_frame_size), which still bounds the messages received and forwarded.
+    ///
+    /// Default is `None`, i.e., only the maximum frame size applies.
+    pub fn max_publish_size(&self) -> Option<usize> {
+        self.max_publish_size
+    }
+
+    /// Update the maximum frame size (see
+    /// [`Behaviour::set_max_frame_size`](crate::Behaviour::set_max_frame_size)).
+    pub(crate) fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
     /// The time a connection is maintained to a peer without being in the mesh and without
     /// send/receiving a message from. Connections that idle beyond this timeout are disconnected.
     ///
@@ -82,4 +651,1867 @@ impl Config {
     pub fn message_cache_ttl(&self) -> Duration {
         self.message_cache_ttl
     }
+
+    /// The maximum total size, in bytes, of the cached messages, each message costing its encoded
+    /// size. Once exceeded, the oldest messages are evicted, in addition to the
+    /// [capacity](Config::message_cache_capacity) eviction. A `None`, or zero, limit only bounds
+    /// the cache by its capacity.
+    ///
+    /// Default is `None`.
+    pub fn message_cache_max_bytes(&self) -> Option<usize> {
+        self.message_cache_max_bytes
+    }
+
+    /// The maximum number of times a peer can re-send a message already present in the message
+    /// cache before being flagged as misbehaving. The first receipt of a message from each peer is
+    /// not counted as a resend.
+    ///
+    /// When the threshold is exceeded, a [`PeerMisbehaving`](crate::Event::PeerMisbehaving) event
+    /// is emitted.
+    ///
+    /// Default is 16.
+    pub fn max_duplicate_resends(&self) -> usize {
+        self.max_duplicate_resends
+    }
+
+    /// The time during which the frames received from a peer flagged as duplicate flooder are
+    /// dropped without being processed.
+    ///
+    /// If `None`, the peer is only reported and its frames are still processed.
+    ///
+    /// Default is `None`.
+    pub fn duplicate_flood_cooldown(&self) -> Option<Duration> {
+        self.duplicate_flood_cooldown
+    }
+
+    /// Whether to notify the first time a message published by the local node is received back
+    /// from a remote peer (see [`Event::PublishedMessageEchoed`](crate::Event::PublishedMessageEchoed)).
+    ///
+    /// The published message ids are tracked in the seen messages cache, so an echo is only
+    /// detected while the message is cached (see [`Config::message_cache_ttl`]).
+    ///
+    /// Default is `false`.
+    pub fn detect_echo(&self) -> bool {
+        self.detect_echo
+    }
+
+    /// Whether to deliver the messages published by the local node to the local node too.
+    ///
+    /// If enabled, a successful publish also emits an
+    /// [`Event::MessageReceived`](crate::Event::MessageReceived) event, from the local peer and
+    /// flagged as local, carrying the metadata passed to
+    /// [`Behaviour::publish_with_metadata`](crate::Behaviour::publish_with_metadata), if any. The
+    /// published message is recorded in the seen messages cache, so its echo received from the
+    /// remote peers is not delivered again. A message re-published while still cached is not
+    /// delivered again either, nor are the chunks of a chunked message.
+    ///
+    /// Default is `false`.
+    pub fn deliver_own_messages(&self) -> bool {
+        self.deliver_own_messages
+    }
+
+    /// Whether to notify the messages published while no connected peer is known to be
+    /// subscribed to their topic (see
+    /// [`Event::PublishedWithoutSubscribers`](crate::Event::PublishedWithoutSubscribers)), e.g.,
+    /// when publishing to a misspelled topic.
+    ///
+    /// The message is still published, as the peers subscriptions may not be announced yet.
+    ///
+    /// Default is `true`.
+    pub fn warn_on_no_subscribers(&self) -> bool {
+        self.warn_on_no_subscribers
+    }
+
+    /// The time window during which the messages destined to a peer are buffered before being
+    /// flushed as a single multi-message frame. A batch is flushed earlier if adding a new message
+    /// would exceed the [maximum frame size](Config::max_frame_size). Subscription requests and
+    /// control messages are never batched.
+    ///
+    /// If `None`, the messages are sent immediately, one message per frame.
+    ///
+    /// Default is `None`.
+    pub fn publish_batch_window(&self) -> Option<Duration> {
+        self.publish_batch_window
+    }
+
+    /// The time window during which the local subscription changes are held before being
+    /// announced to the remote peers. The changes to a topic are netted out during the window and
+    /// only the final subscription state is announced, e.g., an unsubscription followed by a
+    /// subscription to the same topic within the window announces nothing.
+    ///
+    /// The local subscription state changes apply immediately.
+    ///
+    /// If `None`, the subscription changes are announced immediately.
+    ///
+    /// Default is `None`.
+    pub fn subscription_debounce(&self) -> Option<Duration> {
+        self.subscription_debounce
+    }
+
+    /// The number of connected peers up to which the known peers (see
+    /// [`Behaviour::add_known_peer`](crate::Behaviour::add_known_peer)) are dialed. The known
+    /// peers are only dialed while the local node is subscribed to at least one topic.
+    ///
+    /// Default is 8.
+    pub fn target_peer_count(&self) -> usize {
+        self.target_peer_count
+    }
+
+    /// The maximum number of consecutive failed dial attempts before a known peer is pruned.
+    ///
+    /// Default is 5.
+    pub fn max_dial_attempts(&self) -> usize {
+        self.max_dial_attempts
+    }
+
+    /// The time to wait before retrying a failed dial to a known peer. The backoff doubles after
+    /// each consecutive failed dial attempt, up to 5 minutes.
+    ///
+    /// Default is 1 second.
+    pub fn dial_backoff(&self) -> Duration {
+        self.dial_backoff
+    }
+
+    /// The maximum number of chunked transfers (see
+    /// [`Behaviour::publish_chunked`](crate::Behaviour::publish_chunked)) reassembled
+    /// concurrently. The chunks of new transfers received while the limit is reached are dropped.
+    ///
+    /// Default is 8.
+    pub fn max_concurrent_transfers(&self) -> usize {
+        self.max_concurrent_transfers
+    }
+
+    /// The maximum payload size of a chunked transfer. The chunks of larger transfers are dropped.
+    ///
+    /// Default is 16 MiB.
+    pub fn max_transfer_size(&self) -> usize {
+        self.max_transfer_size
+    }
+
+    /// The time to wait for all the chunks of a chunked transfer to be received. Once elapsed,
+    /// the transfer's received chunks are discarded.
+    ///
+    /// Default is 60 seconds.
+    pub fn transfer_timeout(&self) -> Duration {
+        self.transfer_timeout
+    }
+
+    /// The maximum number of known remote peers whose subscriptions are tracked. The known remote
+    /// peers are the peers the local node is not connected to, whose subscriptions were learned
+    /// from third parties, e.g., the peer exchange of a `Prune` control message. Once the limit is
+    /// reached, the least recently learned known remote peers are evicted.
+    ///
+    /// Default is 1024.
+    pub fn max_known_remote_peers(&self) -> usize {
+        self.max_known_remote_peers
+    }
+
+    /// The clock skew tolerated when checking the expiry time of the messages published with a
+    /// time-to-live (see [`Message::set_ttl`](crate::Message::set_ttl)). A message is only
+    /// considered expired once its expiry time plus this tolerance has passed.
+    ///
+    /// Default is 1 second.
+    pub fn message_ttl_clock_skew(&self) -> Duration {
+        self.message_ttl_clock_skew
+    }
+
+    /// The maximum number of interned topic hashes. The topic hashes of the received messages and
+    /// subscription actions are interned, so the repeated decodes of the same topic share one
+    /// allocation. Once the limit is reached, the least recently received topic hashes are
+    /// evicted. Set to 0 to disable the interning.
+    ///
+    /// Default is 4096.
+    pub fn max_interned_topics(&self) -> usize {
+        self.max_interned_topics
+    }
+
+    /// The maximum size in bytes of the received messages key field. The key carries the
+    /// public key the message signature is verified with, e.g., when it cannot be inlined into
+    /// the author peer ID. The received messages with a larger key are dropped, and notified as
+    /// [`MessageValidationError::KeyTooLarge`](crate::MessageValidationError::KeyTooLarge)
+    /// validation errors.
+    ///
+    /// Default is 2048.
+    pub fn max_key_size(&self) -> usize {
+        self.max_key_size
+    }
+
+    /// The validation rules of the topic strings. The local subscriptions to an invalid topic are
+    /// rejected with a [`TopicValidationError`](crate::TopicValidationError), and the invalid
+    /// topics announced by the remote peers are dropped.
+    ///
+    /// Default is a maximum length of 1024 bytes, no control characters, and no Unicode
+    /// replacement characters.
+    pub fn topic_validation(&self) -> &TopicValidation {
+        &self.topic_validation
+    }
+
+    /// The maximum time a message received on a topic subscribed with ordered delivery (see
+    /// [`SubscriptionBuilder::ordered_delivery`](crate::SubscriptionBuilder::ordered_delivery)) is
+    /// held back waiting for the messages missing before it.
+    ///
+    /// Default is 1 second.
+    pub fn ordering_max_delay(&self) -> Duration {
+        self.ordering_max_delay
+    }
+
+    /// The maximum number of messages held back per ordered delivery topic and author. Once the
+    /// limit is reached, the held back messages are delivered skipping the missing ones.
+    ///
+    /// Default is 64.
+    pub fn ordering_max_held_messages(&self) -> usize {
+        self.ordering_max_held_messages
+    }
+
+    /// The number of heartbeat intervals the message cache history spans. The message cache
+    /// records the ids of the messages seen on each topic during each heartbeat interval, and
+    /// drops the records older than this number of heartbeat intervals.
+    ///
+    /// Default is 5.
+    pub fn history_length(&self) -> usize {
+        self.history_length
+    }
+
+    /// The number of heartbeat intervals of message history notified to the protocol routers on
+    /// every heartbeat (see [`ProtocolRouterInEvent::MessageHistory`](
+    /// crate::protocol::ProtocolRouterInEvent::MessageHistory)). Must not be greater than the
+    /// [history length](Config::history_length). Set to 0 to disable the notification.
+    ///
+    /// Default is 3.
+    pub fn history_gossip(&self) -> usize {
+        self.history_gossip
+    }
+
+    /// The maximum number of attempts to send the local subscriptions to a newly connected peer.
+    /// A subscription sync fails if the subscriptions frame exceeds the [maximum frame size](
+    /// Config::max_frame_size), or if the peer has no enabled connection. Once the limit is
+    /// reached, an [`Event::SubscriptionSyncFailed`](crate::Event::SubscriptionSyncFailed) event
+    /// is emitted.
+    ///
+    /// Default is 3.
+    pub fn subscription_sync_max_attempts(&self) -> usize {
+        self.subscription_sync_max_attempts
+    }
+
+    /// The time to wait before retrying a failed subscription sync with a connected peer. The
+    /// sync is also retried as soon as a new connection to the peer is established.
+    ///
+    /// Default is 1 second.
+    pub fn subscription_sync_retry_interval(&self) -> Duration {
+        self.subscription_sync_retry_interval
+    }
+
+    /// The maximum number of topics announced to a newly connected peer per subscriptions frame.
+    ///
+    /// The local subscriptions are announced to a newly connected peer in batches of this number
+    /// of topics, spaced by the [announcement interval](Config::subscription_announce_interval),
+    /// instead of a single burst. The topics subscribed while an announcement is in flight are
+    /// appended to it. The announcement is aborted if the peer disconnects. If `None`, all the
+    /// local subscriptions are announced at once.
+    ///
+    /// Default is `None`.
+    pub fn subscription_announce_batch(&self) -> Option<usize> {
+        self.subscription_announce_batch
+    }
+
+    /// The time between two subscriptions frames of an announcement to a newly connected peer
+    /// (see [`Config::subscription_announce_batch`]).
+    ///
+    /// Default is 100 milliseconds.
+    pub fn subscription_announce_interval(&self) -> Duration {
+        self.subscription_announce_interval
+    }
+
+    /// The maximum number of peers a received message is forwarded to. The destination peers are
+    /// picked deterministically per message by the protocol router (see [`ForwardFanout`](crate::ForwardFanout)), so
+    /// each relay forwards a message to a different subset of its subscribed peers.
+    ///
+    /// If `None`, the messages are forwarded to all the subscribed peers.
+    ///
+    /// Default is `None`.
+    pub fn max_forward_fanout(&self) -> Option<usize> {
+        self.max_forward_fanout
+    }
+
+    /// The maximum number of peers a locally published message is sent to. Usually higher than
+    /// the [forwarding fan-out](Config::max_forward_fanout), as the local node is the only source
+    /// of its published messages.
+    ///
+    /// If `None`, the messages are sent to all the subscribed peers.
+    ///
+    /// Default is `None`.
+    pub fn max_publish_fanout(&self) -> Option<usize> {
+        self.max_publish_fanout
+    }
+
+    /// Whether to record the inputs intentionally dropped by the behaviour, e.g., the received
+    /// messages of unsubscribed topics or the frames exceeding the maximum frame size, as
+    /// [dead letters](crate::DeadLetter) (see
+    /// [`Behaviour::dead_letters`](crate::Behaviour::dead_letters)).
+    ///
+    /// This is a debugging facility. When disabled, the dropped inputs are not described at all.
+    ///
+    /// Default is `false`.
+    pub fn dead_letter(&self) -> bool {
+        self.dead_letter
+    }
+
+    /// The maximum number of dead letters kept. Once reached, the oldest dead letter is evicted
+    /// for every newly recorded one.
+    ///
+    /// Default is 256.
+    pub fn dead_letter_capacity(&self) -> usize {
+        self.dead_letter_capacity
+    }
+
+    /// Whether to also emit the recorded dead letters as
+    /// [`Event::DeadLetter`](crate::Event::DeadLetter) events. Has no effect unless the
+    /// [dead letters](Config::dead_letter) are recorded.
+    ///
+    /// Default is `false`.
+    pub fn dead_letter_events(&self) -> bool {
+        self.dead_letter_events
+    }
+
+    /// The sliding window over which the per-peer framing statistics are computed (see
+    /// [`Behaviour::peer_framing_stats`](crate::Behaviour::peer_framing_stats)).
+    ///
+    /// Default is 10 seconds.
+    pub fn framing_stats_window(&self) -> Duration {
+        self.framing_stats_window
+    }
+
+    /// The average number of messages per frame, over the [framing statistics
+    /// window](Config::framing_stats_window), below which a peer is notified as not batching its
+    /// messages (see [`Event::InefficientPeerFraming`](crate::Event::InefficientPeerFraming)).
+    ///
+    /// Default is 1.5 messages per frame.
+    pub fn inefficient_framing_threshold(&self) -> f64 {
+        self.inefficient_framing_threshold
+    }
+
+    /// The message rate, in messages per second, from which an inefficient peer framing is
+    /// notified. The peers sending few messages are not expected to batch them.
+    ///
+    /// Default is 100 messages per second.
+    pub fn inefficient_framing_min_message_rate(&self) -> f64 {
+        self.inefficient_framing_min_message_rate
+    }
+
+    /// The minimum time between two inefficient framing notifications of the same peer.
+    ///
+    /// Default is 60 seconds.
+    pub fn inefficient_framing_event_interval(&self) -> Duration {
+        self.inefficient_framing_event_interval
+    }
+
+    /// The warm-up period, following the first connection after the startup, during which the
+    /// received messages are delivered locally and cached, but not forwarded to the remote peers.
+    ///
+    /// This avoids a burst of duplicates while the (re)connecting peers announce their
+    /// subscriptions. The messages received during the warm-up are not forwarded once it expires
+    /// (see [`Behaviour::is_warming_up`](crate::Behaviour::is_warming_up)).
+    ///
+    /// If `None`, the received messages are forwarded right away.
+    ///
+    /// Default is `None`.
+    pub fn forward_warmup(&self) -> Option<Duration> {
+        self.forward_warmup
+    }
+
+    /// Whether to track the remote peer subscriptions per connection, e.g., for gateways
+    /// multiplexing several logical clients, with distinct subscriptions, behind one peer.
+    ///
+    /// If enabled, the subscription requests received on a connection only affect that connection
+    /// subscriptions (see
+    /// [`Behaviour::connection_subscriptions`](crate::Behaviour::connection_subscriptions)), and
+    /// the messages forwarded to a peer are sent to each of its connections subscribed to the
+    /// message topic. The peer is subscribed to a topic as long as any of its connections is.
+    ///
+    /// The local node subscriptions are still announced to the peer as a whole, i.e., over one of
+    /// its connections.
+    ///
+    /// Default is `false`.
+    pub fn connection_scoped_subscriptions(&self) -> bool {
+        self.connection_scoped_subscriptions
+    }
+
+    /// The scope the seen messages are deduplicated in (see [`DedupScope`]).
+    ///
+    /// With [`DedupScope::Global`], a message is a duplicate of any seen message with the same id,
+    /// whatever its topic. With [`DedupScope::PerTopic`], only the seen messages published to the
+    /// same topic are considered, so a message id function that does not cover the message topic
+    /// (e.g., [`sha256_message_id_fn`](crate::sha256_message_id_fn)) can be used for the same
+    /// payloads published to several topics.
+    ///
+    /// Default is [`DedupScope::Global`].
+    pub fn dedup_scope(&self) -> DedupScope {
+        self.dedup_scope
+    }
+
+    /// The message id function used for the topics subscribed without a specific message id
+    /// function.
+    ///
+    /// Default is [`default_message_id_fn`](crate::default_message_id_fn).
+    pub fn default_message_id_fn(&self) -> fn(Option<&PeerId>, &MessageRef) -> MessageId {
+        self.default_message_id_fn
+    }
+
+    /// The minimum time between two re-sendings of the local subscriptions to the same peer, be it
+    /// a [subscription refresh](crate::Behaviour::request_subscription_refresh) requested by the
+    /// application or the answer to a peer re-announcing its subscriptions.
+    ///
+    /// Default is 30 seconds.
+    pub fn subscription_refresh_cooldown(&self) -> Duration {
+        self.subscription_refresh_cooldown
+    }
+
+    /// The time after a [subscription refresh](crate::Behaviour::request_subscription_refresh)
+    /// during which the topics newly announced by the peer are attributed to the refresh (see
+    /// [`Event::SubscriptionRefreshCompleted`](crate::Event::SubscriptionRefreshCompleted)).
+    ///
+    /// Default is 2 seconds.
+    pub fn subscription_refresh_window(&self) -> Duration {
+        self.subscription_refresh_window
+    }
+
+    /// The time without send failures after which the frame size limit suspected for a peer is
+    /// dropped (see [`Behaviour::peer_frame_limit`](crate::Behaviour::peer_frame_limit)).
+    ///
+    /// A send failure may have other causes than the frame size, so the frames sent to the peer
+    /// are not clamped forever.
+    ///
+    /// Default is 5 minutes.
+    pub fn peer_frame_limit_decay(&self) -> Duration {
+        self.peer_frame_limit_decay
+    }
+
+    /// The sliding window over which the per-peer subscription churn is tracked (see
+    /// [`Behaviour::peer_subscription_churn`](crate::Behaviour::peer_subscription_churn)).
+    ///
+    /// The window advances on every heartbeat, so it is rounded up to a multiple of the
+    /// [heartbeat interval](Config::heartbeat_interval).
+    ///
+    /// Default is 60 seconds.
+    pub fn subscription_churn_window(&self) -> Duration {
+        self.subscription_churn_window
+    }
+
+    /// The subscription transitions rate to a topic, in transitions per minute over the
+    /// [churn window](Config::subscription_churn_window), from which a peer is notified as
+    /// flapping its subscription (see [`Event::PeerFlapping`](crate::Event::PeerFlapping)).
+    ///
+    /// Default is 10 transitions per minute.
+    pub fn subscription_flapping_threshold(&self) -> f64 {
+        self.subscription_flapping_threshold
+    }
+
+    /// Whether to flag the received messages whose claimed author may be spoofed, as an
+    /// [`Event::SuspectedSpoofedMessage`](crate::Event::SuspectedSpoofedMessage) event.
+    ///
+    /// A message is suspected to be spoofed when it carries an author but no signature, its
+    /// author is a directly connected peer, and it was propagated by a different peer. This is a
+    /// cheap heuristic, not a signature validation: the message signatures are not verified, so
+    /// a signed message is never flagged, and a legitimate message relayed by another peer before
+    /// its author sent it to the local node is flagged too.
+    ///
+    /// Default is `false`.
+    pub fn verify_from_matches_signature(&self) -> bool {
+        self.verify_from_matches_signature
+    }
+
+    /// Whether to drop the received messages suspected to be spoofed (see
+    /// [`Config::verify_from_matches_signature`]), instead of only flagging them. Has no effect
+    /// unless the suspected spoofed messages are flagged.
+    ///
+    /// Default is `false`.
+    pub fn drop_suspected_spoofed(&self) -> bool {
+        self.drop_suspected_spoofed
+    }
+
+    /// The maximum age of the cached messages backfilled to a peer subscribing to a topic whose
+    /// local subscription enables the backfill (see
+    /// [`SubscriptionBuilder::backfill_on_peer_subscribe`](
+    /// crate::SubscriptionBuilder::backfill_on_peer_subscribe)).
+    ///
+    /// The messages are only cached for the [message cache TTL](Config::message_cache_ttl), so
+    /// a longer maximum age has no effect.
+    ///
+    /// Default is 5 seconds.
+    pub fn backfill_max_age(&self) -> Duration {
+        self.backfill_max_age
+    }
+
+    /// The maximum number of cached messages backfilled to a peer subscribing to a topic, the
+    /// most recent ones (see [`Config::backfill_max_age`]).
+    ///
+    /// Default is 16 messages.
+    pub fn backfill_max_messages(&self) -> usize {
+        self.backfill_max_messages
+    }
+
+    /// The maximum number of message forwards, i.e., messages sent to a peer, released to the
+    /// connection handlers per behaviour poll cycle.
+    ///
+    /// A poll cycle spans the behaviour polls until the behaviour has no more events to report.
+    /// The forwards above the limit are kept in order, and released on the next poll cycles, so a
+    /// large fan-out does not monopolize the executor. The pending forwards are reported by
+    /// [`Behaviour::pending_forwards`](crate::Behaviour::pending_forwards). A zero limit is
+    /// treated as 1.
+    ///
+    /// Default is 1024 forwards.
+    pub fn max_forwards_per_poll(&self) -> usize {
+        self.max_forwards_per_poll
+    }
+
+    /// The maximum number of message forwards released to a single peer per behaviour poll cycle
+    /// (see [`Config::max_forwards_per_poll`]).
+    ///
+    /// The messages are never reordered relative to each other toward the same peer. A zero limit
+    /// is treated as 1.
+    ///
+    /// Default is 128 forwards.
+    pub fn max_peer_forwards_per_poll(&self) -> usize {
+        self.max_peer_forwards_per_poll
+    }
+
+    /// The topic authorizer, deciding which peers may subscribe or publish to each topic (see
+    /// [`TopicAuthorizer`]).
+    ///
+    /// The authorizer is consulted when a remote peer announces a topic subscription: the
+    /// announcements of the peers not authorized to subscribe are ignored (see
+    /// [`DropStats::unauthorized_subscriptions`](crate::DropStats::unauthorized_subscriptions)).
+    /// It is also consulted when a message is received, for its propagation source and, if any,
+    /// its claimed author: the messages of the peers not authorized to publish are dropped before
+    /// being cached or delivered, and notified with an [`Event::UnauthorizedMessage`](
+    /// crate::Event::UnauthorizedMessage) event.
+    ///
+    /// The authorizer is called on the hot path, so it must be cheap. See [`StaticAllowlist`](
+    /// crate::StaticAllowlist) for a prebuilt allowlist authorizer.
+    ///
+    /// If `None`, all the peers are authorized.
+    ///
+    /// Default is `None`.
+    pub fn topic_authorizer(&self) -> Option<&Rc<dyn TopicAuthorizer<Output = bool>>> {
+        self.topic_authorizer
+            .as_ref()
+            .map(|authorizer| &authorizer.0)
+    }
+
+    /// The minimum time between two [`Event::UnauthorizedMessage`](
+    /// crate::Event::UnauthorizedMessage) events for the same peer and topic (see
+    /// [`Config::topic_authorizer`]).
+    ///
+    /// Default is 60 seconds.
+    pub fn unauthorized_message_event_interval(&self) -> Duration {
+        self.unauthorized_message_event_interval
+    }
+
+    /// The persistent message dedup store configuration.
+    ///
+    /// If set, the ids of the seen messages, and the time they expire from the message cache (see
+    /// [`Config::message_cache_ttl`]), are appended to the dedup store file every flush interval,
+    /// so the messages seen before a node restart are not delivered again. The file is written on
+    /// the message cache heartbeat with blocking IO, and the pending records are written when the
+    /// behaviour is dropped. On startup, the expired records are dropped from the file.
+    ///
+    /// If the file cannot be read, is corrupt, or cannot be written, the message cache falls back
+    /// to memory-only, and a [`DedupPersistenceFailed`](crate::Event::DedupPersistenceFailed)
+    /// event is emitted.
+    ///
+    /// Default is `None`.
+    pub fn dedup_persistence(&self) -> Option<&DedupPersistenceConfig> {
+        self.dedup_persistence.as_ref()
+    }
+
+    /// The connection gate, deciding whether each established connection is used for pubsub
+    /// (see [`ConnectionGate`]).
+    ///
+    /// The gate is consulted when an inbound or outbound connection is established, before its
+    /// connection handler is created, so the denied peers cannot negotiate a pubsub substream.
+    /// The connections [denied](GateDecision::Deny) are refused to the swarm, which closes them.
+    /// The connections [allowed disabled](GateDecision::AllowDisabled) are kept for the other
+    /// behaviours, but ignored by pubsub. See [`AllowList`](crate::AllowList) and
+    /// [`DenyList`](crate::DenyList) for prebuilt gates.
+    ///
+    /// If `None`, all the connections are used for pubsub.
+    ///
+    /// Default is `None`.
+    pub fn connection_gate(&self) -> Option<&Rc<dyn ConnectionGate<Output = GateDecision>>> {
+        self.connection_gate.as_ref().map(|gate| &gate.0)
+    }
+
+    /// Whether the frame corruption diagnostics are enabled.
+    ///
+    /// If enabled, a CRC32 checksum of each sent frame is appended to the frame in a trailing
+    /// protobuf field, ignored by the peers not supporting it (field number 2047, see
+    /// [`FRAME_CHECKSUM_FIELD_TAG`](crate::FRAME_CHECKSUM_FIELD_TAG)). The checksum of the
+    /// received frames carrying the field is verified before decoding them, so the frames
+    /// corrupted by the transport are told apart from the frames malformed by the peer (see
+    /// [`FrameFailureClass`](crate::FrameFailureClass)). The checksum adds 7 bytes to each frame.
+    ///
+    /// Default is `false`.
+    pub fn frame_diagnostics(&self) -> bool {
+        self.frame_diagnostics
+    }
+
+    /// The sliding window over which the peers outbound send health is tracked.
+    ///
+    /// A peer is suspected of asymmetric connectivity if, over this window, a frame was received
+    /// from it while most of the frames sent to it failed (see
+    /// [`Config::asymmetric_connectivity_threshold`]).
+    ///
+    /// Default is 30 seconds.
+    pub fn send_health_window(&self) -> Duration {
+        self.send_health_window
+    }
+
+    /// The failed to attempted sends ratio, over the send health window, above which a peer still
+    /// sending frames to the local node is suspected of asymmetric connectivity (see
+    /// [`Event::AsymmetricConnectivitySuspected`](crate::Event::AsymmetricConnectivitySuspected)).
+    ///
+    /// Default is 0.5.
+    pub fn asymmetric_connectivity_threshold(&self) -> f64 {
+        self.asymmetric_connectivity_threshold
+    }
+
+    /// The minimum number of sends to a peer over the send health window to suspect it of
+    /// asymmetric connectivity, so a few early failures are not enough.
+    ///
+    /// Default is 8.
+    pub fn asymmetric_connectivity_min_sends(&self) -> usize {
+        self.asymmetric_connectivity_min_sends
+    }
+
+    /// Whether the peers suspected of asymmetric connectivity are deprioritized as message forward
+    /// destinations.
+    ///
+    /// If enabled, the protocol routers are notified of the suspected peers (see
+    /// [`ProtocolRouterConnectionEvent::PeerDeprioritized`](
+    /// crate::protocol::ProtocolRouterConnectionEvent::PeerDeprioritized)), and pick them last
+    /// when the message forwarding fan-out is capped (see [`Config::max_forward_fanout`]).
+    ///
+    /// Default is `true`.
+    pub fn deprioritize_asymmetric_peers(&self) -> bool {
+        self.deprioritize_asymmetric_peers
+    }
+
+    /// The minimum time between two [`Event::TopicHashMismatchSuspected`](
+    /// crate::Event::TopicHashMismatchSuspected) events for the same received topic.
+    ///
+    /// Default is 60 seconds.
+    pub fn topic_hash_mismatch_event_interval(&self) -> Duration {
+        self.topic_hash_mismatch_event_interval
+    }
+
+    /// The maximum number of control messages (GRAFT, PRUNE, IHAVE and IWANT) per received frame.
+    ///
+    /// The received frames limits are enforced before the frames are processed, so a hostile
+    /// peer cannot pack a small frame with enough entries to cause a disproportionate amount of
+    /// work, or of response bandwidth. The frames exceeding a limit are truncated or rejected
+    /// (see [`Config::frame_limit_policy`]), and notified as
+    /// [`FrameValidationError::LimitExceeded`](crate::FrameValidationError::LimitExceeded)
+    /// framing validation failures.
+    ///
+    /// Default is 1024.
+    pub fn max_control_entries_per_frame(&self) -> usize {
+        self.max_control_entries_per_frame
+    }
+
+    /// The maximum number of message ids per received IHAVE control message (see
+    /// [`Config::max_control_entries_per_frame`]).
+    ///
+    /// Default is 5000.
+    pub fn max_ihave_ids_per_message(&self) -> usize {
+        self.max_ihave_ids_per_message
+    }
+
+    /// The maximum number of message ids, over all the IWANT control messages, per received
+    /// frame (see [`Config::max_control_entries_per_frame`]).
+    ///
+    /// Default is 5000.
+    pub fn max_iwant_ids_per_frame(&self) -> usize {
+        self.max_iwant_ids_per_frame
+    }
+
+    /// The maximum number of subscription actions per received frame (see
+    /// [`Config::max_control_entries_per_frame`]).
+    ///
+    /// The peers announcing more subscriptions than the limit at once have their announcement
+    /// truncated, or rejected, so the limit must be above the number of topics of the largest
+    /// announcement (see [`Config::subscription_announce_batch`]).
+    ///
+    /// Default is 5000.
+    pub fn max_subscriptions_per_frame(&self) -> usize {
+        self.max_subscriptions_per_frame
+    }
+
+    /// How the received frames exceeding one of the received frames limits are handled: the
+    /// entries beyond the limit are dropped, or the whole frame is dropped (see
+    /// [`Config::max_control_entries_per_frame`]).
+    ///
+    /// Default is [`FrameLimitPolicy::Truncate`].
+    pub fn frame_limit_policy(&self) -> FrameLimitPolicy {
+        self.frame_limit_policy
+    }
+
+    /// The maximum number of received frames and messages validation failures cached.
+    ///
+    /// The received frames and messages failing the validation are not inserted into the message
+    /// cache, so a peer re-sending an invalid frame or message would have it validated again
+    /// every time. The validation failures are cached, keyed by a hash of the raw frame bytes, or
+    /// of the message fields, so the re-received invalid frames and messages are dropped, and
+    /// notified as validation failures, without validating them again (see
+    /// [`Behaviour::negative_validation_cache_hits`](crate::Behaviour::negative_validation_cache_hits)).
+    ///
+    /// Once the capacity is reached, the oldest failures are evicted. A capacity of zero disables
+    /// the cache.
+    ///
+    /// Default is 256.
+    pub fn negative_validation_cache_capacity(&self) -> usize {
+        self.negative_validation_cache_capacity
+    }
+
+    /// The time a received frame or message validation failure is cached (see
+    /// [`Config::negative_validation_cache_capacity`]).
+    ///
+    /// Default is 60 seconds.
+    pub fn negative_validation_cache_ttl(&self) -> Duration {
+        self.negative_validation_cache_ttl
+    }
+
+    /// The sliding window over which the delivered messages provenance is accounted (see
+    /// [`Behaviour::topic_provenance_stats`](crate::Behaviour::topic_provenance_stats)).
+    ///
+    /// Default is 60 seconds.
+    pub fn provenance_stats_window(&self) -> Duration {
+        self.provenance_stats_window
+    }
+
+    /// The interval at which an [`Event::TopicActivitySummary`](crate::Event::TopicActivitySummary)
+    /// event is emitted, reporting the locally subscribed topics activity over the interval.
+    ///
+    /// Default is `None`, i.e., no summaries are emitted.
+    pub fn activity_summaries(&self) -> Option<Duration> {
+        self.activity_summaries
+    }
+
+    /// The maximum number of topics reported individually in a topic activity summary. The
+    /// activity of the least active topics beyond the cap is rolled up into a single entry.
+    ///
+    /// Default is 64 topics.
+    pub fn activity_summary_max_topics(&self) -> usize {
+        self.activity_summary_max_topics
+    }
+
+    /// The interval at which the protocol router subscriptions and routing tables are replaced
+    /// with the behaviour view (see
+    /// [`Behaviour::force_router_sync`](crate::Behaviour::force_router_sync)), as a safety net
+    /// against their divergence.
+    ///
+    /// The sync is checked on every heartbeat, so the actual interval is rounded up to a multiple
+    /// of the [heartbeat interval](Config::heartbeat_interval).
+    ///
+    /// Default is `None`, i.e., the router state is only updated incrementally.
+    pub fn router_sync_interval(&self) -> Option<Duration> {
+        self.router_sync_interval
+    }
+
+    /// The duration without any message received on the locally subscribed topics, while active
+    /// peers are subscribed to them, after which an
+    /// [`Event::SubscribedButSilent`](crate::Event::SubscribedButSilent) event is emitted.
+    ///
+    /// The event is emitted once per silence period: the watchdog is re-armed by the next message
+    /// received on a subscribed topic. The silence is checked on every heartbeat, so the event is
+    /// delayed up to a [heartbeat interval](Config::heartbeat_interval).
+    ///
+    /// Default is `None`, i.e., the silence is not watched.
+    pub fn silence_watchdog(&self) -> Option<Duration> {
+        self.silence_watchdog
+    }
+
+    /// The maximum number of messages served to a peer's IWANT control messages per
+    /// [heartbeat interval](Config::heartbeat_interval).
+    ///
+    /// The requested messages are looked up in the message cache and forwarded to the requesting
+    /// peer. The requested ids exceeding the quota are not served, and the unknown ids are
+    /// silently ignored (see [`DropStats::iwant_unserved`](crate::DropStats::iwant_unserved)).
+    /// Serving the IWANT requests requires the message cache to keep the messages, and not only
+    /// their ids.
+    ///
+    /// Default is `0`, i.e., the IWANT requests are not served.
+    pub fn max_iwant_served_per_peer_per_heartbeat(&self) -> usize {
+        self.max_iwant_served_per_peer_per_heartbeat
+    }
+
+    /// The maximum number of times the same message is served to the same peer's IWANT control
+    /// messages, as long as the message is in the message cache.
+    ///
+    /// Default is `3`, as in gossipsub.
+    pub fn max_iwant_serves_per_message(&self) -> usize {
+        self.max_iwant_serves_per_message
+    }
+
+    /// The number of messages of a received frame above which the frame messages are validated
+    /// in parallel, on the [rayon](https://docs.rs/rayon) global thread pool.
+    ///
+    /// Only applies if the `parallel` feature is enabled. Otherwise, the frame messages are always
+    /// validated on the behaviour's poll thread. Either way, the received messages are processed
+    /// in the frame order.
+    ///
+    /// Default is `128`.
+    pub fn parallel_validation_threshold(&self) -> usize {
+        self.parallel_validation_threshold
+    }
+
+    /// The raw frames sampling configuration, for wire-level debugging.
+    ///
+    /// If set, each received frame is sampled, before any decoding, with the configured
+    /// probability, and a copy of its bytes, truncated to the maximum sampled frame size, is kept
+    /// in a bounded buffer (see [`Behaviour::sampled_frames`](crate::Behaviour::sampled_frames)).
+    /// The frames sent are sampled too, if enabled (see [`SamplingConfig::sample_sent`]).
+    ///
+    /// Default is `None`.
+    pub fn frame_sampling(&self) -> Option<&SamplingConfig> {
+        self.frame_sampling.as_ref()
+    }
+
+    /// The egress bandwidth budget, in bytes per second, of the frames sent to the remote peers.
+    ///
+    /// If set, the frames handed to the connection handlers are paced by a token bucket refilled
+    /// at the budget rate, and holding up to one second of budget. The frames exceeding the
+    /// budget are held back, in order, and released as the bucket refills, several times per
+    /// [heartbeat interval](Config::heartbeat_interval). The high priority frames are released
+    /// first, and the subscription and control frames are never held back, though they draw from
+    /// the budget too. See [`Behaviour::egress_stats`](crate::Behaviour::egress_stats) for the
+    /// pacer state and counters, and
+    /// [`Behaviour::set_egress_budget`](crate::Behaviour::set_egress_budget) to update it.
+    ///
+    /// Default is `None`.
+    pub fn egress_budget(&self) -> Option<BytesPerSecond> {
+        self.egress_budget
+    }
+
+    /// Whether to emit the peers' first confirmation of the local subscriptions as
+    /// [`Event::PeerConfirmedSubscription`](crate::Event::PeerConfirmedSubscription) events. The
+    /// confirmations are recorded regardless (see
+    /// [`Behaviour::peer_confirmed_topics`](crate::Behaviour::peer_confirmed_topics)).
+    ///
+    /// Default is `false`.
+    pub fn subscription_confirmation_events(&self) -> bool {
+        self.subscription_confirmation_events
+    }
+
+    /// Update the egress bandwidth budget (see
+    /// [`Behaviour::set_egress_budget`](crate::Behaviour::set_egress_budget)).
+    pub(crate) fn set_egress_budget(&mut self, budget: Option<BytesPerSecond>) {
+        self.egress_budget = budget;
+    }
+
+    /// The received frames limits.
+    pub(crate) fn frame_limits(&self) -> FrameLimits {
+        FrameLimits {
+            max_control_entries: self.max_control_entries_per_frame,
+            max_ihave_ids_per_message: self.max_ihave_ids_per_message,
+            max_iwant_ids: self.max_iwant_ids_per_frame,
+            max_subscriptions: self.max_subscriptions_per_frame,
+            policy: self.frame_limit_policy,
+        }
+    }
+}
+
+/// A builder for the pubsub [`Config`].
+#[derive(Debug, Clone, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Create a new config builder with the default configuration values.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The maximum byte size for each pubsub frame (see [`Config::max_frame_size`]).
+    pub fn max_frame_size(&mut self, max_frame_size: usize) -> &mut Self {
+        self.config.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// The maximum encoded size of a message published by the local node (see
+    /// [`Config::max_publish_size`]).
+    pub fn max_publish_size(&mut self, max_publish_size: Option<usize>) -> &mut Self {
+        self.config.max_publish_size = max_publish_size;
+        self
+    }
+
+    /// The time a connection is kept without activity (see [`Config::connection_idle_timeout`]).
+    pub fn connection_idle_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.config.connection_idle_timeout = timeout;
+        self
+    }
+
+    /// The number of retries attempted to send a frame over a connection before giving up on the
+    /// connection (see [`Config::max_connection_send_retry_attempts`]).
+    pub fn max_connection_send_retry_attempts(&mut self, attempts: usize) -> &mut Self {
+        self.config.max_connection_send_retry_attempts = attempts;
+        self
+    }
+
+    /// The time between each heartbeat (see [`Config::heartbeat_interval`]).
+    pub fn heartbeat_interval(&mut self, interval: Duration) -> &mut Self {
+        self.config.heartbeat_interval = interval;
+        self
+    }
+
+    /// The maximum number of messages to cache (see [`Config::message_cache_capacity`]).
+    pub fn message_cache_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.config.message_cache_capacity = capacity;
+        self
+    }
+
+    /// The time a message is kept in the cache (see [`Config::message_cache_ttl`]).
+    pub fn message_cache_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.config.message_cache_ttl = ttl;
+        self
+    }
+
+    /// The maximum total size, in bytes, of the cached messages (see
+    /// [`Config::message_cache_max_bytes`]).
+    pub fn message_cache_max_bytes(&mut self, max_bytes: Option<usize>) -> &mut Self {
+        self.config.message_cache_max_bytes = max_bytes;
+        self
+    }
+
+    /// The maximum number of times a peer can re-send an already seen message before being
+    /// flagged as misbehaving (see [`Config::max_duplicate_resends`]).
+    pub fn max_duplicate_resends(&mut self, max_resends: usize) -> &mut Self {
+        self.config.max_duplicate_resends = max_resends;
+        self
+    }
+
+    /// The time during which the frames received from a duplicate flooder peer are dropped (see
+    /// [`Config::duplicate_flood_cooldown`]).
+    pub fn duplicate_flood_cooldown(&mut self, cooldown: Option<Duration>) -> &mut Self {
+        self.config.duplicate_flood_cooldown = cooldown;
+        self
+    }
+
+    /// Whether to notify the first echo of the locally published messages (see
+    /// [`Config::detect_echo`]).
+    pub fn detect_echo(&mut self, detect_echo: bool) -> &mut Self {
+        self.config.detect_echo = detect_echo;
+        self
+    }
+
+    /// Whether to deliver the locally published messages to the local node too (see
+    /// [`Config::deliver_own_messages`]).
+    pub fn deliver_own_messages(&mut self, deliver: bool) -> &mut Self {
+        self.config.deliver_own_messages = deliver;
+        self
+    }
+
+    /// Whether to notify the messages published while no connected peer is subscribed to their
+    /// topic (see [`Config::warn_on_no_subscribers`]).
+    pub fn warn_on_no_subscribers(&mut self, warn: bool) -> &mut Self {
+        self.config.warn_on_no_subscribers = warn;
+        self
+    }
+
+    /// The time window during which the messages destined to a peer are batched into a single
+    /// frame (see [`Config::publish_batch_window`]).
+    pub fn publish_batch_window(&mut self, window: Option<Duration>) -> &mut Self {
+        self.config.publish_batch_window = window;
+        self
+    }
+
+    /// The time window during which the local subscription changes are held before being
+    /// announced (see [`Config::subscription_debounce`]).
+    pub fn subscription_debounce(&mut self, window: Option<Duration>) -> &mut Self {
+        self.config.subscription_debounce = window;
+        self
+    }
+
+    /// The number of connected peers up to which the known peers are dialed (see
+    /// [`Config::target_peer_count`]).
+    pub fn target_peer_count(&mut self, count: usize) -> &mut Self {
+        self.config.target_peer_count = count;
+        self
+    }
+
+    /// The maximum number of consecutive failed dial attempts before a known peer is pruned (see
+    /// [`Config::max_dial_attempts`]).
+    pub fn max_dial_attempts(&mut self, attempts: usize) -> &mut Self {
+        self.config.max_dial_attempts = attempts;
+        self
+    }
+
+    /// The time to wait before retrying a failed dial to a known peer (see
+    /// [`Config::dial_backoff`]).
+    pub fn dial_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.config.dial_backoff = backoff;
+        self
+    }
+
+    /// The maximum number of chunked transfers reassembled concurrently (see
+    /// [`Config::max_concurrent_transfers`]).
+    pub fn max_concurrent_transfers(&mut self, max_transfers: usize) -> &mut Self {
+        self.config.max_concurrent_transfers = max_transfers;
+        self
+    }
+
+    /// The maximum payload size of a chunked transfer (see [`Config::max_transfer_size`]).
+    pub fn max_transfer_size(&mut self, max_size: usize) -> &mut Self {
+        self.config.max_transfer_size = max_size;
+        self
+    }
+
+    /// The time to wait for all the chunks of a chunked transfer to be received (see
+    /// [`Config::transfer_timeout`]).
+    pub fn transfer_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.config.transfer_timeout = timeout;
+        self
+    }
+
+    /// The maximum number of known remote peers whose subscriptions are tracked (see
+    /// [`Config::max_known_remote_peers`]).
+    pub fn max_known_remote_peers(&mut self, max_peers: usize) -> &mut Self {
+        self.config.max_known_remote_peers = max_peers;
+        self
+    }
+
+    /// The clock skew tolerated when checking the messages expiry time (see
+    /// [`Config::message_ttl_clock_skew`]).
+    pub fn message_ttl_clock_skew(&mut self, clock_skew: Duration) -> &mut Self {
+        self.config.message_ttl_clock_skew = clock_skew;
+        self
+    }
+
+    /// The maximum number of interned topic hashes (see [`Config::max_interned_topics`]).
+    pub fn max_interned_topics(&mut self, max_topics: usize) -> &mut Self {
+        self.config.max_interned_topics = max_topics;
+        self
+    }
+
+    /// The maximum size in bytes of the received messages key field (see
+    /// [`Config::max_key_size`]).
+    pub fn max_key_size(&mut self, max_key_size: usize) -> &mut Self {
+        self.config.max_key_size = max_key_size;
+        self
+    }
+
+    /// The validation rules of the topic strings (see [`Config::topic_validation`]).
+    pub fn topic_validation(&mut self, topic_validation: TopicValidation) -> &mut Self {
+        self.config.topic_validation = topic_validation;
+        self
+    }
+
+    /// The maximum time a message is held back on the ordered delivery topics (see
+    /// [`Config::ordering_max_delay`]).
+    pub fn ordering_max_delay(&mut self, max_delay: Duration) -> &mut Self {
+        self.config.ordering_max_delay = max_delay;
+        self
+    }
+
+    /// The maximum number of held back messages per ordered delivery topic and author (see
+    /// [`Config::ordering_max_held_messages`]).
+    pub fn ordering_max_held_messages(&mut self, max_messages: usize) -> &mut Self {
+        self.config.ordering_max_held_messages = max_messages;
+        self
+    }
+
+    /// The number of heartbeat intervals the message cache history spans (see
+    /// [`Config::history_length`]).
+    pub fn history_length(&mut self, history_length: usize) -> &mut Self {
+        self.config.history_length = history_length;
+        self
+    }
+
+    /// The number of heartbeat intervals of message history notified to the protocol routers (see
+    /// [`Config::history_gossip`]).
+    pub fn history_gossip(&mut self, history_gossip: usize) -> &mut Self {
+        self.config.history_gossip = history_gossip;
+        self
+    }
+
+    /// The maximum number of attempts to send the local subscriptions to a connected peer (see
+    /// [`Config::subscription_sync_max_attempts`]).
+    pub fn subscription_sync_max_attempts(&mut self, attempts: usize) -> &mut Self {
+        self.config.subscription_sync_max_attempts = attempts;
+        self
+    }
+
+    /// The time to wait before retrying a failed subscription sync (see
+    /// [`Config::subscription_sync_retry_interval`]).
+    pub fn subscription_sync_retry_interval(&mut self, interval: Duration) -> &mut Self {
+        self.config.subscription_sync_retry_interval = interval;
+        self
+    }
+
+    /// The maximum number of topics announced to a newly connected peer per subscriptions frame
+    /// (see [`Config::subscription_announce_batch`]).
+    pub fn subscription_announce_batch(&mut self, batch: Option<usize>) -> &mut Self {
+        self.config.subscription_announce_batch = batch;
+        self
+    }
+
+    /// The time between two subscriptions frames of an announcement to a newly connected peer
+    /// (see [`Config::subscription_announce_interval`]).
+    pub fn subscription_announce_interval(&mut self, interval: Duration) -> &mut Self {
+        self.config.subscription_announce_interval = interval;
+        self
+    }
+
+    /// The maximum number of peers a received message is forwarded to (see
+    /// [`Config::max_forward_fanout`]).
+    pub fn max_forward_fanout(&mut self, max_peers: Option<usize>) -> &mut Self {
+        self.config.max_forward_fanout = max_peers;
+        self
+    }
+
+    /// The maximum number of peers a locally published message is sent to (see
+    /// [`Config::max_publish_fanout`]).
+    pub fn max_publish_fanout(&mut self, max_peers: Option<usize>) -> &mut Self {
+        self.config.max_publish_fanout = max_peers;
+        self
+    }
+
+    /// Whether to record the intentionally dropped inputs as dead letters (see
+    /// [`Config::dead_letter`]).
+    pub fn dead_letter(&mut self, dead_letter: bool) -> &mut Self {
+        self.config.dead_letter = dead_letter;
+        self
+    }
+
+    /// The maximum number of dead letters kept (see [`Config::dead_letter_capacity`]).
+    pub fn dead_letter_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.config.dead_letter_capacity = capacity;
+        self
+    }
+
+    /// Whether to emit the recorded dead letters as behaviour events (see
+    /// [`Config::dead_letter_events`]).
+    pub fn dead_letter_events(&mut self, dead_letter_events: bool) -> &mut Self {
+        self.config.dead_letter_events = dead_letter_events;
+        self
+    }
+
+    /// The per-peer framing statistics sliding window duration (see
+    /// [`Config::framing_stats_window`]).
+    pub fn framing_stats_window(&mut self, window: Duration) -> &mut Self {
+        self.config.framing_stats_window = window;
+        self
+    }
+
+    /// The average number of messages per frame below which a peer framing is inefficient (see
+    /// [`Config::inefficient_framing_threshold`]).
+    pub fn inefficient_framing_threshold(&mut self, threshold: f64) -> &mut Self {
+        self.config.inefficient_framing_threshold = threshold;
+        self
+    }
+
+    /// The message rate from which an inefficient peer framing is notified (see
+    /// [`Config::inefficient_framing_min_message_rate`]).
+    pub fn inefficient_framing_min_message_rate(&mut self, rate: f64) -> &mut Self {
+        self.config.inefficient_framing_min_message_rate = rate;
+        self
+    }
+
+    /// The minimum time between two inefficient framing notifications of the same peer (see
+    /// [`Config::inefficient_framing_event_interval`]).
+    pub fn inefficient_framing_event_interval(&mut self, interval: Duration) -> &mut Self {
+        self.config.inefficient_framing_event_interval = interval;
+        self
+    }
+
+    /// The warm-up period during which the received messages are not forwarded (see
+    /// [`Config::forward_warmup`]).
+    pub fn forward_warmup(&mut self, warmup: Option<Duration>) -> &mut Self {
+        self.config.forward_warmup = warmup;
+        self
+    }
+
+    /// Whether to track the remote peer subscriptions per connection (see
+    /// [`Config::connection_scoped_subscriptions`]).
+    pub fn connection_scoped_subscriptions(&mut self, scoped: bool) -> &mut Self {
+        self.config.connection_scoped_subscriptions = scoped;
+        self
+    }
+
+    /// The scope the seen messages are deduplicated in (see [`Config::dedup_scope`]).
+    pub fn dedup_scope(&mut self, scope: DedupScope) -> &mut Self {
+        self.config.dedup_scope = scope;
+        self
+    }
+
+    /// The message id function used for the topics subscribed without a specific message id
+    /// function (see [`SubscriptionBuilder::message_id_fn`](crate::SubscriptionBuilder::message_id_fn)).
+    ///
+    /// Networks publishing anonymous messages should set this to
+    /// [`sha256_message_id_fn`](crate::sha256_message_id_fn).
+    pub fn default_message_id_fn(
+        &mut self,
+        id_fn: fn(Option<&PeerId>, &MessageRef) -> MessageId,
+    ) -> &mut Self {
+        self.config.default_message_id_fn = id_fn;
+        self
+    }
+
+    /// The minimum time between two re-sendings of the local subscriptions to the same peer (see
+    /// [`Config::subscription_refresh_cooldown`]).
+    pub fn subscription_refresh_cooldown(&mut self, cooldown: Duration) -> &mut Self {
+        self.config.subscription_refresh_cooldown = cooldown;
+        self
+    }
+
+    /// The time after a subscription refresh during which the peer announced topics are
+    /// attributed to the refresh (see [`Config::subscription_refresh_window`]).
+    pub fn subscription_refresh_window(&mut self, window: Duration) -> &mut Self {
+        self.config.subscription_refresh_window = window;
+        self
+    }
+
+    /// The time without send failures after which the frame size limit suspected for a peer is
+    /// dropped (see [`Config::peer_frame_limit_decay`]).
+    pub fn peer_frame_limit_decay(&mut self, decay: Duration) -> &mut Self {
+        self.config.peer_frame_limit_decay = decay;
+        self
+    }
+
+    /// The per-peer subscription churn sliding window duration (see
+    /// [`Config::subscription_churn_window`]).
+    pub fn subscription_churn_window(&mut self, window: Duration) -> &mut Self {
+        self.config.subscription_churn_window = window;
+        self
+    }
+
+    /// The subscription transitions rate from which a peer is notified as flapping a topic
+    /// subscription (see [`Config::subscription_flapping_threshold`]).
+    pub fn subscription_flapping_threshold(&mut self, threshold: f64) -> &mut Self {
+        self.config.subscription_flapping_threshold = threshold;
+        self
+    }
+
+    /// Whether to flag the received messages whose claimed author may be spoofed (see
+    /// [`Config::verify_from_matches_signature`]).
+    pub fn verify_from_matches_signature(&mut self, verify: bool) -> &mut Self {
+        self.config.verify_from_matches_signature = verify;
+        self
+    }
+
+    /// Whether to drop the received messages suspected to be spoofed (see
+    /// [`Config::drop_suspected_spoofed`]).
+    pub fn drop_suspected_spoofed(&mut self, drop: bool) -> &mut Self {
+        self.config.drop_suspected_spoofed = drop;
+        self
+    }
+
+    /// The maximum age of the cached messages backfilled to a newly subscribed peer (see
+    /// [`Config::backfill_max_age`]).
+    pub fn backfill_max_age(&mut self, max_age: Duration) -> &mut Self {
+        self.config.backfill_max_age = max_age;
+        self
+    }
+
+    /// The maximum number of messages backfilled to a newly subscribed peer (see
+    /// [`Config::backfill_max_messages`]).
+    pub fn backfill_max_messages(&mut self, max_messages: usize) -> &mut Self {
+        self.config.backfill_max_messages = max_messages;
+        self
+    }
+
+    /// The maximum number of message forwards released per behaviour poll cycle (see
+    /// [`Config::max_forwards_per_poll`]).
+    pub fn max_forwards_per_poll(&mut self, max_forwards: usize) -> &mut Self {
+        self.config.max_forwards_per_poll = max_forwards;
+        self
+    }
+
+    /// The maximum number of message forwards released to a single peer per behaviour poll cycle
+    /// (see [`Config::max_peer_forwards_per_poll`]).
+    pub fn max_peer_forwards_per_poll(&mut self, max_forwards: usize) -> &mut Self {
+        self.config.max_peer_forwards_per_poll = max_forwards;
+        self
+    }
+
+    /// The topic authorizer, deciding which peers may subscribe or publish to each topic (see
+    /// [`Config::topic_authorizer`]).
+    pub fn topic_authorizer(
+        &mut self,
+        authorizer: Option<Rc<dyn TopicAuthorizer<Output = bool>>>,
+    ) -> &mut Self {
+        self.config.topic_authorizer = authorizer.map(SharedTopicAuthorizer);
+        self
+    }
+
+    /// The minimum time between two unauthorized message events for the same peer and topic (see
+    /// [`Config::unauthorized_message_event_interval`]).
+    pub fn unauthorized_message_event_interval(&mut self, interval: Duration) -> &mut Self {
+        self.config.unauthorized_message_event_interval = interval;
+        self
+    }
+
+    /// The persistent message dedup store configuration (see [`Config::dedup_persistence`]).
+    pub fn dedup_persistence(&mut self, persistence: Option<DedupPersistenceConfig>) -> &mut Self {
+        self.config.dedup_persistence = persistence;
+        self
+    }
+
+    /// The connection gate, deciding whether each established connection is used for pubsub (see
+    /// [`Config::connection_gate`]).
+    pub fn connection_gate(
+        &mut self,
+        gate: Option<Rc<dyn ConnectionGate<Output = GateDecision>>>,
+    ) -> &mut Self {
+        self.config.connection_gate = gate.map(SharedConnectionGate);
+        self
+    }
+
+    /// Whether the frame corruption diagnostics are enabled (see [`Config::frame_diagnostics`]).
+    pub fn frame_diagnostics(&mut self, frame_diagnostics: bool) -> &mut Self {
+        self.config.frame_diagnostics = frame_diagnostics;
+        self
+    }
+
+    /// The sliding window over which the peers outbound send health is tracked (see
+    /// [`Config::send_health_window`]).
+    pub fn send_health_window(&mut self, window: Duration) -> &mut Self {
+        self.config.send_health_window = window;
+        self
+    }
+
+    /// The failed to attempted sends ratio above which a peer is suspected of asymmetric
+    /// connectivity (see [`Config::asymmetric_connectivity_threshold`]).
+    pub fn asymmetric_connectivity_threshold(&mut self, threshold: f64) -> &mut Self {
+        self.config.asymmetric_connectivity_threshold = threshold;
+        self
+    }
+
+    /// The minimum number of sends to a peer to suspect it of asymmetric connectivity (see
+    /// [`Config::asymmetric_connectivity_min_sends`]).
+    pub fn asymmetric_connectivity_min_sends(&mut self, min_sends: usize) -> &mut Self {
+        self.config.asymmetric_connectivity_min_sends = min_sends;
+        self
+    }
+
+    /// Whether the peers suspected of asymmetric connectivity are deprioritized as message forward
+    /// destinations (see [`Config::deprioritize_asymmetric_peers`]).
+    pub fn deprioritize_asymmetric_peers(&mut self, deprioritize: bool) -> &mut Self {
+        self.config.deprioritize_asymmetric_peers = deprioritize;
+        self
+    }
+
+    /// The minimum time between two topic hash mismatch events for the same received topic (see
+    /// [`Config::topic_hash_mismatch_event_interval`]).
+    pub fn topic_hash_mismatch_event_interval(&mut self, interval: Duration) -> &mut Self {
+        self.config.topic_hash_mismatch_event_interval = interval;
+        self
+    }
+
+    /// The maximum number of control messages per received frame (see
+    /// [`Config::max_control_entries_per_frame`]).
+    pub fn max_control_entries_per_frame(&mut self, max_entries: usize) -> &mut Self {
+        self.config.max_control_entries_per_frame = max_entries;
+        self
+    }
+
+    /// The maximum number of message ids per received IHAVE control message (see
+    /// [`Config::max_ihave_ids_per_message`]).
+    pub fn max_ihave_ids_per_message(&mut self, max_ids: usize) -> &mut Self {
+        self.config.max_ihave_ids_per_message = max_ids;
+        self
+    }
+
+    /// The maximum number of IWANT message ids per received frame (see
+    /// [`Config::max_iwant_ids_per_frame`]).
+    pub fn max_iwant_ids_per_frame(&mut self, max_ids: usize) -> &mut Self {
+        self.config.max_iwant_ids_per_frame = max_ids;
+        self
+    }
+
+    /// The maximum number of subscription actions per received frame (see
+    /// [`Config::max_subscriptions_per_frame`]).
+    pub fn max_subscriptions_per_frame(&mut self, max_subscriptions: usize) -> &mut Self {
+        self.config.max_subscriptions_per_frame = max_subscriptions;
+        self
+    }
+
+    /// How the received frames exceeding a limit are handled (see
+    /// [`Config::frame_limit_policy`]).
+    pub fn frame_limit_policy(&mut self, policy: FrameLimitPolicy) -> &mut Self {
+        self.config.frame_limit_policy = policy;
+        self
+    }
+
+    /// The maximum number of received frames and messages validation failures cached (see
+    /// [`Config::negative_validation_cache_capacity`]).
+    pub fn negative_validation_cache_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.config.negative_validation_cache_capacity = capacity;
+        self
+    }
+
+    /// The time a received frame or message validation failure is cached (see
+    /// [`Config::negative_validation_cache_ttl`]).
+    pub fn negative_validation_cache_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.config.negative_validation_cache_ttl = ttl;
+        self
+    }
+
+    /// The sliding window over which the delivered messages provenance is accounted (see
+    /// [`Config::provenance_stats_window`]).
+    pub fn provenance_stats_window(&mut self, window: Duration) -> &mut Self {
+        self.config.provenance_stats_window = window;
+        self
+    }
+
+    /// The interval at which a topic activity summary event is emitted, if enabled (see
+    /// [`Config::activity_summaries`]).
+    pub fn activity_summaries(&mut self, interval: Option<Duration>) -> &mut Self {
+        self.config.activity_summaries = interval;
+        self
+    }
+
+    /// The maximum number of topics reported individually in a topic activity summary (see
+    /// [`Config::activity_summary_max_topics`]).
+    pub fn activity_summary_max_topics(&mut self, max_topics: usize) -> &mut Self {
+        self.config.activity_summary_max_topics = max_topics;
+        self
+    }
+
+    /// The interval at which the protocol router state is synced with the behaviour, if enabled
+    /// (see [`Config::router_sync_interval`]).
+    pub fn router_sync_interval(&mut self, interval: Option<Duration>) -> &mut Self {
+        self.config.router_sync_interval = interval;
+        self
+    }
+
+    /// The silence duration after which the subscribed topics silence watchdog fires, if enabled
+    /// (see [`Config::silence_watchdog`]).
+    pub fn silence_watchdog(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.config.silence_watchdog = timeout;
+        self
+    }
+
+    /// The maximum number of messages served to a peer's IWANT control messages per heartbeat
+    /// interval (see [`Config::max_iwant_served_per_peer_per_heartbeat`]).
+    pub fn max_iwant_served_per_peer_per_heartbeat(&mut self, max: usize) -> &mut Self {
+        self.config.max_iwant_served_per_peer_per_heartbeat = max;
+        self
+    }
+
+    /// The maximum number of times the same message is served to the same peer's IWANT control
+    /// messages (see [`Config::max_iwant_serves_per_message`]).
+    pub fn max_iwant_serves_per_message(&mut self, max: usize) -> &mut Self {
+        self.config.max_iwant_serves_per_message = max;
+        self
+    }
+
+    /// The number of messages of a received frame above which the frame messages are validated
+    /// in parallel, if the `parallel` feature is enabled (see
+    /// [`Config::parallel_validation_threshold`]).
+    pub fn parallel_validation_threshold(&mut self, threshold: usize) -> &mut Self {
+        self.config.parallel_validation_threshold = threshold;
+        self
+    }
+
+    /// The raw frames sampling configuration (see [`Config::frame_sampling`]).
+    pub fn frame_sampling(&mut self, sampling: Option<SamplingConfig>) -> &mut Self {
+        self.config.frame_sampling = sampling;
+        self
+    }
+
+    /// The egress bandwidth budget (see [`Config::egress_budget`]).
+    pub fn egress_budget(&mut self, budget: Option<BytesPerSecond>) -> &mut Self {
+        self.config.egress_budget = budget;
+        self
+    }
+
+    /// Whether to emit the peers' first confirmation of the local subscriptions as behaviour
+    /// events (see [`Config::subscription_confirmation_events`]).
+    pub fn subscription_confirmation_events(
+        &mut self,
+        subscription_confirmation_events: bool,
+    ) -> &mut Self {
+        self.config.subscription_confirmation_events = subscription_confirmation_events;
+        self
+    }
+
+    pub fn build(&self) -> Config {
+        self.config.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::authorization::StaticAllowlist;
+    use crate::gate::AllowList;
+    use crate::message_id::sha256_message_id_fn;
+
+    use super::*;
+
+    /// A builder setter changing a single configuration field from its default value.
+    type Setter = fn(&mut ConfigBuilder) -> &mut ConfigBuilder;
+
+    /// A setter for each configuration field, by field name.
+    fn setters() -> Vec<(&'static str, Setter)> {
+        vec![
+            ("max_frame_size", |b| b.max_frame_size(1024)),
+            ("max_publish_size", |b| b.max_publish_size(Some(1024))),
+            ("connection_idle_timeout", |b| {
+                b.connection_idle_timeout(Duration::from_secs(1))
+            }),
+            ("max_connection_send_retry_attempts", |b| {
+                b.max_connection_send_retry_attempts(5)
+            }),
+            ("heartbeat_interval", |b| {
+                b.heartbeat_interval(Duration::from_millis(100))
+            }),
+            ("message_cache_capacity", |b| b.message_cache_capacity(1)),
+            ("message_cache_ttl", |b| {
+                b.message_cache_ttl(Duration::from_secs(1))
+            }),
+            ("message_cache_max_bytes", |b| {
+                b.message_cache_max_bytes(Some(1024))
+            }),
+            ("max_duplicate_resends", |b| b.max_duplicate_resends(1)),
+            ("duplicate_flood_cooldown", |b| {
+                b.duplicate_flood_cooldown(Some(Duration::from_secs(1)))
+            }),
+            ("detect_echo", |b| b.detect_echo(true)),
+            ("deliver_own_messages", |b| b.deliver_own_messages(true)),
+            ("warn_on_no_subscribers", |b| {
+                b.warn_on_no_subscribers(false)
+            }),
+            ("publish_batch_window", |b| {
+                b.publish_batch_window(Some(Duration::from_millis(10)))
+            }),
+            ("subscription_debounce", |b| {
+                b.subscription_debounce(Some(Duration::from_millis(10)))
+            }),
+            ("target_peer_count", |b| b.target_peer_count(1)),
+            ("max_dial_attempts", |b| b.max_dial_attempts(1)),
+            ("dial_backoff", |b| b.dial_backoff(Duration::from_secs(10))),
+            ("max_concurrent_transfers", |b| {
+                b.max_concurrent_transfers(1)
+            }),
+            ("max_transfer_size", |b| b.max_transfer_size(1)),
+            ("transfer_timeout", |b| {
+                b.transfer_timeout(Duration::from_secs(1))
+            }),
+            ("max_known_remote_peers", |b| b.max_known_remote_peers(1)),
+            ("message_ttl_clock_skew", |b| {
+                b.message_ttl_clock_skew(Duration::from_secs(10))
+            }),
+            ("max_interned_topics", |b| b.max_interned_topics(1)),
+            ("max_key_size", |b| b.max_key_size(1)),
+            ("topic_validation", |b| {
+                b.topic_validation(TopicValidation {
+                    max_length: 1,
+                    ..Default::default()
+                })
+            }),
+            ("ordering_max_delay", |b| {
+                b.ordering_max_delay(Duration::from_secs(10))
+            }),
+            ("ordering_max_held_messages", |b| {
+                b.ordering_max_held_messages(1)
+            }),
+            ("history_length", |b| b.history_length(10)),
+            ("history_gossip", |b| b.history_gossip(1)),
+            ("subscription_sync_max_attempts", |b| {
+                b.subscription_sync_max_attempts(1)
+            }),
+            ("subscription_sync_retry_interval", |b| {
+                b.subscription_sync_retry_interval(Duration::from_secs(10))
+            }),
+            ("subscription_announce_batch", |b| {
+                b.subscription_announce_batch(Some(1))
+            }),
+            ("subscription_announce_interval", |b| {
+                b.subscription_announce_interval(Duration::from_secs(10))
+            }),
+            ("max_forward_fanout", |b| b.max_forward_fanout(Some(1))),
+            ("max_publish_fanout", |b| b.max_publish_fanout(Some(1))),
+            ("dead_letter", |b| b.dead_letter(true)),
+            ("dead_letter_capacity", |b| b.dead_letter_capacity(1)),
+            ("dead_letter_events", |b| b.dead_letter_events(true)),
+            ("framing_stats_window", |b| {
+                b.framing_stats_window(Duration::from_secs(1))
+            }),
+            ("inefficient_framing_threshold", |b| {
+                b.inefficient_framing_threshold(3.0)
+            }),
+            ("inefficient_framing_min_message_rate", |b| {
+                b.inefficient_framing_min_message_rate(1.0)
+            }),
+            ("inefficient_framing_event_interval", |b| {
+                b.inefficient_framing_event_interval(Duration::from_secs(1))
+            }),
+            ("forward_warmup", |b| {
+                b.forward_warmup(Some(Duration::from_secs(1)))
+            }),
+            ("connection_scoped_subscriptions", |b| {
+                b.connection_scoped_subscriptions(true)
+            }),
+            ("dedup_scope", |b| b.dedup_scope(DedupScope::PerTopic)),
+            ("subscription_refresh_cooldown", |b| {
+                b.subscription_refresh_cooldown(Duration::from_secs(1))
+            }),
+            ("subscription_refresh_window", |b| {
+                b.subscription_refresh_window(Duration::from_secs(10))
+            }),
+            ("peer_frame_limit_decay", |b| {
+                b.peer_frame_limit_decay(Duration::from_secs(1))
+            }),
+            ("subscription_churn_window", |b| {
+                b.subscription_churn_window(Duration::from_secs(1))
+            }),
+            ("subscription_flapping_threshold", |b| {
+                b.subscription_flapping_threshold(1.0)
+            }),
+            ("verify_from_matches_signature", |b| {
+                b.verify_from_matches_signature(true)
+            }),
+            ("drop_suspected_spoofed", |b| b.drop_suspected_spoofed(true)),
+            ("backfill_max_age", |b| {
+                b.backfill_max_age(Duration::from_secs(1))
+            }),
+            ("backfill_max_messages", |b| b.backfill_max_messages(1)),
+            ("max_forwards_per_poll", |b| b.max_forwards_per_poll(1)),
+            ("max_peer_forwards_per_poll", |b| {
+                b.max_peer_forwards_per_poll(1)
+            }),
+            ("unauthorized_message_event_interval", |b| {
+                b.unauthorized_message_event_interval(Duration::from_secs(1))
+            }),
+            ("dedup_persistence", |b| {
+                b.dedup_persistence(Some(DedupPersistenceConfig::new("dedup.log")))
+            }),
+            ("frame_diagnostics", |b| b.frame_diagnostics(true)),
+            ("send_health_window", |b| {
+                b.send_health_window(Duration::from_secs(1))
+            }),
+            ("asymmetric_connectivity_threshold", |b| {
+                b.asymmetric_connectivity_threshold(0.9)
+            }),
+            ("asymmetric_connectivity_min_sends", |b| {
+                b.asymmetric_connectivity_min_sends(1)
+            }),
+            ("deprioritize_asymmetric_peers", |b| {
+                b.deprioritize_asymmetric_peers(false)
+            }),
+            ("topic_hash_mismatch_event_interval", |b| {
+                b.topic_hash_mismatch_event_interval(Duration::from_secs(1))
+            }),
+            ("max_control_entries_per_frame", |b| {
+                b.max_control_entries_per_frame(1)
+            }),
+            ("max_ihave_ids_per_message", |b| {
+                b.max_ihave_ids_per_message(1)
+            }),
+            ("max_iwant_ids_per_frame", |b| b.max_iwant_ids_per_frame(1)),
+            ("max_subscriptions_per_frame", |b| {
+                b.max_subscriptions_per_frame(1)
+            }),
+            ("frame_limit_policy", |b| {
+                b.frame_limit_policy(FrameLimitPolicy::Reject)
+            }),
+            ("negative_validation_cache_capacity", |b| {
+                b.negative_validation_cache_capacity(0)
+            }),
+            ("negative_validation_cache_ttl", |b| {
+                b.negative_validation_cache_ttl(Duration::from_secs(1))
+            }),
+            ("provenance_stats_window", |b| {
+                b.provenance_stats_window(Duration::from_secs(1))
+            }),
+            ("activity_summaries", |b| {
+                b.activity_summaries(Some(Duration::from_secs(1)))
+            }),
+            ("activity_summary_max_topics", |b| {
+                b.activity_summary_max_topics(1)
+            }),
+            ("router_sync_interval", |b| {
+                b.router_sync_interval(Some(Duration::from_secs(1)))
+            }),
+            ("silence_watchdog", |b| {
+                b.silence_watchdog(Some(Duration::from_secs(1)))
+            }),
+            ("max_iwant_served_per_peer_per_heartbeat", |b| {
+                b.max_iwant_served_per_peer_per_heartbeat(16)
+            }),
+            ("max_iwant_serves_per_message", |b| {
+                b.max_iwant_serves_per_message(1)
+            }),
+            ("parallel_validation_threshold", |b| {
+                b.parallel_validation_threshold(16)
+            }),
+            ("frame_sampling", |b| {
+                b.frame_sampling(Some(SamplingConfig::new(0.5)))
+            }),
+            ("egress_budget", |b| {
+                b.egress_budget(Some(BytesPerSecond(1_000_000)))
+            }),
+            ("subscription_confirmation_events", |b| {
+                b.subscription_confirmation_events(true)
+            }),
+            ("default_message_id_fn", |b| {
+                b.default_message_id_fn(sha256_message_id_fn)
+            }),
+            ("topic_authorizer", |b| {
+                b.topic_authorizer(Some(StaticAllowlist::new().into_authorizer()))
+            }),
+            ("connection_gate", |b| {
+                b.connection_gate(Some(AllowList::new([]).into_gate()))
+            }),
+        ]
+    }
+
+    #[test]
+    fn default_configs_are_equal() {
+        //// Then
+        assert_eq!(Config::default(), Config::builder().build());
+        assert!(Config::default().diff(&Config::default()).is_empty());
+    }
+
+    #[test]
+    fn diff_lists_exactly_the_changed_fields() {
+        //// Given
+        let config = Config::builder()
+            .max_frame_size(1024)
+            .heartbeat_interval(Duration::from_millis(500))
+            .max_forward_fanout(Some(6))
+            .build();
+
+        //// When
+        let diff = Config::default().diff(&config);
+
+        //// Then
+        assert_ne!(Config::default(), config);
+        assert_eq!(
+            diff,
+            [
+                ConfigDiff {
+                    field: "max_frame_size",
+                    left: "65537".to_string(),
+                    right: "1024".to_string(),
+                },
+                ConfigDiff {
+                    field: "heartbeat_interval",
+                    left: "1s".to_string(),
+                    right: "500ms".to_string(),
+                },
+                ConfigDiff {
+                    field: "max_forward_fanout",
+                    left: "None".to_string(),
+                    right: "Some(6)".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn every_field_has_a_setter_reported_by_the_diff_and_debug() {
+        //// Given
+        let default = Config::default();
+        let debug = format!("{default:?}");
+        let setters = setters();
+
+        //// Then
+        assert_eq!(
+            setters.len(),
+            default.fields().len(),
+            "Every configuration field should have a builder setter"
+        );
+
+        for (field, setter) in setters {
+            let config = setter(&mut Config::builder()).build();
+
+            let diff = default.diff(&config);
+            assert_eq!(
+                diff.iter().map(|diff| diff.field).collect::<Vec<_>>(),
+                [field],
+                "The setter should only change the {field} field"
+            );
+            assert!(
+                debug.contains(field),
+                "The Debug output should contain the {field} field"
+            );
+        }
+    }
+
+    #[test]
+    fn non_comparable_fields_are_compared_by_presence() {
+        //// Given
+        let config_a = Config::builder()
+            .topic_authorizer(Some(StaticAllowlist::new().into_authorizer()))
+            .build();
+        let config_b = Config::builder()
+            .topic_authorizer(Some(StaticAllowlist::new().into_authorizer()))
+            .build();
+
+        //// When
+        let diff = Config::default().diff(&config_a);
+
+        //// Then
+        assert_eq!(config_a, config_b);
+        assert_eq!(
+            diff,
+            [ConfigDiff {
+                field: "topic_authorizer",
+                left: "None".to_string(),
+                right: "Some(..)".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn default_config_passes_preflight() {
+        //// When
+        let warnings = Config::default().preflight();
+
+        //// Then
+        assert!(warnings.is_empty(), "Unexpected warnings: {warnings:?}");
+    }
+
+    #[test]
+    fn preflight_flags_heartbeat_longer_than_cache_ttl() {
+        //// Given
+        let config = Config::builder()
+            .heartbeat_interval(Duration::from_secs(10))
+            .message_cache_ttl(Duration::from_secs(5))
+            .build();
+
+        //// When
+        let warnings = config.preflight();
+
+        //// Then
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, PreflightCode::HeartbeatExceedsCacheTtl);
+        assert_eq!(
+            warnings[0].values,
+            [
+                ("heartbeat_interval", "10s".to_string()),
+                ("message_cache_ttl", "5s".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn preflight_flags_history_gossip_longer_than_history_length() {
+        //// Given
+        let config = Config::builder()
+            .history_length(2)
+            .history_gossip(3)
+            .build();
+
+        //// When
+        let warnings = config.preflight();
+
+        //// Then
+        assert_eq!(
+            warnings.iter().map(|w| w.code).collect::<Vec<_>>(),
+            [PreflightCode::HistoryGossipExceedsHistoryLength]
+        );
+    }
 }
@@ -0,0 +1,112 @@
+//! The peers' knowledge of the local subscriptions (see
+//! [`Behaviour::peer_confirmed_topics`](crate::Behaviour::peer_confirmed_topics)).
+
+use std::collections::{BTreeSet, HashMap};
+
+use libp2p::identity::PeerId;
+
+use crate::topic::TopicHash;
+
+/// Tracks, by peer, the locally subscribed topics the peer confirmed knowing about, i.e., the peer
+/// forwarded a message on the topic, or grafted the local node into the topic mesh.
+///
+/// The confirmations are best-effort: the pubsub protocol has no acknowledgement of the
+/// subscription announcements.
+#[derive(Debug, Default)]
+pub(crate) struct SubscriptionConfirmations {
+    /// The confirmed topics, by peer.
+    confirmed: HashMap<PeerId, BTreeSet<TopicHash>>,
+}
+
+impl SubscriptionConfirmations {
+    /// Record the peer's confirmation of the local subscription to the topic.
+    ///
+    /// Returns `true` if this is the first confirmation of the topic by the peer.
+    pub(crate) fn confirm(&mut self, peer: PeerId, topic: &TopicHash) -> bool {
+        let topics = self.confirmed.entry(peer).or_default();
+        if topics.contains(topic) {
+            return false;
+        }
+
+        topics.insert(topic.clone())
+    }
+
+    /// Get the locally subscribed topics the peer confirmed knowing about.
+    pub(crate) fn confirmed_topics(&self, peer: &PeerId) -> BTreeSet<TopicHash> {
+        self.confirmed.get(peer).cloned().unwrap_or_default()
+    }
+
+    /// Drop the disconnected peer's confirmations.
+    pub(crate) fn peer_disconnected(&mut self, peer: &PeerId) {
+        self.confirmed.remove(peer);
+    }
+
+    /// Drop the confirmations of the topic the local node unsubscribed from.
+    pub(crate) fn topic_unsubscribed(&mut self, topic: &TopicHash) {
+        self.confirmed.retain(|_, topics| {
+            topics.remove(topic);
+            !topics.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_topic(name: &str) -> TopicHash {
+        TopicHash::from_raw(format!("/test/{name}"))
+    }
+
+    #[test]
+    fn first_confirmation_of_a_topic_by_a_peer_is_reported_once() {
+        //// Given
+        let topic = new_test_topic("topic");
+        let peer = PeerId::random();
+        let other_peer = PeerId::random();
+        let mut confirmations = SubscriptionConfirmations::default();
+
+        //// When
+        let first = confirmations.confirm(peer, &topic);
+        let second = confirmations.confirm(peer, &topic);
+        let other_first = confirmations.confirm(other_peer, &topic);
+
+        //// Then
+        assert!(first);
+        assert!(!second, "The confirmation should be reported once");
+        assert!(other_first, "The confirmations should be per peer");
+        assert_eq!(
+            confirmations.confirmed_topics(&peer),
+            BTreeSet::from([topic])
+        );
+    }
+
+    #[test]
+    fn confirmations_are_reset_on_disconnect_and_unsubscribe() {
+        //// Given
+        let topic_a = new_test_topic("a");
+        let topic_b = new_test_topic("b");
+        let peer = PeerId::random();
+        let other_peer = PeerId::random();
+        let mut confirmations = SubscriptionConfirmations::default();
+        for topic in [&topic_a, &topic_b] {
+            confirmations.confirm(peer, topic);
+            confirmations.confirm(other_peer, topic);
+        }
+
+        //// When
+        confirmations.peer_disconnected(&peer);
+        confirmations.topic_unsubscribed(&topic_a);
+
+        //// Then
+        assert!(confirmations.confirmed_topics(&peer).is_empty());
+        assert_eq!(
+            confirmations.confirmed_topics(&other_peer),
+            BTreeSet::from([topic_b])
+        );
+        assert!(
+            confirmations.confirm(peer, &topic_a),
+            "The topic should be confirmed again after the reset"
+        );
+    }
+}
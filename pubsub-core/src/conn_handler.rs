@@ -2,6 +2,7 @@ pub use events::Command;
 pub use events::Event;
 pub use handler::Handler;
 
+mod coalesce;
 mod codec;
 mod downstream;
 mod events;
@@ -9,3 +10,5 @@ mod events_stream_handler;
 mod handler;
 mod recv_only_stream_handler;
 mod send_only_stream_handler;
+#[cfg(test)]
+mod tests;
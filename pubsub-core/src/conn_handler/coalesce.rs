@@ -0,0 +1,102 @@
+//! Outbound frames coalescing.
+//!
+//! All the pubsub frame protobuf fields are length-delimited, and a protobuf message decoded from
+//! the concatenation of two encoded messages is the merge of both: repeated fields are appended
+//! and embedded messages are merged. Adjacent queued frames can therefore be coalesced into a
+//! single frame by concatenating their bytes, without decoding and re-encoding them.
+//!
+//! The receiver processes the messages of a frame first, then its subscription actions and,
+//! finally, its control messages. Frames are only coalesced if their contents are not reordered,
+//! e.g., a frame carrying a subscription action is not coalesced with a following frame carrying
+//! a message.
+
+use bytes::{Bytes, BytesMut};
+
+/// The protobuf length-delimited wire type.
+const WIRE_TYPE_LEN: u64 = 2;
+
+/// Get the rank, in the receiver processing order, of a frame protobuf field.
+fn field_rank(tag: u64) -> Option<u8> {
+    match tag {
+        // Messages (`publish`).
+        2 => Some(0),
+        // Subscription actions (`subscriptions`).
+        1 => Some(1),
+        // Control messages (`control`).
+        3 => Some(2),
+        _ => None,
+    }
+}
+
+/// Scan the frame top-level fields and return the minimum and maximum rank of its fields.
+///
+/// An empty frame returns `(u8::MAX, 0)`, so it can be coalesced with any other frame. Returns
+/// `None` if the frame is not a well-formed frame protobuf, or contains unknown fields.
+fn frame_ranks(mut frame: &[u8]) -> Option<(u8, u8)> {
+    let (mut min, mut max) = (u8::MAX, 0);
+
+    while !frame.is_empty() {
+        let (key, rest) = unsigned_varint::decode::u64(frame).ok()?;
+        if key & 0x7 != WIRE_TYPE_LEN {
+            return None;
+        }
+        let rank = field_rank(key >> 3)?;
+
+        let (len, rest) = unsigned_varint::decode::usize(rest).ok()?;
+        if len > rest.len() {
+            return None;
+        }
+        frame = &rest[len..];
+
+        min = min.min(rank);
+        max = max.max(rank);
+    }
+
+    Some((min, max))
+}
+
+/// Coalesce the longest run of frames at the front of the send queue whose combined size does not
+/// exceed `max_frame_size` and whose contents are not reordered by the coalescing.
+///
+/// Returns the frame to send and the number of queued frames it covers, or `None` if the queue is
+/// empty. Frames that cannot be scanned are sent as they are.
+pub(super) fn coalesce_front<'a>(
+    queue: impl Iterator<Item = &'a Bytes> + Clone,
+    max_frame_size: usize,
+) -> Option<(Bytes, usize)> {
+    let first = queue.clone().next()?;
+
+    let Some((_, mut ranks_max)) = frame_ranks(first) else {
+        return Some((first.clone(), 1));
+    };
+
+    let mut len = first.len();
+    let mut count = 1;
+    for frame in queue.clone().skip(1) {
+        if len + frame.len() > max_frame_size {
+            break;
+        }
+
+        let Some((min, max)) = frame_ranks(frame) else {
+            break;
+        };
+        if ranks_max > min {
+            break;
+        }
+
+        ranks_max = ranks_max.max(max);
+        len += frame.len();
+        count += 1;
+    }
+
+    if count == 1 {
+        return Some((first.clone(), 1));
+    }
+
+    let mut frame = BytesMut::with_capacity(len);
+    for queued in queue.take(count) {
+        frame.extend_from_slice(queued);
+    }
+
+    Some((frame.freeze(), count))
+}
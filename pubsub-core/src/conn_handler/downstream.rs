@@ -1,35 +1,52 @@
 use std::collections::VecDeque;
 use std::task::{Context, Poll};
+use std::time::Instant;
 
 use asynchronous_codec::Framed;
 use bytes::Bytes;
+use futures::{AsyncRead, AsyncWrite};
 use libp2p::Stream;
 
 use libp2p_pubsub_common::service::{BufferedContext, PollCtx, Service, ServiceContext};
 
+use crate::message::Priority;
+
+use super::coalesce::coalesce_front;
 use super::codec::Codec;
 use super::events_stream_handler::{StreamHandlerIn, StreamHandlerOut};
 use super::send_only_stream_handler::SendOnlyStreamHandler;
 
 #[allow(clippy::large_enum_variant)]
-pub enum DownstreamIn {
-    /// Send bytes to the downstream.
-    Send(Bytes),
+pub enum DownstreamIn<S = Stream> {
+    /// Send bytes to the downstream, unless still queued past the deadline, if any. The high
+    /// priority bytes are queued ahead of the normal priority bytes not being sent yet.
+    Send(Bytes, Option<Instant>, Priority),
+    /// Send bytes to the downstream, and report the tag once they are flushed.
+    SendTagged(Bytes, u64),
+    /// Revoke the queued tagged bytes, if not being sent yet.
+    CancelTagged(u64),
+    /// Set (or clear) the suspected frame size limit of the remote.
+    SetFrameLimit(Option<usize>),
     /// A connection handler event,
-    ConnHandlerEvent(DownstreamConnHandlerInEvent),
+    ConnHandlerEvent(DownstreamConnHandlerInEvent<S>),
 }
 
 #[allow(clippy::large_enum_variant)]
-pub enum DownstreamConnHandlerInEvent {
+pub enum DownstreamConnHandlerInEvent<S = Stream> {
     /// The substream has been fully negotiated.
-    FullyNegotiated(Framed<Stream, Codec>),
+    FullyNegotiated(Framed<S, Codec>),
     /// The substream upgrade failed.
     UpradeError,
 }
 
 pub enum DownstreamOut {
-    /// Acknowledge the send action.
-    SendAck,
+    /// Acknowledge the send action, along with the tags of the tagged frames sent.
+    SendAck { tags: Vec<u64> },
+    /// Queued frames were dropped: `expired` past their deadline, and `cancelled` revoked.
+    FramesDropped { expired: usize, cancelled: usize },
+    /// The outbound substream failed after sending frames of up to `frame_size` bytes. The
+    /// unacknowledged frames are re-sent over a new substream.
+    SendFailed { frame_size: usize },
     /// A connection handler event.
     ConnHandlerEvent(DownstreamConnHandlerOutEvent),
 }
@@ -47,27 +64,63 @@ pub enum DownstreamError {
     MaxRetriesReached,
 }
 
-pub struct Downstream {
+/// A frame queued for sending.
+struct QueuedFrame {
+    /// The encoded frame.
+    bytes: Bytes,
+    /// The frame tag, reported once the frame is flushed.
+    tag: Option<u64>,
+    /// The frame deadline. The frame is dropped if still queued past the deadline.
+    expires_at: Option<Instant>,
+    /// The frame local scheduling priority.
+    priority: Priority,
+}
+
+pub struct Downstream<S = Stream>
+where
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
+{
     /// The outbound substream.
-    outbound_substream: Option<BufferedContext<SendOnlyStreamHandler>>,
+    outbound_substream: Option<BufferedContext<SendOnlyStreamHandler<S>>>,
     /// If the outbound substream is currently being negotiated.
     outbound_substream_requested: bool,
     /// The send queue.
-    send_queue: VecDeque<Bytes>,
+    send_queue: VecDeque<QueuedFrame>,
+    /// The number of queued frames coalesced into the frame being sent.
+    in_flight: usize,
+    /// The maximum frame size. Queued frames are only coalesced up to this size.
+    max_frame_size: usize,
+    /// The suspected frame size limit of the remote, if lower than the maximum frame size.
+    frame_limit: Option<usize>,
+    /// The size of the largest frame sent over the current outbound substream.
+    largest_sent: usize,
     /// The maximum number of send retry attempts.
     max_send_retry_attempts: usize,
     /// The number of send retries.
     send_retries: usize,
+    /// The number of queued frames dropped past their deadline, pending to be reported.
+    expired: usize,
+    /// The number of queued tagged frames revoked, pending to be reported.
+    cancelled: usize,
 }
 
-impl Downstream {
-    pub fn new(max_send_retry_attempts: usize) -> Self {
+impl<S> Downstream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    pub fn new(max_send_retry_attempts: usize, max_frame_size: usize) -> Self {
         Self {
             max_send_retry_attempts,
             outbound_substream: None,
             outbound_substream_requested: false,
             send_retries: 0,
             send_queue: VecDeque::new(),
+            in_flight: 0,
+            max_frame_size,
+            frame_limit: None,
+            largest_sent: 0,
+            expired: 0,
+            cancelled: 0,
         }
     }
 
@@ -75,10 +128,85 @@ impl Downstream {
     pub fn is_sending(&self) -> bool {
         matches!(self.outbound_substream, Some(ref s) if s.is_sending())
     }
+
+    /// Set the suspected frame size limit of the remote.
+    ///
+    /// The queued frames, not yet in flight, larger than the limit are dropped: the remote would
+    /// reset the substream on receiving them.
+    fn set_frame_limit(&mut self, frame_limit: Option<usize>) {
+        self.frame_limit = frame_limit;
+
+        if let Some(limit) = frame_limit {
+            let mut pending = self.send_queue.split_off(self.in_flight);
+            let queued = pending.len();
+            pending.retain(|frame| frame.bytes.len() <= limit);
+            if pending.len() < queued {
+                tracing::debug!(
+                    limit,
+                    dropped = queued - pending.len(),
+                    "Dropping queued frames above the remote frame limit"
+                );
+            }
+            self.send_queue.extend(pending);
+        }
+    }
+
+    /// Queue a frame for sending.
+    ///
+    /// The high priority frames are queued after the frames in flight and the other queued high
+    /// priority frames, ahead of the queued normal priority frames.
+    fn enqueue(&mut self, frame: QueuedFrame) {
+        if frame.priority == Priority::Normal {
+            self.send_queue.push_back(frame);
+            return;
+        }
+
+        let position = self
+            .send_queue
+            .iter()
+            .skip(self.in_flight)
+            .position(|queued| queued.priority == Priority::Normal)
+            .map_or(self.send_queue.len(), |position| self.in_flight + position);
+        self.send_queue.insert(position, frame);
+    }
+
+    /// Revoke the queued tagged frame with the given tag.
+    ///
+    /// The frames in flight are not revoked: they are reported once flushed.
+    fn cancel_tagged(&mut self, tag: u64) {
+        let position = self
+            .send_queue
+            .iter()
+            .skip(self.in_flight)
+            .position(|frame| frame.tag == Some(tag));
+        if let Some(position) = position {
+            tracing::trace!(tag, "Cancelling queued tagged frame");
+            self.send_queue.remove(self.in_flight + position);
+            self.cancelled += 1;
+        }
+    }
+
+    /// Drop the queued frames whose deadline has passed.
+    ///
+    /// Only called when no frame is in flight, right before dequeuing the next frames to send.
+    fn drop_expired_frames(&mut self, now: Instant) {
+        let queued = self.send_queue.len();
+        self.send_queue
+            .retain(|frame| frame.expires_at.map_or(true, |deadline| deadline > now));
+
+        let expired = queued - self.send_queue.len();
+        if expired > 0 {
+            tracing::debug!(expired, "Dropping queued frames past their deadline");
+            self.expired += expired;
+        }
+    }
 }
 
-impl Service for Downstream {
-    type InEvent = DownstreamIn;
+impl<S> Service for Downstream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    type InEvent = DownstreamIn<S>;
     type OutEvent = Result<DownstreamOut, DownstreamError>;
 
     fn poll<'a>(
@@ -87,7 +215,7 @@ impl Service for Downstream {
         cx: &mut Context<'_>,
     ) -> Poll<Self::OutEvent> {
         // Process input events.
-        if let Some(ev) = svc_cx.pop_next() {
+        while let Some(ev) = svc_cx.pop_next() {
             match ev {
                 DownstreamIn::ConnHandlerEvent(DownstreamConnHandlerInEvent::FullyNegotiated(
                     stream,
@@ -95,6 +223,7 @@ impl Service for Downstream {
                     self.outbound_substream_requested = false;
                     self.outbound_substream =
                         Some(BufferedContext::new(SendOnlyStreamHandler::new(stream)));
+                    self.largest_sent = 0;
                 }
                 DownstreamIn::ConnHandlerEvent(DownstreamConnHandlerInEvent::UpradeError) => {
                     self.outbound_substream_requested = false;
@@ -102,8 +231,27 @@ impl Service for Downstream {
 
                     return Poll::Ready(Err(DownstreamError::UpgradeError));
                 }
-                DownstreamIn::Send(bytes) => {
-                    self.send_queue.push_back(bytes);
+                DownstreamIn::Send(bytes, expires_at, priority) => {
+                    self.enqueue(QueuedFrame {
+                        bytes,
+                        tag: None,
+                        expires_at,
+                        priority,
+                    });
+                }
+                DownstreamIn::SendTagged(bytes, tag) => {
+                    self.enqueue(QueuedFrame {
+                        bytes,
+                        tag: Some(tag),
+                        expires_at: None,
+                        priority: Priority::Normal,
+                    });
+                }
+                DownstreamIn::CancelTagged(tag) => {
+                    self.cancel_tagged(tag);
+                }
+                DownstreamIn::SetFrameLimit(frame_limit) => {
+                    self.set_frame_limit(frame_limit);
                 }
             }
         }
@@ -123,29 +271,44 @@ impl Service for Downstream {
         }
 
         if let Some(mut outbound_substream) = self.outbound_substream.take() {
-            // If the outbound substream is idle, send the next byte sequence.
+            // If the outbound substream is idle, send the next byte sequence, coalescing the
+            // adjacent queued frames into a single frame when possible.
             if outbound_substream.is_idle() {
-                if let Some(bytes) = self.send_queue.front() {
-                    outbound_substream.do_send(StreamHandlerIn::Send(bytes.clone()));
+                self.drop_expired_frames(Instant::now());
+
+                let max_frame_size = self
+                    .frame_limit
+                    .map_or(self.max_frame_size, |limit| limit.min(self.max_frame_size));
+                let queue = self.send_queue.iter().map(|frame| &frame.bytes);
+                if let Some((frame, count)) = coalesce_front(queue, max_frame_size) {
+                    self.in_flight = count;
+                    self.largest_sent = self.largest_sent.max(frame.len());
+                    outbound_substream.do_send(StreamHandlerIn::Send(frame));
                 }
             }
 
-            if let Poll::Ready(ev) = outbound_substream.poll(cx) {
-                match ev {
+            match outbound_substream.poll(cx) {
+                Poll::Ready(ev) => match ev {
                     Ok(StreamHandlerOut::SendAck) => {
                         // Reset the send retries and re-insert the outbound substream.
                         self.send_retries = 0;
                         self.outbound_substream = Some(outbound_substream);
 
-                        // Drop the sent bytes.
-                        self.send_queue.pop_front();
+                        // Drop the sent frames, and report the tagged ones.
+                        let tags = self
+                            .send_queue
+                            .drain(..self.in_flight)
+                            .filter_map(|frame| frame.tag)
+                            .collect();
+                        self.in_flight = 0;
 
-                        return Poll::Ready(Ok(DownstreamOut::SendAck));
+                        return Poll::Ready(Ok(DownstreamOut::SendAck { tags }));
                     }
                     Err(err) => {
                         tracing::debug!("send failed: {}", err);
 
                         self.outbound_substream = None;
+                        self.in_flight = 0;
 
                         // If the maximum number of send retries has been reached, return an error,
                         // otherwise increment the retries counter.
@@ -155,17 +318,29 @@ impl Service for Downstream {
                             self.send_retries += 1;
                         }
 
-                        // Request a new outbound substream.
-                        self.outbound_substream_requested = true;
-                        return Poll::Ready(Ok(DownstreamOut::ConnHandlerEvent(
-                            DownstreamConnHandlerOutEvent::RequestNewSubstream,
-                        )));
+                        // Report the failure, along with the size of the largest frame sent over the
+                        // failed substream. A new outbound substream is requested on the next
+                        // poll, as the unacknowledged frames are still queued.
+                        let frame_size = std::mem::take(&mut self.largest_sent);
+                        return Poll::Ready(Ok(DownstreamOut::SendFailed { frame_size }));
                     }
                     _ => unreachable!("unexpected event: {:?}", ev),
+                },
+                Poll::Pending => {
+                    // Re-insert the outbound substream, the send is still in progress.
+                    self.outbound_substream = Some(outbound_substream);
                 }
             }
         }
 
+        // Report the queued frames dropped.
+        if self.expired > 0 || self.cancelled > 0 {
+            return Poll::Ready(Ok(DownstreamOut::FramesDropped {
+                expired: std::mem::take(&mut self.expired),
+                cancelled: std::mem::take(&mut self.cancelled),
+            }));
+        }
+
         Poll::Pending
     }
 }
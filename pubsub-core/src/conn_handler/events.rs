@@ -1,26 +1,114 @@
 use std::fmt::{Debug, Formatter};
+use std::time::Instant;
 
 use bytes::Bytes;
 
+use crate::event::DisabledReason;
+use crate::message::Priority;
+
+/// A command sent by the behaviour to the connection handler.
+#[non_exhaustive]
 pub enum Command {
     /// A pubsub frame to send to the remote.
-    SendFrame(Bytes),
+    SendFrame {
+        /// The encoded frame.
+        frame: Bytes,
+        /// The frame deadline. If the frame is still queued once the deadline has passed, it is
+        /// dropped instead of being sent. If `None`, the frame never expires.
+        expires_at: Option<Instant>,
+        /// The frame local scheduling priority. The high priority frames are queued ahead of the
+        /// normal priority frames not being sent yet.
+        priority: Priority,
+    },
+
+    /// A pubsub frame to send to the remote, reported with an [`Event::TaggedFrameSent`] event,
+    /// carrying the tag, once flushed to the substream.
+    SendTaggedFrame {
+        /// The encoded frame.
+        frame: Bytes,
+        /// The frame tag.
+        tag: u64,
+    },
+
+    /// Revoke the queued tagged frame with the given tag (see [`Command::SendTaggedFrame`]).
+    ///
+    /// The frame is only revoked if it is not being sent yet. A revoked frame is not reported with
+    /// an [`Event::TaggedFrameSent`] event.
+    CancelTagged(u64),
+
+    /// Set (or clear, if `None`) the suspected frame size limit of the remote.
+    ///
+    /// The queued frames are only coalesced up to this limit, and the queued frames larger than
+    /// the limit are dropped.
+    SetFrameLimit(Option<usize>),
 }
 
 impl Debug for Command {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Command::SendFrame(_) => write!(f, "SendFrame(...)"),
+            Command::SendFrame {
+                expires_at,
+                priority,
+                ..
+            } => {
+                write!(
+                    f,
+                    "SendFrame(..., expires_at: {expires_at:?}, priority: {priority:?})"
+                )
+            }
+            Command::SendTaggedFrame { tag, .. } => write!(f, "SendTaggedFrame({tag}, ...)"),
+            Command::CancelTagged(tag) => write!(f, "CancelTagged({tag})"),
+            Command::SetFrameLimit(limit) => write!(f, "SetFrameLimit({limit:?})"),
         }
     }
 }
 
+/// An event reported by the connection handler to the behaviour.
+#[non_exhaustive]
 pub enum Event {
     /// A pubsub frame has been received.
     FrameReceived(Bytes),
 
     /// The frame was sent.
     FrameSent,
+
+    /// The tagged frame was flushed to the substream (see [`Command::SendTaggedFrame`]).
+    ///
+    /// Reported after the [`Event::FrameSent`] event of the send that carried the frame.
+    TaggedFrameSent {
+        /// The frame tag.
+        tag: u64,
+    },
+
+    /// The outbound substream failed. The unacknowledged frames are re-sent over a new
+    /// substream.
+    FrameSendFailed {
+        /// The size, in bytes, of the largest frame sent over the failed substream.
+        frame_size: usize,
+    },
+
+    /// Queued frames were dropped before being sent.
+    ///
+    /// The drops are aggregated, and reported once per connection handler poll.
+    FramesDropped {
+        /// The number of frames dropped because their deadline passed while queued (see
+        /// [`Command::SendFrame`]).
+        expired: usize,
+        /// The number of tagged frames revoked by the behaviour (see [`Command::CancelTagged`]).
+        cancelled: usize,
+    },
+
+    /// The pubsub protocol was negotiated with the remote.
+    ///
+    /// Only reported once per connection, and only if the protocol upgrade supports multiple
+    /// protocol ids.
+    ProtocolNegotiated(String),
+
+    /// The connection handler was disabled. No more frames will be sent over the connection.
+    Disabled {
+        /// The reason the handler was disabled.
+        reason: DisabledReason,
+    },
 }
 
 impl Debug for Event {
@@ -28,6 +116,15 @@ impl Debug for Event {
         match self {
             Event::FrameReceived(_) => write!(f, "FrameReceived(...)"),
             Event::FrameSent => write!(f, "FrameSent"),
+            Event::TaggedFrameSent { tag } => write!(f, "TaggedFrameSent({tag})"),
+            Event::FrameSendFailed { frame_size } => {
+                write!(f, "FrameSendFailed({frame_size} bytes)")
+            }
+            Event::FramesDropped { expired, cancelled } => {
+                write!(f, "FramesDropped({expired} expired, {cancelled} cancelled)")
+            }
+            Event::ProtocolNegotiated(protocol) => write!(f, "ProtocolNegotiated({protocol})"),
+            Event::Disabled { reason } => write!(f, "Disabled({reason:?})"),
         }
     }
 }
@@ -50,4 +50,7 @@ pub enum StreamHandlerError {
 
     #[error("stream closed by remote")]
     ClosedByRemote,
+
+    #[error("received frame exceeds the maximum frame size")]
+    FrameTooLarge,
 }
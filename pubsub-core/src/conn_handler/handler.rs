@@ -1,21 +1,26 @@
+use std::collections::VecDeque;
 use std::convert::Infallible;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
 
 use asynchronous_codec::Framed;
+use futures::{AsyncRead, AsyncWrite};
 use libp2p::swarm::handler::{
     ConnectionEvent, DialUpgradeError, FullyNegotiatedInbound, FullyNegotiatedOutbound,
 };
 use libp2p::swarm::{
-    ConnectionHandler, ConnectionHandlerEvent, KeepAlive, StreamUpgradeError, SubstreamProtocol,
+    ConnectionHandler, ConnectionHandlerEvent, KeepAlive, Stream, StreamUpgradeError,
+    SubstreamProtocol,
 };
 
 use libp2p_pubsub_common::service::{BufferedContext, ServiceContext};
 
 use crate::conn_handler::downstream::{
-    DownstreamConnHandlerInEvent, DownstreamConnHandlerOutEvent, DownstreamIn, DownstreamOut,
+    DownstreamConnHandlerInEvent, DownstreamConnHandlerOutEvent, DownstreamError, DownstreamIn,
+    DownstreamOut,
 };
-use crate::upgrade::{ProtocolUpgradeOutput, ProtocolUpgradeSend};
+use crate::event::DisabledReason;
+use crate::upgrade::{ProtocolUpgradeOutput, SocketProtocolUpgradeSend};
 
 use super::codec::Codec;
 use super::downstream::Downstream;
@@ -25,7 +30,13 @@ use super::recv_only_stream_handler::RecvOnlyStreamHandler;
 
 /// A connection handler that manages a single, inbound and outbound, long-lived substream over
 /// a connection with a peer.
-pub struct Handler<U> {
+///
+/// The substream socket type defaults to the libp2p [`Stream`]. It is generic so the handler can be
+/// driven with a mock substream in tests.
+pub struct Handler<U, S = Stream>
+where
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
+{
     /// The protocol upgrade.
     upgrade: U,
 
@@ -39,21 +50,37 @@ pub struct Handler<U> {
     max_frame_size: usize,
 
     /// The single long-lived outbound substream.
-    downstream: BufferedContext<Downstream>,
+    downstream: BufferedContext<Downstream<S>>,
 
     /// The single long-lived inbound substream.
-    inbound_substream: Option<BufferedContext<RecvOnlyStreamHandler>>,
+    inbound_substream: Option<BufferedContext<RecvOnlyStreamHandler<S>>>,
 
     /// The last time we performed IO on the connection.
     last_io_activity: Instant,
 
     /// The amount of time we keep an idle connection alive.
     idle_timeout: Duration,
+
+    /// Whether the negotiated protocol should be reported to the behaviour.
+    ///
+    /// It is only reported if the protocol upgrade supports multiple protocol ids.
+    report_protocol: bool,
+
+    /// The protocol negotiated on the first fully negotiated substream, pending to be reported to
+    /// the behaviour.
+    negotiated_protocol: Option<String>,
+
+    /// The tags of the tagged frames sent, pending to be reported to the behaviour.
+    sent_tags: VecDeque<u64>,
+
+    /// Whether the handler was created disabled (see [`Handler::new_disabled`]).
+    disabled: bool,
 }
 
-impl<U> Handler<U>
+impl<U, S> Handler<U, S>
 where
-    U: ProtocolUpgradeSend + 'static,
+    U: SocketProtocolUpgradeSend<S> + 'static,
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
 {
     pub fn new(
         upgrade: U,
@@ -61,21 +88,60 @@ where
         idle_timeout: Duration,
         max_send_retry_attempts: usize,
     ) -> Self {
+        let report_protocol = upgrade.protocol_info().nth(1).is_some();
+
         Self {
             upgrade,
             keep_alive: true,
             max_frame_size,
-            downstream: BufferedContext::new(Downstream::new(max_send_retry_attempts)),
+            downstream: BufferedContext::new(Downstream::new(
+                max_send_retry_attempts,
+                max_frame_size,
+            )),
             inbound_substream: Default::default(),
             last_io_activity: Instant::now(),
             idle_timeout,
+            report_protocol,
+            negotiated_protocol: None,
+            sent_tags: Default::default(),
+            disabled: false,
+        }
+    }
+
+    /// Create a new disabled connection handler, for a connection kept for the other behaviours
+    /// but ignored by pubsub (see [`GateDecision::AllowDisabled`](crate::GateDecision::AllowDisabled)).
+    ///
+    /// A disabled handler never opens an outbound substream, drops the inbound substreams, ignores
+    /// the behaviour commands, and does not keep the connection alive.
+    pub fn new_disabled(upgrade: U, max_frame_size: usize) -> Self {
+        Self {
+            keep_alive: false,
+            report_protocol: false,
+            disabled: true,
+            ..Self::new(upgrade, max_frame_size, Duration::ZERO, 0)
+        }
+    }
+}
+
+impl<U, S> Handler<U, S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    /// Record the protocol negotiated on a substream, to be reported to the behaviour.
+    ///
+    /// Only the protocol negotiated on the first substream is reported.
+    fn on_protocol_negotiated(&mut self, protocol: &str) {
+        if self.report_protocol {
+            self.report_protocol = false;
+            self.negotiated_protocol = Some(protocol.to_string());
         }
     }
 }
 
-impl<U> ConnectionHandler for Handler<U>
+impl<U, S> ConnectionHandler for Handler<U, S>
 where
-    U: ProtocolUpgradeSend + Clone,
+    U: SocketProtocolUpgradeSend<S> + Clone,
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
     type FromBehaviour = Command;
     type ToBehaviour = Event;
@@ -117,6 +183,20 @@ where
             return Poll::Pending;
         }
 
+        // Report the negotiated protocol to the behaviour.
+        if let Some(protocol) = self.negotiated_protocol.take() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                Event::ProtocolNegotiated(protocol),
+            ));
+        }
+
+        // Report the tagged frames sent.
+        if let Some(tag) = self.sent_tags.pop_front() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                Event::TaggedFrameSent { tag },
+            ));
+        }
+
         if let Some(mut inbound_substream) = self.inbound_substream.take() {
             // Poll the inbound substream (upstream).
             if let Poll::Ready(ev) = inbound_substream.poll(cx) {
@@ -152,13 +232,29 @@ where
         // Poll the downstream handler (outbound).
         if let Poll::Ready(ev) = self.downstream.poll(cx) {
             match ev {
-                Ok(DownstreamOut::SendAck) => {
+                Ok(DownstreamOut::SendAck { tags }) => {
                     // Update the last IO activity time.
                     self.last_io_activity = Instant::now();
 
+                    // Report the tagged frames sent after the send acknowledgement.
+                    self.sent_tags.extend(tags);
+
                     // Notify the behaviour about the received frame.
                     return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Event::FrameSent));
                 }
+                Ok(DownstreamOut::FramesDropped { expired, cancelled }) => {
+                    // Notify the behaviour about the queued frames dropped.
+                    return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                        Event::FramesDropped { expired, cancelled },
+                    ));
+                }
+                Ok(DownstreamOut::SendFailed { frame_size }) => {
+                    // Notify the behaviour about the failed substream, so it can infer the remote
+                    // frame size limit.
+                    return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                        Event::FrameSendFailed { frame_size },
+                    ));
+                }
                 Ok(DownstreamOut::ConnHandlerEvent(
                     DownstreamConnHandlerOutEvent::RequestNewSubstream,
                 )) => {
@@ -172,6 +268,15 @@ where
 
                     // Mark the connection as not keep alive.
                     self.keep_alive = false;
+
+                    // Notify the behaviour about the disabled handler.
+                    let reason = match err {
+                        DownstreamError::UpgradeError => DisabledReason::UpgradeFailed,
+                        DownstreamError::MaxRetriesReached => DisabledReason::MaxSendRetriesReached,
+                    };
+                    return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Event::Disabled {
+                        reason,
+                    }));
                 }
             }
         }
@@ -181,10 +286,32 @@ where
 
     fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
         tracing::trace!(?event, "Received behaviour event");
+        if self.disabled {
+            return;
+        }
+
         match event {
-            Command::SendFrame(bytes) => {
+            Command::SendFrame {
+                frame,
+                expires_at,
+                priority,
+            } => {
                 // Notify the downstream handler about the new frame to be sent.
-                self.downstream.do_send(DownstreamIn::Send(bytes));
+                self.downstream
+                    .do_send(DownstreamIn::Send(frame, expires_at, priority));
+            }
+            Command::SendTaggedFrame { frame, tag } => {
+                // Notify the downstream handler about the new tagged frame to be sent.
+                self.downstream
+                    .do_send(DownstreamIn::SendTagged(frame, tag));
+            }
+            Command::CancelTagged(tag) => {
+                // Notify the downstream handler about the revoked tagged frame.
+                self.downstream.do_send(DownstreamIn::CancelTagged(tag));
+            }
+            Command::SetFrameLimit(limit) => {
+                // Notify the downstream handler about the remote frame size limit.
+                self.downstream.do_send(DownstreamIn::SetFrameLimit(limit));
             }
         }
     }
@@ -199,10 +326,16 @@ where
         >,
     ) {
         match event {
+            ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound { .. })
+                if self.disabled =>
+            {
+                tracing::trace!("Dropping inbound substream, handler disabled");
+            }
             ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound {
                 protocol, ..
             }) => {
-                let ProtocolUpgradeOutput { socket, .. } = protocol;
+                let ProtocolUpgradeOutput { socket, info } = protocol;
+                self.on_protocol_negotiated(info.as_ref());
 
                 let codec = Codec::new(self.max_frame_size);
                 let stream = Framed::new(socket, codec);
@@ -216,7 +349,8 @@ where
             ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
                 protocol, ..
             }) => {
-                let ProtocolUpgradeOutput { socket, .. } = protocol;
+                let ProtocolUpgradeOutput { socket, info } = protocol;
+                self.on_protocol_negotiated(info.as_ref());
 
                 let codec = Codec::new(self.max_frame_size);
                 let stream = Framed::new(socket, codec);
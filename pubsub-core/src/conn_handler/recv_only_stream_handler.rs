@@ -2,7 +2,7 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use asynchronous_codec::Framed;
-use futures::{Sink, StreamExt};
+use futures::{AsyncRead, AsyncWrite, Sink, StreamExt};
 use libp2p::swarm::Stream;
 
 use libp2p_pubsub_common::service::{PollCtx, Service};
@@ -14,11 +14,11 @@ use super::events_stream_handler::{StreamHandlerError, StreamHandlerIn, StreamHa
 ///
 /// This enum acts as a state machine for the inbound substream. It is used to process inbound
 /// messages and close the substream.
-enum SubstreamState {
+enum SubstreamState<S> {
     /// Waiting for a message from the remote. The idle state for an inbound substream.
-    Idle(Framed<Stream, Codec>),
+    Idle(Framed<S, Codec>),
     /// The substream is being closed.
-    Closing(Framed<Stream, Codec>),
+    Closing(Framed<S, Codec>),
     /// The substream is disabled.
     Disabled,
     /// An error occurred during processing.
@@ -28,20 +28,23 @@ enum SubstreamState {
     Poisoned,
 }
 
-pub struct RecvOnlyStreamHandler {
-    state: SubstreamState,
+pub struct RecvOnlyStreamHandler<S = Stream> {
+    state: SubstreamState<S>,
 }
 
-impl RecvOnlyStreamHandler {
+impl<S> RecvOnlyStreamHandler<S> {
     /// Creates a new stream handler with the given stream.
-    pub fn new(stream: Framed<Stream, Codec>) -> Self {
+    pub fn new(stream: Framed<S, Codec>) -> Self {
         Self {
             state: SubstreamState::Idle(stream),
         }
     }
 }
 
-impl Service for RecvOnlyStreamHandler {
+impl<S> Service for RecvOnlyStreamHandler<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
+{
     type InEvent = StreamHandlerIn;
     type OutEvent = Result<StreamHandlerOut, StreamHandlerError>;
 
@@ -62,10 +65,19 @@ impl Service for RecvOnlyStreamHandler {
                         }
                         Poll::Ready(Some(Err(err))) => {
                             match err {
-                                e @ Error::MaxMessageLenExceeded => {
-                                    tracing::trace!("Ignoring received message: {}", e);
-                                    self.state = SubstreamState::Idle(stream);
-                                    continue;
+                                Error::MaxMessageLenExceeded => {
+                                    tracing::debug!("Received frame exceeds maximum frame size");
+
+                                    // Emit an error event.
+                                    svc_cx.emit(Err(StreamHandlerError::FrameTooLarge));
+
+                                    // The oversized frame bytes are not consumed, so the
+                                    // following bytes cannot be decoded. Drop the stream without
+                                    // closing it, so it is reset and the peer's pending writes
+                                    // fail. The peer will re-establish its outbound stream.
+                                    drop(stream);
+                                    self.state = SubstreamState::Disabled;
+                                    break;
                                 }
                                 e @ Error::LengthPrefixError(_) => {
                                     tracing::trace!("Ignoring received message: {}", e);
@@ -3,7 +3,7 @@ use std::task::{Context, Poll};
 
 use asynchronous_codec::Framed;
 use bytes::Bytes;
-use futures::Sink;
+use futures::{AsyncRead, AsyncWrite, Sink};
 use libp2p::swarm::Stream;
 
 use libp2p_pubsub_common::service::{PollCtx, Service};
@@ -12,26 +12,26 @@ use super::codec::{Codec, Error};
 use super::events_stream_handler::{StreamHandlerError, StreamHandlerIn, StreamHandlerOut};
 
 /// State of the outbound substream, opened either by us or by the remote.
-enum SubstreamState {
+enum SubstreamState<S> {
     /// Waiting for the user to send a message. The idle state for an outbound substream.
-    Idle(Framed<Stream, Codec>),
+    Idle(Framed<S, Codec>),
     /// Waiting to send a message to the remote.
-    PendingSend(Framed<Stream, Codec>, Bytes),
+    PendingSend(Framed<S, Codec>, Bytes),
     /// Waiting to flush the substream so that the data arrives to the remote.
-    PendingFlush(Framed<Stream, Codec>),
+    PendingFlush(Framed<S, Codec>),
     /// Disabled state.
     Disabled,
     /// An error occurred during processing.
     Poisoned,
 }
 
-pub struct SendOnlyStreamHandler {
-    state: SubstreamState,
+pub struct SendOnlyStreamHandler<S = Stream> {
+    state: SubstreamState<S>,
 }
 
-impl SendOnlyStreamHandler {
+impl<S> SendOnlyStreamHandler<S> {
     /// Creates a new `DownstreamHandler` with the given stream.
-    pub fn new(stream: Framed<Stream, Codec>) -> Self {
+    pub fn new(stream: Framed<S, Codec>) -> Self {
         Self {
             state: SubstreamState::Idle(stream),
         }
@@ -49,7 +49,10 @@ impl SendOnlyStreamHandler {
     }
 }
 
-impl Service for SendOnlyStreamHandler {
+impl<S> Service for SendOnlyStreamHandler<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + 'static,
+{
     type InEvent = StreamHandlerIn;
     type OutEvent = Result<StreamHandlerOut, StreamHandlerError>;
 
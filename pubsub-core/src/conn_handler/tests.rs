@@ -0,0 +1,820 @@
+use std::convert::Infallible;
+use std::iter;
+use std::time::{Duration, Instant};
+
+use assert_matches::assert_matches;
+use bytes::Bytes;
+use futures::future;
+use libp2p::core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
+use libp2p::swarm::{ConnectionHandler, ConnectionHandlerEvent, KeepAlive, Stream};
+use prost::Message as _;
+
+use libp2p_pubsub_proto::pubsub::{FrameProto, MessageProto, SubOptsProto};
+
+use testlib::handler::{MockBehavior, MockSubstream};
+
+use crate::event::DisabledReason;
+use crate::message::Priority;
+use crate::upgrade::ProtocolUpgradeOutput;
+
+use super::events::{Command, Event};
+use super::handler::Handler;
+
+const TEST_PROTOCOL_ID: &str = "/test/1.0.0";
+
+/// A protocol upgrade whose output socket is a [`MockSubstream`].
+///
+/// The upgrade is never applied: the tests inject the fully negotiated connection events directly.
+#[derive(Debug, Clone)]
+struct MockProtocolUpgrade;
+
+impl UpgradeInfo for MockProtocolUpgrade {
+    type Info = &'static str;
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(TEST_PROTOCOL_ID)
+    }
+}
+
+impl InboundUpgrade<Stream> for MockProtocolUpgrade {
+    type Output = ProtocolUpgradeOutput<&'static str, MockSubstream>;
+    type Error = Infallible;
+    type Future = future::Pending<Result<Self::Output, Self::Error>>;
+
+    fn upgrade_inbound(self, _socket: Stream, _info: Self::Info) -> Self::Future {
+        future::pending()
+    }
+}
+
+impl OutboundUpgrade<Stream> for MockProtocolUpgrade {
+    type Output = ProtocolUpgradeOutput<&'static str, MockSubstream>;
+    type Error = Infallible;
+    type Future = future::Pending<Result<Self::Output, Self::Error>>;
+
+    fn upgrade_outbound(self, _socket: Stream, _info: Self::Info) -> Self::Future {
+        future::pending()
+    }
+}
+
+type TestHandler = Handler<MockProtocolUpgrade, MockSubstream>;
+
+/// Create a new test connection handler with the given maximum number of send retry attempts.
+fn new_test_handler(max_send_retry_attempts: usize) -> TestHandler {
+    Handler::new(
+        MockProtocolUpgrade,
+        1024,
+        Duration::from_secs(60),
+        max_send_retry_attempts,
+    )
+}
+
+/// Wrap the mock substream in a protocol upgrade output.
+fn new_upgrade_output(socket: MockSubstream) -> ProtocolUpgradeOutput<&'static str, MockSubstream> {
+    ProtocolUpgradeOutput {
+        socket,
+        info: TEST_PROTOCOL_ID,
+    }
+}
+
+/// Encode the frame bytes as an unsigned-varint length-prefixed frame.
+fn encode_frame(frame: &[u8]) -> Vec<u8> {
+    let mut buf = unsigned_varint::encode::usize_buffer();
+    let prefix = unsigned_varint::encode::usize(frame.len(), &mut buf);
+    [prefix, frame].concat()
+}
+
+/// Assert the connection is kept alive until it idles for longer than the idle timeout.
+#[allow(deprecated)]
+fn assert_keep_alive_until_idle(handler: &TestHandler) {
+    assert_matches!(
+        handler.connection_keep_alive(),
+        KeepAlive::Until(_),
+        "Connection should be kept alive until idle timeout"
+    );
+}
+
+/// Request sending a frame and complete the outbound substream negotiation with `substream`.
+///
+/// Returns the events emitted by the handler before the substream negotiation.
+fn send_frame_over(
+    handler: &mut TestHandler,
+    frame: Bytes,
+    substream: MockSubstream,
+) -> Vec<testlib::handler::HandlerEvent<TestHandler>> {
+    handler.on_behaviour_event(Command::SendFrame {
+        frame,
+        expires_at: None,
+        priority: Priority::Normal,
+    });
+    let events = testlib::handler::drive(handler, 1);
+
+    handler.on_connection_event(testlib::handler::fully_negotiated_outbound::<TestHandler>(
+        new_upgrade_output(substream),
+        (),
+    ));
+
+    events
+}
+
+#[test]
+fn send_frame_happy_path() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    let frame = Bytes::from_static(b"test-frame");
+
+    //// When
+    let events_before = send_frame_over(&mut handler, frame.clone(), substream.clone());
+    let events_after = testlib::handler::drive(&mut handler, 4);
+
+    //// Then
+    assert_eq!(events_before.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(
+        &events_before[0],
+        ConnectionHandlerEvent::OutboundSubstreamRequest { .. }
+    );
+
+    assert_eq!(events_after.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(
+        &events_after[0],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::FrameSent)
+    );
+
+    assert_eq!(substream.written(), encode_frame(&frame));
+    assert_eq!(
+        substream.flush_count(),
+        1,
+        "Substream should be flushed once"
+    );
+}
+
+#[test]
+fn sink_error_mid_send_requests_a_new_substream() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new().with_write_behavior(MockBehavior::ErrorAfter(3));
+
+    let frame = Bytes::from_static(b"test-frame");
+
+    //// When
+    send_frame_over(&mut handler, frame, substream.clone());
+    let events = testlib::handler::drive(&mut handler, 4);
+
+    //// Then
+    assert_eq!(events.len(), 2, "Only 2 events should be emitted");
+    assert_matches!(
+        &events[0],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::FrameSendFailed { frame_size: 10 }),
+        "The behaviour should be notified about the failed frame size"
+    );
+    assert_matches!(
+        &events[1],
+        ConnectionHandlerEvent::OutboundSubstreamRequest { .. },
+        "A new outbound substream should be requested"
+    );
+    assert_eq!(
+        substream.written().len(),
+        3,
+        "Only 3 bytes should be written"
+    );
+    assert_keep_alive_until_idle(&handler);
+}
+
+#[test]
+fn sink_error_after_max_send_retries_closes_the_connection() {
+    //// Given
+    let mut handler = new_test_handler(0);
+    let substream = MockSubstream::new().with_write_behavior(MockBehavior::ErrorAfter(3));
+
+    let frame = Bytes::from_static(b"test-frame");
+
+    //// When
+    send_frame_over(&mut handler, frame, substream);
+    let events = testlib::handler::drive(&mut handler, 4);
+
+    //// Then
+    assert_eq!(events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(
+        &events[0],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::Disabled {
+            reason: DisabledReason::MaxSendRetriesReached
+        }),
+        "The behaviour should be notified about the disabled handler"
+    );
+    assert_eq!(handler.connection_keep_alive(), KeepAlive::No);
+}
+
+#[test]
+fn inbound_frames_are_notified_to_the_behaviour() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    let frame_a = Bytes::from_static(b"test-frame-a");
+    let frame_b = Bytes::from_static(b"test-frame-b");
+
+    handler.on_connection_event(testlib::handler::fully_negotiated_inbound::<TestHandler>(
+        new_upgrade_output(substream.clone()),
+        (),
+    ));
+
+    //// When
+    substream.push_read(encode_frame(&frame_a));
+    substream.push_read(encode_frame(&frame_b));
+
+    let events = testlib::handler::drive(&mut handler, 4);
+
+    //// Then
+    assert_eq!(events.len(), 2, "Only 2 events should be emitted");
+    assert_matches!(&events[0], ConnectionHandlerEvent::NotifyBehaviour(Event::FrameReceived(frame)) => {
+        assert_eq!(frame, &frame_a);
+    });
+    assert_matches!(&events[1], ConnectionHandlerEvent::NotifyBehaviour(Event::FrameReceived(frame)) => {
+        assert_eq!(frame, &frame_b);
+    });
+    assert!(
+        !substream.is_closed(),
+        "Inbound substream should not be closed"
+    );
+}
+
+#[test]
+fn inbound_read_error_closes_the_inbound_substream() {
+    //// Given
+    let mut handler = new_test_handler(2);
+
+    let frame = Bytes::from_static(b"test-frame");
+    let encoded_frame = encode_frame(&frame);
+
+    // Fail right after the first frame was read.
+    let substream =
+        MockSubstream::new().with_read_behavior(MockBehavior::ErrorAfter(encoded_frame.len()));
+
+    handler.on_connection_event(testlib::handler::fully_negotiated_inbound::<TestHandler>(
+        new_upgrade_output(substream.clone()),
+        (),
+    ));
+
+    //// When
+    substream.push_read(&encoded_frame);
+    substream.push_read(&encoded_frame);
+
+    let events = testlib::handler::drive(&mut handler, 4);
+
+    //// Then
+    assert_eq!(events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&events[0], ConnectionHandlerEvent::NotifyBehaviour(Event::FrameReceived(received)) => {
+        assert_eq!(received, &frame);
+    });
+    assert!(substream.is_closed(), "Inbound substream should be closed");
+    assert_keep_alive_until_idle(&handler);
+}
+
+#[test]
+fn inbound_frame_above_max_frame_size_drops_the_inbound_substream() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    handler.on_connection_event(testlib::handler::fully_negotiated_inbound::<TestHandler>(
+        new_upgrade_output(substream.clone()),
+        (),
+    ));
+
+    //// When
+    // The test handler maximum frame size is 1024 bytes.
+    substream.push_read(encode_frame(&[0; 2048]));
+    substream.push_read(encode_frame(b"test-frame"));
+
+    let events = testlib::handler::drive(&mut handler, 4);
+
+    //// Then
+    assert!(events.is_empty(), "No events should be emitted");
+    assert!(
+        !substream.is_closed(),
+        "Inbound substream should be dropped without closing it, so the remote send fails"
+    );
+    assert_keep_alive_until_idle(&handler);
+}
+
+#[test]
+fn inbound_substream_closed_by_remote_is_closed() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    handler.on_connection_event(testlib::handler::fully_negotiated_inbound::<TestHandler>(
+        new_upgrade_output(substream.clone()),
+        (),
+    ));
+
+    //// When
+    substream.close_read();
+
+    let events = testlib::handler::drive(&mut handler, 2);
+
+    //// Then
+    assert!(events.is_empty(), "No events should be emitted");
+    assert!(substream.is_closed(), "Inbound substream should be closed");
+}
+
+#[test]
+fn keep_alive_while_sending_a_frame() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new().with_write_behavior(MockBehavior::Pending);
+
+    let frame = Bytes::from_static(b"test-frame");
+
+    assert_keep_alive_until_idle(&handler);
+
+    //// When
+    send_frame_over(&mut handler, frame, substream.clone());
+    let events_while_sending = testlib::handler::drive(&mut handler, 2);
+    let keep_alive_while_sending = handler.connection_keep_alive();
+
+    substream.set_write_behavior(MockBehavior::Ready);
+    let events_after_sending = testlib::handler::drive(&mut handler, 2);
+
+    //// Then
+    assert!(
+        events_while_sending.is_empty(),
+        "No events should be emitted while sending"
+    );
+    assert_eq!(
+        keep_alive_while_sending,
+        KeepAlive::Yes,
+        "Connection should be kept alive while sending"
+    );
+
+    assert_eq!(
+        events_after_sending.len(),
+        1,
+        "Only 1 event should be emitted"
+    );
+    assert_matches!(
+        &events_after_sending[0],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::FrameSent)
+    );
+    assert_keep_alive_until_idle(&handler);
+}
+
+#[test]
+fn tagged_frame_is_reported_once_flushed() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new().with_write_behavior(MockBehavior::Pending);
+
+    let frame = Bytes::from_static(b"test-frame");
+
+    //// When
+    handler.on_behaviour_event(Command::SendTaggedFrame { frame, tag: 42 });
+    testlib::handler::drive(&mut handler, 1);
+    handler.on_connection_event(testlib::handler::fully_negotiated_outbound::<TestHandler>(
+        new_upgrade_output(substream.clone()),
+        (),
+    ));
+    let events_while_sending = testlib::handler::drive(&mut handler, 2);
+
+    substream.set_write_behavior(MockBehavior::Ready);
+    let events_after_sending = testlib::handler::drive(&mut handler, 4);
+
+    //// Then
+    assert!(
+        events_while_sending.is_empty(),
+        "No events should be emitted while the frame is not flushed"
+    );
+
+    assert_eq!(events_after_sending.len(), 2, "2 events should be emitted");
+    assert_matches!(
+        &events_after_sending[0],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::FrameSent)
+    );
+    assert_matches!(
+        &events_after_sending[1],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::TaggedFrameSent { tag: 42 })
+    );
+}
+
+/// Create a new encoded frame carrying a subscription action to the given topic.
+fn new_subscription_frame(topic: &str) -> Bytes {
+    FrameProto {
+        subscriptions: vec![SubOptsProto {
+            subscribe: Some(true),
+            topic_id: Some(topic.to_string()),
+        }],
+        ..Default::default()
+    }
+    .encode_to_vec()
+    .into()
+}
+
+/// Create a new encoded frame carrying a message with the given payload.
+fn new_message_frame(topic: &str, data: &[u8]) -> Bytes {
+    FrameProto {
+        publish: vec![MessageProto {
+            topic: topic.to_string(),
+            data: Some(Bytes::copy_from_slice(data)),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+    .encode_to_vec()
+    .into()
+}
+
+/// Request sending the frames and complete the outbound substream negotiation with `substream`.
+fn send_frames_over(
+    handler: &mut TestHandler,
+    frames: impl IntoIterator<Item = Bytes>,
+    substream: MockSubstream,
+) {
+    for frame in frames {
+        handler.on_behaviour_event(Command::SendFrame {
+            frame,
+            expires_at: None,
+            priority: Priority::Normal,
+        });
+    }
+    testlib::handler::drive(handler, 1);
+
+    handler.on_connection_event(testlib::handler::fully_negotiated_outbound::<TestHandler>(
+        new_upgrade_output(substream),
+        (),
+    ));
+}
+
+/// Split the written bytes into the length-prefixed frames and decode them.
+fn decode_written_frames(mut written: &[u8]) -> Vec<FrameProto> {
+    let mut frames = Vec::new();
+    while !written.is_empty() {
+        let (len, rest) = unsigned_varint::decode::usize(written).expect("valid length prefix");
+        frames.push(FrameProto::decode(&rest[..len]).expect("valid frame"));
+        written = &rest[len..];
+    }
+    frames
+}
+
+#[test]
+fn queued_frames_are_coalesced_into_a_single_write() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    let frames = vec![
+        new_message_frame("topic-a", b"message-1"),
+        new_message_frame("topic-a", b"message-2"),
+        new_subscription_frame("topic-b"),
+        new_subscription_frame("topic-c"),
+    ];
+
+    //// When
+    send_frames_over(&mut handler, frames, substream.clone());
+    let events = testlib::handler::drive(&mut handler, 8);
+
+    //// Then
+    assert_eq!(events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(
+        &events[0],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::FrameSent)
+    );
+    assert_eq!(
+        substream.flush_count(),
+        1,
+        "Substream should be flushed once"
+    );
+
+    let written = decode_written_frames(&substream.written());
+    assert_eq!(written.len(), 1, "Only 1 frame should be written");
+    assert_eq!(
+        written[0]
+            .publish
+            .iter()
+            .map(|message| message.data.as_deref())
+            .collect::<Vec<_>>(),
+        vec![Some(&b"message-1"[..]), Some(&b"message-2"[..])],
+        "Messages order should be preserved"
+    );
+    assert_eq!(
+        written[0]
+            .subscriptions
+            .iter()
+            .map(|sub| sub.topic_id.as_deref())
+            .collect::<Vec<_>>(),
+        vec![Some("topic-b"), Some("topic-c")],
+        "Subscription actions order should be preserved"
+    );
+}
+
+#[test]
+fn queued_frames_are_not_coalesced_if_their_contents_would_be_reordered() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    // The receiver processes the messages before the subscription actions of a frame.
+    let frames = vec![
+        new_subscription_frame("topic-a"),
+        new_message_frame("topic-a", b"message-1"),
+    ];
+
+    //// When
+    send_frames_over(&mut handler, frames.clone(), substream.clone());
+    let events = testlib::handler::drive(&mut handler, 8);
+
+    //// Then
+    assert_eq!(events.len(), 2, "Only 2 events should be emitted");
+    assert_eq!(
+        substream.flush_count(),
+        2,
+        "Substream should be flushed twice"
+    );
+    assert_eq!(
+        substream.written(),
+        [encode_frame(&frames[0]), encode_frame(&frames[1])].concat()
+    );
+}
+
+#[test]
+fn queued_frames_are_not_coalesced_above_max_frame_size() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    // The test handler maximum frame size is 1024 bytes.
+    let frames = vec![
+        new_message_frame("topic-a", &[1; 600]),
+        new_message_frame("topic-a", &[2; 600]),
+    ];
+
+    //// When
+    send_frames_over(&mut handler, frames.clone(), substream.clone());
+    let events = testlib::handler::drive(&mut handler, 8);
+
+    //// Then
+    assert_eq!(events.len(), 2, "Only 2 events should be emitted");
+    assert_eq!(
+        substream.written(),
+        [encode_frame(&frames[0]), encode_frame(&frames[1])].concat()
+    );
+}
+
+#[test]
+fn queued_frames_are_not_coalesced_above_the_remote_frame_limit() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    let frames = vec![
+        new_message_frame("topic-a", &[1; 400]),
+        new_message_frame("topic-a", &[2; 400]),
+        new_message_frame("topic-a", &[3; 600]),
+    ];
+
+    //// When
+    send_frames_over(&mut handler, frames.clone(), substream.clone());
+    handler.on_behaviour_event(Command::SetFrameLimit(Some(512)));
+    let events = testlib::handler::drive(&mut handler, 8);
+
+    //// Then
+    // The queued frame above the remote frame limit is dropped.
+    assert_eq!(events.len(), 2, "Only 2 events should be emitted");
+    assert_eq!(
+        substream.written(),
+        [encode_frame(&frames[0]), encode_frame(&frames[1])].concat()
+    );
+}
+
+/// Collect the payloads of the messages carried by the written frames, in order.
+fn written_messages(substream: &MockSubstream) -> Vec<Bytes> {
+    decode_written_frames(&substream.written())
+        .into_iter()
+        .flat_map(|frame| frame.publish)
+        .filter_map(|message| message.data)
+        .collect()
+}
+
+#[test]
+fn expired_frames_are_dropped_at_dequeue_time() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    // The frame deadline passes while it waits for the outbound substream.
+    let expired_deadline = Instant::now();
+    let future_deadline = Instant::now() + Duration::from_secs(60);
+
+    //// When
+    handler.on_behaviour_event(Command::SendFrame {
+        frame: new_message_frame("topic-a", b"expired"),
+        expires_at: Some(expired_deadline),
+        priority: Priority::Normal,
+    });
+    handler.on_behaviour_event(Command::SendFrame {
+        frame: new_message_frame("topic-a", b"not-expired"),
+        expires_at: Some(future_deadline),
+        priority: Priority::Normal,
+    });
+    handler.on_behaviour_event(Command::SendFrame {
+        frame: new_message_frame("topic-a", b"no-deadline"),
+        expires_at: None,
+        priority: Priority::Normal,
+    });
+    testlib::handler::drive(&mut handler, 1);
+    handler.on_connection_event(testlib::handler::fully_negotiated_outbound::<TestHandler>(
+        new_upgrade_output(substream.clone()),
+        (),
+    ));
+    let events = testlib::handler::drive(&mut handler, 8);
+
+    //// Then
+    assert_eq!(events.len(), 2, "Only 2 events should be emitted");
+    assert_matches!(
+        &events[0],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::FrameSent)
+    );
+    assert_matches!(
+        &events[1],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::FramesDropped {
+            expired: 1,
+            cancelled: 0
+        }),
+        "The behaviour should be notified about the expired frame"
+    );
+
+    assert_eq!(
+        written_messages(&substream),
+        [
+            Bytes::from_static(b"not-expired"),
+            Bytes::from_static(b"no-deadline")
+        ],
+        "Only the expired frame should be dropped"
+    );
+}
+
+#[test]
+fn cancel_tagged_removes_exactly_the_tagged_frame() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    //// When
+    handler.on_behaviour_event(Command::SendTaggedFrame {
+        frame: new_message_frame("topic-a", b"tagged-1"),
+        tag: 1,
+    });
+    handler.on_behaviour_event(Command::SendTaggedFrame {
+        frame: new_message_frame("topic-a", b"tagged-2"),
+        tag: 2,
+    });
+    handler.on_behaviour_event(Command::SendFrame {
+        frame: new_message_frame("topic-a", b"untagged"),
+        expires_at: None,
+        priority: Priority::Normal,
+    });
+    handler.on_behaviour_event(Command::CancelTagged(1));
+    testlib::handler::drive(&mut handler, 1);
+    handler.on_connection_event(testlib::handler::fully_negotiated_outbound::<TestHandler>(
+        new_upgrade_output(substream.clone()),
+        (),
+    ));
+    let events = testlib::handler::drive(&mut handler, 8);
+
+    //// Then
+    assert_eq!(events.len(), 3, "Only 3 events should be emitted");
+    assert_matches!(
+        &events[0],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::FrameSent)
+    );
+    assert_matches!(
+        &events[1],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::TaggedFrameSent { tag: 2 }),
+        "Only the frame not cancelled should be reported"
+    );
+    assert_matches!(
+        &events[2],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::FramesDropped {
+            expired: 0,
+            cancelled: 1
+        }),
+        "The behaviour should be notified about the cancelled frame"
+    );
+
+    assert_eq!(
+        written_messages(&substream),
+        [
+            Bytes::from_static(b"tagged-2"),
+            Bytes::from_static(b"untagged")
+        ],
+        "Only the cancelled frame should be removed"
+    );
+}
+
+#[test]
+fn untagged_frames_without_deadline_are_not_dropped() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new();
+
+    let frames = vec![
+        new_message_frame("topic-a", b"message-1"),
+        new_message_frame("topic-a", b"message-2"),
+    ];
+
+    //// When
+    send_frames_over(&mut handler, frames, substream.clone());
+    // A cancellation of an unknown tag does not affect the untagged frames.
+    handler.on_behaviour_event(Command::CancelTagged(42));
+    let events = testlib::handler::drive(&mut handler, 8);
+
+    //// Then
+    assert_eq!(events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(
+        &events[0],
+        ConnectionHandlerEvent::NotifyBehaviour(Event::FrameSent)
+    );
+
+    assert_eq!(
+        written_messages(&substream),
+        [
+            Bytes::from_static(b"message-1"),
+            Bytes::from_static(b"message-2")
+        ]
+    );
+}
+
+#[test]
+fn disabled_handler_ignores_the_connection() {
+    //// Given
+    let mut handler = TestHandler::new_disabled(MockProtocolUpgrade, 1024);
+    let substream = MockSubstream::new();
+
+    //// When
+    handler.on_behaviour_event(Command::SendFrame {
+        frame: Bytes::from_static(b"test-frame"),
+        expires_at: None,
+        priority: Priority::Normal,
+    });
+    handler.on_connection_event(testlib::handler::fully_negotiated_inbound::<TestHandler>(
+        new_upgrade_output(substream.clone()),
+        (),
+    ));
+    substream.push_read(encode_frame(b"test-frame"));
+
+    let events = testlib::handler::drive(&mut handler, 4);
+
+    //// Then
+    assert!(
+        events.is_empty(),
+        "No substream should be requested, nor frame received"
+    );
+    assert_eq!(handler.connection_keep_alive(), KeepAlive::No);
+}
+
+#[test]
+fn high_priority_frames_are_sent_ahead_of_the_queued_normal_frames() {
+    //// Given
+    let mut handler = new_test_handler(2);
+    let substream = MockSubstream::new().with_write_behavior(MockBehavior::Pending);
+
+    // The first frame is in flight, and the substream is backed up.
+    send_frame_over(
+        &mut handler,
+        new_message_frame("topic-a", b"in-flight"),
+        substream.clone(),
+    );
+    testlib::handler::drive(&mut handler, 2);
+
+    //// When
+    for idx in 0..10 {
+        handler.on_behaviour_event(Command::SendFrame {
+            frame: new_message_frame("topic-a", format!("normal-{idx}").as_bytes()),
+            expires_at: None,
+            priority: Priority::Normal,
+        });
+    }
+    handler.on_behaviour_event(Command::SendFrame {
+        frame: new_message_frame("topic-a", b"high"),
+        expires_at: None,
+        priority: Priority::High,
+    });
+    testlib::handler::drive(&mut handler, 2);
+
+    substream.set_write_behavior(MockBehavior::Ready);
+    testlib::handler::drive(&mut handler, 8);
+
+    //// Then
+    let written = written_messages(&substream);
+    assert_eq!(written.len(), 12, "All the frames should be sent");
+    assert_eq!(
+        written[..2],
+        [
+            Bytes::from_static(b"in-flight"),
+            Bytes::from_static(b"high")
+        ],
+        "The high priority frame should be sent right after the frame in flight"
+    );
+    assert_eq!(written[2], Bytes::from_static(b"normal-0"));
+}
@@ -0,0 +1,273 @@
+//! Local message consumers.
+//!
+//! Several independent components of the application, e.g., plugins, may consume the messages
+//! received on overlapping topics. Each component registers a consumer on the topics it is
+//! interested in (see [`Behaviour::register_consumer`](crate::Behaviour::register_consumer)), and
+//! every received message is notified once per consumer registered on its topic, as an
+//! [`Event::MessageForConsumer`](crate::Event::MessageForConsumer) event tagged with the consumer
+//! tag.
+//!
+//! The node stays subscribed to a topic while a consumer is registered on it, or while it is
+//! explicitly subscribed to (see [`Behaviour::subscribe`](crate::Behaviour::subscribe)).
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::topic::TopicHash;
+
+/// The tag identifying a local consumer in the
+/// [`Event::MessageForConsumer`](crate::Event::MessageForConsumer) events.
+///
+/// Tags are chosen by the application, several consumers may share the same tag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConsumerTag(String);
+
+impl ConsumerTag {
+    /// Create a new consumer tag.
+    #[must_use]
+    pub fn new(tag: impl Into<String>) -> Self {
+        Self(tag.into())
+    }
+
+    /// The consumer tag as a string slice.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ConsumerTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for ConsumerTag {
+    fn from(tag: &str) -> Self {
+        Self::new(tag)
+    }
+}
+
+impl From<String> for ConsumerTag {
+    fn from(tag: String) -> Self {
+        Self::new(tag)
+    }
+}
+
+/// The unique identifier of a registered consumer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ConsumerId(u64);
+
+/// The consumers released by dropping their handle, pending their unregistration.
+type ReleasedConsumers = Rc<RefCell<Vec<(ConsumerId, TopicHash)>>>;
+
+/// The handle of a local consumer registered on a topic.
+///
+/// The consumer is unregistered when the handle is dropped, during the next poll of the
+/// behaviour, or right away with
+/// [`Behaviour::unregister_consumer`](crate::Behaviour::unregister_consumer).
+#[must_use = "the consumer is unregistered when the handle is dropped"]
+pub struct ConsumerHandle {
+    id: ConsumerId,
+    topic: TopicHash,
+    tag: ConsumerTag,
+    /// The registry released consumers queue. `None` once unregistered.
+    released: Option<ReleasedConsumers>,
+}
+
+impl ConsumerHandle {
+    /// The topic the consumer is registered on.
+    #[must_use]
+    pub fn topic(&self) -> &TopicHash {
+        &self.topic
+    }
+
+    /// The consumer tag.
+    #[must_use]
+    pub fn tag(&self) -> &ConsumerTag {
+        &self.tag
+    }
+}
+
+impl fmt::Debug for ConsumerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsumerHandle")
+            .field("topic", &self.topic)
+            .field("tag", &self.tag)
+            .finish()
+    }
+}
+
+impl Drop for ConsumerHandle {
+    fn drop(&mut self) {
+        if let Some(released) = self.released.take() {
+            released.borrow_mut().push((self.id, self.topic.clone()));
+        }
+    }
+}
+
+/// The local consumers registered on each topic, and the topics explicitly subscribed to.
+#[derive(Default)]
+pub(crate) struct ConsumerRegistry {
+    /// The next consumer identifier.
+    next_id: u64,
+
+    /// The consumers registered on each topic, in registration order.
+    consumers: HashMap<TopicHash, Vec<(ConsumerId, ConsumerTag)>>,
+
+    /// The topics explicitly subscribed to, independently of the registered consumers.
+    explicit_subscriptions: HashSet<TopicHash>,
+
+    /// The consumers whose handle was dropped.
+    released: ReleasedConsumers,
+}
+
+impl ConsumerRegistry {
+    /// Register a new consumer on the given topic.
+    ///
+    /// Returns the consumer handle, and whether the topic had no consumer registered nor an
+    /// explicit subscription, i.e., the node must subscribe to the topic.
+    pub(crate) fn register(
+        &mut self,
+        topic: TopicHash,
+        tag: ConsumerTag,
+    ) -> (ConsumerHandle, bool) {
+        let id = ConsumerId(self.next_id);
+        self.next_id += 1;
+
+        let subscribe = !self.is_retained(&topic);
+        self.consumers
+            .entry(topic.clone())
+            .or_default()
+            .push((id, tag.clone()));
+
+        let handle = ConsumerHandle {
+            id,
+            topic,
+            tag,
+            released: Some(self.released.clone()),
+        };
+        (handle, subscribe)
+    }
+
+    /// Unregister the consumer of the given handle, without waiting for the handle to be
+    /// dropped.
+    ///
+    /// Returns whether the topic is no longer retained, i.e., the node must unsubscribe from it.
+    pub(crate) fn unregister(&mut self, mut handle: ConsumerHandle) -> bool {
+        handle.released = None;
+        self.remove(handle.id, &handle.topic)
+    }
+
+    /// Unregister the consumers whose handle was dropped.
+    ///
+    /// Returns the topics no longer retained, i.e., the node must unsubscribe from them.
+    pub(crate) fn unregister_released(&mut self) -> Vec<TopicHash> {
+        let released = std::mem::take(&mut *self.released.borrow_mut());
+        released
+            .into_iter()
+            .filter_map(|(id, topic)| self.remove(id, &topic).then_some(topic))
+            .collect()
+    }
+
+    /// The tags of the consumers registered on the given topic, in registration order.
+    pub(crate) fn consumers(&self, topic: &TopicHash) -> impl Iterator<Item = &ConsumerTag> {
+        self.consumers
+            .get(topic)
+            .into_iter()
+            .flatten()
+            .map(|(_, tag)| tag)
+    }
+
+    /// Whether any consumer is registered on the given topic.
+    pub(crate) fn has_consumers(&self, topic: &TopicHash) -> bool {
+        self.consumers.contains_key(topic)
+    }
+
+    /// Record an explicit subscription to the given topic.
+    pub(crate) fn add_explicit_subscription(&mut self, topic: TopicHash) {
+        self.explicit_subscriptions.insert(topic);
+    }
+
+    /// Remove the explicit subscription to the given topic.
+    pub(crate) fn remove_explicit_subscription(&mut self, topic: &TopicHash) {
+        self.explicit_subscriptions.remove(topic);
+    }
+
+    /// Whether the given topic is explicitly subscribed to.
+    pub(crate) fn is_explicitly_subscribed(&self, topic: &TopicHash) -> bool {
+        self.explicit_subscriptions.contains(topic)
+    }
+
+    /// Whether the node must stay subscribed to the given topic, i.e., the topic is explicitly
+    /// subscribed to, or a consumer is registered on it.
+    fn is_retained(&self, topic: &TopicHash) -> bool {
+        self.is_explicitly_subscribed(topic) || self.has_consumers(topic)
+    }
+
+    /// Remove a consumer. Returns whether the topic is no longer retained.
+    fn remove(&mut self, id: ConsumerId, topic: &TopicHash) -> bool {
+        let Some(consumers) = self.consumers.get_mut(topic) else {
+            return false;
+        };
+        consumers.retain(|(consumer, _)| *consumer != id);
+        if consumers.is_empty() {
+            self.consumers.remove(topic);
+        }
+
+        !self.is_retained(topic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topic_is_released_with_the_last_consumer() {
+        //// Given
+        let topic = TopicHash::from_raw("/test/topic");
+        let mut registry = ConsumerRegistry::default();
+
+        let (handle_a, subscribe_a) = registry.register(topic.clone(), "a".into());
+        let (handle_b, subscribe_b) = registry.register(topic.clone(), "b".into());
+
+        //// When
+        drop(handle_a);
+        let released_a = registry.unregister_released();
+        let tags = registry.consumers(&topic).cloned().collect::<Vec<_>>();
+
+        let released_b = registry.unregister(handle_b);
+
+        //// Then
+        assert!(subscribe_a, "The first consumer subscribes to the topic");
+        assert!(!subscribe_b);
+        assert!(released_a.is_empty(), "Consumer B is still registered");
+        assert_eq!(tags, [ConsumerTag::new("b")]);
+        assert!(released_b, "The last consumer releases the topic");
+        assert!(!registry.has_consumers(&topic));
+        assert!(
+            registry.unregister_released().is_empty(),
+            "An unregistered handle is not released again"
+        );
+    }
+
+    #[test]
+    fn explicit_subscription_retains_the_topic() {
+        //// Given
+        let topic = TopicHash::from_raw("/test/topic");
+        let mut registry = ConsumerRegistry::default();
+        registry.add_explicit_subscription(topic.clone());
+
+        //// When
+        let (handle, subscribe) = registry.register(topic.clone(), "a".into());
+        drop(handle);
+        let released = registry.unregister_released();
+
+        //// Then
+        assert!(!subscribe, "The topic is already subscribed to");
+        assert!(released.is_empty(), "The topic is explicitly subscribed to");
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+/// The processing stage at which an input was intentionally dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DeadLetterStage {
+    /// A received frame, or one of its messages or subscription actions, failed the upstream
+    /// framing validation.
+    FramingValidation,
+    /// A local or remote subscription request did not change the subscriptions state, e.g., a
+    /// subscription request for an already subscribed topic.
+    SubscriptionFilter,
+    /// A received or published message was already seen.
+    CacheDuplicate,
+    /// A frame exceeded the maximum frame size.
+    SizeLimit,
+    /// A received message topic is not subscribed by the local node.
+    NotSubscribed,
+    /// A received message topic is paused by the local node.
+    TopicPaused,
+    /// A received message was suspected to be spoofed (see
+    /// [`Config::drop_suspected_spoofed`](crate::Config::drop_suspected_spoofed)).
+    SuspectedSpoofed,
+    /// A remote subscription request or received message was not authorized (see
+    /// [`Config::topic_authorizer`](crate::Config::topic_authorizer)).
+    Unauthorized,
+}
+
+impl fmt::Display for DeadLetterStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self {
+            DeadLetterStage::FramingValidation => "framing-validation",
+            DeadLetterStage::SubscriptionFilter => "subscription-filter",
+            DeadLetterStage::CacheDuplicate => "cache-duplicate",
+            DeadLetterStage::SizeLimit => "size-limit",
+            DeadLetterStage::NotSubscribed => "not-subscribed",
+            DeadLetterStage::TopicPaused => "topic-paused",
+            DeadLetterStage::SuspectedSpoofed => "suspected-spoofed",
+            DeadLetterStage::Unauthorized => "unauthorized",
+        };
+        f.write_str(stage)
+    }
+}
+
+/// A record of an input intentionally dropped by the behaviour.
+///
+/// Dead letters are a debugging facility, recorded only if enabled (see
+/// [`Config::dead_letter`](crate::Config::dead_letter)). The `summary` is a human-readable
+/// description of the dropped input, and its format is not stable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeadLetter {
+    /// The processing stage that dropped the input.
+    pub stage: DeadLetterStage,
+    /// The reason the input was dropped.
+    pub reason: &'static str,
+    /// A description of the dropped input.
+    pub summary: String,
+}
+
+impl DeadLetter {
+    /// Create a new dead letter record.
+    #[must_use]
+    pub fn new(stage: DeadLetterStage, reason: &'static str, summary: String) -> Self {
+        Self {
+            stage,
+            reason,
+            summary,
+        }
+    }
+}
+
+/// A bounded ring buffer of dead letters. Once full, the oldest dead letter is evicted for every
+/// newly recorded one.
+#[derive(Debug, Default)]
+pub(crate) struct DeadLetterBuffer {
+    /// The maximum number of dead letters kept.
+    capacity: usize,
+    /// The recorded dead letters, oldest first.
+    letters: VecDeque<DeadLetter>,
+}
+
+impl DeadLetterBuffer {
+    /// Create a new dead letter buffer keeping up to `capacity` dead letters.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            letters: VecDeque::new(),
+        }
+    }
+
+    /// Record a dead letter, evicting the oldest one if the buffer is full.
+    pub(crate) fn push(&mut self, letter: DeadLetter) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.letters.len() >= self.capacity {
+            self.letters.pop_front();
+        }
+        self.letters.push_back(letter);
+    }
+
+    /// Iterate over the recorded dead letters, oldest first.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &DeadLetter> {
+        self.letters.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_letter(n: usize) -> DeadLetter {
+        DeadLetter::new(
+            DeadLetterStage::SubscriptionFilter,
+            "test",
+            format!("letter-{n}"),
+        )
+    }
+
+    #[test]
+    fn buffer_evicts_the_oldest_letters_once_full() {
+        //// Given
+        let mut buffer = DeadLetterBuffer::new(2);
+
+        //// When
+        for n in 0..5 {
+            buffer.push(new_test_letter(n));
+        }
+
+        //// Then
+        let summaries = buffer
+            .iter()
+            .map(|letter| letter.summary.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(summaries, ["letter-3", "letter-4"]);
+    }
+
+    #[test]
+    fn zero_capacity_buffer_keeps_no_letters() {
+        //// Given
+        let mut buffer = DeadLetterBuffer::new(0);
+
+        //// When
+        buffer.push(new_test_letter(0));
+
+        //// Then
+        assert_eq!(buffer.iter().count(), 0);
+    }
+}
@@ -0,0 +1,178 @@
+//! Local publish delivery modes (see
+//! [`SubscriptionBuilder::delivery_mode`](crate::SubscriptionBuilder::delivery_mode)).
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Bound;
+
+use libp2p::identity::PeerId;
+
+use crate::topic::TopicHash;
+
+/// How the messages published by the local node on a topic are delivered to the topic's
+/// subscribed peers.
+///
+/// The delivery mode only applies to the messages published by the local node. The received
+/// messages are forwarded as usual.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DeliveryMode {
+    /// Every published message is sent to the subscribed peers selected by the protocol router.
+    #[default]
+    Broadcast,
+    /// Every published message is sent to exactly one subscribed peer, rotating through the
+    /// topic's connected subscribed peers.
+    ///
+    /// This approximates work-queue semantics: each message is processed by a single subscriber.
+    /// The subscribers must not forward the topic messages (see
+    /// [`SubscriptionBuilder::forwarding`](crate::SubscriptionBuilder::forwarding)), otherwise
+    /// the message reaches the other subscribers anyway.
+    RoundRobinPublish,
+}
+
+/// The publish rotations of the round-robin topics (see [`DeliveryMode::RoundRobinPublish`]).
+///
+/// The rotation follows the peer id order: every message is sent to the first candidate peer
+/// after the last selected peer, wrapping around. The rotation is kept by peer id, not by index,
+/// so the peers subscribing, unsubscribing or disconnecting mid-rotation neither reset the
+/// rotation nor make it skip the remaining peers.
+#[derive(Debug, Default)]
+pub(crate) struct RoundRobinRotations {
+    /// The last peer selected, by round-robin topic.
+    last_selected: HashMap<TopicHash, Option<PeerId>>,
+}
+
+impl RoundRobinRotations {
+    /// Start the rotation of a round-robin topic.
+    pub(crate) fn track_topic(&mut self, topic: TopicHash) {
+        self.last_selected.entry(topic).or_default();
+    }
+
+    /// Drop the rotation of a round-robin topic.
+    pub(crate) fn untrack_topic(&mut self, topic: &TopicHash) {
+        self.last_selected.remove(topic);
+    }
+
+    /// Returns `true` if the topic is published in round-robin.
+    pub(crate) fn is_tracked(&self, topic: &TopicHash) -> bool {
+        self.last_selected.contains_key(topic)
+    }
+
+    /// Select the next peer of the topic rotation among the candidate peers, i.e., the topic's
+    /// connected subscribed peers.
+    ///
+    /// Returns `None` if the topic is not published in round-robin, or if there is no candidate.
+    pub(crate) fn next_peer(
+        &mut self,
+        topic: &TopicHash,
+        candidates: impl IntoIterator<Item = PeerId>,
+    ) -> Option<PeerId> {
+        let last_selected = self.last_selected.get_mut(topic)?;
+        let candidates = candidates.into_iter().collect::<BTreeSet<_>>();
+
+        let next = match last_selected {
+            Some(last) => candidates
+                .range((Bound::Excluded(*last), Bound::Unbounded))
+                .next()
+                .or_else(|| candidates.first()),
+            None => candidates.first(),
+        };
+
+        let next = next.copied()?;
+        *last_selected = Some(next);
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_topic() -> TopicHash {
+        TopicHash::from_raw("test-topic")
+    }
+
+    /// Create `count` peer ids, sorted.
+    fn new_test_peers(count: usize) -> Vec<PeerId> {
+        let peers = (0..count)
+            .map(|_| PeerId::random())
+            .collect::<BTreeSet<_>>();
+        peers.into_iter().collect()
+    }
+
+    #[test]
+    fn peers_are_selected_in_rotation() {
+        //// Given
+        let topic = new_test_topic();
+        let peers = new_test_peers(3);
+
+        let mut rotations = RoundRobinRotations::default();
+        rotations.track_topic(topic.clone());
+
+        //// When
+        let selected = (0..6)
+            .map(|_| rotations.next_peer(&topic, peers.iter().copied()))
+            .collect::<Vec<_>>();
+
+        //// Then
+        let expected = [0, 1, 2, 0, 1, 2].map(|idx| Some(peers[idx]));
+        assert_eq!(selected, expected);
+    }
+
+    #[test]
+    fn disconnected_peer_is_skipped_without_resetting_the_rotation() {
+        //// Given
+        let topic = new_test_topic();
+        let peers = new_test_peers(4);
+
+        let mut rotations = RoundRobinRotations::default();
+        rotations.track_topic(topic.clone());
+
+        let first = rotations.next_peer(&topic, peers.iter().copied());
+
+        //// When
+        // The next peer in the rotation, peer 1, disconnects.
+        let remaining = [peers[0], peers[2], peers[3]];
+        let selected = (0..3)
+            .map(|_| rotations.next_peer(&topic, remaining))
+            .collect::<Vec<_>>();
+
+        //// Then
+        assert_eq!(first, Some(peers[0]));
+        assert_eq!(selected, [Some(peers[2]), Some(peers[3]), Some(peers[0])]);
+    }
+
+    #[test]
+    fn single_peer_is_always_selected() {
+        //// Given
+        let topic = new_test_topic();
+        let peer = PeerId::random();
+
+        let mut rotations = RoundRobinRotations::default();
+        rotations.track_topic(topic.clone());
+
+        //// When
+        let selected = (0..3)
+            .map(|_| rotations.next_peer(&topic, [peer]))
+            .collect::<Vec<_>>();
+
+        //// Then
+        assert_eq!(selected, [Some(peer); 3]);
+    }
+
+    #[test]
+    fn no_peer_is_selected_without_candidates_or_for_untracked_topics() {
+        //// Given
+        let topic = new_test_topic();
+        let untracked_topic = TopicHash::from_raw("untracked-topic");
+
+        let mut rotations = RoundRobinRotations::default();
+        rotations.track_topic(topic.clone());
+
+        //// When
+        let no_candidates = rotations.next_peer(&topic, []);
+        let untracked = rotations.next_peer(&untracked_topic, [PeerId::random()]);
+
+        //// Then
+        assert_eq!(no_candidates, None);
+        assert_eq!(untracked, None);
+    }
+}
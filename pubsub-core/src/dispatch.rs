@@ -0,0 +1,53 @@
+//! The behaviour services events dispatching.
+//!
+//! The behaviour wires its services together: the output events of a service are fanned out to
+//! the services consuming them. Each service declares the output events it consumes by
+//! implementing [`Consumes`] for its input event type, mapping each source event variant to the
+//! input event to send, if any. The behaviour then dispatches every source event to the list of
+//! its consumers with the [`dispatch!`] macro.
+//!
+//! The [`Consumes`] implementations match the source events exhaustively, without a wildcard arm,
+//! so adding an event variant does not compile until every consumer decides whether to handle
+//! it. And listing a service as a consumer of an event it does not declare does not compile
+//! either.
+//!
+//! Only the events derived from the source event alone are dispatched: the connections and
+//! subscriptions events, the router control messages to send, and the received control messages.
+//! The forwards depending on the behaviour state, e.g., the received messages handed to the
+//! router once deduplicated, or the subscription updates sent to the active peers, are written
+//! in the behaviour drain loops, and the consumers do not consume the corresponding source
+//! events.
+
+use libp2p_pubsub_common::service::ServiceContext;
+
+/// A service input event derived from the output events, of type `E`, of another service.
+pub(crate) trait Consumes<E>: Sized {
+    /// Map the source event to the input event to send to the consumer service, or `None` if the
+    /// consumer service is not interested in this event.
+    fn consume(ev: &E) -> Option<Self>;
+}
+
+/// Send the input event derived from the source event, if any, to the consumer service.
+pub(crate) fn dispatch_to<E, C>(consumer: &mut C, ev: &E)
+where
+    C: ServiceContext,
+    C::InEvent: Consumes<E>,
+{
+    if let Some(ev) = C::InEvent::consume(ev) {
+        consumer.do_send(ev);
+    }
+}
+
+/// Dispatch a source event to each of the given consumer services, in order.
+///
+/// ```ignore
+/// dispatch!(conn_event => self.subscriptions_service, self.dialer_service);
+/// ```
+macro_rules! dispatch {
+    ($ev:expr => $($consumer:expr),+ $(,)?) => {{
+        let ev = &$ev;
+        $($crate::dispatch::dispatch_to(&mut $consumer, ev);)+
+    }};
+}
+
+pub(crate) use dispatch;
@@ -0,0 +1,32 @@
+//! Dropped frames, messages and requests accounting (see
+//! [`Behaviour::drop_stats`](crate::Behaviour::drop_stats)).
+
+/// The number of frames, messages and requests dropped by the behaviour, by cause.
+///
+/// Each counter is accounted by the component dropping the items, and is never reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DropStats {
+    /// The frames dropped by the connection handlers because their deadline passed while queued
+    /// for sending.
+    pub frames_expired: u64,
+    /// The queued frames revoked before being sent by the connection handlers.
+    pub frames_cancelled: u64,
+    /// The remote subscription announcements ignored because the peer was not authorized to
+    /// subscribe to the topic (see [`Config::topic_authorizer`](crate::Config::topic_authorizer)).
+    pub unauthorized_subscriptions: u64,
+    /// The received messages dropped because their propagation source, or claimed author, was
+    /// not authorized to publish on the topic (see
+    /// [`Config::topic_authorizer`](crate::Config::topic_authorizer)).
+    pub unauthorized_messages: u64,
+    /// The message ids requested by the peers' IWANT control messages and not served, as the peer
+    /// exceeded its serving quotas (see
+    /// [`Config::max_iwant_served_per_peer_per_heartbeat`](crate::Config::max_iwant_served_per_peer_per_heartbeat)
+    /// and [`Config::max_iwant_serves_per_message`](crate::Config::max_iwant_serves_per_message)).
+    ///
+    /// The ids of the messages not in the message cache are ignored, and not counted.
+    pub iwant_unserved: u64,
+    /// The message forwards dropped, by destination peer, as the local node was in listen-only
+    /// mode (see [`Behaviour::set_listen_only`](crate::Behaviour::set_listen_only)).
+    pub listen_only_forwards: u64,
+}
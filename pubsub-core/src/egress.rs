@@ -0,0 +1,408 @@
+//! Egress bandwidth pacing (see [`Config::egress_budget`](crate::Config::egress_budget)).
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use libp2p::identity::PeerId;
+
+use crate::message::Priority;
+
+/// The number of egress pacer refills per heartbeat interval.
+const REFILLS_PER_HEARTBEAT: u32 = 10;
+
+/// The egress pacer refill interval: a fraction of the heartbeat interval, so the held back
+/// frames are released smoothly.
+pub(crate) fn refill_interval(heartbeat_interval: Duration) -> Duration {
+    (heartbeat_interval / REFILLS_PER_HEARTBEAT).max(Duration::from_millis(1))
+}
+
+/// An egress bandwidth budget, in bytes per second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BytesPerSecond(pub u64);
+
+impl fmt::Display for BytesPerSecond {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} B/s", self.0)
+    }
+}
+
+/// The egress pacer state and counters (see
+/// [`Behaviour::egress_stats`](crate::Behaviour::egress_stats)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EgressStats {
+    /// The egress budget.
+    pub budget: BytesPerSecond,
+    /// The bytes currently available in the token bucket. Zero while the bucket is in debt, i.e.,
+    /// after a frame larger than the available bytes was sent.
+    pub level: u64,
+    /// The frames currently held back, waiting for the bucket to refill.
+    pub queued_frames: usize,
+    /// The bytes of the frames currently held back.
+    pub queued_bytes: u64,
+    /// The number of frames held back since the pacer was enabled.
+    pub delayed_frames: u64,
+    /// The bytes of the frames held back since the pacer was enabled.
+    pub delayed_bytes: u64,
+}
+
+/// A frame held back by the egress pacer.
+#[derive(Debug)]
+struct PacedFrame<T> {
+    /// The destination peer.
+    dest: PeerId,
+    /// The frame size, in bytes.
+    len: u64,
+    /// The frame to release.
+    frame: T,
+}
+
+/// Paces the frames handed to the connection handlers to an egress bandwidth budget.
+///
+/// The pacer is a token bucket, refilled at the budget rate and holding up to one second of
+/// budget. A frame is sent if the bucket holds its size, and held back otherwise, until the bucket
+/// refills. A frame larger than the bucket capacity is sent once the bucket is full, leaving the
+/// bucket in debt.
+///
+/// The held back frames are released in the order they were submitted, so the frames toward the
+/// same peer are never reordered. The only exception are the high priority frames (see
+/// [`Priority`]): they are released ahead of all the normal priority frames, in order. The exempt
+/// frames, e.g., the subscription and control frames, are never held back, but their size is
+/// still drawn from the bucket.
+#[derive(Debug)]
+pub(crate) struct EgressPacer<T> {
+    /// The refill rate, in bytes per second.
+    budget: BytesPerSecond,
+    /// The bytes available in the bucket. Negative while in debt.
+    tokens: f64,
+    /// The last bucket refill time.
+    last_refill: Instant,
+    /// The high priority frames held back, in order.
+    pending_high: VecDeque<PacedFrame<T>>,
+    /// The normal priority frames held back, in order.
+    pending: VecDeque<PacedFrame<T>>,
+    /// The bytes of the held back frames.
+    queued_bytes: u64,
+    /// The number of frames held back since the pacer creation.
+    delayed_frames: u64,
+    /// The bytes of the frames held back since the pacer creation.
+    delayed_bytes: u64,
+}
+
+impl<T> EgressPacer<T> {
+    /// Create a new egress pacer, with a full bucket.
+    pub(crate) fn new(budget: BytesPerSecond, now: Instant) -> Self {
+        Self {
+            budget,
+            tokens: budget.0 as f64,
+            last_refill: now,
+            pending_high: Default::default(),
+            pending: Default::default(),
+            queued_bytes: 0,
+            delayed_frames: 0,
+            delayed_bytes: 0,
+        }
+    }
+
+    /// The bucket capacity, in bytes: one second of budget.
+    fn capacity(&self) -> f64 {
+        self.budget.0 as f64
+    }
+
+    /// The number of frames held back.
+    pub(crate) fn len(&self) -> usize {
+        self.pending_high.len() + self.pending.len()
+    }
+
+    /// Update the egress budget. The bucket level is capped to the new capacity.
+    pub(crate) fn set_budget(&mut self, budget: BytesPerSecond, now: Instant) {
+        self.refill(now);
+        self.budget = budget;
+        self.tokens = self.tokens.min(self.capacity());
+    }
+
+    /// Get the pacer state and counters.
+    pub(crate) fn stats(&self) -> EgressStats {
+        EgressStats {
+            budget: self.budget,
+            level: self.tokens.max(0.0) as u64,
+            queued_frames: self.len(),
+            queued_bytes: self.queued_bytes,
+            delayed_frames: self.delayed_frames,
+            delayed_bytes: self.delayed_bytes,
+        }
+    }
+
+    /// Refill the bucket with the budget accrued since the last refill.
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.last_refill = self.last_refill.max(now);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.budget.0 as f64).min(self.capacity());
+    }
+
+    /// Draw the frame size from the bucket, if the bucket holds it, or is full.
+    fn try_consume(&mut self, len: u64) -> bool {
+        let len = len as f64;
+        if self.tokens < len.min(self.capacity()) {
+            return false;
+        }
+
+        self.tokens -= len;
+        true
+    }
+
+    /// Submit a frame of `len` bytes toward the `dest` peer.
+    ///
+    /// Returns the frame if it can be sent right away. Otherwise, the frame is held back until
+    /// released (see [`EgressPacer::release`]). The exempt frames are always returned.
+    pub(crate) fn submit(
+        &mut self,
+        dest: PeerId,
+        len: u64,
+        priority: Priority,
+        exempt: bool,
+        frame: T,
+        now: Instant,
+    ) -> Option<T> {
+        self.refill(now);
+
+        if exempt {
+            self.tokens -= len as f64;
+            return Some(frame);
+        }
+
+        // Keep the submission order: a frame is only sent right away if no frame it must follow
+        // is held back.
+        let queue_empty = match priority {
+            Priority::High => self.pending_high.is_empty(),
+            Priority::Normal => self.len() == 0,
+        };
+        if queue_empty && self.try_consume(len) {
+            return Some(frame);
+        }
+
+        self.queued_bytes += len;
+        self.delayed_frames += 1;
+        self.delayed_bytes += len;
+        let paced = PacedFrame { dest, len, frame };
+        match priority {
+            Priority::High => self.pending_high.push_back(paced),
+            Priority::Normal => self.pending.push_back(paced),
+        }
+        None
+    }
+
+    /// Release the held back frames, the high priority ones first, in order, as long as the
+    /// bucket holds their size.
+    pub(crate) fn release(&mut self, now: Instant) -> Vec<(PeerId, T)> {
+        let mut released = Vec::new();
+        if self.len() == 0 {
+            return released;
+        }
+
+        self.refill(now);
+        for high in [true, false] {
+            loop {
+                let queue = if high {
+                    &self.pending_high
+                } else {
+                    &self.pending
+                };
+                let Some(len) = queue.front().map(|paced| paced.len) else {
+                    break;
+                };
+                if !self.try_consume(len) {
+                    return released;
+                }
+
+                let queue = if high {
+                    &mut self.pending_high
+                } else {
+                    &mut self.pending
+                };
+                let paced = queue.pop_front().expect("front frame to be present");
+                self.queued_bytes -= paced.len;
+                released.push((paced.dest, paced.frame));
+            }
+        }
+
+        released
+    }
+
+    /// Release all the held back frames, the high priority ones first, regardless of the budget.
+    pub(crate) fn drain(&mut self) -> Vec<(PeerId, T)> {
+        self.queued_bytes = 0;
+        self.pending_high
+            .drain(..)
+            .chain(self.pending.drain(..))
+            .map(|paced| (paced.dest, paced.frame))
+            .collect()
+    }
+
+    /// Drop the frames toward the disconnected peer, and return their number.
+    pub(crate) fn peer_disconnected(&mut self, peer: &PeerId) -> usize {
+        let queued = self.len();
+        let mut dropped_bytes = 0;
+        for queue in [&mut self.pending_high, &mut self.pending] {
+            queue.retain(|paced| {
+                let keep = &paced.dest != peer;
+                if !keep {
+                    dropped_bytes += paced.len;
+                }
+                keep
+            });
+        }
+
+        self.queued_bytes -= dropped_bytes;
+        queued - self.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Submit the `len` bytes frame at the given time, and return `true` if sent right away.
+    fn submit(
+        pacer: &mut EgressPacer<u32>,
+        dest: PeerId,
+        len: u64,
+        priority: Priority,
+        frame: u32,
+        now: Instant,
+    ) -> bool {
+        pacer
+            .submit(dest, len, priority, false, frame, now)
+            .is_some()
+    }
+
+    #[test]
+    fn sustained_load_is_shaped_to_the_budget() {
+        //// Given
+        let start = Instant::now();
+        let peer = PeerId::random();
+        let mut pacer = EgressPacer::new(BytesPerSecond(10_000), start);
+
+        //// When
+        // Submit 1000 bytes every 10ms, i.e., 100_000 bytes per second, for 10 seconds, releasing
+        // the held back frames every 10ms.
+        let mut sent_bytes = 0;
+        for tick in 0..1_000u32 {
+            let now = start + Duration::from_millis(10) * tick;
+            sent_bytes += pacer.release(now).len() as u64 * 1_000;
+            if submit(&mut pacer, peer, 1_000, Priority::Normal, tick, now) {
+                sent_bytes += 1_000;
+            }
+        }
+
+        //// Then
+        // The initial full bucket, plus 10 seconds of budget.
+        let expected = 10_000 + 10 * 10_000;
+        assert!(
+            sent_bytes.abs_diff(expected) <= 2_000,
+            "The sent bytes should match the budget, got {sent_bytes}"
+        );
+        let stats = pacer.stats();
+        assert_eq!(stats.queued_frames as u64 * 1_000, stats.queued_bytes);
+        assert_eq!(stats.delayed_bytes, stats.delayed_frames * 1_000);
+        assert!(stats.delayed_frames > 800);
+    }
+
+    #[test]
+    fn held_back_frames_are_released_in_order_as_the_bucket_refills() {
+        //// Given
+        let start = Instant::now();
+        let peer_a = PeerId::random();
+        let peer_b = PeerId::random();
+        let mut pacer = EgressPacer::new(BytesPerSecond(1_000), start);
+
+        //// When
+        let sent = [
+            submit(&mut pacer, peer_a, 600, Priority::Normal, 1, start),
+            submit(&mut pacer, peer_b, 600, Priority::Normal, 2, start),
+            // Held back, as a previous frame is held back, even if the bucket holds it.
+            submit(&mut pacer, peer_a, 100, Priority::Normal, 3, start),
+        ];
+        let released_early = pacer.release(start + Duration::from_millis(100));
+        let released = pacer.release(start + Duration::from_millis(500));
+
+        //// Then
+        assert_eq!(sent, [true, false, false]);
+        assert!(released_early.is_empty());
+        assert_eq!(released, [(peer_b, 2), (peer_a, 3)]);
+        assert_eq!(pacer.len(), 0);
+        assert_eq!(pacer.stats().delayed_bytes, 700);
+    }
+
+    #[test]
+    fn priority_and_exempt_frames_bypass_the_held_back_frames() {
+        //// Given
+        let start = Instant::now();
+        let peer = PeerId::random();
+        let mut pacer = EgressPacer::new(BytesPerSecond(1_000), start);
+        submit(&mut pacer, peer, 1_000, Priority::Normal, 1, start);
+        submit(&mut pacer, peer, 500, Priority::Normal, 2, start);
+
+        //// When
+        let exempt = pacer.submit(peer, 500, Priority::Normal, true, 3, start);
+        let high = submit(&mut pacer, peer, 400, Priority::High, 4, start);
+        let released = pacer.release(start + Duration::from_millis(900));
+
+        //// Then
+        assert_eq!(
+            exempt,
+            Some(3),
+            "The exempt frames should never be held back"
+        );
+        assert!(!high, "The bucket is in debt");
+        assert_eq!(
+            released,
+            [(peer, 4)],
+            "The high priority frame should be released first"
+        );
+    }
+
+    #[test]
+    fn oversized_frame_is_sent_once_the_bucket_is_full() {
+        //// Given
+        let start = Instant::now();
+        let peer = PeerId::random();
+        let mut pacer = EgressPacer::new(BytesPerSecond(1_000), start);
+        submit(&mut pacer, peer, 500, Priority::Normal, 1, start);
+
+        //// When
+        let sent = submit(&mut pacer, peer, 3_000, Priority::Normal, 2, start);
+        let released_early = pacer.release(start + Duration::from_millis(400));
+        let released = pacer.release(start + Duration::from_millis(500));
+
+        //// Then
+        assert!(!sent);
+        assert!(released_early.is_empty());
+        assert_eq!(released, [(peer, 2)]);
+        assert_eq!(pacer.stats().level, 0, "The bucket should be in debt");
+    }
+
+    #[test]
+    fn drained_and_dropped_frames_leave_the_queue() {
+        //// Given
+        let start = Instant::now();
+        let peer_a = PeerId::random();
+        let peer_b = PeerId::random();
+        let mut pacer = EgressPacer::new(BytesPerSecond(100), start);
+        for (frame, dest) in [peer_a, peer_b, peer_a, peer_b].into_iter().enumerate() {
+            submit(&mut pacer, dest, 100, Priority::Normal, frame as u32, start);
+        }
+
+        //// When
+        let dropped = pacer.peer_disconnected(&peer_b);
+        let drained = pacer.drain();
+
+        //// Then
+        assert_eq!(dropped, 2);
+        assert_eq!(drained, [(peer_a, 2)]);
+        assert_eq!(pacer.stats().queued_bytes, 0);
+        assert_eq!(pacer.len(), 0);
+    }
+}
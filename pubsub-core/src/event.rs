@@ -1,24 +1,695 @@
+use std::any::Any;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
 use libp2p::identity::PeerId;
+use libp2p::swarm::ConnectionId;
 
+use crate::activity::TopicActivity;
+use crate::consumer::ConsumerTag;
+use crate::dead_letter::DeadLetter;
+use crate::flush::{FlushId, FlushReport};
 use crate::message::Message;
 use crate::message_id::MessageId;
+use crate::provenance::Provenance;
+use crate::reqres::RequestId;
+use crate::topic::TopicHash;
 
 /// This enum represents events that can be emitted by the pubsub
 /// [`Behaviour`](super::behaviour::Behaviour).
+///
+/// New variants may be added in minor releases, so matches on this enum must include a wildcard
+/// arm. The struct variants with several fields may also gain new fields; construct them with the
+/// associated constructors (e.g., [`Event::message_received`]) and match them with `..`.
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+#[non_exhaustive]
 pub enum Event {
     /// Emitted by the pubsub behaviour when a message associated with a topic the node is
     /// subscribed to is received.
+    #[non_exhaustive]
     MessageReceived {
         /// Peer that propagated the message.
         ///
         /// Do not confuse with the original author of the message, which is optionally included in
         /// the message itself in the message's `from` field.
-        /// field.
         src: PeerId,
         /// The message itself.
         message: Message,
         /// The message id.
         message_id: MessageId,
+        /// The message provenance, i.e., whether the propagation source is the message author.
+        provenance: Provenance,
+        /// Whether the message was published by the local node, and delivered locally (see
+        /// [`Config::deliver_own_messages`](crate::Config::deliver_own_messages)). If so, the
+        /// propagation source is the local peer.
+        is_local: bool,
+        /// The metadata the locally published message was published with (see
+        /// [`Behaviour::publish_with_metadata`](super::behaviour::Behaviour::publish_with_metadata)).
+        /// Never sent on the wire, so always `None` for the messages received from the remote
+        /// peers.
+        local_metadata: Option<Arc<dyn Any + Send + Sync>>,
+    },
+    /// Emitted by the pubsub behaviour, once per local consumer registered on the message topic
+    /// (see [`Behaviour::register_consumer`](super::behaviour::Behaviour::register_consumer)),
+    /// when a message is received.
+    ///
+    /// The [`Event::MessageReceived`] event is only emitted for the messages on the topics the
+    /// node is explicitly subscribed to, or without registered consumers.
+    #[non_exhaustive]
+    MessageForConsumer {
+        /// The consumer tag.
+        tag: ConsumerTag,
+        /// The message itself.
+        message: Message,
+        /// The message id.
+        message_id: MessageId,
+    },
+    /// Emitted by the pubsub behaviour when a local subscription request (see
+    /// [`Behaviour::subscribe`](super::behaviour::Behaviour::subscribe)) has been processed.
+    ///
+    /// From this event on, the node is subscribed to the topic and messages can be published to
+    /// it. Unless a [subscription debounce window](crate::Config::subscription_debounce) is set,
+    /// the subscription announcement to the currently connected peers has already been queued.
+    Subscribed {
+        /// The subscribed topic.
+        topic: TopicHash,
+    },
+    /// Emitted by the pubsub behaviour when a local unsubscription request (see
+    /// [`Behaviour::unsubscribe`](super::behaviour::Behaviour::unsubscribe)) has been processed.
+    ///
+    /// Unless a [subscription debounce window](crate::Config::subscription_debounce) is set, the
+    /// unsubscription announcement to the currently connected peers has already been queued.
+    Unsubscribed {
+        /// The unsubscribed topic.
+        topic: TopicHash,
+    },
+    /// Emitted by the pubsub behaviour when a remote peer is detected misbehaving.
+    #[non_exhaustive]
+    PeerMisbehaving {
+        /// The misbehaving peer.
+        peer: PeerId,
+        /// The detected misbehaviour.
+        reason: MisbehaviourReason,
+    },
+    /// Emitted by the pubsub behaviour when the connection handler of a connection with a remote
+    /// peer is disabled.
+    ///
+    /// No more frames are sent over a disabled connection. If all the peer connections are
+    /// disabled, the peer is no longer used for routing. Applications may decide to disconnect
+    /// from the peer.
+    #[non_exhaustive]
+    ConnectionDisabled {
+        /// The remote peer.
+        peer: PeerId,
+        /// The disabled connection.
+        connection_id: ConnectionId,
+        /// The reason the connection handler was disabled.
+        reason: DisabledReason,
+    },
+    /// Emitted by the pubsub behaviour when the messages of an author on a topic subscribed with
+    /// ordered delivery (see
+    /// [`SubscriptionBuilder::ordered_delivery`](crate::SubscriptionBuilder::ordered_delivery))
+    /// are delivered skipping missing messages.
+    ///
+    /// The event is emitted before the first message following the gap is delivered.
+    #[non_exhaustive]
+    MessageGap {
+        /// The messages topic.
+        topic: TopicHash,
+        /// The messages author.
+        source: PeerId,
+        /// The sequence numbers of the missing messages.
+        missing: Range<u64>,
+    },
+    /// Emitted by the pubsub behaviour when the local subscriptions could not be sent to a
+    /// connected peer after the [maximum number of attempts](
+    /// crate::Config::subscription_sync_max_attempts).
+    ///
+    /// The peer is not aware of the local subscriptions sent before the event. The subscription
+    /// updates are still announced to the peer.
+    SubscriptionSyncFailed {
+        /// The remote peer.
+        peer: PeerId,
+    },
+    /// Emitted by the pubsub behaviour, if [echo detection](crate::Config::detect_echo) is
+    /// enabled, the first time a message published by the local node is received back from a
+    /// remote peer.
+    ///
+    /// An echo indicates that the published message was propagated through the network.
+    #[non_exhaustive]
+    PublishedMessageEchoed {
+        /// The published message id.
+        message_id: MessageId,
+        /// The peer that echoed the message.
+        echoed_by: PeerId,
+    },
+    /// Emitted by the pubsub behaviour, if [enabled](crate::Config::warn_on_no_subscribers), when
+    /// a message is published while no connected peer is known to be subscribed to its topic.
+    ///
+    /// The message was published anyway, but it will likely not reach any peer. This usually
+    /// indicates a misspelled topic, or a publication before the remote peers subscriptions were
+    /// received.
+    #[non_exhaustive]
+    PublishedWithoutSubscribers {
+        /// The message topic.
+        topic: TopicHash,
+        /// The published message id.
+        message_id: MessageId,
+    },
+    /// Emitted by the pubsub behaviour when the protocol router requested to disconnect a peer
+    /// (see [`ProtocolRouterOutEvent::DisconnectPeer`](
+    /// crate::protocol::ProtocolRouterOutEvent::DisconnectPeer)).
+    ///
+    /// All the peer connections are being closed.
+    #[non_exhaustive]
+    PeerDisconnectRequested {
+        /// The disconnected peer.
+        peer: PeerId,
+        /// The disconnection reason.
+        reason: String,
+    },
+    /// Emitted by the pubsub behaviour when a peer sends few messages per frame, on average, while
+    /// its message rate is high (see [`Config::inefficient_framing_threshold`](
+    /// crate::Config::inefficient_framing_threshold)).
+    ///
+    /// This is an observability event, the peer frames are processed as usual. The event is
+    /// emitted at most once per [notification interval](
+    /// crate::Config::inefficient_framing_event_interval) and peer.
+    #[non_exhaustive]
+    InefficientPeerFraming {
+        /// The remote peer.
+        peer: PeerId,
+        /// The average number of messages per frame over the framing statistics window.
+        avg_msgs_per_frame: f64,
+    },
+    /// Emitted by the pubsub behaviour when a peer flaps its subscription to a topic, i.e., it
+    /// subscribes and unsubscribes the topic at a rate above the flapping threshold (see
+    /// [`Config::subscription_flapping_threshold`](
+    /// crate::Config::subscription_flapping_threshold)).
+    ///
+    /// This is an observability event, the peer subscription actions are processed as usual. The
+    /// event is emitted at most once per [churn window](crate::Config::subscription_churn_window)
+    /// and peer topic.
+    #[non_exhaustive]
+    PeerFlapping {
+        /// The remote peer.
+        peer: PeerId,
+        /// The topic the peer flaps its subscription to.
+        topic: TopicHash,
+        /// The peer subscription transitions rate to the topic, in transitions per minute.
+        transitions_per_min: f64,
+    },
+    /// Emitted by the pubsub behaviour when a received message is suspected to be spoofed, i.e.,
+    /// it claims to be authored by a directly connected peer, carries no signature, and was
+    /// propagated by a different peer (see [`Config::verify_from_matches_signature`](
+    /// crate::Config::verify_from_matches_signature)).
+    ///
+    /// This is a heuristic, a legitimate message may be flagged. Unless the suspected spoofed
+    /// messages are dropped (see [`Config::drop_suspected_spoofed`](
+    /// crate::Config::drop_suspected_spoofed)), the message is processed as usual.
+    #[non_exhaustive]
+    SuspectedSpoofedMessage {
+        /// The author claimed by the message.
+        claimed_from: PeerId,
+        /// The peer that propagated the message.
+        propagated_by: PeerId,
+        /// The message id.
+        message_id: MessageId,
+    },
+    /// Emitted by the pubsub behaviour when the recently cached messages of a topic were sent to a
+    /// peer that subscribed to it (see [`SubscriptionBuilder::backfill_on_peer_subscribe`](
+    /// crate::SubscriptionBuilder::backfill_on_peer_subscribe)).
+    ///
+    /// Not emitted if no cached message was eligible for the backfill.
+    #[non_exhaustive]
+    BackfillSent {
+        /// The peer the messages were sent to.
+        peer: PeerId,
+        /// The topic the peer subscribed to.
+        topic: TopicHash,
+        /// The number of messages sent.
+        count: usize,
+    },
+    /// Emitted by the pubsub behaviour when a received message is dropped because a peer is not
+    /// authorized to publish on its topic (see [`Config::topic_authorizer`](
+    /// crate::Config::topic_authorizer)).
+    ///
+    /// The event is emitted at most once per [notification interval](
+    /// crate::Config::unauthorized_message_event_interval), peer and topic.
+    #[non_exhaustive]
+    UnauthorizedMessage {
+        /// The peer not authorized: the peer that propagated the message, or its claimed author.
+        peer: PeerId,
+        /// The message topic.
+        topic: TopicHash,
+    },
+    /// Emitted by the pubsub behaviour when the persistent message dedup store cannot be read, or
+    /// written (see [`Config::dedup_persistence`](crate::Config::dedup_persistence)).
+    ///
+    /// The message cache is memory-only from now on, i.e., the messages seen before a node
+    /// restart may be delivered again.
+    DedupPersistenceFailed {
+        /// The dedup store error.
+        error: std::io::Error,
+    },
+    /// Emitted by the pubsub behaviour when the [forwarding warm-up](crate::Config::forward_warmup)
+    /// completes. The received messages are forwarded from now on.
+    WarmupCompleted,
+    /// Emitted by the pubsub behaviour when a response to a request sent by the local node (see
+    /// [`Behaviour::send_request`](super::behaviour::Behaviour::send_request)) is received.
+    ///
+    /// Only the first response received before the request timed out is notified, the following
+    /// responses are dropped.
+    #[non_exhaustive]
+    ResponseReceived {
+        /// The request correlation id.
+        request_id: RequestId,
+        /// The responder, if the response is authored, otherwise the peer that propagated the
+        /// response.
+        from: PeerId,
+        /// The response payload.
+        payload: Vec<u8>,
+    },
+    /// Emitted by the pubsub behaviour when no response to a request sent by the local node was
+    /// received before the request timeout. The timeouts are checked on every heartbeat.
+    RequestTimedOut {
+        /// The request correlation id.
+        request_id: RequestId,
+    },
+    /// Emitted by the pubsub behaviour when a peer, asked to refresh its subscriptions (see
+    /// [`Behaviour::request_subscription_refresh`](super::behaviour::Behaviour::request_subscription_refresh)),
+    /// announced topics the local node did not know it was subscribed to.
+    ///
+    /// The topics are learned within the [refresh window](crate::Config::subscription_refresh_window),
+    /// and the event is emitted on the first heartbeat after the window closes. No event is emitted
+    /// if the refresh taught no new topic.
+    #[non_exhaustive]
+    SubscriptionRefreshCompleted {
+        /// The refreshed peer.
+        peer: PeerId,
+        /// The topics the peer was newly found subscribed to.
+        topics_learned: Vec<TopicHash>,
+    },
+    /// Emitted by the pubsub behaviour when a flushed unsubscription (see
+    /// [`Behaviour::unsubscribe_many_and_flush`](super::behaviour::Behaviour::unsubscribe_many_and_flush))
+    /// completes: the unsubscription frame was flushed to every peer, or the deadline passed.
+    ///
+    /// The report lists the peers the unsubscription frame was flushed to, and the peers it failed
+    /// to be flushed to.
+    #[non_exhaustive]
+    UnsubscriptionFlushed {
+        /// The flushed unsubscription id.
+        flush_id: FlushId,
+        /// The per-peer flush outcome.
+        report: FlushReport,
+    },
+    /// Emitted by the pubsub behaviour, if [dead letter events](crate::Config::dead_letter_events)
+    /// are enabled, when an input is intentionally dropped.
+    DeadLetter(DeadLetter),
+    /// Emitted by the pubsub behaviour when a peer is suspected of asymmetric connectivity: frames
+    /// are still received from the peer, while most of the frames sent to it fail (see
+    /// [`Config::asymmetric_connectivity_threshold`](
+    /// crate::Config::asymmetric_connectivity_threshold)).
+    ///
+    /// This event is emitted once per suspicion. The suspicion is dropped once the peer send
+    /// failures ratio falls back below the threshold.
+    #[non_exhaustive]
+    AsymmetricConnectivitySuspected {
+        /// The suspected peer.
+        peer: PeerId,
+        /// The ratio of failed to attempted sends to the peer over the
+        /// [send health window](crate::Config::send_health_window).
+        fail_ratio: f64,
+    },
+    /// Emitted by the pubsub behaviour when a message is received on a topic the local node is not
+    /// subscribed to, while the local node is subscribed to the same topic string hashed
+    /// differently, e.g., the peer uses the identity hash of a topic the local node subscribed to
+    /// with the SHA-256 hash (see [`Sha256Topic`](crate::Sha256Topic)).
+    ///
+    /// The message is dropped. To accept it, subscribe with the received topic hash as an alias
+    /// (see [`SubscriptionBuilder::also_accept_alias`](
+    /// crate::SubscriptionBuilder::also_accept_alias)).
+    ///
+    /// This event is emitted at most once per received topic every
+    /// [`Config::topic_hash_mismatch_event_interval`](
+    /// crate::Config::topic_hash_mismatch_event_interval).
+    #[non_exhaustive]
+    TopicHashMismatchSuspected {
+        /// The received message topic hash.
+        received_topic: TopicHash,
+        /// The local subscription topic hash, hashing the same topic string.
+        matching_local_topic: TopicHash,
+    },
+    /// Emitted by the pubsub behaviour every [activity summary interval](
+    /// crate::Config::activity_summaries), reporting the activity of the locally subscribed topics
+    /// over the elapsed window.
+    ///
+    /// The topics without activity in the window are omitted. If more than
+    /// [`Config::activity_summary_max_topics`](crate::Config::activity_summary_max_topics) topics
+    /// were active, the activity of the least active ones is rolled up into `others`.
+    #[non_exhaustive]
+    TopicActivitySummary {
+        /// The summary window duration.
+        window: Duration,
+        /// The activity of the reported topics, most active first.
+        per_topic: Vec<(TopicHash, TopicActivity)>,
+        /// The rolled up activity of the topics beyond the summary entries cap, if any.
+        others: Option<TopicActivity>,
+    },
+    /// Emitted by the pubsub behaviour when no message was received on the locally subscribed
+    /// topics for the [silence watchdog](crate::Config::silence_watchdog) duration, while active
+    /// peers are subscribed to them, e.g., because the peers use different topic hashes, or an
+    /// upstream relay is broken.
+    ///
+    /// Emitted once per silence period: the event is emitted again only after a message is
+    /// received on a subscribed topic, and the topics fall silent again.
+    #[non_exhaustive]
+    SubscribedButSilent {
+        /// The locally subscribed topics active peers are subscribed to.
+        topics: Vec<TopicHash>,
+        /// The active peers subscribed to the topics.
+        peers: Vec<PeerId>,
+        /// The time since the last message was received on a subscribed topic.
+        silent_for: Duration,
+    },
+    /// Emitted by the pubsub behaviour, if
+    /// [subscription confirmation events](crate::Config::subscription_confirmation_events) are
+    /// enabled, when a connected peer confirms, for the first time, knowing about the local
+    /// subscription to a topic, i.e., the peer forwarded a message on the topic, or grafted the
+    /// local node into the topic mesh.
+    ///
+    /// The confirmation is best-effort: the pubsub protocol has no acknowledgement of the
+    /// subscription announcements. The confirmations are reset when the peer disconnects, or the
+    /// local node unsubscribes from the topic (see
+    /// [`Behaviour::peer_confirmed_topics`](crate::Behaviour::peer_confirmed_topics)).
+    #[non_exhaustive]
+    PeerConfirmedSubscription {
+        /// The peer confirming the subscription.
+        peer: PeerId,
+        /// The locally subscribed topic.
+        topic: TopicHash,
+    },
+    /// Emitted by the pubsub behaviour when the listen-only mode is enabled, or disabled (see
+    /// [`Behaviour::set_listen_only`](crate::Behaviour::set_listen_only)).
+    ListenOnlyChanged(bool),
+}
+
+impl Event {
+    /// Create a new [`Event::MessageReceived`] event.
+    ///
+    /// The message provenance is derived from the propagation source and the message author.
+    #[must_use]
+    pub fn message_received(src: PeerId, message: Message, message_id: MessageId) -> Self {
+        let provenance = Provenance::new(&src, message.from.as_ref());
+        Self::MessageReceived {
+            src,
+            message,
+            message_id,
+            provenance,
+            is_local: false,
+            local_metadata: None,
+        }
+    }
+
+    /// Create a new [`Event::MessageReceived`] event for a message published by the local node,
+    /// and delivered locally (see
+    /// [`Config::deliver_own_messages`](crate::Config::deliver_own_messages)).
+    #[must_use]
+    pub fn local_message_received(
+        local_peer_id: PeerId,
+        message: Message,
+        message_id: MessageId,
+        local_metadata: Option<Arc<dyn Any + Send + Sync>>,
+    ) -> Self {
+        let provenance = Provenance::new(&local_peer_id, message.from.as_ref());
+        Self::MessageReceived {
+            src: local_peer_id,
+            message,
+            message_id,
+            provenance,
+            is_local: true,
+            local_metadata,
+        }
+    }
+
+    /// Create a new [`Event::MessageForConsumer`] event.
+    #[must_use]
+    pub fn message_for_consumer(tag: ConsumerTag, message: Message, message_id: MessageId) -> Self {
+        Self::MessageForConsumer {
+            tag,
+            message,
+            message_id,
+        }
+    }
+
+    /// Create a new [`Event::Subscribed`] event.
+    #[must_use]
+    pub fn subscribed(topic: TopicHash) -> Self {
+        Self::Subscribed { topic }
+    }
+
+    /// Create a new [`Event::Unsubscribed`] event.
+    #[must_use]
+    pub fn unsubscribed(topic: TopicHash) -> Self {
+        Self::Unsubscribed { topic }
+    }
+
+    /// Create a new [`Event::PeerMisbehaving`] event.
+    #[must_use]
+    pub fn peer_misbehaving(peer: PeerId, reason: MisbehaviourReason) -> Self {
+        Self::PeerMisbehaving { peer, reason }
+    }
+
+    /// Create a new [`Event::ConnectionDisabled`] event.
+    #[must_use]
+    pub fn connection_disabled(
+        peer: PeerId,
+        connection_id: ConnectionId,
+        reason: DisabledReason,
+    ) -> Self {
+        Self::ConnectionDisabled {
+            peer,
+            connection_id,
+            reason,
+        }
+    }
+
+    /// Create a new [`Event::MessageGap`] event.
+    #[must_use]
+    pub fn message_gap(topic: TopicHash, source: PeerId, missing: Range<u64>) -> Self {
+        Self::MessageGap {
+            topic,
+            source,
+            missing,
+        }
+    }
+
+    /// Create a new [`Event::SubscriptionSyncFailed`] event.
+    #[must_use]
+    pub fn subscription_sync_failed(peer: PeerId) -> Self {
+        Self::SubscriptionSyncFailed { peer }
+    }
+
+    /// Create a new [`Event::PublishedMessageEchoed`] event.
+    #[must_use]
+    pub fn published_message_echoed(message_id: MessageId, echoed_by: PeerId) -> Self {
+        Self::PublishedMessageEchoed {
+            message_id,
+            echoed_by,
+        }
+    }
+
+    /// Create a new [`Event::PublishedWithoutSubscribers`] event.
+    #[must_use]
+    pub fn published_without_subscribers(topic: TopicHash, message_id: MessageId) -> Self {
+        Self::PublishedWithoutSubscribers { topic, message_id }
+    }
+
+    /// Create a new [`Event::PeerDisconnectRequested`] event.
+    #[must_use]
+    pub fn peer_disconnect_requested(peer: PeerId, reason: impl Into<String>) -> Self {
+        Self::PeerDisconnectRequested {
+            peer,
+            reason: reason.into(),
+        }
+    }
+
+    /// Create a new [`Event::ResponseReceived`] event.
+    #[must_use]
+    pub fn response_received(request_id: RequestId, from: PeerId, payload: Vec<u8>) -> Self {
+        Self::ResponseReceived {
+            request_id,
+            from,
+            payload,
+        }
+    }
+
+    /// Create a new [`Event::RequestTimedOut`] event.
+    #[must_use]
+    pub fn request_timed_out(request_id: RequestId) -> Self {
+        Self::RequestTimedOut { request_id }
+    }
+
+    /// Create a new [`Event::UnsubscriptionFlushed`] event.
+    #[must_use]
+    pub fn unsubscription_flushed(flush_id: FlushId, report: FlushReport) -> Self {
+        Self::UnsubscriptionFlushed { flush_id, report }
+    }
+
+    /// Create a new [`Event::SubscriptionRefreshCompleted`] event.
+    #[must_use]
+    pub fn subscription_refresh_completed(peer: PeerId, topics_learned: Vec<TopicHash>) -> Self {
+        Self::SubscriptionRefreshCompleted {
+            peer,
+            topics_learned,
+        }
+    }
+
+    /// Create a new [`Event::InefficientPeerFraming`] event.
+    #[must_use]
+    pub fn inefficient_peer_framing(peer: PeerId, avg_msgs_per_frame: f64) -> Self {
+        Self::InefficientPeerFraming {
+            peer,
+            avg_msgs_per_frame,
+        }
+    }
+
+    /// Create a new [`Event::SuspectedSpoofedMessage`] event.
+    #[must_use]
+    pub fn suspected_spoofed_message(
+        claimed_from: PeerId,
+        propagated_by: PeerId,
+        message_id: MessageId,
+    ) -> Self {
+        Self::SuspectedSpoofedMessage {
+            claimed_from,
+            propagated_by,
+            message_id,
+        }
+    }
+
+    /// Create a new [`Event::PeerFlapping`] event.
+    #[must_use]
+    pub fn peer_flapping(peer: PeerId, topic: TopicHash, transitions_per_min: f64) -> Self {
+        Self::PeerFlapping {
+            peer,
+            topic,
+            transitions_per_min,
+        }
+    }
+
+    /// Create a new [`Event::BackfillSent`] event.
+    #[must_use]
+    pub fn backfill_sent(peer: PeerId, topic: TopicHash, count: usize) -> Self {
+        Self::BackfillSent { peer, topic, count }
+    }
+
+    /// Create a new [`Event::UnauthorizedMessage`] event.
+    #[must_use]
+    pub fn unauthorized_message(peer: PeerId, topic: TopicHash) -> Self {
+        Self::UnauthorizedMessage { peer, topic }
+    }
+
+    /// Create a new [`Event::DedupPersistenceFailed`] event.
+    #[must_use]
+    pub fn dedup_persistence_failed(error: std::io::Error) -> Self {
+        Self::DedupPersistenceFailed { error }
+    }
+
+    /// Create a new [`Event::AsymmetricConnectivitySuspected`] event.
+    #[must_use]
+    pub fn asymmetric_connectivity_suspected(peer: PeerId, fail_ratio: f64) -> Self {
+        Self::AsymmetricConnectivitySuspected { peer, fail_ratio }
+    }
+
+    /// Create a new [`Event::TopicHashMismatchSuspected`] event.
+    #[must_use]
+    pub fn topic_hash_mismatch_suspected(
+        received_topic: TopicHash,
+        matching_local_topic: TopicHash,
+    ) -> Self {
+        Self::TopicHashMismatchSuspected {
+            received_topic,
+            matching_local_topic,
+        }
+    }
+
+    /// Create a new [`Event::TopicActivitySummary`] event.
+    #[must_use]
+    pub fn topic_activity_summary(
+        window: Duration,
+        per_topic: Vec<(TopicHash, TopicActivity)>,
+        others: Option<TopicActivity>,
+    ) -> Self {
+        Self::TopicActivitySummary {
+            window,
+            per_topic,
+            others,
+        }
+    }
+
+    /// Create a new [`Event::SubscribedButSilent`] event.
+    #[must_use]
+    pub fn subscribed_but_silent(
+        topics: Vec<TopicHash>,
+        peers: Vec<PeerId>,
+        silent_for: Duration,
+    ) -> Self {
+        Self::SubscribedButSilent {
+            topics,
+            peers,
+            silent_for,
+        }
+    }
+
+    /// Create a new [`Event::PeerConfirmedSubscription`] event.
+    #[must_use]
+    pub fn peer_confirmed_subscription(peer: PeerId, topic: TopicHash) -> Self {
+        Self::PeerConfirmedSubscription { peer, topic }
+    }
+
+    /// Create a new [`Event::ListenOnlyChanged`] event.
+    #[must_use]
+    pub fn listen_only_changed(enabled: bool) -> Self {
+        Self::ListenOnlyChanged(enabled)
+    }
+}
+
+/// The reason a remote peer was flagged as misbehaving.
+///
+/// New reasons may be added in minor releases, so matches on this enum must include a wildcard
+/// arm.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum MisbehaviourReason {
+    /// The peer re-sent the same message more times than the
+    /// [maximum number of duplicate resends](crate::Config::max_duplicate_resends) allowed.
+    DuplicateFlood {
+        /// The flooded message id.
+        message_id: MessageId,
     },
 }
+
+impl MisbehaviourReason {
+    /// Create a new [`MisbehaviourReason::DuplicateFlood`] reason.
+    #[must_use]
+    pub fn duplicate_flood(message_id: MessageId) -> Self {
+        Self::DuplicateFlood { message_id }
+    }
+}
+
+/// The reason a connection handler was disabled.
+///
+/// New reasons may be added in minor releases, so matches on this enum must include a wildcard
+/// arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DisabledReason {
+    /// The outbound substream protocol upgrade failed.
+    UpgradeFailed,
+    /// The maximum number of [send retry attempts](crate::Config::max_connection_send_retry_attempts)
+    /// was reached.
+    MaxSendRetriesReached,
+}
@@ -0,0 +1,140 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use libp2p::identity::PeerId;
+
+use crate::message_id::MessageId;
+
+/// Caps the number of peers a message is forwarded to.
+///
+/// The destination peers are chosen deterministically by ranking the candidate peers by a seeded
+/// hash of the message id and the peer id, and keeping the lowest ranked ones. The same node picks
+/// the same peers for a given message, while nodes seeded differently (e.g., with their own peer
+/// id, see [`ForwardFanout::seed_from_peer_id`]) pick different subsets of their peers.
+///
+/// Capping the fan-out trades delivery guarantees for bandwidth: a peer is no longer guaranteed to
+/// receive a message from every subscribed neighbour, but as the relays pick their subsets
+/// independently, a message still reaches most of the topic subscribers with high probability as
+/// long as the cap is not too low relative to the network size. The locally published messages
+/// have no other source to compensate for a missed peer, so they have a separate, usually higher,
+/// cap.
+///
+/// The deprioritized peers (see [`ForwardFanout::set_deprioritized`]) are picked last, i.e., only
+/// if the cap leaves room after all the other candidate peers.
+#[derive(Debug, Clone, Default)]
+pub struct ForwardFanout {
+    /// The peer selection hash seed.
+    seed: u64,
+
+    /// The maximum number of peers a received message is forwarded to. If `None`, no limit.
+    max_forward: Option<usize>,
+
+    /// The maximum number of peers a published message is sent to. If `None`, no limit.
+    max_publish: Option<usize>,
+
+    /// The peers picked last, e.g., the peers suspected of asymmetric connectivity.
+    deprioritized: HashSet<PeerId>,
+}
+
+impl ForwardFanout {
+    /// Create a new fan-out selector with the given hash seed and caps.
+    #[must_use]
+    pub fn new(seed: u64, max_forward: Option<usize>, max_publish: Option<usize>) -> Self {
+        Self {
+            seed,
+            max_forward,
+            max_publish,
+            deprioritized: Default::default(),
+        }
+    }
+
+    /// Mark a peer as deprioritized, or no longer, as a destination.
+    ///
+    /// The deprioritized peers are still selected when the fan-out is not capped, but they are
+    /// ranked after all the other candidate peers.
+    pub fn set_deprioritized(&mut self, peer: PeerId, deprioritized: bool) {
+        if deprioritized {
+            self.deprioritized.insert(peer);
+        } else {
+            self.deprioritized.remove(&peer);
+        }
+    }
+
+    /// Whether a peer is deprioritized as a destination.
+    #[must_use]
+    pub fn is_deprioritized(&self, peer: &PeerId) -> bool {
+        self.deprioritized.contains(peer)
+    }
+
+    /// Derive a peer selection hash seed from the local node peer id.
+    #[must_use]
+    pub fn seed_from_peer_id(peer: &PeerId) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        peer.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Select the peers a received message is forwarded to.
+    ///
+    /// The `src` peer, the message propagation source, is never selected.
+    #[must_use]
+    pub fn select_forward(
+        &self,
+        message_id: &MessageId,
+        src: &PeerId,
+        peers: impl IntoIterator<Item = PeerId>,
+    ) -> Vec<PeerId> {
+        let peers = peers.into_iter().filter(|peer| peer != src);
+        self.select(message_id, peers, self.max_forward)
+    }
+
+    /// Select the peers a published message is sent to.
+    #[must_use]
+    pub fn select_publish(
+        &self,
+        message_id: &MessageId,
+        peers: impl IntoIterator<Item = PeerId>,
+    ) -> Vec<PeerId> {
+        self.select(message_id, peers, self.max_publish)
+    }
+
+    /// Keep the `max` peers with the lowest selection rank for the message, if set. The
+    /// deprioritized peers are ranked last.
+    fn select(
+        &self,
+        message_id: &MessageId,
+        peers: impl IntoIterator<Item = PeerId>,
+        max: Option<usize>,
+    ) -> Vec<PeerId> {
+        let mut peers = peers.into_iter().collect::<Vec<_>>();
+        if !self.deprioritized.is_empty() {
+            peers.sort_by_key(|peer| self.deprioritized.contains(peer));
+        }
+
+        let Some(max) = max else {
+            return peers;
+        };
+        if peers.len() <= max {
+            return peers;
+        }
+
+        peers.sort_by_cached_key(|peer| {
+            (
+                self.deprioritized.contains(peer),
+                self.rank(message_id, peer),
+            )
+        });
+        peers.truncate(max);
+        peers
+    }
+
+    /// The peer selection rank for the message.
+    fn rank(&self, message_id: &MessageId, peer: &PeerId) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.seed.hash(&mut hasher);
+        message_id.hash(&mut hasher);
+        peer.hash(&mut hasher);
+        hasher.finish()
+    }
+}
@@ -0,0 +1,291 @@
+//! Flushed unsubscriptions (see
+//! [`Behaviour::unsubscribe_many_and_flush`](crate::Behaviour::unsubscribe_many_and_flush)).
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::time::Instant;
+
+use libp2p::identity::PeerId;
+
+use crate::topic::TopicHash;
+
+/// The id of a flushed unsubscription, unique per behaviour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FlushId(u64);
+
+impl FlushId {
+    /// Create a new flush id from its raw value.
+    #[must_use]
+    pub fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    /// The flush id raw value.
+    #[must_use]
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for FlushId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The outcome of a flushed unsubscription, per peer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FlushReport {
+    /// The peers the unsubscription frame was flushed to before the deadline.
+    pub flushed: BTreeSet<PeerId>,
+    /// The peers the unsubscription frame was not flushed to before the deadline, or that
+    /// disconnected in the meantime.
+    pub failed: BTreeSet<PeerId>,
+}
+
+impl FlushReport {
+    /// Create a new flush report.
+    #[must_use]
+    pub fn new(flushed: BTreeSet<PeerId>, failed: BTreeSet<PeerId>) -> Self {
+        Self { flushed, failed }
+    }
+}
+
+/// An in-flight flushed unsubscription.
+#[derive(Debug)]
+struct PendingFlush {
+    /// The time the flush is reported, whether the frames were flushed or not.
+    deadline: Instant,
+    /// The topics whose unsubscription is pending to be processed.
+    unprocessed: BTreeSet<TopicHash>,
+    /// The topics unsubscribed, to be sent in the unsubscription frame.
+    topics: Vec<TopicHash>,
+    /// Whether the unsubscription frame was sent to the peers.
+    sent: bool,
+    /// The peers the unsubscription frame was sent to, and not yet flushed.
+    awaiting: HashSet<PeerId>,
+    /// The flush outcome so far.
+    report: FlushReport,
+}
+
+impl PendingFlush {
+    /// Whether the flush outcome is known for every peer.
+    fn is_complete(&self) -> bool {
+        self.sent && self.awaiting.is_empty()
+    }
+}
+
+/// Tracks the in-flight flushed unsubscriptions.
+///
+/// A flushed unsubscription goes through three steps: the unsubscriptions are processed, the
+/// unsubscription frame is sent to every active peer, tagged with the flush id, and the connection
+/// handlers report the frame flushed to each peer. The peers that did not report the frame flushed
+/// by the deadline are reported as failed.
+#[derive(Debug, Default)]
+pub(crate) struct PendingFlushes {
+    /// The next flush id.
+    next_id: u64,
+    /// The in-flight flushes, by flush id.
+    pending: HashMap<FlushId, PendingFlush>,
+}
+
+impl PendingFlushes {
+    /// Register a new flushed unsubscription of the `topics`, reported at the given deadline.
+    pub(crate) fn insert(&mut self, topics: BTreeSet<TopicHash>, deadline: Instant) -> FlushId {
+        let flush_id = FlushId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.pending.insert(
+            flush_id,
+            PendingFlush {
+                deadline,
+                unprocessed: topics,
+                topics: Vec::new(),
+                sent: false,
+                awaiting: HashSet::new(),
+                report: FlushReport::default(),
+            },
+        );
+        flush_id
+    }
+
+    /// Record the processed unsubscription of the topic. Returns `true` if the unsubscription
+    /// belongs to an in-flight flush, and must not be announced to the peers otherwise.
+    pub(crate) fn topic_unsubscribed(&mut self, topic: &TopicHash) -> bool {
+        let flush = self
+            .pending
+            .values_mut()
+            .find(|flush| flush.unprocessed.contains(topic));
+
+        match flush {
+            Some(flush) => {
+                flush.unprocessed.remove(topic);
+                flush.topics.push(topic.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return the flushes whose unsubscriptions were all processed, and whose unsubscription frame
+    /// is pending to be sent, along with the unsubscribed topics.
+    pub(crate) fn ready(&self) -> Vec<(FlushId, Vec<TopicHash>)> {
+        let mut ready = self
+            .pending
+            .iter()
+            .filter(|(_, flush)| !flush.sent && flush.unprocessed.is_empty())
+            .map(|(flush_id, flush)| (*flush_id, flush.topics.clone()))
+            .collect::<Vec<_>>();
+        ready.sort_unstable_by_key(|(flush_id, _)| *flush_id);
+        ready
+    }
+
+    /// Record the unsubscription frame sent to the `peers`.
+    pub(crate) fn sent(&mut self, flush_id: &FlushId, peers: impl IntoIterator<Item = PeerId>) {
+        if let Some(flush) = self.pending.get_mut(flush_id) {
+            flush.sent = true;
+            flush.awaiting.extend(peers);
+        }
+    }
+
+    /// Record the unsubscription frame flushed to the peer.
+    pub(crate) fn flushed(&mut self, flush_id: &FlushId, peer: PeerId) {
+        if let Some(flush) = self.pending.get_mut(flush_id) {
+            if flush.awaiting.remove(&peer) {
+                flush.report.flushed.insert(peer);
+            }
+        }
+    }
+
+    /// Record the unsubscription frame not sent, or not flushed, to the peer.
+    pub(crate) fn failed(&mut self, flush_id: &FlushId, peer: PeerId) {
+        if let Some(flush) = self.pending.get_mut(flush_id) {
+            if flush.awaiting.remove(&peer) {
+                flush.report.failed.insert(peer);
+            }
+        }
+    }
+
+    /// Record the peer disconnection. The in-flight flushes to the peer fail.
+    pub(crate) fn peer_disconnected(&mut self, peer: &PeerId) {
+        for flush in self.pending.values_mut() {
+            if flush.awaiting.remove(peer) {
+                flush.report.failed.insert(*peer);
+            }
+        }
+    }
+
+    /// Remove and return the flushes complete, or whose deadline passed, at `now`, in flush order.
+    ///
+    /// The peers not yet reported at the deadline are reported as failed.
+    pub(crate) fn complete(&mut self, now: Instant) -> Vec<(FlushId, FlushReport)> {
+        let mut completed = self
+            .pending
+            .iter()
+            .filter(|(_, flush)| flush.is_complete() || flush.deadline <= now)
+            .map(|(flush_id, _)| *flush_id)
+            .collect::<Vec<_>>();
+        completed.sort_unstable();
+
+        completed
+            .into_iter()
+            .filter_map(|flush_id| {
+                let mut flush = self.pending.remove(&flush_id)?;
+                flush.report.failed.extend(flush.awaiting);
+                Some((flush_id, flush.report))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn flush_completes_once_flushed_to_every_peer() {
+        //// Given
+        let mut flushes = PendingFlushes::default();
+        let topic = TopicHash::from_raw("/test/topic");
+        let (peer_a, peer_b) = (PeerId::random(), PeerId::random());
+        let now = Instant::now();
+
+        let flush_id = flushes.insert(
+            BTreeSet::from([topic.clone()]),
+            now + Duration::from_secs(1),
+        );
+
+        //// When
+        let ready_before = flushes.ready();
+        let unsubscribed = flushes.topic_unsubscribed(&topic);
+        let ready = flushes.ready();
+        flushes.sent(&flush_id, [peer_a, peer_b]);
+
+        flushes.flushed(&flush_id, peer_a);
+        let completed_partially = flushes.complete(now);
+        flushes.flushed(&flush_id, peer_b);
+        let completed = flushes.complete(now);
+
+        //// Then
+        assert!(ready_before.is_empty());
+        assert!(unsubscribed);
+        assert_eq!(ready, [(flush_id, vec![topic])]);
+        assert!(completed_partially.is_empty());
+        assert_eq!(
+            completed,
+            [(
+                flush_id,
+                FlushReport::new(BTreeSet::from([peer_a, peer_b]), BTreeSet::new())
+            )]
+        );
+    }
+
+    #[test]
+    fn peers_not_flushed_by_the_deadline_are_reported_as_failed() {
+        //// Given
+        let mut flushes = PendingFlushes::default();
+        let topic = TopicHash::from_raw("/test/topic");
+        let (peer_a, peer_b) = (PeerId::random(), PeerId::random());
+        let now = Instant::now();
+
+        let flush_id = flushes.insert(
+            BTreeSet::from([topic.clone()]),
+            now + Duration::from_secs(1),
+        );
+        flushes.topic_unsubscribed(&topic);
+        flushes.sent(&flush_id, [peer_a, peer_b]);
+        flushes.flushed(&flush_id, peer_a);
+
+        //// When
+        let completed = flushes.complete(now + Duration::from_secs(1));
+
+        //// Then
+        assert_eq!(
+            completed,
+            [(
+                flush_id,
+                FlushReport::new(BTreeSet::from([peer_a]), BTreeSet::from([peer_b]))
+            )]
+        );
+    }
+
+    #[test]
+    fn unsubscriptions_not_flushed_are_not_claimed() {
+        //// Given
+        let mut flushes = PendingFlushes::default();
+        let now = Instant::now();
+
+        flushes.insert(
+            BTreeSet::from([TopicHash::from_raw("/test/topic-a")]),
+            now + Duration::from_secs(1),
+        );
+
+        //// When
+        let unsubscribed = flushes.topic_unsubscribed(&TopicHash::from_raw("/test/topic-b"));
+
+        //// Then
+        assert!(!unsubscribed);
+    }
+}
@@ -0,0 +1,21 @@
+use crate::topic::TopicHash;
+
+/// Errors that can occur when forwarding a message on behalf of the application.
+///
+/// See [`Behaviour::forward_message`](crate::Behaviour::forward_message) for more details.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ForwardError {
+    /// The local node is not subscribed to the message topic.
+    #[error("not subscribed to topic: {0}")]
+    NotSubscribed(TopicHash),
+
+    /// The message time-to-live expired.
+    #[error("message expired")]
+    Expired,
+
+    /// The local node is in listen-only mode (see
+    /// [`Behaviour::set_listen_only`](crate::Behaviour::set_listen_only)).
+    #[error("listen-only mode")]
+    ListenOnly,
+}
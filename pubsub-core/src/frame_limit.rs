@@ -0,0 +1,179 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use libp2p::identity::PeerId;
+use libp2p::swarm::ConnectionId;
+
+/// The lowest frame size limit suspected for a peer.
+///
+/// Below this size, the failures are not attributed to the frame size, and the suspected limit is
+/// not lowered any further.
+pub(crate) const MIN_SUSPECTED_FRAME_LIMIT: usize = 512;
+
+/// A frame size limit suspected for a peer.
+#[derive(Debug)]
+struct SuspectedLimit {
+    /// The suspected frame size limit.
+    limit: usize,
+    /// The time of the last send failure the limit was inferred from.
+    updated_at: Instant,
+    /// The peer connections whose connection handler was told about the limit.
+    connections: HashSet<ConnectionId>,
+}
+
+/// Tracks the frame size limits suspected for the remote peers (see
+/// [`Behaviour::peer_frame_limit`](crate::Behaviour::peer_frame_limit)).
+///
+/// There is no handshake to learn a peer's maximum frame size. A peer receiving a frame above its
+/// limit resets the substream, so an outbound substream failing after sending a frame of `S`
+/// bytes hints at a limit below `S`. Each failure halves the suspected limit, down to
+/// [`MIN_SUSPECTED_FRAME_LIMIT`]. As a failure may have other causes, the suspicion is dropped
+/// once the peer went the decay period without failures.
+#[derive(Debug)]
+pub(crate) struct PeerFrameLimits {
+    /// The time without send failures after which a suspected limit is dropped.
+    decay: Duration,
+    /// The suspected frame size limits, by peer.
+    limits: HashMap<PeerId, SuspectedLimit>,
+}
+
+impl PeerFrameLimits {
+    /// Create a new peer frame limits tracker.
+    pub(crate) fn new(decay: Duration) -> Self {
+        Self {
+            decay,
+            limits: Default::default(),
+        }
+    }
+
+    /// The frame size limit suspected for the peer, if any.
+    pub(crate) fn limit(&self, peer: &PeerId) -> Option<usize> {
+        self.limits.get(peer).map(|suspected| suspected.limit)
+    }
+
+    /// Record a send failure over the peer connection, after sending frames of up to
+    /// `frame_size` bytes, and return the peer suspected limit.
+    ///
+    /// Returns `None` if no limit is suspected, i.e., the failed frames were not larger than
+    /// [`MIN_SUSPECTED_FRAME_LIMIT`] and no earlier failure was recorded.
+    pub(crate) fn record_send_failure(
+        &mut self,
+        peer: PeerId,
+        connection: ConnectionId,
+        frame_size: usize,
+        now: Instant,
+    ) -> Option<usize> {
+        let candidate = (frame_size / 2).max(MIN_SUSPECTED_FRAME_LIMIT);
+        if frame_size <= MIN_SUSPECTED_FRAME_LIMIT && !self.limits.contains_key(&peer) {
+            return None;
+        }
+
+        let suspected = self.limits.entry(peer).or_insert_with(|| SuspectedLimit {
+            limit: candidate,
+            updated_at: now,
+            connections: HashSet::new(),
+        });
+        suspected.limit = suspected.limit.min(candidate);
+        suspected.updated_at = now;
+        suspected.connections.insert(connection);
+
+        Some(suspected.limit)
+    }
+
+    /// Remove and return the suspected limits that decayed at `now`, along with the peer
+    /// connections told about them.
+    pub(crate) fn expire(&mut self, now: Instant) -> Vec<(PeerId, HashSet<ConnectionId>)> {
+        let expired = self
+            .limits
+            .iter()
+            .filter(|(_, suspected)| {
+                now.saturating_duration_since(suspected.updated_at) >= self.decay
+            })
+            .map(|(peer, _)| *peer)
+            .collect::<Vec<_>>();
+
+        expired
+            .into_iter()
+            .filter_map(|peer| {
+                let suspected = self.limits.remove(&peer)?;
+                Some((peer, suspected.connections))
+            })
+            .collect()
+    }
+
+    /// Drop the peer suspected limit, e.g., on disconnection.
+    pub(crate) fn peer_disconnected(&mut self, peer: &PeerId) {
+        self.limits.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_limits() -> PeerFrameLimits {
+        PeerFrameLimits::new(Duration::from_secs(60))
+    }
+
+    #[test]
+    fn each_send_failure_halves_the_suspected_limit() {
+        //// Given
+        let mut limits = new_test_limits();
+        let peer = PeerId::random();
+        let connection = ConnectionId::new_unchecked(1);
+        let now = Instant::now();
+
+        //// When
+        let first = limits.record_send_failure(peer, connection, 4096, now);
+        let second = limits.record_send_failure(peer, connection, 2048, now);
+        let larger = limits.record_send_failure(peer, connection, 65536, now);
+        let floor = limits.record_send_failure(peer, connection, 600, now);
+
+        //// Then
+        assert_eq!(first, Some(2048));
+        assert_eq!(second, Some(1024));
+        assert_eq!(
+            larger,
+            Some(1024),
+            "a larger frame should not raise the limit"
+        );
+        assert_eq!(floor, Some(MIN_SUSPECTED_FRAME_LIMIT));
+        assert_eq!(limits.limit(&peer), Some(MIN_SUSPECTED_FRAME_LIMIT));
+    }
+
+    #[test]
+    fn small_frame_send_failures_are_not_attributed_to_the_frame_size() {
+        //// Given
+        let mut limits = new_test_limits();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        //// When
+        let limit = limits.record_send_failure(peer, ConnectionId::new_unchecked(1), 100, now);
+
+        //// Then
+        assert_eq!(limit, None);
+        assert_eq!(limits.limit(&peer), None);
+    }
+
+    #[test]
+    fn suspected_limit_decays_without_send_failures() {
+        //// Given
+        let mut limits = new_test_limits();
+        let peer = PeerId::random();
+        let connection = ConnectionId::new_unchecked(1);
+        let now = Instant::now();
+
+        limits.record_send_failure(peer, connection, 4096, now);
+        limits.record_send_failure(peer, connection, 4096, now + Duration::from_secs(30));
+
+        //// When
+        let within_decay = limits.expire(now + Duration::from_secs(60));
+        let expired = limits.expire(now + Duration::from_secs(90));
+
+        //// Then
+        assert!(within_decay.is_empty());
+        assert_eq!(expired, vec![(peer, HashSet::from([connection]))]);
+        assert_eq!(limits.limit(&peer), None);
+    }
+}
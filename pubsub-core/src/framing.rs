@@ -8,3 +8,5 @@ mod message;
 
 mod control;
 mod subopts;
+#[cfg(test)]
+mod tests;
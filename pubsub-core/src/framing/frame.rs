@@ -1,8 +1,17 @@
+use bytes::Bytes;
+use prost::Message as _;
+
+use libp2p_pubsub_proto::pubsub::FrameProto;
+
+use crate::services::framing::FrameValidationError;
+
 use super::control::ControlMessage;
 use super::message::Message;
 use super::subopts::SubscriptionAction;
 
-#[derive(Clone, Debug)]
+/// A pubsub frame, the unit of exchange between peers. It carries subscription actions, messages
+/// and control messages.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Frame {
     /// The subscriptions to add or remove.
     pub(crate) subscriptions: Vec<SubscriptionAction>,
@@ -50,4 +59,43 @@ impl Frame {
             ..Self::empty()
         }
     }
+
+    /// Returns the frame subscription actions.
+    #[must_use]
+    pub fn subscriptions(&self) -> &[SubscriptionAction] {
+        &self.subscriptions
+    }
+
+    /// Returns the frame messages.
+    #[must_use]
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Returns the frame control messages.
+    #[must_use]
+    pub fn control(&self) -> &[ControlMessage] {
+        &self.control
+    }
+
+    /// Returns the frame encoded protobuf size in bytes.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        FrameProto::from(self.clone()).encoded_len()
+    }
+
+    /// Encodes the frame into its protobuf wire format.
+    #[must_use]
+    pub fn to_protobuf_bytes(&self) -> Bytes {
+        FrameProto::from(self.clone()).encode_to_vec().into()
+    }
+
+    /// Decodes a frame from its protobuf wire format.
+    ///
+    /// The decoded frame is validated as the frames received from the remote peers are, but the
+    /// decoding fails if any of the frame parts is invalid, instead of dropping it.
+    pub fn from_protobuf_bytes(bytes: Bytes) -> Result<Self, FrameValidationError> {
+        let proto = FrameProto::decode(bytes).map_err(|_| FrameValidationError::InvalidEncoding)?;
+        Self::try_from(proto)
+    }
 }
@@ -1,18 +1,35 @@
 use bytes::Bytes;
 use libp2p::identity::PeerId;
+use prost::Message as _;
 
 use libp2p_pubsub_proto::pubsub::MessageProto;
 
+use crate::message::Priority;
+use crate::services::framing::MessageValidationError;
 use crate::topic::TopicHash;
 
 /// A message that can be sent or received on a pubsub topic.
 ///
 /// This type is implemented as a wrapper around the protobuf message.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Debug)]
 pub struct Message {
     pub(crate) proto: MessageProto,
+    /// The message topic hash, equal to the protobuf message topic.
+    pub(crate) topic: TopicHash,
+    /// The message local scheduling priority. Not part of the protobuf message.
+    pub(crate) priority: Priority,
 }
 
+// The priority is local scheduling metadata, not part of the message.
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.proto == other.proto && self.topic == other.topic
+    }
+}
+
+// The protobuf message only holds byte and string fields.
+impl Eq for Message {}
+
 impl Message {
     /// Creates a new message.
     ///
@@ -27,12 +44,16 @@ impl Message {
             from: None,
             data: Some(data.into()),
             seqno: None,
-            topic: topic.into_string(),
+            topic: topic.as_str().to_owned(),
             signature: None,
             key: None,
         };
 
-        Self { proto }
+        Self {
+            proto,
+            topic,
+            priority: Priority::Normal,
+        }
     }
 
     /// Creates a new message with a sequence number.
@@ -72,6 +93,28 @@ impl Message {
         &self.proto
     }
 
+    /// Returns the message encoded protobuf size in bytes, without encoding it.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        self.proto.encoded_len()
+    }
+
+    /// Encodes the message into its protobuf wire format.
+    #[must_use]
+    pub fn to_protobuf_bytes(&self) -> Bytes {
+        self.proto.encode_to_vec().into()
+    }
+
+    /// Decodes a message from its protobuf wire format.
+    ///
+    /// The decoded message is validated and sanitized as the messages received from the remote
+    /// peers are, e.g., the empty optional fields are interpreted as not present.
+    pub fn from_protobuf_bytes(bytes: Bytes) -> Result<Self, MessageValidationError> {
+        let proto =
+            MessageProto::decode(bytes).map_err(|_| MessageValidationError::InvalidEncoding)?;
+        Self::try_from(proto)
+    }
+
     /// Returns the message author.
     ///
     /// > NOTE: Do not confuse with the node that forwarded the message.
@@ -111,7 +154,13 @@ impl Message {
     /// Returns the topic.
     #[must_use]
     pub fn topic(&self) -> TopicHash {
-        TopicHash::from_raw(&self.proto.topic)
+        self.topic.clone()
+    }
+
+    /// Set the message topic.
+    pub(crate) fn set_topic(&mut self, topic: TopicHash) {
+        self.proto.topic = topic.as_str().to_owned();
+        self.topic = topic;
     }
 
     /// Returns the topic as a string slice.
@@ -132,6 +181,8 @@ impl Message {
     }
 
     /// Returns the message key bytes when present.
+    ///
+    /// The returned buffer shares the received frame allocation, no copy is made.
     #[must_use]
     pub fn key(&self) -> Option<Bytes> {
         self.proto.key.clone()
@@ -141,6 +192,26 @@ impl Message {
     pub fn set_key(&mut self, key: Option<impl Into<Vec<u8>>>) {
         self.proto.key = key.map(|bytes| bytes.into().into());
     }
+
+    /// Sets the message key bytes, without copying them.
+    pub fn set_key_bytes(&mut self, key: Option<Bytes>) {
+        self.proto.key = key;
+    }
+
+    /// Returns the message local scheduling priority.
+    ///
+    /// The received messages are always of normal priority: the priority is not serialized on the
+    /// wire.
+    #[must_use]
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Sets the message local scheduling priority (see
+    /// [`Message::set_priority`](crate::Message::set_priority)).
+    pub fn set_priority(&mut self, priority: Priority) {
+        self.priority = priority;
+    }
 }
 
 impl AsRef<Message> for Message {
@@ -1,5 +1,6 @@
 use crate::topic::TopicHash;
 
+/// A topic subscription action exchanged between peers.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SubscriptionAction {
     /// Subscribe to a topic.
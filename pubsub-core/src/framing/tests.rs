@@ -0,0 +1,192 @@
+use assert_matches::assert_matches;
+use bytes::Bytes;
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_proto::pubsub::{ControlIHaveProto, ControlIWantProto};
+
+use crate::message_id::{default_message_id_fn, MessageId, MAX_WIRE_MESSAGE_ID_LEN};
+use crate::services::framing::{FrameValidationError, MessageValidationError};
+use crate::topic::TopicHash;
+
+use super::*;
+
+fn new_test_topic() -> TopicHash {
+    TopicHash::from_raw("/pubsub/2/it-pubsub-test")
+}
+
+/// Create a test message with all the optional fields present.
+fn new_test_message_with_all_fields() -> Message {
+    let mut message = Message::new_with_seq_no_and_from(
+        new_test_topic(),
+        b"test-payload".to_vec(),
+        42_u64.to_be_bytes(),
+        PeerId::random(),
+    );
+    message.set_signature(Some(b"test-signature".to_vec()));
+    message.set_key(Some(b"test-key".to_vec()));
+    message
+}
+
+#[test]
+fn message_without_optional_fields_round_trips() {
+    //// Given
+    let message = Message::new(new_test_topic(), b"test-payload".to_vec());
+
+    //// When
+    let bytes = message.to_protobuf_bytes();
+    let decoded = Message::from_protobuf_bytes(bytes.clone()).expect("valid message");
+
+    //// Then
+    assert_eq!(decoded, message);
+    assert_eq!(message.encoded_len(), bytes.len());
+    assert_eq!(decoded.author(), None);
+    assert_eq!(decoded.seqno(), None);
+}
+
+#[test]
+fn message_with_all_optional_fields_round_trips() {
+    //// Given
+    let message = new_test_message_with_all_fields();
+
+    //// When
+    let bytes = message.to_protobuf_bytes();
+    let decoded = Message::from_protobuf_bytes(bytes.clone()).expect("valid message");
+
+    //// Then
+    assert_eq!(decoded, message);
+    assert_eq!(message.encoded_len(), bytes.len());
+    assert_eq!(decoded.author(), message.author());
+    assert_eq!(decoded.signature(), message.signature());
+    assert_eq!(decoded.key(), message.key());
+}
+
+#[test]
+fn decoded_message_has_the_same_default_message_id() {
+    //// Given
+    let message = new_test_message_with_all_fields();
+
+    //// When
+    let decoded = Message::from_protobuf_bytes(message.to_protobuf_bytes()).expect("valid message");
+
+    //// Then
+    let original_id: MessageId = default_message_id_fn(None, &(&message).into());
+    let decoded_id: MessageId = default_message_id_fn(None, &(&decoded).into());
+    assert_eq!(decoded_id, original_id);
+}
+
+#[test]
+fn invalid_message_bytes_are_rejected() {
+    //// Given
+    let garbage = Bytes::from_static(&[0xff, 0xff, 0xff]);
+    let empty_topic = Message::new(TopicHash::from_raw(""), b"test-payload".to_vec());
+
+    //// When
+    let garbage_result = Message::from_protobuf_bytes(garbage);
+    let empty_topic_result = Message::from_protobuf_bytes(empty_topic.to_protobuf_bytes());
+
+    //// Then
+    assert_matches!(garbage_result, Err(MessageValidationError::InvalidEncoding));
+    assert_matches!(empty_topic_result, Err(MessageValidationError::EmptyTopic));
+}
+
+#[test]
+fn frame_round_trips() {
+    //// Given
+    let topic = new_test_topic();
+    let frame = Frame {
+        subscriptions: vec![
+            SubscriptionAction::Subscribe(topic.clone()),
+            SubscriptionAction::Unsubscribe(TopicHash::from_raw("/other/topic")),
+        ],
+        messages: vec![
+            Message::new(topic.clone(), b"test-payload".to_vec()),
+            new_test_message_with_all_fields(),
+        ],
+        control: vec![
+            ControlMessage::Graft(GraftControlMessage {
+                topic_hash: topic.clone(),
+            }),
+            ControlMessage::IHave(IHaveControlMessage {
+                topic_hash: topic,
+                message_ids: vec![MessageId::new(b"test-id".to_vec())],
+            }),
+        ],
+    };
+
+    //// When
+    let bytes = frame.to_protobuf_bytes();
+    let decoded = Frame::from_protobuf_bytes(bytes.clone()).expect("valid frame");
+
+    //// Then
+    assert_eq!(decoded, frame);
+    assert_eq!(frame.encoded_len(), bytes.len());
+}
+
+#[test]
+fn frame_with_an_invalid_part_is_rejected() {
+    //// Given
+    let empty_frame = Frame::empty();
+    let invalid_message_frame = Frame::new_with_messages([
+        Message::new(new_test_topic(), b"test-payload".to_vec()),
+        Message::new(TopicHash::from_raw(""), b"test-payload".to_vec()),
+    ]);
+
+    //// When
+    let empty_result = Frame::from_protobuf_bytes(empty_frame.to_protobuf_bytes());
+    let invalid_message_result =
+        Frame::from_protobuf_bytes(invalid_message_frame.to_protobuf_bytes());
+
+    //// Then
+    assert_matches!(empty_result, Err(FrameValidationError::EmptyFrame));
+    assert_matches!(
+        invalid_message_result,
+        Err(FrameValidationError::InvalidMessage(
+            MessageValidationError::EmptyTopic
+        ))
+    );
+}
+
+#[test]
+fn oversized_wire_message_ids_are_dropped() {
+    //// Given
+    let valid_id = Bytes::from(vec![0x42; MAX_WIRE_MESSAGE_ID_LEN]);
+    let oversized_id = Bytes::from(vec![0x42; MAX_WIRE_MESSAGE_ID_LEN + 1]);
+
+    let ihave = ControlIHaveProto {
+        topic_id: Some(new_test_topic().into_string()),
+        message_ids: vec![oversized_id.clone(), valid_id.clone()],
+    };
+    let iwant = ControlIWantProto {
+        message_ids: vec![valid_id.clone(), oversized_id.clone()],
+    };
+
+    //// When
+    let ihave = IHaveControlMessage::try_from(ihave).expect("valid IHAVE");
+    let iwant = IWantControlMessage::try_from(iwant).expect("valid IWANT");
+
+    //// Then
+    assert_eq!(ihave.message_ids, vec![MessageId::from(valid_id.clone())]);
+    assert_eq!(iwant.message_ids, vec![MessageId::from(valid_id)]);
+}
+
+#[test]
+fn control_messages_with_only_oversized_wire_message_ids_are_rejected() {
+    //// Given
+    let oversized_id = Bytes::from(vec![0x42; MAX_WIRE_MESSAGE_ID_LEN + 1]);
+
+    let ihave = ControlIHaveProto {
+        topic_id: Some(new_test_topic().into_string()),
+        message_ids: vec![oversized_id.clone()],
+    };
+    let iwant = ControlIWantProto {
+        message_ids: vec![oversized_id],
+    };
+
+    //// When
+    let ihave_result = IHaveControlMessage::try_from(ihave);
+    let iwant_result = IWantControlMessage::try_from(iwant);
+
+    //// Then
+    assert!(ihave_result.is_err(), "IHAVE should be rejected");
+    assert!(iwant_result.is_err(), "IWANT should be rejected");
+}
@@ -0,0 +1,245 @@
+//! Entry points for the `cargo-fuzz` targets in the workspace `fuzz/` directory, and the framing
+//! and received messages pipeline benchmarks.
+//!
+//! The framing services are crate-private. This module exposes thin wrappers around them
+//! so the fuzz targets and benchmarks exercise the same code paths as the behaviour does when
+//! processing frames received from remote peers.
+//!
+//! This module is only available with the `fuzzing` feature enabled and it is not part of the
+//! crate's public API.
+
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::{Bytes, BytesMut};
+use futures::task::noop_waker_ref;
+use libp2p::identity::PeerId;
+use libp2p::swarm::ConnectionId;
+use prost::Message as _;
+
+use libp2p_pubsub_common::service::{BufferedContext, ServiceContext};
+use libp2p_pubsub_proto::pubsub::FrameProto;
+
+use crate::config::Config;
+use crate::framing::{Frame, Message as FrameMessage};
+use crate::message::Message;
+use crate::services::framing::{
+    UpstreamFramingParams, UpstreamFramingService, UpstreamInEvent, UpstreamOutEvent,
+};
+use crate::services::message_cache::{
+    MessageCacheInEvent, MessageCacheMessageEvent, MessageCacheService,
+};
+use crate::services::message_id::{
+    MessageIdInEvent, MessageIdMessageEvent, MessageIdOutEvent, MessageIdService,
+};
+use crate::services::ordering::{OrderingInEvent, OrderingOutEvent, OrderingService};
+use crate::topic::TopicHash;
+
+/// The number of valid frame parts emitted by the upstream framing service.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessedFrame {
+    /// The number of valid messages.
+    pub messages: usize,
+    /// The number of valid subscription actions.
+    pub subscriptions: usize,
+    /// The number of valid control messages.
+    pub control: usize,
+}
+
+/// Decode, validate and process a raw frame as if it was received from a remote peer.
+///
+/// The valid messages are converted into the public [`Message`] type to exercise the accessors
+/// relying on the conversion invariants (e.g., a valid author peer id).
+pub fn process_raw_frame(frame: Bytes) -> ProcessedFrame {
+    process_raw_frame_with(UpstreamFramingService::default(), frame)
+}
+
+/// Same as [`process_raw_frame`], but the frame messages are validated in parallel if the frame
+/// carries more than `threshold` messages, and the `parallel` feature is enabled (see
+/// [`Config::parallel_validation_threshold`]).
+pub fn process_raw_frame_with_parallel_validation_threshold(
+    frame: Bytes,
+    threshold: usize,
+) -> ProcessedFrame {
+    let service = UpstreamFramingService::new(UpstreamFramingParams {
+        parallel_validation_threshold: threshold,
+        ..Default::default()
+    });
+    process_raw_frame_with(service, frame)
+}
+
+/// Decode, validate and process a raw frame with the given upstream framing service.
+fn process_raw_frame_with(service: UpstreamFramingService, frame: Bytes) -> ProcessedFrame {
+    let mut service = BufferedContext::new(service);
+    service.do_send(UpstreamInEvent::RawFrameReceived {
+        src: PeerId::random(),
+        connection: ConnectionId::new_unchecked(0),
+        frame,
+    });
+
+    let mut processed = ProcessedFrame::default();
+    let mut cx = Context::from_waker(noop_waker_ref());
+    while let Poll::Ready(event) = service.poll(&mut cx) {
+        match event {
+            UpstreamOutEvent::MessageReceived(ctx) => {
+                let _ = Message::from((**ctx.message()).clone());
+                processed.messages += 1;
+            }
+            UpstreamOutEvent::SubscriptionRequestReceived { .. } => {
+                processed.subscriptions += 1;
+            }
+            UpstreamOutEvent::ControlMessageReceived { .. } => {
+                processed.control += 1;
+            }
+            UpstreamOutEvent::ValidationFailed { .. }
+            | UpstreamOutEvent::InefficientFraming { .. } => {}
+        }
+    }
+
+    processed
+}
+
+/// An upstream framing service processing raw frames as if they were received from a remote peer.
+///
+/// Unlike [`process_raw_frame`], the service state (e.g., the interned topic hashes) is kept
+/// across the processed frames.
+pub struct UpstreamFraming {
+    service: BufferedContext<UpstreamFramingService>,
+    src: PeerId,
+}
+
+impl UpstreamFraming {
+    /// Creates a new upstream framing service interning up to `max_interned_topics` topic hashes.
+    pub fn new(max_interned_topics: usize) -> Self {
+        Self {
+            service: BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+                max_interned_topics,
+                ..Default::default()
+            })),
+            src: PeerId::random(),
+        }
+    }
+
+    /// Decode, validate and process a raw frame, and return the topics of the valid messages.
+    pub fn received_message_topics(&mut self, frame: Bytes) -> Vec<TopicHash> {
+        self.service.do_send(UpstreamInEvent::RawFrameReceived {
+            src: self.src,
+            connection: ConnectionId::new_unchecked(0),
+            frame,
+        });
+
+        let mut topics = Vec::new();
+        let mut cx = Context::from_waker(noop_waker_ref());
+        while let Poll::Ready(event) = self.service.poll(&mut cx) {
+            if let UpstreamOutEvent::MessageReceived(ctx) = event {
+                topics.push(ctx.topic().clone());
+            }
+        }
+
+        topics
+    }
+}
+
+/// The received messages pipeline: the upstream framing, message id, message cache and ordering
+/// services, wired as the behaviour does.
+///
+/// The frames are processed as if received from a remote peer, from their decoding to the
+/// messages release for delivery, without the connection handlers and the protocol router.
+pub struct ReceivedMessagePipeline {
+    framing: BufferedContext<UpstreamFramingService>,
+    message_id: BufferedContext<MessageIdService>,
+    message_cache: BufferedContext<MessageCacheService>,
+    ordering: BufferedContext<OrderingService>,
+    src: PeerId,
+}
+
+impl Default for ReceivedMessagePipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReceivedMessagePipeline {
+    /// Creates a new received messages pipeline with the default configuration.
+    pub fn new() -> Self {
+        let config = Config::default();
+        Self {
+            framing: BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+                message_ttl_clock_skew: config.message_ttl_clock_skew(),
+                ..Default::default()
+            })),
+            message_id: Default::default(),
+            message_cache: BufferedContext::new(MessageCacheService::new(
+                config.message_cache_capacity(),
+                config.message_cache_ttl(),
+                config.dedup_scope(),
+                config.max_duplicate_resends(),
+                config.history_length(),
+                config.heartbeat_interval(),
+                Duration::from_secs(0),
+            )),
+            ordering: BufferedContext::new(OrderingService::new(
+                config.ordering_max_delay(),
+                config.ordering_max_held_messages(),
+            )),
+            src: PeerId::random(),
+        }
+    }
+
+    /// Process a raw frame through the pipeline, and return the number of messages released for
+    /// delivery.
+    pub fn process(&mut self, frame: Bytes) -> usize {
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        self.framing.do_send(UpstreamInEvent::RawFrameReceived {
+            src: self.src,
+            connection: ConnectionId::new_unchecked(0),
+            frame,
+        });
+        while let Poll::Ready(event) = self.framing.poll(&mut cx) {
+            if let UpstreamOutEvent::MessageReceived(ctx) = event {
+                self.message_id.do_send(MessageIdInEvent::MessageEvent(
+                    MessageIdMessageEvent::Received(ctx),
+                ));
+            }
+        }
+
+        while let Poll::Ready(event) = self.message_id.poll(&mut cx) {
+            let MessageIdOutEvent::MessageReceived(ctx) = event else {
+                continue;
+            };
+
+            if self.message_cache.contains(ctx.topic(), ctx.message_id()) {
+                continue;
+            }
+
+            self.message_cache
+                .do_send(MessageCacheInEvent::MessageEvent(
+                    MessageCacheMessageEvent::MessageReceived(ctx.clone()),
+                ));
+            self.ordering.do_send(OrderingInEvent::MessageReceived(ctx));
+        }
+        while self.message_cache.poll(&mut cx).is_ready() {}
+
+        let mut released = 0;
+        while let Poll::Ready(event) = self.ordering.poll(&mut cx) {
+            if let OrderingOutEvent::MessageReleased(_) = event {
+                released += 1;
+            }
+        }
+
+        released
+    }
+}
+
+/// Encode the given messages into a single multi-message frame, as the downstream framing service
+/// does.
+pub fn encode_messages_frame(messages: impl IntoIterator<Item = FrameMessage>) -> Bytes {
+    let frame = FrameProto::from(Frame::new_with_messages(messages));
+
+    let mut bytes = BytesMut::with_capacity(frame.encoded_len());
+    frame
+        .encode(&mut bytes)
+        .expect("buffer has enough capacity");
+    bytes.freeze()
+}
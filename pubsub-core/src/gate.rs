@@ -0,0 +1,246 @@
+//! Connection gating (see [`Config::connection_gate`](crate::Config::connection_gate)).
+//!
+//! In a private deployment, the unknown peers must be rejected at the earliest point, before they
+//! can negotiate a pubsub substream. The connection gate is consulted when a connection is
+//! established, before its connection handler is created, and decides whether the connection is
+//! denied, used for pubsub, or kept for the other behaviours only.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+use libp2p::core::Endpoint;
+use libp2p::identity::PeerId;
+use libp2p::swarm::ConnectionId;
+use libp2p::Multiaddr;
+
+/// The connection gate decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GateDecision {
+    /// The connection is used for pubsub.
+    Allow,
+    /// The connection is denied, and closed by the swarm.
+    Deny,
+    /// The connection is kept for the other behaviours, but pubsub ignores it: no substream is
+    /// opened, the inbound substreams are dropped, and the connection is not kept alive by pubsub.
+    AllowDisabled,
+}
+
+/// The established connection the connection gate is consulted for.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ConnectionGateContext {
+    /// The connection id.
+    pub connection_id: ConnectionId,
+    /// The connection direction: [`Endpoint::Dialer`] if the connection is outbound,
+    /// [`Endpoint::Listener`] if inbound.
+    pub direction: Endpoint,
+    /// The local address the connection was accepted on, if inbound.
+    pub local_addr: Option<Multiaddr>,
+    /// The remote peer address.
+    pub remote_addr: Multiaddr,
+}
+
+impl ConnectionGateContext {
+    /// Create a new inbound connection gate context.
+    #[must_use]
+    pub fn new_inbound(
+        connection_id: ConnectionId,
+        local_addr: Multiaddr,
+        remote_addr: Multiaddr,
+    ) -> Self {
+        Self {
+            connection_id,
+            direction: Endpoint::Listener,
+            local_addr: Some(local_addr),
+            remote_addr,
+        }
+    }
+
+    /// Create a new outbound connection gate context.
+    #[must_use]
+    pub fn new_outbound(connection_id: ConnectionId, remote_addr: Multiaddr) -> Self {
+        Self {
+            connection_id,
+            direction: Endpoint::Dialer,
+            local_addr: None,
+            remote_addr,
+        }
+    }
+}
+
+/// The error a connection denied by the connection gate is refused to the swarm with (see
+/// [`GateDecision::Deny`]).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("connection with peer {peer} denied by the connection gate")]
+pub struct ConnectionGateDenied {
+    /// The remote peer.
+    pub peer: PeerId,
+}
+
+// NOTE: Use `trait_set` crate as `trait_alias` is not yet stable.
+//       https://github.com/rust-lang/rust/issues/41517
+trait_set::trait_set! {
+    /// The connection gate function type.
+    ///
+    /// Returns the [`GateDecision`] for the connection with the peer. The gate is called once per
+    /// established connection.
+    pub trait ConnectionGate = Fn(&PeerId, ConnectionGateContext) -> GateDecision;
+}
+
+/// A shared connection gate, as set in the configuration.
+#[derive(Clone)]
+pub(crate) struct SharedConnectionGate(pub(crate) Rc<dyn ConnectionGate<Output = GateDecision>>);
+
+impl fmt::Debug for SharedConnectionGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConnectionGate(..)")
+    }
+}
+
+/// A connection gate allowing the connections with the listed peers only.
+///
+/// The connections with the other peers are denied, unless configured otherwise (see
+/// [`AllowList::otherwise`]).
+///
+/// ```
+/// # use libp2p::identity::PeerId;
+/// # use libp2p_pubsub_core::{AllowList, ConfigBuilder};
+/// let member = PeerId::random();
+///
+/// let config = ConfigBuilder::default()
+///     .connection_gate(Some(AllowList::new([member]).into_gate()))
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct AllowList {
+    /// The allowed peers.
+    peers: HashSet<PeerId>,
+    /// The decision for the connections with the peers not listed.
+    otherwise: GateDecision,
+}
+
+impl AllowList {
+    /// Create a new allowlist with the given peers. The connections with the other peers are
+    /// denied.
+    #[must_use]
+    pub fn new(peers: impl IntoIterator<Item = PeerId>) -> Self {
+        Self {
+            peers: peers.into_iter().collect(),
+            otherwise: GateDecision::Deny,
+        }
+    }
+
+    /// Add the peer to the allowlist.
+    pub fn allow(&mut self, peer: PeerId) -> &mut Self {
+        self.peers.insert(peer);
+        self
+    }
+
+    /// The decision for the connections with the peers not listed, e.g.,
+    /// [`GateDecision::AllowDisabled`] to keep them for the other behaviours.
+    ///
+    /// Default is [`GateDecision::Deny`].
+    pub fn otherwise(&mut self, decision: GateDecision) -> &mut Self {
+        self.otherwise = decision;
+        self
+    }
+
+    /// Returns the gate decision for the connection with the peer.
+    #[must_use]
+    pub fn decide(&self, peer: &PeerId) -> GateDecision {
+        if self.peers.contains(peer) {
+            GateDecision::Allow
+        } else {
+            self.otherwise
+        }
+    }
+
+    /// Convert the allowlist into a connection gate (see
+    /// [`ConfigBuilder::connection_gate`](crate::ConfigBuilder::connection_gate)).
+    #[must_use]
+    pub fn into_gate(self) -> Rc<dyn ConnectionGate<Output = GateDecision>> {
+        Rc::new(move |peer: &PeerId, _: ConnectionGateContext| self.decide(peer))
+    }
+}
+
+/// A connection gate denying the connections with the listed peers. The connections with the
+/// other peers are allowed.
+#[derive(Debug, Clone, Default)]
+pub struct DenyList {
+    /// The denied peers.
+    peers: HashSet<PeerId>,
+}
+
+impl DenyList {
+    /// Create a new denylist with the given peers.
+    #[must_use]
+    pub fn new(peers: impl IntoIterator<Item = PeerId>) -> Self {
+        Self {
+            peers: peers.into_iter().collect(),
+        }
+    }
+
+    /// Add the peer to the denylist.
+    pub fn deny(&mut self, peer: PeerId) -> &mut Self {
+        self.peers.insert(peer);
+        self
+    }
+
+    /// Returns the gate decision for the connection with the peer.
+    #[must_use]
+    pub fn decide(&self, peer: &PeerId) -> GateDecision {
+        if self.peers.contains(peer) {
+            GateDecision::Deny
+        } else {
+            GateDecision::Allow
+        }
+    }
+
+    /// Convert the denylist into a connection gate (see
+    /// [`ConfigBuilder::connection_gate`](crate::ConfigBuilder::connection_gate)).
+    #[must_use]
+    pub fn into_gate(self) -> Rc<dyn ConnectionGate<Output = GateDecision>> {
+        Rc::new(move |peer: &PeerId, _: ConnectionGateContext| self.decide(peer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowlist_denies_the_peers_not_listed() {
+        //// Given
+        let (member, stranger) = (PeerId::random(), PeerId::random());
+
+        let allowlist = AllowList::new([member]);
+        let mut disabling_allowlist = allowlist.clone();
+        disabling_allowlist.otherwise(GateDecision::AllowDisabled);
+
+        //// Then
+        assert_eq!(allowlist.decide(&member), GateDecision::Allow);
+        assert_eq!(allowlist.decide(&stranger), GateDecision::Deny);
+        assert_eq!(disabling_allowlist.decide(&member), GateDecision::Allow);
+        assert_eq!(
+            disabling_allowlist.decide(&stranger),
+            GateDecision::AllowDisabled
+        );
+    }
+
+    #[test]
+    fn denylist_denies_the_listed_peers() {
+        //// Given
+        let (member, stranger) = (PeerId::random(), PeerId::random());
+        let context =
+            ConnectionGateContext::new_outbound(ConnectionId::new_unchecked(0), Multiaddr::empty());
+
+        //// When
+        let gate = DenyList::new([stranger]).into_gate();
+
+        //// Then
+        assert_eq!(gate(&member, context.clone()), GateDecision::Allow);
+        assert_eq!(gate(&stranger, context), GateDecision::Deny);
+    }
+}
@@ -0,0 +1,39 @@
+use libp2p::identity::{Keypair, PeerId};
+
+/// The local node identity, determining the authorship of the messages it publishes.
+///
+/// The published messages without a [`from`](crate::Message::from) field are stamped with the
+/// identity author, if any. See [`Behaviour::new_with_identity`](crate::Behaviour::new_with_identity).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Identity {
+    /// The local node key-pair. The messages are authored by the key-pair peer id.
+    ///
+    /// The key-pair is kept to sign the published messages.
+    Keypair(Keypair),
+    /// The local node peer id. The messages are authored by the peer id.
+    PeerId(PeerId),
+    /// The local node, identified by the given peer id, publishes anonymous messages. The
+    /// messages are not stamped with an author.
+    Anonymous(PeerId),
+}
+
+impl Identity {
+    /// The local node peer id.
+    #[must_use]
+    pub fn peer_id(&self) -> PeerId {
+        match self {
+            Identity::Keypair(keypair) => keypair.public().to_peer_id(),
+            Identity::PeerId(peer_id) | Identity::Anonymous(peer_id) => *peer_id,
+        }
+    }
+
+    /// The published messages author, if any.
+    #[must_use]
+    pub fn author(&self) -> Option<PeerId> {
+        match self {
+            Identity::Keypair(_) | Identity::PeerId(_) => Some(self.peer_id()),
+            Identity::Anonymous(_) => None,
+        }
+    }
+}
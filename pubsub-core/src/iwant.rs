@@ -0,0 +1,168 @@
+//! The IWANT requests serving quotas (see
+//! [`Config::max_iwant_served_per_peer_per_heartbeat`](
+//! crate::Config::max_iwant_served_per_peer_per_heartbeat)).
+
+use std::collections::HashMap;
+
+use libp2p::identity::PeerId;
+
+use crate::message_id::MessageId;
+
+/// Tracks the messages served to the peers' IWANT requests, to bound the number of messages served
+/// to each peer per heartbeat interval, and the number of times the same message is served to the
+/// same peer.
+#[derive(Debug)]
+pub(crate) struct IWantQuotas {
+    /// The maximum number of messages served to a peer per heartbeat interval.
+    max_per_peer_per_heartbeat: usize,
+    /// The maximum number of times the same message is served to the same peer.
+    max_per_message: usize,
+    /// The number of messages served to each peer during the current heartbeat interval.
+    served_this_heartbeat: HashMap<PeerId, usize>,
+    /// The number of times each message was served, by message id and peer.
+    ///
+    /// The entries of the messages no longer cached are pruned on every heartbeat.
+    served_per_message: HashMap<MessageId, HashMap<PeerId, usize>>,
+    /// The number of messages not served, as the requesting peer exceeded its quotas.
+    unserved: u64,
+}
+
+impl IWantQuotas {
+    /// Create new IWANT serving quotas.
+    pub(crate) fn new(max_per_peer_per_heartbeat: usize, max_per_message: usize) -> Self {
+        Self {
+            max_per_peer_per_heartbeat,
+            max_per_message,
+            served_this_heartbeat: Default::default(),
+            served_per_message: Default::default(),
+            unserved: 0,
+        }
+    }
+
+    /// The number of messages not served, as the requesting peer exceeded its quotas.
+    pub(crate) fn unserved(&self) -> u64 {
+        self.unserved
+    }
+
+    /// Check the quotas of the peer requesting the message, and account the message as served if
+    /// they allow it.
+    ///
+    /// Returns `false`, and accounts the message as unserved, if the peer exhausted its heartbeat
+    /// quota, or the message was already served to the peer the maximum number of times.
+    pub(crate) fn try_serve(&mut self, peer: PeerId, message_id: &MessageId) -> bool {
+        let served = self.served_this_heartbeat.entry(peer).or_default();
+        if *served >= self.max_per_peer_per_heartbeat {
+            self.unserved += 1;
+            return false;
+        }
+
+        let serves = self
+            .served_per_message
+            .entry(message_id.clone())
+            .or_default()
+            .entry(peer)
+            .or_default();
+        if *serves >= self.max_per_message {
+            self.unserved += 1;
+            return false;
+        }
+
+        *serves += 1;
+        *served += 1;
+        true
+    }
+
+    /// Reset the peers' heartbeat quotas, and drop the serve counts of the messages no longer
+    /// cached.
+    pub(crate) fn heartbeat(&mut self, is_cached: impl Fn(&MessageId) -> bool) {
+        self.served_this_heartbeat.clear();
+        self.served_per_message
+            .retain(|message_id, _| is_cached(message_id));
+    }
+
+    /// Drop the disconnected peer's serve counts.
+    pub(crate) fn peer_disconnected(&mut self, peer: &PeerId) {
+        self.served_this_heartbeat.remove(peer);
+        self.served_per_message.retain(|_, peers| {
+            peers.remove(peer);
+            !peers.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_message_id(id: u8) -> MessageId {
+        MessageId::new(vec![id])
+    }
+
+    #[test]
+    fn requests_are_served_within_the_heartbeat_quota() {
+        //// Given
+        let peer = PeerId::random();
+        let other_peer = PeerId::random();
+        let mut quotas = IWantQuotas::new(2, 3);
+
+        //// When
+        let served = (0..4)
+            .map(|id| quotas.try_serve(peer, &new_test_message_id(id)))
+            .collect::<Vec<_>>();
+        let other_served = quotas.try_serve(other_peer, &new_test_message_id(0));
+
+        quotas.heartbeat(|_| true);
+        let served_next_heartbeat = quotas.try_serve(peer, &new_test_message_id(2));
+
+        //// Then
+        assert_eq!(
+            served,
+            vec![true, true, false, false],
+            "The requests exceeding the quota should not be served"
+        );
+        assert!(other_served, "The quota should be per peer");
+        assert!(
+            served_next_heartbeat,
+            "The quota should be reset on heartbeat"
+        );
+    }
+
+    #[test]
+    fn same_message_is_served_to_the_same_peer_at_most_max_times() {
+        //// Given
+        let peer = PeerId::random();
+        let other_peer = PeerId::random();
+        let message_id = new_test_message_id(0);
+        let mut quotas = IWantQuotas::new(16, 3);
+
+        //// When
+        let mut served = Vec::new();
+        for _ in 0..5 {
+            served.push(quotas.try_serve(peer, &message_id));
+            quotas.heartbeat(|_| true);
+        }
+        let other_served = quotas.try_serve(other_peer, &message_id);
+
+        //// Then
+        assert_eq!(served, vec![true, true, true, false, false]);
+        assert!(other_served, "The repeat cap should be per peer");
+    }
+
+    #[test]
+    fn serve_counts_are_dropped_once_the_message_is_not_cached() {
+        //// Given
+        let peer = PeerId::random();
+        let message_id = new_test_message_id(0);
+        let mut quotas = IWantQuotas::new(16, 1);
+        quotas.try_serve(peer, &message_id);
+
+        //// When
+        let served_while_cached = quotas.try_serve(peer, &message_id);
+        quotas.heartbeat(|_| false);
+        let served_after_eviction = quotas.try_serve(peer, &message_id);
+
+        //// Then
+        assert!(!served_while_cached);
+        assert!(served_after_eviction);
+    }
+}
@@ -1,21 +1,111 @@
+//! # API stability
+//!
+//! The public event and error enums, e.g., [`Event`], [`MisbehaviourReason`] and the
+//! [protocol router events](protocol::ProtocolRouterInEvent), are `#[non_exhaustive]`: new
+//! variants may be added in minor releases, so downstream matches must include a wildcard arm. The
+//! struct variants and structs with several fields are also `#[non_exhaustive]` and provide
+//! associated constructors (e.g., [`Event::message_received`]) so that adding a field is not a
+//! breaking change.
+
+pub use activity::TopicActivity;
+pub use authorization::{StaticAllowlist, TopicAction, TopicAuthorizer};
+pub use backoff::{BackoffTracker, DEFAULT_PRUNE_BACKOFF};
 pub use behaviour::Behaviour;
-pub use config::Config;
-pub use event::Event;
-pub use framing::Message as FrameMessage;
-pub use message::Message;
-pub use message_id::{default_message_id_fn, MessageId, MessageIdFn, MessageRef};
-pub use subscription::{Subscription, SubscriptionBuilder};
-pub use topic::{Hasher, IdentTopic, IdentityHash, Sha256Hash, Sha256Topic, Topic, TopicHash};
+pub use builder::{BehaviourBuilder, BuildError};
+#[cfg(feature = "chaos")]
+pub use chaos::{ChaosCounters, ChaosDirection, ChaosPolicy, ChaosStats};
+pub use config::{Config, ConfigBuilder, ConfigDiff};
+pub use consumer::{ConsumerHandle, ConsumerTag};
+pub use dead_letter::{DeadLetter, DeadLetterStage};
+pub use delivery::DeliveryMode;
+pub use drops::DropStats;
+pub use egress::{BytesPerSecond, EgressStats};
+pub use event::{DisabledReason, Event, MisbehaviourReason};
+pub use fanout::ForwardFanout;
+pub use flush::{FlushId, FlushReport};
+pub use forward::ForwardError;
+pub use framing::{
+    ControlMessage, Frame, GraftControlMessage, IHaveControlMessage, IWantControlMessage,
+    Message as FrameMessage, PruneControlMessage, SubscriptionAction,
+};
+pub use gate::{
+    AllowList, ConnectionGate, ConnectionGateContext, ConnectionGateDenied, DenyList, GateDecision,
+};
+pub use identity::Identity;
+pub use message::{Message, Priority};
+pub use message_id::{
+    default_message_id_fn, sha256_message_id_fn, DedupScope, MessageId, MessageIdFn,
+    MessageIdFnError, MessageRef, ParseMessageIdError, MAX_WIRE_MESSAGE_ID_LEN,
+};
+pub use pending::PendingCounts;
+pub use preflight::{PreflightCode, PreflightWarning};
+pub use provenance::{Provenance, ProvenanceStats};
+pub use publish::{PublishError, PublishReceipt};
+pub use sampling::{FrameDirection, SampledFrame, SamplingConfig};
+pub use services::framing::{
+    FrameFailureClass, FrameLimit, FrameLimitPolicy, FrameValidationError, MessageValidationError,
+    PeerFramingStats, SubOptsValidationError, FRAME_CHECKSUM_FIELD_TAG,
+};
+pub use services::message_cache::DedupPersistenceConfig;
+pub use services::subscriptions::ChurnStats;
+pub use subscription::{
+    PeerSubscriptionInfo, Subscription, SubscriptionBuilder, SubscriptionError, SubscriptionInfo,
+};
+pub use topic::{
+    Hasher, IdentTopic, IdentityHash, Sha256Hash, Sha256Topic, Topic, TopicHash, TopicValidation,
+    TopicValidationError,
+};
+pub use topology::{PeerTopology, TopologySnapshot};
+pub use traffic::{TopicTraffic, MESSAGE_SIZE_BUCKETS};
 
+mod activity;
+mod authorization;
+mod backoff;
 mod behaviour;
+mod builder;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod chunk;
 mod config;
+mod confirmation;
 mod conn_handler;
+mod consumer;
+mod dead_letter;
+mod delivery;
+mod dispatch;
+mod drops;
+mod egress;
 mod event;
+mod fanout;
+mod flush;
+mod forward;
+mod frame_limit;
 mod framing;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+mod gate;
+mod identity;
+mod iwant;
+mod lifecycle;
 mod message;
 mod message_id;
+mod pending;
+mod preflight;
 pub mod protocol;
+mod provenance;
+mod publish;
+mod refresh;
+pub mod reqres;
+mod sampling;
+mod scheduler;
+mod send_health;
 mod services;
+mod silence;
 mod subscription;
 mod topic;
+mod topology;
+mod traffic;
+mod ttl;
 pub mod upgrade;
+mod warmup;
@@ -0,0 +1,253 @@
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use libp2p::identity::PeerId;
+
+use crate::framing::Message as FrameMessage;
+use crate::message_id::MessageId;
+use crate::topic::TopicHash;
+
+/// The number of message lifecycle stages.
+const STAGES_COUNT: usize = 5;
+
+/// A message lifecycle stage, following the message decoding or publication.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MessageStage {
+    /// The message was checked against the message cache, and found not seen before.
+    Deduplicated = 0,
+    /// The message was delivered to the application.
+    Delivered = 1,
+    /// The message was handed to the protocol router.
+    Routed = 2,
+    /// The message was handed to the framing service, to be sent to the destination peers.
+    Forwarded = 3,
+    /// The message was encoded into a frame.
+    Framed = 4,
+}
+
+/// The lifecycle context of a message, travelling with the message through the behaviour
+/// services.
+///
+/// The context holds the message lifecycle span, with the message topic and id as fields, and the
+/// time each stage was reached. Every stage reached is recorded as an event of the span, with the
+/// latency since the message was decoded, or published. The span is a `DEBUG` level span, so it
+/// is disabled, and cheap, unless a subscriber is interested in it.
+#[derive(Debug, Clone)]
+pub(crate) struct MessageContext {
+    /// The time the message was decoded, or published.
+    received_at: Instant,
+    /// The time each stage was reached, by stage.
+    stages: [Option<Instant>; STAGES_COUNT],
+    /// The message lifecycle span.
+    span: tracing::Span,
+}
+
+impl MessageContext {
+    /// Create the lifecycle context of a message received from the `src` peer, as it is decoded.
+    pub(crate) fn received(src: &PeerId, topic: &TopicHash) -> Self {
+        let span = tracing::debug_span!(
+            "message",
+            kind = "received",
+            %src,
+            %topic,
+            message_id = tracing::field::Empty
+        );
+        Self::new(span)
+    }
+
+    /// Create the lifecycle context of a message published by the local node.
+    pub(crate) fn published(topic: &TopicHash) -> Self {
+        let span = tracing::debug_span!(
+            "message",
+            kind = "published",
+            %topic,
+            message_id = tracing::field::Empty
+        );
+        Self::new(span)
+    }
+
+    /// Create the lifecycle context of a message forwarded on behalf of the application.
+    pub(crate) fn forwarded(topic: &TopicHash) -> Self {
+        let span = tracing::debug_span!(
+            "message",
+            kind = "forwarded",
+            %topic,
+            message_id = tracing::field::Empty
+        );
+        Self::new(span)
+    }
+
+    fn new(span: tracing::Span) -> Self {
+        Self {
+            received_at: Instant::now(),
+            stages: [None; STAGES_COUNT],
+            span,
+        }
+    }
+
+    /// The time the message was decoded, or published.
+    #[cfg(test)]
+    pub(crate) fn received_at(&self) -> Instant {
+        self.received_at
+    }
+
+    /// The time the given stage was reached, if reached.
+    #[cfg(test)]
+    pub(crate) fn stage_at(&self, stage: MessageStage) -> Option<Instant> {
+        self.stages[stage as usize]
+    }
+
+    /// Record the message id in the lifecycle span, once computed.
+    pub(crate) fn record_message_id(&self, message_id: &MessageId) {
+        self.span
+            .record("message_id", tracing::field::display(message_id));
+    }
+
+    /// Record that the message reached the given stage.
+    ///
+    /// Returns the latency since the message was decoded, or published.
+    pub(crate) fn record_stage(&mut self, stage: MessageStage) -> Duration {
+        let now = Instant::now();
+        self.stages[stage as usize] = Some(now);
+
+        let latency = now.saturating_duration_since(self.received_at);
+        tracing::trace!(parent: &self.span, ?stage, ?latency, "Message stage reached");
+        latency
+    }
+}
+
+/// A message received from a remote peer, travelling through the behaviour services.
+///
+/// The context is created once, as the message is decoded by the upstream framing service, and
+/// is moved from service to service down the received messages pipeline, instead of every service
+/// event re-wrapping the message, its source and its id. The message id is set by the message id
+/// service.
+#[derive(Debug, Clone)]
+pub(crate) struct ReceivedMessageCtx {
+    /// The peer that propagated the message.
+    src: PeerId,
+    /// The received message.
+    message: Rc<FrameMessage>,
+    /// The message id. `None` until computed by the message id service.
+    message_id: Option<MessageId>,
+    /// The message lifecycle context, started as the message is decoded.
+    lifecycle: MessageContext,
+}
+
+impl ReceivedMessageCtx {
+    /// Create the context of a message received from the `src` peer, as it is decoded.
+    pub(crate) fn new(src: PeerId, message: FrameMessage) -> Self {
+        let lifecycle = MessageContext::received(&src, &message.topic);
+        Self {
+            src,
+            message: Rc::new(message),
+            message_id: None,
+            lifecycle,
+        }
+    }
+
+    /// The peer that propagated the message.
+    pub(crate) fn src(&self) -> PeerId {
+        self.src
+    }
+
+    /// The received message.
+    pub(crate) fn message(&self) -> &Rc<FrameMessage> {
+        &self.message
+    }
+
+    /// The received message topic.
+    pub(crate) fn topic(&self) -> &TopicHash {
+        &self.message.topic
+    }
+
+    /// Rewrite the received message topic, e.g., to the local subscription topic the message
+    /// topic is an alias of.
+    pub(crate) fn set_topic(&mut self, topic: TopicHash) {
+        Rc::make_mut(&mut self.message).set_topic(topic);
+    }
+
+    /// The message id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the message id was not computed yet, i.e., before the message id service.
+    pub(crate) fn message_id(&self) -> &MessageId {
+        self.message_id
+            .as_ref()
+            .expect("message id computed by the message id service")
+    }
+
+    /// Set the message id, once computed, and record it in the lifecycle span.
+    pub(crate) fn set_message_id(&mut self, message_id: MessageId) {
+        self.lifecycle.record_message_id(&message_id);
+        self.message_id = Some(message_id);
+    }
+
+    /// Record that the message reached the given stage.
+    ///
+    /// Returns the latency since the message was decoded.
+    pub(crate) fn record_stage(&mut self, stage: MessageStage) -> Duration {
+        self.lifecycle.record_stage(stage)
+    }
+
+    /// Split the context into the message source, the message, its id and its lifecycle context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the message id was not computed yet, i.e., before the message id service.
+    pub(crate) fn into_parts(self) -> (PeerId, Rc<FrameMessage>, MessageId, MessageContext) {
+        let message_id = self
+            .message_id
+            .expect("message id computed by the message id service");
+        (self.src, self.message, message_id, self.lifecycle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_timestamps_are_monotonic() {
+        //// Given
+        let topic = TopicHash::from_raw("test-topic");
+        let mut context = MessageContext::received(&PeerId::random(), &topic);
+
+        //// When
+        let stages = [
+            MessageStage::Deduplicated,
+            MessageStage::Delivered,
+            MessageStage::Routed,
+            MessageStage::Forwarded,
+            MessageStage::Framed,
+        ];
+        let latencies = stages.map(|stage| context.record_stage(stage));
+
+        //// Then
+        let mut last = context.received_at();
+        for stage in stages {
+            let reached_at = context.stage_at(stage).expect("stage to be reached");
+            assert!(
+                reached_at >= last,
+                "{stage:?} reached before the previous stage"
+            );
+            last = reached_at;
+        }
+        assert!(latencies.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn not_reached_stages_have_no_timestamp() {
+        //// Given
+        let topic = TopicHash::from_raw("test-topic");
+        let mut context = MessageContext::published(&topic);
+
+        //// When
+        context.record_stage(MessageStage::Routed);
+
+        //// Then
+        assert!(context.stage_at(MessageStage::Routed).is_some());
+        assert!(context.stage_at(MessageStage::Delivered).is_none());
+    }
+}
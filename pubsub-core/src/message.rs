@@ -3,6 +3,8 @@
 //!
 //! This module contains the public API type of a pubsub message.
 
+use std::time::Duration;
+
 use bytes::Bytes;
 use libp2p::identity::PeerId;
 
@@ -22,7 +24,17 @@ pub struct Message {
     /// The signature of this message.
     pub signature: Option<Bytes>,
     /// The key of this message.
+    ///
+    /// The public key the message signature is verified with, e.g., an out-of-band application
+    /// key. The key is propagated untouched to the receivers (see [`Message::set_key`]).
     pub key: Option<Bytes>,
+    /// The time-to-live of this message.
+    ///
+    /// When publishing, the message expires once this time has elapsed (see
+    /// [`Message::set_ttl`]). On received messages, this is the remaining time-to-live.
+    pub ttl: Option<Duration>,
+    /// The local scheduling priority of this message (see [`Message::set_priority`]).
+    pub priority: Priority,
 }
 
 impl Message {
@@ -36,6 +48,8 @@ impl Message {
             topic: topic.into(),
             signature: None,
             key: None,
+            ttl: None,
+            priority: Priority::Normal,
         }
     }
 
@@ -53,6 +67,58 @@ impl Message {
             topic: topic.into(),
             signature: None,
             key: None,
+            ttl: None,
+            priority: Priority::Normal,
         }
     }
+
+    /// Sets the message key.
+    ///
+    /// The key is neither interpreted nor verified by the pubsub stack, and it is not required to
+    /// be the author libp2p identity key. The receivers reject the messages whose key is larger
+    /// than their [maximum key size](crate::Config::max_key_size).
+    pub fn set_key(&mut self, key: impl Into<Bytes>) -> &mut Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Sets the message time-to-live.
+    ///
+    /// On publish, the message absolute expiry time is stamped into a small envelope wrapping the
+    /// message payload. The expired messages are neither delivered to the application nor
+    /// forwarded by the nodes supporting message expiry. Other nodes see the envelope as part of
+    /// the message payload.
+    pub fn set_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the message local scheduling priority.
+    ///
+    /// The high priority messages are sent ahead of the normal priority messages queued toward
+    /// the same peer, and they are never held back by the publish batch window (see
+    /// [`Config::publish_batch_window`](crate::Config::publish_batch_window)). This is useful for
+    /// small control-plane messages that must not be delayed behind bulk traffic on congested
+    /// links.
+    ///
+    /// The priority is not serialized on the wire: it is not propagated to the other nodes, which
+    /// forward the message with the priority of its topic (see
+    /// [`SubscriptionBuilder::priority`](crate::SubscriptionBuilder::priority)).
+    pub fn set_priority(&mut self, priority: Priority) -> &mut Self {
+        self.priority = priority;
+        self
+    }
+}
+
+/// The local scheduling priority of a message.
+///
+/// The priority only orders the messages queued on the local node. It is not serialized on the
+/// wire, so it is not propagated to the other nodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// The message is sent in order with the other normal priority messages.
+    #[default]
+    Normal,
+    /// The message is sent ahead of the normal priority messages, and never batched.
+    High,
 }
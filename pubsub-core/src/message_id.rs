@@ -1,12 +1,83 @@
+//! Message identification.
+//!
+//! Every pubsub message is identified by a [`MessageId`] computed by a [`MessageIdFn`] from the
+//! message contents (see [`MessageRef`]). The message id is used to deduplicate messages and, by
+//! the protocols that support it, to advertise and request messages to and from other peers.
+//!
+//! Two built-in message id functions are provided:
+//!
+//!  - [`default_message_id_fn`]: The default message id function as defined in the libp2p pubsub
+//!    spec and implemented by go-libp2p's `DefaultMsgIdFn`. The message id is the concatenation of
+//!    the raw bytes of the message `from` field (the author's peer id in its binary multihash
+//!    representation, **not** the base58 string) and the raw bytes of the message `seqno` field:
+//!
+//!    ```text
+//!    message_id = from || seqno
+//!    ```
+//!
+//!    Absent fields are treated as empty byte sequences. This function is only suitable for signed
+//!    (or, at least, authored) messages carrying a sequence number.
+//!
+//!  - [`sha256_message_id_fn`]: A content-addressed message id function for networks publishing
+//!    anonymous messages (i.e., messages without `from` and `seqno` fields). The message id is the
+//!    SHA-256 digest of the message `data` field:
+//!
+//!    ```text
+//!    message_id = sha256(data)
+//!    ```
+//!
+//! Both algorithms are considered stable: the message id computed for a given message will not
+//! change across releases, so the ids can be safely persisted and shared with other libp2p
+//! implementations.
+//!
+//! The message id function can be selected per topic via
+//! [`SubscriptionBuilder::message_id_fn`](crate::SubscriptionBuilder::message_id_fn), or for all
+//! the topics without a specific message id function via
+//! [`ConfigBuilder::default_message_id_fn`](crate::ConfigBuilder::default_message_id_fn).
+//!
+//! Message ids are variable-length byte sequences, as a custom message id function can return
+//! ids of any length. The ids received from the network in the protocol control messages are
+//! capped to [`MAX_WIRE_MESSAGE_ID_LEN`] bytes.
+//!
+//! A topic-specific message id function must return a non-empty id of at most
+//! [`MAX_WIRE_MESSAGE_ID_LEN`] bytes, and must not panic. Otherwise, publishing the message fails
+//! with a [`PublishError::MessageIdComputation`](crate::PublishError::MessageIdComputation)
+//! error, and the id of a received message is computed with the default message id function
+//! instead (see [`Behaviour::message_id_fallbacks`](crate::Behaviour::message_id_fallbacks)).
+//! The panics are only caught if the crate is built with `panic = "unwind"`.
+//!
+//! The seen messages are deduplicated by message id. By default, the deduplication is global: a
+//! message is dropped as a duplicate if a message with the same id was seen on any topic. A
+//! message id function not covering the message topic, as [`sha256_message_id_fn`] or a custom
+//! function hashing only the payload, computes the same id for the same payload published to
+//! different topics. Set the [`DedupScope::PerTopic`] deduplication scope (see
+//! [`ConfigBuilder::dedup_scope`](crate::ConfigBuilder::dedup_scope)) to deduplicate the messages
+//! per topic instead.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+
 use bytes::Bytes;
 use libp2p::identity::PeerId;
+use sha2::{Digest, Sha256};
 use smallvec::SmallVec;
 
 use crate::topic::TopicHash;
 
+/// The maximum length, in bytes, of the message ids received from the network in the protocol
+/// control messages (e.g., IHAVE and IWANT). The longer ids are dropped on decoding, as their
+/// length is controlled by the remote peer.
+pub const MAX_WIRE_MESSAGE_ID_LEN: usize = 128;
+
 /// The message id is used to uniquely identify a message.
 ///
-/// Backed by a 32 bytes `SmallVec` to avoid heap allocations for ID sizes up to 256 bits.
+/// Message ids are variable-length: their length depends on the message id function that computed
+/// them (see [`MessageIdFn`]). Backed by a 32 bytes `SmallVec` to avoid heap allocations for ID
+/// sizes up to 256 bits.
+///
+/// A message id is displayed, and parsed from, its lowercase hex representation. With the `serde`
+/// feature, it is serialized as a hex string in human-readable formats, and as a byte sequence
+/// otherwise.
 #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct MessageId(SmallVec<[u8; 32]>);
 
@@ -21,12 +92,35 @@ impl MessageId {
         Self(SmallVec::from(value))
     }
 
+    /// The message id length, in bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the message id is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Convert the `MessageId` into a `Vec<u8>`.
     fn into_vec(self) -> Vec<u8> {
         self.0.into_vec()
     }
 }
 
+impl AsRef<[u8]> for MessageId {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for MessageId {
+    /// Convert a 256 bits digest, e.g., a SHA-256 digest, into a `MessageId`.
+    fn from(value: [u8; 32]) -> Self {
+        Self(SmallVec::from_buf(value))
+    }
+}
+
 impl From<Vec<u8>> for MessageId {
     /// Convert a `Vec<u8>` into a `MessageId`.
     fn from(value: Vec<u8>) -> Self {
@@ -67,11 +161,113 @@ impl std::fmt::Debug for MessageId {
     }
 }
 
+/// The errors parsing a [`MessageId`] from its hex representation.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ParseMessageIdError {
+    /// The hex representation has an odd number of digits.
+    #[error("odd number of hex digits")]
+    OddLength,
+
+    /// The hex representation contains a non-hex digit character.
+    #[error("invalid hex digit: {0:?}")]
+    InvalidHexDigit(char),
+}
+
+impl FromStr for MessageId {
+    type Err = ParseMessageIdError;
+
+    /// Parses a message id from its hex representation, with an optional `0x` prefix. Both
+    /// lowercase and uppercase hex digits are accepted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix("0x").unwrap_or(s);
+        if hex.len() % 2 != 0 {
+            return Err(ParseMessageIdError::OddLength);
+        }
+
+        let digit = |c: char| {
+            c.to_digit(16)
+                .map(|d| d as u8)
+                .ok_or(ParseMessageIdError::InvalidHexDigit(c))
+        };
+
+        let mut bytes = SmallVec::with_capacity(hex.len() / 2);
+        let mut chars = hex.chars();
+        while let (Some(high), Some(low)) = (chars.next(), chars.next()) {
+            bytes.push(digit(high)? << 4 | digit(low)?);
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use std::fmt;
+
+    use serde::de::{self, SeqAccess, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use smallvec::SmallVec;
+
+    use super::MessageId;
+
+    impl Serialize for MessageId {
+        /// Serializes the message id as a hex string in human-readable formats, and as a byte
+        /// sequence otherwise.
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                serializer.collect_str(self)
+            } else {
+                serializer.serialize_bytes(&self.0)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for MessageId {
+        /// Deserializes the message id from a hex string in human-readable formats, and from a
+        /// byte sequence otherwise.
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                let hex = String::deserialize(deserializer)?;
+                hex.parse().map_err(de::Error::custom)
+            } else {
+                deserializer.deserialize_bytes(BytesVisitor)
+            }
+        }
+    }
+
+    /// Visits the message id byte sequence.
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = MessageId;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a message id byte sequence")
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(MessageId::new_from_slice(v))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = SmallVec::new();
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(MessageId(bytes))
+        }
+    }
+}
+
 /// A message is a piece of data published on a topic.
 ///
 /// This is a immutable reference wrapper around the internal message type that provides a more
 /// convenient interface to the message data while decoupling the internal message type from the
 /// public API.
+///
+/// The fields of this type mirror the fields of the libp2p pubsub wire message. Empty `from`,
+/// `seqno`, `signature` and `key` fields received from the network are normalized to `None`.
 #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct MessageRef {
     /// The message author.
@@ -103,23 +299,44 @@ impl From<&crate::framing::Message> for MessageRef {
     }
 }
 
-/// The default message id function as defined in the libp2p pusub spec.
+/// The default message id function as defined in the libp2p pubsub spec.
 ///
-/// The default message id is computed as: author + sequence number.
+/// The default message id is computed as the concatenation of the message author's peer id bytes
+/// and the message sequence number bytes: `from || seqno`. If any of the fields is not present, it
+/// is considered an empty byte sequence.
 ///
-/// NOTE: If either the message author is not provided, we set it to 0.
+/// This function is byte-for-byte compatible with go-libp2p's `DefaultMsgIdFn`.
 pub fn default_message_id_fn(_src: Option<&PeerId>, msg: &MessageRef) -> MessageId {
-    // If either the peer_id or source is not provided, we set to 0
-    let mut source_string = if let Some(peer_id) = msg.from {
-        peer_id.to_base58().into_bytes()
-    } else {
-        PeerId::from_bytes(&[0, 1, 0])
-            .unwrap()
-            .to_base58()
-            .into_bytes()
-    };
-    source_string.extend(msg.seqno.as_ref().unwrap_or(&Bytes::new()));
-    MessageId::new(source_string)
+    let mut id = msg
+        .from
+        .map(|peer_id| peer_id.to_bytes())
+        .unwrap_or_default();
+    id.extend_from_slice(msg.seqno.as_deref().unwrap_or_default());
+    MessageId::new(id)
+}
+
+/// A content-addressed message id function for anonymous messages.
+///
+/// The message id is computed as the SHA-256 digest of the message data: `sha256(data)`. As the
+/// message author and sequence number are not taken into account, messages with the same payload
+/// are considered duplicates.
+pub fn sha256_message_id_fn(_src: Option<&PeerId>, msg: &MessageRef) -> MessageId {
+    MessageId::new_from_slice(Sha256::digest(&msg.data).as_slice())
+}
+
+/// The scope the seen messages are deduplicated in.
+///
+/// New scopes may be added in minor releases, so matches on this enum must include a wildcard
+/// arm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DedupScope {
+    /// A message is a duplicate of any seen message with the same id, whatever the topic.
+    #[default]
+    Global,
+    /// A message is a duplicate of a seen message with the same id published to the same topic.
+    /// The same message id on different topics identifies independent messages.
+    PerTopic,
 }
 
 // NOTE: Use `trait_set` crate as `trait_alias` is not yet stable.
@@ -128,11 +345,61 @@ trait_set::trait_set! {
     /// The message id function type.
     ///
     /// The message id function is used to compute the message id from a message.
+    ///
+    /// A topic-specific message id function (see
+    /// [`SubscriptionBuilder::message_id_fn`](crate::SubscriptionBuilder::message_id_fn)) must
+    /// return a non-empty message id of at most [`MAX_WIRE_MESSAGE_ID_LEN`] bytes, and must not
+    /// panic (see [`MessageIdFnError`]).
     pub trait MessageIdFn = Fn(Option<&PeerId>, &MessageRef) -> MessageId;
 }
 
+/// Errors that can occur when computing a message id with a message id function.
+///
+/// See [`MessageIdFn`] for the message id function contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum MessageIdFnError {
+    /// The message id function panicked.
+    #[error("message id function panicked")]
+    Panicked,
+
+    /// The message id function returned an empty message id.
+    #[error("empty message id")]
+    Empty,
+
+    /// The message id function returned a message id longer than
+    /// [`MAX_WIRE_MESSAGE_ID_LEN`] bytes.
+    #[error("message id too long: {0} bytes")]
+    TooLong(usize),
+}
+
+/// Compute the message id of a message with the given message id function, enforcing the
+/// message id function contract.
+///
+/// The message id function panics are caught, and the empty and oversized message ids are
+/// rejected.
+pub(crate) fn try_message_id(
+    id_fn: &dyn MessageIdFn<Output = MessageId>,
+    src: Option<&PeerId>,
+    message: &MessageRef,
+) -> Result<MessageId, MessageIdFnError> {
+    let message_id = panic::catch_unwind(AssertUnwindSafe(|| id_fn(src, message)))
+        .map_err(|_| MessageIdFnError::Panicked)?;
+
+    if message_id.is_empty() {
+        return Err(MessageIdFnError::Empty);
+    }
+    if message_id.len() > MAX_WIRE_MESSAGE_ID_LEN {
+        return Err(MessageIdFnError::TooLong(message_id.len()));
+    }
+
+    Ok(message_id)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+
     use rand::random;
 
     use crate::framing::Message as FrameMessage;
@@ -187,4 +454,252 @@ mod tests {
         //// Then
         assert_eq!(message_id, message_id2);
     }
+
+    #[test]
+    fn sha256_message_id_fn_should_return_same_id_for_same_payload() {
+        //// Given
+        let message_a = new_test_message(Some(PeerId::random()), Some(new_test_seqno()));
+        let message_b = new_test_message(None, None);
+
+        let id_fn: Box<dyn MessageIdFn<Output = MessageId>> = Box::new(sha256_message_id_fn);
+
+        //// When
+        let message_id_a = id_fn(None, &message_a.as_ref().into());
+        let message_id_b = id_fn(None, &message_b.as_ref().into());
+
+        //// Then
+        assert_eq!(message_id_a, message_id_b);
+    }
+
+    #[test]
+    fn message_id_is_displayed_as_lowercase_hex() {
+        //// Given
+        let message_id = MessageId::new(vec![0x00, 0xab, 0xCD, 0xff]);
+
+        //// Then
+        assert_eq!(message_id.to_string(), "00abcdff");
+        assert_eq!(format!("{message_id:?}"), "MessageId(00abcdff)");
+    }
+
+    #[test]
+    fn message_id_from_str_display_round_trip() {
+        //// Given
+        let message_id = MessageId::from(random::<[u8; 32]>());
+
+        //// When
+        let parsed = message_id.to_string().parse::<MessageId>().unwrap();
+        let parsed_prefixed = format!("0x{message_id}").parse::<MessageId>().unwrap();
+        let parsed_uppercase = message_id
+            .to_string()
+            .to_uppercase()
+            .parse::<MessageId>()
+            .unwrap();
+
+        //// Then
+        assert_eq!(parsed, message_id);
+        assert_eq!(parsed_prefixed, message_id);
+        assert_eq!(parsed_uppercase, message_id);
+    }
+
+    #[test]
+    fn invalid_hex_message_id_is_rejected() {
+        //// When
+        let odd_length = MessageId::from_str("abc");
+        let invalid_digit = MessageId::from_str("0xzz");
+
+        //// Then
+        assert_eq!(odd_length, Err(ParseMessageIdError::OddLength));
+        assert_eq!(
+            invalid_digit,
+            Err(ParseMessageIdError::InvalidHexDigit('z'))
+        );
+    }
+
+    #[test]
+    fn message_id_conversions_preserve_the_bytes() {
+        //// Given
+        let digest = random::<[u8; 32]>();
+        let long_id = vec![0x42; 64];
+
+        //// When
+        let from_array = MessageId::from(digest);
+        let from_vec = MessageId::from(long_id.clone());
+        let from_bytes = MessageId::from(Bytes::from(long_id.clone()));
+
+        //// Then
+        assert_eq!(from_array.as_ref(), &digest[..]);
+        assert_eq!(from_array.len(), 32);
+        assert_eq!(from_vec.as_ref(), &long_id[..]);
+        assert_eq!(from_vec.len(), 64);
+        assert_eq!(from_bytes, from_vec);
+        assert_eq!(Vec::from(from_vec), long_id);
+        assert!(MessageId::new(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn message_ids_are_ordered_by_bytes() {
+        //// Given
+        let short = MessageId::new(vec![0x01]);
+        let low = MessageId::new(vec![0x01, 0x00]);
+        let high = MessageId::new(vec![0x02]);
+
+        //// Then
+        assert!(short < low);
+        assert!(low < high);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn message_id_serde_round_trip() {
+        //// Given
+        let message_id = MessageId::new(vec![0x00, 0xab, 0xcd, 0xff]);
+
+        //// When
+        let json = serde_json::to_string(&message_id).unwrap();
+        let deserialized = serde_json::from_str::<MessageId>(&json).unwrap();
+
+        //// Then
+        assert_eq!(json, "\"00abcdff\"");
+        assert_eq!(deserialized, message_id);
+    }
+
+    mod golden_vectors {
+        //! Golden vectors matching go-libp2p's message id computation.
+        //!
+        //! Changing any of these vectors is a compatibility-affecting change.
+
+        use super::*;
+
+        /// The test vectors message author.
+        ///
+        /// Peer id bytes: `002508021221032c4e6f3694c04bf61803aebfc0b2fcdbc9981fe71355f3e4db9bb27a69e18127`
+        fn test_author() -> PeerId {
+            PeerId::from_str("16Uiu2HAmFdwf4rPmUzWgKeQRz8WkxMLvqyrN6QFFSUpPm5TiCTqQ").unwrap()
+        }
+
+        /// The test vectors message sequence number (big-endian encoded `u64`).
+        fn test_seqno() -> Bytes {
+            Bytes::from_static(&[0x16, 0xf5, 0xc3, 0xa8, 0xd9, 0xe1, 0xb2, 0xc4])
+        }
+
+        fn test_message(from: Option<PeerId>, seqno: Option<Bytes>) -> MessageRef {
+            MessageRef {
+                from,
+                data: Bytes::from_static(b"test-data"),
+                seqno,
+                topic: TopicHash::from_raw("/test/0.1.0"),
+                signature: None,
+                key: None,
+            }
+        }
+
+        #[test]
+        fn default_message_id_fn_from_and_seqno() {
+            //// Given
+            let message = test_message(Some(test_author()), Some(test_seqno()));
+
+            //// When
+            let message_id = default_message_id_fn(None, &message);
+
+            //// Then
+            assert_eq!(
+                message_id.to_string(),
+                "002508021221032c4e6f3694c04bf61803aebfc0b2fcdbc9981fe71355f3e4db9bb27a69e18127\
+                 16f5c3a8d9e1b2c4"
+            );
+        }
+
+        #[test]
+        fn default_message_id_fn_from_only() {
+            //// Given
+            let message = test_message(Some(test_author()), None);
+
+            //// When
+            let message_id = default_message_id_fn(None, &message);
+
+            //// Then
+            assert_eq!(
+                message_id.to_string(),
+                "002508021221032c4e6f3694c04bf61803aebfc0b2fcdbc9981fe71355f3e4db9bb27a69e18127"
+            );
+        }
+
+        #[test]
+        fn default_message_id_fn_seqno_only() {
+            //// Given
+            let message = test_message(None, Some(test_seqno()));
+
+            //// When
+            let message_id = default_message_id_fn(None, &message);
+
+            //// Then
+            assert_eq!(message_id.to_string(), "16f5c3a8d9e1b2c4");
+        }
+
+        #[test]
+        fn default_message_id_fn_ignores_propagation_source() {
+            //// Given
+            let message = test_message(Some(test_author()), Some(test_seqno()));
+
+            //// When
+            let message_id = default_message_id_fn(Some(&PeerId::random()), &message);
+
+            //// Then
+            assert_eq!(message_id, default_message_id_fn(None, &message));
+        }
+
+        #[test]
+        fn sha256_message_id_fn_data() {
+            //// Given
+            let message = test_message(None, None);
+
+            //// When
+            let message_id = sha256_message_id_fn(None, &message);
+
+            //// Then
+            assert_eq!(
+                message_id.to_string(),
+                "a186000422feab857329c684e9fe91412b1a5db084100b37a98cfc95b62aa867"
+            );
+        }
+    }
+
+    #[test]
+    fn try_message_id_rejects_panicking_message_id_fn() {
+        //// Given
+        let message = new_test_message(Some(PeerId::random()), Some(new_test_seqno()));
+        let id_fn: Box<dyn MessageIdFn<Output = MessageId>> =
+            Box::new(|_: Option<&PeerId>, _: &MessageRef| -> MessageId { panic!("no id field") });
+
+        //// When
+        let result = try_message_id(id_fn.as_ref(), None, &(&message).into());
+
+        //// Then
+        assert_eq!(result, Err(MessageIdFnError::Panicked));
+    }
+
+    #[test]
+    fn try_message_id_rejects_empty_and_oversized_message_ids() {
+        //// Given
+        let message = new_test_message(Some(PeerId::random()), Some(new_test_seqno()));
+        let empty_id_fn: Box<dyn MessageIdFn<Output = MessageId>> =
+            Box::new(|_: Option<&PeerId>, _: &MessageRef| MessageId::new(Vec::new()));
+        let oversized_id_fn: Box<dyn MessageIdFn<Output = MessageId>> =
+            Box::new(|_: Option<&PeerId>, _: &MessageRef| {
+                MessageId::new(vec![0x01; MAX_WIRE_MESSAGE_ID_LEN + 1])
+            });
+
+        //// When
+        let empty = try_message_id(empty_id_fn.as_ref(), None, &(&message).into());
+        let oversized = try_message_id(oversized_id_fn.as_ref(), None, &(&message).into());
+        let valid = try_message_id(&default_message_id_fn, None, &(&message).into());
+
+        //// Then
+        assert_eq!(empty, Err(MessageIdFnError::Empty));
+        assert_eq!(
+            oversized,
+            Err(MessageIdFnError::TooLong(MAX_WIRE_MESSAGE_ID_LEN + 1))
+        );
+        assert_eq!(valid, Ok(default_message_id_fn(None, &(&message).into())));
+    }
 }
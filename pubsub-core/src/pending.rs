@@ -0,0 +1,119 @@
+//! Queued events accounting (see
+//! [`Behaviour::pending_event_counts`](crate::Behaviour::pending_event_counts)).
+
+/// The number of events queued in the behaviour, by service and by mailbox.
+///
+/// The service counts include both the events waiting to be processed by the service and the
+/// events emitted by the service, waiting to be dispatched by the behaviour. The mailbox counts are
+/// the events waiting to be taken by the swarm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PendingCounts {
+    /// The connections service queued events.
+    pub connections: usize,
+    /// The subscriptions service queued events.
+    pub subscriptions: usize,
+    /// The subscriptions debounce service queued events.
+    pub subscriptions_debounce: usize,
+    /// The subscription sync service queued events.
+    pub subscription_sync: usize,
+    /// The subscription announce service queued events.
+    pub subscription_announce: usize,
+    /// The dialer service queued events.
+    pub dialer: usize,
+    /// The message ID service queued events.
+    pub message_id: usize,
+    /// The message cache service queued events.
+    pub message_cache: usize,
+    /// The reassembly service queued events.
+    pub reassembly: usize,
+    /// The ordering service queued events.
+    pub ordering: usize,
+    /// The protocol router service queued events.
+    pub protocol_router: usize,
+    /// The framing service queued events.
+    pub framing: usize,
+    /// The message forwards held back by the forwarding scheduler (see
+    /// [`Config::max_forwards_per_poll`](crate::Config::max_forwards_per_poll)).
+    pub scheduled_forwards: usize,
+    /// The frames held back by the egress pacer (see
+    /// [`Config::egress_budget`](crate::Config::egress_budget)).
+    pub paced_frames: usize,
+    /// The connection handler mailbox queued commands.
+    pub conn_handler_mailbox: usize,
+    /// The dial requests mailbox queued requests.
+    pub dial_mailbox: usize,
+    /// The close connection requests mailbox queued requests.
+    pub close_mailbox: usize,
+    /// The behaviour output mailbox queued events.
+    pub behaviour_output_mailbox: usize,
+}
+
+impl PendingCounts {
+    /// The number of events queued in the services, and held back by the forwarding scheduler.
+    #[must_use]
+    pub fn services(&self) -> usize {
+        self.connections
+            + self.subscriptions
+            + self.subscriptions_debounce
+            + self.subscription_sync
+            + self.subscription_announce
+            + self.dialer
+            + self.message_id
+            + self.message_cache
+            + self.reassembly
+            + self.ordering
+            + self.protocol_router
+            + self.framing
+            + self.scheduled_forwards
+    }
+
+    /// The number of events queued in the mailboxes, waiting to be taken by the swarm.
+    #[must_use]
+    pub fn mailboxes(&self) -> usize {
+        self.conn_handler_mailbox
+            + self.dial_mailbox
+            + self.close_mailbox
+            + self.behaviour_output_mailbox
+    }
+
+    /// The total number of queued events, including the frames held back by the egress pacer.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.services() + self.paced_frames + self.mailboxes()
+    }
+
+    /// Returns `true` if no event is queued.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_are_summed_by_services_and_mailboxes() {
+        //// Given
+        let counts = PendingCounts {
+            connections: 1,
+            framing: 2,
+            scheduled_forwards: 3,
+            conn_handler_mailbox: 4,
+            behaviour_output_mailbox: 5,
+            ..Default::default()
+        };
+
+        //// When
+        let (services, mailboxes, total) = (counts.services(), counts.mailboxes(), counts.total());
+
+        //// Then
+        assert_eq!(services, 6);
+        assert_eq!(mailboxes, 9);
+        assert_eq!(total, 15);
+        assert!(!counts.is_empty());
+        assert!(PendingCounts::default().is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+/// The code of a preflight check warning (see [`Behaviour::preflight`](crate::Behaviour::preflight)
+/// and [`Config::preflight`](crate::Config::preflight)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PreflightCode {
+    /// The heartbeat interval is longer than the message cache TTL. The expired message cache
+    /// entries are only pruned on every heartbeat, so the seen messages are kept up to a heartbeat
+    /// interval longer than the TTL.
+    HeartbeatExceedsCacheTtl,
+    /// The message history notified to the protocol routers spans more heartbeat intervals than
+    /// the message cache history length.
+    HistoryGossipExceedsHistoryLength,
+    /// The backfill maximum age is longer than the message cache TTL, while a local subscription
+    /// enables the backfill. The messages are only retained for the TTL, so the older messages are
+    /// never backfilled.
+    BackfillMaxAgeExceedsCacheTtl,
+    /// A local subscriptions announcement frame is larger than the maximum frame size, so the
+    /// subscriptions sync with every peer fails.
+    AnnouncementExceedsMaxFrameSize,
+    /// Two local subscriptions are the same topic string hashed differently, e.g., an
+    /// [`IdentTopic`](crate::IdentTopic) and a [`Sha256Topic`](crate::Sha256Topic).
+    MixedTopicHashing,
+    /// A topic hash is accepted by more than one local subscription, either as the subscription
+    /// topic or as an alias.
+    DuplicateTopicHash,
+    /// The local node is not subscribed to any topic, so it can neither publish nor relay
+    /// messages.
+    NoSubscriptions,
+}
+
+impl fmt::Display for PreflightCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = match self {
+            PreflightCode::HeartbeatExceedsCacheTtl => "heartbeat-exceeds-cache-ttl",
+            PreflightCode::HistoryGossipExceedsHistoryLength => {
+                "history-gossip-exceeds-history-length"
+            }
+            PreflightCode::BackfillMaxAgeExceedsCacheTtl => "backfill-max-age-exceeds-cache-ttl",
+            PreflightCode::AnnouncementExceedsMaxFrameSize => "announcement-exceeds-max-frame-size",
+            PreflightCode::MixedTopicHashing => "mixed-topic-hashing",
+            PreflightCode::DuplicateTopicHash => "duplicate-topic-hash",
+            PreflightCode::NoSubscriptions => "no-subscriptions",
+        };
+        f.write_str(code)
+    }
+}
+
+/// A preflight check warning: a configuration, or subscriptions, inconsistency likely to misbehave
+/// once the node joins the network.
+///
+/// The `message` is a human-readable description of the inconsistency, and its format is not
+/// stable. The `values` are the offending values, as `(name, value)` pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PreflightWarning {
+    /// The warning code.
+    pub code: PreflightCode,
+    /// A description of the inconsistency.
+    pub message: String,
+    /// The offending values, as `(name, value)` pairs.
+    pub values: Vec<(&'static str, String)>,
+}
+
+impl PreflightWarning {
+    /// Create a new preflight warning.
+    #[must_use]
+    pub fn new(
+        code: PreflightCode,
+        message: impl Into<String>,
+        values: Vec<(&'static str, String)>,
+    ) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            values,
+        }
+    }
+}
+
+impl fmt::Display for PreflightWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)?;
+        for (idx, (name, value)) in self.values.iter().enumerate() {
+            let sep = if idx == 0 { " (" } else { ", " };
+            write!(f, "{sep}{name}: {value}")?;
+        }
+        if !self.values.is_empty() {
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
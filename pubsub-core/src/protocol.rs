@@ -1,3 +1,4 @@
+pub use composite::{CompositeProtocol, CompositeRouter};
 pub use protocol_trait::Protocol;
 pub use router_trait::{
     ProtocolRouter, ProtocolRouterConnectionEvent, ProtocolRouterControlEvent,
@@ -5,5 +6,6 @@ pub use router_trait::{
     ProtocolRouterSubscriptionEvent,
 };
 
+mod composite;
 mod protocol_trait;
 mod router_trait;
@@ -0,0 +1,388 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+
+use crate::topic::TopicHash;
+use crate::upgrade::{protocol_ids, CompositeProtocolUpgrade};
+
+use super::protocol_trait::Protocol;
+use super::router_trait::{
+    ProtocolRouter, ProtocolRouterConnectionEvent, ProtocolRouterInEvent,
+    ProtocolRouterMessageEvent, ProtocolRouterOutEvent, ProtocolRouterSubscriptionEvent,
+};
+
+/// A pubsub protocol hosting two pubsub protocols side by side.
+///
+/// The connection handler negotiates any of the protocol ids of both protocols. Each peer is
+/// attributed to the protocol whose protocol id was negotiated with it, and its connection,
+/// subscription and message events are only routed to that protocol router. Local subscriptions
+/// are routed to both protocol routers unless the subscription carries a
+/// [`protocol_hint`](crate::subscription::Subscription::protocol_hint) naming one of the protocol
+/// ids.
+///
+/// More than two protocols can be hosted by nesting composite protocols.
+pub struct CompositeProtocol<P1, P2> {
+    first: P1,
+    second: P2,
+}
+
+impl<P1, P2> CompositeProtocol<P1, P2>
+where
+    P1: Protocol,
+    P2: Protocol,
+{
+    /// Create a new composite protocol. The first protocol ids take precedence during the
+    /// protocol negotiation.
+    pub fn new(first: P1, second: P2) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<P1, P2> Protocol for CompositeProtocol<P1, P2>
+where
+    P1: Protocol,
+    P2: Protocol,
+{
+    type Upgrade = CompositeProtocolUpgrade<P1::Upgrade, P2::Upgrade>;
+    type RouterService = CompositeRouter<P1::RouterService, P2::RouterService>;
+
+    fn upgrade() -> Self::Upgrade {
+        CompositeProtocolUpgrade::new(P1::upgrade(), P2::upgrade())
+    }
+
+    fn router(&self, local_peer_id: PeerId) -> Self::RouterService {
+        CompositeRouter::new(
+            self.first.router(local_peer_id),
+            protocol_ids(&P1::upgrade()),
+            self.second.router(local_peer_id),
+            protocol_ids(&P2::upgrade()),
+        )
+    }
+}
+
+/// The protocol router a peer is attributed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    First,
+    Second,
+}
+
+/// The [`ProtocolRouter`] of the [`CompositeProtocol`].
+///
+/// Dispatches the router input events to the inner protocol routers based on the protocol
+/// negotiated with each peer. The inner routers output events are emitted as they are.
+pub struct CompositeRouter<R1, R2> {
+    first: R1,
+    first_protocols: Vec<String>,
+    second: R2,
+    second_protocols: Vec<String>,
+
+    /// The connected peers whose protocol has not been negotiated yet.
+    pending_peers: HashSet<PeerId>,
+
+    /// The protocol router each peer is attributed to.
+    peer_routers: HashMap<PeerId, Side>,
+
+    /// The protocol router each local subscription with a protocol hint is routed by.
+    topic_routers: HashMap<TopicHash, Side>,
+}
+
+impl<R1, R2> CompositeRouter<R1, R2>
+where
+    R1: ProtocolRouter,
+    R2: ProtocolRouter,
+{
+    /// Create a new composite router from the inner routers and their supported protocol ids.
+    pub fn new(
+        first: R1,
+        first_protocols: Vec<impl Into<String>>,
+        second: R2,
+        second_protocols: Vec<impl Into<String>>,
+    ) -> Self {
+        Self {
+            first,
+            first_protocols: first_protocols.into_iter().map(Into::into).collect(),
+            second,
+            second_protocols: second_protocols.into_iter().map(Into::into).collect(),
+            pending_peers: Default::default(),
+            peer_routers: Default::default(),
+            topic_routers: Default::default(),
+        }
+    }
+
+    /// Get the router side supporting the given protocol id, if any.
+    fn protocol_side(&self, protocol: &str) -> Option<Side> {
+        if self.first_protocols.iter().any(|p| p == protocol) {
+            Some(Side::First)
+        } else if self.second_protocols.iter().any(|p| p == protocol) {
+            Some(Side::Second)
+        } else {
+            None
+        }
+    }
+
+    /// Get the local topics routed by the router on the given side, i.e., the topics not hinted to
+    /// the other router.
+    fn side_topics(&self, topics: &BTreeSet<TopicHash>, side: Side) -> BTreeSet<TopicHash> {
+        topics
+            .iter()
+            .filter(|topic| self.topic_routers.get(*topic).map_or(true, |s| *s == side))
+            .cloned()
+            .collect()
+    }
+
+    /// Send the event to the router on the given side.
+    fn send_to<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, ProtocolRouterOutEvent>,
+        side: Side,
+        ev: ProtocolRouterInEvent,
+    ) {
+        match side {
+            Side::First => self.first.on_event(svc_cx, ev),
+            Side::Second => self.second.on_event(svc_cx, ev),
+        }
+    }
+
+    /// Send the event to both routers.
+    fn send_to_all<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, ProtocolRouterOutEvent>,
+        ev: ProtocolRouterInEvent,
+    ) {
+        self.first.on_event(svc_cx, ev.clone());
+        self.second.on_event(svc_cx, ev);
+    }
+
+    /// Send the event to the router the peer is attributed to, if any.
+    fn send_to_peer_router<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, ProtocolRouterOutEvent>,
+        peer: &PeerId,
+        ev: ProtocolRouterInEvent,
+    ) {
+        match self.peer_routers.get(peer) {
+            Some(side) => self.send_to(svc_cx, *side, ev),
+            None => tracing::trace!(%peer, "Peer protocol not negotiated, dropping router event"),
+        }
+    }
+
+    fn on_connection_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, ProtocolRouterOutEvent>,
+        conn_ev: ProtocolRouterConnectionEvent,
+    ) {
+        match &conn_ev {
+            ProtocolRouterConnectionEvent::PeerConnected(peer) => {
+                let peer = *peer;
+
+                // Defer the connection notification until the peer protocol is known.
+                if self.peer_routers.contains_key(&peer) {
+                    self.send_to_peer_router(
+                        svc_cx,
+                        &peer,
+                        ProtocolRouterInEvent::ConnectionEvent(conn_ev),
+                    );
+                } else {
+                    self.pending_peers.insert(peer);
+                }
+            }
+            ProtocolRouterConnectionEvent::PeerDisconnected(peer) => {
+                let peer = *peer;
+                self.pending_peers.remove(&peer);
+                self.send_to_peer_router(
+                    svc_cx,
+                    &peer,
+                    ProtocolRouterInEvent::ConnectionEvent(conn_ev),
+                );
+                self.peer_routers.remove(&peer);
+            }
+            ProtocolRouterConnectionEvent::PeerProtocolNegotiated { peer, protocol } => {
+                let peer = *peer;
+                let Some(side) = self.protocol_side(protocol) else {
+                    tracing::debug!(%peer, %protocol, "Unknown protocol negotiated");
+                    return;
+                };
+
+                self.peer_routers.insert(peer, side);
+                if self.pending_peers.remove(&peer) {
+                    self.send_to(
+                        svc_cx,
+                        side,
+                        ProtocolRouterInEvent::ConnectionEvent(
+                            ProtocolRouterConnectionEvent::PeerConnected(peer),
+                        ),
+                    );
+                }
+
+                // Forward the negotiation event, so nested composite routers can dispatch it.
+                self.send_to(
+                    svc_cx,
+                    side,
+                    ProtocolRouterInEvent::ConnectionEvent(conn_ev),
+                );
+            }
+            ProtocolRouterConnectionEvent::PeerDeprioritized { peer, .. } => {
+                let peer = *peer;
+                self.send_to_peer_router(
+                    svc_cx,
+                    &peer,
+                    ProtocolRouterInEvent::ConnectionEvent(conn_ev),
+                );
+            }
+        }
+    }
+
+    fn on_subscription_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, ProtocolRouterOutEvent>,
+        sub_ev: ProtocolRouterSubscriptionEvent,
+    ) {
+        match &sub_ev {
+            ProtocolRouterSubscriptionEvent::Subscribed(sub) => {
+                let side = sub
+                    .protocol_hint
+                    .as_deref()
+                    .and_then(|hint| self.protocol_side(hint));
+                match side {
+                    Some(side) => {
+                        self.topic_routers.insert(sub.topic.clone(), side);
+                        self.send_to(
+                            svc_cx,
+                            side,
+                            ProtocolRouterInEvent::SubscriptionEvent(sub_ev),
+                        )
+                    }
+                    None => {
+                        self.send_to_all(svc_cx, ProtocolRouterInEvent::SubscriptionEvent(sub_ev))
+                    }
+                }
+            }
+            ProtocolRouterSubscriptionEvent::Unsubscribed(topic) => {
+                self.topic_routers.remove(topic);
+                self.send_to_all(svc_cx, ProtocolRouterInEvent::SubscriptionEvent(sub_ev));
+            }
+            ProtocolRouterSubscriptionEvent::KnownPeerSubscribed { .. }
+            | ProtocolRouterSubscriptionEvent::KnownPeerForgotten(_) => {
+                self.send_to_all(svc_cx, ProtocolRouterInEvent::SubscriptionEvent(sub_ev));
+            }
+            ProtocolRouterSubscriptionEvent::PeerSubscribed { peer, .. }
+            | ProtocolRouterSubscriptionEvent::PeerUnsubscribed { peer, .. }
+            | ProtocolRouterSubscriptionEvent::PeerSubscribedMany { peer, .. }
+            | ProtocolRouterSubscriptionEvent::PeerUnsubscribedMany { peer, .. } => {
+                let peer = *peer;
+                self.send_to_peer_router(
+                    svc_cx,
+                    &peer,
+                    ProtocolRouterInEvent::SubscriptionEvent(sub_ev),
+                );
+            }
+        }
+    }
+}
+
+impl<R1, R2> EventHandler for CompositeRouter<R1, R2>
+where
+    R1: ProtocolRouter,
+    R2: ProtocolRouter,
+{
+    type InEvent = ProtocolRouterInEvent;
+    type OutEvent = ProtocolRouterOutEvent;
+
+    fn on_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
+        ev: Self::InEvent,
+    ) {
+        match ev {
+            ProtocolRouterInEvent::ConnectionEvent(conn_ev) => {
+                self.on_connection_event(svc_cx, conn_ev);
+            }
+            ProtocolRouterInEvent::SubscriptionEvent(sub_ev) => {
+                self.on_subscription_event(svc_cx, sub_ev);
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessageReceived {
+                src,
+                message,
+                message_id,
+                exclude,
+            }) => {
+                let ev = ProtocolRouterInEvent::MessageEvent(
+                    ProtocolRouterMessageEvent::MessageReceived {
+                        src,
+                        message,
+                        message_id,
+                        exclude,
+                    },
+                );
+
+                // The messages forwarded by the local node have no attributed router.
+                if self.peer_routers.contains_key(&src) {
+                    self.send_to_peer_router(svc_cx, &src, ev);
+                } else {
+                    self.send_to_all(svc_cx, ev);
+                }
+            }
+            ProtocolRouterInEvent::MessageEvent(
+                msg_ev @ ProtocolRouterMessageEvent::MessagePublished { .. },
+            ) => {
+                self.send_to_all(svc_cx, ProtocolRouterInEvent::MessageEvent(msg_ev));
+            }
+            ProtocolRouterInEvent::Heartbeat => {
+                self.send_to_all(svc_cx, ProtocolRouterInEvent::Heartbeat);
+            }
+            ProtocolRouterInEvent::MessageHistory(history) => {
+                self.send_to_all(svc_cx, ProtocolRouterInEvent::MessageHistory(history));
+            }
+            ProtocolRouterInEvent::ForwardFanout(fanout) => {
+                self.send_to_all(svc_cx, ProtocolRouterInEvent::ForwardFanout(fanout));
+            }
+            ProtocolRouterInEvent::StateSync {
+                local_topics,
+                peers,
+            } => {
+                // Each router is synced with the peers attributed to it, and with the local topics
+                // not hinted to the other router. The peers whose protocol has not been negotiated
+                // yet are not routed by either router.
+                let (first_peers, second_peers) = peers
+                    .into_iter()
+                    .filter_map(|(peer, topics)| {
+                        self.peer_routers
+                            .get(&peer)
+                            .map(|side| (*side, (peer, topics)))
+                    })
+                    .partition::<Vec<_>, _>(|(side, _)| *side == Side::First);
+
+                let (first_topics, second_topics) = (
+                    self.side_topics(&local_topics, Side::First),
+                    self.side_topics(&local_topics, Side::Second),
+                );
+
+                self.first.on_event(
+                    svc_cx,
+                    ProtocolRouterInEvent::StateSync {
+                        local_topics: first_topics,
+                        peers: first_peers.into_iter().map(|(_, peer)| peer).collect(),
+                    },
+                );
+                self.second.on_event(
+                    svc_cx,
+                    ProtocolRouterInEvent::StateSync {
+                        local_topics: second_topics,
+                        peers: second_peers.into_iter().map(|(_, peer)| peer).collect(),
+                    },
+                );
+            }
+            ProtocolRouterInEvent::ControlEvent(ctrl_ev) => {
+                let src = ctrl_ev.src;
+                self.send_to_peer_router(
+                    svc_cx,
+                    &src,
+                    ProtocolRouterInEvent::ControlEvent(ctrl_ev),
+                );
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use libp2p::identity::PeerId;
+
 use crate::upgrade::ProtocolUpgradeSend;
 
 use super::router_trait::ProtocolRouter;
@@ -16,8 +18,8 @@ pub trait Protocol {
     /// See [`ProtocolUpgrade`](crate::upgrade::ProtocolUpgrade) for more information.
     fn upgrade() -> Self::Upgrade;
 
-    /// Returns the protocol's router service.
+    /// Returns the protocol's router service for the local node, identified by `local_peer_id`.
     ///
     /// See [`ProtocolRouter`] for more information.
-    fn router(&self) -> Self::RouterService;
+    fn router(&self, local_peer_id: PeerId) -> Self::RouterService;
 }
@@ -1,16 +1,28 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::rc::Rc;
 
 use libp2p::PeerId;
 
 use libp2p_pubsub_common::service::EventHandler;
 
+use crate::dispatch::Consumes;
+use crate::fanout::ForwardFanout;
 use crate::framing::{ControlMessage, Message as FrameMessage};
 use crate::message_id::MessageId;
+use crate::services::connections::ConnectionsOutEvent;
+use crate::services::framing::{
+    FramingDownstreamOutEvent, FramingOutEvent, FramingUpstreamOutEvent,
+};
+use crate::services::subscriptions::SubscriptionsOutEvent;
 use crate::subscription::Subscription;
 use crate::topic::TopicHash;
 
 /// A pubsub protocol router input event.
+///
+/// New events may be added in minor releases, so protocol routers must ignore the events they do
+/// not handle with a wildcard arm.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ProtocolRouterInEvent {
     /// A connection event.
     ConnectionEvent(ProtocolRouterConnectionEvent),
@@ -20,19 +32,76 @@ pub enum ProtocolRouterInEvent {
     MessageEvent(ProtocolRouterMessageEvent),
     /// A pubsub control message event.
     ControlEvent(ProtocolRouterControlEvent),
+    /// A periodic heartbeat, emitted every [`Config::heartbeat_interval`](
+    /// crate::Config::heartbeat_interval).
+    ///
+    /// Routers can use it to perform their periodic maintenance, e.g., removing the expired
+    /// [`BackoffTracker`](crate::BackoffTracker) backoffs.
+    Heartbeat,
+    /// The ids of the messages seen on the local node subscribed topics during the last
+    /// [`Config::history_gossip`](crate::Config::history_gossip) heartbeat intervals, from the
+    /// most to the least recent.
+    ///
+    /// Notified right before every [`Heartbeat`](ProtocolRouterInEvent::Heartbeat), so routers
+    /// can advertise the recently seen messages (e.g., gossipsub's IHAVE control messages).
+    MessageHistory(BTreeMap<TopicHash, Vec<MessageId>>),
+    /// The message forwarding fan-out caps, as configured by [`Config::max_forward_fanout`](
+    /// crate::Config::max_forward_fanout) and [`Config::max_publish_fanout`](
+    /// crate::Config::max_publish_fanout).
+    ///
+    /// Notified once, before any other event. Routers should select the destination peers of the
+    /// messages they forward and publish with it.
+    ForwardFanout(ForwardFanout),
+    /// A snapshot of the local node subscriptions and of the connected peers subscriptions, as
+    /// tracked by the behaviour.
+    ///
+    /// Notified on [`Behaviour::force_router_sync`](crate::Behaviour::force_router_sync) and every
+    /// [`Config::router_sync_interval`](crate::Config::router_sync_interval), if enabled. Routers
+    /// must replace their subscriptions and routing tables wholesale with the snapshot, so any
+    /// divergence from the incremental events is reconciled.
+    StateSync {
+        /// The topics the local node is subscribed to.
+        local_topics: BTreeSet<TopicHash>,
+        /// The connected peers and the topics they are subscribed to.
+        peers: Vec<(PeerId, BTreeSet<TopicHash>)>,
+    },
 }
 
 /// A pubsub protocol router connection event.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ProtocolRouterConnectionEvent {
     /// A new peer connected.
     PeerConnected(PeerId),
     /// A peer disconnected.
     PeerDisconnected(PeerId),
+    /// The pubsub protocol negotiated with a peer.
+    ///
+    /// Only notified if the behaviour protocol supports multiple protocol ids (see
+    /// [`CompositeProtocol`](super::CompositeProtocol)).
+    PeerProtocolNegotiated {
+        /// The peer.
+        peer: PeerId,
+        /// The negotiated protocol id.
+        protocol: String,
+    },
+    /// A peer was deprioritized, or no longer is, as a message forward destination.
+    ///
+    /// Notified if [`Config::deprioritize_asymmetric_peers`](
+    /// crate::Config::deprioritize_asymmetric_peers) is enabled, when a peer is suspected of
+    /// asymmetric connectivity and when the suspicion is dropped. Routers should pick the
+    /// deprioritized peers last (see [`ForwardFanout::set_deprioritized`]).
+    PeerDeprioritized {
+        /// The peer.
+        peer: PeerId,
+        /// Whether the peer is deprioritized.
+        deprioritized: bool,
+    },
 }
 
 /// A pubsub protocol router topic subscription event.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ProtocolRouterSubscriptionEvent {
     /// A subscription event.
     Subscribed(Subscription),
@@ -42,12 +111,46 @@ pub enum ProtocolRouterSubscriptionEvent {
     PeerSubscribed { peer: PeerId, topic: TopicHash },
     /// A peer unsubscribed from a topic.
     PeerUnsubscribed { peer: PeerId, topic: TopicHash },
+    /// A peer subscribed to several topics.
+    ///
+    /// The subscriptions of a peer received in the same behaviour poll are batched into a single
+    /// event, e.g., the subscriptions announced by the peers reconnecting after a network
+    /// partition. A single topic subscription is still notified as [`PeerSubscribed`].
+    ///
+    /// [`PeerSubscribed`]: ProtocolRouterSubscriptionEvent::PeerSubscribed
+    PeerSubscribedMany {
+        peer: PeerId,
+        topics: Vec<TopicHash>,
+    },
+    /// A peer unsubscribed from several topics.
+    ///
+    /// The batched counterpart of [`PeerUnsubscribed`].
+    ///
+    /// [`PeerUnsubscribed`]: ProtocolRouterSubscriptionEvent::PeerUnsubscribed
+    PeerUnsubscribedMany {
+        peer: PeerId,
+        topics: Vec<TopicHash>,
+    },
+    /// A peer the local node is not connected to is known to be subscribed to a topic, as learned
+    /// from a third party.
+    ///
+    /// Routers may use this information for candidate selection, e.g., to pick peers to dial, but
+    /// must not forward messages to these peers.
+    KnownPeerSubscribed { peer: PeerId, topic: TopicHash },
+    /// A known remote peer is no longer tracked, either because it connected or because it was
+    /// evicted.
+    KnownPeerForgotten(PeerId),
 }
 
 /// A pubsub protocol router message event.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ProtocolRouterMessageEvent {
     /// A message was received from a peer.
+    ///
+    /// This event is also generated by the `forward_message` method of the pubsub behaviour, for
+    /// the messages re-injected by the application, with the local node as the message
+    /// propagator.
     MessageReceived {
         /// The message propagator.
         src: PeerId,
@@ -55,6 +158,10 @@ pub enum ProtocolRouterMessageEvent {
         message: Rc<FrameMessage>,
         /// The message id.
         message_id: MessageId,
+        /// The peers, other than the message propagator, the message must not be forwarded to.
+        ///
+        /// Empty for the messages received from the network.
+        exclude: BTreeSet<PeerId>,
     },
     /// A message ready to publish.
     ///
@@ -64,20 +171,35 @@ pub enum ProtocolRouterMessageEvent {
         message: Rc<FrameMessage>,
         /// The message id.
         message_id: MessageId,
+        /// The single peer the message must be sent to, if the message topic is published in
+        /// round-robin (see [`DeliveryMode::RoundRobinPublish`](crate::DeliveryMode::RoundRobinPublish)).
+        ///
+        /// `None` if the router selects the destination peers as usual.
+        dest: Option<PeerId>,
     },
 }
 
 /// A pubsub protocol control message event.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct ProtocolRouterControlEvent {
     /// The message source.
-    pub(crate) src: PeerId,
+    pub src: PeerId,
     /// The message.
-    pub(crate) message: ControlMessage,
+    pub message: ControlMessage,
+}
+
+impl ProtocolRouterControlEvent {
+    /// Create a new control message event.
+    #[must_use]
+    pub fn new(src: PeerId, message: ControlMessage) -> Self {
+        Self { src, message }
+    }
 }
 
 /// A pubsub protocol router output event.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ProtocolRouterOutEvent {
     /// Forward the message to the given peers.
     ForwardMessage {
@@ -93,6 +215,29 @@ pub enum ProtocolRouterOutEvent {
         // The control message.
         message: ControlMessage,
     },
+    /// Disconnect the given peer, closing all its connections.
+    ///
+    /// The behaviour ignores the requests to disconnect a peer that is not connected, or whose
+    /// disconnection was already requested, so a router requesting a disconnection on every
+    /// [`PeerDisconnected`](ProtocolRouterConnectionEvent::PeerDisconnected) event does not loop.
+    #[non_exhaustive]
+    DisconnectPeer {
+        /// The peer to disconnect.
+        peer: PeerId,
+        /// The reason of the disconnection.
+        reason: String,
+    },
+}
+
+impl ProtocolRouterOutEvent {
+    /// Create a new [`ProtocolRouterOutEvent::DisconnectPeer`] event.
+    #[must_use]
+    pub fn disconnect_peer(peer: PeerId, reason: impl Into<String>) -> Self {
+        Self::DisconnectPeer {
+            peer,
+            reason: reason.into(),
+        }
+    }
 }
 
 // NOTE: Use `trait_set` crate as `trait_alias` is not yet stable.
@@ -104,5 +249,86 @@ trait_set::trait_set! {
     /// to the appropriate peers.
     ///
     /// It handles the [`ProtocolRouterInEvent`] and generates [`ProtocolRouterOutEvent`] events.
+    ///
+    /// The routers can be unit tested with the `libp2p-pubsub-common` crate test utilities,
+    /// enabled with its `test-utils` feature (see `libp2p_pubsub_common::test_utils`).
     pub trait ProtocolRouter = EventHandler<InEvent = ProtocolRouterInEvent, OutEvent = ProtocolRouterOutEvent>;
 }
+
+impl Consumes<ConnectionsOutEvent> for ProtocolRouterInEvent {
+    fn consume(ev: &ConnectionsOutEvent) -> Option<Self> {
+        let ev = match ev {
+            ConnectionsOutEvent::NewPeerConnected { peer, .. } => {
+                ProtocolRouterConnectionEvent::PeerConnected(*peer)
+            }
+            ConnectionsOutEvent::PeerDisconnected(peer) => {
+                ProtocolRouterConnectionEvent::PeerDisconnected(*peer)
+            }
+        };
+        Some(ProtocolRouterInEvent::ConnectionEvent(ev))
+    }
+}
+
+/// The peer unsubscriptions are not consumed: the behaviour holds them back until the messages
+/// published before they were processed are handed to the router (see
+/// [`Behaviour::publish`](crate::Behaviour::publish)).
+impl Consumes<SubscriptionsOutEvent> for ProtocolRouterInEvent {
+    fn consume(ev: &SubscriptionsOutEvent) -> Option<Self> {
+        let ev = match ev {
+            SubscriptionsOutEvent::Subscribed(sub) => {
+                ProtocolRouterSubscriptionEvent::Subscribed(sub.clone())
+            }
+            SubscriptionsOutEvent::Unsubscribed { topic, .. } => {
+                ProtocolRouterSubscriptionEvent::Unsubscribed(topic.clone())
+            }
+            SubscriptionsOutEvent::PeerSubscribed { peer, topic } => {
+                ProtocolRouterSubscriptionEvent::PeerSubscribed {
+                    peer: *peer,
+                    topic: topic.clone(),
+                }
+            }
+            SubscriptionsOutEvent::PeerSubscribedMany { peer, topics } => {
+                ProtocolRouterSubscriptionEvent::PeerSubscribedMany {
+                    peer: *peer,
+                    topics: topics.clone(),
+                }
+            }
+            SubscriptionsOutEvent::KnownPeerSubscribed { peer, topic } => {
+                ProtocolRouterSubscriptionEvent::KnownPeerSubscribed {
+                    peer: *peer,
+                    topic: topic.clone(),
+                }
+            }
+            SubscriptionsOutEvent::KnownPeerForgotten(peer) => {
+                ProtocolRouterSubscriptionEvent::KnownPeerForgotten(*peer)
+            }
+            SubscriptionsOutEvent::PeerUnsubscribed { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribedMany { .. }
+            | SubscriptionsOutEvent::PeerFlapping { .. }
+            | SubscriptionsOutEvent::SendSubscriptions { .. } => return None,
+        };
+        Some(ProtocolRouterInEvent::SubscriptionEvent(ev))
+    }
+}
+
+/// The received messages are not consumed: the behaviour hands them to the router once
+/// deduplicated (see [`ProtocolRouterMessageEvent::MessageReceived`]).
+impl Consumes<FramingOutEvent> for ProtocolRouterInEvent {
+    fn consume(ev: &FramingOutEvent) -> Option<Self> {
+        match ev {
+            FramingOutEvent::Upstream(FramingUpstreamOutEvent::ControlMessageReceived {
+                src,
+                message,
+            }) => Some(ProtocolRouterInEvent::ControlEvent(
+                ProtocolRouterControlEvent::new(*src, message.clone()),
+            )),
+            FramingOutEvent::Upstream(
+                FramingUpstreamOutEvent::MessageReceived(_)
+                | FramingUpstreamOutEvent::SubscriptionRequestReceived { .. }
+                | FramingUpstreamOutEvent::ValidationFailed { .. }
+                | FramingUpstreamOutEvent::InefficientFraming { .. },
+            )
+            | FramingOutEvent::Downstream(FramingDownstreamOutEvent::SendFrame { .. }) => None,
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Delivered messages provenance (see
+//! [`Behaviour::topic_provenance_stats`](crate::Behaviour::topic_provenance_stats)).
+//!
+//! The wire format carries no hop counter, so the number of hops a message travelled is
+//! estimated locally: a message propagated by its own author travelled a single hop, any other
+//! message was relayed at least once.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use libp2p::identity::PeerId;
+
+use crate::topic::TopicHash;
+
+/// The number of buckets the provenance statistics sliding window is divided into.
+const WINDOW_BUCKETS: u32 = 10;
+
+/// The provenance of a delivered message, i.e., the local estimate of the hops it travelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Provenance {
+    /// The message was propagated by its author, i.e., it travelled a single hop.
+    DirectFromAuthor,
+    /// The message was propagated by a peer other than its author, i.e., it travelled two or
+    /// more hops.
+    Relayed,
+    /// The message is anonymous, i.e., it has no author, so the hops it travelled are unknown.
+    Unknown,
+}
+
+impl Provenance {
+    /// The provenance of a message authored by `from`, if any, propagated by the `src` peer.
+    #[must_use]
+    pub fn new(src: &PeerId, from: Option<&PeerId>) -> Self {
+        match from {
+            Some(from) if from == src => Self::DirectFromAuthor,
+            Some(_) => Self::Relayed,
+            None => Self::Unknown,
+        }
+    }
+
+    /// Whether the message was propagated by its author.
+    ///
+    /// Returns `None` if the message is anonymous.
+    #[must_use]
+    pub fn is_direct_from_author(&self) -> Option<bool> {
+        match self {
+            Self::DirectFromAuthor => Some(true),
+            Self::Relayed => Some(false),
+            Self::Unknown => None,
+        }
+    }
+}
+
+/// The provenance of the messages delivered on a topic over the sliding window (see
+/// [`Config::provenance_stats_window`](crate::Config::provenance_stats_window)).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ProvenanceStats {
+    /// The number of messages delivered over the window propagated by their author.
+    pub direct: u64,
+    /// The number of messages delivered over the window propagated by a peer other than their
+    /// author.
+    pub relayed: u64,
+    /// The number of anonymous messages delivered over the window.
+    pub unknown: u64,
+}
+
+impl ProvenanceStats {
+    /// The ratio of the messages with a known author propagated by their author, over the window.
+    ///
+    /// Returns `None` if no message with a known author was delivered over the window. The
+    /// anonymous messages are not taken into account.
+    #[must_use]
+    pub fn direct_ratio(&self) -> Option<f64> {
+        let known = self.direct + self.relayed;
+        if known == 0 {
+            return None;
+        }
+
+        Some(self.direct as f64 / known as f64)
+    }
+
+    /// Account a delivered message provenance.
+    fn record(&mut self, provenance: Provenance) {
+        let counter = match provenance {
+            Provenance::DirectFromAuthor => &mut self.direct,
+            Provenance::Relayed => &mut self.relayed,
+            Provenance::Unknown => &mut self.unknown,
+        };
+        *counter = counter.saturating_add(1);
+    }
+
+    /// Add the other statistics counters to these counters.
+    fn merge(&mut self, other: &ProvenanceStats) {
+        self.direct = self.direct.saturating_add(other.direct);
+        self.relayed = self.relayed.saturating_add(other.relayed);
+        self.unknown = self.unknown.saturating_add(other.unknown);
+    }
+}
+
+/// The messages delivered during a window bucket.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// The bucket start time.
+    start: Instant,
+    stats: ProvenanceStats,
+}
+
+/// A sliding window of the provenance of the messages delivered on a topic.
+///
+/// The window is divided into [`WINDOW_BUCKETS`] buckets, so its memory is bounded regardless of
+/// the topic message rate. A bucket is evicted once it is entirely older than the window.
+#[derive(Debug, Default)]
+struct ProvenanceWindow {
+    /// The window buckets, oldest first.
+    buckets: VecDeque<Bucket>,
+}
+
+/// Keeps the provenance statistics of the messages delivered on the locally subscribed topics.
+#[derive(Debug)]
+pub(crate) struct ProvenanceTracking {
+    /// The sliding window duration.
+    window: Duration,
+    /// The sliding windows, by locally subscribed topic.
+    topics: HashMap<TopicHash, ProvenanceWindow>,
+}
+
+impl ProvenanceTracking {
+    /// Create a new provenance tracking over a sliding window of the given duration.
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            topics: Default::default(),
+        }
+    }
+
+    /// The duration of a window bucket.
+    fn bucket_len(&self) -> Duration {
+        self.window / WINDOW_BUCKETS
+    }
+
+    /// Whether the bucket is entirely older than the window.
+    fn is_expired(&self, bucket: &Bucket, now: Instant) -> bool {
+        now.saturating_duration_since(bucket.start) >= self.window + self.bucket_len()
+    }
+
+    /// Start tracking the topic delivered messages provenance.
+    pub(crate) fn track_topic(&mut self, topic: TopicHash) {
+        self.topics.entry(topic).or_default();
+    }
+
+    /// Stop tracking the topic delivered messages provenance, dropping its statistics.
+    pub(crate) fn untrack_topic(&mut self, topic: &TopicHash) {
+        self.topics.remove(topic);
+    }
+
+    /// Account the provenance of a message delivered on the topic at `now`.
+    ///
+    /// The messages delivered on the topics not locally subscribed are ignored.
+    pub(crate) fn record(&mut self, topic: &TopicHash, provenance: Provenance, now: Instant) {
+        let bucket_len = self.bucket_len();
+        let window = self.window;
+        let Some(topic_window) = self.topics.get_mut(topic) else {
+            return;
+        };
+
+        while let Some(bucket) = topic_window.buckets.front() {
+            if now.saturating_duration_since(bucket.start) < window + bucket_len {
+                break;
+            }
+            topic_window.buckets.pop_front();
+        }
+
+        match topic_window.buckets.back_mut() {
+            Some(bucket) if now.saturating_duration_since(bucket.start) < bucket_len => {
+                bucket.stats.record(provenance);
+            }
+            _ => {
+                let mut stats = ProvenanceStats::default();
+                stats.record(provenance);
+                topic_window.buckets.push_back(Bucket { start: now, stats });
+            }
+        }
+    }
+
+    /// The provenance statistics of the topic over the window ending at `now`.
+    ///
+    /// Returns `None` if the topic is not locally subscribed.
+    pub(crate) fn stats(&self, topic: &TopicHash, now: Instant) -> Option<ProvenanceStats> {
+        let topic_window = self.topics.get(topic)?;
+        let stats = topic_window
+            .buckets
+            .iter()
+            .filter(|bucket| !self.is_expired(bucket, now))
+            .fold(ProvenanceStats::default(), |mut stats, bucket| {
+                stats.merge(&bucket.stats);
+                stats
+            });
+        Some(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_topic() -> TopicHash {
+        TopicHash::from_raw("test-topic")
+    }
+
+    #[test]
+    fn provenance_compares_the_propagation_source_with_the_author() {
+        //// Given
+        let author = PeerId::random();
+        let relay = PeerId::random();
+
+        //// When
+        let direct = Provenance::new(&author, Some(&author));
+        let relayed = Provenance::new(&relay, Some(&author));
+        let unknown = Provenance::new(&relay, None);
+
+        //// Then
+        assert_eq!(direct, Provenance::DirectFromAuthor);
+        assert_eq!(relayed, Provenance::Relayed);
+        assert_eq!(unknown, Provenance::Unknown);
+        assert_eq!(direct.is_direct_from_author(), Some(true));
+        assert_eq!(relayed.is_direct_from_author(), Some(false));
+        assert_eq!(unknown.is_direct_from_author(), None);
+    }
+
+    #[test]
+    fn direct_ratio_ignores_the_anonymous_messages() {
+        //// Given
+        let topic = new_test_topic();
+        let now = Instant::now();
+
+        let mut tracking = ProvenanceTracking::new(Duration::from_secs(10));
+        tracking.track_topic(topic.clone());
+
+        //// When
+        for provenance in [
+            Provenance::DirectFromAuthor,
+            Provenance::Relayed,
+            Provenance::Relayed,
+            Provenance::Relayed,
+            Provenance::Unknown,
+        ] {
+            tracking.record(&topic, provenance, now);
+        }
+
+        //// Then
+        let stats = tracking
+            .stats(&topic, now)
+            .expect("topic should be tracked");
+        assert_eq!((stats.direct, stats.relayed, stats.unknown), (1, 3, 1));
+        assert_eq!(stats.direct_ratio(), Some(0.25));
+    }
+
+    #[test]
+    fn messages_older_than_the_window_are_not_accounted() {
+        //// Given
+        let topic = new_test_topic();
+        let start = Instant::now();
+
+        let mut tracking = ProvenanceTracking::new(Duration::from_secs(10));
+        tracking.track_topic(topic.clone());
+
+        //// When
+        tracking.record(&topic, Provenance::Relayed, start);
+        tracking.record(
+            &topic,
+            Provenance::DirectFromAuthor,
+            start + Duration::from_secs(15),
+        );
+
+        //// Then
+        let stats = tracking
+            .stats(&topic, start + Duration::from_secs(15))
+            .expect("topic should be tracked");
+        assert_eq!((stats.direct, stats.relayed), (1, 0));
+        assert_eq!(stats.direct_ratio(), Some(1.0));
+    }
+
+    #[test]
+    fn untracked_topics_have_no_stats() {
+        //// Given
+        let topic = new_test_topic();
+        let now = Instant::now();
+
+        let mut tracking = ProvenanceTracking::new(Duration::from_secs(10));
+
+        //// When
+        tracking.record(&topic, Provenance::DirectFromAuthor, now);
+
+        //// Then
+        assert_eq!(tracking.stats(&topic, now), None);
+        assert_eq!(ProvenanceStats::default().direct_ratio(), None);
+    }
+}
@@ -0,0 +1,96 @@
+use libp2p::identity::PeerId;
+
+use crate::message_id::{MessageId, MessageIdFnError};
+use crate::topic::{TopicHash, TopicValidationError};
+
+/// Errors that can occur when publishing a message.
+///
+/// See [`Behaviour::publish`](crate::Behaviour::publish) for more details.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum PublishError {
+    /// The local node is not subscribed to the message topic.
+    #[error("not subscribed to topic: {0}")]
+    NotSubscribed(TopicHash),
+
+    /// The message topic is paused (see
+    /// [`Behaviour::pause_topic`](crate::Behaviour::pause_topic)).
+    #[error("topic paused: {0}")]
+    TopicPaused(TopicHash),
+
+    /// The local node is in listen-only mode (see
+    /// [`Behaviour::set_listen_only`](crate::Behaviour::set_listen_only)).
+    #[error("listen-only mode")]
+    ListenOnly,
+
+    /// The local node has no active connections to publish the message to.
+    #[error("no active connections")]
+    NoActiveConnections,
+
+    /// The message id function of the message topic failed to compute the message id.
+    #[error("message id computation failed: {0}")]
+    MessageIdComputation(MessageIdFnError),
+
+    /// The message encoded size, including its topic, key and signature, exceeds the maximum
+    /// publish size (see [`Config::max_publish_size`](crate::Config::max_publish_size)).
+    #[error("message too large: {size} bytes, max {max_size} bytes")]
+    MessageTooLarge {
+        /// The message encoded size, in bytes.
+        size: usize,
+        /// The maximum publish size, in bytes.
+        max_size: usize,
+    },
+
+    /// The message has a time-to-live, not supported by the chunked messages (see
+    /// [`Behaviour::publish_chunked`](crate::Behaviour::publish_chunked)).
+    #[error("time-to-live not supported by chunked messages")]
+    ChunkedTtl,
+
+    /// The chunk size is zero, or splits the message payload into more than `u32::MAX` chunks.
+    #[error("invalid chunk size: {0}")]
+    InvalidChunkSize(usize),
+
+    /// The encoded frame of a chunk message exceeds the maximum frame size (see
+    /// [`Config::max_frame_size`](crate::Config::max_frame_size)).
+    #[error("chunk too large: {size} bytes, max {max_size} bytes")]
+    ChunkTooLarge {
+        /// The chunk frame encoded size, in bytes.
+        size: usize,
+        /// The maximum frame size, in bytes.
+        max_size: usize,
+    },
+
+    /// The local node reply topic is longer than the request envelope supports (see
+    /// [`Behaviour::send_request`](crate::Behaviour::send_request)).
+    #[error("reply topic too long: {0}")]
+    ReplyTopicTooLong(TopicHash),
+
+    /// The reply topic fails the [topic validation](crate::Config::topic_validation), so it
+    /// cannot be subscribed to.
+    #[error("invalid reply topic: {0}")]
+    InvalidReplyTopic(TopicValidationError),
+}
+
+/// The receipt of a message published by the local node.
+///
+/// See [`Behaviour::publish_with_receipt`](crate::Behaviour::publish_with_receipt) for more
+/// details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PublishReceipt {
+    /// The message id, as computed by the topic's message id function.
+    pub message_id: MessageId,
+    /// The peer the message was sent to, if the topic is published in round-robin (see
+    /// [`DeliveryMode::RoundRobinPublish`](crate::DeliveryMode::RoundRobinPublish)).
+    ///
+    /// `None` if the message is broadcast, or if no connected peer is subscribed to the topic.
+    pub peer: Option<PeerId>,
+}
+
+impl PublishReceipt {
+    /// Create a new publish receipt.
+    #[must_use]
+    pub fn new(message_id: MessageId, peer: Option<PeerId>) -> Self {
+        Self { message_id, peer }
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use libp2p::identity::PeerId;
+
+use crate::topic::TopicHash;
+
+/// A subscription refresh awaiting the remote peer re-announcement.
+#[derive(Debug)]
+struct PendingRefresh {
+    /// The time the refresh window closes.
+    deadline: Instant,
+    /// The topics the peer was newly subscribed to during the refresh window.
+    topics_learned: Vec<TopicHash>,
+}
+
+/// Tracks the subscription refreshes exchanged with the remote peers (see
+/// [`Behaviour::request_subscription_refresh`](crate::Behaviour::request_subscription_refresh)).
+///
+/// The local subscriptions are re-sent to a peer at most once per cooldown period, whether on
+/// the application request or in answer to the peer re-announcing its subscriptions. This stops
+/// two nodes from answering each other's re-announcements forever.
+#[derive(Debug)]
+pub(crate) struct SubscriptionRefreshes {
+    /// The minimum time between two refreshes sent to the same peer.
+    cooldown: Duration,
+    /// The time after a refresh request during which the peer newly announced topics are learned.
+    window: Duration,
+    /// The last time the local subscriptions were re-sent, by peer.
+    last_sent: HashMap<PeerId, Instant>,
+    /// The refreshes requested by the local node, by peer.
+    pending: HashMap<PeerId, PendingRefresh>,
+}
+
+impl SubscriptionRefreshes {
+    /// Create a new subscription refreshes tracker.
+    pub(crate) fn new(cooldown: Duration, window: Duration) -> Self {
+        Self {
+            cooldown,
+            window,
+            last_sent: Default::default(),
+            pending: Default::default(),
+        }
+    }
+
+    /// Record the re-sending of the local subscriptions to the peer, unless the peer is in its
+    /// cooldown period. Returns `false` if the peer is in cooldown.
+    fn try_send(&mut self, peer: PeerId, now: Instant) -> bool {
+        if let Some(last_sent) = self.last_sent.get(&peer) {
+            if now.saturating_duration_since(*last_sent) < self.cooldown {
+                return false;
+            }
+        }
+
+        self.last_sent.insert(peer, now);
+        true
+    }
+
+    /// Register a refresh requested by the local node. Returns `false` if the peer is in its
+    /// cooldown period, and the refresh must not be sent.
+    pub(crate) fn request(&mut self, peer: PeerId, now: Instant) -> bool {
+        if !self.try_send(peer, now) {
+            return false;
+        }
+
+        self.pending.insert(
+            peer,
+            PendingRefresh {
+                deadline: now + self.window,
+                topics_learned: Vec::new(),
+            },
+        );
+        true
+    }
+
+    /// Whether to answer the peer re-announcing its subscriptions with the local subscriptions.
+    /// Returns `false` if the peer is in its cooldown period.
+    pub(crate) fn answer(&mut self, peer: PeerId, now: Instant) -> bool {
+        self.try_send(peer, now)
+    }
+
+    /// Record the topics the peer newly subscribed to, if a refresh of the peer is pending.
+    pub(crate) fn learned(&mut self, peer: &PeerId, topics: impl IntoIterator<Item = TopicHash>) {
+        if let Some(pending) = self.pending.get_mut(peer) {
+            pending.topics_learned.extend(topics);
+        }
+    }
+
+    /// Remove and return the refreshes whose window closed at `now`, along with the topics
+    /// learned from the peer. The refreshes that taught no new topic are not returned.
+    pub(crate) fn complete(&mut self, now: Instant) -> Vec<(PeerId, Vec<TopicHash>)> {
+        let completed = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(peer, _)| *peer)
+            .collect::<Vec<_>>();
+
+        completed
+            .into_iter()
+            .filter_map(|peer| {
+                let pending = self.pending.remove(&peer)?;
+                (!pending.topics_learned.is_empty()).then_some((peer, pending.topics_learned))
+            })
+            .collect()
+    }
+
+    /// Drop the peer refresh state, e.g., on disconnection.
+    pub(crate) fn peer_disconnected(&mut self, peer: &PeerId) {
+        self.last_sent.remove(peer);
+        self.pending.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_refreshes() -> SubscriptionRefreshes {
+        SubscriptionRefreshes::new(Duration::from_secs(10), Duration::from_secs(1))
+    }
+
+    #[test]
+    fn refresh_is_not_sent_twice_within_cooldown() {
+        //// Given
+        let mut refreshes = new_test_refreshes();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        //// When
+        let first = refreshes.request(peer, now);
+        let answer = refreshes.answer(peer, now + Duration::from_secs(1));
+        let second = refreshes.request(peer, now + Duration::from_secs(5));
+        let after_cooldown = refreshes.request(peer, now + Duration::from_secs(10));
+
+        //// Then
+        assert!(first);
+        assert!(!answer, "the re-announcement answer should be in cooldown");
+        assert!(!second, "the second refresh should be in cooldown");
+        assert!(after_cooldown);
+    }
+
+    #[test]
+    fn refresh_completes_with_topics_learned_within_window() {
+        //// Given
+        let mut refreshes = new_test_refreshes();
+        let peer = PeerId::random();
+        let other_peer = PeerId::random();
+        let topic = TopicHash::from_raw("/test/topic");
+        let now = Instant::now();
+
+        refreshes.request(peer, now);
+        refreshes.request(other_peer, now);
+
+        //// When
+        refreshes.learned(&peer, [topic.clone()]);
+        let within_window = refreshes.complete(now + Duration::from_millis(500));
+        let completed = refreshes.complete(now + Duration::from_secs(1));
+
+        //// Then
+        assert!(within_window.is_empty());
+        assert_eq!(completed, vec![(peer, vec![topic])]);
+        assert!(
+            refreshes.complete(now + Duration::from_secs(2)).is_empty(),
+            "the refreshes should be completed once"
+        );
+    }
+
+    #[test]
+    fn topics_learned_without_pending_refresh_are_ignored() {
+        //// Given
+        let mut refreshes = new_test_refreshes();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        //// When
+        refreshes.learned(&peer, [TopicHash::from_raw("/test/topic")]);
+
+        //// Then
+        assert!(refreshes.complete(now + Duration::from_secs(2)).is_empty());
+    }
+}
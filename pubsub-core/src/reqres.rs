@@ -0,0 +1,341 @@
+//! Request/response over pubsub topics.
+//!
+//! A request is published to a topic the responders are subscribed to (see
+//! [`Behaviour::send_request`](crate::Behaviour::send_request)), and the responses are published
+//! to the requester's reply topic (see [`reply_topic`] and
+//! [`Behaviour::send_response`](crate::Behaviour::send_response)). Requests and responses carry
+//! their correlation id in an envelope wrapping the message payload:
+//!
+//! ```text
+//! +-------+---------+------+------------+--------------+----------+---------------+
+//! | magic | version | kind | request id | reply-to len | reply-to | payload bytes |
+//! +-------+---------+------+------------+--------------+----------+---------------+
+//!     4        1        1         8             2        variable     variable
+//! ```
+//!
+//!  - `magic`: The `PSRR` ASCII bytes.
+//!  - `version`: The envelope format version. Currently, `1`.
+//!  - `kind`: `0` for a request, `1` for a response.
+//!  - `request id`: The request correlation id, big-endian encoded.
+//!  - `reply-to len`: The reply-to topic length in bytes, big-endian encoded. Requests only.
+//!  - `reply-to`: The UTF-8 encoded topic the responses are published to. Requests only.
+//!
+//! The responders decode the received request envelopes with [`RequestEnvelope::decode`]. The
+//! response envelopes are recognized by the requester and notified as
+//! [`Event::ResponseReceived`](crate::Event::ResponseReceived) events, instead of
+//! [`Event::MessageReceived`](crate::Event::MessageReceived) events.
+//!
+//! Nodes not supporting request/response see the envelope as part of the message payload.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Instant;
+
+use libp2p::identity::PeerId;
+
+use crate::topic::TopicHash;
+
+/// The envelope magic bytes.
+const REQRES_MAGIC: [u8; 4] = *b"PSRR";
+
+/// The envelope format version.
+const REQRES_VERSION: u8 = 1;
+
+/// The envelope header length in bytes, common to requests and responses.
+const REQRES_HEADER_LEN: usize = 4 + 1 + 1 + 8;
+
+/// The request envelope kind.
+const KIND_REQUEST: u8 = 0;
+
+/// The response envelope kind.
+const KIND_RESPONSE: u8 = 1;
+
+/// The reply topics prefix.
+const REPLY_TOPIC_PREFIX: &str = "/reqres/1/reply/";
+
+/// The correlation id of a request, unique per requester.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RequestId(u64);
+
+impl RequestId {
+    /// Create a new request id from its raw value.
+    #[must_use]
+    pub fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    /// The request id raw value.
+    #[must_use]
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The topic the responses to the requests of the given peer are published to.
+#[must_use]
+pub fn reply_topic(peer: &PeerId) -> TopicHash {
+    TopicHash::from_raw(format!("{REPLY_TOPIC_PREFIX}{peer}"))
+}
+
+/// A decoded request envelope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RequestEnvelope {
+    /// The request correlation id.
+    pub request_id: RequestId,
+    /// The topic the responses are published to.
+    pub reply_to: TopicHash,
+    /// The request payload.
+    pub payload: Vec<u8>,
+}
+
+impl RequestEnvelope {
+    /// Decode a request envelope from a message payload.
+    ///
+    /// Returns `None` if the data is not a valid request envelope.
+    #[must_use]
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let (KIND_REQUEST, request_id, rest) = decode_header(data)? else {
+            return None;
+        };
+
+        if rest.len() < 2 {
+            return None;
+        }
+        let (reply_to_len, rest) = rest.split_at(2);
+        let reply_to_len = u16::from_be_bytes(reply_to_len.try_into().ok()?) as usize;
+        if rest.len() < reply_to_len {
+            return None;
+        }
+        let (reply_to, payload) = rest.split_at(reply_to_len);
+        let reply_to = std::str::from_utf8(reply_to).ok()?;
+
+        Some(Self {
+            request_id,
+            reply_to: TopicHash::from_raw(reply_to),
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// A decoded response envelope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ResponseEnvelope {
+    /// The correlation id of the request this is a response to.
+    pub request_id: RequestId,
+    /// The response payload.
+    pub payload: Vec<u8>,
+}
+
+impl ResponseEnvelope {
+    /// Decode a response envelope from a message payload.
+    ///
+    /// Returns `None` if the data is not a valid response envelope.
+    #[must_use]
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let (KIND_RESPONSE, request_id, payload) = decode_header(data)? else {
+            return None;
+        };
+
+        Some(Self {
+            request_id,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// Decode the envelope common header into its kind, the request id and the rest of the data.
+fn decode_header(data: &[u8]) -> Option<(u8, RequestId, &[u8])> {
+    if data.len() < REQRES_HEADER_LEN {
+        return None;
+    }
+
+    let (header, rest) = data.split_at(REQRES_HEADER_LEN);
+    let (magic, header) = header.split_at(REQRES_MAGIC.len());
+    let (version, header) = header.split_at(1);
+    let (kind, request_id) = header.split_at(1);
+    if magic != REQRES_MAGIC || version[0] != REQRES_VERSION {
+        return None;
+    }
+
+    let request_id = RequestId(u64::from_be_bytes(request_id.try_into().ok()?));
+    Some((kind[0], request_id, rest))
+}
+
+/// Encode the envelope common header.
+fn encode_header(data: &mut Vec<u8>, kind: u8, request_id: RequestId) {
+    data.extend_from_slice(&REQRES_MAGIC);
+    data.push(REQRES_VERSION);
+    data.push(kind);
+    data.extend_from_slice(&request_id.0.to_be_bytes());
+}
+
+/// Wrap the payload into a request envelope.
+///
+/// Returns `None` if the reply-to topic is longer than the envelope supports.
+pub(crate) fn encode_request(
+    request_id: RequestId,
+    reply_to: &TopicHash,
+    payload: &[u8],
+) -> Option<Vec<u8>> {
+    let reply_to = reply_to.as_str().as_bytes();
+    let reply_to_len = u16::try_from(reply_to.len()).ok()?;
+
+    let mut data = Vec::with_capacity(REQRES_HEADER_LEN + 2 + reply_to.len() + payload.len());
+    encode_header(&mut data, KIND_REQUEST, request_id);
+    data.extend_from_slice(&reply_to_len.to_be_bytes());
+    data.extend_from_slice(reply_to);
+    data.extend_from_slice(payload);
+    Some(data)
+}
+
+/// Wrap the payload into a response envelope.
+pub(crate) fn encode_response(request_id: RequestId, payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(REQRES_HEADER_LEN + payload.len());
+    encode_header(&mut data, KIND_RESPONSE, request_id);
+    data.extend_from_slice(payload);
+    data
+}
+
+/// The local node's in-flight requests, awaiting a response until their deadline.
+#[derive(Debug, Default)]
+pub(crate) struct PendingRequests {
+    /// The next request id.
+    next_id: u64,
+    /// The in-flight requests deadlines, by request id.
+    deadlines: HashMap<RequestId, Instant>,
+}
+
+impl PendingRequests {
+    /// Register a new in-flight request, expiring at the given deadline.
+    pub(crate) fn insert(&mut self, deadline: Instant) -> RequestId {
+        let request_id = RequestId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.deadlines.insert(request_id, deadline);
+        request_id
+    }
+
+    /// Complete the in-flight request. Returns `false` if the request is unknown or timed out.
+    pub(crate) fn complete(&mut self, request_id: &RequestId) -> bool {
+        self.deadlines.remove(request_id).is_some()
+    }
+
+    /// Remove and return the requests whose deadline passed at `now`, in request order.
+    pub(crate) fn expire(&mut self, now: Instant) -> Vec<RequestId> {
+        let mut expired = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(request_id, _)| *request_id)
+            .collect::<Vec<_>>();
+        expired.sort_unstable();
+
+        for request_id in &expired {
+            self.deadlines.remove(request_id);
+        }
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn request_envelope_roundtrip() {
+        //// Given
+        let reply_to = reply_topic(&PeerId::random());
+        let data = encode_request(RequestId::new(42), &reply_to, b"request-payload")
+            .expect("reply-to topic to fit");
+
+        //// When
+        let request = RequestEnvelope::decode(&data);
+
+        //// Then
+        assert_eq!(
+            request,
+            Some(RequestEnvelope {
+                request_id: RequestId::new(42),
+                reply_to,
+                payload: b"request-payload".to_vec(),
+            })
+        );
+        assert_eq!(
+            ResponseEnvelope::decode(&data),
+            None,
+            "A request is not a response"
+        );
+    }
+
+    #[test]
+    fn response_envelope_roundtrip() {
+        //// Given
+        let data = encode_response(RequestId::new(7), b"response-payload");
+
+        //// When
+        let response = ResponseEnvelope::decode(&data);
+
+        //// Then
+        assert_eq!(
+            response,
+            Some(ResponseEnvelope {
+                request_id: RequestId::new(7),
+                payload: b"response-payload".to_vec(),
+            })
+        );
+        assert_eq!(
+            RequestEnvelope::decode(&data),
+            None,
+            "A response is not a request"
+        );
+    }
+
+    #[test]
+    fn invalid_envelopes_are_not_decoded() {
+        //// Given
+        let mut truncated = encode_request(RequestId::new(1), &reply_topic(&PeerId::random()), b"")
+            .expect("reply-to topic to fit");
+        truncated.truncate(truncated.len() - 1);
+
+        let mut unknown_version = encode_response(RequestId::new(1), b"payload");
+        unknown_version[4] = REQRES_VERSION + 1;
+
+        //// Then
+        assert_eq!(RequestEnvelope::decode(&truncated), None);
+        assert_eq!(ResponseEnvelope::decode(&unknown_version), None);
+        assert_eq!(ResponseEnvelope::decode(b"plain payload"), None);
+    }
+
+    #[test]
+    fn pending_requests_expire_once_past_their_deadline() {
+        //// Given
+        let now = Instant::now();
+        let mut pending = PendingRequests::default();
+
+        let short = pending.insert(now + Duration::from_millis(10));
+        let completed = pending.insert(now + Duration::from_millis(10));
+        let long = pending.insert(now + Duration::from_secs(10));
+
+        //// When
+        let completed_known = pending.complete(&completed);
+        let expired = pending.expire(now + Duration::from_secs(1));
+
+        //// Then
+        assert!(completed_known);
+        assert_eq!(expired, [short]);
+        assert!(
+            !pending.complete(&short),
+            "The expired request is forgotten"
+        );
+        assert!(pending.complete(&long));
+    }
+}
@@ -0,0 +1,410 @@
+//! The raw frames sampling (see [`Config::frame_sampling`](crate::Config::frame_sampling)).
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use libp2p::identity::PeerId;
+use prost::Message as _;
+use rand::Rng;
+
+use libp2p_pubsub_proto::pubsub::FrameProto;
+
+use crate::framing::{
+    Frame, GraftControlMessage, IHaveControlMessage, IWantControlMessage, Message,
+    PruneControlMessage, SubscriptionAction,
+};
+
+/// The raw frames sampling configuration (see
+/// [`Config::frame_sampling`](crate::Config::frame_sampling)).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplingConfig {
+    /// The probability, between `0.0` and `1.0`, a frame is sampled.
+    pub rate: f64,
+    /// The maximum number of sampled frames kept. Once reached, the oldest sampled frame is
+    /// evicted for every newly sampled one.
+    pub max_stored: usize,
+    /// The maximum number of bytes kept of each sampled frame. The larger frames are truncated.
+    pub max_frame_bytes: usize,
+    /// Whether the frames sent to the remote peers are sampled too.
+    pub sample_sent: bool,
+}
+
+impl SamplingConfig {
+    /// Create a new sampling configuration with the given sampling rate, keeping up to 64 received
+    /// frames, truncated to 4096 bytes.
+    #[must_use]
+    pub fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            max_stored: 64,
+            max_frame_bytes: 4096,
+            sample_sent: false,
+        }
+    }
+}
+
+/// The direction of a sampled frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FrameDirection {
+    /// The frame was received from the remote peer.
+    Received,
+    /// The frame was sent to the remote peer.
+    Sent,
+}
+
+/// A raw frame sampled by the behaviour, before any decoding (see
+/// [`Behaviour::sampled_frames`](crate::Behaviour::sampled_frames)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SampledFrame {
+    /// The frame direction.
+    pub direction: FrameDirection,
+    /// The remote peer the frame was received from, or sent to.
+    pub peer: PeerId,
+    /// The time the frame was sampled.
+    pub timestamp: SystemTime,
+    /// The frame bytes, truncated to the maximum sampled frame size (see
+    /// [`SamplingConfig::max_frame_bytes`]).
+    pub bytes: Bytes,
+    /// The frame size, in bytes, before truncation.
+    pub frame_len: usize,
+}
+
+impl SampledFrame {
+    /// Create a new sampled frame record.
+    #[must_use]
+    pub fn new(
+        direction: FrameDirection,
+        peer: PeerId,
+        timestamp: SystemTime,
+        bytes: Bytes,
+        frame_len: usize,
+    ) -> Self {
+        Self {
+            direction,
+            peer,
+            timestamp,
+            bytes,
+            frame_len,
+        }
+    }
+
+    /// Returns `true` if the frame was truncated when sampled.
+    #[must_use]
+    pub fn is_truncated(&self) -> bool {
+        self.bytes.len() < self.frame_len
+    }
+
+    /// Decode the sampled frame, and describe its parts with their validation results, one per
+    /// line.
+    ///
+    /// The frame is decoded with [`Frame::from_protobuf_bytes`], and each of its subscription
+    /// actions, messages and control messages is validated on its own, so the report points out
+    /// the invalid parts, dropped on reception. A truncated frame, or a frame carrying a checksum
+    /// trailer (see [`Config::frame_diagnostics`](crate::Config::frame_diagnostics)), may fail to
+    /// decode. The report format is not stable.
+    #[must_use]
+    pub fn decode_report(&self) -> String {
+        let mut report = String::new();
+
+        let direction = match self.direction {
+            FrameDirection::Received => "received from",
+            FrameDirection::Sent => "sent to",
+        };
+        let _ = write!(
+            report,
+            "frame {direction} {}, {} bytes",
+            self.peer, self.frame_len
+        );
+        if self.is_truncated() {
+            let _ = write!(report, " (truncated to {} bytes)", self.bytes.len());
+        }
+
+        match Frame::from_protobuf_bytes(self.bytes.clone()) {
+            Ok(_) => report.push_str("\nframe: valid"),
+            Err(err) => {
+                let _ = write!(report, "\nframe: invalid: {err}");
+            }
+        }
+
+        // Validate the frame parts one by one, as the received frames processing does.
+        let Ok(proto) = FrameProto::decode(self.bytes.clone()) else {
+            return report;
+        };
+
+        for (idx, subscription) in proto.subscriptions.into_iter().enumerate() {
+            let _ = match SubscriptionAction::try_from(subscription) {
+                Ok(SubscriptionAction::Subscribe(topic)) => {
+                    write!(report, "\nsubscription #{idx}: subscribe {topic}")
+                }
+                Ok(SubscriptionAction::Unsubscribe(topic)) => {
+                    write!(report, "\nsubscription #{idx}: unsubscribe {topic}")
+                }
+                Err(err) => write!(report, "\nsubscription #{idx}: invalid: {err}"),
+            };
+        }
+
+        for (idx, message) in proto.publish.into_iter().enumerate() {
+            let _ = match Message::try_from(message) {
+                Ok(message) => write!(
+                    report,
+                    "\nmessage #{idx}: topic {}, {} bytes",
+                    message.topic_str(),
+                    message.data().len()
+                ),
+                Err(err) => write!(report, "\nmessage #{idx}: invalid: {err}"),
+            };
+        }
+
+        let Some(control) = proto.control else {
+            return report;
+        };
+        for (idx, graft) in control.graft.into_iter().enumerate() {
+            let _ = match GraftControlMessage::try_from(graft) {
+                Ok(graft) => write!(report, "\ngraft #{idx}: topic {}", graft.topic_hash),
+                Err(_) => write!(report, "\ngraft #{idx}: invalid"),
+            };
+        }
+        for (idx, prune) in control.prune.into_iter().enumerate() {
+            let _ = match PruneControlMessage::try_from(prune) {
+                Ok(prune) => write!(report, "\nprune #{idx}: topic {}", prune.topic_hash),
+                Err(_) => write!(report, "\nprune #{idx}: invalid"),
+            };
+        }
+        for (idx, ihave) in control.ihave.into_iter().enumerate() {
+            let _ = match IHaveControlMessage::try_from(ihave) {
+                Ok(ihave) => write!(
+                    report,
+                    "\nihave #{idx}: topic {}, {} message ids",
+                    ihave.topic_hash,
+                    ihave.message_ids.len()
+                ),
+                Err(_) => write!(report, "\nihave #{idx}: invalid"),
+            };
+        }
+        for (idx, iwant) in control.iwant.into_iter().enumerate() {
+            let _ = match IWantControlMessage::try_from(iwant) {
+                Ok(iwant) => write!(
+                    report,
+                    "\niwant #{idx}: {} message ids",
+                    iwant.message_ids.len()
+                ),
+                Err(_) => write!(report, "\niwant #{idx}: invalid"),
+            };
+        }
+
+        report
+    }
+}
+
+/// Samples the raw frames, and keeps the sampled frames in a bounded ring buffer. Once full, the
+/// oldest sampled frame is evicted for every newly sampled one.
+#[derive(Debug)]
+pub(crate) struct FrameSampler {
+    /// The sampling configuration.
+    config: SamplingConfig,
+    /// The sampled frames, oldest first.
+    frames: VecDeque<SampledFrame>,
+}
+
+impl FrameSampler {
+    /// Create a new frame sampler.
+    pub(crate) fn new(config: SamplingConfig) -> Self {
+        Self {
+            config,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Sample the frame with the configured probability, if of the sampled direction.
+    ///
+    /// Returns `true` if the frame was sampled.
+    pub(crate) fn sample(
+        &mut self,
+        direction: FrameDirection,
+        peer: PeerId,
+        frame: &Bytes,
+    ) -> bool {
+        if direction == FrameDirection::Sent && !self.config.sample_sent {
+            return false;
+        }
+        if self.config.max_stored == 0 || !self.roll() {
+            return false;
+        }
+
+        // Copy the kept bytes, so the sampled frame does not retain the whole frame buffer.
+        let len = frame.len().min(self.config.max_frame_bytes);
+        let sampled = SampledFrame::new(
+            direction,
+            peer,
+            SystemTime::now(),
+            Bytes::copy_from_slice(&frame[..len]),
+            frame.len(),
+        );
+
+        if self.frames.len() >= self.config.max_stored {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(sampled);
+        true
+    }
+
+    /// Draw whether the next frame is sampled, with the configured probability.
+    fn roll(&self) -> bool {
+        // A NaN, or non-positive, rate samples no frame.
+        if self.config.rate.is_nan() || self.config.rate <= 0.0 {
+            return false;
+        }
+        if self.config.rate >= 1.0 {
+            return true;
+        }
+
+        rand::thread_rng().gen_bool(self.config.rate)
+    }
+
+    /// Iterate over the sampled frames, oldest first.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &SampledFrame> {
+        self.frames.iter()
+    }
+
+    /// Drop all the sampled frames.
+    pub(crate) fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framing::Message as FrameMessage;
+    use crate::topic::TopicHash;
+
+    use super::*;
+
+    fn new_test_sampler(rate: f64, max_stored: usize, max_frame_bytes: usize) -> FrameSampler {
+        FrameSampler::new(SamplingConfig {
+            rate,
+            max_stored,
+            max_frame_bytes,
+            sample_sent: false,
+        })
+    }
+
+    fn new_test_frame(len: usize) -> Bytes {
+        Bytes::from(vec![0xAB; len])
+    }
+
+    #[test]
+    fn frames_are_sampled_with_the_configured_probability() {
+        //// Given
+        let peer = PeerId::random();
+        let frame = new_test_frame(8);
+        let mut sampler = new_test_sampler(0.25, usize::MAX, 8);
+        let mut never = new_test_sampler(0.0, usize::MAX, 8);
+        let mut always = new_test_sampler(1.0, usize::MAX, 8);
+
+        //// When
+        let sampled = (0..10_000)
+            .filter(|_| sampler.sample(FrameDirection::Received, peer, &frame))
+            .count();
+        let never_sampled = (0..1_000)
+            .filter(|_| never.sample(FrameDirection::Received, peer, &frame))
+            .count();
+        let always_sampled = (0..1_000)
+            .filter(|_| always.sample(FrameDirection::Received, peer, &frame))
+            .count();
+
+        //// Then
+        // The expected count is 2500, with a standard deviation of ~43.
+        assert!(
+            (2_000..=3_000).contains(&sampled),
+            "The sampled count should be close to the rate, got {sampled}"
+        );
+        assert_eq!(never_sampled, 0);
+        assert_eq!(always_sampled, 1_000);
+    }
+
+    #[test]
+    fn sampled_frames_are_capped_to_the_most_recent() {
+        //// Given
+        let peer = PeerId::random();
+        let mut sampler = new_test_sampler(1.0, 3, 64);
+
+        //// When
+        for len in 1..=5 {
+            sampler.sample(FrameDirection::Received, peer, &new_test_frame(len));
+        }
+
+        //// Then
+        assert_eq!(
+            sampler
+                .iter()
+                .map(|frame| frame.frame_len)
+                .collect::<Vec<_>>(),
+            [3, 4, 5],
+            "The oldest sampled frames should be evicted"
+        );
+
+        sampler.clear();
+        assert_eq!(sampler.iter().count(), 0);
+    }
+
+    #[test]
+    fn sampled_frames_are_truncated() {
+        //// Given
+        let peer = PeerId::random();
+        let mut sampler = new_test_sampler(1.0, 8, 16);
+
+        //// When
+        sampler.sample(FrameDirection::Received, peer, &new_test_frame(100));
+        sampler.sample(FrameDirection::Received, peer, &new_test_frame(16));
+        let sent_sampled = sampler.sample(FrameDirection::Sent, peer, &new_test_frame(16));
+
+        //// Then
+        let frames = sampler.iter().collect::<Vec<_>>();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].bytes, new_test_frame(16));
+        assert_eq!(frames[0].frame_len, 100);
+        assert!(frames[0].is_truncated());
+        assert!(!frames[1].is_truncated());
+        assert!(
+            !sent_sampled,
+            "The sent frames should not be sampled, unless enabled"
+        );
+    }
+
+    #[test]
+    fn decode_report_describes_the_frame_parts_validation() {
+        //// Given
+        let topic = TopicHash::from_raw("/test/topic");
+        let mut proto = FrameProto::from(Frame::new_with_messages([FrameMessage::new(
+            topic.clone(),
+            b"payload".to_vec(),
+        )]));
+        // An invalid message, without a topic.
+        let mut invalid = proto.publish[0].clone();
+        invalid.topic = String::new();
+        proto.publish.push(invalid);
+        let frame = Bytes::from(proto.encode_to_vec());
+
+        let sampled = SampledFrame::new(
+            FrameDirection::Received,
+            PeerId::random(),
+            SystemTime::now(),
+            frame.clone(),
+            frame.len(),
+        );
+
+        //// When
+        let report = sampled.decode_report();
+
+        //// Then
+        let lines = report.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4, "Unexpected report: {report}");
+        assert!(lines[1].starts_with("frame: invalid: invalid message"));
+        assert_eq!(lines[2], "message #0: topic /test/topic, 7 bytes");
+        assert!(lines[3].starts_with("message #1: invalid: "));
+    }
+}
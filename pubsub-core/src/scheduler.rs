@@ -0,0 +1,307 @@
+//! Message forwarding scheduling (see
+//! [`Config::max_forwards_per_poll`](crate::Config::max_forwards_per_poll)).
+
+use std::collections::{HashMap, VecDeque};
+
+use libp2p::identity::PeerId;
+
+use crate::message::Priority;
+
+/// Smooths the message forwarding bursts over several behaviour poll cycles.
+///
+/// A single received frame carrying many messages on a topic with many subscribers results in
+/// thousands of forwards. The scheduler releases at most a global number of forwards, and at most
+/// a per-peer number of forwards, per poll cycle. The forwards above the limits are kept in
+/// order and released on the next poll cycles.
+///
+/// The forwards are released in the order they were scheduled, and a peer reaching its limit holds
+/// back all its later forwards, so the forwards toward the same peer are never reordered. The only
+/// exception are the high priority forwards (see [`Priority`]): they are released ahead of all the
+/// normal priority forwards, in the order they were scheduled.
+#[derive(Debug)]
+pub(crate) struct ForwardScheduler<T> {
+    /// The maximum number of forwards released per poll cycle.
+    max_forwards: usize,
+    /// The maximum number of forwards released to a single peer per poll cycle.
+    max_peer_forwards: usize,
+    /// The high priority forwards pending to be released, in order.
+    pending_high: VecDeque<(PeerId, T)>,
+    /// The normal priority forwards pending to be released, in order.
+    pending: VecDeque<(PeerId, T)>,
+    /// The number of pending forwards, by destination peer.
+    pending_by_peer: HashMap<PeerId, usize>,
+    /// The number of forwards released during the current poll cycle.
+    released: usize,
+    /// The number of forwards released during the current poll cycle, by destination peer.
+    released_by_peer: HashMap<PeerId, usize>,
+    /// The number of forwards discarded, cleared while pending or never scheduled, e.g., in
+    /// listen-only mode.
+    discarded: u64,
+}
+
+impl<T> ForwardScheduler<T> {
+    /// Create a new forward scheduler with the given per poll cycle limits. A zero limit is
+    /// treated as 1.
+    pub(crate) fn new(max_forwards: usize, max_peer_forwards: usize) -> Self {
+        Self {
+            max_forwards: max_forwards.max(1),
+            max_peer_forwards: max_peer_forwards.max(1),
+            pending_high: Default::default(),
+            pending: Default::default(),
+            pending_by_peer: Default::default(),
+            released: 0,
+            released_by_peer: Default::default(),
+            discarded: 0,
+        }
+    }
+
+    /// The number of forwards discarded, cleared while pending or never scheduled.
+    pub(crate) fn discarded(&self) -> u64 {
+        self.discarded
+    }
+
+    /// The number of forwards pending to be released.
+    pub(crate) fn len(&self) -> usize {
+        self.pending_high.len() + self.pending.len()
+    }
+
+    /// Returns `true` if no forward is pending to be released.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pending_high.is_empty() && self.pending.is_empty()
+    }
+
+    /// The number of forwards toward the peer pending to be released.
+    pub(crate) fn peer_len(&self, peer: &PeerId) -> usize {
+        self.pending_by_peer.get(peer).copied().unwrap_or_default()
+    }
+
+    /// Schedule a forward toward the `dest` peer.
+    pub(crate) fn schedule(&mut self, dest: PeerId, priority: Priority, forward: T) {
+        match priority {
+            Priority::High => self.pending_high.push_back((dest, forward)),
+            Priority::Normal => self.pending.push_back((dest, forward)),
+        }
+        *self.pending_by_peer.entry(dest).or_default() += 1;
+    }
+
+    /// Release the pending forwards, the high priority ones first, in order, up to the current
+    /// poll cycle limits.
+    pub(crate) fn release(&mut self) -> Vec<(PeerId, T)> {
+        let mut released = Vec::new();
+        if self.is_empty() || self.released >= self.max_forwards {
+            return released;
+        }
+
+        let pending_high = std::mem::take(&mut self.pending_high);
+        self.pending_high = self.release_queue(pending_high, &mut released);
+        let pending = std::mem::take(&mut self.pending);
+        self.pending = self.release_queue(pending, &mut released);
+
+        released
+    }
+
+    /// Release the forwards of the `pending` queue, in order, up to the current poll cycle limits,
+    /// and return the held back forwards.
+    fn release_queue(
+        &mut self,
+        mut pending: VecDeque<(PeerId, T)>,
+        released: &mut Vec<(PeerId, T)>,
+    ) -> VecDeque<(PeerId, T)> {
+        let mut held_back = VecDeque::new();
+        while let Some((dest, forward)) = pending.pop_front() {
+            if self.released >= self.max_forwards {
+                held_back.push_back((dest, forward));
+                held_back.append(&mut pending);
+                break;
+            }
+
+            // Hold back the forward, and the peer's later forwards, if the peer reached its limit.
+            let peer_released = self.released_by_peer.entry(dest).or_default();
+            if *peer_released >= self.max_peer_forwards {
+                held_back.push_back((dest, forward));
+                continue;
+            }
+
+            *peer_released += 1;
+            self.released += 1;
+            self.forward_released(&dest);
+            released.push((dest, forward));
+        }
+
+        held_back
+    }
+
+    /// Start a new poll cycle, resetting the released forwards counters.
+    pub(crate) fn end_cycle(&mut self) {
+        self.released = 0;
+        self.released_by_peer.clear();
+    }
+
+    /// Drop the forwards toward the disconnected peer, and return their number.
+    pub(crate) fn peer_disconnected(&mut self, peer: &PeerId) -> usize {
+        let Some(dropped) = self.pending_by_peer.remove(peer) else {
+            return 0;
+        };
+
+        self.pending_high.retain(|(dest, _)| dest != peer);
+        self.pending.retain(|(dest, _)| dest != peer);
+        dropped
+    }
+
+    /// Discard all the pending forwards, and return their number.
+    pub(crate) fn clear(&mut self) -> usize {
+        let dropped = self.len();
+        self.pending_high.clear();
+        self.pending.clear();
+        self.pending_by_peer.clear();
+        self.discard(dropped);
+        dropped
+    }
+
+    /// Account forwards discarded before being scheduled.
+    pub(crate) fn discard(&mut self, count: usize) {
+        self.discarded += count as u64;
+    }
+
+    /// Decrement the peer pending forwards counter.
+    fn forward_released(&mut self, peer: &PeerId) {
+        if let Some(pending) = self.pending_by_peer.get_mut(peer) {
+            *pending -= 1;
+            if *pending == 0 {
+                self.pending_by_peer.remove(peer);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Release the forwards of every poll cycle until none is pending, and return them by cycle.
+    fn release_all(scheduler: &mut ForwardScheduler<usize>) -> Vec<Vec<(PeerId, usize)>> {
+        let mut cycles = Vec::new();
+        while !scheduler.is_empty() {
+            cycles.push(scheduler.release());
+            scheduler.end_cycle();
+        }
+        cycles
+    }
+
+    #[test]
+    fn forwards_above_the_global_limit_are_released_on_the_next_cycles() {
+        //// Given
+        let peers = (0..100).map(|_| PeerId::random()).collect::<Vec<_>>();
+        let mut scheduler = ForwardScheduler::new(1000, 100);
+
+        // 50 messages forwarded to 100 peers.
+        for message in 0..50 {
+            for peer in &peers {
+                scheduler.schedule(*peer, Priority::Normal, message);
+            }
+        }
+
+        //// When
+        let cycles = release_all(&mut scheduler);
+
+        //// Then
+        assert_eq!(
+            cycles.iter().map(Vec::len).collect::<Vec<_>>(),
+            [1000, 1000, 1000, 1000, 1000]
+        );
+        assert_eq!(scheduler.len(), 0);
+    }
+
+    #[test]
+    fn forwards_above_the_peer_limit_are_released_on_the_next_cycles_in_order() {
+        //// Given
+        let (peer_a, peer_b) = (PeerId::random(), PeerId::random());
+        let mut scheduler = ForwardScheduler::new(1000, 2);
+
+        for message in 0..5 {
+            scheduler.schedule(peer_a, Priority::Normal, message);
+        }
+        scheduler.schedule(peer_b, Priority::Normal, 0);
+
+        //// When
+        let first_cycle = scheduler.release();
+        let pending_after_first_cycle = scheduler.peer_len(&peer_a);
+
+        // No more forwards are released to the peer until the next cycle.
+        let same_cycle = scheduler.release();
+        scheduler.end_cycle();
+
+        let next_cycles = release_all(&mut scheduler);
+
+        //// Then
+        assert_eq!(first_cycle, [(peer_a, 0), (peer_a, 1), (peer_b, 0)]);
+        assert_eq!(pending_after_first_cycle, 3);
+        assert!(same_cycle.is_empty());
+        assert_eq!(
+            next_cycles,
+            [vec![(peer_a, 2), (peer_a, 3)], vec![(peer_a, 4)]]
+        );
+    }
+
+    #[test]
+    fn high_priority_forwards_are_released_ahead_of_the_backed_up_normal_ones() {
+        //// Given
+        let peer = PeerId::random();
+        let mut scheduler = ForwardScheduler::new(1000, 2);
+
+        // The peer is backed up: it is sent up to 2 forwards per cycle.
+        for message in 0..10 {
+            scheduler.schedule(peer, Priority::Normal, message);
+        }
+        scheduler.schedule(peer, Priority::High, 10);
+
+        //// When
+        let first_cycle = scheduler.release();
+        scheduler.end_cycle();
+        let second_cycle = scheduler.release();
+
+        //// Then
+        assert_eq!(first_cycle, [(peer, 10), (peer, 0)]);
+        assert_eq!(second_cycle, [(peer, 1), (peer, 2)]);
+        assert_eq!(scheduler.peer_len(&peer), 7);
+    }
+
+    #[test]
+    fn disconnected_peer_forwards_are_dropped() {
+        //// Given
+        let (peer_a, peer_b) = (PeerId::random(), PeerId::random());
+        let mut scheduler = ForwardScheduler::new(1, 1);
+
+        scheduler.schedule(peer_a, Priority::Normal, 0);
+        scheduler.schedule(peer_b, Priority::Normal, 0);
+        scheduler.schedule(peer_a, Priority::Normal, 1);
+
+        //// When
+        let dropped = scheduler.peer_disconnected(&peer_a);
+
+        //// Then
+        assert_eq!(dropped, 2);
+        assert_eq!(scheduler.peer_len(&peer_a), 0);
+        assert_eq!(release_all(&mut scheduler), [vec![(peer_b, 0)]]);
+    }
+
+    #[test]
+    fn cleared_forwards_are_dropped() {
+        //// Given
+        let (peer_a, peer_b) = (PeerId::random(), PeerId::random());
+        let mut scheduler = ForwardScheduler::new(1, 1);
+
+        scheduler.schedule(peer_a, Priority::High, 0);
+        scheduler.schedule(peer_b, Priority::Normal, 0);
+        scheduler.schedule(peer_a, Priority::Normal, 1);
+
+        //// When
+        let dropped = scheduler.clear();
+
+        //// Then
+        assert_eq!(dropped, 3);
+        assert_eq!(scheduler.discarded(), 3);
+        assert_eq!(scheduler.peer_len(&peer_a), 0);
+        assert!(scheduler.release().is_empty());
+    }
+}
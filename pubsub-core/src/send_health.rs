@@ -0,0 +1,321 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use libp2p::identity::PeerId;
+
+/// The number of buckets the send health window is split into.
+const WINDOW_BUCKETS: u32 = 32;
+
+/// A peer outbound send health transition.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SendHealthChange {
+    /// The peer connectivity is suspected to be asymmetric.
+    Suspected {
+        /// The ratio of failed to attempted sends over the window.
+        fail_ratio: f64,
+    },
+    /// The peer connectivity is no longer suspected to be asymmetric.
+    Recovered,
+}
+
+/// The send attempts started in a time bucket.
+#[derive(Debug)]
+struct SendBucket {
+    /// The bucket start time.
+    start: Instant,
+    /// The send attempts in the bucket.
+    attempts: usize,
+    /// The failed send attempts in the bucket.
+    failed: usize,
+}
+
+/// The outbound send health of a peer.
+#[derive(Debug, Default)]
+struct PeerSendHealth {
+    /// The send attempts over the window, bucketed by time, from the oldest to the most recent.
+    buckets: VecDeque<SendBucket>,
+    /// The number of send attempts over the window.
+    attempts: usize,
+    /// The number of failed send attempts over the window.
+    failed: usize,
+    /// The time the last frame was received from the peer.
+    last_received: Option<Instant>,
+    /// Whether the peer connectivity is suspected to be asymmetric.
+    suspected: bool,
+}
+
+impl PeerSendHealth {
+    /// Record a send attempt.
+    fn record(&mut self, failed: bool, bucket_len: Duration, now: Instant) {
+        let bucket = match self.buckets.back_mut() {
+            Some(bucket) if now.saturating_duration_since(bucket.start) < bucket_len => bucket,
+            _ => {
+                self.buckets.push_back(SendBucket {
+                    start: now,
+                    attempts: 0,
+                    failed: 0,
+                });
+                self.buckets.back_mut().expect("bucket just pushed")
+            }
+        };
+
+        bucket.attempts += 1;
+        self.attempts += 1;
+        if failed {
+            bucket.failed += 1;
+            self.failed += 1;
+        }
+    }
+
+    /// Drop the send attempts that fell out of the window.
+    fn expire(&mut self, window: Duration, now: Instant) {
+        while let Some(bucket) = self.buckets.front() {
+            if now.saturating_duration_since(bucket.start) < window {
+                break;
+            }
+            self.attempts -= bucket.attempts;
+            self.failed -= bucket.failed;
+            self.buckets.pop_front();
+        }
+    }
+
+    /// The ratio of failed to attempted sends over the window.
+    fn fail_ratio(&self) -> f64 {
+        if self.attempts == 0 {
+            return 0.0;
+        }
+        self.failed as f64 / self.attempts as f64
+    }
+}
+
+/// Tracks the remote peers outbound send health, to detect the peers with asymmetric
+/// connectivity (see [`Behaviour::is_connectivity_asymmetric`](
+/// crate::Behaviour::is_connectivity_asymmetric)).
+///
+/// A peer whose frames still reach the local node while most of the frames sent to it fail, e.g.,
+/// because the peer can open substreams to the local node but not the other way around, is
+/// suspected of asymmetric connectivity. A peer is suspected once, over the sliding window, at
+/// least `min_sends` frames were sent to it, the ratio of failed sends exceeds the threshold, and
+/// a frame was received from it. The suspicion is dropped once the conditions no longer hold.
+///
+/// The frames dropped by the connection handlers before being sent are also accounted.
+#[derive(Debug)]
+pub(crate) struct SendHealthTracker {
+    /// The send health sliding window.
+    window: Duration,
+    /// The failed to attempted sends ratio above which a peer is suspected.
+    threshold: f64,
+    /// The minimum number of send attempts over the window to suspect a peer.
+    min_sends: usize,
+    /// The send health, by peer.
+    peers: HashMap<PeerId, PeerSendHealth>,
+    /// The number of frames dropped by the connection handlers because their deadline passed
+    /// while queued.
+    frames_expired: u64,
+    /// The number of queued frames revoked before being sent by the connection handlers.
+    frames_cancelled: u64,
+}
+
+impl SendHealthTracker {
+    /// Create a new send health tracker.
+    pub(crate) fn new(window: Duration, threshold: f64, min_sends: usize) -> Self {
+        Self {
+            window,
+            threshold,
+            min_sends,
+            peers: Default::default(),
+            frames_expired: 0,
+            frames_cancelled: 0,
+        }
+    }
+
+    /// The number of frames dropped by the connection handlers because their deadline passed
+    /// while queued.
+    pub(crate) fn frames_expired(&self) -> u64 {
+        self.frames_expired
+    }
+
+    /// The number of queued frames revoked before being sent by the connection handlers.
+    pub(crate) fn frames_cancelled(&self) -> u64 {
+        self.frames_cancelled
+    }
+
+    /// Whether the peer connectivity is suspected to be asymmetric.
+    pub(crate) fn is_suspected(&self, peer: &PeerId) -> bool {
+        self.peers
+            .get(peer)
+            .map_or(false, |health| health.suspected)
+    }
+
+    /// Record a frame received from the peer.
+    pub(crate) fn record_received(&mut self, peer: PeerId, now: Instant) {
+        self.peers.entry(peer).or_default().last_received = Some(now);
+    }
+
+    /// Record a send attempt to the peer, and return the peer send health transition, if any.
+    pub(crate) fn record_send(
+        &mut self,
+        peer: PeerId,
+        failed: bool,
+        now: Instant,
+    ) -> Option<SendHealthChange> {
+        let health = self.peers.entry(peer).or_default();
+        health.record(failed, self.window / WINDOW_BUCKETS, now);
+
+        Self::evaluate(self.window, self.threshold, self.min_sends, health, now)
+    }
+
+    /// Record the queued frames dropped by a connection handler before being sent.
+    pub(crate) fn record_dropped(&mut self, expired: usize, cancelled: usize) {
+        self.frames_expired += expired as u64;
+        self.frames_cancelled += cancelled as u64;
+    }
+
+    /// Advance the sliding window, and return the peers send health transitions.
+    pub(crate) fn heartbeat(&mut self, now: Instant) -> Vec<(PeerId, SendHealthChange)> {
+        let changes = self
+            .peers
+            .iter_mut()
+            .filter_map(|(peer, health)| {
+                Self::evaluate(self.window, self.threshold, self.min_sends, health, now)
+                    .map(|change| (*peer, change))
+            })
+            .collect();
+
+        // Forget the peers with no recent activity.
+        self.peers.retain(|_, health| {
+            health.suspected
+                || health.attempts > 0
+                || health
+                    .last_received
+                    .map_or(false, |at| now.saturating_duration_since(at) < self.window)
+        });
+
+        changes
+    }
+
+    /// Drop the peer send health, e.g., on disconnection.
+    pub(crate) fn peer_disconnected(&mut self, peer: &PeerId) {
+        self.peers.remove(peer);
+    }
+
+    /// Re-evaluate the peer send health at `now`, and return its transition, if any.
+    fn evaluate(
+        window: Duration,
+        threshold: f64,
+        min_sends: usize,
+        health: &mut PeerSendHealth,
+        now: Instant,
+    ) -> Option<SendHealthChange> {
+        health.expire(window, now);
+
+        let receiving = health
+            .last_received
+            .map_or(false, |at| now.saturating_duration_since(at) < window);
+        let fail_ratio = health.fail_ratio();
+        let suspected = receiving && health.attempts >= min_sends && fail_ratio > threshold;
+
+        match (health.suspected, suspected) {
+            (false, true) => {
+                health.suspected = true;
+                Some(SendHealthChange::Suspected { fail_ratio })
+            }
+            (true, false) => {
+                health.suspected = false;
+                Some(SendHealthChange::Recovered)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_tracker() -> SendHealthTracker {
+        SendHealthTracker::new(Duration::from_secs(30), 0.5, 4)
+    }
+
+    #[test]
+    fn failing_sends_to_a_receiving_peer_are_suspected() {
+        //// Given
+        let mut tracker = new_test_tracker();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        tracker.record_received(peer, now);
+
+        //// When
+        let changes = (0..4)
+            .map(|_| tracker.record_send(peer, true, now))
+            .collect::<Vec<_>>();
+
+        //// Then
+        assert_eq!(
+            changes,
+            vec![
+                None,
+                None,
+                None,
+                Some(SendHealthChange::Suspected { fail_ratio: 1.0 })
+            ]
+        );
+        assert!(tracker.is_suspected(&peer));
+    }
+
+    #[test]
+    fn failing_sends_to_a_silent_peer_are_not_suspected() {
+        //// Given
+        let mut tracker = new_test_tracker();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        //// When
+        for _ in 0..8 {
+            tracker.record_send(peer, true, now);
+        }
+
+        //// Then
+        assert!(!tracker.is_suspected(&peer));
+    }
+
+    #[test]
+    fn mostly_successful_sends_are_not_suspected() {
+        //// Given
+        let mut tracker = new_test_tracker();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        tracker.record_received(peer, now);
+
+        //// When
+        for failed in [true, false, true, false, false, true, false, false] {
+            tracker.record_send(peer, failed, now);
+        }
+
+        //// Then
+        assert!(!tracker.is_suspected(&peer));
+    }
+
+    #[test]
+    fn suspicion_is_dropped_once_the_failures_leave_the_window() {
+        //// Given
+        let mut tracker = new_test_tracker();
+        let peer = PeerId::random();
+        let start = Instant::now();
+
+        tracker.record_received(peer, start);
+        for _ in 0..4 {
+            tracker.record_send(peer, true, start);
+        }
+        assert!(tracker.is_suspected(&peer));
+
+        //// When
+        let changes = tracker.heartbeat(start + Duration::from_secs(31));
+
+        //// Then
+        assert_eq!(changes, vec![(peer, SendHealthChange::Recovered)]);
+        assert!(!tracker.is_suspected(&peer));
+    }
+}
@@ -1,5 +1,10 @@
 pub mod connections;
+pub mod dialer;
 pub mod framing;
 pub mod message_cache;
 pub mod message_id;
+pub mod ordering;
+pub mod reassembly;
+pub mod subscription_announce;
+pub mod subscription_sync;
 pub mod subscriptions;
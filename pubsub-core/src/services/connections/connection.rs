@@ -1,5 +1,11 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
 use libp2p::Multiaddr;
 
+/// The maximum number of remote address changes kept in a connection address history.
+pub const MAX_ADDRESS_HISTORY_LEN: usize = 4;
+
 /// The direction of a connection.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ConnectionDirection {
@@ -27,6 +33,25 @@ pub enum ConnectionState {
     /// This is the state of a connection once it is established and the substreams negotiated.
     /// In this state, the connection is ready to be used.
     Established,
+
+    /// The connection handler was disabled, e.g., the outbound substream could not be
+    /// negotiated.
+    ///
+    /// In this state, the connection is still open, but it can no longer be used to send frames.
+    Disabled,
+}
+
+/// A connection remote address change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressChange {
+    /// The previous remote address.
+    pub old_addr: Multiaddr,
+
+    /// The new remote address.
+    pub new_addr: Multiaddr,
+
+    /// The instant the address changed.
+    pub timestamp: Instant,
 }
 
 /// A connection.
@@ -46,6 +71,11 @@ pub struct Connection {
 
     /// The connection remote address.
     remote_addr: Multiaddr,
+
+    /// The last remote address changes, from the oldest to the most recent.
+    ///
+    /// Only the last [`MAX_ADDRESS_HISTORY_LEN`] changes are kept.
+    address_history: VecDeque<AddressChange>,
 }
 
 impl Connection {
@@ -56,6 +86,7 @@ impl Connection {
         Self {
             local_addr: Some(local_addr),
             remote_addr,
+            address_history: VecDeque::new(),
             state: ConnectionState::Connecting,
             direction: ConnectionDirection::Inbound,
         }
@@ -68,6 +99,7 @@ impl Connection {
         Self {
             local_addr: None,
             remote_addr,
+            address_history: VecDeque::new(),
             state: ConnectionState::Connecting,
             direction: ConnectionDirection::Outbound,
         }
@@ -78,9 +110,36 @@ impl Connection {
         self.state = state;
     }
 
-    /// Update connection remote address.
+    /// Update connection remote address, and record the change in the address history.
     pub fn set_remote_address(&mut self, remote_addr: Multiaddr) {
-        self.remote_addr = remote_addr;
+        let old_addr = std::mem::replace(&mut self.remote_addr, remote_addr.clone());
+
+        if self.address_history.len() == MAX_ADDRESS_HISTORY_LEN {
+            self.address_history.pop_front();
+        }
+        self.address_history.push_back(AddressChange {
+            old_addr,
+            new_addr: remote_addr,
+            timestamp: Instant::now(),
+        });
+    }
+
+    /// The connection direction.
+    #[must_use]
+    pub fn direction(&self) -> ConnectionDirection {
+        self.direction
+    }
+
+    /// The connection remote address.
+    #[must_use]
+    pub fn remote_addr(&self) -> &Multiaddr {
+        &self.remote_addr
+    }
+
+    /// The last remote address changes, from the oldest to the most recent.
+    #[must_use]
+    pub fn address_history(&self) -> &VecDeque<AddressChange> {
+        &self.address_history
     }
 
     /// Whether the connection is in established state.
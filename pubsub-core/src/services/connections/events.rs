@@ -3,6 +3,8 @@ use libp2p::identity::PeerId;
 use libp2p::swarm::ConnectionId;
 use libp2p::Multiaddr;
 
+use super::connection::ConnectionDirection;
+
 /// The events emitted by libp2p's [`Swarm`](libp2p::swarm::Swarm)'s connection handling logic.
 #[derive(Debug, Clone)]
 pub enum ServiceIn {
@@ -36,6 +38,13 @@ pub enum ServiceIn {
     },
     /// Inform the behaviour that a connection event, coming from the swarm, happened.
     SwarmEvent(SwarmEvent),
+    /// The connection handler of an established connection was disabled.
+    ///
+    /// The connection is no longer used to send frames, but it remains registered until closed.
+    ConnectionDisabled {
+        connection_id: ConnectionId,
+        peer_id: PeerId,
+    },
 }
 
 impl ServiceIn {
@@ -102,7 +111,12 @@ pub enum ServiceOut {
     ///
     /// As peers are removed from the connection service when they are disconnected, when a
     /// previously disconnected peer is reconnected, this event will be emitted again.
-    NewPeerConnected(PeerId),
+    NewPeerConnected {
+        /// The connected peer.
+        peer: PeerId,
+        /// The direction of the first established connection with the peer.
+        first_connection_direction: ConnectionDirection,
+    },
     /// This event is emitted when all connections to a peer are closed. In this case the peer is
     /// removed from the connection service.
     PeerDisconnected(PeerId),
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 use libp2p::identity::PeerId;
 use libp2p::swarm::ConnectionId;
@@ -6,7 +6,7 @@ use libp2p::Multiaddr;
 
 use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
 
-use super::connection::{Connection, ConnectionState};
+use super::connection::{AddressChange, Connection, ConnectionDirection, ConnectionState};
 use super::events::{ServiceIn, ServiceOut, SwarmEvent};
 
 /// Manages the connections of the floodsub protocol behaviour.
@@ -131,7 +131,30 @@ impl ConnectionsService {
         }
     }
 
-    /// Update the connection state of the connection with the given ID. It is a no-op if the
+    /// Check if any of the given connections is established and not disabled.
+    fn has_usable_connection(&self, connections: &[ConnectionId]) -> bool {
+        connections.iter().any(|id| {
+            self.connections
+                .get(id)
+                .map_or(false, Connection::is_established)
+        })
+    }
+
+    /// Count the given established connections by direction, as `(inbound, outbound)`.
+    fn count_directions<'a>(
+        &self,
+        connections: impl IntoIterator<Item = &'a ConnectionId>,
+    ) -> (usize, usize) {
+        connections
+            .into_iter()
+            .filter_map(|id| self.connections.get(id))
+            .fold((0, 0), |(inbound, outbound), conn| match conn.direction() {
+                ConnectionDirection::Inbound => (inbound + 1, outbound),
+                ConnectionDirection::Outbound => (inbound, outbound + 1),
+            })
+    }
+
+    /// Update the remote address of the connection with the given ID. It is a no-op if the
     /// connection does not exist.
     fn update_connection_remote_address(
         &mut self,
@@ -154,19 +177,77 @@ impl ConnectionsService {
             .map_or(0, |v| v.len())
     }
 
+    /// Returns the number of connections established with the given peer by direction, as
+    /// `(inbound, outbound)`.
+    #[must_use]
+    pub fn peer_connection_directions(&self, peer: &PeerId) -> (usize, usize) {
+        self.peer_active_connections
+            .get(peer)
+            .map_or((0, 0), |conns| self.count_directions(conns))
+    }
+
+    /// Returns the number of established connections with all the peers by direction, as
+    /// `(inbound, outbound)`.
+    #[must_use]
+    pub fn connection_directions(&self) -> (usize, usize) {
+        self.count_directions(self.peer_active_connections.values().flatten())
+    }
+
+    /// Returns `true` if the peer has at least one connection in established state.
+    ///
+    /// The peers whose connections are all disabled are not active.
+    #[must_use]
+    pub fn is_active(&self, peer: &PeerId) -> bool {
+        self.peer_active_connections
+            .get(peer)
+            .map_or(false, |conns| self.has_usable_connection(conns))
+    }
+
     /// Get a list of all peers with at least one established connections.
+    ///
+    /// The peers whose connections are all disabled are not included.
     #[must_use]
     pub fn active_peers(&self) -> Vec<PeerId> {
         self.peer_active_connections
-            .keys()
-            .cloned()
+            .iter()
+            .filter(|(_, conns)| self.has_usable_connection(conns))
+            .map(|(peer, _)| *peer)
             .collect::<Vec<_>>()
     }
 
     /// Get then number of peers with at least one connection in established state.
+    ///
+    /// The peers whose connections are all disabled are not counted.
     #[must_use]
     pub fn active_peers_count(&self) -> usize {
-        self.peer_active_connections.len()
+        self.peer_active_connections
+            .values()
+            .filter(|conns| self.has_usable_connection(conns))
+            .count()
+    }
+
+    /// Returns the current remote address of the connection with the given ID.
+    ///
+    /// If the connection does not exist, this returns `None`.
+    #[must_use]
+    pub fn connection_remote_addr(&self, connection: ConnectionId) -> Option<&Multiaddr> {
+        self.connections
+            .get(&connection)
+            .map(Connection::remote_addr)
+    }
+
+    /// Returns the last remote address changes of the connection with the given ID, from the
+    /// oldest to the most recent.
+    ///
+    /// If the connection does not exist, this returns `None`.
+    #[must_use]
+    pub fn connection_address_history(
+        &self,
+        connection: ConnectionId,
+    ) -> Option<&VecDeque<AddressChange>> {
+        self.connections
+            .get(&connection)
+            .map(Connection::address_history)
     }
 }
 
@@ -203,6 +284,15 @@ impl EventHandler for ConnectionsService {
                 tracing::trace!(peer = %peer_id, "Established outbound connection");
                 self.register_outbound(connection_id, peer_id, remote_addr.clone());
             }
+            ServiceIn::ConnectionDisabled {
+                connection_id,
+                peer_id,
+            } => {
+                tracing::trace!(peer = %peer_id, "Connection disabled");
+                if let Some(conn) = self.connections.get_mut(&connection_id) {
+                    conn.set_state(ConnectionState::Disabled);
+                }
+            }
             ServiceIn::SwarmEvent(swarm_ev) => match swarm_ev {
                 SwarmEvent::ConnectionEstablished {
                     connection_id,
@@ -217,7 +307,12 @@ impl EventHandler for ConnectionsService {
 
                     // If this is the first connection with the peer, emit a `NewPeerConnected` event.
                     if self.peer_connections_count(&peer_id) == 1 {
-                        svc_cx.emit(ServiceOut::NewPeerConnected(peer_id));
+                        if let Some(conn) = self.connections.get(&connection_id) {
+                            svc_cx.emit(ServiceOut::NewPeerConnected {
+                                peer: peer_id,
+                                first_connection_direction: conn.direction(),
+                            });
+                        }
                     }
                 }
                 SwarmEvent::ConnectionClosed {
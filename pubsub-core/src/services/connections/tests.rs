@@ -1,6 +1,7 @@
 use std::net::Ipv4Addr;
 
 use assert_matches::assert_matches;
+use libp2p::core::{ConnectedPoint, Endpoint};
 use libp2p::identity::PeerId;
 use libp2p::swarm::ConnectionId;
 use libp2p::Multiaddr;
@@ -9,6 +10,7 @@ use rand::Rng;
 use testlib;
 use testlib::service::noop_context;
 
+use super::connection::{ConnectionDirection, MAX_ADDRESS_HISTORY_LEN};
 use super::{ConnectionsInEvent, ConnectionsOutEvent, ConnectionsService, ConnectionsSwarmEvent};
 
 /// Convenience function to create a new `ConnectionId` for testing.
@@ -219,8 +221,9 @@ fn emit_new_peer_connected_event_on_first_inbound_connection() {
 
     // Assert output events
     assert_eq!(output_events.len(), 1, "Only one event should be emitted");
-    assert_matches!(output_events[0], ConnectionsOutEvent::NewPeerConnected(peer_id) => {
-            assert_eq!(peer_id, peer_id);
+    assert_matches!(output_events[0], ConnectionsOutEvent::NewPeerConnected { peer, first_connection_direction } => {
+        assert_eq!(peer, remote_peer_id);
+        assert_eq!(first_connection_direction, ConnectionDirection::Inbound);
     }, "A NewPeerConnected event for peer should be emitted");
 }
 
@@ -275,8 +278,9 @@ fn emit_new_peer_connected_event_on_first_outbound_connection() {
 
     // Assert output events
     assert_eq!(output_events.len(), 1, "Only one event should be emitted");
-    assert_matches!(output_events[0], ConnectionsOutEvent::NewPeerConnected(peer_id) => {
-            assert_eq!(peer_id, peer_id);
+    assert_matches!(output_events[0], ConnectionsOutEvent::NewPeerConnected { peer, first_connection_direction } => {
+        assert_eq!(peer, remote_peer_id);
+        assert_eq!(first_connection_direction, ConnectionDirection::Outbound);
     }, "A NewPeerConnected event for peer should be emitted");
 }
 
@@ -388,7 +392,215 @@ fn emit_peer_disconnected_event_when_no_remaining_connections() {
 }
 
 #[test]
-#[ignore]
+fn count_established_connections_by_direction() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<ConnectionsService>();
+
+    let local_addr = new_test_multiaddr();
+    let remote_addr = new_test_multiaddr();
+    let peer_a = new_test_peer_id();
+    let peer_b = new_test_peer_id();
+
+    //// When
+    // Simulate one inbound and two outbound connections with peer A, and one inbound connection
+    // with peer B.
+    let input_events = itertools::chain!(
+        new_inbound_connection_seq(
+            new_test_connection_id(),
+            peer_a,
+            local_addr.clone(),
+            remote_addr.clone(),
+        ),
+        new_outbound_connection_seq(new_test_connection_id(), peer_a, remote_addr.clone()),
+        new_outbound_connection_seq(new_test_connection_id(), peer_a, remote_addr.clone()),
+        new_inbound_connection_seq(
+            new_test_connection_id(),
+            peer_b,
+            local_addr.clone(),
+            remote_addr.clone(),
+        ),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(service.peer_connection_directions(&peer_a), (1, 2));
+    assert_eq!(service.peer_connection_directions(&peer_b), (1, 0));
+    assert_eq!(
+        service.peer_connection_directions(&new_test_peer_id()),
+        (0, 0),
+        "Unknown peers should have no connections"
+    );
+    assert_eq!(service.connection_directions(), (2, 2));
+
+    assert_eq!(
+        output_events.len(),
+        2,
+        "One event per peer should be emitted"
+    );
+    assert_matches!(output_events[0], ConnectionsOutEvent::NewPeerConnected { peer, first_connection_direction } => {
+        assert_eq!(peer, peer_a);
+        assert_eq!(first_connection_direction, ConnectionDirection::Inbound);
+    });
+    assert_matches!(output_events[1], ConnectionsOutEvent::NewPeerConnected { peer, first_connection_direction } => {
+        assert_eq!(peer, peer_b);
+        assert_eq!(first_connection_direction, ConnectionDirection::Inbound);
+    });
+}
+
+#[test]
+fn connection_direction_is_kept_when_other_direction_connection_closes() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<ConnectionsService>();
+
+    let inbound_connection_id = new_test_connection_id();
+    let outbound_connection_id = new_test_connection_id();
+
+    let local_addr = new_test_multiaddr();
+    let remote_addr = new_test_multiaddr();
+    let remote_peer_id = new_test_peer_id();
+
+    // Simulate two connections established to the same peer
+    let conn_established_events = itertools::chain!(
+        new_inbound_connection_seq(
+            inbound_connection_id,
+            remote_peer_id,
+            local_addr.clone(),
+            remote_addr.clone(),
+        ),
+        new_outbound_connection_seq(outbound_connection_id, remote_peer_id, remote_addr.clone()),
+    );
+    testlib::service::inject_events(&mut service, conn_established_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    // Simulate the inbound connection close
+    let conn_closed_events = new_connection_closed_seq(inbound_connection_id, remote_peer_id);
+    testlib::service::inject_events(&mut service, conn_closed_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        service.peer_connection_directions(&remote_peer_id),
+        (0, 1),
+        "Only the outbound connection should remain"
+    );
+    assert_eq!(service.connection_directions(), (0, 1));
+    assert!(output_events.is_empty(), "No events should be emitted");
+}
+
+/// Create a sequence of events that simulate a connection remote address change.
+fn new_address_change_seq(
+    connection_id: ConnectionId,
+    peer_id: PeerId,
+    old_remote_addr: Multiaddr,
+    new_remote_addr: Multiaddr,
+) -> impl IntoIterator<Item = ConnectionsInEvent> {
+    [ConnectionsInEvent::SwarmEvent(
+        ConnectionsSwarmEvent::AddressChange {
+            connection_id,
+            peer_id,
+            old: ConnectedPoint::Dialer {
+                address: old_remote_addr,
+                role_override: Endpoint::Dialer,
+            },
+            new: ConnectedPoint::Dialer {
+                address: new_remote_addr,
+                role_override: Endpoint::Dialer,
+            },
+        },
+    )]
+}
+
+#[test]
 fn handle_connection_address_change() {
-    todo!()
+    //// Given
+    let mut service = testlib::service::default_test_service::<ConnectionsService>();
+
+    let connection_id = new_test_connection_id();
+    let remote_peer_id = new_test_peer_id();
+    let old_remote_addr = new_test_multiaddr();
+    let new_remote_addr = new_test_multiaddr();
+
+    let input_events =
+        new_outbound_connection_seq(connection_id, remote_peer_id, old_remote_addr.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    let input_events = new_address_change_seq(
+        connection_id,
+        remote_peer_id,
+        old_remote_addr.clone(),
+        new_remote_addr.clone(),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    // Assert state
+    assert_eq!(
+        service.connection_remote_addr(connection_id),
+        Some(&new_remote_addr),
+        "The connection remote address should be updated"
+    );
+    assert_matches!(service.connection_address_history(connection_id), Some(history) => {
+        assert_eq!(history.len(), 1, "Only one address change should be recorded");
+        assert_eq!(history[0].old_addr, old_remote_addr);
+        assert_eq!(history[0].new_addr, new_remote_addr);
+    });
+
+    // Assert output events
+    assert_eq!(output_events.len(), 0, "No events should be emitted");
+}
+
+#[test]
+fn connection_address_history_is_truncated() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<ConnectionsService>();
+
+    let connection_id = new_test_connection_id();
+    let remote_peer_id = new_test_peer_id();
+    let remote_addrs = (0..=MAX_ADDRESS_HISTORY_LEN + 1)
+        .map(|_| new_test_multiaddr())
+        .collect::<Vec<_>>();
+
+    let input_events =
+        new_outbound_connection_seq(connection_id, remote_peer_id, remote_addrs[0].clone());
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    let input_events = remote_addrs.windows(2).flat_map(|addrs| {
+        new_address_change_seq(
+            connection_id,
+            remote_peer_id,
+            addrs[0].clone(),
+            addrs[1].clone(),
+        )
+    });
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        service.connection_remote_addr(connection_id),
+        remote_addrs.last(),
+        "The connection remote address should be the last one"
+    );
+    assert_matches!(service.connection_address_history(connection_id), Some(history) => {
+        assert_eq!(
+            history.len(),
+            MAX_ADDRESS_HISTORY_LEN,
+            "Only the last address changes should be kept"
+        );
+        assert_eq!(
+            history.iter().map(|change| &change.new_addr).collect::<Vec<_>>(),
+            remote_addrs[2..].iter().collect::<Vec<_>>(),
+            "The oldest address change should be dropped"
+        );
+    });
 }
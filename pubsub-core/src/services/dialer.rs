@@ -0,0 +1,7 @@
+pub use events::{ServiceIn as DialerInEvent, ServiceOut as DialerOutEvent};
+pub use service::DialerService;
+
+mod events;
+mod service;
+#[cfg(test)]
+mod tests;
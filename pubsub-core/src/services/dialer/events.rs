@@ -0,0 +1,72 @@
+use crate::dispatch::Consumes;
+use crate::services::connections::ConnectionsOutEvent;
+use crate::services::subscriptions::SubscriptionsOutEvent;
+use libp2p::identity::PeerId;
+use libp2p::Multiaddr;
+
+/// Dialer service input event.
+#[derive(Debug, Clone)]
+pub enum ServiceIn {
+    /// The application suggested a peer and its addresses.
+    KnownPeerAdded {
+        /// The peer id.
+        peer: PeerId,
+        /// The peer addresses.
+        addrs: Vec<Multiaddr>,
+    },
+    /// The local node subscriptions changed.
+    LocalSubscriptionsChanged {
+        /// Whether the local node is subscribed to at least one topic.
+        subscribed: bool,
+    },
+    /// A connection to a peer was established.
+    PeerConnected(PeerId),
+    /// All connections to a peer were closed.
+    PeerDisconnected(PeerId),
+    /// The dial to a peer failed.
+    DialFailure(PeerId),
+}
+
+/// Dialer service output event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceOut {
+    /// Dial the peer at the given addresses.
+    Dial {
+        /// The peer id.
+        peer: PeerId,
+        /// The peer addresses.
+        addrs: Vec<Multiaddr>,
+    },
+}
+
+impl Consumes<ConnectionsOutEvent> for ServiceIn {
+    fn consume(ev: &ConnectionsOutEvent) -> Option<Self> {
+        match ev {
+            ConnectionsOutEvent::NewPeerConnected { peer, .. } => {
+                Some(ServiceIn::PeerConnected(*peer))
+            }
+            ConnectionsOutEvent::PeerDisconnected(peer) => Some(ServiceIn::PeerDisconnected(*peer)),
+        }
+    }
+}
+
+/// The local unsubscriptions are not consumed: whether the node is still subscribed to a topic
+/// depends on the subscriptions service state, so the behaviour notifies them itself.
+impl Consumes<SubscriptionsOutEvent> for ServiceIn {
+    fn consume(ev: &SubscriptionsOutEvent) -> Option<Self> {
+        match ev {
+            SubscriptionsOutEvent::Subscribed(_) => {
+                Some(ServiceIn::LocalSubscriptionsChanged { subscribed: true })
+            }
+            SubscriptionsOutEvent::Unsubscribed { .. }
+            | SubscriptionsOutEvent::PeerSubscribed { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribed { .. }
+            | SubscriptionsOutEvent::PeerSubscribedMany { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribedMany { .. }
+            | SubscriptionsOutEvent::KnownPeerSubscribed { .. }
+            | SubscriptionsOutEvent::KnownPeerForgotten(_)
+            | SubscriptionsOutEvent::PeerFlapping { .. }
+            | SubscriptionsOutEvent::SendSubscriptions { .. } => None,
+        }
+    }
+}
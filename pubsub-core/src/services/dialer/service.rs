@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p::identity::PeerId;
+use libp2p::Multiaddr;
+
+use libp2p_pubsub_common::service::{InCtx, OutCtx, PollCtx, Service};
+
+use super::events::{ServiceIn, ServiceOut};
+
+/// The maximum time between two dial attempts to a known peer.
+const MAX_DIAL_BACKOFF: Duration = Duration::from_secs(300);
+
+/// The dial state of a known peer.
+enum DialState {
+    /// The peer is not connected and can be dialed.
+    Idle,
+    /// A dial to the peer is in progress.
+    Dialing,
+    /// The peer is connected.
+    Connected,
+    /// The last dial to the peer failed. The peer cannot be dialed until the timer expires.
+    Backoff(Delay),
+}
+
+/// A peer suggested by the application.
+struct KnownPeer {
+    /// The peer addresses.
+    addrs: Vec<Multiaddr>,
+    /// The peer dial state.
+    state: DialState,
+    /// The number of consecutive failed dial attempts.
+    failures: usize,
+}
+
+/// The dialer service keeps track of the peers suggested by the application and dials them
+/// while the local node is subscribed to at least one topic and the number of connected peers
+/// is below the target peer count.
+///
+/// A failed dial is retried with an exponential backoff. After the maximum number of consecutive
+/// failed dial attempts, the peer is pruned from the known peers.
+pub struct DialerService {
+    /// The number of connected peers the service dials known peers up to.
+    target_peer_count: usize,
+
+    /// The maximum number of consecutive failed dial attempts before pruning a known peer.
+    max_dial_attempts: usize,
+
+    /// The time to wait before retrying a failed dial. It doubles after each consecutive failure.
+    dial_backoff: Duration,
+
+    /// Whether the local node is subscribed to at least one topic.
+    subscribed: bool,
+
+    /// The connected peers, known or not.
+    connected_peers: HashSet<PeerId>,
+
+    /// The peers suggested by the application.
+    known_peers: HashMap<PeerId, KnownPeer>,
+}
+
+impl Default for DialerService {
+    fn default() -> Self {
+        Self::new(8, 5, Duration::from_secs(1))
+    }
+}
+
+impl DialerService {
+    /// Creates a new dialer service.
+    pub fn new(target_peer_count: usize, max_dial_attempts: usize, dial_backoff: Duration) -> Self {
+        Self {
+            target_peer_count,
+            max_dial_attempts,
+            dial_backoff,
+            subscribed: false,
+            connected_peers: Default::default(),
+            known_peers: Default::default(),
+        }
+    }
+
+    /// Get the known peers and their addresses.
+    pub fn known_peers(&self) -> impl Iterator<Item = (&PeerId, &[Multiaddr])> {
+        self.known_peers
+            .iter()
+            .map(|(peer, known)| (peer, known.addrs.as_slice()))
+    }
+
+    /// Get the addresses of a known peer.
+    pub fn known_peer_addrs(&self, peer: &PeerId) -> Option<&[Multiaddr]> {
+        self.known_peers
+            .get(peer)
+            .map(|known| known.addrs.as_slice())
+    }
+
+    /// Register a failed dial attempt to a known peer.
+    ///
+    /// The peer is pruned if the maximum number of consecutive failed dial attempts is reached.
+    /// Otherwise, the peer is put in backoff.
+    fn register_dial_failure(&mut self, peer: PeerId) {
+        let Some(known) = self.known_peers.get_mut(&peer) else {
+            return;
+        };
+
+        // Ignore the dial failures while connected (e.g., a concurrent dial attempt failed).
+        if matches!(known.state, DialState::Connected) {
+            return;
+        }
+
+        known.failures += 1;
+        if known.failures >= self.max_dial_attempts {
+            tracing::debug!(%peer, "Maximum dial attempts reached, pruning known peer");
+            self.known_peers.remove(&peer);
+            return;
+        }
+
+        let backoff = dial_backoff(self.dial_backoff, known.failures);
+        tracing::trace!(%peer, ?backoff, "Dial failed, backing off");
+        known.state = DialState::Backoff(Delay::new(backoff));
+    }
+}
+
+/// Compute the time to wait before the next dial attempt after the given number of consecutive
+/// failures. The backoff doubles after each failure, up to [`MAX_DIAL_BACKOFF`].
+fn dial_backoff(base: Duration, failures: usize) -> Duration {
+    let exp = failures.saturating_sub(1).min(16) as u32;
+    base.saturating_mul(2u32.pow(exp)).min(MAX_DIAL_BACKOFF)
+}
+
+impl Service for DialerService {
+    type InEvent = ServiceIn;
+    type OutEvent = ServiceOut;
+
+    fn poll<'a>(
+        &mut self,
+        svc_cx: impl PollCtx<'a, Self::InEvent, Self::OutEvent>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::OutEvent> {
+        let (mut in_cx, mut out_cx) = svc_cx.split();
+
+        // Process the incoming events.
+        while let Some(ev) = in_cx.pop_next() {
+            match ev {
+                ServiceIn::KnownPeerAdded { peer, addrs } => {
+                    let connected = self.connected_peers.contains(&peer);
+                    let known = self.known_peers.entry(peer).or_insert_with(|| KnownPeer {
+                        addrs: Vec::new(),
+                        state: if connected {
+                            DialState::Connected
+                        } else {
+                            DialState::Idle
+                        },
+                        failures: 0,
+                    });
+                    for addr in addrs {
+                        if !known.addrs.contains(&addr) {
+                            known.addrs.push(addr);
+                        }
+                    }
+                }
+                ServiceIn::LocalSubscriptionsChanged { subscribed } => {
+                    self.subscribed = subscribed;
+                }
+                ServiceIn::PeerConnected(peer) => {
+                    self.connected_peers.insert(peer);
+                    if let Some(known) = self.known_peers.get_mut(&peer) {
+                        known.state = DialState::Connected;
+                        known.failures = 0;
+                    }
+                }
+                ServiceIn::PeerDisconnected(peer) => {
+                    self.connected_peers.remove(&peer);
+                    if let Some(known) = self.known_peers.get_mut(&peer) {
+                        known.state = DialState::Idle;
+                    }
+                }
+                ServiceIn::DialFailure(peer) => {
+                    self.register_dial_failure(peer);
+                }
+            }
+        }
+
+        // Poll the backoff timers and make the expired peers dialable again.
+        for known in self.known_peers.values_mut() {
+            if let DialState::Backoff(timer) = &mut known.state {
+                if timer.poll_unpin(cx).is_ready() {
+                    known.state = DialState::Idle;
+                }
+            }
+        }
+
+        // Dial the idle known peers if the local node is subscribed to at least one topic and the
+        // number of connected (and being dialed) peers is below the target.
+        if !self.subscribed {
+            return Poll::Pending;
+        }
+
+        let dialing = self
+            .known_peers
+            .values()
+            .filter(|known| matches!(known.state, DialState::Dialing))
+            .count();
+        let mut quota = self
+            .target_peer_count
+            .saturating_sub(self.connected_peers.len() + dialing);
+
+        for (peer, known) in self.known_peers.iter_mut() {
+            if quota == 0 {
+                break;
+            }
+
+            if !matches!(known.state, DialState::Idle) {
+                continue;
+            }
+
+            tracing::trace!(%peer, "Dialing known peer");
+            known.state = DialState::Dialing;
+            out_cx.emit(ServiceOut::Dial {
+                peer: *peer,
+                addrs: known.addrs.clone(),
+            });
+            quota -= 1;
+        }
+
+        Poll::Pending
+    }
+}
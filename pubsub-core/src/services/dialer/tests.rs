@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::identity::PeerId;
+use libp2p::Multiaddr;
+
+use libp2p_pubsub_common::service::BufferedContext;
+use testlib;
+use testlib::service::noop_context;
+
+use crate::services::dialer::{DialerInEvent, DialerOutEvent, DialerService};
+
+/// Create a new random peer ID.
+fn new_test_peer_id() -> PeerId {
+    PeerId::random()
+}
+
+/// Create a new test memory address.
+fn new_test_addr(port: u64) -> Multiaddr {
+    format!("/memory/{port}").parse().expect("valid multiaddr")
+}
+
+/// Create a test `DialerService` with the given target peer count, maximum dial attempts and
+/// dial backoff.
+fn new_test_service(
+    target_peer_count: usize,
+    max_dial_attempts: usize,
+    dial_backoff: Duration,
+) -> BufferedContext<DialerService> {
+    BufferedContext::new(DialerService::new(
+        target_peer_count,
+        max_dial_attempts,
+        dial_backoff,
+    ))
+}
+
+/// Create a new known peer added event sequence for the given peer and address.
+fn new_known_peer_seq(peer: PeerId, addr: Multiaddr) -> impl IntoIterator<Item = DialerInEvent> {
+    [DialerInEvent::KnownPeerAdded {
+        peer,
+        addrs: vec![addr],
+    }]
+}
+
+/// Create a new local subscriptions changed event sequence.
+fn new_subscribed_seq(subscribed: bool) -> impl IntoIterator<Item = DialerInEvent> {
+    [DialerInEvent::LocalSubscriptionsChanged { subscribed }]
+}
+
+#[test]
+fn known_peer_is_dialed_once_subscribed() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<DialerService>();
+
+    let peer = new_test_peer_id();
+    let addr = new_test_addr(1);
+
+    testlib::service::inject_events(&mut service, new_known_peer_seq(peer, addr.clone()));
+    let output_events_before = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// When
+    testlib::service::inject_events(&mut service, new_subscribed_seq(true));
+    let output_events_after = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(
+        output_events_before.is_empty(),
+        "No dial should be requested without local subscriptions"
+    );
+
+    assert_eq!(
+        output_events_after.len(),
+        1,
+        "Only 1 event should be emitted"
+    );
+    assert_matches!(&output_events_after[0], DialerOutEvent::Dial { peer: dialed, addrs } => {
+        assert_eq!(dialed, &peer);
+        assert_eq!(addrs, &vec![addr]);
+    });
+}
+
+#[test]
+fn known_peers_are_not_dialed_above_target_peer_count() {
+    //// Given
+    let mut service = new_test_service(2, 5, Duration::from_secs(1));
+
+    let connected_peer = new_test_peer_id();
+    let peer_a = new_test_peer_id();
+    let peer_b = new_test_peer_id();
+
+    //// When
+    let input_events = new_subscribed_seq(true)
+        .into_iter()
+        .chain([DialerInEvent::PeerConnected(connected_peer)])
+        .chain(new_known_peer_seq(peer_a, new_test_addr(1)))
+        .chain(new_known_peer_seq(peer_b, new_test_addr(2)));
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        output_events.len(),
+        1,
+        "Only 1 peer should be dialed to reach the target"
+    );
+    assert_matches!(&output_events[0], DialerOutEvent::Dial { peer, .. } => {
+        assert!(peer == &peer_a || peer == &peer_b);
+    });
+}
+
+#[tokio::test]
+async fn failed_dial_is_retried_after_backoff() {
+    //// Given
+    let mut service = new_test_service(8, 5, Duration::from_millis(50));
+
+    let peer = new_test_peer_id();
+
+    let input_events = new_subscribed_seq(true)
+        .into_iter()
+        .chain(new_known_peer_seq(peer, new_test_addr(1)));
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events_dial = testlib::service::async_collect_events(&mut service).await;
+
+    //// When
+    testlib::service::inject_events(&mut service, [DialerInEvent::DialFailure(peer)]);
+    let output_events_before = testlib::service::async_collect_events(&mut service).await;
+
+    // Wait for the dial backoff to elapse
+    tokio::time::sleep(Duration::from_millis(60)).await;
+
+    let output_events_after = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert_eq!(
+        output_events_dial.len(),
+        1,
+        "Only 1 event should be emitted"
+    );
+    assert!(
+        output_events_before.is_empty(),
+        "No dial should be requested before the backoff elapses"
+    );
+    assert_eq!(
+        output_events_after.len(),
+        1,
+        "Only 1 event should be emitted"
+    );
+    assert_matches!(&output_events_after[0], DialerOutEvent::Dial { peer: dialed, .. } => {
+        assert_eq!(dialed, &peer);
+    });
+}
+
+#[test]
+fn known_peer_is_pruned_after_max_dial_attempts() {
+    //// Given
+    let mut service = new_test_service(8, 1, Duration::from_secs(1));
+
+    let peer = new_test_peer_id();
+
+    let input_events = new_subscribed_seq(true)
+        .into_iter()
+        .chain(new_known_peer_seq(peer, new_test_addr(1)));
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    testlib::service::inject_events(&mut service, [DialerInEvent::DialFailure(peer)]);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(
+        service.known_peer_addrs(&peer).is_none(),
+        "Peer should be pruned"
+    );
+    assert_eq!(service.known_peers().count(), 0);
+}
@@ -1,15 +1,34 @@
+pub use checksum::FRAME_CHECKSUM_FIELD_TAG;
 pub use context::FramingServiceContext;
+pub use convert::{MessageValidationError, SubOptsValidationError};
 pub use events::{
     DownstreamInEvent as FramingDownstreamInEvent, DownstreamOutEvent as FramingDownstreamOutEvent,
     ServiceIn as FramingInEvent, ServiceOut as FramingOutEvent,
     UpstreamInEvent as FramingUpstreamInEvent, UpstreamOutEvent as FramingUpstreamOutEvent,
 };
+pub use negative_cache::NegativeCacheParams;
+pub use service_upstream::UpstreamFramingParams;
+pub use stats::{FramingStatsParams, PeerFramingStats};
+pub use validation::{
+    FrameFailureClass, FrameLimit, FrameLimitPolicy, FrameLimits, FrameValidationError,
+};
+
+#[cfg(feature = "fuzzing")]
+pub(crate) use events::{UpstreamInEvent, UpstreamOutEvent};
+#[cfg(feature = "fuzzing")]
+pub(crate) use service_upstream::UpstreamFramingService;
 
+mod checksum;
 mod context;
 mod convert;
 mod events;
+mod interner;
+mod negative_cache;
 mod service_downstream;
 mod service_upstream;
+mod stats;
 #[cfg(test)]
 mod tests;
 mod validation;
+#[cfg(test)]
+mod wire_compat;
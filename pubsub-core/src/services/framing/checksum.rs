@@ -0,0 +1,120 @@
+//! The frame checksum trailer (see [`Config::frame_diagnostics`](crate::Config::frame_diagnostics)).
+//!
+//! The checksum trailer is a length-delimited protobuf field, appended after the encoded frame,
+//! carrying the CRC32 (IEEE) of the frame bytes preceding it, as a little-endian `u32`:
+//!
+//! ```text
+//! +-----------------+--------+---------------------+
+//! | 0xFA 0x7F (key) | 0x04   | CRC32 (4 bytes, LE) |
+//! +-----------------+--------+---------------------+
+//! ```
+//!
+//! The field number is not part of the frame protobuf schema, so the protobuf decoders skip it
+//! as an unknown field, and the peers not supporting the checksum ignore it.
+
+use bytes::{Bytes, BytesMut};
+
+/// The frame checksum trailer protobuf field number.
+pub const FRAME_CHECKSUM_FIELD_TAG: u32 = 2047;
+
+/// The checksum trailer header: the field key (field number 2047, length-delimited wire type)
+/// varint and the 4 bytes length.
+const TRAILER_HEADER: [u8; 3] = [0xFA, 0x7F, 0x04];
+
+/// The checksum trailer length, in bytes.
+pub(crate) const TRAILER_LEN: usize = TRAILER_HEADER.len() + 4;
+
+/// The CRC32 (IEEE, reflected polynomial `0xEDB88320`) lookup table.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Compute the CRC32 (IEEE) checksum of the bytes.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, byte| {
+        CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// The result of the received frame checksum check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FrameChecksum {
+    /// The frame carries no checksum trailer.
+    Absent,
+    /// The frame checksum is valid. Contains the frame without the checksum trailer.
+    Valid(Bytes),
+    /// The frame checksum does not match the frame bytes.
+    Mismatch,
+}
+
+/// Append the checksum trailer to the encoded frame.
+pub(crate) fn append_checksum(frame: &mut BytesMut) {
+    let crc = crc32(frame);
+    frame.reserve(TRAILER_LEN);
+    frame.extend_from_slice(&TRAILER_HEADER);
+    frame.extend_from_slice(&crc.to_le_bytes());
+}
+
+/// Check the received frame checksum trailer, if any.
+///
+/// A frame whose trailer header is corrupted is seen as carrying no checksum trailer, and fails
+/// the protobuf decoding, if any, as a protocol error.
+pub(crate) fn check_checksum(frame: &Bytes) -> FrameChecksum {
+    if frame.len() < TRAILER_LEN {
+        return FrameChecksum::Absent;
+    }
+
+    let (payload, trailer) = frame.split_at(frame.len() - TRAILER_LEN);
+    let (header, crc) = trailer.split_at(TRAILER_HEADER.len());
+    if header != TRAILER_HEADER {
+        return FrameChecksum::Absent;
+    }
+
+    let crc = u32::from_le_bytes(crc.try_into().expect("trailer length checked"));
+    if crc32(payload) != crc {
+        return FrameChecksum::Mismatch;
+    }
+
+    FrameChecksum::Valid(frame.slice(..payload.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        // The CRC32 (IEEE) check value, i.e., the checksum of the ASCII digits "123456789".
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn trailer_header_is_the_checksum_field_key() {
+        //// Given
+        let mut key = Vec::new();
+        prost::encoding::encode_key(
+            FRAME_CHECKSUM_FIELD_TAG,
+            prost::encoding::WireType::LengthDelimited,
+            &mut key,
+        );
+
+        //// Then
+        assert_eq!(key, TRAILER_HEADER[..2]);
+    }
+}
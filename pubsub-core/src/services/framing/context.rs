@@ -1,10 +1,28 @@
+#[cfg(feature = "chaos")]
+use std::collections::VecDeque;
 use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+#[cfg(feature = "chaos")]
+use std::time::Instant;
+
+#[cfg(feature = "chaos")]
+use bytes::Bytes;
+use libp2p::identity::PeerId;
+#[cfg(feature = "chaos")]
+use libp2p::swarm::ConnectionId;
 
 use libp2p_pubsub_common::service::{BufferedContext, ServiceContext};
 
-use super::events::{ServiceIn, ServiceOut};
+#[cfg(feature = "chaos")]
+use crate::chaos::{ChaosLayer, ChaosPolicy, ChaosStats};
+
+#[cfg(feature = "chaos")]
+use super::events::UpstreamInEvent;
+use super::events::{DownstreamOutEvent, ServiceIn, ServiceOut};
 use super::service_downstream::DownstreamFramingService;
-use super::service_upstream::UpstreamFramingService;
+use super::service_upstream::{UpstreamFramingParams, UpstreamFramingService};
+use super::stats::PeerFramingStats;
+use super::validation::FrameFailureClass;
 
 /// A multiplexing service context for the framing upstream and downstream services.
 ///
@@ -13,10 +31,157 @@ use super::service_upstream::UpstreamFramingService;
 ///
 /// See the [`FramingServiceContext::poll`] method for more details on the event processing
 /// strategy.
+///
+/// With the `chaos` feature enabled, the chaos policy faults (see
+/// [`Behaviour::set_chaos_policy`](crate::Behaviour::set_chaos_policy)) are injected in the
+/// received frames, before they enter the upstream service, and in the frames sent by the
+/// downstream service.
 #[derive(Default)]
 pub struct FramingServiceContext {
     downstream: BufferedContext<DownstreamFramingService>,
     upstream: BufferedContext<UpstreamFramingService>,
+
+    /// The received and sent frames fault injection layer.
+    #[cfg(feature = "chaos")]
+    chaos: ChaosLayer<ReceivedFrame, DownstreamOutEvent>,
+
+    /// The sent frames events, once the chaos policy faults injected, pending to be emitted.
+    #[cfg(feature = "chaos")]
+    chaos_outbox: VecDeque<DownstreamOutEvent>,
+}
+
+/// A received frame: the source peer, the connection it was received on and the raw frame.
+#[cfg(feature = "chaos")]
+type ReceivedFrame = (PeerId, ConnectionId, Bytes);
+
+impl FramingServiceContext {
+    /// Creates a new framing service context.
+    ///
+    /// See [`DownstreamFramingService`] for more details on the `max_frame_size` and
+    /// `publish_batch_window` parameters, and [`UpstreamFramingService`] for more details on the
+    /// `upstream` parameters. The upstream `frame_diagnostics` parameter applies to both services.
+    pub fn new(
+        max_frame_size: usize,
+        publish_batch_window: Option<Duration>,
+        upstream: UpstreamFramingParams,
+    ) -> Self {
+        Self {
+            downstream: BufferedContext::new(DownstreamFramingService::new(
+                max_frame_size,
+                publish_batch_window,
+                upstream.frame_diagnostics,
+            )),
+            upstream: BufferedContext::new(UpstreamFramingService::new(upstream)),
+            #[cfg(feature = "chaos")]
+            chaos: ChaosLayer::new(),
+            #[cfg(feature = "chaos")]
+            chaos_outbox: VecDeque::new(),
+        }
+    }
+
+    /// Get the number of events queued in the downstream and upstream services input mailboxes.
+    pub fn inbox_len(&self) -> usize {
+        self.downstream.inbox_len() + self.upstream.inbox_len()
+    }
+
+    /// Get the number of events queued in the downstream and upstream services output mailboxes.
+    pub fn outbox_len(&self) -> usize {
+        self.downstream.outbox_len() + self.upstream.outbox_len()
+    }
+
+    /// Get the framing statistics of the frames received from the peer, if any.
+    pub fn peer_framing_stats(&self, peer: &PeerId) -> Option<PeerFramingStats> {
+        self.upstream.peer_stats(peer)
+    }
+
+    /// Get the number of received subscription actions dropped for failing the topic validation.
+    pub fn invalid_topic_announcements(&self) -> u64 {
+        self.upstream.invalid_topic_announcements()
+    }
+
+    /// Get the number of received frame validation failures of the given class.
+    pub fn frame_failures(&self, class: FrameFailureClass) -> u64 {
+        self.upstream.frame_failures(class)
+    }
+
+    /// Get the number of received frames and messages dropped as their validation failure was
+    /// cached.
+    pub fn negative_cache_hits(&self) -> u64 {
+        self.upstream.negative_cache_hits()
+    }
+
+    /// Get the number of received frames and messages whose validation failure was looked up in
+    /// the negative validation cache, and not found.
+    pub fn negative_cache_misses(&self) -> u64 {
+        self.upstream.negative_cache_misses()
+    }
+
+    /// Set, or clear, the chaos policy. The frames already delayed are still released when due.
+    #[cfg(feature = "chaos")]
+    pub fn set_chaos_policy(&mut self, policy: Option<ChaosPolicy>) {
+        self.chaos.set_policy(policy);
+    }
+
+    /// Get the number of faults injected by the chaos policy.
+    #[cfg(feature = "chaos")]
+    pub fn chaos_stats(&self) -> ChaosStats {
+        self.chaos.stats()
+    }
+
+    /// Poll the downstream service for events.
+    #[cfg(not(feature = "chaos"))]
+    fn poll_downstream(&mut self, cx: &mut TaskContext<'_>) -> Poll<DownstreamOutEvent> {
+        self.downstream.poll(cx)
+    }
+
+    /// Poll the downstream service for events, injecting the chaos policy faults in the sent
+    /// frames. The frames reported once flushed, i.e., the tagged frames, are left untouched.
+    #[cfg(feature = "chaos")]
+    fn poll_downstream(&mut self, cx: &mut TaskContext<'_>) -> Poll<DownstreamOutEvent> {
+        if let Some(event) = self.chaos_outbox.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        while let Poll::Ready(event) = self.downstream.poll(cx) {
+            match event {
+                DownstreamOutEvent::SendFrame { tag: Some(_), .. } => {
+                    self.chaos_outbox.push_back(event);
+                }
+                event => {
+                    let sent = self.chaos.outbound(event, Instant::now());
+                    self.chaos_outbox.extend(sent);
+                }
+            }
+        }
+
+        self.chaos_outbox
+            .pop_front()
+            .map_or(Poll::Pending, Poll::Ready)
+    }
+
+    /// Release the chaos policy delayed frames due: the received frames to the upstream service,
+    /// and the sent frames to the output.
+    ///
+    /// The released frames are processed on the next poll, e.g., a duplicated frame is processed
+    /// after the original, as a late re-delivery, rather than within the same batch. The task is
+    /// woken up if any frame is released.
+    #[cfg(feature = "chaos")]
+    fn release_chaos_due_frames(&mut self, cx: &mut TaskContext<'_>) {
+        let (received, sent) = self.chaos.poll_due(cx);
+        if received.is_empty() && sent.is_empty() {
+            return;
+        }
+
+        for (src, connection, frame) in received {
+            self.upstream.do_send(UpstreamInEvent::RawFrameReceived {
+                src,
+                connection,
+                frame,
+            });
+        }
+        self.chaos_outbox.extend(sent);
+        cx.waker().wake_by_ref();
+    }
 }
 
 impl ServiceContext for FramingServiceContext {
@@ -30,6 +195,22 @@ impl ServiceContext for FramingServiceContext {
     /// more details.
     fn do_send(&mut self, ev: ServiceIn) {
         match ev {
+            // Inject the chaos policy faults in the received frames.
+            #[cfg(feature = "chaos")]
+            ServiceIn::Upstream(UpstreamInEvent::RawFrameReceived {
+                src,
+                connection,
+                frame,
+            }) => {
+                let received = self.chaos.inbound((src, connection, frame), Instant::now());
+                for (src, connection, frame) in received {
+                    self.upstream.do_send(UpstreamInEvent::RawFrameReceived {
+                        src,
+                        connection,
+                        frame,
+                    });
+                }
+            }
             ServiceIn::Upstream(ev) => self.upstream.do_send(ev),
             ServiceIn::Downstream(ev) => self.downstream.do_send(ev),
         }
@@ -42,7 +223,7 @@ impl ServiceContext for FramingServiceContext {
     /// first and its events will be emitted before the upstream service events.
     fn poll(&mut self, cx: &mut TaskContext<'_>) -> Poll<ServiceOut> {
         // Poll the downstream service for events.
-        if let Poll::Ready(event) = self.downstream.poll(cx) {
+        if let Poll::Ready(event) = self.poll_downstream(cx) {
             return Poll::Ready(ServiceOut::Downstream(event));
         }
 
@@ -51,6 +232,9 @@ impl ServiceContext for FramingServiceContext {
             return Poll::Ready(ServiceOut::Upstream(event));
         }
 
+        #[cfg(feature = "chaos")]
+        self.release_chaos_due_frames(cx);
+
         Poll::Pending
     }
 }
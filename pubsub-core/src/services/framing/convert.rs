@@ -13,13 +13,19 @@ use crate::framing::{
     ControlMessage, Frame, GraftControlMessage, IHaveControlMessage, IWantControlMessage, Message,
     PruneControlMessage, SubscriptionAction,
 };
-use crate::message_id::MessageId;
-use crate::topic::TopicHash;
+use crate::message::Priority;
+use crate::message_id::{MessageId, MAX_WIRE_MESSAGE_ID_LEN};
+use crate::topic::{TopicHash, TopicValidationError};
+
+use super::validation::{
+    validate_frame_proto, FrameLimit, FrameLimitPolicy, FrameLimits, FrameValidationError,
+};
 
 /// Errors that can occur when validating a [`SubOptsProto`].
 ///
-/// See [`validate_subopts_proto`] for more details.
-#[derive(Debug, Clone, Copy, thiserror::Error)]
+/// See [`SubscriptionAction::try_from_proto`] for more details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
 pub enum SubOptsValidationError {
     /// Empty message topic.
     #[error("empty topic")]
@@ -32,6 +38,10 @@ pub enum SubOptsValidationError {
     /// Action not present.
     #[error("subscription action not present")]
     MissingAction,
+
+    /// The topic fails the topic validation rules.
+    #[error("invalid topic: {0}")]
+    InvalidTopic(TopicValidationError),
 }
 
 impl TryFrom<SubOptsProto> for SubscriptionAction {
@@ -39,33 +49,38 @@ impl TryFrom<SubOptsProto> for SubscriptionAction {
 
     /// Convert a [`SubOptsProto`] into a [`SubscriptionAction`].
     ///
+    /// See [`SubscriptionAction::try_from_proto`] for more details.
+    fn try_from(proto: SubOptsProto) -> Result<Self, Self::Error> {
+        Self::try_from_proto(proto, |topic| TopicHash::from_raw(topic))
+    }
+}
+
+impl SubscriptionAction {
+    /// Convert a [`SubOptsProto`] into a [`SubscriptionAction`], creating the topic hash with the
+    /// `topic_hash` function (e.g., interning it).
+    ///
     /// A subscription option protobuf is valid if:
     /// - The `topic_id` is present and not empty.
     /// - The `subscribe` field is present.
     ///
     /// If the subscription option is invalid, a [`SubOptsValidationError`] is returned.
-    fn try_from(proto: SubOptsProto) -> Result<Self, Self::Error> {
-        match proto.topic_id.as_ref() {
-            None => {
-                // Topic field must be present.
-                return Err(SubOptsValidationError::MissingTopic);
-            }
-            Some(topic) if topic.is_empty() => {
-                // Topic field must not be empty.
-                return Err(SubOptsValidationError::EmptyTopic);
-            }
-            _ => {}
-        }
+    pub(crate) fn try_from_proto(
+        proto: SubOptsProto,
+        topic_hash: impl FnOnce(&str) -> TopicHash,
+    ) -> Result<Self, SubOptsValidationError> {
+        let topic = match proto.topic_id {
+            // Topic field must be present.
+            None => return Err(SubOptsValidationError::MissingTopic),
+            // Topic field must not be empty.
+            Some(topic) if topic.is_empty() => return Err(SubOptsValidationError::EmptyTopic),
+            Some(topic) => topic_hash(&topic),
+        };
 
-        if proto.subscribe.is_none() {
+        let action = match proto.subscribe {
             // Action field must be present.
-            return Err(SubOptsValidationError::MissingAction);
-        }
-
-        let action = if proto.subscribe.unwrap() {
-            SubscriptionAction::Subscribe(TopicHash::from_raw(proto.topic_id.unwrap()))
-        } else {
-            SubscriptionAction::Unsubscribe(TopicHash::from_raw(proto.topic_id.unwrap()))
+            None => return Err(SubOptsValidationError::MissingAction),
+            Some(true) => SubscriptionAction::Subscribe(topic),
+            Some(false) => SubscriptionAction::Unsubscribe(topic),
         };
 
         Ok(action)
@@ -86,15 +101,22 @@ impl From<SubscriptionAction> for SubOptsProto {
 
 /// Errors that can occur when validating a [`MessageProto`].
 ///
-/// See [`validate_message_proto`] for more details.
-#[derive(Debug, Clone, Copy, thiserror::Error)]
+/// See [`Message::try_from_proto`] for more details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
 pub enum MessageValidationError {
+    /// Invalid protobuf encoding.
+    #[error("invalid protobuf encoding")]
+    InvalidEncoding,
     /// Empty message topic.
     #[error("empty topic")]
     EmptyTopic,
     /// The message source was invalid (invalid peer ID).
     #[error("invalid peer id")]
     InvalidPeerId,
+    /// The message key exceeds the maximum key size.
+    #[error("key too large")]
+    KeyTooLarge,
 }
 
 impl TryFrom<MessageProto> for Message {
@@ -102,59 +124,96 @@ impl TryFrom<MessageProto> for Message {
 
     /// Convert from a [`MessageProto`] into a [`Message`].
     ///
-    /// A message protobuf is valid if:
-    /// - The `topic` is not empty.
-    /// - The `from` field's peer ID, if present, is valid.
+    /// See [`Message::try_from_proto`] for more details.
+    fn try_from(proto: MessageProto) -> Result<Self, Self::Error> {
+        Self::try_from_proto(proto, |topic| TopicHash::from_raw(topic))
+    }
+}
+
+impl Message {
+    /// Convert from a [`MessageProto`] into a [`Message`], creating the topic hash with the
+    /// `topic_hash` function (e.g., interning it).
     ///
-    /// Additionally. sanitize the protobuf message by removing optional fields when empty.
-    fn try_from(mut proto: MessageProto) -> Result<Self, Self::Error> {
-        if proto.topic.is_empty() {
-            // topic field must not be empty
-            return Err(MessageValidationError::EmptyTopic);
-        }
+    /// See [`sanitize_message_proto`] for the validation and sanitization rules.
+    pub(crate) fn try_from_proto(
+        proto: MessageProto,
+        topic_hash: impl FnOnce(&str) -> TopicHash,
+    ) -> Result<Self, MessageValidationError> {
+        sanitize_message_proto(proto).map(|proto| Self::from_sanitized_proto(proto, topic_hash))
+    }
 
-        // A non-present data field should be interpreted as an empty payload.
-        if proto.data.is_none() {
-            proto.data = Some(Bytes::new());
-        }
+    /// Wrap a message protobuf already validated and sanitized (see [`sanitize_message_proto`])
+    /// into a [`Message`], creating the topic hash with the `topic_hash` function.
+    pub(crate) fn from_sanitized_proto(
+        proto: MessageProto,
+        topic_hash: impl FnOnce(&str) -> TopicHash,
+    ) -> Self {
+        let topic = topic_hash(&proto.topic);
 
-        // An empty from field should be interpreted as not present.
-        match proto.from.as_ref() {
-            Some(from) if from.is_empty() => {
-                proto.from = None;
-            }
-            Some(from) => {
-                // If present, from field must hold a valid PeerId
-                if PeerId::from_bytes(from).is_err() {
-                    return Err(MessageValidationError::InvalidPeerId);
-                }
-            }
-            None => {}
+        Self {
+            proto,
+            topic,
+            priority: Priority::Normal,
         }
+    }
+}
 
-        // An empty seq_no field should be interpreted as not present.
-        if let Some(seq_no) = proto.seqno.as_ref() {
-            if seq_no.is_empty() {
-                proto.seqno = None;
-            }
+/// Validate a [`MessageProto`], and sanitize it.
+///
+/// A message protobuf is valid if:
+/// - The `topic` is not empty.
+/// - The `from` field's peer ID, if present, is valid.
+///
+/// Additionally. sanitize the protobuf message by removing optional fields when empty.
+///
+/// The validation holds no shared state, so the messages of a frame can be validated in parallel.
+pub(crate) fn sanitize_message_proto(
+    mut proto: MessageProto,
+) -> Result<MessageProto, MessageValidationError> {
+    if proto.topic.is_empty() {
+        // topic field must not be empty
+        return Err(MessageValidationError::EmptyTopic);
+    }
+
+    // A non-present data field should be interpreted as an empty payload.
+    if proto.data.is_none() {
+        proto.data = Some(Bytes::new());
+    }
+
+    // An empty from field should be interpreted as not present.
+    match proto.from.as_ref() {
+        Some(from) if from.is_empty() => {
+            proto.from = None;
         }
+        // If present, from field must hold a valid PeerId
+        Some(from) if PeerId::from_bytes(from).is_err() => {
+            return Err(MessageValidationError::InvalidPeerId);
+        }
+        _ => {}
+    }
 
-        // An empty signature field should be interpreted as not present.
-        if let Some(signature) = proto.signature.as_ref() {
-            if signature.is_empty() {
-                proto.signature = None;
-            }
+    // An empty seq_no field should be interpreted as not present.
+    if let Some(seq_no) = proto.seqno.as_ref() {
+        if seq_no.is_empty() {
+            proto.seqno = None;
         }
+    }
 
-        // An empty key field should be interpreted as not present.
-        if let Some(key) = proto.key.as_ref() {
-            if key.is_empty() {
-                proto.key = None;
-            }
+    // An empty signature field should be interpreted as not present.
+    if let Some(signature) = proto.signature.as_ref() {
+        if signature.is_empty() {
+            proto.signature = None;
         }
+    }
 
-        Ok(Self { proto })
+    // An empty key field should be interpreted as not present.
+    if let Some(key) = proto.key.as_ref() {
+        if key.is_empty() {
+            proto.key = None;
+        }
     }
+
+    Ok(proto)
 }
 
 impl From<Message> for MessageProto {
@@ -255,7 +314,7 @@ impl TryFrom<ControlIWantProto> for IWantControlMessage {
         let message_ids = value
             .message_ids
             .into_iter()
-            .filter(|id| !id.is_empty())
+            .filter(|id| !id.is_empty() && id.len() <= MAX_WIRE_MESSAGE_ID_LEN)
             .map(MessageId::new)
             .collect::<Vec<_>>();
 
@@ -303,7 +362,7 @@ impl TryFrom<ControlIHaveProto> for IHaveControlMessage {
         let message_ids = value
             .message_ids
             .into_iter()
-            .filter(|id| !id.is_empty())
+            .filter(|id| !id.is_empty() && id.len() <= MAX_WIRE_MESSAGE_ID_LEN)
             .map(MessageId::new)
             .collect::<Vec<_>>();
 
@@ -371,3 +430,168 @@ impl From<Frame> for FrameProto {
         }
     }
 }
+
+/// Enforce the received frames limits on a [`FrameProto`], before its parts are converted.
+///
+/// The limits are checked in order: the number of control messages, the number of message ids of
+/// each IHAVE control message, the number of IWANT message ids over the frame, and the number of
+/// subscription actions. The entries are counted as received, including the invalid ones.
+///
+/// With the [`FrameLimitPolicy::Truncate`] policy, the entries beyond each limit are dropped,
+/// keeping the first ones, and the exceeded limits are returned. With the
+/// [`FrameLimitPolicy::Reject`] policy, the frame is left untouched, and the first exceeded limit
+/// is returned as an error.
+pub(crate) fn enforce_frame_limits(
+    frame: &mut FrameProto,
+    limits: &FrameLimits,
+) -> Result<Vec<FrameLimit>, FrameLimit> {
+    if limits.policy == FrameLimitPolicy::Reject {
+        return match exceeded_frame_limits(frame, limits).first() {
+            Some(limit) => Err(*limit),
+            None => Ok(Vec::new()),
+        };
+    }
+
+    let mut truncated = Vec::new();
+    if let Some(control) = frame.control.as_mut() {
+        // Keep the first control messages, in the GRAFT, PRUNE, IHAVE and IWANT order.
+        let mut budget = limits.max_control_entries;
+        let mut exceeded = false;
+        exceeded |= truncate_to_budget(&mut control.graft, &mut budget);
+        exceeded |= truncate_to_budget(&mut control.prune, &mut budget);
+        exceeded |= truncate_to_budget(&mut control.ihave, &mut budget);
+        exceeded |= truncate_to_budget(&mut control.iwant, &mut budget);
+        if exceeded {
+            truncated.push(FrameLimit::ControlEntries);
+        }
+
+        let mut exceeded = false;
+        for ihave in &mut control.ihave {
+            let mut budget = limits.max_ihave_ids_per_message;
+            exceeded |= truncate_to_budget(&mut ihave.message_ids, &mut budget);
+        }
+        if exceeded {
+            truncated.push(FrameLimit::IHaveIds);
+        }
+
+        let mut budget = limits.max_iwant_ids;
+        let mut exceeded = false;
+        for iwant in &mut control.iwant {
+            exceeded |= truncate_to_budget(&mut iwant.message_ids, &mut budget);
+        }
+        if exceeded {
+            control.iwant.retain(|iwant| !iwant.message_ids.is_empty());
+            truncated.push(FrameLimit::IWantIds);
+        }
+    }
+
+    let mut budget = limits.max_subscriptions;
+    if truncate_to_budget(&mut frame.subscriptions, &mut budget) {
+        truncated.push(FrameLimit::Subscriptions);
+    }
+
+    Ok(truncated)
+}
+
+/// The received frames limits the [`FrameProto`] exceeds, in the [`enforce_frame_limits`] order.
+fn exceeded_frame_limits(frame: &FrameProto, limits: &FrameLimits) -> Vec<FrameLimit> {
+    let mut exceeded = Vec::new();
+    if let Some(control) = frame.control.as_ref() {
+        let entries =
+            control.graft.len() + control.prune.len() + control.ihave.len() + control.iwant.len();
+        if entries > limits.max_control_entries {
+            exceeded.push(FrameLimit::ControlEntries);
+        }
+
+        if control
+            .ihave
+            .iter()
+            .any(|ihave| ihave.message_ids.len() > limits.max_ihave_ids_per_message)
+        {
+            exceeded.push(FrameLimit::IHaveIds);
+        }
+
+        let iwant_ids = control
+            .iwant
+            .iter()
+            .map(|iwant| iwant.message_ids.len())
+            .sum::<usize>();
+        if iwant_ids > limits.max_iwant_ids {
+            exceeded.push(FrameLimit::IWantIds);
+        }
+    }
+
+    if frame.subscriptions.len() > limits.max_subscriptions {
+        exceeded.push(FrameLimit::Subscriptions);
+    }
+
+    exceeded
+}
+
+/// Truncate the entries to the remaining `budget`, and consume it. Returns `true` if entries were
+/// dropped.
+fn truncate_to_budget<T>(entries: &mut Vec<T>, budget: &mut usize) -> bool {
+    let kept = entries.len().min(*budget);
+    let exceeded = kept < entries.len();
+    entries.truncate(kept);
+    *budget -= kept;
+    exceeded
+}
+
+impl TryFrom<FrameProto> for Frame {
+    type Error = FrameValidationError;
+
+    /// Convert a [`FrameProto`] into a [`Frame`].
+    ///
+    /// Unlike the received frames processing, which drops the invalid frame parts, the conversion
+    /// fails if any of the frame messages, subscription actions or control messages is invalid.
+    fn try_from(proto: FrameProto) -> Result<Self, Self::Error> {
+        validate_frame_proto(&proto)?;
+
+        let messages = proto
+            .publish
+            .into_iter()
+            .map(Message::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let subscriptions = proto
+            .subscriptions
+            .into_iter()
+            .map(SubscriptionAction::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut control = Vec::new();
+        if let Some(ctrl) = proto.control {
+            for graft in ctrl.graft {
+                let graft = graft
+                    .try_into()
+                    .map_err(|_| FrameValidationError::InvalidControl)?;
+                control.push(ControlMessage::Graft(graft));
+            }
+            for prune in ctrl.prune {
+                let prune = prune
+                    .try_into()
+                    .map_err(|_| FrameValidationError::InvalidControl)?;
+                control.push(ControlMessage::Prune(prune));
+            }
+            for ihave in ctrl.ihave {
+                let ihave = ihave
+                    .try_into()
+                    .map_err(|_| FrameValidationError::InvalidControl)?;
+                control.push(ControlMessage::IHave(ihave));
+            }
+            for iwant in ctrl.iwant {
+                let iwant = iwant
+                    .try_into()
+                    .map_err(|_| FrameValidationError::InvalidControl)?;
+                control.push(ControlMessage::IWant(iwant));
+            }
+        }
+
+        Ok(Self {
+            subscriptions,
+            messages,
+            control,
+        })
+    }
+}
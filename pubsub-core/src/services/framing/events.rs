@@ -2,8 +2,16 @@ use std::rc::Rc;
 
 use bytes::Bytes;
 use libp2p::identity::PeerId;
+use libp2p::swarm::ConnectionId;
 
+use crate::dispatch::Consumes;
 use crate::framing::{ControlMessage, Message as FrameMessage, SubscriptionAction};
+use crate::lifecycle::{MessageContext, ReceivedMessageCtx};
+use crate::message::Priority;
+use crate::protocol::ProtocolRouterOutEvent;
+use crate::services::connections::ConnectionsOutEvent;
+
+use super::validation::FrameFailureClass;
 
 /// The input event for the framing service.
 #[derive(Debug, Clone)]
@@ -31,27 +39,31 @@ pub enum UpstreamInEvent {
     RawFrameReceived {
         /// The peer that propagated the frame.
         src: PeerId,
+        /// The connection the frame was received on.
+        connection: ConnectionId,
         /// The raw frame.
         frame: Bytes,
     },
+    /// The peer disconnected.
+    ///
+    /// The per-peer framing statistics of the peer are dropped.
+    PeerDisconnected(PeerId),
 }
 
 #[derive(Debug, Clone)]
 #[allow(clippy::enum_variant_names)]
 pub enum UpstreamOutEvent {
     /// A message forwarded by the `src` peer.
-    MessageReceived {
-        /// The peer that propagated the message.
-        src: PeerId,
-        /// The frame message.
-        ///
-        /// This message is the result of validating and decoding the raw frame.
-        message: Rc<FrameMessage>,
-    },
+    ///
+    /// The message is the result of validating and decoding the raw frame. Its context travels
+    /// with it down the received messages pipeline.
+    MessageReceived(ReceivedMessageCtx),
     /// A subscription action request received by the `src` peer.
     SubscriptionRequestReceived {
         /// The peer that propagated the message.
         src: PeerId,
+        /// The connection the subscription action request was received on.
+        connection: ConnectionId,
         /// A peer's subscription action request.
         action: SubscriptionAction,
     },
@@ -62,6 +74,24 @@ pub enum UpstreamOutEvent {
         /// The peer control message.
         message: ControlMessage,
     },
+    /// A frame, or a frame message, received by the `src` peer failed the validation and was
+    /// dropped.
+    ValidationFailed {
+        /// The peer that propagated the frame.
+        src: PeerId,
+        /// The validation error.
+        error: Rc<anyhow::Error>,
+        /// The validation failure class.
+        class: FrameFailureClass,
+    },
+    /// The frames received by the `src` peer carry few messages per frame, on average, while
+    /// the peer message rate is high.
+    InefficientFraming {
+        /// The peer that propagated the frames.
+        src: PeerId,
+        /// The average number of messages per frame over the statistics window.
+        avg_msgs_per_frame: f64,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -70,8 +100,14 @@ pub enum DownstreamInEvent {
     ForwardMessage {
         /// THe destination peer.
         dest: PeerId,
+        /// The destination peer connection. If `None`, any of the peer connections.
+        connection: Option<ConnectionId>,
         /// The message to propagate.
         message: Rc<FrameMessage>,
+        /// The message lifecycle context, if any.
+        context: Option<MessageContext>,
+        /// The message local scheduling priority. The high priority messages are never batched.
+        priority: Priority,
     },
     /// A subscription action to be sent to the `dest` peer.
     SendSubscriptionRequest {
@@ -79,6 +115,8 @@ pub enum DownstreamInEvent {
         dest: PeerId,
         /// The subscription actions to send.
         actions: Vec<SubscriptionAction>,
+        /// The frame tag, if the frame must be reported once flushed to the peer.
+        tag: Option<u64>,
     },
     /// A control message to be sent to the `dest` peer.
     SendControlMessage {
@@ -87,6 +125,15 @@ pub enum DownstreamInEvent {
         /// The control message to send.
         message: ControlMessage,
     },
+    /// The maximum frame size changed.
+    MaxFrameSizeChanged(usize),
+    /// The frame size limit suspected for the `peer` changed.
+    PeerFrameLimitChanged {
+        /// The peer.
+        peer: PeerId,
+        /// The suspected frame size limit. If `None`, the maximum frame size applies.
+        limit: Option<usize>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -95,7 +142,43 @@ pub enum DownstreamOutEvent {
     SendFrame {
         /// The destination peer.
         dest: PeerId,
+        /// The destination peer connection. If `None`, any of the peer connections.
+        connection: Option<ConnectionId>,
         /// The raw frame to propagate.
         frame: Bytes,
+        /// The frame tag, if the frame must be reported once flushed to the peer.
+        tag: Option<u64>,
+        /// The frame local scheduling priority.
+        priority: Priority,
+        /// Whether the frame carries subscription actions or control messages only.
+        control: bool,
     },
 }
+
+impl Consumes<ConnectionsOutEvent> for ServiceIn {
+    fn consume(ev: &ConnectionsOutEvent) -> Option<Self> {
+        match ev {
+            ConnectionsOutEvent::NewPeerConnected { .. } => None,
+            ConnectionsOutEvent::PeerDisconnected(peer) => Some(ServiceIn::Upstream(
+                UpstreamInEvent::PeerDisconnected(*peer),
+            )),
+        }
+    }
+}
+
+/// The forwarded messages are not consumed: the behaviour schedules them per destination peer
+/// connection before handing them to the framing service.
+impl Consumes<ProtocolRouterOutEvent> for ServiceIn {
+    fn consume(ev: &ProtocolRouterOutEvent) -> Option<Self> {
+        match ev {
+            ProtocolRouterOutEvent::SendControlMessage { dest, message } => Some(
+                ServiceIn::Downstream(DownstreamInEvent::SendControlMessage {
+                    dest: *dest,
+                    message: message.clone(),
+                }),
+            ),
+            ProtocolRouterOutEvent::ForwardMessage { .. }
+            | ProtocolRouterOutEvent::DisconnectPeer { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::cell::RefCell;
+
+use hashlink::LinkedHashSet;
+
+use crate::config::Config;
+use crate::topic::TopicHash;
+
+/// A bounded topic hash interner.
+///
+/// Interning a topic hash string returns a [`TopicHash`] sharing the hash string allocation with
+/// the previously interned topic hashes of the same string. This avoids allocating a new topic hash
+/// for every decoded frame part, and makes the interned topic hashes comparisons pointer-fast.
+///
+/// The interner tracks up to `capacity` topic hashes. Once the limit is reached, the least recently
+/// interned topic hashes are evicted. A capacity of zero disables the interning.
+pub struct TopicHashInterner {
+    /// The maximum number of interned topic hashes.
+    capacity: usize,
+
+    /// The interned topic hashes, from the least to the most recently interned.
+    topics: RefCell<LinkedHashSet<TopicHash>>,
+}
+
+impl Default for TopicHashInterner {
+    fn default() -> Self {
+        Self::new(Config::default().max_interned_topics())
+    }
+}
+
+impl TopicHashInterner {
+    /// Creates a new topic hash interner tracking up to `capacity` topic hashes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            topics: Default::default(),
+        }
+    }
+
+    /// Returns the interned topic hash of the given topic hash string, and marks it as the most
+    /// recently interned.
+    pub fn intern(&self, topic: &str) -> TopicHash {
+        if self.capacity == 0 {
+            return TopicHash::from_raw(topic);
+        }
+
+        let mut topics = self.topics.borrow_mut();
+
+        if topics.to_back(topic) {
+            if let Some(hash) = topics.back() {
+                return hash.clone();
+            }
+        }
+
+        let hash = TopicHash::from_raw(topic);
+        topics.insert(hash.clone());
+
+        // Evict the least recently interned topic hashes exceeding the limit.
+        while topics.len() > self.capacity {
+            topics.pop_front();
+        }
+
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use super::*;
+
+    fn hash_of(topic: &TopicHash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        topic.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn interned_and_non_interned_topic_hashes_are_equivalent() {
+        //// Given
+        let interner = TopicHashInterner::new(8);
+
+        //// When
+        let interned = interner.intern("/test/topic");
+        let non_interned = TopicHash::from_raw("/test/topic");
+
+        //// Then
+        assert_eq!(interned, non_interned);
+        assert_eq!(hash_of(&interned), hash_of(&non_interned));
+        assert!(!interned.ptr_eq(&non_interned));
+    }
+
+    #[test]
+    fn repeated_interning_reuses_the_topic_hash_allocation() {
+        //// Given
+        let interner = TopicHashInterner::new(8);
+
+        //// When
+        let first = interner.intern("/test/topic");
+        let second = interner.intern("/test/topic");
+
+        //// Then
+        assert!(
+            first.ptr_eq(&second),
+            "Interned topic hashes should share the allocation"
+        );
+    }
+
+    #[test]
+    fn least_recently_interned_topic_hash_is_evicted() {
+        //// Given
+        let interner = TopicHashInterner::new(2);
+
+        let topic_a = interner.intern("/test/topic-a");
+        let topic_b = interner.intern("/test/topic-b");
+
+        //// When
+        // Refresh topic A, so topic B becomes the least recently interned topic hash.
+        interner.intern("/test/topic-a");
+        interner.intern("/test/topic-c");
+
+        //// Then
+        assert!(interner.intern("/test/topic-a").ptr_eq(&topic_a));
+        assert!(
+            !interner.intern("/test/topic-b").ptr_eq(&topic_b),
+            "Topic B should have been evicted"
+        );
+    }
+
+    #[test]
+    fn zero_capacity_disables_interning() {
+        //// Given
+        let interner = TopicHashInterner::new(0);
+
+        //// When
+        let first = interner.intern("/test/topic");
+        let second = interner.intern("/test/topic");
+
+        //// Then
+        assert_eq!(first, second);
+        assert!(!first.ptr_eq(&second));
+    }
+}
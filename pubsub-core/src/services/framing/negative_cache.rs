@@ -0,0 +1,197 @@
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use hashlink::LinkedHashMap;
+
+use libp2p_pubsub_proto::pubsub::MessageProto;
+
+use crate::config::Config;
+
+/// The negative validation cache parameters.
+#[derive(Debug, Clone, Copy)]
+pub struct NegativeCacheParams {
+    /// The maximum number of cached validation failures. A capacity of zero disables the cache.
+    pub capacity: usize,
+    /// The time a validation failure is cached.
+    pub ttl: Duration,
+}
+
+impl Default for NegativeCacheParams {
+    fn default() -> Self {
+        let config = Config::default();
+        Self {
+            capacity: config.negative_validation_cache_capacity(),
+            ttl: config.negative_validation_cache_ttl(),
+        }
+    }
+}
+
+/// A bounded cache of the validation failures of the received frames, or messages.
+///
+/// Only the valid messages enter the message cache, so a frame, or message, failing the
+/// validation is validated again every time it is re-received. The negative cache maps a keyed
+/// hash of the raw frame bytes, or of the message fields, to the validation failure, so the
+/// re-received invalid frames and messages are dropped without validating them again. The raw
+/// frame bytes are hashed before decoding them, so the frames failing to decode are cached too.
+///
+/// The failures are cached for `ttl`, and up to `capacity` failures are cached. Once the limit is
+/// reached, the oldest failures are evicted.
+pub struct NegativeValidationCache<E> {
+    /// The cache parameters.
+    params: NegativeCacheParams,
+
+    /// The randomly keyed hasher builder, so the remote peers cannot craft colliding keys.
+    hasher: RandomState,
+
+    /// The cached validation failures and their expiry time, from the oldest to the newest.
+    entries: RefCell<LinkedHashMap<u64, (Instant, E)>>,
+
+    /// The number of lookups that found a cached validation failure.
+    hits: Cell<u64>,
+
+    /// The number of lookups that found no cached validation failure.
+    misses: Cell<u64>,
+}
+
+impl<E> Default for NegativeValidationCache<E> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<E> NegativeValidationCache<E> {
+    /// Creates a new negative validation cache.
+    pub fn new(params: NegativeCacheParams) -> Self {
+        Self {
+            params,
+            hasher: RandomState::new(),
+            entries: Default::default(),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        }
+    }
+
+    /// Get the number of lookups that found a cached validation failure.
+    pub fn hits(&self) -> u64 {
+        self.hits.get()
+    }
+
+    /// Get the number of lookups that found no cached validation failure.
+    pub fn misses(&self) -> u64 {
+        self.misses.get()
+    }
+
+    /// Returns the cache key of the raw frame bytes.
+    pub fn frame_key(&self, frame: &[u8]) -> u64 {
+        let mut hasher = self.hasher.build_hasher();
+        frame.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns the cache key of the message.
+    ///
+    /// All the message fields are hashed, so two messages share a key only if their encodings
+    /// are equivalent.
+    pub fn message_key(&self, message: &MessageProto) -> u64 {
+        let mut hasher = self.hasher.build_hasher();
+        message.from.hash(&mut hasher);
+        message.data.hash(&mut hasher);
+        message.seqno.hash(&mut hasher);
+        message.topic.hash(&mut hasher);
+        message.signature.hash(&mut hasher);
+        message.key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Inserts the validation failure of the frame, or message, with the given key.
+    pub fn insert(&self, key: u64, failure: E) {
+        if self.params.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.borrow_mut();
+        entries.remove(&key);
+        entries.insert(key, (Instant::now() + self.params.ttl, failure));
+
+        // Evict the oldest failures exceeding the limit.
+        while entries.len() > self.params.capacity {
+            entries.pop_front();
+        }
+    }
+}
+
+impl<E: Clone> NegativeValidationCache<E> {
+    /// Returns the cached validation failure of the frame, or message, with the given key, if
+    /// any, and accounts the lookup.
+    pub fn get(&self, key: u64) -> Option<E> {
+        if self.params.capacity == 0 {
+            return None;
+        }
+
+        let mut entries = self.entries.borrow_mut();
+
+        // Evict the expired failures. The failures share the time-to-live, so they expire in
+        // insertion order.
+        let now = Instant::now();
+        while matches!(entries.front(), Some((_, (expires_at, _))) if *expires_at <= now) {
+            entries.pop_front();
+        }
+
+        match entries.get(&key) {
+            Some((_, failure)) => {
+                self.hits.set(self.hits.get() + 1);
+                Some(failure.clone())
+            }
+            None => {
+                self.misses.set(self.misses.get() + 1);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_cache(capacity: usize) -> NegativeValidationCache<&'static str> {
+        NegativeValidationCache::new(NegativeCacheParams {
+            capacity,
+            ttl: Duration::from_secs(60),
+        })
+    }
+
+    #[test]
+    fn oldest_failure_is_evicted_once_the_capacity_is_reached() {
+        //// Given
+        let cache = new_test_cache(2);
+        let keys = [b"frame-a", b"frame-b", b"frame-c"].map(|frame| cache.frame_key(frame));
+
+        //// When
+        for key in keys {
+            cache.insert(key, "invalid frame");
+        }
+
+        //// Then
+        assert_eq!(cache.get(keys[0]), None, "Frame A should have been evicted");
+        assert_eq!(cache.get(keys[1]), Some("invalid frame"));
+        assert_eq!(cache.get(keys[2]), Some("invalid frame"));
+        assert_eq!((cache.hits(), cache.misses()), (2, 1));
+    }
+
+    #[test]
+    fn zero_capacity_disables_the_cache() {
+        //// Given
+        let cache = new_test_cache(0);
+        let key = cache.frame_key(b"frame");
+
+        //// When
+        cache.insert(key, "invalid frame");
+
+        //// Then
+        assert_eq!(cache.get(key), None);
+        assert_eq!((cache.hits(), cache.misses()), (0, 0));
+    }
+}
@@ -1,69 +1,352 @@
+use std::collections::HashMap;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
 use bytes::{Bytes, BytesMut};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p::identity::PeerId;
+use libp2p::swarm::ConnectionId;
 use prost::Message;
 
-use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+use libp2p_pubsub_common::service::{InCtx, OutCtx, PollCtx, Service};
 use libp2p_pubsub_proto::pubsub::FrameProto;
 
-use crate::framing::Frame;
+use crate::config::DEFAULT_MAX_FRAME_SIZE;
+use crate::framing::{Frame, Message as FrameMessage};
+use crate::lifecycle::{MessageContext, MessageStage};
+use crate::message::Priority;
 
+use super::checksum::{append_checksum, TRAILER_LEN};
 use super::events::{DownstreamInEvent, DownstreamOutEvent};
 
+/// The frame protobuf `publish` field tag.
+const FRAME_PUBLISH_FIELD_TAG: u32 = 2;
+
+/// The destination of a batch: a peer, and optionally one of its connections.
+type BatchDest = (PeerId, Option<ConnectionId>);
+
+/// A batch flush timer, completing once the batch window elapses.
+type FlushTimer = BoxFuture<'static, ()>;
+
+/// Create a batch flush timer elapsing after the given duration.
+fn new_flush_timer(window: Duration) -> FlushTimer {
+    Delay::new(window).boxed()
+}
+
+/// A batch of messages pending to be sent to a peer.
+struct PendingBatch {
+    /// The batched messages.
+    messages: Vec<FrameMessage>,
+    /// The batched messages lifecycle contexts.
+    contexts: Vec<MessageContext>,
+    /// The encoded size of the frame containing the batched messages.
+    encoded_len: usize,
+    /// The batch flush timer.
+    flush_timer: FlushTimer,
+}
+
 /// The downstream framing service is responsible for encoding the messages and subscription
 /// requests into frames and sending them to the destination peer.
-#[derive(Default)]
-pub struct DownstreamFramingService;
+///
+/// If a publish batch window is set, the messages destined to a peer are buffered for up to the
+/// batch window duration (or until the frame would exceed the maximum frame size, or the frame
+/// size limit suspected for the peer) and flushed as a single multi-message frame. Subscription
+/// requests, control messages and high priority messages (see [`Priority`]) are never batched:
+/// they are sent immediately, ahead of the pending batches.
+///
+/// If the frame diagnostics are enabled, a checksum trailer is appended to every sent frame (see
+/// [`Config::frame_diagnostics`](crate::Config::frame_diagnostics)). The batches are flushed
+/// before the frame, trailer included, would exceed the frame size limit.
+pub struct DownstreamFramingService {
+    /// The maximum size of a frame.
+    max_frame_size: usize,
+
+    /// The publish batch window duration. If `None`, messages are sent immediately.
+    batch_window: Option<Duration>,
+
+    /// The per-peer (and per-connection, if the messages destination is a peer connection)
+    /// pending message batches.
+    pending_batches: HashMap<BatchDest, PendingBatch>,
+
+    /// The frame size limits suspected for the peers, if lower than the maximum frame size.
+    peer_frame_limits: HashMap<PeerId, usize>,
+
+    /// Whether a checksum trailer is appended to the sent frames.
+    frame_diagnostics: bool,
+
+    /// The batch flush timers factory.
+    flush_timer_fn: fn(Duration) -> FlushTimer,
+}
+
+impl Default for DownstreamFramingService {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FRAME_SIZE, None, false)
+    }
+}
+
+impl DownstreamFramingService {
+    /// Creates a new downstream framing service.
+    pub fn new(
+        max_frame_size: usize,
+        batch_window: Option<Duration>,
+        frame_diagnostics: bool,
+    ) -> Self {
+        Self {
+            max_frame_size,
+            batch_window,
+            pending_batches: Default::default(),
+            peer_frame_limits: Default::default(),
+            frame_diagnostics,
+            flush_timer_fn: new_flush_timer,
+        }
+    }
+
+    /// Replace the batch flush timers factory, e.g., to drive the batch window from a
+    /// controlled clock.
+    #[cfg(test)]
+    pub(crate) fn with_flush_timer_fn(
+        mut self,
+        flush_timer_fn: fn(Duration) -> FlushTimer,
+    ) -> Self {
+        self.flush_timer_fn = flush_timer_fn;
+        self
+    }
+
+    /// The maximum size of the frames sent to the peer, excluding the checksum trailer, if any.
+    fn frame_size_limit(&self, peer: &PeerId) -> usize {
+        let limit = self
+            .peer_frame_limits
+            .get(peer)
+            .map_or(self.max_frame_size, |limit| {
+                (*limit).min(self.max_frame_size)
+            });
+
+        if self.frame_diagnostics {
+            limit.saturating_sub(TRAILER_LEN)
+        } else {
+            limit
+        }
+    }
+
+    /// Add a message to the `dest` pending batch.
+    ///
+    /// If adding the message to the batch would exceed the maximum frame size (or the frame size
+    /// limit suspected for the peer), the current batch is flushed first and a new batch is
+    /// started. A message reaching the frame size limit on its own is not batched: the current
+    /// batch is flushed, and the message is sent immediately after it.
+    fn enqueue_message<'a>(
+        &mut self,
+        out_cx: &mut impl OutCtx<'a, Event = DownstreamOutEvent>,
+        batch_window: Duration,
+        dest: BatchDest,
+        message: FrameMessage,
+        context: Option<MessageContext>,
+    ) {
+        let message_len =
+            prost::encoding::message::encoded_len(FRAME_PUBLISH_FIELD_TAG, message.as_proto());
 
-/// Encode a frame into a byte buffer.
+        let frame_size_limit = self.frame_size_limit(&dest.0);
+        if message_len >= frame_size_limit {
+            tracing::trace!(dest = %dest.0, "Message reaches the frame size limit, sending it unbatched");
+            self.flush_batch(out_cx, dest);
+            self.send_message(out_cx, dest, message, context, Priority::Normal);
+            return;
+        }
+
+        if let Some(batch) = self.pending_batches.get(&dest) {
+            if batch.encoded_len + message_len > frame_size_limit {
+                tracing::trace!(dest = %dest.0, "Frame size limit reached, flushing batch");
+                self.flush_batch(out_cx, dest);
+            }
+        }
+
+        let flush_timer_fn = self.flush_timer_fn;
+        let batch = self
+            .pending_batches
+            .entry(dest)
+            .or_insert_with(|| PendingBatch {
+                messages: Vec::new(),
+                contexts: Vec::new(),
+                encoded_len: 0,
+                flush_timer: flush_timer_fn(batch_window),
+            });
+        batch.messages.push(message);
+        batch.contexts.extend(context);
+        batch.encoded_len += message_len;
+    }
+
+    /// Send the message to `dest` in a frame of its own.
+    fn send_message<'a>(
+        &self,
+        out_cx: &mut impl OutCtx<'a, Event = DownstreamOutEvent>,
+        dest: BatchDest,
+        message: FrameMessage,
+        context: Option<MessageContext>,
+        priority: Priority,
+    ) {
+        // Create a new frame with the message, encode it and send it to the destination peer.
+        // The resulting frame will contain only one message.
+        let frame = encode_frame(Frame::new_with_messages([message]), self.frame_diagnostics);
+        if let Some(mut context) = context {
+            context.record_stage(MessageStage::Framed);
+        }
+
+        let (dest, connection) = dest;
+        out_cx.emit(DownstreamOutEvent::SendFrame {
+            dest,
+            connection,
+            frame,
+            tag: None,
+            priority,
+            control: false,
+        });
+    }
+
+    /// Flush the `dest` pending batch, if any.
+    fn flush_batch<'a>(
+        &mut self,
+        out_cx: &mut impl OutCtx<'a, Event = DownstreamOutEvent>,
+        dest: BatchDest,
+    ) {
+        if let Some(batch) = self.pending_batches.remove(&dest) {
+            let frame = encode_frame(
+                Frame::new_with_messages(batch.messages),
+                self.frame_diagnostics,
+            );
+            for mut context in batch.contexts {
+                context.record_stage(MessageStage::Framed);
+            }
+
+            let (dest, connection) = dest;
+            out_cx.emit(DownstreamOutEvent::SendFrame {
+                dest,
+                connection,
+                frame,
+                tag: None,
+                priority: Priority::Normal,
+                control: false,
+            });
+        }
+    }
+}
+
+/// Encode a frame into a byte buffer, appending the checksum trailer if `checksum` is `true`.
 ///
 /// This function uses the `prost` crate to encode the frame into a byte buffer.
-fn encode_frame(frame: impl Into<FrameProto>) -> Bytes {
+fn encode_frame(frame: impl Into<FrameProto>, checksum: bool) -> Bytes {
     let frame = frame.into();
 
-    let mut bytes = BytesMut::with_capacity(frame.encoded_len());
+    let mut bytes = BytesMut::with_capacity(frame.encoded_len() + TRAILER_LEN);
     frame.encode(&mut bytes).unwrap();
+    if checksum {
+        append_checksum(&mut bytes);
+    }
     bytes.freeze()
 }
 
-impl EventHandler for DownstreamFramingService {
+impl Service for DownstreamFramingService {
     type InEvent = DownstreamInEvent;
     type OutEvent = DownstreamOutEvent;
 
-    fn on_event<'a>(
+    fn poll<'a>(
         &mut self,
-        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
-        ev: Self::InEvent,
-    ) {
-        match ev {
-            DownstreamInEvent::ForwardMessage { dest, message } => {
-                // Create a new frame with the message, encode it and send it to the destination
-                // peer. The resulting frame will contain only one message.
-                let frame = Frame::new_with_messages([
+        svc_cx: impl PollCtx<'a, Self::InEvent, Self::OutEvent>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::OutEvent> {
+        let (mut in_cx, mut out_cx) = svc_cx.split();
+
+        // Process the incoming events.
+        while let Some(ev) = in_cx.pop_next() {
+            match ev {
+                DownstreamInEvent::ForwardMessage {
+                    dest,
+                    connection,
+                    message,
+                    context,
+                    priority,
+                } => {
                     // Clone the message as it is wrapped in an `Rc`.
-                    (*message).clone(),
-                ]);
+                    let message = (*message).clone();
 
-                // Encode the frame into a byte buffer and send it to the destination peer.
-                let frame = encode_frame(frame);
-                svc_cx.emit(DownstreamOutEvent::SendFrame { dest, frame });
-            }
-            DownstreamInEvent::SendSubscriptionRequest { dest, actions } => {
-                // Create a new frame with the subscription actions, encode it and send it to the
-                // destination peer. The resulting frame will contain only subscription actions.
-                let frame = Frame::new_with_subscriptions(actions);
-
-                // Encode the frame into a byte buffer and send it to the destination peer.
-                let frame = encode_frame(frame);
-                svc_cx.emit(DownstreamOutEvent::SendFrame { dest, frame });
-            }
-            DownstreamInEvent::SendControlMessage { dest, message } => {
-                // Create a new frame with the control message, encode it and send it to the
-                // destination peer. The resulting frame will contain only one control message.
-                let frame = Frame::new_with_control([message]);
-
-                // Encode the frame into a byte buffer and send it to the destination peer.
-                let frame = encode_frame(frame);
-                svc_cx.emit(DownstreamOutEvent::SendFrame { dest, frame });
+                    // If a batch window is set, add the message to the peer's pending batch. The
+                    // high priority messages never wait for the batch window.
+                    if let (Some(batch_window), Priority::Normal) = (self.batch_window, priority) {
+                        self.enqueue_message(
+                            &mut out_cx,
+                            batch_window,
+                            (dest, connection),
+                            message,
+                            context,
+                        );
+                        continue;
+                    }
+
+                    self.send_message(&mut out_cx, (dest, connection), message, context, priority);
+                }
+                DownstreamInEvent::SendSubscriptionRequest { dest, actions, tag } => {
+                    // Create a new frame with the subscription actions, encode it and send it to
+                    // the destination peer. The resulting frame will contain only subscription
+                    // actions.
+                    let frame = Frame::new_with_subscriptions(actions);
+
+                    // Encode the frame into a byte buffer and send it to the destination peer.
+                    let frame = encode_frame(frame, self.frame_diagnostics);
+                    out_cx.emit(DownstreamOutEvent::SendFrame {
+                        dest,
+                        connection: None,
+                        frame,
+                        tag,
+                        priority: Priority::Normal,
+                        control: true,
+                    });
+                }
+                DownstreamInEvent::SendControlMessage { dest, message } => {
+                    // Create a new frame with the control message, encode it and send it to the
+                    // destination peer. The resulting frame will contain only one control message.
+                    let frame = Frame::new_with_control([message]);
+
+                    // Encode the frame into a byte buffer and send it to the destination peer.
+                    let frame = encode_frame(frame, self.frame_diagnostics);
+                    out_cx.emit(DownstreamOutEvent::SendFrame {
+                        dest,
+                        connection: None,
+                        frame,
+                        tag: None,
+                        priority: Priority::Normal,
+                        control: true,
+                    });
+                }
+                DownstreamInEvent::MaxFrameSizeChanged(max_frame_size) => {
+                    // The pending batches are flushed on their timer, and new messages are
+                    // batched up to the new limit.
+                    self.max_frame_size = max_frame_size;
+                }
+                DownstreamInEvent::PeerFrameLimitChanged { peer, limit } => {
+                    // The peer pending batches are flushed on their timer, and new messages are
+                    // batched up to the new limit.
+                    match limit {
+                        Some(limit) => self.peer_frame_limits.insert(peer, limit),
+                        None => self.peer_frame_limits.remove(&peer),
+                    };
+                }
             }
         }
+
+        // Poll the pending batches flush timers and flush the expired batches.
+        let expired = self
+            .pending_batches
+            .iter_mut()
+            .filter_map(|(dest, batch)| {
+                batch.flush_timer.poll_unpin(cx).is_ready().then_some(*dest)
+            })
+            .collect::<Vec<_>>();
+        for dest in expired {
+            tracing::trace!(dest = %dest.0, "Batch window elapsed, flushing batch");
+            self.flush_batch(&mut out_cx, dest);
+        }
+
+        Poll::Pending
     }
 }
@@ -1,6 +1,9 @@
+use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
 
 use bytes::Bytes;
+use itertools::Either;
 use libp2p::identity::PeerId;
 use prost::Message as _;
 
@@ -9,15 +12,234 @@ use libp2p_pubsub_proto::pubsub::{
     ControlMessageProto, FrameProto as RawFrame, MessageProto, SubOptsProto,
 };
 
+use crate::config::Config;
 use crate::framing::{ControlMessage, Message as FrameMessage, SubscriptionAction};
+use crate::lifecycle::ReceivedMessageCtx;
+use crate::topic::TopicValidation;
+use crate::ttl;
 
+use super::checksum::{check_checksum, FrameChecksum};
+use super::convert::{
+    enforce_frame_limits, sanitize_message_proto, MessageValidationError, SubOptsValidationError,
+};
 use super::events::{UpstreamInEvent, UpstreamOutEvent};
-use super::validation::validate_frame_proto;
+use super::interner::TopicHashInterner;
+use super::negative_cache::{NegativeCacheParams, NegativeValidationCache};
+use super::stats::{FramingStatsParams, FramingWindow, PeerFramingStats};
+use super::validation::{
+    validate_frame_proto, FrameFailureClass, FrameLimits, FrameValidationError,
+};
 
 /// The upstream framing service is responsible for decoding, validating and processing the
 /// received frames and emitting the  received messages and subscription request events.
-#[derive(Default)]
-pub struct UpstreamFramingService;
+///
+/// The received frames, messages and subscription actions failing the validation are dropped, and
+/// notified as
+/// [`UpstreamOutEvent::ValidationFailed`] events.
+///
+/// The received messages whose expiry time (see [`Message::set_ttl`](crate::Message::set_ttl))
+/// has passed, taking into account the `message_ttl_clock_skew` tolerance, are dropped.
+///
+/// The received messages whose key exceeds the maximum key size are dropped, and notified as
+/// [`MessageValidationError::KeyTooLarge`] validation errors.
+///
+/// The subscription actions whose topic fails the topic validation rules (see
+/// [`TopicValidation`]) are silently dropped, and counted.
+///
+/// The topic hashes of the received messages and subscription actions are interned (see
+/// [`TopicHashInterner`]), so the repeated decodes of the same topic share one allocation.
+///
+/// The average number of messages per frame of each peer is tracked over a sliding window (see
+/// [`FramingStatsParams`]). The peers sending few messages per frame at a high message rate are
+/// notified as [`UpstreamOutEvent::InefficientFraming`] events, at most once per notification
+/// interval. The peer statistics are dropped once the peer disconnects.
+///
+/// If the frame diagnostics are enabled, the checksum trailer of the received frames carrying
+/// one is verified before decoding them (see
+/// [`Config::frame_diagnostics`](crate::Config::frame_diagnostics)). The frames whose checksum
+/// does not match are dropped as [`FrameFailureClass::TransportCorruption`] failures, all the
+/// other validation failures are [`FrameFailureClass::ProtocolError`] failures. The validation
+/// failures are counted by class.
+///
+/// The received frames limits (see [`FrameLimits`]) are enforced right after decoding the frames,
+/// before any of their parts is processed, so a small frame packed with control messages or
+/// subscription actions does not cause a disproportionate amount of work. Each exceeded limit is
+/// notified as a [`FrameValidationError::LimitExceeded`] protocol error, whether the frame was
+/// truncated or rejected.
+///
+/// The validation failures of the received frames, and messages, are cached (see
+/// [`NegativeValidationCache`]). The re-received invalid frames and messages are dropped, and
+/// notified with the cached validation failure, without being decoded nor validated again. The
+/// transport corruption failures are not cached.
+///
+/// If the `parallel` feature is enabled, the messages of the frames carrying more messages than
+/// the parallel validation threshold are validated in parallel (see
+/// [`Config::parallel_validation_threshold`]). The received messages are emitted in the frame
+/// order.
+pub struct UpstreamFramingService {
+    /// The clock skew tolerated when checking the received messages expiry time.
+    message_ttl_clock_skew: Duration,
+
+    /// The maximum size of the received messages key field.
+    max_key_size: usize,
+
+    /// The topic validation rules of the received subscription actions.
+    topic_validation: TopicValidation,
+
+    /// The number of received subscription actions dropped for failing the topic validation.
+    invalid_topic_announcements: u64,
+
+    /// The received topic hashes interner.
+    topic_interner: TopicHashInterner,
+
+    /// The per-peer framing statistics parameters.
+    stats_params: FramingStatsParams,
+
+    /// The per-peer framing statistics sliding windows.
+    peers_stats: HashMap<PeerId, FramingWindow>,
+
+    /// Whether the received frames checksum trailers are verified.
+    frame_diagnostics: bool,
+
+    /// The received frames limits.
+    limits: FrameLimits,
+
+    /// The received frames validation failures cache.
+    negative_frames: NegativeValidationCache<Rc<anyhow::Error>>,
+
+    /// The received messages validation failures cache.
+    negative_messages: NegativeValidationCache<MessageValidationError>,
+
+    /// The number of validation failures caused by a transport corruption.
+    transport_corruption_failures: u64,
+
+    /// The number of validation failures caused by a protocol error.
+    protocol_error_failures: u64,
+
+    /// The number of messages of a frame above which the frame messages are validated in
+    /// parallel, if the `parallel` feature is enabled.
+    parallel_validation_threshold: usize,
+}
+
+/// The upstream framing service parameters.
+///
+/// See [`UpstreamFramingService`] for more details on each parameter.
+#[derive(Debug, Clone, Copy)]
+pub struct UpstreamFramingParams {
+    /// The clock skew tolerated when checking the received messages expiry time.
+    pub message_ttl_clock_skew: Duration,
+    /// The maximum number of interned topic hashes.
+    pub max_interned_topics: usize,
+    /// The maximum size of the received messages key field.
+    pub max_key_size: usize,
+    /// The topic validation rules of the received subscription actions.
+    pub topic_validation: TopicValidation,
+    /// The per-peer framing statistics parameters.
+    pub stats: FramingStatsParams,
+    /// Whether the received frames checksum trailers are verified.
+    pub frame_diagnostics: bool,
+    /// The received frames limits.
+    pub limits: FrameLimits,
+    /// The received frames and messages validation failures cache parameters.
+    pub negative_cache: NegativeCacheParams,
+    /// The number of messages of a frame above which the frame messages are validated in
+    /// parallel, if the `parallel` feature is enabled.
+    pub parallel_validation_threshold: usize,
+}
+
+impl Default for UpstreamFramingParams {
+    fn default() -> Self {
+        let config = Config::default();
+        Self {
+            message_ttl_clock_skew: Duration::ZERO,
+            max_interned_topics: config.max_interned_topics(),
+            max_key_size: config.max_key_size(),
+            topic_validation: *config.topic_validation(),
+            stats: Default::default(),
+            frame_diagnostics: config.frame_diagnostics(),
+            limits: config.frame_limits(),
+            negative_cache: Default::default(),
+            parallel_validation_threshold: config.parallel_validation_threshold(),
+        }
+    }
+}
+
+impl Default for UpstreamFramingService {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl UpstreamFramingService {
+    /// Creates a new upstream framing service.
+    pub fn new(params: UpstreamFramingParams) -> Self {
+        Self {
+            message_ttl_clock_skew: params.message_ttl_clock_skew,
+            max_key_size: params.max_key_size,
+            topic_validation: params.topic_validation,
+            invalid_topic_announcements: 0,
+            topic_interner: TopicHashInterner::new(params.max_interned_topics),
+            stats_params: params.stats,
+            peers_stats: Default::default(),
+            frame_diagnostics: params.frame_diagnostics,
+            limits: params.limits,
+            negative_frames: NegativeValidationCache::new(params.negative_cache),
+            negative_messages: NegativeValidationCache::new(params.negative_cache),
+            transport_corruption_failures: 0,
+            protocol_error_failures: 0,
+            parallel_validation_threshold: params.parallel_validation_threshold,
+        }
+    }
+
+    /// Get the framing statistics of the frames received from the peer, if any.
+    pub fn peer_stats(&self, peer: &PeerId) -> Option<PeerFramingStats> {
+        self.peers_stats
+            .get(peer)
+            .map(|window| window.stats(Instant::now()))
+    }
+
+    /// Get the number of received subscription actions dropped for failing the topic
+    /// validation.
+    pub fn invalid_topic_announcements(&self) -> u64 {
+        self.invalid_topic_announcements
+    }
+
+    /// Get the number of received frame validation failures of the given class.
+    pub fn frame_failures(&self, class: FrameFailureClass) -> u64 {
+        match class {
+            FrameFailureClass::TransportCorruption => self.transport_corruption_failures,
+            FrameFailureClass::ProtocolError => self.protocol_error_failures,
+        }
+    }
+
+    /// Get the number of received frames and messages dropped as their validation failure was
+    /// cached.
+    pub fn negative_cache_hits(&self) -> u64 {
+        self.negative_frames.hits() + self.negative_messages.hits()
+    }
+
+    /// Get the number of received frames and messages whose validation failure was looked up in
+    /// the negative validation cache, and not found.
+    pub fn negative_cache_misses(&self) -> u64 {
+        self.negative_frames.misses() + self.negative_messages.misses()
+    }
+
+    /// Account a frame carrying `messages` messages received from the `src` peer, and check
+    /// whether the peer framing is inefficient.
+    fn record_frame(&mut self, src: PeerId, messages: usize) -> Option<f64> {
+        if messages == 0 {
+            return None;
+        }
+
+        let now = Instant::now();
+        let window = self
+            .peers_stats
+            .entry(src)
+            .or_insert_with(|| FramingWindow::new(self.stats_params.window));
+        window.record(now, messages);
+        window.check_inefficient(now, &self.stats_params)
+    }
+}
 
 /// Decode a pubsub frame from a byte buffer.
 fn decode_frame(frame: Bytes) -> anyhow::Result<RawFrame> {
@@ -25,12 +247,19 @@ fn decode_frame(frame: Bytes) -> anyhow::Result<RawFrame> {
 }
 
 /// Validate, sanitize and process a raw frame received from the `src` peer.
-fn process_raw_frame(
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn process_raw_frame<'a>(
     src: PeerId,
     frame: RawFrame,
+    message_ttl_clock_skew: Duration,
+    max_key_size: usize,
+    parallel_validation_threshold: usize,
+    topic_validation: &TopicValidation,
+    topic_interner: &'a TopicHashInterner,
+    negative_cache: &'a NegativeValidationCache<MessageValidationError>,
 ) -> anyhow::Result<(
-    impl IntoIterator<Item = FrameMessage>,
-    impl IntoIterator<Item = SubscriptionAction>,
+    impl IntoIterator<Item = Result<FrameMessage, MessageValidationError>> + 'a,
+    impl IntoIterator<Item = Result<SubscriptionAction, SubOptsValidationError>> + 'a,
     impl IntoIterator<Item = ControlMessage>,
 )> {
     // 1. Validate the RPC frame.
@@ -41,10 +270,23 @@ fn process_raw_frame(
     tracing::trace!(%src, "Frame received");
 
     // 2. Validate, sanitize and process the frame messages'.
-    let messages_iter = process_raw_frame_messages(src, frame.publish);
+    let messages_iter = process_raw_frame_messages(
+        src,
+        frame.publish,
+        message_ttl_clock_skew,
+        max_key_size,
+        parallel_validation_threshold,
+        topic_interner,
+        negative_cache,
+    );
 
     // 3. Validate, sanitize and process the frame subscription actions.
-    let subscriptions_iter = process_raw_frame_subscription_requests(src, frame.subscriptions);
+    let subscriptions_iter = process_raw_frame_subscription_requests(
+        src,
+        frame.subscriptions,
+        *topic_validation,
+        topic_interner,
+    );
 
     // 4. Validate, sanitize and process the frame control messages.
     let control_iter = process_raw_frame_control_messages(src, frame.control);
@@ -53,41 +295,222 @@ fn process_raw_frame(
 }
 
 /// Validates, sanitizes and processes the raw frame messages.
-fn process_raw_frame_messages(
+///
+/// The expired messages are skipped, and the invalid messages, including the messages whose key
+/// exceeds the maximum key size, are returned as errors. The messages whose validation failure is
+/// cached are returned as errors without being validated again.
+///
+/// If the `parallel` feature is enabled, and the frame carries more messages than the parallel
+/// validation threshold, the messages are processed as a batch (see
+/// [`process_raw_frame_messages_batch`]).
+fn process_raw_frame_messages<'a>(
     src: PeerId,
     messages: Vec<MessageProto>,
-) -> impl IntoIterator<Item = FrameMessage> {
-    messages
+    message_ttl_clock_skew: Duration,
+    max_key_size: usize,
+    parallel_validation_threshold: usize,
+    topic_interner: &'a TopicHashInterner,
+    negative_cache: &'a NegativeValidationCache<MessageValidationError>,
+) -> impl IntoIterator<Item = Result<FrameMessage, MessageValidationError>> + 'a {
+    let now = SystemTime::now();
+    if cfg!(feature = "parallel") && messages.len() > parallel_validation_threshold {
+        return Either::Left(
+            process_raw_frame_messages_batch(
+                src,
+                messages,
+                now,
+                message_ttl_clock_skew,
+                max_key_size,
+                topic_interner,
+                negative_cache,
+            )
+            .into_iter(),
+        );
+    }
+
+    Either::Right(messages.into_iter().filter_map(move |msg| {
+        let key = negative_cache.message_key(&msg);
+        if let Some(err) = negative_cache.get(key) {
+            tracing::trace!(%src, "Received cached invalid message: {}", err);
+            return Some(Err(err));
+        }
+
+        let msg = validate_message_proto(msg, now, message_ttl_clock_skew, max_key_size);
+        process_validated_message(src, key, msg, topic_interner, negative_cache)
+    }))
+}
+
+/// Validates, sanitizes and processes the raw frame messages as a batch.
+///
+/// The negative validation cache lookups and updates, and the topic hashes interning, are done
+/// serially, while the messages validation, which holds no shared state, is done in parallel (see
+/// [`validate_message_protos`]). The messages are returned in the frame order.
+fn process_raw_frame_messages_batch(
+    src: PeerId,
+    messages: Vec<MessageProto>,
+    now: SystemTime,
+    message_ttl_clock_skew: Duration,
+    max_key_size: usize,
+    topic_interner: &TopicHashInterner,
+    negative_cache: &NegativeValidationCache<MessageValidationError>,
+) -> Vec<Result<FrameMessage, MessageValidationError>> {
+    // 1. Look up the cached validation failures, and set aside the messages to validate.
+    let mut lookups = Vec::with_capacity(messages.len());
+    let mut pending = Vec::with_capacity(messages.len());
+    for msg in messages {
+        let key = negative_cache.message_key(&msg);
+        let cached = negative_cache.get(key);
+        if cached.is_none() {
+            pending.push(msg);
+        }
+        lookups.push((key, cached));
+    }
+
+    // 2. Validate the messages.
+    let mut validated =
+        validate_message_protos(pending, now, message_ttl_clock_skew, max_key_size).into_iter();
+
+    // 3. Wrap the valid messages, and cache the validation failures, in the frame order.
+    lookups
         .into_iter()
-        .filter_map(move |msg| match msg.try_into() {
-            Ok(msg) => {
-                tracing::trace!(%src, "Message received");
-                Some(msg)
+        .filter_map(|(key, cached)| match cached {
+            Some(err) => {
+                tracing::trace!(%src, "Received cached invalid message: {}", err);
+                Some(Err(err))
             }
-            Err(err) => {
-                tracing::trace!(%src, "Received invalid message: {}", err);
-                None
+            None => {
+                let msg = validated.next()?;
+                process_validated_message(src, key, msg, topic_interner, negative_cache)
             }
         })
+        .collect()
+}
+
+/// A received message protobuf validation outcome.
+///
+/// The validated messages are plain protobuf messages, not yet wrapped into [`FrameMessage`]s, so
+/// they can be sent across threads.
+#[derive(Debug)]
+enum ValidatedMessage {
+    /// The message is valid, and sanitized.
+    Valid(MessageProto),
+    /// The message is valid, but its expiry time has passed.
+    Expired,
+    /// The message is invalid.
+    Invalid(MessageValidationError),
+}
+
+/// Validates and sanitizes a received message protobuf, and checks its expiry time.
+fn validate_message_proto(
+    msg: MessageProto,
+    now: SystemTime,
+    message_ttl_clock_skew: Duration,
+    max_key_size: usize,
+) -> ValidatedMessage {
+    let msg = match sanitize_message_proto(msg) {
+        Ok(msg) => msg,
+        Err(err) => return ValidatedMessage::Invalid(err),
+    };
+
+    match msg.key.as_ref() {
+        Some(key) if key.len() > max_key_size => {
+            ValidatedMessage::Invalid(MessageValidationError::KeyTooLarge)
+        }
+        _ if ttl::is_expired(
+            msg.data.as_deref().unwrap_or_default(),
+            now,
+            message_ttl_clock_skew,
+        ) =>
+        {
+            ValidatedMessage::Expired
+        }
+        _ => ValidatedMessage::Valid(msg),
+    }
+}
+
+/// Validates the received message protobufs in parallel, preserving their order.
+#[cfg(feature = "parallel")]
+fn validate_message_protos(
+    messages: Vec<MessageProto>,
+    now: SystemTime,
+    message_ttl_clock_skew: Duration,
+    max_key_size: usize,
+) -> Vec<ValidatedMessage> {
+    use rayon::prelude::*;
+
+    messages
+        .into_par_iter()
+        .map(|msg| validate_message_proto(msg, now, message_ttl_clock_skew, max_key_size))
+        .collect()
+}
+
+/// Validates the received message protobufs, preserving their order.
+#[cfg(not(feature = "parallel"))]
+fn validate_message_protos(
+    messages: Vec<MessageProto>,
+    now: SystemTime,
+    message_ttl_clock_skew: Duration,
+    max_key_size: usize,
+) -> Vec<ValidatedMessage> {
+    messages
+        .into_iter()
+        .map(|msg| validate_message_proto(msg, now, message_ttl_clock_skew, max_key_size))
+        .collect()
+}
+
+/// Processes a validated message: the valid message is wrapped, interning its topic hash, the
+/// expired message is skipped, and the invalid message validation failure is cached.
+fn process_validated_message(
+    src: PeerId,
+    key: u64,
+    msg: ValidatedMessage,
+    topic_interner: &TopicHashInterner,
+    negative_cache: &NegativeValidationCache<MessageValidationError>,
+) -> Option<Result<FrameMessage, MessageValidationError>> {
+    match msg {
+        ValidatedMessage::Valid(msg) => {
+            tracing::trace!(%src, "Message received");
+            Some(Ok(FrameMessage::from_sanitized_proto(msg, |topic| {
+                topic_interner.intern(topic)
+            })))
+        }
+        ValidatedMessage::Expired => {
+            tracing::trace!(%src, "Received expired message");
+            None
+        }
+        ValidatedMessage::Invalid(err) => {
+            tracing::trace!(%src, "Received invalid message: {}", err);
+            negative_cache.insert(key, err);
+            Some(Err(err))
+        }
+    }
 }
 
 /// Validates, sanitizes and processes the raw frame subscription requests.
+///
+/// The invalid subscription requests are returned as errors. The topic is validated before it is
+/// interned, so the invalid topics are not interned.
 fn process_raw_frame_subscription_requests(
     src: PeerId,
     subscriptions: Vec<SubOptsProto>,
-) -> impl IntoIterator<Item = SubscriptionAction> {
-    subscriptions
-        .into_iter()
-        .filter_map(move |sub| match sub.try_into() {
-            Ok(sub) => {
-                tracing::trace!(%src, "Subscription request received");
-                Some(sub)
-            }
-            Err(err) => {
-                tracing::trace!(%src, "Received invalid subscription action: {}", err);
-                None
-            }
-        })
+    topic_validation: TopicValidation,
+    topic_interner: &TopicHashInterner,
+) -> impl IntoIterator<Item = Result<SubscriptionAction, SubOptsValidationError>> + '_ {
+    subscriptions.into_iter().map(move |sub| {
+        let sub = match sub
+            .topic_id
+            .as_deref()
+            .map(|topic| topic_validation.validate(topic))
+        {
+            Some(Err(err)) => Err(SubOptsValidationError::InvalidTopic(err)),
+            _ => SubscriptionAction::try_from_proto(sub, |topic| topic_interner.intern(topic)),
+        };
+        match &sub {
+            Ok(_) => tracing::trace!(%src, "Subscription request received"),
+            Err(err) => tracing::trace!(%src, "Received invalid subscription action: {}", err),
+        }
+        sub
+    })
 }
 
 /// Validates, sanitizes and processes the raw frame control messages.
@@ -152,33 +575,150 @@ impl EventHandler for UpstreamFramingService {
         ev: Self::InEvent,
     ) {
         match ev {
-            UpstreamInEvent::RawFrameReceived { src, frame } => {
+            UpstreamInEvent::RawFrameReceived {
+                src,
+                connection,
+                frame,
+            } => {
+                // Verify the received frame checksum, if any, before decoding it.
+                let frame = match self.frame_diagnostics.then(|| check_checksum(&frame)) {
+                    Some(FrameChecksum::Valid(payload)) => payload,
+                    Some(FrameChecksum::Mismatch) => {
+                        tracing::debug!(%src, "Corrupted frame received");
+                        self.transport_corruption_failures += 1;
+                        svc_cx.emit(UpstreamOutEvent::ValidationFailed {
+                            src,
+                            error: Rc::new(FrameValidationError::ChecksumMismatch.into()),
+                            class: FrameFailureClass::TransportCorruption,
+                        });
+                        return;
+                    }
+                    Some(FrameChecksum::Absent) | None => frame,
+                };
+
+                // Drop the frames whose validation failure is cached, before decoding them.
+                let frame_key = self.negative_frames.frame_key(&frame);
+                if let Some(error) = self.negative_frames.get(frame_key) {
+                    tracing::trace!(%src, "Cached invalid frame received: {}", error);
+                    self.protocol_error_failures += 1;
+                    svc_cx.emit(UpstreamOutEvent::ValidationFailed {
+                        src,
+                        error,
+                        class: FrameFailureClass::ProtocolError,
+                    });
+                    return;
+                }
+
                 // Decode the received frame.
-                let frame = match decode_frame(frame) {
+                let mut frame = match decode_frame(frame) {
                     Ok(frame) => frame,
                     Err(err) => {
                         tracing::trace!(%src, "Invalid frame received: {}", err);
+                        let error = Rc::new(err);
+                        self.negative_frames.insert(frame_key, Rc::clone(&error));
+                        self.protocol_error_failures += 1;
+                        svc_cx.emit(UpstreamOutEvent::ValidationFailed {
+                            src,
+                            error,
+                            class: FrameFailureClass::ProtocolError,
+                        });
                         return;
                     }
                 };
 
+                // Enforce the received frames limits before processing the frame parts.
+                match enforce_frame_limits(&mut frame, &self.limits) {
+                    Ok(truncated) => {
+                        for limit in truncated {
+                            tracing::debug!(%src, %limit, "Frame limit exceeded, truncating frame");
+                            self.protocol_error_failures += 1;
+                            svc_cx.emit(UpstreamOutEvent::ValidationFailed {
+                                src,
+                                error: Rc::new(FrameValidationError::LimitExceeded(limit).into()),
+                                class: FrameFailureClass::ProtocolError,
+                            });
+                        }
+                    }
+                    Err(limit) => {
+                        tracing::debug!(%src, %limit, "Frame limit exceeded, rejecting frame");
+                        let error = Rc::new(FrameValidationError::LimitExceeded(limit).into());
+                        self.negative_frames.insert(frame_key, Rc::clone(&error));
+                        self.protocol_error_failures += 1;
+                        svc_cx.emit(UpstreamOutEvent::ValidationFailed {
+                            src,
+                            error,
+                            class: FrameFailureClass::ProtocolError,
+                        });
+                        return;
+                    }
+                }
+
+                // Account the received frame messages.
+                if let Some(avg_msgs_per_frame) = self.record_frame(src, frame.publish.len()) {
+                    tracing::debug!(%src, avg_msgs_per_frame, "Inefficient peer framing");
+                    svc_cx.emit(UpstreamOutEvent::InefficientFraming {
+                        src,
+                        avg_msgs_per_frame,
+                    });
+                }
+
                 // Process the received frames.
-                match process_raw_frame(src, frame) {
+                match process_raw_frame(
+                    src,
+                    frame,
+                    self.message_ttl_clock_skew,
+                    self.max_key_size,
+                    self.parallel_validation_threshold,
+                    &self.topic_validation,
+                    &self.topic_interner,
+                    &self.negative_messages,
+                ) {
                     Ok((messages, subscriptions, control)) => {
-                        // Emit the received messages.
-                        let messages =
-                            messages
-                                .into_iter()
-                                .map(|message| UpstreamOutEvent::MessageReceived {
+                        // Emit the received messages, and the invalid messages validation errors.
+                        let protocol_error_failures = &mut self.protocol_error_failures;
+                        let messages = messages.into_iter().map(|message| match message {
+                            Ok(message) => UpstreamOutEvent::MessageReceived(
+                                ReceivedMessageCtx::new(src, message),
+                            ),
+                            Err(err) => {
+                                *protocol_error_failures += 1;
+                                UpstreamOutEvent::ValidationFailed {
                                     src,
-                                    message: Rc::new(message),
-                                });
+                                    error: Rc::new(
+                                        FrameValidationError::InvalidMessage(err).into(),
+                                    ),
+                                    class: FrameFailureClass::ProtocolError,
+                                }
+                            }
+                        });
                         svc_cx.emit_batch(messages);
 
-                        // Emit the received subscription actions.
-                        let subscriptions = subscriptions.into_iter().map(|action| {
-                            UpstreamOutEvent::SubscriptionRequestReceived { src, action }
-                        });
+                        // Emit the received subscription actions, and the invalid subscription actions
+                        // validation errors. The invalid topic announcements are silently dropped.
+                        let invalid_topic_announcements = &mut self.invalid_topic_announcements;
+                        let protocol_error_failures = &mut self.protocol_error_failures;
+                        let subscriptions =
+                            subscriptions.into_iter().filter_map(|action| match action {
+                                Ok(action) => Some(UpstreamOutEvent::SubscriptionRequestReceived {
+                                    src,
+                                    connection,
+                                    action,
+                                }),
+                                Err(SubOptsValidationError::InvalidTopic(_)) => {
+                                    *invalid_topic_announcements += 1;
+                                    None
+                                }
+                                Err(err) => {
+                                    *protocol_error_failures += 1;
+                                    Some(UpstreamOutEvent::ValidationFailed {
+                                        src,
+                                        error: Rc::new(
+                                            FrameValidationError::InvalidSubscription(err).into(),
+                                        ),
+                                        class: FrameFailureClass::ProtocolError,
+                                    })
+                                }
+                            });
                         svc_cx.emit_batch(subscriptions);
 
                         // Emit the received control messages.
@@ -189,9 +729,21 @@ impl EventHandler for UpstreamFramingService {
                     }
                     Err(err) => {
                         tracing::trace!(%src, "Invalid frame received: {}", err);
+                        let error = Rc::new(err);
+                        self.negative_frames.insert(frame_key, Rc::clone(&error));
+                        self.protocol_error_failures += 1;
+                        svc_cx.emit(UpstreamOutEvent::ValidationFailed {
+                            src,
+                            error,
+                            class: FrameFailureClass::ProtocolError,
+                        });
                     }
                 }
             }
+            UpstreamInEvent::PeerDisconnected(peer) => {
+                // Drop the disconnected peer framing statistics.
+                self.peers_stats.remove(&peer);
+            }
         }
     }
 }
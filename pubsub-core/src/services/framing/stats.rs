@@ -0,0 +1,290 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The number of buckets the framing statistics sliding window is divided into.
+const WINDOW_BUCKETS: u32 = 10;
+
+/// The framing statistics of the frames received from a peer over the sliding window (see
+/// [`Config::framing_stats_window`](crate::Config::framing_stats_window)).
+///
+/// Only the frames carrying at least one message are accounted, the frames carrying only
+/// subscription actions or control messages are ignored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct PeerFramingStats {
+    /// The number of frames received over the window.
+    pub frames: u64,
+    /// The number of messages received over the window.
+    pub messages: u64,
+    /// The average number of messages per frame over the window.
+    pub avg_msgs_per_frame: f64,
+    /// The average number of messages received per second over the window.
+    pub message_rate: f64,
+}
+
+/// The per-peer framing statistics parameters.
+#[derive(Debug, Clone, Copy)]
+pub struct FramingStatsParams {
+    /// The framing statistics sliding window duration.
+    pub window: Duration,
+    /// The average number of messages per frame below which a peer framing is inefficient.
+    pub inefficient_threshold: f64,
+    /// The message rate, in messages per second, above which an inefficient peer framing is
+    /// notified.
+    pub min_message_rate: f64,
+    /// The minimum time between two inefficient framing notifications of the same peer.
+    pub notify_interval: Duration,
+}
+
+impl Default for FramingStatsParams {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(10),
+            inefficient_threshold: 1.5,
+            min_message_rate: 100.0,
+            notify_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// The frames and messages received during a window bucket.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// The bucket start time.
+    start: Instant,
+    frames: u64,
+    messages: u64,
+}
+
+/// A sliding window of the frames received from a peer.
+///
+/// The window is divided into [`WINDOW_BUCKETS`] buckets, so its memory is bounded regardless of
+/// the peer frame rate. A bucket is evicted once it is entirely older than the window.
+#[derive(Debug)]
+pub(crate) struct FramingWindow {
+    /// The window duration.
+    window: Duration,
+    /// The window buckets, oldest first.
+    buckets: VecDeque<Bucket>,
+    /// The last time the peer inefficient framing was notified.
+    last_notified: Option<Instant>,
+}
+
+impl FramingWindow {
+    /// Create a new sliding window of the given duration.
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            buckets: VecDeque::new(),
+            last_notified: None,
+        }
+    }
+
+    /// The duration of a window bucket.
+    fn bucket_len(&self) -> Duration {
+        self.window / WINDOW_BUCKETS
+    }
+
+    /// Whether the bucket is entirely older than the window.
+    fn is_expired(&self, bucket: &Bucket, now: Instant) -> bool {
+        now.saturating_duration_since(bucket.start) >= self.window + self.bucket_len()
+    }
+
+    /// Record a frame carrying `messages` messages received at `now`.
+    pub(crate) fn record(&mut self, now: Instant, messages: usize) {
+        while let Some(bucket) = self.buckets.front() {
+            if !self.is_expired(bucket, now) {
+                break;
+            }
+            self.buckets.pop_front();
+        }
+
+        let bucket_len = self.bucket_len();
+        match self.buckets.back_mut() {
+            Some(bucket) if now.saturating_duration_since(bucket.start) < bucket_len => {
+                bucket.frames += 1;
+                bucket.messages += messages as u64;
+            }
+            _ => self.buckets.push_back(Bucket {
+                start: now,
+                frames: 1,
+                messages: messages as u64,
+            }),
+        }
+    }
+
+    /// The framing statistics over the window ending at `now`.
+    pub(crate) fn stats(&self, now: Instant) -> PeerFramingStats {
+        let (frames, messages) = self
+            .buckets
+            .iter()
+            .filter(|bucket| !self.is_expired(bucket, now))
+            .fold((0, 0), |(frames, messages), bucket| {
+                (frames + bucket.frames, messages + bucket.messages)
+            });
+
+        let avg_msgs_per_frame = if frames == 0 {
+            0.0
+        } else {
+            messages as f64 / frames as f64
+        };
+        let window_secs = self.window.as_secs_f64();
+        let message_rate = if window_secs == 0.0 {
+            0.0
+        } else {
+            messages as f64 / window_secs
+        };
+
+        PeerFramingStats {
+            frames,
+            messages,
+            avg_msgs_per_frame,
+            message_rate,
+        }
+    }
+
+    /// Check whether the peer framing is inefficient at `now`, and it was not notified during the
+    /// last notification interval. If so, returns the window average messages per frame and marks
+    /// the peer as notified.
+    pub(crate) fn check_inefficient(
+        &mut self,
+        now: Instant,
+        params: &FramingStatsParams,
+    ) -> Option<f64> {
+        let stats = self.stats(now);
+        if stats.frames == 0
+            || stats.avg_msgs_per_frame >= params.inefficient_threshold
+            || stats.message_rate < params.min_message_rate
+        {
+            return None;
+        }
+
+        if let Some(last) = self.last_notified {
+            if now.saturating_duration_since(last) < params.notify_interval {
+                return None;
+            }
+        }
+
+        self.last_notified = Some(now);
+        Some(stats.avg_msgs_per_frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_params() -> FramingStatsParams {
+        FramingStatsParams {
+            window: Duration::from_secs(10),
+            inefficient_threshold: 1.5,
+            min_message_rate: 1.0,
+            notify_interval: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn stats_average_the_messages_per_frame_over_the_window() {
+        //// Given
+        let start = Instant::now();
+        let mut window = FramingWindow::new(Duration::from_secs(10));
+
+        //// When
+        window.record(start, 1);
+        window.record(start + Duration::from_millis(500), 3);
+        window.record(start + Duration::from_secs(5), 2);
+
+        //// Then
+        let stats = window.stats(start + Duration::from_secs(5));
+        assert_eq!(stats.frames, 3);
+        assert_eq!(stats.messages, 6);
+        assert_eq!(stats.avg_msgs_per_frame, 2.0);
+        assert_eq!(stats.message_rate, 0.6);
+    }
+
+    #[test]
+    fn frames_older_than_the_window_are_evicted() {
+        //// Given
+        let start = Instant::now();
+        let mut window = FramingWindow::new(Duration::from_secs(10));
+
+        window.record(start, 1);
+        window.record(start + Duration::from_secs(8), 4);
+
+        //// When
+        let now = start + Duration::from_secs(12);
+        window.record(now, 4);
+
+        //// Then
+        let stats = window.stats(now);
+        assert_eq!(stats.frames, 2, "The oldest frame should be evicted");
+        assert_eq!(stats.messages, 8);
+        assert_eq!(stats.avg_msgs_per_frame, 4.0);
+
+        let stats = window.stats(now + Duration::from_secs(30));
+        assert_eq!(stats.frames, 0, "All the frames should be expired");
+        assert_eq!(stats.avg_msgs_per_frame, 0.0);
+        assert_eq!(stats.message_rate, 0.0);
+    }
+
+    #[test]
+    fn window_memory_is_bounded_by_the_buckets_count() {
+        //// Given
+        let start = Instant::now();
+        let mut window = FramingWindow::new(Duration::from_secs(10));
+
+        //// When
+        for i in 0..10_000 {
+            window.record(start + Duration::from_millis(i * 10), 1);
+        }
+
+        //// Then
+        assert!(window.buckets.len() <= WINDOW_BUCKETS as usize + 1);
+    }
+
+    #[test]
+    fn inefficient_framing_is_notified_once_per_interval() {
+        //// Given
+        let start = Instant::now();
+        let params = new_test_params();
+        let mut window = FramingWindow::new(params.window);
+
+        //// When
+        let mut notified = Vec::new();
+        for i in 0..100 {
+            let now = start + Duration::from_secs(i);
+            window.record(now, 1);
+            if let Some(avg) = window.check_inefficient(now, &params) {
+                notified.push((i, avg));
+            }
+        }
+
+        //// Then
+        // The rate reaches 1 message per second once the window is filled.
+        assert_eq!(notified, [(9, 1.0), (69, 1.0)]);
+    }
+
+    #[test]
+    fn efficient_or_low_rate_framing_is_not_notified() {
+        //// Given
+        let start = Instant::now();
+        let params = new_test_params();
+        let mut batching = FramingWindow::new(params.window);
+        let mut low_rate = FramingWindow::new(params.window);
+
+        //// When
+        for i in 0..20 {
+            let now = start + Duration::from_secs(i);
+            batching.record(now, 8);
+            if i % 2 == 0 {
+                low_rate.record(now, 1);
+            }
+        }
+
+        //// Then
+        let now = start + Duration::from_secs(19);
+        assert_eq!(batching.check_inefficient(now, &params), None);
+        assert_eq!(low_rate.check_inefficient(now, &params), None);
+    }
+}
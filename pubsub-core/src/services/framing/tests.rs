@@ -1,21 +1,28 @@
+use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::{Duration, SystemTime};
 
 use assert_matches::assert_matches;
 use bytes::{Bytes, BytesMut};
 use libp2p::identity::PeerId;
+use libp2p::swarm::ConnectionId;
 use prost::Message;
 use rand::random;
 
-use libp2p_pubsub_proto::pubsub::FrameProto;
+use libp2p_pubsub_common::service::BufferedContext;
+use libp2p_pubsub_proto::pubsub::{FrameProto, MessageProto, SubOptsProto};
 use testlib;
 use testlib::service::noop_context;
 
 use crate::framing::{Frame, Message as FrameMessage, SubscriptionAction};
-use crate::topic::TopicHash;
+use crate::message::Priority;
+use crate::topic::{TopicHash, TopicValidation};
+use crate::ttl;
 
 use super::events::{DownstreamInEvent, DownstreamOutEvent, UpstreamInEvent, UpstreamOutEvent};
 use super::service_downstream::DownstreamFramingService;
-use super::service_upstream::UpstreamFramingService;
+use super::service_upstream::{UpstreamFramingParams, UpstreamFramingService};
+use super::stats::FramingStatsParams;
 
 /// Convenience function to create a new `PeerId` for testing.
 fn new_test_peer_id() -> PeerId {
@@ -57,6 +64,7 @@ mod upstream {
     ) -> impl IntoIterator<Item = UpstreamInEvent> {
         [UpstreamInEvent::RawFrameReceived {
             src,
+            connection: ConnectionId::new_unchecked(0),
             frame: encode_frame(frame),
         }]
     }
@@ -76,7 +84,11 @@ mod upstream {
         let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
 
         //// Then
-        assert_eq!(output_events.len(), 0, "No events should be emitted");
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::ValidationFailed { src, error, .. } => {
+            assert_eq!(src, &remote_peer);
+            assert_eq!(error.to_string(), "empty frame");
+        });
     }
 
     #[test]
@@ -97,7 +109,47 @@ mod upstream {
         let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
 
         //// Then
-        assert_eq!(output_events.len(), 0, "No events should be emitted");
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::ValidationFailed { src, error, .. } => {
+            assert_eq!(src, &remote_peer);
+            assert_eq!(error.to_string(), "invalid message: empty topic");
+        });
+    }
+
+    #[test]
+    fn process_frame_with_message_key_larger_than_max_key_size() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        let mut valid_message = new_test_message(topic.clone());
+        valid_message.set_key(Some(vec![0x01; 32]));
+        let mut invalid_message = new_test_message(topic);
+        invalid_message.set_key(Some(vec![0x02; 33]));
+        let frame = Frame::new_with_messages([valid_message.clone(), invalid_message]);
+
+        let mut service =
+            BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+                max_interned_topics: 0,
+                max_key_size: 32,
+                ..Default::default()
+            }));
+
+        //// When
+        let input_events = new_raw_frame_received_seq(remote_peer, frame);
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(output_events.len(), 2, "2 events should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::MessageReceived(ctx) => {
+            assert_eq!(ctx.message().key(), valid_message.key(), "The key should be untouched");
+        });
+        assert_matches!(&output_events[1], UpstreamOutEvent::ValidationFailed { src, error, .. } => {
+            assert_eq!(src, &remote_peer);
+            assert_eq!(error.to_string(), "invalid message: key too large");
+        });
     }
 
     #[test]
@@ -117,10 +169,193 @@ mod upstream {
 
         let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
 
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::ValidationFailed { src, .. } => {
+            assert_eq!(src, &remote_peer);
+        });
+    }
+
+    #[test]
+    fn process_frame_with_invalid_subscription_request_missing_action() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+
+        let frame = FrameProto {
+            subscriptions: vec![SubOptsProto {
+                subscribe: None,
+                topic_id: Some(new_test_topic().into_string()),
+            }],
+            ..Default::default()
+        };
+
+        let mut service = testlib::service::default_test_service::<UpstreamFramingService>();
+
+        //// When
+        let input_events = new_raw_frame_received_seq(remote_peer, frame);
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::ValidationFailed { src, .. } => {
+            assert_eq!(src, &remote_peer);
+        });
+    }
+
+    #[test]
+    fn process_frame_with_invalid_message_author() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+
+        let frame = FrameProto {
+            publish: vec![MessageProto {
+                from: Some(Bytes::from_static(b"\x00\x25not-a-peer-id")),
+                data: Some(Bytes::from_static(b"test-payload")),
+                topic: new_test_topic().into_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut service = testlib::service::default_test_service::<UpstreamFramingService>();
+
+        //// When
+        let input_events = new_raw_frame_received_seq(remote_peer, frame);
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::ValidationFailed { src, error, .. } => {
+            assert_eq!(src, &remote_peer);
+            assert_eq!(error.to_string(), "invalid message: invalid peer id");
+        });
+    }
+
+    #[test]
+    fn process_frame_with_message_without_data() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        let frame = FrameProto {
+            publish: vec![MessageProto {
+                data: None,
+                topic: topic.clone().into_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut service = testlib::service::default_test_service::<UpstreamFramingService>();
+
+        //// When
+        let input_events = new_raw_frame_received_seq(remote_peer, frame);
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::MessageReceived(ctx) => {
+            assert_eq!(ctx.topic(), &topic);
+            assert!(ctx.message().data().is_empty(), "A missing payload should be empty");
+        });
+    }
+
+    #[test]
+    fn drop_expired_message_outside_clock_skew_tolerance() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        // A message that expired 2 seconds ago.
+        let expiry = SystemTime::now() - Duration::from_secs(2);
+        let frame = Frame::new_with_messages([FrameMessage::new(
+            topic,
+            ttl::encode(expiry, b"expired-payload"),
+        )]);
+
+        let mut service =
+            BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+                message_ttl_clock_skew: Duration::from_secs(1),
+                max_interned_topics: 0,
+                ..Default::default()
+            }));
+
+        //// When
+        let input_events = new_raw_frame_received_seq(remote_peer, frame);
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
         //// Then
         assert_eq!(output_events.len(), 0, "No events should be emitted");
     }
 
+    #[test]
+    fn accept_expired_message_within_clock_skew_tolerance() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        // A message that expired 2 seconds ago, from a peer whose clock may be 5 seconds behind.
+        let expiry = SystemTime::now() - Duration::from_secs(2);
+        let frame = Frame::new_with_messages([FrameMessage::new(
+            topic.clone(),
+            ttl::encode(expiry, b"expired-payload"),
+        )]);
+
+        let mut service =
+            BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+                message_ttl_clock_skew: Duration::from_secs(5),
+                max_interned_topics: 0,
+                ..Default::default()
+            }));
+
+        //// When
+        let input_events = new_raw_frame_received_seq(remote_peer, frame);
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::MessageReceived(ctx) => {
+            assert_eq!(ctx.topic(), &topic);
+        });
+    }
+
+    #[test]
+    fn process_malformed_frame_bytes() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+
+        // A truncated length-delimited `publish` field.
+        let frame = Bytes::from_static(&[0x12, 0x80, 0x80, 0x80, 0x80, 0x10]);
+
+        let mut service = testlib::service::default_test_service::<UpstreamFramingService>();
+
+        //// When
+        let input_events = [UpstreamInEvent::RawFrameReceived {
+            src: remote_peer,
+            connection: ConnectionId::new_unchecked(0),
+            frame,
+        }];
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::ValidationFailed { src, .. } => {
+            assert_eq!(src, &remote_peer);
+        });
+    }
+
     #[test]
     fn process_frame_with_multiple_messages() {
         //// Given
@@ -144,13 +379,13 @@ mod upstream {
 
         //// Then
         assert_eq!(output_events.len(), 2, "Only 2 events should be emitted");
-        assert_matches!(&output_events[0], UpstreamOutEvent::MessageReceived { src, message } => {
-            assert_eq!(src, &remote_peer);
-            assert_eq!(message.as_ref(), &message_a);
+        assert_matches!(&output_events[0], UpstreamOutEvent::MessageReceived(ctx) => {
+            assert_eq!(ctx.src(), remote_peer);
+            assert_eq!(ctx.message().as_ref(), &message_a);
         });
-        assert_matches!(&output_events[1], UpstreamOutEvent::MessageReceived { src, message } => {
-            assert_eq!(src, &remote_peer);
-            assert_eq!(message.as_ref(), &message_b);
+        assert_matches!(&output_events[1], UpstreamOutEvent::MessageReceived(ctx) => {
+            assert_eq!(ctx.src(), remote_peer);
+            assert_eq!(ctx.message().as_ref(), &message_b);
         });
     }
 
@@ -180,15 +415,814 @@ mod upstream {
 
         //// Then
         assert_eq!(output_events.len(), 2, "Only 2 events should be emitted");
-        assert_matches!(&output_events[0], UpstreamOutEvent::SubscriptionRequestReceived { src, action } => {
+        assert_matches!(&output_events[0], UpstreamOutEvent::SubscriptionRequestReceived { src, action, .. } => {
             assert_eq!(src, &remote_peer);
             assert_eq!(action, &subscription_request_a);
         });
-        assert_matches!(&output_events[1], UpstreamOutEvent::SubscriptionRequestReceived { src, action } => {
+        assert_matches!(&output_events[1], UpstreamOutEvent::SubscriptionRequestReceived { src, action, .. } => {
             assert_eq!(src, &remote_peer);
             assert_eq!(action, &subscription_request_b);
         });
     }
+
+    /// Create a new upstream framing service validating the received topics with the given
+    /// topic validation rules.
+    fn new_topic_validation_test_service(
+        topic_validation: TopicValidation,
+    ) -> BufferedContext<UpstreamFramingService> {
+        BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+            max_interned_topics: 0,
+            topic_validation,
+            ..Default::default()
+        }))
+    }
+
+    /// Process a frame subscribing to a valid topic and to the `invalid_topic`, and assert that
+    /// only the valid topic subscription is emitted, and the invalid one is dropped and counted.
+    fn assert_invalid_topic_announcement_is_dropped(
+        topic_validation: TopicValidation,
+        invalid_topic: &str,
+    ) {
+        //// Given
+        let remote_peer = new_test_peer_id();
+
+        let valid_request = SubscriptionAction::Subscribe(TopicHash::from_raw("/test/topic"));
+        let invalid_request = SubscriptionAction::Subscribe(TopicHash::from_raw(invalid_topic));
+        let frame = Frame::new_with_subscriptions([invalid_request, valid_request.clone()]);
+
+        let mut service = new_topic_validation_test_service(topic_validation);
+
+        //// When
+        let input_events = new_raw_frame_received_seq(remote_peer, frame);
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::SubscriptionRequestReceived { src, action, .. } => {
+            assert_eq!(src, &remote_peer);
+            assert_eq!(action, &valid_request);
+        });
+        assert_eq!(service.invalid_topic_announcements(), 1);
+    }
+
+    #[test]
+    fn drop_subscription_request_with_topic_longer_than_max_length() {
+        let topic_validation = TopicValidation {
+            max_length: 16,
+            ..Default::default()
+        };
+        assert_invalid_topic_announcement_is_dropped(topic_validation, "/test/too-long-topic");
+    }
+
+    #[test]
+    fn drop_subscription_request_with_control_characters_in_topic() {
+        assert_invalid_topic_announcement_is_dropped(Default::default(), "/test/topic\n");
+    }
+
+    #[test]
+    fn drop_subscription_request_with_replacement_character_in_topic() {
+        assert_invalid_topic_announcement_is_dropped(Default::default(), "/test/topic-\u{fffd}");
+    }
+
+    #[test]
+    fn drop_subscription_request_rejected_by_custom_topic_validation() {
+        let topic_validation = TopicValidation {
+            custom: Some(|topic| topic.starts_with("/test/")),
+            ..Default::default()
+        };
+        assert_invalid_topic_announcement_is_dropped(topic_validation, "/other/topic");
+    }
+
+    #[test]
+    fn accept_subscription_request_with_control_characters_if_allowed() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+
+        let request = SubscriptionAction::Subscribe(TopicHash::from_raw("/test/topic\n"));
+        let frame = Frame::new_with_subscriptions([request.clone()]);
+
+        let mut service = new_topic_validation_test_service(TopicValidation {
+            allow_control_chars: true,
+            ..Default::default()
+        });
+
+        //// When
+        let input_events = new_raw_frame_received_seq(remote_peer, frame);
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::SubscriptionRequestReceived { action, .. } => {
+            assert_eq!(action, &request);
+        });
+        assert_eq!(service.invalid_topic_announcements(), 0);
+    }
+
+    /// Create a new upstream framing service notifying any peer sending fewer than 2 messages per
+    /// frame.
+    fn new_framing_stats_test_service() -> BufferedContext<UpstreamFramingService> {
+        BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+            message_ttl_clock_skew: Duration::from_secs(1),
+            max_interned_topics: 0,
+            stats: FramingStatsParams {
+                window: Duration::from_secs(10),
+                inefficient_threshold: 2.0,
+                min_message_rate: 0.0,
+                notify_interval: Duration::from_secs(60),
+            },
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn track_peer_framing_stats() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        let single_message_frame = Frame::new_with_messages([new_test_message(topic.clone())]);
+        let batched_frame = Frame::new_with_messages([
+            new_test_message(topic.clone()),
+            new_test_message(topic.clone()),
+            new_test_message(topic.clone()),
+        ]);
+        let subscriptions_frame =
+            Frame::new_with_subscriptions([SubscriptionAction::Subscribe(topic)]);
+
+        let mut service = testlib::service::default_test_service::<UpstreamFramingService>();
+
+        //// When
+        let input_events = itertools::chain!(
+            new_raw_frame_received_seq(remote_peer, single_message_frame),
+            new_raw_frame_received_seq(remote_peer, batched_frame),
+            new_raw_frame_received_seq(remote_peer, subscriptions_frame),
+        );
+        testlib::service::inject_events(&mut service, input_events);
+        testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        let stats = service
+            .peer_stats(&remote_peer)
+            .expect("Peer framing stats should be tracked");
+        assert_eq!(
+            stats.frames, 2,
+            "Only the message frames should be accounted"
+        );
+        assert_eq!(stats.messages, 4);
+        assert_eq!(stats.avg_msgs_per_frame, 2.0);
+        assert!(service.peer_stats(&new_test_peer_id()).is_none());
+    }
+
+    #[test]
+    fn notify_inefficient_peer_framing_once() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        let mut service = new_framing_stats_test_service();
+
+        //// When
+        let input_events = (0..3).flat_map(|_| {
+            new_raw_frame_received_seq(
+                remote_peer,
+                Frame::new_with_messages([new_test_message(topic.clone())]),
+            )
+        });
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        let notifications = output_events
+            .iter()
+            .filter(|ev| matches!(ev, UpstreamOutEvent::InefficientFraming { .. }))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            notifications.len(),
+            1,
+            "Only 1 notification should be emitted"
+        );
+        assert_matches!(notifications[0], UpstreamOutEvent::InefficientFraming { src, avg_msgs_per_frame } => {
+            assert_eq!(src, &remote_peer);
+            assert_eq!(*avg_msgs_per_frame, 1.0);
+        });
+    }
+
+    #[test]
+    fn drop_peer_framing_stats_on_disconnect() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        let mut service = new_framing_stats_test_service();
+
+        testlib::service::inject_events(
+            &mut service,
+            new_raw_frame_received_seq(
+                remote_peer,
+                Frame::new_with_messages([new_test_message(topic.clone())]),
+            ),
+        );
+        testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// When
+        testlib::service::inject_events(
+            &mut service,
+            itertools::chain!(
+                [UpstreamInEvent::PeerDisconnected(remote_peer)],
+                new_raw_frame_received_seq(
+                    remote_peer,
+                    Frame::new_with_messages([new_test_message(topic.clone())]),
+                ),
+            ),
+        );
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert!(
+            output_events
+                .iter()
+                .any(|ev| matches!(ev, UpstreamOutEvent::InefficientFraming { .. })),
+            "The reconnected peer should be notified again"
+        );
+        let stats = service
+            .peer_stats(&remote_peer)
+            .expect("Peer framing stats should be tracked");
+        assert_eq!(
+            stats.frames, 1,
+            "The stats should restart after the disconnect"
+        );
+
+        //// When
+        testlib::service::inject_events(
+            &mut service,
+            [UpstreamInEvent::PeerDisconnected(remote_peer)],
+        );
+        testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert!(
+            service.peer_stats(&remote_peer).is_none(),
+            "The disconnected peer stats should be dropped"
+        );
+    }
+
+    mod limits {
+        use crate::framing::{
+            ControlMessage, GraftControlMessage, IHaveControlMessage, IWantControlMessage,
+        };
+        use crate::message_id::MessageId;
+        use crate::services::framing::{
+            FrameFailureClass, FrameLimit, FrameLimitPolicy, FrameLimits, FrameValidationError,
+        };
+
+        use super::*;
+
+        /// Create a new upstream framing service enforcing the given received frames limits.
+        fn new_limits_test_service(limits: FrameLimits) -> BufferedContext<UpstreamFramingService> {
+            BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+                max_interned_topics: 0,
+                limits,
+                ..Default::default()
+            }))
+        }
+
+        /// The received frames limits used by the tests, with the given policy.
+        fn test_limits(policy: FrameLimitPolicy) -> FrameLimits {
+            FrameLimits {
+                max_control_entries: 4,
+                max_ihave_ids_per_message: 4,
+                max_iwant_ids: 4,
+                max_subscriptions: 4,
+                policy,
+            }
+        }
+
+        /// Create `count` distinct test message ids.
+        fn new_test_message_ids(count: usize) -> Vec<MessageId> {
+            (0..count)
+                .map(|idx| MessageId::new(format!("message-id-{idx}")))
+                .collect()
+        }
+
+        /// Create `count` GRAFT control messages for distinct topics.
+        fn new_graft_messages(count: usize) -> Vec<ControlMessage> {
+            (0..count)
+                .map(|idx| {
+                    ControlMessage::Graft(GraftControlMessage {
+                        topic_hash: TopicHash::from_raw(format!("/test/topic-{idx}")),
+                    })
+                })
+                .collect()
+        }
+
+        /// Create a frame subscribing to `count` distinct topics.
+        fn new_subscriptions_frame(count: usize) -> Frame {
+            Frame::new_with_subscriptions((0..count).map(|idx| {
+                SubscriptionAction::Subscribe(TopicHash::from_raw(format!("/test/topic-{idx}")))
+            }))
+        }
+
+        /// A frame exceeding the test limits for each limit.
+        fn oversized_frames() -> Vec<(FrameLimit, Frame)> {
+            let topic = TopicHash::from_raw("/test/topic");
+            vec![
+                (
+                    FrameLimit::ControlEntries,
+                    Frame::new_with_control(new_graft_messages(6)),
+                ),
+                (
+                    FrameLimit::IHaveIds,
+                    Frame::new_with_control([ControlMessage::IHave(IHaveControlMessage {
+                        topic_hash: topic,
+                        message_ids: new_test_message_ids(10),
+                    })]),
+                ),
+                (
+                    FrameLimit::IWantIds,
+                    Frame::new_with_control([
+                        ControlMessage::IWant(IWantControlMessage {
+                            message_ids: new_test_message_ids(3),
+                        }),
+                        ControlMessage::IWant(IWantControlMessage {
+                            message_ids: new_test_message_ids(3),
+                        }),
+                    ]),
+                ),
+                (FrameLimit::Subscriptions, new_subscriptions_frame(6)),
+            ]
+        }
+
+        /// Process the raw frame received from the remote peer.
+        fn receive_frame(
+            service: &mut BufferedContext<UpstreamFramingService>,
+            src: PeerId,
+            frame: Frame,
+        ) -> Vec<UpstreamOutEvent> {
+            testlib::service::inject_events(service, new_raw_frame_received_seq(src, frame));
+            testlib::service::collect_events(service, &mut noop_context())
+        }
+
+        /// Assert the events start with a limit exceeded validation failure.
+        fn assert_limit_exceeded(events: &[UpstreamOutEvent], limit: FrameLimit) {
+            assert_matches!(events.first(), Some(UpstreamOutEvent::ValidationFailed { error, class, .. }) => {
+                assert_eq!(class, &FrameFailureClass::ProtocolError);
+                assert_eq!(
+                    error.to_string(),
+                    FrameValidationError::LimitExceeded(limit).to_string()
+                );
+            });
+        }
+
+        #[test]
+        fn control_entries_beyond_the_limit_are_truncated() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let grafts = new_graft_messages(6);
+            let frame = Frame::new_with_control(grafts.clone());
+
+            let mut service = new_limits_test_service(test_limits(FrameLimitPolicy::Truncate));
+
+            //// When
+            let output_events = receive_frame(&mut service, remote_peer, frame);
+
+            //// Then
+            assert_eq!(output_events.len(), 5, "Only 5 events should be emitted");
+            assert_limit_exceeded(&output_events, FrameLimit::ControlEntries);
+            let received = output_events[1..]
+                .iter()
+                .map(|ev| assert_matches!(ev, UpstreamOutEvent::ControlMessageReceived { message, .. } => message.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(received, grafts[..4], "The first 4 GRAFTs should be kept");
+            assert_eq!(service.frame_failures(FrameFailureClass::ProtocolError), 1);
+        }
+
+        #[test]
+        fn ihave_ids_beyond_the_limit_are_truncated() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let topic = TopicHash::from_raw("/test/topic");
+            let message_ids = new_test_message_ids(10);
+            let frame = Frame::new_with_control([ControlMessage::IHave(IHaveControlMessage {
+                topic_hash: topic.clone(),
+                message_ids: message_ids.clone(),
+            })]);
+
+            let mut service = new_limits_test_service(test_limits(FrameLimitPolicy::Truncate));
+
+            //// When
+            let output_events = receive_frame(&mut service, remote_peer, frame);
+
+            //// Then
+            assert_eq!(output_events.len(), 2, "Only 2 events should be emitted");
+            assert_limit_exceeded(&output_events, FrameLimit::IHaveIds);
+            assert_matches!(&output_events[1], UpstreamOutEvent::ControlMessageReceived { message, .. } => {
+                assert_eq!(
+                    message,
+                    &ControlMessage::IHave(IHaveControlMessage {
+                        topic_hash: topic,
+                        message_ids: message_ids[..4].to_vec(),
+                    })
+                );
+            });
+        }
+
+        #[test]
+        fn iwant_ids_beyond_the_frame_limit_are_truncated() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let message_ids = new_test_message_ids(9);
+            let frame = Frame::new_with_control(message_ids.chunks(3).map(|ids| {
+                ControlMessage::IWant(IWantControlMessage {
+                    message_ids: ids.to_vec(),
+                })
+            }));
+
+            let mut service = new_limits_test_service(test_limits(FrameLimitPolicy::Truncate));
+
+            //// When
+            let output_events = receive_frame(&mut service, remote_peer, frame);
+
+            //// Then
+            assert_eq!(output_events.len(), 3, "Only 3 events should be emitted");
+            assert_limit_exceeded(&output_events, FrameLimit::IWantIds);
+            let received = output_events[1..]
+                .iter()
+                .map(|ev| assert_matches!(ev, UpstreamOutEvent::ControlMessageReceived { message, .. } => message.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                received,
+                [
+                    ControlMessage::IWant(IWantControlMessage {
+                        message_ids: message_ids[..3].to_vec(),
+                    }),
+                    ControlMessage::IWant(IWantControlMessage {
+                        message_ids: message_ids[3..4].to_vec(),
+                    }),
+                ],
+                "The first 4 IWANT message ids should be kept, and the emptied IWANT dropped"
+            );
+        }
+
+        #[test]
+        fn subscriptions_beyond_the_limit_are_truncated() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let frame = new_subscriptions_frame(6);
+
+            let mut service = new_limits_test_service(test_limits(FrameLimitPolicy::Truncate));
+
+            //// When
+            let output_events = receive_frame(&mut service, remote_peer, frame.clone());
+
+            //// Then
+            assert_eq!(output_events.len(), 5, "Only 5 events should be emitted");
+            assert_limit_exceeded(&output_events, FrameLimit::Subscriptions);
+            let received = output_events[1..]
+                .iter()
+                .map(|ev| assert_matches!(ev, UpstreamOutEvent::SubscriptionRequestReceived { action, .. } => action.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(received, frame.subscriptions[..4]);
+        }
+
+        #[test]
+        fn frames_exceeding_a_limit_are_rejected_with_the_reject_policy() {
+            for (limit, frame) in oversized_frames() {
+                //// Given
+                let remote_peer = new_test_peer_id();
+                let mut service = new_limits_test_service(test_limits(FrameLimitPolicy::Reject));
+
+                //// When
+                let output_events = receive_frame(&mut service, remote_peer, frame);
+
+                //// Then
+                assert_eq!(
+                    output_events.len(),
+                    1,
+                    "Only the validation failure should be emitted for {limit:?}"
+                );
+                assert_limit_exceeded(&output_events, limit);
+                assert_eq!(service.frame_failures(FrameFailureClass::ProtocolError), 1);
+            }
+        }
+
+        #[test]
+        fn compliant_frames_are_untouched() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let topic = TopicHash::from_raw("/test/topic");
+            let control = [
+                ControlMessage::Graft(GraftControlMessage {
+                    topic_hash: topic.clone(),
+                }),
+                ControlMessage::IHave(IHaveControlMessage {
+                    topic_hash: topic,
+                    message_ids: new_test_message_ids(4),
+                }),
+                ControlMessage::IWant(IWantControlMessage {
+                    message_ids: new_test_message_ids(2),
+                }),
+                ControlMessage::IWant(IWantControlMessage {
+                    message_ids: new_test_message_ids(2),
+                }),
+            ];
+            let subscriptions = new_subscriptions_frame(4).subscriptions;
+
+            for policy in [FrameLimitPolicy::Truncate, FrameLimitPolicy::Reject] {
+                let mut frame = Frame::new_with_control(control.clone());
+                frame.subscriptions = subscriptions.clone();
+
+                let mut service = new_limits_test_service(test_limits(policy));
+
+                //// When
+                let output_events = receive_frame(&mut service, remote_peer, frame);
+
+                //// Then
+                let received_subscriptions = output_events
+                    .iter()
+                    .filter_map(|ev| match ev {
+                        UpstreamOutEvent::SubscriptionRequestReceived { action, .. } => {
+                            Some(action.clone())
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let received_control = output_events
+                    .iter()
+                    .filter_map(|ev| match ev {
+                        UpstreamOutEvent::ControlMessageReceived { message, .. } => {
+                            Some(message.clone())
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    output_events.len(),
+                    8,
+                    "No validation failure should be emitted"
+                );
+                assert_eq!(received_subscriptions, subscriptions);
+                assert_eq!(received_control, control);
+                assert_eq!(service.frame_failures(FrameFailureClass::ProtocolError), 0);
+            }
+        }
+    }
+
+    mod negative_cache {
+        use crate::services::framing::{FrameFailureClass, NegativeCacheParams};
+
+        use super::*;
+
+        /// Create a new upstream framing service caching the validation failures for `ttl`.
+        fn new_negative_cache_test_service(
+            ttl: Duration,
+        ) -> BufferedContext<UpstreamFramingService> {
+            BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+                max_interned_topics: 0,
+                negative_cache: NegativeCacheParams { capacity: 8, ttl },
+                ..Default::default()
+            }))
+        }
+
+        /// Process the raw frame bytes received from the remote peer `times` times.
+        fn receive_raw_frame(
+            service: &mut BufferedContext<UpstreamFramingService>,
+            src: PeerId,
+            frame: &Bytes,
+            times: usize,
+        ) -> Vec<UpstreamOutEvent> {
+            let input_events = (0..times).map(|_| UpstreamInEvent::RawFrameReceived {
+                src,
+                connection: ConnectionId::new_unchecked(0),
+                frame: frame.clone(),
+            });
+            testlib::service::inject_events(service, input_events);
+            testlib::service::collect_events(service, &mut noop_context())
+        }
+
+        /// Assert every event is a protocol error validation failure with the given error.
+        fn assert_validation_failures(events: &[UpstreamOutEvent], expected_error: &str) {
+            for event in events {
+                assert_matches!(event, UpstreamOutEvent::ValidationFailed { error, class, .. } => {
+                    assert_eq!(class, &FrameFailureClass::ProtocolError);
+                    assert_eq!(error.to_string(), expected_error);
+                });
+            }
+        }
+
+        #[test]
+        fn undecodable_frame_received_repeatedly_is_decoded_once() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let garbage = Bytes::from_static(b"\xff\xff\xff\xff");
+
+            let mut service = new_negative_cache_test_service(Duration::from_secs(60));
+
+            //// When
+            let output_events = receive_raw_frame(&mut service, remote_peer, &garbage, 5);
+
+            //// Then
+            assert_eq!(
+                output_events.len(),
+                5,
+                "Every frame should be notified as invalid"
+            );
+            let expected_error = decode_error(&garbage);
+            assert_validation_failures(&output_events, &expected_error);
+            assert_eq!(service.negative_cache_misses(), 1);
+            assert_eq!(service.negative_cache_hits(), 4);
+            assert_eq!(service.frame_failures(FrameFailureClass::ProtocolError), 5);
+        }
+
+        #[test]
+        fn invalid_message_received_repeatedly_is_validated_once() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let invalid_message = new_test_message(TopicHash::from_raw(""));
+            let frame = encode_frame(Frame::new_with_messages([invalid_message]));
+
+            let mut service = new_negative_cache_test_service(Duration::from_secs(60));
+
+            //// When
+            let output_events = receive_raw_frame(&mut service, remote_peer, &frame, 5);
+
+            //// Then
+            assert_eq!(
+                output_events.len(),
+                5,
+                "Every message should be notified as invalid"
+            );
+            assert_validation_failures(&output_events, "invalid message: empty topic");
+            // The frame is decoded every time, its message is only validated the first time.
+            assert_eq!(service.negative_cache_misses(), 5 + 1);
+            assert_eq!(service.negative_cache_hits(), 4);
+        }
+
+        #[test]
+        fn cached_validation_failures_expire() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let garbage = Bytes::from_static(b"\xff\xff\xff\xff");
+
+            let mut service = new_negative_cache_test_service(Duration::from_millis(10));
+            receive_raw_frame(&mut service, remote_peer, &garbage, 2);
+
+            //// When
+            std::thread::sleep(Duration::from_millis(20));
+            let output_events = receive_raw_frame(&mut service, remote_peer, &garbage, 1);
+
+            //// Then
+            assert_eq!(
+                output_events.len(),
+                1,
+                "The frame should be notified as invalid"
+            );
+            assert_eq!(service.negative_cache_hits(), 1);
+            assert_eq!(
+                service.negative_cache_misses(),
+                2,
+                "The expired failure should be looked up and missed"
+            );
+        }
+
+        #[test]
+        fn valid_frames_are_not_cached() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let message = new_test_message(new_test_topic());
+            let frame = encode_frame(Frame::new_with_messages([message]));
+
+            let mut service = new_negative_cache_test_service(Duration::from_secs(60));
+
+            //// When
+            let output_events = receive_raw_frame(&mut service, remote_peer, &frame, 3);
+
+            //// Then
+            assert_eq!(output_events.len(), 3, "Every message should be received");
+            assert!(output_events
+                .iter()
+                .all(|ev| matches!(ev, UpstreamOutEvent::MessageReceived(..))));
+            assert_eq!(service.negative_cache_hits(), 0);
+        }
+
+        /// The error of decoding the given raw frame bytes.
+        fn decode_error(frame: &Bytes) -> String {
+            FrameProto::decode(frame.as_ref())
+                .expect_err("The frame should fail to decode")
+                .to_string()
+        }
+    }
+
+    mod parallel_validation {
+        use super::*;
+
+        /// Create a new upstream framing service validating the frames carrying more than
+        /// `threshold` messages in parallel, if the `parallel` feature is enabled.
+        fn new_parallel_validation_test_service(
+            threshold: usize,
+        ) -> BufferedContext<UpstreamFramingService> {
+            BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+                max_key_size: 32,
+                parallel_validation_threshold: threshold,
+                ..Default::default()
+            }))
+        }
+
+        /// Create a frame of `count` messages mixing valid, invalid and expired messages.
+        fn new_mixed_frame(count: usize) -> Bytes {
+            let topic = new_test_topic();
+            let expiry = SystemTime::now() - Duration::from_secs(2);
+            let messages = (0..count).map(|i| match i % 5 {
+                1 => FrameMessage::new(TopicHash::from_raw(""), format!("empty-topic-{i}")),
+                2 => {
+                    let mut message = FrameMessage::new(topic.clone(), format!("large-key-{i}"));
+                    message.set_key(Some(vec![0x01; 33]));
+                    message
+                }
+                3 => FrameMessage::new(
+                    topic.clone(),
+                    ttl::encode(expiry, format!("expired-{i}").as_bytes()),
+                ),
+                _ => FrameMessage::new(topic.clone(), format!("valid-{i}")),
+            });
+            encode_frame(Frame::new_with_messages(messages))
+        }
+
+        /// Process the raw frame, and describe the output events, in order.
+        fn process_frame(
+            service: &mut BufferedContext<UpstreamFramingService>,
+            frame: &Bytes,
+        ) -> Vec<String> {
+            let input_events = new_raw_frame_received_seq(new_test_peer_id(), decode_frame(frame));
+            testlib::service::inject_events(service, input_events);
+            testlib::service::collect_events(service, &mut noop_context())
+                .into_iter()
+                .map(|ev| match ev {
+                    UpstreamOutEvent::MessageReceived(ctx) => format!(
+                        "received {}: {}",
+                        ctx.topic(),
+                        String::from_utf8_lossy(&ctx.message().data())
+                    ),
+                    UpstreamOutEvent::ValidationFailed { error, .. } => {
+                        format!("failed: {error}")
+                    }
+                    ev => format!("{ev:?}"),
+                })
+                .collect()
+        }
+
+        #[test]
+        fn batch_validation_outcomes_and_order_match_the_serial_validation() {
+            //// Given
+            let frame = new_mixed_frame(500);
+
+            let mut serial_service = new_parallel_validation_test_service(usize::MAX);
+            let mut batch_service = new_parallel_validation_test_service(0);
+
+            //// When
+            let serial_events = process_frame(&mut serial_service, &frame);
+            let batch_events = process_frame(&mut batch_service, &frame);
+
+            //// Then
+            assert_eq!(batch_events, serial_events);
+            assert_eq!(
+                serial_events.len(),
+                400,
+                "The expired messages should be dropped"
+            );
+            assert_eq!(serial_events[0], {
+                let topic = &decode_frame(&frame).publish[0].topic;
+                format!("received {topic}: valid-0")
+            });
+            assert_eq!(serial_events[1], "failed: invalid message: empty topic");
+            assert_eq!(serial_events[2], "failed: invalid message: key too large");
+            assert!(serial_events[3].ends_with("valid-4"));
+        }
+
+        #[test]
+        fn batch_validation_failures_are_cached() {
+            //// Given
+            let frame = new_mixed_frame(500);
+
+            let mut service = new_parallel_validation_test_service(0);
+            let first_events = process_frame(&mut service, &frame);
+
+            //// When
+            let second_events = process_frame(&mut service, &frame);
+
+            //// Then
+            assert_eq!(second_events, first_events);
+            assert_eq!(
+                service.negative_cache_hits(),
+                200,
+                "The invalid messages should not be validated again"
+            );
+        }
+    }
 }
 
 mod downstream {
@@ -201,7 +1235,10 @@ mod downstream {
     ) -> impl IntoIterator<Item = DownstreamInEvent> {
         [DownstreamInEvent::ForwardMessage {
             dest,
+            connection: None,
             message: Rc::new(message),
+            context: None,
+            priority: Priority::Normal,
         }]
     }
 
@@ -217,6 +1254,7 @@ mod downstream {
                 .into_iter()
                 .map(SubscriptionAction::Subscribe)
                 .collect(),
+            tag: None,
         }]
     }
 
@@ -237,7 +1275,7 @@ mod downstream {
 
         //// Then
         assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
-        assert_matches!(&output_events[0], DownstreamOutEvent::SendFrame { dest, frame } => {
+        assert_matches!(&output_events[0], DownstreamOutEvent::SendFrame { dest, frame, .. } => {
             // Assert the destination peer is the expected one.
             assert_eq!(dest, &remote_peer);
             // Assert the frame content
@@ -273,7 +1311,7 @@ mod downstream {
 
         //// Then
         assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
-        assert_matches!(&output_events[0], DownstreamOutEvent::SendFrame { dest, frame } => {
+        assert_matches!(&output_events[0], DownstreamOutEvent::SendFrame { dest, frame, .. } => {
             // Assert the destination peer is the expected one.
             assert_eq!(dest, &remote_peer);
             // Assert the frame content
@@ -302,4 +1340,506 @@ mod downstream {
             });
         });
     }
+
+    mod batching {
+        use futures::FutureExt;
+
+        use super::*;
+
+        /// Create a test `DownstreamFramingService` with the given max frame size and batch
+        /// window. The batch window is driven by the tokio clock, so it can be paused and
+        /// advanced by the tests.
+        fn new_test_service(
+            max_frame_size: usize,
+            batch_window: Duration,
+        ) -> BufferedContext<DownstreamFramingService> {
+            BufferedContext::new(
+                DownstreamFramingService::new(max_frame_size, Some(batch_window), false)
+                    .with_flush_timer_fn(|window| tokio::time::sleep(window).boxed()),
+            )
+        }
+
+        #[tokio::test(start_paused = true)]
+        async fn messages_are_batched_until_window_elapses() {
+            //// Given
+            let remote_peer_a = new_test_peer_id();
+            let remote_peer_b = new_test_peer_id();
+            let topic = new_test_topic();
+            let message_a = new_test_message(topic.clone());
+            let message_b = new_test_message(topic.clone());
+            let message_c = new_test_message(topic.clone());
+
+            let mut service = new_test_service(65536, Duration::from_millis(50));
+
+            //// When
+            let input_events = itertools::chain!(
+                new_forward_message_seq(remote_peer_a, message_a.clone()),
+                new_forward_message_seq(remote_peer_b, message_b.clone()),
+                new_forward_message_seq(remote_peer_a, message_c.clone()),
+            );
+            testlib::service::inject_events(&mut service, input_events);
+
+            let output_events_before = testlib::service::async_collect_events(&mut service).await;
+
+            // Let the batch window elapse
+            tokio::time::advance(Duration::from_millis(50)).await;
+
+            let output_events_after = testlib::service::async_collect_events(&mut service).await;
+
+            //// Then
+            assert!(
+                output_events_before.is_empty(),
+                "No frames should be sent before the batch window elapses"
+            );
+            assert_eq!(
+                output_events_after.len(),
+                2,
+                "One frame per peer should be sent"
+            );
+
+            let frames = output_events_after
+                .into_iter()
+                .map(|ev| match ev {
+                    DownstreamOutEvent::SendFrame { dest, frame, .. } => {
+                        (dest, decode_frame(&frame))
+                    }
+                })
+                .collect::<HashMap<_, _>>();
+            assert_matches!(frames.get(&remote_peer_a), Some(frame) => {
+                assert_eq!(frame.publish.len(), 2, "Peer A frame should contain 2 messages");
+                assert_eq!(frame.publish[0], message_a.as_proto().clone());
+                assert_eq!(frame.publish[1], message_c.as_proto().clone());
+            });
+            assert_matches!(frames.get(&remote_peer_b), Some(frame) => {
+                assert_eq!(frame.publish.len(), 1, "Peer B frame should contain 1 message");
+                assert_eq!(frame.publish[0], message_b.as_proto().clone());
+            });
+        }
+
+        #[tokio::test(start_paused = true)]
+        async fn high_priority_message_is_sent_ahead_of_the_pending_batch() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let topic = new_test_topic();
+            let normal_messages = (0..10)
+                .map(|_| new_test_message(topic.clone()))
+                .collect::<Vec<_>>();
+            let high_message = new_test_message(topic.clone());
+
+            let mut service = new_test_service(65536, Duration::from_millis(50));
+
+            //// When
+            let input_events = normal_messages
+                .iter()
+                .map(|message| (message, Priority::Normal))
+                .chain([(&high_message, Priority::High)])
+                .map(|(message, priority)| DownstreamInEvent::ForwardMessage {
+                    dest: remote_peer,
+                    connection: None,
+                    message: Rc::new(message.clone()),
+                    context: None,
+                    priority,
+                })
+                .collect::<Vec<_>>();
+            testlib::service::inject_events(&mut service, input_events);
+
+            let output_events_before = testlib::service::async_collect_events(&mut service).await;
+
+            // Let the batch window elapse
+            tokio::time::advance(Duration::from_millis(50)).await;
+
+            let output_events_after = testlib::service::async_collect_events(&mut service).await;
+
+            //// Then
+            assert_eq!(
+                output_events_before.len(),
+                1,
+                "Only the high priority message should be sent before the batch window elapses"
+            );
+            assert_matches!(&output_events_before[0], DownstreamOutEvent::SendFrame { dest, frame, priority, .. } => {
+                assert_eq!(dest, &remote_peer);
+                assert_eq!(priority, &Priority::High);
+
+                let frame = decode_frame(frame);
+                assert_eq!(frame.publish, [high_message.as_proto().clone()]);
+            });
+
+            assert_eq!(output_events_after.len(), 1, "One batch should be sent");
+            assert_matches!(&output_events_after[0], DownstreamOutEvent::SendFrame { frame, priority, .. } => {
+                assert_eq!(priority, &Priority::Normal);
+
+                let frame = decode_frame(frame);
+                assert_eq!(
+                    frame.publish,
+                    normal_messages
+                        .iter()
+                        .map(|message| message.as_proto().clone())
+                        .collect::<Vec<_>>()
+                );
+            });
+        }
+
+        #[tokio::test(start_paused = true)]
+        async fn messages_to_distinct_peer_connections_are_batched_separately() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let connection_a = ConnectionId::new_unchecked(1);
+            let connection_b = ConnectionId::new_unchecked(2);
+            let topic = new_test_topic();
+
+            let mut service = new_test_service(65536, Duration::from_millis(50));
+
+            //// When
+            let input_events = [connection_a, connection_b, connection_a].map(|connection| {
+                DownstreamInEvent::ForwardMessage {
+                    dest: remote_peer,
+                    connection: Some(connection),
+                    message: Rc::new(new_test_message(topic.clone())),
+                    context: None,
+                    priority: Priority::Normal,
+                }
+            });
+            testlib::service::inject_events(&mut service, input_events);
+            testlib::service::async_collect_events(&mut service).await;
+
+            // Let the batch window elapse
+            tokio::time::advance(Duration::from_millis(50)).await;
+
+            let output_events = testlib::service::async_collect_events(&mut service).await;
+
+            //// Then
+            let frames = output_events
+                .into_iter()
+                .map(|ev| match ev {
+                    DownstreamOutEvent::SendFrame {
+                        dest,
+                        connection,
+                        frame,
+                        ..
+                    } => {
+                        assert_eq!(dest, remote_peer);
+                        (connection, decode_frame(&frame).publish.len())
+                    }
+                })
+                .collect::<HashMap<_, _>>();
+            assert_eq!(frames.len(), 2, "One frame per connection should be sent");
+            assert_eq!(frames.get(&Some(connection_a)), Some(&2));
+            assert_eq!(frames.get(&Some(connection_b)), Some(&1));
+        }
+
+        #[tokio::test]
+        async fn batch_is_flushed_early_when_frame_size_limit_is_reached() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let topic = new_test_topic();
+            let message_a = new_test_message(topic.clone());
+            let message_b = new_test_message(topic.clone());
+            let message_c = new_test_message(topic.clone());
+
+            // A frame can hold two messages, but not three.
+            let frame_size = encode_frame(Frame::new_with_messages([message_a.clone()])).len();
+            let mut service = new_test_service(frame_size * 2 + 1, Duration::from_secs(10));
+
+            //// When
+            let input_events = itertools::chain!(
+                new_forward_message_seq(remote_peer, message_a.clone()),
+                new_forward_message_seq(remote_peer, message_b.clone()),
+                new_forward_message_seq(remote_peer, message_c.clone()),
+            );
+            testlib::service::inject_events(&mut service, input_events);
+
+            let output_events = testlib::service::async_collect_events(&mut service).await;
+
+            //// Then
+            assert_eq!(output_events.len(), 1, "Only 1 frame should be flushed");
+            assert_matches!(&output_events[0], DownstreamOutEvent::SendFrame { dest, frame, .. } => {
+                assert_eq!(dest, &remote_peer);
+                assert!(frame.len() <= frame_size * 2 + 1, "Frame should not exceed the limit");
+
+                let frame = decode_frame(frame);
+                assert_eq!(frame.publish.len(), 2, "Frame should contain 2 messages");
+                assert_eq!(frame.publish[0], message_a.as_proto().clone());
+                assert_eq!(frame.publish[1], message_b.as_proto().clone());
+            });
+        }
+
+        #[tokio::test]
+        async fn batch_is_flushed_early_when_peer_frame_limit_is_reached() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let other_peer = new_test_peer_id();
+            let topic = new_test_topic();
+            let message_a = new_test_message(topic.clone());
+            let message_b = new_test_message(topic.clone());
+
+            // A frame can hold two messages, but the remote peer is suspected to accept only one.
+            let frame_size = encode_frame(Frame::new_with_messages([message_a.clone()])).len();
+            let mut service = new_test_service(65536, Duration::from_secs(10));
+
+            //// When
+            let input_events = itertools::chain!(
+                [DownstreamInEvent::PeerFrameLimitChanged {
+                    peer: remote_peer,
+                    limit: Some(frame_size + frame_size / 2),
+                }],
+                new_forward_message_seq(remote_peer, message_a.clone()),
+                new_forward_message_seq(remote_peer, message_b.clone()),
+                new_forward_message_seq(other_peer, message_a.clone()),
+                new_forward_message_seq(other_peer, message_b.clone()),
+            );
+            testlib::service::inject_events(&mut service, input_events);
+
+            let output_events = testlib::service::async_collect_events(&mut service).await;
+
+            //// Then
+            assert_eq!(output_events.len(), 1, "Only 1 frame should be flushed");
+            assert_matches!(&output_events[0], DownstreamOutEvent::SendFrame { dest, frame, .. } => {
+                assert_eq!(dest, &remote_peer);
+
+                let frame = decode_frame(frame);
+                assert_eq!(frame.publish.len(), 1, "Frame should contain 1 message");
+                assert_eq!(frame.publish[0], message_a.as_proto().clone());
+            });
+        }
+
+        #[tokio::test]
+        async fn message_reaching_the_frame_size_limit_is_sent_without_waiting_for_the_window() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let topic = new_test_topic();
+            let message_a = new_test_message(topic.clone());
+            let message_b = FrameMessage::new(topic.clone(), vec![0; 1024]);
+
+            // A frame can hold the small message, but not the large one.
+            let mut service = new_test_service(512, Duration::from_secs(10));
+
+            //// When
+            let input_events = itertools::chain!(
+                new_forward_message_seq(remote_peer, message_a.clone()),
+                new_forward_message_seq(remote_peer, message_b.clone()),
+            );
+            testlib::service::inject_events(&mut service, input_events);
+
+            let output_events = testlib::service::async_collect_events(&mut service).await;
+
+            //// Then
+            assert_eq!(output_events.len(), 2, "2 frames should be sent");
+            assert_matches!(&output_events[0], DownstreamOutEvent::SendFrame { dest, frame, .. } => {
+                assert_eq!(dest, &remote_peer);
+
+                let frame = decode_frame(frame);
+                assert_eq!(
+                    frame.publish,
+                    [message_a.as_proto().clone()],
+                    "The pending batch should be flushed first"
+                );
+            });
+            assert_matches!(&output_events[1], DownstreamOutEvent::SendFrame { dest, frame, .. } => {
+                assert_eq!(dest, &remote_peer);
+
+                let frame = decode_frame(frame);
+                assert_eq!(
+                    frame.publish,
+                    [message_b.as_proto().clone()],
+                    "The large message should be sent in a frame of its own"
+                );
+            });
+        }
+
+        #[tokio::test]
+        async fn subscription_requests_bypass_the_batch_window() {
+            //// Given
+            let remote_peer = new_test_peer_id();
+            let topic = new_test_topic();
+            let message = new_test_message(topic.clone());
+
+            let mut service = new_test_service(65536, Duration::from_secs(10));
+
+            //// When
+            let input_events = itertools::chain!(
+                new_forward_message_seq(remote_peer, message),
+                new_send_subscription_request_seq(remote_peer, [topic.clone()]),
+            );
+            testlib::service::inject_events(&mut service, input_events);
+
+            let output_events = testlib::service::async_collect_events(&mut service).await;
+
+            //// Then
+            assert_eq!(output_events.len(), 1, "Only 1 frame should be sent");
+            assert_matches!(&output_events[0], DownstreamOutEvent::SendFrame { dest, frame, .. } => {
+                assert_eq!(dest, &remote_peer);
+
+                let frame = decode_frame(frame);
+                assert!(frame.publish.is_empty(), "No messages should be encoded");
+                assert_eq!(frame.subscriptions.len(), 1, "Subscription should be encoded");
+            });
+        }
+    }
+}
+
+mod diagnostics {
+    use super::*;
+
+    use crate::services::framing::FrameFailureClass;
+
+    /// Create a new upstream framing service, verifying the frames checksum trailers.
+    fn new_upstream_test_service() -> BufferedContext<UpstreamFramingService> {
+        BufferedContext::new(UpstreamFramingService::new(UpstreamFramingParams {
+            frame_diagnostics: true,
+            ..Default::default()
+        }))
+    }
+
+    /// Create a new downstream framing service, appending a checksum trailer to the frames.
+    fn new_downstream_test_service() -> BufferedContext<DownstreamFramingService> {
+        BufferedContext::new(DownstreamFramingService::new(65536, None, true))
+    }
+
+    /// Encode the message in a frame carrying a checksum trailer.
+    fn encode_frame_with_checksum(message: FrameMessage) -> Bytes {
+        let mut service = new_downstream_test_service();
+        testlib::service::inject_events(
+            &mut service,
+            [DownstreamInEvent::ForwardMessage {
+                dest: new_test_peer_id(),
+                connection: None,
+                message: Rc::new(message),
+                context: None,
+                priority: Priority::Normal,
+            }],
+        );
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+        assert_matches!(&output_events[..], [DownstreamOutEvent::SendFrame { frame, .. }] => {
+            frame.clone()
+        })
+    }
+
+    /// Process the raw frame received from the remote peer.
+    fn receive_frame(
+        service: &mut BufferedContext<UpstreamFramingService>,
+        src: PeerId,
+        frame: Bytes,
+    ) -> Vec<UpstreamOutEvent> {
+        testlib::service::inject_events(
+            service,
+            [UpstreamInEvent::RawFrameReceived {
+                src,
+                connection: ConnectionId::new_unchecked(0),
+                frame,
+            }],
+        );
+        testlib::service::collect_events(service, &mut noop_context())
+    }
+
+    #[test]
+    fn frame_with_checksum_roundtrip() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+        let message = new_test_message(new_test_topic());
+
+        let mut service = new_upstream_test_service();
+
+        //// When
+        let frame = encode_frame_with_checksum(message.clone());
+        let output_events = receive_frame(&mut service, remote_peer, frame.clone());
+
+        //// Then
+        assert_eq!(
+            frame.len(),
+            encode_frame(Frame::new_with_messages([message.clone()])).len() + 7,
+            "The checksum trailer should be 7 bytes long"
+        );
+        assert_eq!(
+            decode_frame(&frame).publish,
+            [message.as_proto().clone()],
+            "The checksum trailer should be ignored by the protobuf decoder"
+        );
+
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::MessageReceived(ctx) => {
+            assert_eq!(ctx.message().as_ref(), &message);
+        });
+        assert_eq!(
+            service.frame_failures(FrameFailureClass::TransportCorruption),
+            0
+        );
+        assert_eq!(service.frame_failures(FrameFailureClass::ProtocolError), 0);
+    }
+
+    #[test]
+    fn frame_without_checksum_is_accepted() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+        let message = new_test_message(new_test_topic());
+
+        let mut service = new_upstream_test_service();
+
+        //// When
+        let frame = encode_frame(Frame::new_with_messages([message.clone()]));
+        let output_events = receive_frame(&mut service, remote_peer, frame);
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::MessageReceived(ctx) => {
+            assert_eq!(ctx.message().as_ref(), &message);
+        });
+    }
+
+    #[test]
+    fn corrupted_frame_is_classified_as_transport_corruption() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+        let frame = encode_frame_with_checksum(new_test_message(new_test_topic()));
+
+        // Flip a byte of the frame payload.
+        let mut corrupted = frame.to_vec();
+        corrupted[frame.len() / 2] ^= 0xFF;
+
+        let mut service = new_upstream_test_service();
+
+        //// When
+        let output_events = receive_frame(&mut service, remote_peer, Bytes::from(corrupted));
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(
+            &output_events[0],
+            UpstreamOutEvent::ValidationFailed { src, error, class } => {
+                assert_eq!(src, &remote_peer);
+                assert_eq!(error.to_string(), "frame checksum mismatch");
+                assert_eq!(class, &FrameFailureClass::TransportCorruption);
+            }
+        );
+        assert_eq!(
+            service.frame_failures(FrameFailureClass::TransportCorruption),
+            1
+        );
+        assert_eq!(service.frame_failures(FrameFailureClass::ProtocolError), 0);
+    }
+
+    #[test]
+    fn malformed_frame_is_classified_as_protocol_error() {
+        //// Given
+        let remote_peer = new_test_peer_id();
+
+        // A truncated length-delimited `publish` field, without checksum trailer.
+        let frame = Bytes::from_static(&[0x12, 0x80, 0x80, 0x80, 0x80, 0x10]);
+
+        let mut service = new_upstream_test_service();
+
+        //// When
+        let output_events = receive_frame(&mut service, remote_peer, frame);
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+        assert_matches!(&output_events[0], UpstreamOutEvent::ValidationFailed { class, .. } => {
+            assert_eq!(class, &FrameFailureClass::ProtocolError);
+        });
+        assert_eq!(
+            service.frame_failures(FrameFailureClass::TransportCorruption),
+            0
+        );
+        assert_eq!(service.frame_failures(FrameFailureClass::ProtocolError), 1);
+    }
 }
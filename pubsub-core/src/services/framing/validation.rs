@@ -2,11 +2,18 @@
 
 use libp2p_pubsub_proto::pubsub::{ControlMessageProto, FrameProto};
 
+use super::convert::{MessageValidationError, SubOptsValidationError};
+
 /// Errors that can occur when validating a [`FrameProto`].
 ///
 /// See [`validate_frame_proto`] for more details.
-#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
 pub enum FrameValidationError {
+    /// Invalid protobuf encoding.
+    #[error("invalid protobuf encoding")]
+    InvalidEncoding,
+
     /// Empty frame.
     #[error("empty frame")]
     EmptyFrame,
@@ -14,6 +21,106 @@ pub enum FrameValidationError {
     /// Empty control message.
     #[error("empty control message")]
     EmptyControl,
+
+    /// Invalid message.
+    #[error("invalid message: {0}")]
+    InvalidMessage(#[from] MessageValidationError),
+
+    /// Invalid subscription action.
+    #[error("invalid subscription action: {0}")]
+    InvalidSubscription(#[from] SubOptsValidationError),
+
+    /// Invalid control message.
+    #[error("invalid control message")]
+    InvalidControl,
+
+    /// The frame checksum does not match the frame bytes (see
+    /// [`Config::frame_diagnostics`](crate::Config::frame_diagnostics)).
+    #[error("frame checksum mismatch")]
+    ChecksumMismatch,
+
+    /// The frame exceeds one of the received frames limits. Depending on the
+    /// [limit policy](crate::Config::frame_limit_policy), the entries beyond the limit were
+    /// truncated, or the frame was rejected.
+    #[error("frame limit exceeded: {0}")]
+    LimitExceeded(FrameLimit),
+}
+
+/// A received frames limit (see
+/// [`Config::max_control_entries_per_frame`](crate::Config::max_control_entries_per_frame)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+#[non_exhaustive]
+pub enum FrameLimit {
+    /// The number of control messages per frame (see
+    /// [`Config::max_control_entries_per_frame`](crate::Config::max_control_entries_per_frame)).
+    #[error("too many control messages")]
+    ControlEntries,
+
+    /// The number of message ids per IHAVE control message (see
+    /// [`Config::max_ihave_ids_per_message`](crate::Config::max_ihave_ids_per_message)).
+    #[error("too many IHAVE message ids")]
+    IHaveIds,
+
+    /// The number of IWANT message ids per frame (see
+    /// [`Config::max_iwant_ids_per_frame`](crate::Config::max_iwant_ids_per_frame)).
+    #[error("too many IWANT message ids")]
+    IWantIds,
+
+    /// The number of subscription actions per frame (see
+    /// [`Config::max_subscriptions_per_frame`](crate::Config::max_subscriptions_per_frame)).
+    #[error("too many subscription actions")]
+    Subscriptions,
+}
+
+/// How the received frames exceeding a limit are handled (see
+/// [`Config::frame_limit_policy`](crate::Config::frame_limit_policy)).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FrameLimitPolicy {
+    /// The entries beyond the limit are dropped, and the first entries, up to the limit, are
+    /// processed.
+    #[default]
+    Truncate,
+    /// The whole frame is dropped.
+    Reject,
+}
+
+/// The received frames limits, enforced before the frame is processed.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameLimits {
+    /// The maximum number of control messages (GRAFT, PRUNE, IHAVE and IWANT) per frame.
+    pub max_control_entries: usize,
+    /// The maximum number of message ids per IHAVE control message.
+    pub max_ihave_ids_per_message: usize,
+    /// The maximum number of message ids, over all the IWANT control messages, per frame.
+    pub max_iwant_ids: usize,
+    /// The maximum number of subscription actions per frame.
+    pub max_subscriptions: usize,
+    /// How the frames exceeding a limit are handled.
+    pub policy: FrameLimitPolicy,
+}
+
+impl Default for FrameLimits {
+    fn default() -> Self {
+        Self {
+            max_control_entries: 1024,
+            max_ihave_ids_per_message: 5000,
+            max_iwant_ids: 5000,
+            max_subscriptions: 5000,
+            policy: FrameLimitPolicy::Truncate,
+        }
+    }
+}
+
+/// The class of a received frame validation failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FrameFailureClass {
+    /// The frame was corrupted in transit, i.e., its checksum does not match its bytes (see
+    /// [`Config::frame_diagnostics`](crate::Config::frame_diagnostics)).
+    TransportCorruption,
+    /// The frame, or one of its parts, is malformed or invalid, as sent by the peer.
+    ProtocolError,
 }
 
 /// Validates a [`FrameProto`].
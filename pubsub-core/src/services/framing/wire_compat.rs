@@ -0,0 +1,472 @@
+//! Wire compatibility golden test vectors.
+//!
+//! Each vector is a hex-encoded `Frame` protobuf payload, laid out as the go-libp2p and
+//! rust-libp2p gossipsub and floodsub implementations write it on the wire, along with the
+//! [`Frame`] it decodes to, or the [`FrameValidationError`] its decoding fails with. The cases the conversion layer
+//! sanitizes (e.g., the empty optional message fields, interpreted as not present) are covered,
+//! so a conversion change breaking the compatibility with other implementations fails here,
+//! without spinning up swarms.
+//!
+//! The reverse direction is covered by the canonical [`Frame`] values and the exact bytes the
+//! encoder must produce for them.
+//!
+//! # Field ordering
+//!
+//! The protobuf encoding does not mandate a field order, and the decoders must accept the fields
+//! in any order. The go-libp2p (gogo-protobuf) and rust-libp2p (quick-protobuf) encoders, as does
+//! ours (prost), write the fields in field number order. The vectors are written in this
+//! canonical order. The payloads whose fields are in another order are compared after
+//! normalization, i.e., decoded into a `FrameProto` and re-encoded (see [`normalize`]).
+//!
+//! # Decoded control messages order
+//!
+//! The decoded control messages are grouped by kind, in the `GRAFT`, `PRUNE`, `IHAVE` and `IWANT`
+//! order, while the encoder writes them in the protobuf field order: `IHAVE`, `IWANT`, `GRAFT`
+//! and `PRUNE`.
+
+use bytes::Bytes;
+use libp2p::identity::PeerId;
+use prost::Message as _;
+
+use libp2p_pubsub_proto::pubsub::FrameProto;
+
+use crate::framing::{
+    ControlMessage, Frame, GraftControlMessage, IHaveControlMessage, IWantControlMessage, Message,
+    PruneControlMessage, SubscriptionAction,
+};
+use crate::message_id::{MessageId, MAX_WIRE_MESSAGE_ID_LEN};
+use crate::topic::TopicHash;
+
+use super::convert::{MessageValidationError, SubOptsValidationError};
+use super::validation::FrameValidationError;
+
+/// A subscriptions-only frame: subscribe to `topic-a`, unsubscribe from `topic-b`.
+const SUBSCRIPTIONS_ONLY: &str = "0a0b08011207746f7069632d610a0b08001207746f7069632d62";
+
+/// A frame with a message carrying all the fields: author, data, sequence number, topic,
+/// signature and key.
+const MESSAGE_ALL_FIELDS: &str = concat!(
+    "124e",
+    "0a26", // from
+    "0024080112200101010101010101010101010101010101010101010101010101010101010101",
+    "120568656c6c6f",       // data
+    "1a080000000000000001", // seqno
+    "2207746f7069632d61",   // topic
+    "2a04deadbeef",         // signature
+    "3204cafebabe",         // key
+);
+
+/// A frame with a message carrying all its optional fields empty, as sent by the implementations
+/// not omitting the empty fields.
+const MESSAGE_EMPTY_OPTIONAL_FIELDS: &str = "12130a0012001a002207746f7069632d612a003200";
+
+/// A frame with a message carrying no data field, as sent by the implementations omitting the
+/// empty fields.
+const MESSAGE_MISSING_DATA: &str = "120c1a012a2207746f7069632d61";
+
+/// A frame with one control message of each kind: `IHAVE` and `IWANT` with 20 bytes ids, `GRAFT`
+/// and `PRUNE` with a 60 seconds backoff.
+const CONTROL: &str = concat!(
+    "1a67",
+    "0a35", // ihave
+    "0a07746f7069632d61",
+    "12140101010101010101010101010101010101010101",
+    "12140202020202020202020202020202020202020202",
+    "1216", // iwant
+    "0a140303030303030303030303030303030303030303",
+    "1a09", // graft
+    "0a07746f7069632d62",
+    "220b", // prune
+    "0a07746f7069632d63183c",
+);
+
+/// A frame mixing a subscription, a message and a control message.
+const MIXED: &str = concat!(
+    "0a0b08011207746f7069632d61",
+    "1213120568656c6c6f1a012a2207746f7069632d61",
+    "1a0b1a090a07746f7069632d62",
+);
+
+/// The [`MIXED`] frame with its fields, and its parts fields, in reverse field number order.
+const MIXED_REORDERED: &str = concat!(
+    "1a0b1a090a07746f7069632d62",
+    "12132207746f7069632d611a012a120568656c6c6f",
+    "0a0b1207746f7069632d610801",
+);
+
+/// A subscriptions frame followed by an unknown field, e.g., the frame checksum trailer.
+const UNKNOWN_FIELD: &str = "0a0b08011207746f7069632d61fa7f0400000000";
+
+/// Decode a hex string into bytes.
+fn from_hex(hex: &str) -> Bytes {
+    assert_eq!(hex.len() % 2, 0, "odd length hex string");
+    (0..hex.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).expect("valid hex digits"))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Encode bytes into a lowercase hex string.
+fn to_hex(bytes: &[u8]) -> String {
+    hex_fmt::HexFmt(bytes).to_string().to_lowercase()
+}
+
+/// Normalize a frame payload fields order, by decoding and re-encoding it.
+fn normalize(bytes: &[u8]) -> Bytes {
+    FrameProto::decode(bytes)
+        .expect("valid frame protobuf")
+        .encode_to_vec()
+        .into()
+}
+
+/// Decode a hex-encoded frame payload.
+fn decode(hex: &str) -> Result<Frame, FrameValidationError> {
+    Frame::from_protobuf_bytes(from_hex(hex))
+}
+
+/// The peer id carried by the test vectors: an identity multihash of an Ed25519 public key.
+fn test_peer_id() -> PeerId {
+    PeerId::from_bytes(&from_hex(
+        "0024080112200101010101010101010101010101010101010101010101010101010101010101",
+    ))
+    .expect("valid peer id")
+}
+
+fn topic(name: &str) -> TopicHash {
+    TopicHash::from_raw(name)
+}
+
+fn message_id(byte: u8, len: usize) -> MessageId {
+    MessageId::new(vec![byte; len])
+}
+
+fn subscriptions_only_frame() -> Frame {
+    Frame::new_with_subscriptions([
+        SubscriptionAction::Subscribe(topic("topic-a")),
+        SubscriptionAction::Unsubscribe(topic("topic-b")),
+    ])
+}
+
+fn message_all_fields_frame() -> Frame {
+    let mut message = Message::new_with_seq_no_and_from(
+        topic("topic-a"),
+        b"hello".to_vec(),
+        1u64.to_be_bytes(),
+        test_peer_id(),
+    );
+    message.set_signature(Some(vec![0xde, 0xad, 0xbe, 0xef]));
+    message.set_key(Some(vec![0xca, 0xfe, 0xba, 0xbe]));
+
+    Frame::new_with_messages([message])
+}
+
+fn ihave(topic_name: &str, message_ids: Vec<MessageId>) -> ControlMessage {
+    ControlMessage::IHave(IHaveControlMessage {
+        topic_hash: topic(topic_name),
+        message_ids,
+    })
+}
+
+fn iwant(message_ids: Vec<MessageId>) -> ControlMessage {
+    ControlMessage::IWant(IWantControlMessage { message_ids })
+}
+
+fn graft(topic_name: &str) -> ControlMessage {
+    ControlMessage::Graft(GraftControlMessage {
+        topic_hash: topic(topic_name),
+    })
+}
+
+fn prune(topic_name: &str, backoff: Option<u64>) -> ControlMessage {
+    ControlMessage::Prune(PruneControlMessage {
+        topic_hash: topic(topic_name),
+        peers: Vec::new(),
+        backoff,
+    })
+}
+
+fn mixed_frame() -> Frame {
+    let mut frame =
+        Frame::new_with_subscriptions([SubscriptionAction::Subscribe(topic("topic-a"))]);
+    frame.messages = vec![Message::new_with_sequence_number(
+        topic("topic-a"),
+        b"hello".to_vec(),
+        [0x2a],
+    )];
+    frame.control = vec![graft("topic-b")];
+    frame
+}
+
+mod decode {
+    use super::*;
+
+    #[test]
+    fn subscriptions_only_frame_vector() {
+        assert_eq!(decode(SUBSCRIPTIONS_ONLY), Ok(subscriptions_only_frame()));
+    }
+
+    #[test]
+    fn message_with_all_fields_vector() {
+        //// When
+        let frame = decode(MESSAGE_ALL_FIELDS).expect("valid frame");
+
+        //// Then
+        assert_eq!(frame, message_all_fields_frame());
+
+        let message = &frame.messages()[0];
+        assert_eq!(message.author(), Some(test_peer_id()));
+        assert_eq!(
+            message.seqno(),
+            Some(Bytes::from(1u64.to_be_bytes().to_vec()))
+        );
+        assert_eq!(
+            message.signature(),
+            Some(Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef]))
+        );
+        assert_eq!(
+            message.key(),
+            Some(Bytes::from_static(&[0xca, 0xfe, 0xba, 0xbe]))
+        );
+    }
+
+    #[test]
+    fn message_with_empty_optional_fields_vector() {
+        //// When
+        let frame = decode(MESSAGE_EMPTY_OPTIONAL_FIELDS).expect("valid frame");
+
+        //// Then
+        // The empty optional fields are interpreted as not present, and the empty data as an
+        // empty payload.
+        assert_eq!(
+            frame,
+            Frame::new_with_messages([Message::new(topic("topic-a"), Vec::new())])
+        );
+
+        let message = &frame.messages()[0];
+        assert_eq!(message.author(), None);
+        assert_eq!(message.seqno(), None);
+        assert_eq!(message.signature(), None);
+        assert_eq!(message.key(), None);
+        assert_eq!(message.data(), Bytes::new());
+    }
+
+    #[test]
+    fn message_with_missing_data_vector() {
+        //// When
+        let frame = decode(MESSAGE_MISSING_DATA).expect("valid frame");
+
+        //// Then
+        // A missing data field is interpreted as an empty payload.
+        assert_eq!(
+            frame,
+            Frame::new_with_messages([Message::new_with_sequence_number(
+                topic("topic-a"),
+                Vec::new(),
+                [0x2a],
+            )])
+        );
+    }
+
+    #[test]
+    fn control_messages_vector() {
+        assert_eq!(
+            decode(CONTROL),
+            Ok(Frame::new_with_control([
+                graft("topic-b"),
+                prune("topic-c", Some(60)),
+                ihave("topic-a", vec![message_id(0x01, 20), message_id(0x02, 20)]),
+                iwant(vec![message_id(0x03, 20)]),
+            ]))
+        );
+    }
+
+    #[test]
+    fn ihave_with_oversized_message_id_vector() {
+        //// Given
+        // An IHAVE with an id one byte above the maximum wire id length, and one at the maximum.
+        let vector = [
+            "1a93020a90020a07746f7069632d61",
+            "128101",
+            &"04".repeat(MAX_WIRE_MESSAGE_ID_LEN + 1),
+            "128001",
+            &"05".repeat(MAX_WIRE_MESSAGE_ID_LEN),
+        ]
+        .concat();
+
+        //// When
+        let frame = decode(&vector);
+
+        //// Then
+        // The oversized id is dropped.
+        assert_eq!(
+            frame,
+            Ok(Frame::new_with_control([ihave(
+                "topic-a",
+                vec![message_id(0x05, MAX_WIRE_MESSAGE_ID_LEN)],
+            )]))
+        );
+    }
+
+    #[test]
+    fn ihave_with_only_oversized_message_ids_vector() {
+        //// Given
+        let vector = [
+            "1a90010a8d010a07746f7069632d61",
+            "128101",
+            &"04".repeat(MAX_WIRE_MESSAGE_ID_LEN + 1),
+        ]
+        .concat();
+
+        //// Then
+        assert_eq!(decode(&vector), Err(FrameValidationError::InvalidControl));
+    }
+
+    #[test]
+    fn mixed_frame_vector() {
+        assert_eq!(decode(MIXED), Ok(mixed_frame()));
+    }
+
+    #[test]
+    fn reordered_fields_vector() {
+        //// Then
+        assert_eq!(decode(MIXED_REORDERED), Ok(mixed_frame()));
+        assert_eq!(
+            to_hex(&normalize(&from_hex(MIXED_REORDERED))),
+            MIXED,
+            "The normalized payload should match the canonical field order"
+        );
+    }
+
+    #[test]
+    fn unknown_field_vector() {
+        assert_eq!(
+            decode(UNKNOWN_FIELD),
+            Ok(Frame::new_with_subscriptions([
+                SubscriptionAction::Subscribe(topic("topic-a"))
+            ]))
+        );
+    }
+
+    #[test]
+    fn invalid_frame_vectors() {
+        let vectors = [
+            // Empty frame.
+            ("", FrameValidationError::EmptyFrame),
+            // Truncated frame.
+            (
+                "0a0b08011207746f7069632d610a0b08001207746f7069",
+                FrameValidationError::InvalidEncoding,
+            ),
+            // Control message without any IHAVE, IWANT, GRAFT or PRUNE.
+            ("1a00", FrameValidationError::EmptyControl),
+            // GRAFT without topic.
+            ("1a021a00", FrameValidationError::InvalidControl),
+            // Message with an empty topic.
+            (
+                "1207120568656c6c6f",
+                FrameValidationError::InvalidMessage(MessageValidationError::EmptyTopic),
+            ),
+            // Message with an author that is not a valid peer id.
+            (
+                "12150a03010203120568656c6c6f2207746f7069632d61",
+                FrameValidationError::InvalidMessage(MessageValidationError::InvalidPeerId),
+            ),
+            // Subscription action without the subscribe field.
+            (
+                "0a091207746f7069632d61",
+                FrameValidationError::InvalidSubscription(SubOptsValidationError::MissingAction),
+            ),
+            // Subscription action without topic.
+            (
+                "0a020801",
+                FrameValidationError::InvalidSubscription(SubOptsValidationError::MissingTopic),
+            ),
+            // Subscription action with an empty topic.
+            (
+                "0a0408011200",
+                FrameValidationError::InvalidSubscription(SubOptsValidationError::EmptyTopic),
+            ),
+        ];
+
+        for (vector, error) in vectors {
+            assert_eq!(decode(vector), Err(error), "vector: {vector:?}");
+        }
+    }
+}
+
+mod encode {
+    use super::*;
+
+    #[test]
+    fn subscriptions_only_frame_vector() {
+        assert_eq!(
+            to_hex(&subscriptions_only_frame().to_protobuf_bytes()),
+            SUBSCRIPTIONS_ONLY
+        );
+    }
+
+    #[test]
+    fn message_with_all_fields_vector() {
+        assert_eq!(
+            to_hex(&message_all_fields_frame().to_protobuf_bytes()),
+            MESSAGE_ALL_FIELDS
+        );
+    }
+
+    #[test]
+    fn message_without_optional_fields_vector() {
+        //// Given
+        let frame = Frame::new_with_messages([Message::new(topic("topic-a"), Vec::new())]);
+
+        //// Then
+        // The absent optional fields are omitted, but the data field is always written.
+        assert_eq!(
+            to_hex(&frame.to_protobuf_bytes()),
+            "120b12002207746f7069632d61"
+        );
+    }
+
+    #[test]
+    fn control_messages_vector() {
+        //// Given
+        let frame = Frame::new_with_control([
+            ihave("topic-a", vec![message_id(0x01, 20), message_id(0x02, 20)]),
+            iwant(vec![message_id(0x03, 20)]),
+            graft("topic-b"),
+            prune("topic-c", Some(60)),
+        ]);
+
+        //// Then
+        assert_eq!(to_hex(&frame.to_protobuf_bytes()), CONTROL);
+    }
+
+    #[test]
+    fn control_messages_are_written_in_field_order() {
+        //// Given
+        let frame = Frame::new_with_control([
+            prune("topic-c", Some(60)),
+            graft("topic-b"),
+            iwant(vec![message_id(0x03, 20)]),
+            ihave("topic-a", vec![message_id(0x01, 20), message_id(0x02, 20)]),
+        ]);
+
+        //// Then
+        assert_eq!(to_hex(&frame.to_protobuf_bytes()), CONTROL);
+    }
+
+    #[test]
+    fn mixed_frame_vector() {
+        assert_eq!(to_hex(&mixed_frame().to_protobuf_bytes()), MIXED);
+    }
+
+    #[test]
+    fn canonical_vectors_round_trip() {
+        let vectors = [SUBSCRIPTIONS_ONLY, MESSAGE_ALL_FIELDS, MIXED];
+
+        for vector in vectors {
+            let frame = decode(vector).expect("valid frame");
+            assert_eq!(to_hex(&frame.to_protobuf_bytes()), vector);
+        }
+    }
+}
@@ -1,7 +1,12 @@
-pub use events::{MessageEvent as MessageCacheMessageEvent, ServiceIn as MessageCacheInEvent};
+pub use events::{
+    MessageEvent as MessageCacheMessageEvent, ServiceIn as MessageCacheInEvent,
+    ServiceOut as MessageCacheOutEvent,
+};
+pub use persistence::DedupPersistenceConfig;
 pub use service::MessageCacheService;
 
 mod events;
+mod persistence;
 mod service;
 #[cfg(test)]
 mod tests;
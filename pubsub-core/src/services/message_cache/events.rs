@@ -1,15 +1,28 @@
+use std::io;
 use std::rc::Rc;
 
 use libp2p::identity::PeerId;
 
+use crate::dispatch::Consumes;
 use crate::framing::Message;
+use crate::lifecycle::ReceivedMessageCtx;
 use crate::message_id::MessageId;
+use crate::services::connections::ConnectionsOutEvent;
+use crate::services::subscriptions::SubscriptionsOutEvent;
+use crate::topic::TopicHash;
 
 /// Message cache service input event.
 #[derive(Clone)]
 pub enum ServiceIn {
     /// A message event occurred.
     MessageEvent(MessageEvent),
+    /// The local node subscribed to a topic whose recent messages are retained, to be backfilled
+    /// to the peers subscribing to it.
+    BackfillTopicSubscribed(TopicHash),
+    /// The local node unsubscribed from a topic.
+    TopicUnsubscribed(TopicHash),
+    /// A remote peer disconnected.
+    PeerDisconnected(PeerId),
 }
 
 #[derive(Clone)]
@@ -22,12 +35,74 @@ pub enum MessageEvent {
         message_id: MessageId,
     },
     /// A message was received from a remote peer.
-    MessageReceived {
+    MessageReceived(ReceivedMessageCtx),
+    /// An already seen message was received again from a remote peer.
+    DuplicateMessageReceived {
         /// The propagation node peer id.
         src: PeerId,
-        /// The message.
-        message: Rc<Message>,
+        /// The message topic.
+        topic: TopicHash,
         /// The message id.
         message_id: MessageId,
     },
 }
+
+/// Message cache service output event.
+#[derive(Debug, Clone)]
+pub enum ServiceOut {
+    /// A peer re-sent the same message more times than the maximum number of duplicate resends
+    /// allowed.
+    ///
+    /// This event is emitted only once per peer and message, when the threshold is exceeded.
+    DuplicateFloodDetected {
+        /// The misbehaving peer.
+        peer: PeerId,
+        /// The flooded message id.
+        message_id: MessageId,
+    },
+    /// A message published by the local node was received back from a remote peer.
+    ///
+    /// This event is emitted only once per message, on the first echo.
+    PublishedMessageEchoed {
+        /// The published message id.
+        message_id: MessageId,
+        /// The peer that echoed the message.
+        echoed_by: PeerId,
+    },
+    /// The dedup store could not be read, or written. The cache is memory-only from now on.
+    DedupPersistenceFailed {
+        /// The dedup store error.
+        error: Rc<io::Error>,
+    },
+}
+
+impl Consumes<ConnectionsOutEvent> for ServiceIn {
+    fn consume(ev: &ConnectionsOutEvent) -> Option<Self> {
+        match ev {
+            ConnectionsOutEvent::NewPeerConnected { .. } => None,
+            ConnectionsOutEvent::PeerDisconnected(peer) => Some(ServiceIn::PeerDisconnected(*peer)),
+        }
+    }
+}
+
+impl Consumes<SubscriptionsOutEvent> for ServiceIn {
+    fn consume(ev: &SubscriptionsOutEvent) -> Option<Self> {
+        match ev {
+            SubscriptionsOutEvent::Subscribed(sub) if sub.backfill_on_peer_subscribe => {
+                Some(ServiceIn::BackfillTopicSubscribed(sub.topic.clone()))
+            }
+            SubscriptionsOutEvent::Unsubscribed { topic, .. } => {
+                Some(ServiceIn::TopicUnsubscribed(topic.clone()))
+            }
+            SubscriptionsOutEvent::Subscribed(_)
+            | SubscriptionsOutEvent::PeerSubscribed { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribed { .. }
+            | SubscriptionsOutEvent::PeerSubscribedMany { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribedMany { .. }
+            | SubscriptionsOutEvent::KnownPeerSubscribed { .. }
+            | SubscriptionsOutEvent::KnownPeerForgotten(_)
+            | SubscriptionsOutEvent::PeerFlapping { .. }
+            | SubscriptionsOutEvent::SendSubscriptions { .. } => None,
+        }
+    }
+}
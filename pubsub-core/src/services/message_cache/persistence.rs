@@ -0,0 +1,262 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::message_id::MessageId;
+use crate::topic::TopicHash;
+
+/// The persistent message dedup store configuration (see
+/// [`Config::dedup_persistence`](crate::Config::dedup_persistence)).
+#[derive(Debug, Clone)]
+pub struct DedupPersistenceConfig {
+    /// The dedup store file path. The file is created if it does not exist.
+    pub path: PathBuf,
+    /// The interval at which the newly seen messages are appended to the file.
+    pub flush_interval: Duration,
+}
+
+impl DedupPersistenceConfig {
+    /// Create a new dedup store configuration with the given file path, flushing the newly seen
+    /// messages every 5 seconds.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            flush_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A seen message record, as persisted in the dedup store file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DedupRecord {
+    /// The message topic.
+    pub(crate) topic: TopicHash,
+    /// The message id.
+    pub(crate) message_id: MessageId,
+    /// The time the message is no longer considered seen.
+    pub(crate) expires_at: SystemTime,
+}
+
+impl DedupRecord {
+    /// Append the length-prefixed record to the buffer. The records of the topics longer than
+    /// `u16::MAX` bytes are skipped.
+    ///
+    /// A record is a little-endian `u32` payload length, followed by the payload: the expiry time
+    /// in milliseconds since the UNIX epoch as a little-endian `u64`, the topic length as a
+    /// little-endian `u16`, the topic and the message id bytes.
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let topic = self.topic.as_str().as_bytes();
+        if topic.len() > u16::MAX as usize {
+            return;
+        }
+
+        let expires_at = self
+            .expires_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let len = 8 + 2 + topic.len() + self.message_id.as_ref().len();
+        buf.extend_from_slice(&(len as u32).to_le_bytes());
+        buf.extend_from_slice(&expires_at.to_le_bytes());
+        buf.extend_from_slice(&(topic.len() as u16).to_le_bytes());
+        buf.extend_from_slice(topic);
+        buf.extend_from_slice(self.message_id.as_ref());
+    }
+
+    /// Decode all the records of the buffer.
+    ///
+    /// Returns an error if a record is truncated or malformed.
+    fn decode_all(mut buf: &[u8]) -> io::Result<Vec<Self>> {
+        let corrupt = |reason| io::Error::new(io::ErrorKind::InvalidData, reason);
+
+        let mut records = Vec::new();
+        while !buf.is_empty() {
+            let (len, rest) = split_le::<4>(buf).ok_or_else(|| corrupt("truncated record"))?;
+            let len = u32::from_le_bytes(len) as usize;
+            if rest.len() < len || len < 8 + 2 {
+                return Err(corrupt("truncated record"));
+            }
+            let (payload, rest) = rest.split_at(len);
+            buf = rest;
+
+            let (expires_at, payload) = split_le::<8>(payload).expect("payload length checked");
+            let (topic_len, payload) = split_le::<2>(payload).expect("payload length checked");
+            let topic_len = u16::from_le_bytes(topic_len) as usize;
+            if payload.len() < topic_len {
+                return Err(corrupt("invalid topic length"));
+            }
+            let (topic, message_id) = payload.split_at(topic_len);
+            let topic = std::str::from_utf8(topic).map_err(|_| corrupt("invalid topic"))?;
+
+            records.push(Self {
+                topic: TopicHash::from_raw(topic),
+                message_id: MessageId::new(message_id),
+                expires_at: UNIX_EPOCH + Duration::from_millis(u64::from_le_bytes(expires_at)),
+            });
+        }
+
+        Ok(records)
+    }
+}
+
+/// Split the first `N` bytes off the buffer.
+fn split_le<const N: usize>(buf: &[u8]) -> Option<([u8; N], &[u8])> {
+    if buf.len() < N {
+        return None;
+    }
+    let (head, rest) = buf.split_at(N);
+    Some((head.try_into().ok()?, rest))
+}
+
+/// An append-only file of the seen messages ids, so the message cache survives the node restarts.
+///
+/// The newly seen messages are buffered, and appended to the file every flush interval, on the
+/// message cache heartbeat, with blocking IO. The pending records are also flushed when the store
+/// is dropped. On open, the expired records are dropped and the file is rewritten with the live
+/// records only.
+#[derive(Debug)]
+pub(crate) struct DedupStore {
+    /// The dedup store file path.
+    path: PathBuf,
+    /// The interval at which the pending records are appended to the file.
+    flush_interval: Duration,
+    /// The time of the last flush.
+    last_flush: Instant,
+    /// The records pending to be appended to the file.
+    pending: Vec<DedupRecord>,
+}
+
+impl DedupStore {
+    /// Open the dedup store, and return the live records.
+    ///
+    /// The expired records are compacted, i.e., the file is rewritten with the live records only.
+    /// Returns an error if the file cannot be read, is corrupt, or cannot be rewritten.
+    pub(crate) fn open(config: DedupPersistenceConfig) -> io::Result<(Self, Vec<DedupRecord>)> {
+        let records = match fs::read(&config.path) {
+            Ok(buf) => DedupRecord::decode_all(&buf)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+
+        let now = SystemTime::now();
+        let records = records
+            .into_iter()
+            .filter(|record| record.expires_at > now)
+            .collect::<Vec<_>>();
+
+        // Compact the file, writing the live records to a temporary file replacing the store file.
+        let mut buf = Vec::new();
+        for record in &records {
+            record.encode(&mut buf);
+        }
+        let compacted = config.path.with_extension("compacting");
+        fs::write(&compacted, &buf)?;
+        fs::rename(&compacted, &config.path)?;
+
+        let store = Self {
+            path: config.path,
+            flush_interval: config.flush_interval,
+            last_flush: Instant::now(),
+            pending: Vec::new(),
+        };
+        Ok((store, records))
+    }
+
+    /// Record a newly seen message, to be appended to the file on the next flush.
+    pub(crate) fn record(&mut self, record: DedupRecord) {
+        self.pending.push(record);
+    }
+
+    /// Append the pending records to the file, if the flush interval elapsed.
+    pub(crate) fn flush_if_due(&mut self, now: Instant) -> io::Result<()> {
+        if now.saturating_duration_since(self.last_flush) < self.flush_interval {
+            return Ok(());
+        }
+
+        self.last_flush = now;
+        self.flush()
+    }
+
+    /// Append the pending records to the file.
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut buf = Vec::new();
+        for record in self.pending.drain(..) {
+            record.encode(&mut buf);
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&buf)?;
+        file.flush()
+    }
+}
+
+impl Drop for DedupStore {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            tracing::warn!(path = %self.path.display(), "Dedup store flush failed: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_roundtrip() {
+        //// Given
+        let records = vec![
+            DedupRecord {
+                topic: TopicHash::from_raw("/test/topic-a"),
+                message_id: MessageId::new(b"message-a".to_vec()),
+                expires_at: UNIX_EPOCH + Duration::from_millis(1_700_000_000_000),
+            },
+            DedupRecord {
+                topic: TopicHash::from_raw(""),
+                message_id: MessageId::new(b"message-b".to_vec()),
+                expires_at: UNIX_EPOCH + Duration::from_millis(1_700_000_000_001),
+            },
+        ];
+
+        //// When
+        let mut buf = Vec::new();
+        for record in &records {
+            record.encode(&mut buf);
+        }
+        let decoded = DedupRecord::decode_all(&buf).expect("valid records");
+
+        //// Then
+        assert_eq!(decoded, records);
+    }
+
+    #[test]
+    fn truncated_record_is_rejected() {
+        //// Given
+        let mut buf = Vec::new();
+        DedupRecord {
+            topic: TopicHash::from_raw("/test/topic"),
+            message_id: MessageId::new(b"message".to_vec()),
+            expires_at: SystemTime::now(),
+        }
+        .encode(&mut buf);
+
+        //// When
+        let result = DedupRecord::decode_all(&buf[..buf.len() - 1]);
+
+        //// Then
+        assert_eq!(
+            result.expect_err("truncated record").kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
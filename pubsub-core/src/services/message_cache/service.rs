@@ -1,16 +1,55 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::rc::Rc;
 use std::task::{Context, Poll};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 use futures::StreamExt;
+use libp2p::identity::PeerId;
 
 use libp2p_pubsub_common::heartbeat::Heartbeat;
-use libp2p_pubsub_common::service::{InCtx, PollCtx, Service};
+use libp2p_pubsub_common::service::{InCtx, OutCtx, PollCtx, Service};
 use libp2p_pubsub_common::ttl_cache::Cache;
 
-use crate::message_id::MessageId;
+use crate::framing::Message as FrameMessage;
+use crate::message_id::{DedupScope, MessageId};
 use crate::services::message_cache::events::MessageEvent;
+use crate::topic::TopicHash;
 
-use super::events::ServiceIn;
+use super::events::{ServiceIn, ServiceOut};
+use super::persistence::{DedupPersistenceConfig, DedupRecord, DedupStore};
+
+/// A seen message cache entry.
+#[derive(Debug, Default)]
+struct SeenEntry {
+    /// The peers we have received the message from and the number of duplicate receipts (resends)
+    /// from each of them.
+    receipts: HashMap<PeerId, usize>,
+    /// Whether the message was published by the local node and not echoed back by a remote peer
+    /// yet.
+    awaiting_echo: bool,
+    /// The peer the message was first received from. `None` if the message was published by the
+    /// local node, or the peer disconnected.
+    first_seen_source: Option<PeerId>,
+}
+
+/// A message retained to be backfilled to the peers subscribing to its topic.
+#[derive(Debug)]
+struct RetainedMessage {
+    /// The message.
+    message: Rc<FrameMessage>,
+    /// The time the message was inserted into the cache.
+    inserted_at: Instant,
+}
+
+/// A seen message cache key.
+///
+/// The topic is only part of the key if the deduplication scope is [`DedupScope::PerTopic`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DedupKey {
+    topic: Option<TopicHash>,
+    message_id: MessageId,
+}
 
 pub struct MessageCacheService {
     /// The internal cache data structure.
@@ -20,8 +59,65 @@ pub struct MessageCacheService {
     /// the map.
     ///
     /// NOTE: For now, this cache is use as "seen cache" to deduplicate messages. We do not store
-    /// the message itself.
-    cache: Cache<MessageId, ()>,
+    /// the message itself, only the peers we received it from.
+    cache: Cache<DedupKey, SeenEntry>,
+
+    /// The scope the messages are deduplicated in.
+    dedup_scope: DedupScope,
+
+    /// The maximum number of times a peer can re-send the same message before it is considered
+    /// misbehaving.
+    max_duplicate_resends: usize,
+
+    /// The message history sliding window.
+    ///
+    /// Each window holds the ids of the messages inserted into the cache during a heartbeat
+    /// interval, by topic. The current window is at the front, and the oldest window at the back.
+    /// On every heartbeat, a new window is pushed to the front and the windows exceeding the
+    /// history length are dropped.
+    history: VecDeque<HashMap<TopicHash, Vec<MessageId>>>,
+
+    /// The number of heartbeat intervals the message history spans.
+    history_length: usize,
+
+    /// The keys of the cache entries holding per-peer state, i.e., a receipt from the peer, by
+    /// peer.
+    ///
+    /// The index is used to drop the per-peer state once the peer disconnects. The keys of the
+    /// expired cache entries are pruned on every heartbeat.
+    peer_entries: HashMap<PeerId, HashSet<DedupKey>>,
+
+    /// The messages retained for backfill, by topic, oldest first.
+    ///
+    /// Only the messages of the topics whose local subscription enables the backfill are
+    /// retained. The messages older than the cache TTL are dropped on every heartbeat, and each
+    /// topic retains at most the cache capacity.
+    retained: HashMap<TopicHash, VecDeque<RetainedMessage>>,
+
+    /// The cache capacity, i.e., the maximum number of messages retained per topic.
+    capacity: usize,
+
+    /// The cache entries time-to-live, i.e., the maximum age of the retained messages.
+    ttl: Duration,
+
+    /// The cache byte limit, if any (see [`MessageCacheService::with_byte_limit`]).
+    max_bytes: Option<usize>,
+
+    /// The cached messages, by message id, if the message lookup is enabled (see
+    /// [`MessageCacheService::with_message_lookup`]).
+    ///
+    /// The lookup cache shares the seen cache capacity, TTL and byte limit.
+    messages: Option<Cache<MessageId, Rc<FrameMessage>>>,
+
+    /// The persistent dedup store, if enabled (see [`MessageCacheService::enable_dedup_persistence`]).
+    dedup_store: Option<DedupStore>,
+
+    /// The messages seen before the node restart, loaded from the dedup store, and the time they
+    /// are no longer considered seen.
+    restored: HashMap<DedupKey, SystemTime>,
+
+    /// The dedup store failure pending to be notified.
+    persistence_failure: Option<Rc<io::Error>>,
 
     /// The service's heartbeat.
     heartbeat: Heartbeat,
@@ -29,22 +125,186 @@ pub struct MessageCacheService {
 
 /// Public API.
 impl MessageCacheService {
-    /// Creates a new `MessageCache` with the given time-to-live and capacity.
+    /// Creates a new `MessageCache` with the given time-to-live and capacity, deduplicating the
+    /// messages in the given scope and keeping the message history of the last `history_length`
+    /// heartbeat intervals.
     pub fn new(
         capacity: usize,
         ttl: Duration,
+        dedup_scope: DedupScope,
+        max_duplicate_resends: usize,
+        history_length: usize,
         heartbeat_interval: Duration,
         heartbeat_initial_delay: Duration,
     ) -> Self {
         Self {
             cache: Cache::with_capacity_and_ttl(capacity, ttl),
+            dedup_scope,
+            max_duplicate_resends,
+            history: VecDeque::from([HashMap::new()]),
+            history_length: history_length.max(1),
+            peer_entries: Default::default(),
+            retained: Default::default(),
+            capacity,
+            ttl,
+            max_bytes: None,
+            messages: None,
+            dedup_store: None,
+            restored: Default::default(),
+            persistence_failure: None,
             heartbeat: Heartbeat::new(heartbeat_interval, heartbeat_initial_delay),
         }
     }
 
-    /// Check if the cache contains the given `Message`.
-    pub fn contains(&self, message_id: &MessageId) -> bool {
-        self.cache.contains_key(message_id)
+    /// Bound the seen cache to the given byte limit, each entry costing its message encoded size
+    /// (see [`Config::message_cache_max_bytes`](crate::Config::message_cache_max_bytes)).
+    ///
+    /// Must be called before any message is inserted, as the cache is re-created.
+    #[must_use]
+    pub fn with_byte_limit(mut self, max_bytes: Option<usize>) -> Self {
+        self.cache = Cache::with_capacity_ttl_and_byte_limit(self.capacity, self.ttl, max_bytes);
+        self.max_bytes = max_bytes;
+        if self.messages.is_some() {
+            self.messages = Some(self.new_message_lookup_cache());
+        }
+        self
+    }
+
+    /// Keep the cached messages, and not only their ids, to look them up by message id (see
+    /// [`MessageCacheService::message`]), e.g., to serve the IWANT requests.
+    ///
+    /// Must be called before any message is inserted, as the lookup cache is re-created.
+    #[must_use]
+    pub fn with_message_lookup(mut self, enabled: bool) -> Self {
+        self.messages = enabled.then(|| self.new_message_lookup_cache());
+        self
+    }
+
+    /// Persist the seen messages ids to the dedup store file, and pre-populate the cache with the
+    /// messages seen before the node restart (see
+    /// [`Config::dedup_persistence`](crate::Config::dedup_persistence)).
+    ///
+    /// If the file cannot be read, or is corrupt, the cache is memory-only and a
+    /// [`ServiceOut::DedupPersistenceFailed`] event is emitted on the next poll.
+    pub fn enable_dedup_persistence(&mut self, config: DedupPersistenceConfig) {
+        if let Err(err) = self.try_enable_dedup_persistence(config) {
+            tracing::warn!(
+                "Dedup store open failed, using a memory-only cache: {}",
+                err
+            );
+            self.persistence_failure = Some(Rc::new(err));
+        }
+    }
+
+    /// Same as [`MessageCacheService::enable_dedup_persistence`], but returns an error if the
+    /// dedup store file cannot be read, or is corrupt, instead of falling back to a memory-only
+    /// cache.
+    pub fn try_enable_dedup_persistence(
+        &mut self,
+        config: DedupPersistenceConfig,
+    ) -> io::Result<()> {
+        let (store, records) = DedupStore::open(config)?;
+        tracing::debug!(restored = records.len(), "Dedup store opened");
+        for record in records {
+            let key = self.dedup_key(&record.topic, record.message_id);
+            self.restored.insert(key, record.expires_at);
+        }
+        self.dedup_store = Some(store);
+        Ok(())
+    }
+
+    /// Check if the cache contains the message with the given id, published to the given topic.
+    ///
+    /// The topic is ignored if the deduplication scope is [`DedupScope::Global`].
+    pub fn contains(&self, topic: &TopicHash, message_id: &MessageId) -> bool {
+        let key = self.dedup_key(topic, message_id.clone());
+        self.cache.contains_key(&key)
+            || self
+                .restored
+                .get(&key)
+                .map_or(false, |expires_at| *expires_at > SystemTime::now())
+    }
+
+    /// Get the ids of the messages seen on the given topic during the last `last_n_ticks`
+    /// heartbeat intervals, including the current one, from the most to the least recent.
+    ///
+    /// The history spans at most the configured history length.
+    pub fn history(&self, topic: &TopicHash, last_n_ticks: usize) -> Vec<MessageId> {
+        self.history
+            .iter()
+            .take(last_n_ticks)
+            .filter_map(|window| window.get(topic))
+            .flat_map(|message_ids| message_ids.iter().rev().cloned())
+            .collect()
+    }
+
+    /// Get the peer the message with the given id, published to the given topic, was first
+    /// received from.
+    ///
+    /// Returns `None` if the message is not in the cache, it was published by the local node, or
+    /// the peer disconnected since.
+    pub fn first_seen_source(&self, topic: &TopicHash, message_id: &MessageId) -> Option<PeerId> {
+        self.cache
+            .get(&self.dedup_key(topic, message_id.clone()))
+            .and_then(|entry| entry.first_seen_source)
+    }
+
+    /// Get the cached message with the given id.
+    ///
+    /// Returns `None` if the message is not in the cache, or the message lookup is not enabled.
+    pub fn message(&self, message_id: &MessageId) -> Option<Rc<FrameMessage>> {
+        self.messages
+            .as_ref()
+            .and_then(|messages| messages.get(message_id))
+            .cloned()
+    }
+
+    /// Check if the messages of the given topic are retained for backfill.
+    pub fn is_backfill_enabled(&self, topic: &TopicHash) -> bool {
+        self.retained.contains_key(topic)
+    }
+
+    /// Get the messages retained for backfill on the given topic, seen during the last `max_age`,
+    /// oldest first.
+    ///
+    /// At most the `max_messages` most recent messages are returned. Returns no message if the
+    /// topic backfill is not enabled.
+    pub fn recent_messages(
+        &self,
+        topic: &TopicHash,
+        max_age: Duration,
+        max_messages: usize,
+    ) -> Vec<Rc<FrameMessage>> {
+        let Some(messages) = self.retained.get(topic) else {
+            return Vec::new();
+        };
+
+        let now = Instant::now();
+        let mut recent = messages
+            .iter()
+            .rev()
+            .take_while(|retained| now.saturating_duration_since(retained.inserted_at) <= max_age)
+            .take(max_messages)
+            .map(|retained| retained.message.clone())
+            .collect::<Vec<_>>();
+        recent.reverse();
+        recent
+    }
+
+    /// Get the number of times the given peer re-sent the given message.
+    ///
+    /// Returns `None` if the message is not in the cache or it was never received from the peer.
+    #[cfg(test)]
+    pub fn duplicate_resends(
+        &self,
+        topic: &TopicHash,
+        message_id: &MessageId,
+        peer: &PeerId,
+    ) -> Option<usize> {
+        self.cache
+            .get(&self.dedup_key(topic, message_id.clone()))
+            .and_then(|entry| entry.receipts.get(peer))
+            .copied()
     }
 
     /// Get the cache usage.
@@ -54,34 +314,263 @@ impl MessageCacheService {
     pub fn usage(&self) -> usize {
         self.cache.len()
     }
+
+    /// Get the cache byte usage.
+    ///
+    /// This is the total encoded size of the messages currently in the cache, including the
+    /// expired messages not swept yet.
+    pub fn byte_usage(&self) -> usize {
+        self.cache.byte_usage()
+    }
+}
+
+/// Internal API.
+impl MessageCacheService {
+    /// Get the seen cache key of a message, according to the deduplication scope.
+    fn dedup_key(&self, topic: &TopicHash, message_id: MessageId) -> DedupKey {
+        let topic = match self.dedup_scope {
+            DedupScope::Global => None,
+            DedupScope::PerTopic => Some(topic.clone()),
+        };
+        DedupKey { topic, message_id }
+    }
+
+    /// Create the message lookup cache, sharing the seen cache bounds.
+    fn new_message_lookup_cache(&self) -> Cache<MessageId, Rc<FrameMessage>> {
+        Cache::with_capacity_ttl_and_byte_limit(self.capacity, self.ttl, self.max_bytes)
+    }
+
+    /// Keep the message for lookup, if the message lookup is enabled.
+    fn store_message(&mut self, message_id: MessageId, message: Rc<FrameMessage>, cost: usize) {
+        if let Some(messages) = self.messages.as_mut() {
+            messages.put_with_cost(message_id, message, cost);
+        }
+    }
+
+    /// Record the message in the current message history window.
+    fn record_history(&mut self, topic: TopicHash, message_id: MessageId) {
+        if let Some(window) = self.history.front_mut() {
+            window.entry(topic).or_default().push(message_id);
+        }
+    }
+
+    /// Drop the per-peer state of the disconnected peer from the cache entries.
+    fn drop_peer_state(&mut self, peer: &PeerId) {
+        let Some(keys) = self.peer_entries.remove(peer) else {
+            return;
+        };
+
+        for key in keys {
+            if let Some(entry) = self.cache.get_mut(&key) {
+                entry.receipts.remove(peer);
+                if entry.first_seen_source.as_ref() == Some(peer) {
+                    entry.first_seen_source = None;
+                }
+            }
+        }
+    }
+
+    /// Prune the keys of the expired cache entries from the per-peer index.
+    fn prune_peer_entries(&mut self) {
+        let cache = &self.cache;
+        self.peer_entries.retain(|_, keys| {
+            keys.retain(|key| cache.contains_key(key));
+            !keys.is_empty()
+        });
+    }
+
+    /// Retain the message for backfill, if its topic backfill is enabled.
+    fn retain_message(&mut self, topic: &TopicHash, message: Rc<FrameMessage>) {
+        let Some(messages) = self.retained.get_mut(topic) else {
+            return;
+        };
+
+        messages.push_back(RetainedMessage {
+            message,
+            inserted_at: Instant::now(),
+        });
+        if messages.len() > self.capacity {
+            messages.pop_front();
+        }
+    }
+
+    /// Drop the retained messages older than the cache TTL.
+    fn prune_retained_messages(&mut self) {
+        let now = Instant::now();
+        for messages in self.retained.values_mut() {
+            while let Some(retained) = messages.front() {
+                if now.saturating_duration_since(retained.inserted_at) <= self.ttl {
+                    break;
+                }
+                messages.pop_front();
+            }
+        }
+    }
+
+    /// Record the newly seen message in the dedup store, if enabled.
+    fn persist_seen(&mut self, topic: &TopicHash, message_id: &MessageId) {
+        if let Some(store) = self.dedup_store.as_mut() {
+            store.record(DedupRecord {
+                topic: topic.clone(),
+                message_id: message_id.clone(),
+                expires_at: SystemTime::now() + self.ttl,
+            });
+        }
+    }
+
+    /// Drop the expired restored messages, and append the newly seen messages to the dedup store,
+    /// if the flush interval elapsed.
+    ///
+    /// If the dedup store write fails, the cache is memory-only from now on.
+    fn maintain_dedup_store(&mut self) {
+        let now = SystemTime::now();
+        self.restored.retain(|_, expires_at| *expires_at > now);
+
+        let Some(store) = self.dedup_store.as_mut() else {
+            return;
+        };
+        if let Err(err) = store.flush_if_due(Instant::now()) {
+            tracing::warn!(
+                "Dedup store write failed, using a memory-only cache: {}",
+                err
+            );
+            self.dedup_store = None;
+            self.persistence_failure = Some(Rc::new(err));
+        }
+    }
+
+    /// Shift the message history window, dropping the windows exceeding the history length.
+    fn shift_history(&mut self) {
+        self.history.push_front(HashMap::new());
+        self.history.truncate(self.history_length);
+    }
 }
 
 impl Service for MessageCacheService {
     type InEvent = ServiceIn;
-    type OutEvent = ();
+    type OutEvent = ServiceOut;
 
     fn poll<'a>(
         &mut self,
         svc_cx: impl PollCtx<'a, Self::InEvent, Self::OutEvent>,
         cx: &mut Context<'_>,
     ) -> Poll<Self::OutEvent> {
-        let (mut in_cx, _out_cx) = svc_cx.split();
+        let (mut in_cx, mut out_cx) = svc_cx.split();
 
         // Poll the heartbeat stream.
         if self.heartbeat.poll_next_unpin(cx).is_ready() {
             self.cache.clear_expired_entries();
+            if let Some(messages) = self.messages.as_mut() {
+                messages.clear_expired_entries();
+            }
+            self.prune_peer_entries();
+            self.prune_retained_messages();
+            self.shift_history();
+            self.maintain_dedup_store();
+        }
+
+        // Notify the dedup store failure, if any.
+        if let Some(error) = self.persistence_failure.take() {
+            out_cx.emit(ServiceOut::DedupPersistenceFailed { error });
         }
 
         // Process the incoming events.
         while let Some(ev) = in_cx.pop_next() {
             match ev {
-                ServiceIn::MessageEvent(MessageEvent::MessageReceived { message_id, .. }) => {
+                ServiceIn::MessageEvent(MessageEvent::MessageReceived(ctx)) => {
                     // Insert message into the cache
-                    self.cache.put(message_id, ());
+                    let (src, message, message_id, _) = ctx.into_parts();
+                    let topic = message.topic();
+                    let cost = message.encoded_len();
+                    let key = self.dedup_key(&topic, message_id.clone());
+                    let mut entry = SeenEntry {
+                        first_seen_source: Some(src),
+                        ..Default::default()
+                    };
+                    entry.receipts.insert(src, 0);
+                    self.cache.put_with_cost(key.clone(), entry, cost);
+                    self.peer_entries.entry(src).or_default().insert(key);
+                    self.persist_seen(&topic, &message_id);
+                    self.store_message(message_id.clone(), message.clone(), cost);
+                    self.retain_message(&topic, message);
+                    self.record_history(topic, message_id);
                 }
-                ServiceIn::MessageEvent(MessageEvent::MessagePublished { message_id, .. }) => {
+                ServiceIn::MessageEvent(MessageEvent::MessagePublished {
+                    message,
+                    message_id,
+                }) => {
                     // Insert message into the cache
-                    self.cache.put(message_id, ());
+                    let topic = message.topic();
+                    let entry = SeenEntry {
+                        awaiting_echo: true,
+                        ..Default::default()
+                    };
+                    let cost = message.encoded_len();
+                    self.cache.put_with_cost(
+                        self.dedup_key(&topic, message_id.clone()),
+                        entry,
+                        cost,
+                    );
+                    self.persist_seen(&topic, &message_id);
+                    self.store_message(message_id.clone(), message.clone(), cost);
+                    self.retain_message(&topic, message);
+                    self.record_history(topic, message_id);
+                }
+                ServiceIn::MessageEvent(MessageEvent::DuplicateMessageReceived {
+                    src,
+                    topic,
+                    message_id,
+                }) => {
+                    let key = self.dedup_key(&topic, message_id.clone());
+                    let Some(entry) = self.cache.get_mut(&key) else {
+                        continue;
+                    };
+
+                    // Notify the first echo of a locally published message.
+                    if entry.awaiting_echo {
+                        entry.awaiting_echo = false;
+                        out_cx.emit(ServiceOut::PublishedMessageEchoed {
+                            message_id: message_id.clone(),
+                            echoed_by: src,
+                        });
+                    }
+
+                    // The first receipt from a peer is not a resend.
+                    let resends = match entry.receipts.get_mut(&src) {
+                        Some(resends) => {
+                            *resends += 1;
+                            *resends
+                        }
+                        None => {
+                            entry.receipts.insert(src, 0);
+                            self.peer_entries.entry(src).or_default().insert(key);
+                            0
+                        }
+                    };
+
+                    // Emit the event only once, when the threshold is exceeded.
+                    if resends == self.max_duplicate_resends + 1 {
+                        tracing::debug!(peer = %src, %message_id, "Duplicate message flood detected");
+                        out_cx.emit(ServiceOut::DuplicateFloodDetected {
+                            peer: src,
+                            message_id,
+                        });
+                    }
+                }
+                ServiceIn::BackfillTopicSubscribed(topic) => {
+                    // Retain the topic messages from now on.
+                    self.retained.entry(topic).or_default();
+                }
+                ServiceIn::TopicUnsubscribed(topic) => {
+                    // Drop the topic message history and retained messages.
+                    for window in self.history.iter_mut() {
+                        window.remove(&topic);
+                    }
+                    self.retained.remove(&topic);
+                }
+                ServiceIn::PeerDisconnected(peer) => {
+                    // Drop the disconnected peer duplicate counters and first-seen attributions.
+                    self.drop_peer_state(&peer);
                 }
             }
         }
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::Duration;
 
@@ -9,10 +10,12 @@ use sha2::{Digest, Sha256};
 use libp2p_pubsub_common::service::BufferedContext;
 
 use crate::framing::Message;
-use crate::message_id::MessageId;
+use crate::lifecycle::ReceivedMessageCtx;
+use crate::message_id::{DedupScope, MessageId};
 use crate::topic::TopicHash;
 
-use super::events::{MessageEvent, ServiceIn as MessageCacheInEvent};
+use super::events::{MessageEvent, ServiceIn as MessageCacheInEvent, ServiceOut};
+use super::persistence::DedupPersistenceConfig;
 use super::service::MessageCacheService;
 
 // Create a test instance of the `MessageCacheService`.
@@ -20,6 +23,25 @@ fn new_test_service() -> BufferedContext<MessageCacheService> {
     BufferedContext::new(MessageCacheService::new(
         1024,
         Duration::from_secs(5),
+        DedupScope::Global,
+        16,
+        5,
+        Duration::from_secs(1),
+        Duration::from_secs(1),
+    ))
+}
+
+/// Create a test instance of the `MessageCacheService` with a custom maximum number of duplicate
+/// resends.
+fn new_test_service_with_max_duplicate_resends(
+    max_duplicate_resends: usize,
+) -> BufferedContext<MessageCacheService> {
+    BufferedContext::new(MessageCacheService::new(
+        1024,
+        Duration::from_secs(5),
+        DedupScope::Global,
+        max_duplicate_resends,
+        5,
         Duration::from_secs(1),
         Duration::from_secs(1),
     ))
@@ -33,11 +55,106 @@ fn new_test_service_with_ttl_and_heartbeat(
     BufferedContext::new(MessageCacheService::new(
         1024,
         ttl,
+        DedupScope::Global,
+        16,
+        5,
         heartbeat_interval,
         Duration::from_secs(0),
     ))
 }
 
+/// Create a test instance of the `MessageCacheService` with a custom deduplication scope and TTL.
+fn new_test_service_with_dedup_scope(
+    dedup_scope: DedupScope,
+    ttl: Duration,
+) -> BufferedContext<MessageCacheService> {
+    BufferedContext::new(MessageCacheService::new(
+        1024,
+        ttl,
+        dedup_scope,
+        16,
+        5,
+        Duration::from_secs(1),
+        Duration::from_secs(1),
+    ))
+}
+
+/// Create a test instance of the `MessageCacheService` with a custom history length and heartbeat
+/// interval.
+fn new_test_service_with_history(
+    history_length: usize,
+    heartbeat_interval: Duration,
+) -> BufferedContext<MessageCacheService> {
+    BufferedContext::new(MessageCacheService::new(
+        1024,
+        Duration::from_secs(5),
+        DedupScope::Global,
+        16,
+        history_length,
+        heartbeat_interval,
+        heartbeat_interval,
+    ))
+}
+
+/// Create a test instance of the `MessageCacheService` with a custom TTL, persisting the seen
+/// messages to the given dedup store file.
+fn new_test_service_with_dedup_store(
+    ttl: Duration,
+    path: &PathBuf,
+) -> BufferedContext<MessageCacheService> {
+    let mut service = MessageCacheService::new(
+        1024,
+        ttl,
+        DedupScope::Global,
+        16,
+        5,
+        Duration::from_secs(1),
+        Duration::from_secs(1),
+    );
+    service.enable_dedup_persistence(DedupPersistenceConfig::new(path));
+    BufferedContext::new(service)
+}
+
+/// Create a test instance of the `MessageCacheService` with the given byte limit.
+fn new_test_service_with_byte_limit(
+    max_bytes: Option<usize>,
+) -> BufferedContext<MessageCacheService> {
+    BufferedContext::new(
+        MessageCacheService::new(
+            1024,
+            Duration::from_secs(5),
+            DedupScope::Global,
+            16,
+            5,
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        )
+        .with_byte_limit(max_bytes),
+    )
+}
+
+/// Create a test instance of the `MessageCacheService` with the message lookup enabled or
+/// disabled.
+fn new_test_service_with_message_lookup(enabled: bool) -> BufferedContext<MessageCacheService> {
+    BufferedContext::new(
+        MessageCacheService::new(
+            1024,
+            Duration::from_secs(5),
+            DedupScope::Global,
+            16,
+            5,
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        )
+        .with_message_lookup(enabled),
+    )
+}
+
+/// Create a new random test dedup store file path.
+fn new_test_dedup_store_path() -> PathBuf {
+    std::env::temp_dir().join(format!("pubsub-dedup-test-{}", random::<u64>()))
+}
+
 /// Create a new random test topic.
 fn new_test_topic() -> TopicHash {
     TopicHash::from_raw(format!("/pubsub/2/it-pubsub-test-{}", random::<u32>()))
@@ -74,15 +191,39 @@ fn new_message_received_seq(
     message: Message,
     message_id: MessageId,
 ) -> impl IntoIterator<Item = MessageCacheInEvent> {
+    new_message_received_from_seq(PeerId::random(), message, message_id)
+}
+
+/// Create a message received event sequence with the given propagation node source.
+fn new_message_received_from_seq(
+    src: PeerId,
+    message: Message,
+    message_id: MessageId,
+) -> impl IntoIterator<Item = MessageCacheInEvent> {
+    let mut ctx = ReceivedMessageCtx::new(src, message);
+    ctx.set_message_id(message_id);
     [MessageCacheInEvent::MessageEvent(
-        MessageEvent::MessageReceived {
-            src: PeerId::random(),
-            message: Rc::new(message),
-            message_id,
-        },
+        MessageEvent::MessageReceived(ctx),
     )]
 }
 
+/// Create a duplicate message received event sequence of `count` duplicates from the `src` peer,
+/// of a message published to the given topic.
+fn new_duplicate_message_received_seq(
+    src: PeerId,
+    topic: TopicHash,
+    message_id: MessageId,
+    count: usize,
+) -> impl IntoIterator<Item = MessageCacheInEvent> {
+    (0..count).map(move |_| {
+        MessageCacheInEvent::MessageEvent(MessageEvent::DuplicateMessageReceived {
+            src,
+            topic: topic.clone(),
+            message_id: message_id.clone(),
+        })
+    })
+}
+
 /// Create a message published event sequence.
 fn new_message_published_seq(
     message: Message,
@@ -129,15 +270,15 @@ async fn not_seen_message_is_added_to_cache() {
 
     //// Then
     assert!(
-        service.contains(&message_a_id),
+        service.contains(&topic_a, &message_a_id),
         "Cache should not contain message A"
     );
     assert!(
-        service.contains(&message_b_id),
+        service.contains(&topic_b, &message_b_id),
         "Cache should not contain message B"
     );
     assert!(
-        !service.contains(&unknown_message_id),
+        !service.contains(&topic_a, &unknown_message_id),
         "Cache should not contain an unknown message id"
     );
 }
@@ -166,11 +307,11 @@ async fn seen_message_should_not_be_added_to_cache() {
 
     //// Then
     assert!(
-        service.contains(&message_a_id),
+        service.contains(&topic, &message_a_id),
         "Cache should contain message A"
     );
     assert!(
-        service.contains(&message_b_id),
+        service.contains(&topic, &message_b_id),
         "Cache should contain message B"
     );
     assert_eq!(service.usage(), 2, "Cache should contain 2 messages");
@@ -199,7 +340,7 @@ async fn seen_message_should_not_be_contained_after_ttl() {
 
     //// Then
     assert!(
-        !service.contains(&message_id),
+        !service.contains(&topic, &message_id),
         "Cache should not contain message"
     );
 }
@@ -233,7 +374,7 @@ async fn seen_message_timestamp_is_updated() {
 
     //// Then
     assert!(
-        service.contains(&message_id),
+        service.contains(&topic, &message_id),
         "Cache should contain message"
     );
 }
@@ -264,7 +405,751 @@ async fn seen_message_should_not_be_contained_after_heartbeat() {
 
     //// Then
     assert!(
-        !service.contains(&message_id),
+        !service.contains(&topic, &message_id),
         "Cache should not contain message"
     );
 }
+
+#[tokio::test]
+async fn same_message_id_on_distinct_topics_is_seen_once_with_global_scope() {
+    //// Given
+    let mut service = new_test_service_with_dedup_scope(DedupScope::Global, Duration::from_secs(5));
+
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+    let message_id = new_test_message_id();
+
+    //// When
+    let input_events = itertools::chain!(
+        new_message_received_seq(new_test_message(topic_a.clone()), message_id.clone()),
+        new_message_received_seq(new_test_message(topic_b.clone()), message_id.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    assert!(service.contains(&topic_a, &message_id));
+    assert!(
+        service.contains(&new_test_topic(), &message_id),
+        "The topic should be ignored"
+    );
+    assert_eq!(service.usage(), 1, "Cache should contain 1 message");
+}
+
+#[tokio::test]
+async fn same_message_id_on_distinct_topics_is_seen_per_topic_with_per_topic_scope() {
+    //// Given
+    let mut service =
+        new_test_service_with_dedup_scope(DedupScope::PerTopic, Duration::from_secs(5));
+
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+    let message_id = new_test_message_id();
+
+    //// When
+    let input_events = itertools::chain!(
+        new_message_received_seq(new_test_message(topic_a.clone()), message_id.clone()),
+        new_message_received_seq(new_test_message(topic_b.clone()), message_id.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    assert!(service.contains(&topic_a, &message_id));
+    assert!(service.contains(&topic_b, &message_id));
+    assert!(
+        !service.contains(&new_test_topic(), &message_id),
+        "The message was not seen on other topics"
+    );
+    assert_eq!(service.usage(), 2, "Cache should contain 2 messages");
+}
+
+/// Test that, with a per-topic deduplication scope, each topic entry of the same message id
+/// expires independently.
+#[tokio::test]
+async fn per_topic_entries_expire_independently() {
+    //// Given
+    let mut service =
+        new_test_service_with_dedup_scope(DedupScope::PerTopic, Duration::from_millis(50));
+
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+    let message_id = new_test_message_id();
+
+    //// When
+    let input_events =
+        new_message_received_seq(new_test_message(topic_a.clone()), message_id.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    tokio::time::sleep(Duration::from_millis(30)).await;
+
+    let input_events =
+        new_message_received_seq(new_test_message(topic_b.clone()), message_id.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    // Wait for the topic A entry TTL to expire
+    tokio::time::sleep(Duration::from_millis(30)).await;
+
+    //// Then
+    assert!(
+        !service.contains(&topic_a, &message_id),
+        "Cache should not contain the topic A message"
+    );
+    assert!(
+        service.contains(&topic_b, &message_id),
+        "Cache should contain the topic B message"
+    );
+}
+
+#[tokio::test]
+async fn peer_resending_a_seen_message_above_threshold_is_flagged_once() {
+    //// Given
+    let mut service = new_test_service_with_max_duplicate_resends(3);
+
+    let peer = PeerId::random();
+    let topic = new_test_topic();
+    let message = new_test_message(topic.clone());
+    let message_id = custom_message_id_fn(&message);
+
+    //// When
+    let input_events = itertools::chain!(
+        new_message_received_from_seq(peer, message.clone(), message_id.clone()),
+        new_duplicate_message_received_seq(peer, topic.clone(), message_id.clone(), 6),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert_eq!(
+        service.duplicate_resends(&topic, &message_id, &peer),
+        Some(6),
+        "The peer should have re-sent the message 6 times"
+    );
+    assert_eq!(output_events.len(), 1, "Only one event should be emitted");
+    assert!(matches!(
+        &output_events[0],
+        ServiceOut::DuplicateFloodDetected { peer: flagged, message_id: id }
+            if flagged == &peer && id == &message_id
+    ));
+}
+
+#[tokio::test]
+async fn distinct_peers_sending_a_seen_message_once_are_not_flagged() {
+    //// Given
+    let mut service = new_test_service_with_max_duplicate_resends(0);
+
+    let peers = (0..8).map(|_| PeerId::random()).collect::<Vec<_>>();
+    let topic = new_test_topic();
+    let message = new_test_message(topic.clone());
+    let message_id = custom_message_id_fn(&message);
+
+    //// When
+    let input_events = itertools::chain!(
+        new_message_received_from_seq(peers[0], message.clone(), message_id.clone()),
+        peers[1..]
+            .iter()
+            .flat_map(|peer| new_duplicate_message_received_seq(
+                *peer,
+                topic.clone(),
+                message_id.clone(),
+                1
+            )),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert!(output_events.is_empty(), "No peer should be flagged");
+    for peer in &peers {
+        assert_eq!(
+            service.duplicate_resends(&topic, &message_id, peer),
+            Some(0),
+            "The first receipt should not count as a resend"
+        );
+    }
+}
+
+#[tokio::test]
+async fn published_message_echo_is_notified_once() {
+    //// Given
+    let mut service = new_test_service();
+
+    let peer_a = PeerId::random();
+    let peer_b = PeerId::random();
+    let topic = new_test_topic();
+    let message = new_test_message(topic.clone());
+    let message_id = custom_message_id_fn(&message);
+
+    //// When
+    let input_events = itertools::chain!(
+        new_message_published_seq(message.clone(), message_id.clone()),
+        new_duplicate_message_received_seq(peer_a, topic.clone(), message_id.clone(), 1),
+        new_duplicate_message_received_seq(peer_b, topic.clone(), message_id.clone(), 1),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert_eq!(output_events.len(), 1, "Only one event should be emitted");
+    assert!(matches!(
+        &output_events[0],
+        ServiceOut::PublishedMessageEchoed { message_id: id, echoed_by }
+            if id == &message_id && echoed_by == &peer_a
+    ));
+}
+
+#[tokio::test]
+async fn received_message_duplicates_are_not_notified_as_echoes() {
+    //// Given
+    let mut service = new_test_service();
+
+    let peer_a = PeerId::random();
+    let peer_b = PeerId::random();
+    let topic = new_test_topic();
+    let message = new_test_message(topic.clone());
+    let message_id = custom_message_id_fn(&message);
+
+    //// When
+    let input_events = itertools::chain!(
+        new_message_received_from_seq(peer_a, message.clone(), message_id.clone()),
+        new_duplicate_message_received_seq(peer_b, topic.clone(), message_id.clone(), 1),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert!(output_events.is_empty(), "No event should be emitted");
+}
+
+#[tokio::test]
+async fn history_window_shifts_on_heartbeat() {
+    //// Given
+    let mut service = new_test_service_with_history(2, Duration::from_millis(50));
+
+    let topic = new_test_topic();
+    let message_a = new_test_message(topic.clone());
+    let message_a_id = custom_message_id_fn(&message_a);
+    let message_b = new_test_message(topic.clone());
+    let message_b_id = custom_message_id_fn(&message_b);
+
+    testlib::service::inject_events(
+        &mut service,
+        new_message_received_seq(message_a, message_a_id.clone()),
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    //// When
+    // Wait for the first heartbeat
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    testlib::service::inject_events(
+        &mut service,
+        new_message_received_seq(message_b, message_b_id.clone()),
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    let history_last_tick = service.history(&topic, 1);
+    let history_all_ticks = service.history(&topic, 2);
+
+    // Wait for the second heartbeat
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    testlib::service::async_poll(&mut service).await;
+
+    let history_after_shift = service.history(&topic, 3);
+
+    //// Then
+    assert_eq!(history_all_ticks, [message_b_id.clone(), message_a_id]);
+    assert_eq!(history_last_tick, [message_b_id]);
+    assert_eq!(
+        history_after_shift, history_last_tick,
+        "Message A should have been shifted out of the history"
+    );
+}
+
+#[tokio::test]
+async fn history_is_tracked_per_topic() {
+    //// Given
+    let mut service = new_test_service_with_history(5, Duration::from_secs(1));
+
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+    let message_a = new_test_message(topic_a.clone());
+    let message_a_id = custom_message_id_fn(&message_a);
+    let message_b = new_test_message(topic_b.clone());
+    let message_b_id = custom_message_id_fn(&message_b);
+
+    //// When
+    let input_events = itertools::chain!(
+        new_message_received_seq(message_a, message_a_id.clone()),
+        new_message_published_seq(message_b, message_b_id.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    assert_eq!(service.history(&topic_a, 5), [message_a_id]);
+    assert_eq!(service.history(&topic_b, 5), [message_b_id]);
+    assert!(service.history(&new_test_topic(), 5).is_empty());
+}
+
+#[tokio::test]
+async fn unsubscribed_topic_history_is_dropped() {
+    //// Given
+    let mut service = new_test_service_with_history(5, Duration::from_secs(1));
+
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+    let message_a = new_test_message(topic_a.clone());
+    let message_a_id = custom_message_id_fn(&message_a);
+    let message_b = new_test_message(topic_b.clone());
+    let message_b_id = custom_message_id_fn(&message_b);
+
+    let input_events = itertools::chain!(
+        new_message_received_seq(message_a, message_a_id.clone()),
+        new_message_received_seq(message_b, message_b_id.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [MessageCacheInEvent::TopicUnsubscribed(topic_a.clone())],
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    assert!(service.history(&topic_a, 5).is_empty());
+    assert_eq!(service.history(&topic_b, 5), [message_b_id]);
+    assert!(
+        service.contains(&topic_a, &message_a_id),
+        "The seen cache should be kept"
+    );
+}
+
+#[tokio::test]
+async fn first_seen_source_is_the_first_peer_sending_the_message() {
+    //// Given
+    let mut service = new_test_service();
+
+    let peer_a = PeerId::random();
+    let peer_b = PeerId::random();
+    let topic = new_test_topic();
+    let message_a = new_test_message(topic.clone());
+    let message_a_id = custom_message_id_fn(&message_a);
+    let message_b = new_test_message(topic.clone());
+    let message_b_id = custom_message_id_fn(&message_b);
+    let published_message = new_test_message(topic.clone());
+    let published_message_id = custom_message_id_fn(&published_message);
+
+    //// When
+    let input_events = itertools::chain!(
+        new_message_received_from_seq(peer_a, message_a.clone(), message_a_id.clone()),
+        new_duplicate_message_received_seq(peer_b, topic.clone(), message_a_id.clone(), 1),
+        new_message_received_from_seq(peer_b, message_b.clone(), message_b_id.clone()),
+        new_duplicate_message_received_seq(peer_a, topic.clone(), message_b_id.clone(), 1),
+        new_message_published_seq(published_message, published_message_id.clone()),
+        new_duplicate_message_received_seq(peer_a, topic.clone(), published_message_id.clone(), 1),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    assert_eq!(
+        service.first_seen_source(&topic, &message_a_id),
+        Some(peer_a)
+    );
+    assert_eq!(
+        service.first_seen_source(&topic, &message_b_id),
+        Some(peer_b)
+    );
+    assert_eq!(
+        service.first_seen_source(&topic, &published_message_id),
+        None,
+        "A published message has no first-seen source"
+    );
+    assert_eq!(
+        service.first_seen_source(&topic, &new_test_message_id()),
+        None
+    );
+}
+
+#[tokio::test]
+async fn disconnected_peer_state_is_dropped() {
+    //// Given
+    let mut service = new_test_service();
+
+    let peer_a = PeerId::random();
+    let peer_b = PeerId::random();
+    let topic = new_test_topic();
+    let message = new_test_message(topic.clone());
+    let message_id = custom_message_id_fn(&message);
+
+    let input_events = itertools::chain!(
+        new_message_received_from_seq(peer_a, message.clone(), message_id.clone()),
+        new_duplicate_message_received_seq(peer_a, topic.clone(), message_id.clone(), 2),
+        new_duplicate_message_received_seq(peer_b, topic.clone(), message_id.clone(), 1),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [MessageCacheInEvent::PeerDisconnected(peer_a)],
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    assert!(
+        service.contains(&topic, &message_id),
+        "The message should still be seen"
+    );
+    assert_eq!(
+        service.first_seen_source(&topic, &message_id),
+        None,
+        "The disconnected peer attribution should be dropped"
+    );
+    assert_eq!(
+        service.duplicate_resends(&topic, &message_id, &peer_a),
+        None,
+        "The disconnected peer duplicate counter should be dropped"
+    );
+    assert_eq!(
+        service.duplicate_resends(&topic, &message_id, &peer_b),
+        Some(0),
+        "The connected peer duplicate counter should be kept"
+    );
+}
+
+#[tokio::test]
+async fn backfill_topic_messages_are_retained_oldest_first() {
+    //// Given
+    let mut service = new_test_service();
+
+    let backfill_topic = new_test_topic();
+    let other_topic = new_test_topic();
+    let messages = (0..3)
+        .map(|_| new_test_message(backfill_topic.clone()))
+        .collect::<Vec<_>>();
+    let other_message = new_test_message(other_topic.clone());
+
+    testlib::service::inject_events(
+        &mut service,
+        [MessageCacheInEvent::BackfillTopicSubscribed(
+            backfill_topic.clone(),
+        )],
+    );
+
+    //// When
+    let input_events = itertools::chain!(
+        new_message_received_seq(messages[0].clone(), new_test_message_id()),
+        new_message_published_seq(messages[1].clone(), new_test_message_id()),
+        new_message_received_seq(messages[2].clone(), new_test_message_id()),
+        new_message_received_seq(other_message, new_test_message_id()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    let recent = service.recent_messages(&backfill_topic, Duration::from_secs(5), 16);
+    assert_eq!(
+        recent.iter().map(|m| (**m).clone()).collect::<Vec<_>>(),
+        messages
+    );
+
+    let capped = service.recent_messages(&backfill_topic, Duration::from_secs(5), 2);
+    assert_eq!(
+        capped.iter().map(|m| (**m).clone()).collect::<Vec<_>>(),
+        messages[1..],
+        "The most recent messages should be kept"
+    );
+
+    assert!(
+        service
+            .recent_messages(&other_topic, Duration::from_secs(5), 16)
+            .is_empty(),
+        "The messages of a topic without backfill should not be retained"
+    );
+}
+
+#[tokio::test]
+async fn retained_messages_older_than_max_age_are_skipped() {
+    //// Given
+    let mut service = new_test_service();
+
+    let topic = new_test_topic();
+    let old_message = new_test_message(topic.clone());
+    let new_message = new_test_message(topic.clone());
+
+    testlib::service::inject_events(
+        &mut service,
+        itertools::chain!(
+            [MessageCacheInEvent::BackfillTopicSubscribed(topic.clone())],
+            new_message_received_seq(old_message, new_test_message_id()),
+        ),
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        new_message_received_seq(new_message.clone(), new_test_message_id()),
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    let recent = service.recent_messages(&topic, Duration::from_millis(50), 16);
+    assert_eq!(
+        recent.iter().map(|m| (**m).clone()).collect::<Vec<_>>(),
+        [new_message]
+    );
+}
+
+#[tokio::test]
+async fn retained_messages_are_dropped_on_unsubscription() {
+    //// Given
+    let mut service = new_test_service();
+
+    let topic = new_test_topic();
+    let message = new_test_message(topic.clone());
+
+    testlib::service::inject_events(
+        &mut service,
+        itertools::chain!(
+            [MessageCacheInEvent::BackfillTopicSubscribed(topic.clone())],
+            new_message_received_seq(message, new_test_message_id()),
+        ),
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [MessageCacheInEvent::TopicUnsubscribed(topic.clone())],
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    assert!(service
+        .recent_messages(&topic, Duration::from_secs(5), 16)
+        .is_empty());
+}
+
+#[tokio::test]
+async fn seen_message_is_contained_after_restart_with_dedup_store() {
+    //// Given
+    let path = new_test_dedup_store_path();
+    let mut service = new_test_service_with_dedup_store(Duration::from_secs(60), &path);
+
+    let topic = new_test_topic();
+    let message = new_test_message(topic.clone());
+    let message_id = custom_message_id_fn(&message);
+
+    let input_events = new_message_received_seq(message, message_id.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    //// When
+    // Simulate a node restart, the pending records are flushed on drop.
+    drop(service);
+    let mut service = new_test_service_with_dedup_store(Duration::from_secs(60), &path);
+    let output_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert!(output_events.is_empty());
+    assert!(
+        service.contains(&topic, &message_id),
+        "Cache should contain the message seen before the restart"
+    );
+
+    std::fs::remove_file(&path).expect("remove dedup store file");
+}
+
+#[tokio::test]
+async fn expired_dedup_store_records_are_compacted_on_restart() {
+    //// Given
+    let path = new_test_dedup_store_path();
+    let topic = new_test_topic();
+
+    let expired_message = new_test_message(topic.clone());
+    let expired_message_id = custom_message_id_fn(&expired_message);
+    let mut service = new_test_service_with_dedup_store(Duration::from_millis(50), &path);
+    let input_events = new_message_received_seq(expired_message, expired_message_id.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+    drop(service);
+
+    let live_message = new_test_message(topic.clone());
+    let live_message_id = custom_message_id_fn(&live_message);
+    let mut service = new_test_service_with_dedup_store(Duration::from_secs(60), &path);
+    let input_events = new_message_received_seq(live_message, live_message_id.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+    drop(service);
+
+    let file_len_before_compaction = std::fs::metadata(&path).expect("dedup store file").len();
+
+    // Wait for the first message TTL to expire
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    //// When
+    let service = new_test_service_with_dedup_store(Duration::from_secs(60), &path);
+
+    //// Then
+    assert!(service.contains(&topic, &live_message_id));
+    assert!(
+        !service.contains(&topic, &expired_message_id),
+        "Cache should not contain the expired message"
+    );
+    assert!(
+        std::fs::metadata(&path).expect("dedup store file").len() < file_len_before_compaction,
+        "The expired record should be dropped from the file"
+    );
+
+    drop(service);
+    std::fs::remove_file(&path).expect("remove dedup store file");
+}
+
+#[tokio::test]
+async fn corrupt_dedup_store_falls_back_to_memory_only_cache() {
+    //// Given
+    let path = new_test_dedup_store_path();
+    std::fs::write(&path, b"not a dedup store").expect("write dedup store file");
+
+    let topic = new_test_topic();
+    let message = new_test_message(topic.clone());
+    let message_id = custom_message_id_fn(&message);
+
+    //// When
+    let mut service = new_test_service_with_dedup_store(Duration::from_secs(60), &path);
+
+    let input_events = new_message_received_seq(message, message_id.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert!(matches!(
+        output_events.as_slice(),
+        [ServiceOut::DedupPersistenceFailed { error }]
+            if error.kind() == std::io::ErrorKind::InvalidData
+    ));
+    assert!(
+        service.contains(&topic, &message_id),
+        "Cache should contain the message"
+    );
+
+    std::fs::remove_file(&path).expect("remove dedup store file");
+}
+
+#[tokio::test]
+async fn byte_limit_evicts_the_oldest_messages_by_encoded_size() {
+    //// Given
+    let topic = new_test_topic();
+    let small_messages = (0..4)
+        .map(|_| Message::new(topic.clone(), vec![0; 100]))
+        .collect::<Vec<_>>();
+    let large_message = Message::new(topic.clone(), vec![1; 250]);
+
+    let small_size = small_messages[0].encoded_len();
+    let max_bytes = 4 * small_size;
+    let mut service = new_test_service_with_byte_limit(Some(max_bytes));
+
+    let small_ids = small_messages
+        .iter()
+        .map(|_| new_test_message_id())
+        .collect::<Vec<_>>();
+    let large_id = new_test_message_id();
+
+    let input_events = small_messages
+        .into_iter()
+        .zip(small_ids.iter().cloned())
+        .flat_map(|(message, message_id)| new_message_received_seq(message, message_id));
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        new_message_published_seq(large_message.clone(), large_id.clone()),
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    // The large message cost is over twice a small message cost, so 3 small messages are evicted
+    assert!(service.contains(&topic, &large_id));
+    assert!(service.contains(&topic, &small_ids[3]));
+    for message_id in &small_ids[..3] {
+        assert!(
+            !service.contains(&topic, message_id),
+            "The oldest small messages should be evicted"
+        );
+    }
+    assert_eq!(service.usage(), 2);
+    assert_eq!(
+        service.byte_usage(),
+        small_size + large_message.encoded_len()
+    );
+    assert!(service.byte_usage() <= max_bytes);
+}
+
+#[tokio::test]
+async fn no_byte_limit_only_bounds_the_cache_by_capacity() {
+    //// Given
+    let topic = new_test_topic();
+    let messages = (0..8)
+        .map(|_| Message::new(topic.clone(), vec![0; 1000]))
+        .collect::<Vec<_>>();
+    let total_size = messages.iter().map(Message::encoded_len).sum::<usize>();
+
+    let mut service = new_test_service_with_byte_limit(None);
+
+    //// When
+    let input_events = messages
+        .into_iter()
+        .flat_map(|message| new_message_received_seq(message, new_test_message_id()));
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::async_poll(&mut service).await;
+
+    //// Then
+    assert_eq!(service.usage(), 8);
+    assert_eq!(service.byte_usage(), total_size);
+}
+
+#[tokio::test]
+async fn cached_messages_are_looked_up_by_id() {
+    //// Given
+    let topic = new_test_topic();
+    let received = Message::new(topic.clone(), b"received".to_vec());
+    let received_id = new_test_message_id();
+    let published = Message::new(topic.clone(), b"published".to_vec());
+    let published_id = new_test_message_id();
+
+    let mut service = new_test_service_with_message_lookup(true);
+    let mut disabled_service = new_test_service_with_message_lookup(false);
+
+    //// When
+    for svc in [&mut service, &mut disabled_service] {
+        let input_events = new_message_received_seq(received.clone(), received_id.clone())
+            .into_iter()
+            .chain(new_message_published_seq(
+                published.clone(),
+                published_id.clone(),
+            ));
+        testlib::service::inject_events(svc, input_events);
+        testlib::service::async_poll(svc).await;
+    }
+
+    //// Then
+    assert_eq!(service.message(&received_id).as_deref(), Some(&received));
+    assert_eq!(service.message(&published_id).as_deref(), Some(&published));
+    assert_eq!(service.message(&new_test_message_id()), None);
+    assert_eq!(
+        disabled_service.message(&received_id),
+        None,
+        "The messages should not be kept if the lookup is disabled"
+    );
+}
@@ -1,13 +1,15 @@
 use std::rc::Rc;
 
-use libp2p::identity::PeerId;
-
+use crate::dispatch::Consumes;
 use crate::framing::Message;
+use crate::lifecycle::{MessageContext, ReceivedMessageCtx};
 use crate::message_id::{MessageId, MessageIdFn};
+use crate::services::subscriptions::SubscriptionsOutEvent;
 use crate::topic::TopicHash;
 
 /// Message cache service input event.
 #[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum ServiceIn {
     /// A subscription event.
     ///
@@ -29,20 +31,29 @@ pub enum SubscriptionEvent {
     },
     /// The node unsubscribed from a topic.
     Unsubscribed(TopicHash),
+    /// The message id function of a subscribed topic changed.
+    ///
+    /// The messages processed afterwards get their message id from the new function.
+    MessageIdFnChanged {
+        /// The subscribed topic.
+        topic: TopicHash,
+        /// The new message id function. If `None`, the default message id function is used.
+        message_id_fn: Option<Rc<dyn MessageIdFn<Output = MessageId>>>,
+    },
 }
 
 /// A message event occurred.
 #[derive(Clone)]
 pub enum MessageEvent {
     /// A message was published by the local node.
-    Published(Rc<Message>),
-    /// A message was received from a remote peer.
-    Received {
-        /// The propagation node peer id.
-        src: PeerId,
+    Published {
         /// The message.
         message: Rc<Message>,
+        /// The message lifecycle context.
+        context: MessageContext,
     },
+    /// A message was received from a remote peer.
+    Received(ReceivedMessageCtx),
 }
 
 #[derive(Debug, Clone)]
@@ -53,14 +64,33 @@ pub enum ServiceOut {
         message: Rc<Message>,
         /// The message id.
         message_id: MessageId,
+        /// The message lifecycle context.
+        context: MessageContext,
     },
-    /// A message was received from a remote peer.
-    MessageReceived {
-        /// The propagation node peer id.
-        src: PeerId,
-        /// The message.
-        message: Rc<Message>,
-        /// The message id.
-        message_id: MessageId,
-    },
+    /// A message was received from a remote peer. The message id is set in its context.
+    MessageReceived(ReceivedMessageCtx),
+}
+
+impl Consumes<SubscriptionsOutEvent> for ServiceIn {
+    fn consume(ev: &SubscriptionsOutEvent) -> Option<Self> {
+        match ev {
+            SubscriptionsOutEvent::Subscribed(sub) => Some(ServiceIn::SubscriptionEvent(
+                SubscriptionEvent::Subscribed {
+                    topic: sub.topic.clone(),
+                    message_id_fn: sub.message_id_fn.clone(),
+                },
+            )),
+            SubscriptionsOutEvent::Unsubscribed { topic, .. } => Some(
+                ServiceIn::SubscriptionEvent(SubscriptionEvent::Unsubscribed(topic.clone())),
+            ),
+            SubscriptionsOutEvent::PeerSubscribed { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribed { .. }
+            | SubscriptionsOutEvent::PeerSubscribedMany { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribedMany { .. }
+            | SubscriptionsOutEvent::KnownPeerSubscribed { .. }
+            | SubscriptionsOutEvent::KnownPeerForgotten(_)
+            | SubscriptionsOutEvent::PeerFlapping { .. }
+            | SubscriptionsOutEvent::SendSubscriptions { .. } => None,
+        }
+    }
 }
@@ -1,9 +1,14 @@
 use std::collections::HashMap;
 use std::rc::Rc;
 
+use libp2p::identity::PeerId;
+
 use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
 
-use crate::message_id::{default_message_id_fn, MessageId, MessageIdFn};
+use crate::framing::Message as FrameMessage;
+use crate::message_id::{
+    default_message_id_fn, try_message_id, MessageId, MessageIdFn, MessageIdFnError,
+};
 use crate::topic::TopicHash;
 
 use super::events::{MessageEvent, ServiceIn, ServiceOut, SubscriptionEvent};
@@ -13,13 +18,97 @@ use super::events::{MessageEvent, ServiceIn, ServiceOut, SubscriptionEvent};
 ///
 /// The `MessageID` is generated by the `MessageID` function associated with the topic of the
 /// message. If at the moment the node subscribes to a topic there is no `MessageID` function
-/// is provided, the [default `MessageID` function](crate::message_id::default_message_id_fn) is
-/// used. If the node is not subscribed to the topic, the message id is computed using the
-/// default `MessageID` function.
-#[derive(Default)]
+/// is provided, the service's default `MessageID` function is used. If the node is not subscribed
+/// to the topic, the message id is computed using the default `MessageID` function.
+///
+/// The `MessageID` function of a subscribed topic can be swapped in place, without
+/// unsubscribing. The messages processed afterwards get their message id from the new function,
+/// while the ids already computed, e.g., the message cache entries, are kept as is.
+///
+/// Unless otherwise specified, the default `MessageID` function is the
+/// [`default_message_id_fn`](crate::message_id::default_message_id_fn).
+///
+/// The topic-specific `MessageID` functions are user-supplied: if one panics, or returns an empty
+/// or oversized `MessageID`, the message id is computed with the default `MessageID` function
+/// instead, and the fallback is counted.
 pub struct MessageIdService {
-    /// A table mapping the Topic with the `MessageID` function.
-    message_id_fn: HashMap<TopicHash, Rc<dyn MessageIdFn<Output = MessageId>>>,
+    /// The default `MessageID` function.
+    default_message_id_fn: Rc<dyn MessageIdFn<Output = MessageId>>,
+
+    /// A table mapping the subscribed Topic with its `MessageID` function. `None` if the topic
+    /// uses the default `MessageID` function.
+    message_id_fn: HashMap<TopicHash, Option<Rc<dyn MessageIdFn<Output = MessageId>>>>,
+
+    /// The number of message ids computed with the default `MessageID` function, as the topic's
+    /// `MessageID` function failed.
+    fallbacks: u64,
+}
+
+impl Default for MessageIdService {
+    fn default() -> Self {
+        Self::new(default_message_id_fn)
+    }
+}
+
+impl MessageIdService {
+    /// Creates a new `MessageIdService` with the given default `MessageID` function.
+    pub fn new(default_message_id_fn: impl MessageIdFn + 'static) -> Self {
+        Self {
+            default_message_id_fn: Rc::new(default_message_id_fn),
+            message_id_fn: Default::default(),
+            fallbacks: 0,
+        }
+    }
+
+    /// Computes the message id of the given message, propagated by `src` if received.
+    ///
+    /// The message id is computed by the message topic `MessageID` function, if subscribed.
+    /// Otherwise, the default `MessageID` function is used.
+    ///
+    /// Returns an error if the topic-specific `MessageID` function panics, or returns an empty or
+    /// oversized message id.
+    pub fn try_message_id(
+        &self,
+        src: Option<&PeerId>,
+        message: &FrameMessage,
+    ) -> Result<MessageId, MessageIdFnError> {
+        match self.message_id_fn.get(&message.topic()) {
+            Some(Some(id_fn)) => try_message_id(id_fn.as_ref(), src, &message.into()),
+            _ => Ok((self.default_message_id_fn)(src, &message.into())),
+        }
+    }
+
+    /// Computes the message id of the given message, propagated by `src` if received.
+    ///
+    /// Unlike [`MessageIdService::try_message_id`], if the topic-specific `MessageID` function
+    /// fails, the message id is computed with the default `MessageID` function.
+    pub fn message_id(&self, src: Option<&PeerId>, message: &FrameMessage) -> MessageId {
+        self.try_message_id(src, message)
+            .unwrap_or_else(|_| (self.default_message_id_fn)(src, &message.into()))
+    }
+
+    /// Get the number of message ids computed with the default `MessageID` function, as the
+    /// topic's `MessageID` function failed.
+    pub fn fallbacks(&self) -> u64 {
+        self.fallbacks
+    }
+
+    /// Computes the message id of the given message, falling back to the default `MessageID`
+    /// function if the topic's `MessageID` function fails.
+    fn message_id_or_fallback(
+        &mut self,
+        src: Option<&PeerId>,
+        message: &FrameMessage,
+    ) -> MessageId {
+        match self.try_message_id(src, message) {
+            Ok(message_id) => message_id,
+            Err(err) => {
+                tracing::warn!(topic = %message.topic(), "Message id computation failed, using the default message id function: {}", err);
+                self.fallbacks += 1;
+                (self.default_message_id_fn)(src, &message.into())
+            }
+        }
+    }
 }
 
 impl EventHandler for MessageIdService {
@@ -37,37 +126,38 @@ impl EventHandler for MessageIdService {
                 topic,
             }) => {
                 // Register the topic's message id function
-                let message_id_fn = message_id_fn.unwrap_or(Rc::new(default_message_id_fn));
                 self.message_id_fn.insert(topic, message_id_fn);
             }
             ServiceIn::SubscriptionEvent(SubscriptionEvent::Unsubscribed(topic)) => {
                 // Unregister the topic's message id function
                 self.message_id_fn.remove(&topic);
             }
-            ServiceIn::MessageEvent(MessageEvent::Published(message)) => {
-                let message_id = match self.message_id_fn.get(&message.topic()) {
-                    None => default_message_id_fn(None, &message.as_ref().into()),
-                    Some(id_fn) => id_fn(None, &message.as_ref().into()),
-                };
+            ServiceIn::SubscriptionEvent(SubscriptionEvent::MessageIdFnChanged {
+                topic,
+                message_id_fn,
+            }) => {
+                // Replace the topic's message id function, if still subscribed
+                if let Some(id_fn) = self.message_id_fn.get_mut(&topic) {
+                    *id_fn = message_id_fn;
+                }
+            }
+            ServiceIn::MessageEvent(MessageEvent::Published { message, context }) => {
+                let message_id = self.message_id_or_fallback(None, &message);
+                context.record_message_id(&message_id);
 
                 // Emit the message event with the message id.
                 svc_cx.emit(ServiceOut::MessagePublished {
                     message,
                     message_id,
+                    context,
                 });
             }
-            ServiceIn::MessageEvent(MessageEvent::Received { src, message }) => {
-                let message_id = match self.message_id_fn.get(&message.topic()) {
-                    None => default_message_id_fn(Some(&src), &message.as_ref().into()),
-                    Some(id_fn) => id_fn(Some(&src), &message.as_ref().into()),
-                };
+            ServiceIn::MessageEvent(MessageEvent::Received(mut ctx)) => {
+                let message_id = self.message_id_or_fallback(Some(&ctx.src()), ctx.message());
+                ctx.set_message_id(message_id);
 
                 // Emit the message event with the message id.
-                svc_cx.emit(ServiceOut::MessageReceived {
-                    src,
-                    message,
-                    message_id,
-                });
+                svc_cx.emit(ServiceOut::MessageReceived(ctx));
             }
         }
     }
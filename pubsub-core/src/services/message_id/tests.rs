@@ -1,4 +1,5 @@
 use std::rc::Rc;
+use std::time::Duration;
 
 use assert_matches::assert_matches;
 use bytes::Bytes;
@@ -6,11 +7,17 @@ use libp2p::PeerId;
 use rand::random;
 use sha2::{Digest, Sha256};
 
-use libp2p_pubsub_common::service::BufferedContext;
+use libp2p_pubsub_common::service::{BufferedContext, ServiceContext};
 use testlib::service::noop_context;
 
 use crate::framing::Message;
-use crate::message_id::{default_message_id_fn, MessageId, MessageIdFn, MessageRef};
+use crate::lifecycle::{MessageContext, ReceivedMessageCtx};
+use crate::message_id::{
+    default_message_id_fn, DedupScope, MessageId, MessageIdFn, MessageIdFnError, MessageRef,
+};
+use crate::services::message_cache::{
+    MessageCacheInEvent, MessageCacheMessageEvent, MessageCacheService,
+};
 use crate::services::message_id::events::ServiceOut;
 use crate::topic::TopicHash;
 
@@ -65,21 +72,35 @@ fn new_unsubscription_seq(topic: TopicHash) -> impl IntoIterator<Item = MessageI
     )]
 }
 
+/// Create a message id function change event sequence.
+fn new_message_id_fn_changed_seq(
+    topic: TopicHash,
+    id_fn: Option<Rc<dyn MessageIdFn<Output = MessageId>>>,
+) -> impl IntoIterator<Item = MessageIdInEvent> {
+    [MessageIdInEvent::SubscriptionEvent(
+        SubscriptionEvent::MessageIdFnChanged {
+            topic,
+            message_id_fn: id_fn,
+        },
+    )]
+}
+
 /// Create a message received event sequence.
 ///
 /// The propagation source is set to a random peer id.
 fn new_message_received_seq(message: Message) -> impl IntoIterator<Item = MessageIdInEvent> {
-    [MessageIdInEvent::MessageEvent(MessageEvent::Received {
-        src: PeerId::random(),
-        message: Rc::new(message),
-    })]
+    let src = PeerId::random();
+    [MessageIdInEvent::MessageEvent(MessageEvent::Received(
+        ReceivedMessageCtx::new(src, message),
+    ))]
 }
 
 /// Create a message published event sequence.
 fn new_message_published_seq(message: Message) -> impl IntoIterator<Item = MessageIdInEvent> {
-    [MessageIdInEvent::MessageEvent(MessageEvent::Published(
-        Rc::new(message),
-    ))]
+    [MessageIdInEvent::MessageEvent(MessageEvent::Published {
+        context: MessageContext::published(&message.topic()),
+        message: Rc::new(message),
+    })]
 }
 
 /// If the node is not subscribed to a topic, the message ID should be generated using the default
@@ -115,9 +136,9 @@ fn unknown_topic_message_uses_default_message_id_fn() {
 
     //// Then
     assert_eq!(output_events.len(), 2, "Only 2 events expected");
-    assert_matches!(&output_events[0], ServiceOut::MessageReceived { message_id, .. } => {
+    assert_matches!(&output_events[0], ServiceOut::MessageReceived(ctx) => {
         let expected_message_id = default_message_id_fn(None, &message_a.as_ref().into());
-        assert_eq!(message_id, &expected_message_id, "Message ID should have been generated using default message ID function");
+        assert_eq!(ctx.message_id(), &expected_message_id, "Message ID should have been generated using default message ID function");
     });
     assert_matches!(&output_events[1], ServiceOut::MessagePublished { message_id, .. } => {
         let expected_message_id = default_message_id_fn(None, &message_b.as_ref().into());
@@ -159,9 +180,9 @@ fn subscribed_topic_message_uses_default_message_id_fn() {
 
     //// Then
     assert_eq!(output_events.len(), 2, "Only 2 events expected");
-    assert_matches!(&output_events[0], ServiceOut::MessageReceived { message_id, .. } => {
+    assert_matches!(&output_events[0], ServiceOut::MessageReceived(ctx) => {
         let expected_message_id = default_message_id_fn(None, &message_a.as_ref().into());
-        assert_eq!(message_id, &expected_message_id, "Message ID should have been generated using default message ID function");
+        assert_eq!(ctx.message_id(), &expected_message_id, "Message ID should have been generated using default message ID function");
     });
     assert_matches!(&output_events[1], ServiceOut::MessagePublished { message_id, .. } => {
         let expected_message_id = default_message_id_fn(None, &message_b.as_ref().into());
@@ -205,9 +226,9 @@ fn subscribed_topic_message_uses_configured_custom_message_id_fn() {
 
     //// Then
     assert_eq!(output_events.len(), 2, "Only 2 events expected");
-    assert_matches!(&output_events[0], ServiceOut::MessageReceived { message_id, .. } => {
+    assert_matches!(&output_events[0], ServiceOut::MessageReceived(ctx) => {
         let expected_message_id = message_id_fn(None, &message_a.as_ref().into());
-        assert_eq!(message_id, &expected_message_id, "Message ID should have been generated using custom message ID function");
+        assert_eq!(ctx.message_id(), &expected_message_id, "Message ID should have been generated using custom message ID function");
     });
     assert_matches!(&output_events[1], ServiceOut::MessagePublished { message_id, .. } => {
         let expected_message_id = message_id_fn(None, &message_b.as_ref().into());
@@ -261,17 +282,260 @@ fn unsubscribed_topic_message_uses_default_message_id_fn() {
     //// Then
     assert_eq!(output_events.len(), 3, "Only 3 events expected");
     // Assert messages before unsubscription.
-    assert_matches!(&output_events[0], ServiceOut::MessageReceived { message_id, .. } => {
+    assert_matches!(&output_events[0], ServiceOut::MessageReceived(ctx) => {
         let expected_message_id = message_id_fn(None, &message_a.as_ref().into());
-        assert_eq!(message_id, &expected_message_id, "Message ID should have been generated using custom message ID function");
+        assert_eq!(ctx.message_id(), &expected_message_id, "Message ID should have been generated using custom message ID function");
     });
     // Assert messages after unsubscription.
-    assert_matches!(&output_events[1], ServiceOut::MessageReceived { message_id, .. } => {
+    assert_matches!(&output_events[1], ServiceOut::MessageReceived(ctx) => {
         let expected_message_id = default_message_id_fn(None, &message_b.as_ref().into());
+        assert_eq!(ctx.message_id(), &expected_message_id, "Message ID should have been generated using default message ID function");
+    });
+    assert_matches!(&output_events[2], ServiceOut::MessagePublished { message_id, .. } => {
+        let expected_message_id = default_message_id_fn(None, &message_c.as_ref().into());
         assert_eq!(message_id, &expected_message_id, "Message ID should have been generated using default message ID function");
     });
+}
+
+/// When the message ID function of a subscribed topic is swapped, the messages before the swap
+/// should get their ID from the previous function, and the messages after the swap from the new
+/// one.
+#[test]
+fn swapped_message_id_fn_is_used_after_the_swap() {
+    //// Given
+    let mut service = new_test_service();
+
+    let remote_peer = new_test_peer_id();
+    let topic = new_test_topic();
+
+    let message_a = Message::new_with_seq_no_and_from(
+        topic.clone(),
+        b"test-payload".to_vec(),
+        new_test_seqno(),
+        remote_peer,
+    );
+    let message_b = Message::new_with_seq_no_and_from(
+        topic.clone(),
+        b"test-payload".to_vec(),
+        new_test_seqno(),
+        remote_peer,
+    );
+    let message_c = Message::new_with_seq_no_and_from(
+        topic.clone(),
+        b"test-payload".to_vec(),
+        new_test_seqno(),
+        remote_peer,
+    );
+
+    let message_id_fn = Rc::new(custom_message_id_fn);
+
+    //// When
+    let input_events = itertools::chain!(
+        new_subscription_seq(topic.clone(), None),
+        new_message_received_seq(message_a.clone()),
+        new_message_id_fn_changed_seq(topic.clone(), Some(message_id_fn.clone())),
+        new_message_received_seq(message_b.clone()),
+        new_message_id_fn_changed_seq(topic.clone(), None), // Back to the default function
+        new_message_published_seq(message_c.clone())
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events.len(), 3, "Only 3 events expected");
+    assert_matches!(&output_events[0], ServiceOut::MessageReceived(ctx) => {
+        let expected_message_id = default_message_id_fn(None, &message_a.as_ref().into());
+        assert_eq!(ctx.message_id(), &expected_message_id, "Message ID should have been generated using default message ID function");
+    });
+    assert_matches!(&output_events[1], ServiceOut::MessageReceived(ctx) => {
+        let expected_message_id = message_id_fn(None, &message_b.as_ref().into());
+        assert_eq!(ctx.message_id(), &expected_message_id, "Message ID should have been generated using custom message ID function");
+    });
     assert_matches!(&output_events[2], ServiceOut::MessagePublished { message_id, .. } => {
         let expected_message_id = default_message_id_fn(None, &message_c.as_ref().into());
         assert_eq!(message_id, &expected_message_id, "Message ID should have been generated using default message ID function");
     });
 }
+
+/// A message ID function change for a topic the node is not subscribed to should be ignored.
+#[test]
+fn message_id_fn_change_of_not_subscribed_topic_is_ignored() {
+    //// Given
+    let mut service = new_test_service();
+
+    let topic = new_test_topic();
+    let message = Message::new_with_seq_no_and_from(
+        topic.clone(),
+        b"test-payload".to_vec(),
+        new_test_seqno(),
+        new_test_peer_id(),
+    );
+
+    //// When
+    let input_events = itertools::chain!(
+        new_message_id_fn_changed_seq(topic.clone(), Some(Rc::new(custom_message_id_fn))),
+        new_message_received_seq(message.clone())
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events.len(), 1, "Only 1 event expected");
+    assert_matches!(&output_events[0], ServiceOut::MessageReceived(ctx) => {
+        let expected_message_id = default_message_id_fn(None, &message.as_ref().into());
+        assert_eq!(ctx.message_id(), &expected_message_id, "Message ID should have been generated using default message ID function");
+    });
+}
+
+/// The message cache entries keep the ID computed before the swap, so the same message received
+/// again after the swap is not deduplicated if the new function computes a different ID.
+#[tokio::test]
+async fn message_seen_before_the_swap_is_not_deduplicated_with_a_distinct_id() {
+    //// Given
+    let mut service = new_test_service();
+    let mut cache = BufferedContext::new(MessageCacheService::new(
+        1024,
+        Duration::from_secs(5),
+        DedupScope::Global,
+        16,
+        5,
+        Duration::from_secs(1),
+        Duration::from_secs(1),
+    ));
+
+    let topic = new_test_topic();
+    let message = Message::new_with_seq_no_and_from(
+        topic.clone(),
+        b"test-payload".to_vec(),
+        new_test_seqno(),
+        new_test_peer_id(),
+    );
+
+    //// When
+    let input_events = itertools::chain!(
+        new_subscription_seq(topic.clone(), None),
+        new_message_received_seq(message.clone()),
+        new_message_id_fn_changed_seq(topic.clone(), Some(Rc::new(custom_message_id_fn))),
+        new_message_received_seq(message.clone())
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    // Feed the received messages to the message cache.
+    let received = output_events
+        .into_iter()
+        .map(|ev| assert_matches!(ev, ServiceOut::MessageReceived(ctx) => ctx))
+        .collect::<Vec<_>>();
+    let message_ids = received
+        .iter()
+        .map(|ctx| ctx.message_id().clone())
+        .collect::<Vec<_>>();
+    let was_seen = received
+        .into_iter()
+        .map(|ctx| {
+            let seen = cache.contains(&topic, ctx.message_id());
+            cache.do_send(MessageCacheInEvent::MessageEvent(
+                MessageCacheMessageEvent::MessageReceived(ctx),
+            ));
+            seen
+        })
+        .collect::<Vec<_>>();
+    testlib::service::async_poll(&mut cache).await;
+
+    //// Then
+    assert_ne!(
+        message_ids[0], message_ids[1],
+        "The message IDs should differ"
+    );
+    assert_eq!(
+        was_seen,
+        [false, false],
+        "The message should not be deduplicated"
+    );
+    assert!(
+        cache.contains(&topic, &message_ids[0]),
+        "The old ID entry should be kept"
+    );
+    assert!(cache.contains(&topic, &message_ids[1]));
+}
+
+/// A custom `MessageIdFn` panicking on every message.
+fn panicking_message_id_fn(_src: Option<&PeerId>, _msg: &MessageRef) -> MessageId {
+    panic!("message id field not found");
+}
+
+/// A custom `MessageIdFn` returning an empty message ID for every message.
+fn empty_message_id_fn(_src: Option<&PeerId>, _msg: &MessageRef) -> MessageId {
+    MessageId::new(Vec::new())
+}
+
+/// When the topic's custom message ID function fails, the received message ID should be
+/// generated using the default message ID function, and the fallback counted.
+#[test]
+fn failing_custom_message_id_fn_falls_back_to_default_message_id_fn() {
+    for id_fn in [
+        Rc::new(panicking_message_id_fn) as Rc<dyn MessageIdFn<Output = MessageId>>,
+        Rc::new(empty_message_id_fn),
+    ] {
+        //// Given
+        let mut service = new_test_service();
+
+        let topic = new_test_topic();
+        let message = Message::new_with_seq_no_and_from(
+            topic.clone(),
+            b"test-payload".to_vec(),
+            new_test_seqno(),
+            new_test_peer_id(),
+        );
+
+        //// When
+        let input_events = itertools::chain!(
+            new_subscription_seq(topic.clone(), Some(id_fn)),
+            new_message_received_seq(message.clone())
+        );
+        testlib::service::inject_events(&mut service, input_events);
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(output_events.len(), 1, "The message should not be dropped");
+        assert_matches!(&output_events[0], ServiceOut::MessageReceived(ctx) => {
+            let expected_message_id = default_message_id_fn(None, &message.as_ref().into());
+            assert_eq!(ctx.message_id(), &expected_message_id, "Message ID should have been generated using default message ID function");
+        });
+        assert_eq!(service.fallbacks(), 1);
+    }
+}
+
+/// The failures of the topic's custom message ID function should be reported to the publisher.
+#[test]
+fn failing_custom_message_id_fn_is_reported_on_publish() {
+    //// Given
+    let mut service = new_test_service();
+
+    let panicking_topic = new_test_topic();
+    let empty_topic = new_test_topic();
+    testlib::service::inject_events(
+        &mut service,
+        itertools::chain!(
+            new_subscription_seq(
+                panicking_topic.clone(),
+                Some(Rc::new(panicking_message_id_fn))
+            ),
+            new_subscription_seq(empty_topic.clone(), Some(Rc::new(empty_message_id_fn)))
+        ),
+    );
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    let panicking_result = service.try_message_id(
+        None,
+        &Message::new(panicking_topic, b"test-payload".to_vec()),
+    );
+    let empty_result =
+        service.try_message_id(None, &Message::new(empty_topic, b"test-payload".to_vec()));
+
+    //// Then
+    assert_eq!(panicking_result, Err(MessageIdFnError::Panicked));
+    assert_eq!(empty_result, Err(MessageIdFnError::Empty));
+    assert_eq!(service.fallbacks(), 0, "Publish failures are not fallbacks");
+}
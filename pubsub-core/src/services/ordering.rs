@@ -0,0 +1,7 @@
+pub use events::{ServiceIn as OrderingInEvent, ServiceOut as OrderingOutEvent};
+pub use service::OrderingService;
+
+mod events;
+mod service;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,58 @@
+use std::ops::Range;
+
+use libp2p::identity::PeerId;
+
+use crate::dispatch::Consumes;
+use crate::lifecycle::ReceivedMessageCtx;
+use crate::services::subscriptions::SubscriptionsOutEvent;
+use crate::topic::TopicHash;
+
+/// Ordering service input event.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum ServiceIn {
+    /// A topic with ordered delivery was subscribed.
+    OrderedTopicSubscribed(TopicHash),
+    /// A topic was unsubscribed.
+    TopicUnsubscribed(TopicHash),
+    /// A message was received from a remote peer.
+    MessageReceived(ReceivedMessageCtx),
+}
+
+/// Ordering service output event.
+#[derive(Debug, Clone)]
+pub enum ServiceOut {
+    /// A received message is released for delivery, in its author sequence number order.
+    MessageReleased(ReceivedMessageCtx),
+    /// The held back messages of an author were released skipping the missing sequence numbers.
+    GapSkipped {
+        /// The messages topic.
+        topic: TopicHash,
+        /// The messages author.
+        source: PeerId,
+        /// The missing sequence numbers.
+        missing: Range<u64>,
+    },
+}
+
+impl Consumes<SubscriptionsOutEvent> for ServiceIn {
+    fn consume(ev: &SubscriptionsOutEvent) -> Option<Self> {
+        match ev {
+            SubscriptionsOutEvent::Subscribed(sub) if sub.ordered_delivery => {
+                Some(ServiceIn::OrderedTopicSubscribed(sub.topic.clone()))
+            }
+            SubscriptionsOutEvent::Unsubscribed { topic, .. } => {
+                Some(ServiceIn::TopicUnsubscribed(topic.clone()))
+            }
+            SubscriptionsOutEvent::Subscribed(_)
+            | SubscriptionsOutEvent::PeerSubscribed { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribed { .. }
+            | SubscriptionsOutEvent::PeerSubscribedMany { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribedMany { .. }
+            | SubscriptionsOutEvent::KnownPeerSubscribed { .. }
+            | SubscriptionsOutEvent::KnownPeerForgotten(_)
+            | SubscriptionsOutEvent::PeerFlapping { .. }
+            | SubscriptionsOutEvent::SendSubscriptions { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_common::service::{InCtx, OutCtx, PollCtx, Service};
+
+use crate::framing::Message;
+use crate::lifecycle::ReceivedMessageCtx;
+use crate::topic::TopicHash;
+
+use super::events::{ServiceIn, ServiceOut};
+
+/// The messages of an author on an ordered topic.
+struct Stream {
+    /// The sequence number of the next message to release.
+    next_seqno: u64,
+    /// The held back messages, by sequence number.
+    held: BTreeMap<u64, ReceivedMessageCtx>,
+    /// The held back messages release timer. Started when a message is held back, and stopped
+    /// once all the held back messages are released.
+    delay: Option<Delay>,
+}
+
+impl Stream {
+    /// Release the held back messages following the last released message.
+    fn release_consecutive(&mut self, released: &mut Vec<ServiceOut>) {
+        while let Some(held) = self.held.remove(&self.next_seqno) {
+            released.push(ServiceOut::MessageReleased(held));
+            self.next_seqno = self.next_seqno.saturating_add(1);
+        }
+    }
+
+    /// Skip the missing sequence numbers before the first held back message.
+    ///
+    /// Returns the skipped sequence numbers, if any message is held back.
+    fn skip_gap(&mut self) -> Option<Range<u64>> {
+        let (&first, _) = self.held.first_key_value()?;
+        let missing = self.next_seqno..first;
+        self.next_seqno = first;
+        Some(missing)
+    }
+}
+
+/// The ordering service releases the messages received on the topics subscribed with ordered
+/// delivery (see [`SubscriptionBuilder::ordered_delivery`](crate::SubscriptionBuilder::ordered_delivery))
+/// in their author sequence number order.
+///
+/// A received message whose sequence number indicates a gap relative to the last released message
+/// of its author is held back until the missing messages are received, for up to the maximum
+/// ordering delay. Once the delay expires, the held back messages are released in sequence number
+/// order, skipping the missing ones. The first message received from an author is released
+/// immediately, and the messages older than the last released message of their author are dropped.
+///
+/// The messages without an author, or without a 8-byte big-endian sequence number, bypass the
+/// ordering. The number of held back messages per topic and author is bounded: once the limit is
+/// reached, the held back messages are released as if the delay expired.
+pub struct OrderingService {
+    /// The maximum time a message is held back waiting for the missing messages.
+    max_delay: Duration,
+
+    /// The maximum number of held back messages per topic and author.
+    max_held_messages: usize,
+
+    /// The topics subscribed with ordered delivery.
+    ordered_topics: HashSet<TopicHash>,
+
+    /// The ordered topics messages, by topic and author.
+    streams: HashMap<(TopicHash, PeerId), Stream>,
+}
+
+impl Default for OrderingService {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), 64)
+    }
+}
+
+impl OrderingService {
+    /// Creates a new ordering service.
+    pub fn new(max_delay: Duration, max_held_messages: usize) -> Self {
+        Self {
+            max_delay,
+            max_held_messages,
+            ordered_topics: Default::default(),
+            streams: Default::default(),
+        }
+    }
+
+    /// Get the number of held back messages.
+    #[cfg(test)]
+    pub fn held_messages_count(&self) -> usize {
+        self.streams.values().map(|stream| stream.held.len()).sum()
+    }
+
+    /// Process a received message.
+    ///
+    /// Returns the released messages and skipped gaps.
+    fn on_message_received(&mut self, ctx: ReceivedMessageCtx) -> Vec<ServiceOut> {
+        let (source, seqno) = match (ctx.message().author(), seqno(ctx.message())) {
+            (Some(source), Some(seqno)) if self.ordered_topics.contains(ctx.topic()) => {
+                (source, seqno)
+            }
+            _ => return vec![ServiceOut::MessageReleased(ctx)],
+        };
+
+        let topic = ctx.topic().clone();
+        let Some(stream) = self.streams.get_mut(&(topic.clone(), source)) else {
+            // The first message received from the author.
+            self.streams.insert(
+                (topic, source),
+                Stream {
+                    next_seqno: seqno.saturating_add(1),
+                    held: Default::default(),
+                    delay: None,
+                },
+            );
+            return vec![ServiceOut::MessageReleased(ctx)];
+        };
+
+        if seqno < stream.next_seqno {
+            tracing::debug!(%source, seqno, "Message older than the last released, dropping");
+            return Vec::new();
+        }
+
+        stream.held.insert(seqno, ctx);
+
+        let mut output = Vec::new();
+        stream.release_consecutive(&mut output);
+
+        // Release the held back messages exceeding the limit, skipping the missing ones.
+        while stream.held.len() > self.max_held_messages {
+            if let Some(missing) = stream.skip_gap() {
+                output.push(ServiceOut::GapSkipped {
+                    topic: topic.clone(),
+                    source,
+                    missing,
+                });
+            }
+            stream.release_consecutive(&mut output);
+        }
+
+        if stream.held.is_empty() {
+            stream.delay = None;
+        } else if stream.delay.is_none() {
+            stream.delay = Some(Delay::new(self.max_delay));
+        }
+
+        output
+    }
+
+    /// Stop ordering the topic messages, discarding the held back messages.
+    fn on_topic_unsubscribed(&mut self, topic: &TopicHash) {
+        self.ordered_topics.remove(topic);
+        self.streams
+            .retain(|(stream_topic, _), _| stream_topic != topic);
+    }
+}
+
+/// Decode the message sequence number as a 8-byte big-endian integer.
+fn seqno(message: &Message) -> Option<u64> {
+    let seqno = message.seqno()?;
+    let bytes = <[u8; 8]>::try_from(seqno.as_ref()).ok()?;
+    Some(u64::from_be_bytes(bytes))
+}
+
+impl Service for OrderingService {
+    type InEvent = ServiceIn;
+    type OutEvent = ServiceOut;
+
+    fn poll<'a>(
+        &mut self,
+        svc_cx: impl PollCtx<'a, Self::InEvent, Self::OutEvent>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::OutEvent> {
+        let (mut in_cx, mut out_cx) = svc_cx.split();
+
+        // Process the incoming events.
+        while let Some(ev) = in_cx.pop_next() {
+            match ev {
+                ServiceIn::OrderedTopicSubscribed(topic) => {
+                    self.ordered_topics.insert(topic);
+                }
+                ServiceIn::TopicUnsubscribed(topic) => {
+                    self.on_topic_unsubscribed(&topic);
+                }
+                ServiceIn::MessageReceived(ctx) => {
+                    let output = self.on_message_received(ctx);
+                    out_cx.emit_batch(output);
+                }
+            }
+        }
+
+        // Poll the streams release timers and release the expired streams held back messages.
+        for ((topic, source), stream) in self.streams.iter_mut() {
+            let expired = match stream.delay.as_mut() {
+                Some(delay) => delay.poll_unpin(cx).is_ready(),
+                None => false,
+            };
+            if !expired {
+                continue;
+            }
+
+            tracing::debug!(%source, held = stream.held.len(), "Ordering delay expired");
+
+            let mut output = Vec::new();
+            while let Some(missing) = stream.skip_gap() {
+                output.push(ServiceOut::GapSkipped {
+                    topic: topic.clone(),
+                    source: *source,
+                    missing,
+                });
+                stream.release_consecutive(&mut output);
+            }
+            stream.delay = None;
+
+            out_cx.emit_batch(output);
+        }
+
+        Poll::Pending
+    }
+}
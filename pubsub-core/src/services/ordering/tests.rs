@@ -0,0 +1,255 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::identity::PeerId;
+use rand::Rng;
+
+use libp2p_pubsub_common::service::BufferedContext;
+use testlib;
+use testlib::service::noop_context;
+
+use crate::framing::Message as FrameMessage;
+use crate::lifecycle::ReceivedMessageCtx;
+use crate::message_id::MessageId;
+use crate::services::ordering::{OrderingInEvent, OrderingOutEvent, OrderingService};
+use crate::topic::{IdentityHash, Topic, TopicHash};
+
+/// Create a new random test topic.
+fn new_test_topic() -> TopicHash {
+    Topic::<IdentityHash>::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+    .hash()
+}
+
+/// Create a new random peer ID.
+fn new_test_peer_id() -> PeerId {
+    PeerId::random()
+}
+
+/// Create a test `OrderingService` ordering the given topic.
+fn new_test_service(
+    topic: &TopicHash,
+    max_delay: Duration,
+    max_held_messages: usize,
+) -> BufferedContext<OrderingService> {
+    let mut service = BufferedContext::new(OrderingService::new(max_delay, max_held_messages));
+    testlib::service::inject_events(
+        &mut service,
+        [OrderingInEvent::OrderedTopicSubscribed(topic.clone())],
+    );
+    testlib::service::poll(&mut service, &mut noop_context());
+    service
+}
+
+/// Create a message received event of the given author and sequence number.
+fn new_message_seq(topic: &TopicHash, source: PeerId, seqno: u64) -> OrderingInEvent {
+    let message = FrameMessage::new_with_seq_no_and_from(
+        topic.clone(),
+        format!("message-{seqno}").into_bytes(),
+        seqno.to_be_bytes(),
+        source,
+    );
+    let mut ctx = ReceivedMessageCtx::new(source, message);
+    ctx.set_message_id(MessageId::new(format!("{source}-{seqno}").into_bytes()));
+    OrderingInEvent::MessageReceived(ctx)
+}
+
+/// Get the released messages author and sequence number, and the skipped gaps.
+fn released_seqnos(events: &[OrderingOutEvent]) -> Vec<(PeerId, u64)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            OrderingOutEvent::MessageReleased(ctx) => {
+                let message = ctx.message();
+                let seqno = message.seqno().expect("sequence number present");
+                let seqno = u64::from_be_bytes(seqno.as_ref().try_into().expect("8-byte seqno"));
+                Some((message.author().expect("author present"), seqno))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn out_of_order_messages_are_released_in_order() {
+    //// Given
+    let topic = new_test_topic();
+    let source = new_test_peer_id();
+    let mut service = new_test_service(&topic, Duration::from_secs(60), 64);
+
+    let input_events = [
+        new_message_seq(&topic, source, 1),
+        new_message_seq(&topic, source, 3),
+        new_message_seq(&topic, source, 4),
+        new_message_seq(&topic, source, 2),
+    ];
+
+    //// When
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        released_seqnos(&output_events),
+        [(source, 1), (source, 2), (source, 3), (source, 4)]
+    );
+    assert_eq!(service.held_messages_count(), 0);
+}
+
+#[tokio::test]
+async fn held_messages_are_released_skipping_the_gap_after_the_delay() {
+    //// Given
+    let topic = new_test_topic();
+    let source = new_test_peer_id();
+    let mut service = new_test_service(&topic, Duration::from_millis(50), 64);
+
+    testlib::service::inject_events(
+        &mut service,
+        [
+            new_message_seq(&topic, source, 1),
+            new_message_seq(&topic, source, 4),
+            new_message_seq(&topic, source, 5),
+        ],
+    );
+    let events_before = testlib::service::async_collect_events(&mut service).await;
+
+    //// When
+    // Wait for the ordering delay to elapse
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    let events_after = testlib::service::async_collect_events(&mut service).await;
+
+    // A late message is older than the last released message.
+    testlib::service::inject_events(&mut service, [new_message_seq(&topic, source, 2)]);
+    let late_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert_eq!(released_seqnos(&events_before), [(source, 1)]);
+
+    assert_eq!(events_after.len(), 3, "Only 3 events should be emitted");
+    assert_matches!(&events_after[0], OrderingOutEvent::GapSkipped { topic: gap_topic, source: gap_source, missing } => {
+        assert_eq!(gap_topic, &topic);
+        assert_eq!(gap_source, &source);
+        assert_eq!(missing, &(2..4));
+    });
+    assert_eq!(released_seqnos(&events_after), [(source, 4), (source, 5)]);
+
+    assert!(late_events.is_empty(), "Late message should be dropped");
+}
+
+#[test]
+fn interleaved_sources_are_ordered_independently() {
+    //// Given
+    let topic = new_test_topic();
+    let source_a = new_test_peer_id();
+    let source_b = new_test_peer_id();
+    let mut service = new_test_service(&topic, Duration::from_secs(60), 64);
+
+    let input_events = [
+        new_message_seq(&topic, source_a, 10),
+        new_message_seq(&topic, source_b, 20),
+        new_message_seq(&topic, source_a, 12),
+        new_message_seq(&topic, source_b, 21),
+        new_message_seq(&topic, source_a, 11),
+    ];
+
+    //// When
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        released_seqnos(&output_events),
+        [
+            (source_a, 10),
+            (source_b, 20),
+            (source_b, 21),
+            (source_a, 11),
+            (source_a, 12),
+        ]
+    );
+}
+
+#[test]
+fn messages_exceeding_the_held_limit_release_the_held_messages() {
+    //// Given
+    let topic = new_test_topic();
+    let source = new_test_peer_id();
+    let mut service = new_test_service(&topic, Duration::from_secs(60), 2);
+
+    let input_events = [
+        new_message_seq(&topic, source, 1),
+        new_message_seq(&topic, source, 3),
+        new_message_seq(&topic, source, 5),
+        new_message_seq(&topic, source, 6),
+    ];
+
+    //// When
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(released_seqnos(&output_events), [(source, 1), (source, 3)]);
+    assert_matches!(&output_events[1], OrderingOutEvent::GapSkipped { missing, .. } => {
+        assert_eq!(missing, &(2..3));
+    });
+    assert_eq!(service.held_messages_count(), 2);
+}
+
+#[test]
+fn messages_without_sequence_number_bypass_the_ordering() {
+    //// Given
+    let topic = new_test_topic();
+    let source = new_test_peer_id();
+    let mut service = new_test_service(&topic, Duration::from_secs(60), 64);
+
+    let mut anonymous_message = ReceivedMessageCtx::new(
+        source,
+        FrameMessage::new(topic.clone(), b"anonymous".to_vec()),
+    );
+    anonymous_message.set_message_id(MessageId::new(b"anonymous".to_vec()));
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [
+            new_message_seq(&topic, source, 1),
+            new_message_seq(&topic, source, 3),
+            OrderingInEvent::MessageReceived(anonymous_message),
+        ],
+    );
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events.len(), 2, "Only 2 events should be emitted");
+    assert_matches!(&output_events[1], OrderingOutEvent::MessageReleased(ctx) => {
+        assert_eq!(ctx.message().data().as_ref(), b"anonymous");
+    });
+    assert_eq!(service.held_messages_count(), 1);
+}
+
+#[test]
+fn unordered_topic_messages_are_released_immediately() {
+    //// Given
+    let ordered_topic = new_test_topic();
+    let topic = new_test_topic();
+    let source = new_test_peer_id();
+    let mut service = new_test_service(&ordered_topic, Duration::from_secs(60), 64);
+
+    let input_events = [
+        new_message_seq(&topic, source, 1),
+        new_message_seq(&topic, source, 3),
+        new_message_seq(&topic, source, 2),
+    ];
+
+    //// When
+    testlib::service::inject_events(&mut service, input_events);
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        released_seqnos(&output_events),
+        [(source, 1), (source, 3), (source, 2)]
+    );
+}
@@ -0,0 +1,7 @@
+pub use events::{ServiceIn as ReassemblyInEvent, ServiceOut as ReassemblyOutEvent};
+pub use service::ReassemblyService;
+
+mod events;
+mod service;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,34 @@
+use std::rc::Rc;
+
+use libp2p::identity::PeerId;
+
+use crate::chunk::{ChunkHeader, TransferId};
+use crate::framing::Message;
+
+/// Reassembly service input event.
+#[derive(Debug, Clone)]
+pub enum ServiceIn {
+    /// A chunk message was received from a remote peer.
+    ChunkReceived {
+        /// The propagation node peer id.
+        src: PeerId,
+        /// The chunk message.
+        message: Rc<Message>,
+        /// The chunk message decoded header.
+        header: ChunkHeader,
+    },
+}
+
+/// Reassembly service output event.
+#[derive(Debug, Clone)]
+pub enum ServiceOut {
+    /// All the chunks of a transfer were received and the payload reassembled.
+    TransferCompleted {
+        /// The propagation node peer id of the last received chunk.
+        src: PeerId,
+        /// The reassembled message.
+        message: Message,
+        /// The transfer id.
+        transfer_id: TransferId,
+    },
+}
@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, HashMap};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_common::service::{InCtx, OutCtx, PollCtx, Service};
+
+use crate::chunk::{ChunkHeader, TransferId, CHUNK_HEADER_LEN};
+use crate::framing::Message;
+use crate::topic::TopicHash;
+
+use super::events::{ServiceIn, ServiceOut};
+
+/// A chunked transfer in progress.
+struct Transfer {
+    /// The transfer topic.
+    topic: TopicHash,
+    /// The transfer author, if any.
+    author: Option<PeerId>,
+    /// The total number of chunks.
+    total: u32,
+    /// The full payload length in bytes.
+    payload_len: u64,
+    /// The received chunks, by chunk index.
+    chunks: BTreeMap<u32, Bytes>,
+    /// The number of payload bytes received so far.
+    received_len: u64,
+    /// The transfer timeout timer.
+    timeout: Delay,
+}
+
+/// The reassembly service buffers the chunks of the chunked transfers and, once all the chunks of
+/// a transfer are received, reassembles the payload.
+///
+/// The number of concurrent transfers and the size of each transfer are bounded. Transfers not
+/// completed within the transfer timeout are discarded.
+pub struct ReassemblyService {
+    /// The maximum number of concurrent transfers.
+    max_concurrent_transfers: usize,
+
+    /// The maximum full payload size of a transfer.
+    max_transfer_size: usize,
+
+    /// The time to wait for all the chunks of a transfer to be received.
+    transfer_timeout: Duration,
+
+    /// The transfers in progress.
+    transfers: HashMap<TransferId, Transfer>,
+}
+
+impl Default for ReassemblyService {
+    fn default() -> Self {
+        Self::new(8, 16 * 1024 * 1024, Duration::from_secs(60))
+    }
+}
+
+impl ReassemblyService {
+    /// Creates a new reassembly service.
+    pub fn new(
+        max_concurrent_transfers: usize,
+        max_transfer_size: usize,
+        transfer_timeout: Duration,
+    ) -> Self {
+        Self {
+            max_concurrent_transfers,
+            max_transfer_size,
+            transfer_timeout,
+            transfers: Default::default(),
+        }
+    }
+
+    /// Get the number of transfers in progress.
+    #[cfg(test)]
+    pub fn transfers_count(&self) -> usize {
+        self.transfers.len()
+    }
+
+    /// Buffer a received chunk.
+    ///
+    /// Returns the reassembled message if the chunk completes its transfer.
+    fn on_chunk_received(&mut self, message: &Message, header: ChunkHeader) -> Option<Message> {
+        let transfer_id = header.transfer_id;
+
+        if header.payload_len > self.max_transfer_size as u64 {
+            tracing::debug!(
+                payload_len = header.payload_len,
+                "Transfer exceeds the maximum transfer size, dropping chunk"
+            );
+            return None;
+        }
+
+        // Every chunk, but the single chunk of an empty payload, carries at least one byte.
+        if u64::from(header.total) > header.payload_len.max(1) {
+            tracing::debug!("Invalid transfer chunks count, dropping chunk");
+            return None;
+        }
+
+        if !self.transfers.contains_key(&transfer_id) {
+            if self.transfers.len() >= self.max_concurrent_transfers {
+                tracing::debug!("Maximum concurrent transfers reached, dropping chunk");
+                return None;
+            }
+
+            self.transfers.insert(
+                transfer_id,
+                Transfer {
+                    topic: message.topic(),
+                    author: message.author(),
+                    total: header.total,
+                    payload_len: header.payload_len,
+                    chunks: Default::default(),
+                    received_len: 0,
+                    timeout: Delay::new(self.transfer_timeout),
+                },
+            );
+        }
+
+        let transfer = self.transfers.get_mut(&transfer_id)?;
+
+        // The chunks of a transfer must agree on the transfer layout.
+        if transfer.total != header.total || transfer.payload_len != header.payload_len {
+            tracing::debug!("Chunk does not match the transfer layout, dropping chunk");
+            return None;
+        }
+
+        // Duplicate chunks are ignored.
+        if transfer.chunks.contains_key(&header.index) {
+            return None;
+        }
+
+        let chunk = message.data().slice(CHUNK_HEADER_LEN..);
+        transfer.received_len += chunk.len() as u64;
+        transfer.chunks.insert(header.index, chunk);
+
+        if transfer.received_len > transfer.payload_len {
+            tracing::debug!("Transfer exceeds its declared size, discarding transfer");
+            self.transfers.remove(&transfer_id);
+            return None;
+        }
+
+        if transfer.chunks.len() < transfer.total as usize {
+            return None;
+        }
+
+        // All the chunks were received, reassemble the payload.
+        let transfer = self.transfers.remove(&transfer_id)?;
+
+        let mut payload = Vec::with_capacity(transfer.payload_len as usize);
+        for chunk in transfer.chunks.values() {
+            payload.extend_from_slice(chunk);
+        }
+
+        if payload.len() as u64 != transfer.payload_len
+            || crate::chunk::transfer_id(&payload) != transfer_id
+        {
+            tracing::debug!("Reassembled payload does not match the transfer id, discarding");
+            return None;
+        }
+
+        let mut message = Message::new(transfer.topic, payload);
+        message.set_author(transfer.author);
+        Some(message)
+    }
+}
+
+impl Service for ReassemblyService {
+    type InEvent = ServiceIn;
+    type OutEvent = ServiceOut;
+
+    fn poll<'a>(
+        &mut self,
+        svc_cx: impl PollCtx<'a, Self::InEvent, Self::OutEvent>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::OutEvent> {
+        let (mut in_cx, mut out_cx) = svc_cx.split();
+
+        // Process the incoming events.
+        while let Some(ev) = in_cx.pop_next() {
+            match ev {
+                ServiceIn::ChunkReceived {
+                    src,
+                    message,
+                    header,
+                } => {
+                    if let Some(message) = self.on_chunk_received(&message, header) {
+                        out_cx.emit(ServiceOut::TransferCompleted {
+                            src,
+                            message,
+                            transfer_id: header.transfer_id,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Poll the transfers timeout timers and discard the expired transfers.
+        self.transfers.retain(|_, transfer| {
+            let expired = transfer.timeout.poll_unpin(cx).is_ready();
+            if expired {
+                tracing::debug!(
+                    received = transfer.chunks.len(),
+                    total = transfer.total,
+                    "Transfer timed out, discarding"
+                );
+            }
+            !expired
+        });
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,212 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::identity::PeerId;
+use rand::Rng;
+
+use libp2p_pubsub_common::service::BufferedContext;
+use testlib;
+use testlib::service::noop_context;
+
+use crate::chunk::{split_into_chunks, transfer_id, ChunkHeader};
+use crate::framing::Message as FrameMessage;
+use crate::services::reassembly::{ReassemblyInEvent, ReassemblyOutEvent, ReassemblyService};
+use crate::topic::{IdentityHash, Topic};
+
+/// Create a new random test topic.
+fn new_test_topic() -> Topic<IdentityHash> {
+    Topic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Create a new random peer ID.
+fn new_test_peer_id() -> PeerId {
+    PeerId::random()
+}
+
+/// Create a new random payload of the given size.
+fn new_test_payload(size: usize) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    (0..size).map(|_| rng.gen()).collect()
+}
+
+/// Create a test `ReassemblyService` with the given limits.
+fn new_test_service(
+    max_concurrent_transfers: usize,
+    max_transfer_size: usize,
+    transfer_timeout: Duration,
+) -> BufferedContext<ReassemblyService> {
+    BufferedContext::new(ReassemblyService::new(
+        max_concurrent_transfers,
+        max_transfer_size,
+        transfer_timeout,
+    ))
+}
+
+/// Create the chunk received events sequence of the payload split into chunks.
+fn new_chunks_seq(
+    src: PeerId,
+    topic: &Topic<IdentityHash>,
+    payload: &[u8],
+    chunk_size: usize,
+) -> Vec<ReassemblyInEvent> {
+    split_into_chunks(payload, chunk_size)
+        .expect("valid chunk size")
+        .into_iter()
+        .map(|chunk| {
+            let (header, _) = ChunkHeader::decode(&chunk).expect("valid chunk");
+            ReassemblyInEvent::ChunkReceived {
+                src,
+                message: Rc::new(FrameMessage::new(topic.hash(), chunk)),
+                header,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn complete_transfer_is_reassembled() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<ReassemblyService>();
+
+    let src = new_test_peer_id();
+    let topic = new_test_topic();
+    let payload = new_test_payload(1000);
+
+    //// When
+    let input_events = new_chunks_seq(src, &topic, &payload, 300);
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&output_events[0], ReassemblyOutEvent::TransferCompleted { src: event_src, message, transfer_id: id } => {
+        assert_eq!(event_src, &src);
+        assert_eq!(message.topic(), topic.hash());
+        assert_eq!(message.data(), payload);
+        assert_eq!(id, &transfer_id(&payload));
+    });
+    assert_eq!(
+        service.transfers_count(),
+        0,
+        "No transfers should be pending"
+    );
+}
+
+#[test]
+fn out_of_order_and_duplicate_chunks_are_reassembled() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<ReassemblyService>();
+
+    let src = new_test_peer_id();
+    let topic = new_test_topic();
+    let payload = new_test_payload(1000);
+
+    let mut chunks = new_chunks_seq(src, &topic, &payload, 300);
+    chunks.reverse();
+
+    //// When
+    let input_events = [chunks[0].clone(), chunks[0].clone()]
+        .into_iter()
+        .chain(chunks[1..].iter().cloned());
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&output_events[0], ReassemblyOutEvent::TransferCompleted { message, .. } => {
+        assert_eq!(message.data(), payload);
+    });
+}
+
+#[tokio::test]
+async fn incomplete_transfer_is_discarded_after_timeout() {
+    //// Given
+    let mut service = new_test_service(8, 1024 * 1024, Duration::from_millis(50));
+
+    let src = new_test_peer_id();
+    let topic = new_test_topic();
+    let payload = new_test_payload(1000);
+
+    let mut chunks = new_chunks_seq(src, &topic, &payload, 300);
+    let last_chunk = chunks.pop().expect("at least one chunk");
+
+    testlib::service::inject_events(&mut service, chunks);
+    testlib::service::async_poll(&mut service).await;
+
+    let transfers_before = service.transfers_count();
+
+    //// When
+    // Wait for the transfer timeout to elapse
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    testlib::service::async_poll(&mut service).await;
+
+    let transfers_after = service.transfers_count();
+
+    // A late chunk starts a new transfer that cannot be completed.
+    testlib::service::inject_events(&mut service, [last_chunk]);
+    let output_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert_eq!(transfers_before, 1, "The transfer should be pending");
+    assert_eq!(transfers_after, 0, "The transfer should be discarded");
+    assert!(output_events.is_empty(), "No events should be emitted");
+}
+
+#[test]
+fn oversize_transfer_is_rejected() {
+    //// Given
+    let mut service = new_test_service(8, 512, Duration::from_secs(60));
+
+    let src = new_test_peer_id();
+    let topic = new_test_topic();
+    let payload = new_test_payload(1000);
+
+    //// When
+    let input_events = new_chunks_seq(src, &topic, &payload, 300);
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(output_events.is_empty(), "No events should be emitted");
+    assert_eq!(
+        service.transfers_count(),
+        0,
+        "No transfers should be pending"
+    );
+}
+
+#[test]
+fn new_transfers_are_rejected_above_max_concurrent_transfers() {
+    //// Given
+    let mut service = new_test_service(1, 1024 * 1024, Duration::from_secs(60));
+
+    let src = new_test_peer_id();
+    let topic = new_test_topic();
+    let payload_a = new_test_payload(1000);
+    let payload_b = new_test_payload(1000);
+
+    let mut chunks_a = new_chunks_seq(src, &topic, &payload_a, 300);
+    let last_chunk_a = chunks_a.pop().expect("at least one chunk");
+
+    //// When
+    let input_events = chunks_a
+        .into_iter()
+        .chain(new_chunks_seq(src, &topic, &payload_b, 300))
+        .chain([last_chunk_a]);
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&output_events[0], ReassemblyOutEvent::TransferCompleted { message, .. } => {
+        assert_eq!(message.data(), payload_a);
+    });
+}
@@ -0,0 +1,9 @@
+pub use events::{
+    ServiceIn as SubscriptionAnnounceInEvent, ServiceOut as SubscriptionAnnounceOutEvent,
+};
+pub use service::SubscriptionAnnounceService;
+
+mod events;
+mod service;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,73 @@
+use libp2p::identity::PeerId;
+
+use crate::dispatch::Consumes;
+use crate::services::connections::ConnectionsOutEvent;
+use crate::services::subscriptions::SubscriptionsOutEvent;
+use crate::topic::TopicHash;
+
+/// Subscription announce service input event.
+#[derive(Debug, Clone)]
+pub enum ServiceIn {
+    /// Announce the local node subscriptions to a peer.
+    ///
+    /// An announcement already in flight to the peer is replaced.
+    AnnounceRequested {
+        /// The destination peer.
+        dest: PeerId,
+        /// The announced topics.
+        topics: Vec<TopicHash>,
+    },
+    /// The local node subscribed to a topic.
+    TopicSubscribed(TopicHash),
+    /// The local node unsubscribed from a topic.
+    TopicUnsubscribed(TopicHash),
+    /// All connections to a peer were closed.
+    PeerDisconnected(PeerId),
+}
+
+/// Subscription announce service output event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceOut {
+    /// Send a batch of the announced subscriptions to a peer.
+    SendSubscriptions {
+        /// The destination peer.
+        dest: PeerId,
+        /// The batch topics.
+        topics: Vec<TopicHash>,
+    },
+}
+
+impl Consumes<ConnectionsOutEvent> for ServiceIn {
+    fn consume(ev: &ConnectionsOutEvent) -> Option<Self> {
+        match ev {
+            ConnectionsOutEvent::NewPeerConnected { .. } => None,
+            ConnectionsOutEvent::PeerDisconnected(peer) => Some(ServiceIn::PeerDisconnected(*peer)),
+        }
+    }
+}
+
+impl Consumes<SubscriptionsOutEvent> for ServiceIn {
+    fn consume(ev: &SubscriptionsOutEvent) -> Option<Self> {
+        match ev {
+            SubscriptionsOutEvent::Subscribed(sub) => {
+                Some(ServiceIn::TopicSubscribed(sub.topic.clone()))
+            }
+            SubscriptionsOutEvent::Unsubscribed { topic, .. } => {
+                Some(ServiceIn::TopicUnsubscribed(topic.clone()))
+            }
+            SubscriptionsOutEvent::SendSubscriptions { dest, topics } => {
+                Some(ServiceIn::AnnounceRequested {
+                    dest: *dest,
+                    topics: topics.clone(),
+                })
+            }
+            SubscriptionsOutEvent::PeerSubscribed { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribed { .. }
+            | SubscriptionsOutEvent::PeerSubscribedMany { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribedMany { .. }
+            | SubscriptionsOutEvent::KnownPeerSubscribed { .. }
+            | SubscriptionsOutEvent::KnownPeerForgotten(_)
+            | SubscriptionsOutEvent::PeerFlapping { .. } => None,
+        }
+    }
+}
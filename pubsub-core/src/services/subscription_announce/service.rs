@@ -0,0 +1,150 @@
+use std::collections::{HashMap, VecDeque};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_common::service::{InCtx, OutCtx, PollCtx, Service};
+
+use crate::topic::TopicHash;
+
+use super::events::{ServiceIn, ServiceOut};
+
+/// An in-flight subscriptions announcement to a peer.
+struct Announcement {
+    /// The topics not announced yet.
+    remaining: VecDeque<TopicHash>,
+    /// The number of topics announced.
+    sent: usize,
+    /// The next batch timer. `None` if the next batch is sent right away.
+    next_batch: Option<Delay>,
+}
+
+/// The subscription announce service paces the announcement of the local node subscriptions to
+/// the newly connected peers.
+///
+/// The announced topics are split into batches of at most the batch size, the first batch is
+/// sent right away, and the following batches are spaced by the announcement interval. The
+/// topics subscribed while an announcement is in flight are appended to it, and the unsubscribed
+/// topics not announced yet are dropped from it. The announcement is aborted when the peer
+/// disconnects.
+pub struct SubscriptionAnnounceService {
+    /// The maximum number of topics per batch. If `None`, all the topics are sent at once.
+    batch_size: Option<usize>,
+
+    /// The time between two batches.
+    interval: Duration,
+
+    /// The in-flight announcements, by peer.
+    announcements: HashMap<PeerId, Announcement>,
+}
+
+impl Default for SubscriptionAnnounceService {
+    fn default() -> Self {
+        Self::new(None, Duration::from_millis(100))
+    }
+}
+
+impl SubscriptionAnnounceService {
+    /// Creates a new subscription announce service.
+    pub fn new(batch_size: Option<usize>, interval: Duration) -> Self {
+        Self {
+            batch_size: batch_size.map(|size| size.max(1)),
+            interval,
+            announcements: Default::default(),
+        }
+    }
+
+    /// Get the progress of the in-flight announcement to the given peer, as the number of topics
+    /// announced and the total number of topics to announce.
+    ///
+    /// Returns `None` if no announcement to the peer is in flight.
+    pub fn progress(&self, peer: &PeerId) -> Option<(usize, usize)> {
+        self.announcements
+            .get(peer)
+            .map(|ann| (ann.sent, ann.sent + ann.remaining.len()))
+    }
+}
+
+impl Service for SubscriptionAnnounceService {
+    type InEvent = ServiceIn;
+    type OutEvent = ServiceOut;
+
+    fn poll<'a>(
+        &mut self,
+        svc_cx: impl PollCtx<'a, Self::InEvent, Self::OutEvent>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::OutEvent> {
+        let (mut in_cx, mut out_cx) = svc_cx.split();
+
+        // Process the incoming events.
+        while let Some(ev) = in_cx.pop_next() {
+            match ev {
+                ServiceIn::AnnounceRequested { dest, topics } => {
+                    self.announcements.insert(
+                        dest,
+                        Announcement {
+                            remaining: topics.into(),
+                            sent: 0,
+                            next_batch: None,
+                        },
+                    );
+                }
+                ServiceIn::TopicSubscribed(topic) => {
+                    for ann in self.announcements.values_mut() {
+                        if !ann.remaining.contains(&topic) {
+                            ann.remaining.push_back(topic.clone());
+                        }
+                    }
+                }
+                ServiceIn::TopicUnsubscribed(topic) => {
+                    for ann in self.announcements.values_mut() {
+                        ann.remaining.retain(|remaining| remaining != &topic);
+                    }
+                }
+                ServiceIn::PeerDisconnected(peer) => {
+                    if self.announcements.remove(&peer).is_some() {
+                        tracing::debug!(%peer, "Subscriptions announcement aborted");
+                    }
+                }
+            }
+        }
+
+        // Send the due batches, and drop the completed announcements.
+        let batch_size = self.batch_size;
+        let interval = self.interval;
+        self.announcements.retain(|dest, ann| {
+            let due = match ann.next_batch.as_mut() {
+                Some(timer) => timer.poll_unpin(cx).is_ready(),
+                None => true,
+            };
+            if !due {
+                return true;
+            }
+
+            let len = batch_size.map_or(ann.remaining.len(), |size| {
+                size.min(ann.remaining.len())
+            });
+            let topics = ann.remaining.drain(..len).collect::<Vec<_>>();
+            ann.sent += topics.len();
+            out_cx.emit(ServiceOut::SendSubscriptions {
+                dest: *dest,
+                topics,
+            });
+
+            if ann.remaining.is_empty() {
+                return false;
+            }
+
+            tracing::trace!(%dest, sent = ann.sent, remaining = ann.remaining.len(), "Subscriptions batch sent");
+            let mut timer = Delay::new(interval);
+            let _ = timer.poll_unpin(cx);
+            ann.next_batch = Some(timer);
+            true
+        });
+
+        Poll::Pending
+    }
+}
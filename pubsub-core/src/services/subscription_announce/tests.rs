@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::identity::PeerId;
+use rand::random;
+
+use libp2p_pubsub_common::service::BufferedContext;
+use testlib;
+
+use crate::services::subscription_announce::{
+    SubscriptionAnnounceInEvent, SubscriptionAnnounceOutEvent, SubscriptionAnnounceService,
+};
+use crate::topic::TopicHash;
+
+/// Create a new random peer ID.
+fn new_test_peer_id() -> PeerId {
+    PeerId::random()
+}
+
+/// Create `count` new random test topics.
+fn new_test_topics(count: usize) -> Vec<TopicHash> {
+    (0..count)
+        .map(|_| TopicHash::from_raw(format!("/pubsub/2/it-pubsub-test-{}", random::<u32>())))
+        .collect()
+}
+
+/// Create a test `SubscriptionAnnounceService` with the given batch size and interval.
+fn new_test_service(
+    batch_size: Option<usize>,
+    interval: Duration,
+) -> BufferedContext<SubscriptionAnnounceService> {
+    BufferedContext::new(SubscriptionAnnounceService::new(batch_size, interval))
+}
+
+/// Get the topics of the batches sent to the given peer.
+fn sent_batches(events: Vec<SubscriptionAnnounceOutEvent>, peer: &PeerId) -> Vec<Vec<TopicHash>> {
+    events
+        .into_iter()
+        .map(|ev| {
+            assert_matches!(ev, SubscriptionAnnounceOutEvent::SendSubscriptions { dest, topics } => {
+                assert_eq!(&dest, peer);
+                topics
+            })
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn not_batched_announcement_is_sent_at_once() {
+    //// Given
+    let mut service = new_test_service(None, Duration::from_millis(50));
+    let peer = new_test_peer_id();
+    let topics = new_test_topics(5);
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [SubscriptionAnnounceInEvent::AnnounceRequested {
+            dest: peer,
+            topics: topics.clone(),
+        }],
+    );
+    let output_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert_eq!(sent_batches(output_events, &peer), [topics]);
+    assert_eq!(service.progress(&peer), None, "The announcement completed");
+}
+
+#[tokio::test]
+async fn announcement_batches_are_sized_and_spaced_by_the_interval() {
+    //// Given
+    let mut service = new_test_service(Some(2), Duration::from_millis(50));
+    let peer = new_test_peer_id();
+    let topics = new_test_topics(5);
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [SubscriptionAnnounceInEvent::AnnounceRequested {
+            dest: peer,
+            topics: topics.clone(),
+        }],
+    );
+    let first_events = testlib::service::async_collect_events(&mut service).await;
+    let first_progress = service.progress(&peer);
+
+    // Before the interval elapses
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let early_events = testlib::service::async_collect_events(&mut service).await;
+
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    let second_events = testlib::service::async_collect_events(&mut service).await;
+    let second_progress = service.progress(&peer);
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    let third_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert_eq!(sent_batches(first_events, &peer), [topics[0..2].to_vec()]);
+    assert_eq!(first_progress, Some((2, 5)));
+    assert!(early_events.is_empty(), "No batch before the interval");
+    assert_eq!(sent_batches(second_events, &peer), [topics[2..4].to_vec()]);
+    assert_eq!(second_progress, Some((4, 5)));
+    assert_eq!(sent_batches(third_events, &peer), [topics[4..].to_vec()]);
+    assert_eq!(service.progress(&peer), None, "The announcement completed");
+}
+
+#[tokio::test]
+async fn peer_disconnection_aborts_the_announcement() {
+    //// Given
+    let mut service = new_test_service(Some(2), Duration::from_millis(50));
+    let peer = new_test_peer_id();
+
+    testlib::service::inject_events(
+        &mut service,
+        [SubscriptionAnnounceInEvent::AnnounceRequested {
+            dest: peer,
+            topics: new_test_topics(5),
+        }],
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [SubscriptionAnnounceInEvent::PeerDisconnected(peer)],
+    );
+    testlib::service::async_poll(&mut service).await;
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    let output_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert_eq!(service.progress(&peer), None);
+    assert!(output_events.is_empty(), "No batch after the disconnection");
+}
+
+#[tokio::test]
+async fn topics_subscribed_during_the_announcement_are_appended() {
+    //// Given
+    let mut service = new_test_service(Some(2), Duration::from_millis(50));
+    let peer = new_test_peer_id();
+    let topics = new_test_topics(3);
+    let late_topic = new_test_topics(1).remove(0);
+    let unsubscribed_topic = topics[2].clone();
+
+    testlib::service::inject_events(
+        &mut service,
+        [SubscriptionAnnounceInEvent::AnnounceRequested {
+            dest: peer,
+            topics: topics.clone(),
+        }],
+    );
+    let first_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [
+            SubscriptionAnnounceInEvent::TopicSubscribed(late_topic.clone()),
+            SubscriptionAnnounceInEvent::TopicUnsubscribed(unsubscribed_topic),
+        ],
+    );
+    testlib::service::async_poll(&mut service).await;
+    let progress = service.progress(&peer);
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    let second_events = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert_eq!(sent_batches(first_events, &peer), [topics[0..2].to_vec()]);
+    assert_eq!(
+        progress,
+        Some((2, 3)),
+        "The late topic should be appended, and the unsubscribed one dropped"
+    );
+    assert_eq!(sent_batches(second_events, &peer), [vec![late_topic]]);
+    assert_eq!(service.progress(&peer), None, "The announcement completed");
+}
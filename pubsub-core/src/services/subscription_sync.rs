@@ -0,0 +1,7 @@
+pub use events::{ServiceIn as SubscriptionSyncInEvent, ServiceOut as SubscriptionSyncOutEvent};
+pub use service::SubscriptionSyncService;
+
+mod events;
+mod service;
+#[cfg(test)]
+mod tests;
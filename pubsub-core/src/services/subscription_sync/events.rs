@@ -0,0 +1,35 @@
+use crate::dispatch::Consumes;
+use crate::services::connections::ConnectionsOutEvent;
+use libp2p::identity::PeerId;
+
+/// Subscription sync service input event.
+#[derive(Debug, Clone)]
+pub enum ServiceIn {
+    /// The local node subscriptions were sent to a peer.
+    SyncSucceeded(PeerId),
+    /// The local node subscriptions could not be sent to a peer (e.g., the subscriptions frame
+    /// exceeds the maximum frame size, or the peer has no enabled connection).
+    SyncFailed(PeerId),
+    /// A new connection to a peer was established.
+    ConnectionEstablished(PeerId),
+    /// All connections to a peer were closed.
+    PeerDisconnected(PeerId),
+}
+
+/// Subscription sync service output event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceOut {
+    /// Send the local node subscriptions to the peer again.
+    RetrySync(PeerId),
+    /// The maximum number of subscription sync attempts to the peer was reached.
+    SyncFailed(PeerId),
+}
+
+impl Consumes<ConnectionsOutEvent> for ServiceIn {
+    fn consume(ev: &ConnectionsOutEvent) -> Option<Self> {
+        match ev {
+            ConnectionsOutEvent::NewPeerConnected { .. } => None,
+            ConnectionsOutEvent::PeerDisconnected(peer) => Some(ServiceIn::PeerDisconnected(*peer)),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_common::service::{InCtx, OutCtx, PollCtx, Service};
+
+use super::events::{ServiceIn, ServiceOut};
+
+/// A peer whose subscription sync failed.
+struct PendingSync {
+    /// The number of failed subscription sync attempts.
+    failures: usize,
+    /// The retry timer. `None` while a retry is in progress.
+    retry: Option<Delay>,
+}
+
+/// The subscription sync service keeps track of the peers the local node subscriptions could not
+/// be sent to, and schedules the subscription sync retries.
+///
+/// A failed subscription sync is retried after the retry interval, or as soon as a new connection
+/// to the peer is established. After the maximum number of failed attempts, the service gives up
+/// on the peer and notifies it. The pending retries are dropped when the peer disconnects.
+pub struct SubscriptionSyncService {
+    /// The maximum number of failed subscription sync attempts before giving up.
+    max_attempts: usize,
+
+    /// The time to wait before retrying a failed subscription sync.
+    retry_interval: Duration,
+
+    /// The peers with a failed subscription sync.
+    pending: HashMap<PeerId, PendingSync>,
+}
+
+impl Default for SubscriptionSyncService {
+    fn default() -> Self {
+        Self::new(3, Duration::from_secs(1))
+    }
+}
+
+impl SubscriptionSyncService {
+    /// Creates a new subscription sync service.
+    pub fn new(max_attempts: usize, retry_interval: Duration) -> Self {
+        Self {
+            max_attempts,
+            retry_interval,
+            pending: Default::default(),
+        }
+    }
+
+    /// Returns whether the subscription sync to the given peer is pending a retry.
+    #[cfg(test)]
+    pub fn is_pending(&self, peer: &PeerId) -> bool {
+        self.pending.contains_key(peer)
+    }
+
+    /// Register a failed subscription sync attempt to a peer.
+    ///
+    /// Returns `true` if the maximum number of failed attempts was reached.
+    fn register_failure(&mut self, peer: PeerId) -> bool {
+        let pending = self.pending.entry(peer).or_insert(PendingSync {
+            failures: 0,
+            retry: None,
+        });
+
+        pending.failures += 1;
+        if pending.failures >= self.max_attempts {
+            tracing::debug!(%peer, "Maximum subscription sync attempts reached");
+            self.pending.remove(&peer);
+            return true;
+        }
+
+        tracing::trace!(%peer, failures = pending.failures, "Subscription sync failed, retrying");
+        pending.retry = Some(Delay::new(self.retry_interval));
+        false
+    }
+}
+
+impl Service for SubscriptionSyncService {
+    type InEvent = ServiceIn;
+    type OutEvent = ServiceOut;
+
+    fn poll<'a>(
+        &mut self,
+        svc_cx: impl PollCtx<'a, Self::InEvent, Self::OutEvent>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::OutEvent> {
+        let (mut in_cx, mut out_cx) = svc_cx.split();
+
+        // Process the incoming events.
+        while let Some(ev) = in_cx.pop_next() {
+            match ev {
+                ServiceIn::SyncSucceeded(peer) => {
+                    self.pending.remove(&peer);
+                }
+                ServiceIn::SyncFailed(peer) => {
+                    if self.register_failure(peer) {
+                        out_cx.emit(ServiceOut::SyncFailed(peer));
+                    }
+                }
+                ServiceIn::ConnectionEstablished(peer) => {
+                    // Retry right away over the new connection.
+                    if let Some(pending) = self.pending.get_mut(&peer) {
+                        if pending.retry.take().is_some() {
+                            out_cx.emit(ServiceOut::RetrySync(peer));
+                        }
+                    }
+                }
+                ServiceIn::PeerDisconnected(peer) => {
+                    self.pending.remove(&peer);
+                }
+            }
+        }
+
+        // Poll the retry timers and retry the expired peers subscription sync.
+        for (peer, pending) in self.pending.iter_mut() {
+            let expired = match pending.retry.as_mut() {
+                Some(retry) => retry.poll_unpin(cx).is_ready(),
+                None => false,
+            };
+            if expired {
+                pending.retry = None;
+                out_cx.emit(ServiceOut::RetrySync(*peer));
+            }
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_common::service::BufferedContext;
+use testlib;
+use testlib::service::noop_context;
+
+use crate::services::subscription_sync::{
+    SubscriptionSyncInEvent, SubscriptionSyncOutEvent, SubscriptionSyncService,
+};
+
+/// Create a new random peer ID.
+fn new_test_peer_id() -> PeerId {
+    PeerId::random()
+}
+
+/// Create a test `SubscriptionSyncService` with the given maximum attempts and retry interval.
+fn new_test_service(
+    max_attempts: usize,
+    retry_interval: Duration,
+) -> BufferedContext<SubscriptionSyncService> {
+    BufferedContext::new(SubscriptionSyncService::new(max_attempts, retry_interval))
+}
+
+#[tokio::test]
+async fn failed_sync_is_retried_after_the_retry_interval() {
+    //// Given
+    let mut service = new_test_service(3, Duration::from_millis(50));
+    let peer = new_test_peer_id();
+
+    testlib::service::inject_events(&mut service, [SubscriptionSyncInEvent::SyncFailed(peer)]);
+    let output_events_before = testlib::service::async_collect_events(&mut service).await;
+
+    //// When
+    // Wait for the retry interval to elapse
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    let output_events_after = testlib::service::async_collect_events(&mut service).await;
+
+    //// Then
+    assert!(
+        output_events_before.is_empty(),
+        "No retry before the interval"
+    );
+    assert_eq!(
+        output_events_after,
+        [SubscriptionSyncOutEvent::RetrySync(peer)]
+    );
+    assert!(service.is_pending(&peer));
+}
+
+#[test]
+fn sync_failure_is_notified_after_the_maximum_attempts() {
+    //// Given
+    let mut service = new_test_service(3, Duration::from_secs(60));
+    let peer = new_test_peer_id();
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [
+            SubscriptionSyncInEvent::SyncFailed(peer),
+            SubscriptionSyncInEvent::SyncFailed(peer),
+            SubscriptionSyncInEvent::SyncFailed(peer),
+        ],
+    );
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events, [SubscriptionSyncOutEvent::SyncFailed(peer)]);
+    assert!(!service.is_pending(&peer));
+}
+
+#[test]
+fn new_connection_triggers_an_immediate_retry() {
+    //// Given
+    let mut service = new_test_service(3, Duration::from_secs(60));
+    let peer = new_test_peer_id();
+    let other_peer = new_test_peer_id();
+
+    testlib::service::inject_events(&mut service, [SubscriptionSyncInEvent::SyncFailed(peer)]);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [
+            SubscriptionSyncInEvent::ConnectionEstablished(peer),
+            SubscriptionSyncInEvent::ConnectionEstablished(other_peer),
+        ],
+    );
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events, [SubscriptionSyncOutEvent::RetrySync(peer)]);
+}
+
+#[test]
+fn successful_sync_and_disconnection_clear_the_pending_retries() {
+    //// Given
+    let mut service = new_test_service(3, Duration::from_secs(60));
+    let peer_a = new_test_peer_id();
+    let peer_b = new_test_peer_id();
+
+    testlib::service::inject_events(
+        &mut service,
+        [
+            SubscriptionSyncInEvent::SyncFailed(peer_a),
+            SubscriptionSyncInEvent::SyncFailed(peer_b),
+        ],
+    );
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [
+            SubscriptionSyncInEvent::SyncSucceeded(peer_a),
+            SubscriptionSyncInEvent::PeerDisconnected(peer_b),
+        ],
+    );
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(output_events.is_empty());
+    assert!(!service.is_pending(&peer_a));
+    assert!(!service.is_pending(&peer_b));
+}
@@ -1,10 +1,14 @@
+pub use churn::{ChurnParams, ChurnStats};
 pub use events::{
     ServiceIn as SubscriptionsInEvent, ServiceOut as SubscriptionsOutEvent,
     SubscriptionsPeerConnectionEvent,
 };
 pub use service::SubscriptionsService;
+pub use service_debounce::SubscriptionsDebounceService;
 
+mod churn;
 mod events;
 mod service;
+mod service_debounce;
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+
+use libp2p::identity::PeerId;
+
+use crate::topic::TopicHash;
+
+/// The subscription churn statistics of a peer over the sliding window (see
+/// [`Config::subscription_churn_window`](crate::Config::subscription_churn_window)).
+///
+/// A transition is a peer subscription, or unsubscription, changing the peer subscription state.
+/// The redundant subscription actions are not accounted.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ChurnStats {
+    /// The number of subscription transitions over the window, across all topics.
+    pub transitions: u64,
+    /// The number of subscription transitions over the window, per topic.
+    pub topics: BTreeMap<TopicHash, u64>,
+}
+
+/// The per-peer subscription churn tracking parameters.
+#[derive(Debug, Clone, Copy)]
+pub struct ChurnParams {
+    /// The subscription churn sliding window duration.
+    pub window: Duration,
+    /// The heartbeat interval, the sliding window advances on every heartbeat.
+    pub heartbeat_interval: Duration,
+    /// The subscription transitions rate, in transitions per minute, from which a peer is
+    /// notified as flapping a topic subscription.
+    pub flapping_threshold: f64,
+}
+
+impl Default for ChurnParams {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            heartbeat_interval: Duration::from_secs(1),
+            flapping_threshold: 10.0,
+        }
+    }
+}
+
+/// The subscription transitions of a peer topic, over the sliding window.
+#[derive(Debug, Default)]
+struct ChurnCounter {
+    /// The number of transitions per heartbeat tick, oldest first. The ticks without transitions
+    /// are not stored.
+    buckets: VecDeque<(u64, u64)>,
+    /// The number of transitions over the window, i.e., the sum of the buckets.
+    transitions: u64,
+    /// The tick the peer topic flapping was last notified at.
+    last_notified: Option<u64>,
+}
+
+/// The rate, in transitions per minute, of `transitions` over the `window`.
+fn transitions_per_min(transitions: u64, window: Duration) -> f64 {
+    let window_secs = window.as_secs_f64();
+    if window_secs == 0.0 {
+        return 0.0;
+    }
+
+    transitions as f64 * 60.0 / window_secs
+}
+
+/// Tracks the peers subscription transitions, per topic, over a sliding window of heartbeat ticks.
+///
+/// Recording a transition is O(1). The transitions older than the window are evicted on every
+/// heartbeat, along with the counters without transitions nor pending notification cooldown.
+#[derive(Debug)]
+pub(crate) struct ChurnTracker {
+    /// The tracking parameters.
+    params: ChurnParams,
+    /// The window length, in heartbeat ticks.
+    window_ticks: u64,
+    /// The current heartbeat tick.
+    tick: u64,
+    /// The subscription transitions counters, per peer and topic.
+    counters: HashMap<PeerId, HashMap<TopicHash, ChurnCounter>>,
+}
+
+impl ChurnTracker {
+    /// Create a new subscription churn tracker.
+    pub(crate) fn new(params: ChurnParams) -> Self {
+        let window_ticks = if params.heartbeat_interval.is_zero() {
+            1
+        } else {
+            let ticks = params.window.as_nanos() / params.heartbeat_interval.as_nanos();
+            let rem = params.window.as_nanos() % params.heartbeat_interval.as_nanos();
+            (ticks + u128::from(rem > 0)).max(1) as u64
+        };
+
+        Self {
+            params,
+            window_ticks,
+            tick: 0,
+            counters: Default::default(),
+        }
+    }
+
+    /// Record a peer subscription transition of the topic.
+    ///
+    /// If the peer topic transitions rate reaches the flapping threshold, and the peer topic
+    /// flapping was not notified during the last window, returns the transitions rate, in
+    /// transitions per minute, and marks the peer topic as notified.
+    pub(crate) fn record(&mut self, peer: PeerId, topic: TopicHash) -> Option<f64> {
+        let (tick, window_ticks) = (self.tick, self.window_ticks);
+        let counter = self
+            .counters
+            .entry(peer)
+            .or_default()
+            .entry(topic)
+            .or_default();
+
+        match counter.buckets.back_mut() {
+            Some((bucket_tick, count)) if *bucket_tick == tick => *count += 1,
+            _ => counter.buckets.push_back((tick, 1)),
+        }
+        counter.transitions += 1;
+
+        let rate = transitions_per_min(counter.transitions, self.params.window);
+        if rate < self.params.flapping_threshold {
+            return None;
+        }
+        if let Some(last) = counter.last_notified {
+            if tick - last < window_ticks {
+                return None;
+            }
+        }
+
+        counter.last_notified = Some(tick);
+        Some(rate)
+    }
+
+    /// Advance the sliding window by one heartbeat tick, and evict the transitions older than
+    /// the window.
+    pub(crate) fn heartbeat(&mut self) {
+        self.tick += 1;
+
+        let (tick, window_ticks) = (self.tick, self.window_ticks);
+        self.counters.retain(|_, topics| {
+            topics.retain(|_, counter| {
+                while let Some((bucket_tick, count)) = counter.buckets.front() {
+                    if tick - bucket_tick < window_ticks {
+                        break;
+                    }
+                    counter.transitions -= count;
+                    counter.buckets.pop_front();
+                }
+
+                let cooling_down = counter
+                    .last_notified
+                    .map_or(false, |last| tick - last < window_ticks);
+                !counter.buckets.is_empty() || cooling_down
+            });
+            !topics.is_empty()
+        });
+    }
+
+    /// The peer subscription churn statistics over the window.
+    pub(crate) fn stats(&self, peer: &PeerId) -> ChurnStats {
+        let Some(topics) = self.counters.get(peer) else {
+            return ChurnStats::default();
+        };
+
+        let topics = topics
+            .iter()
+            .filter(|(_, counter)| counter.transitions > 0)
+            .map(|(topic, counter)| (topic.clone(), counter.transitions))
+            .collect::<BTreeMap<_, _>>();
+        ChurnStats {
+            transitions: topics.values().sum(),
+            topics,
+        }
+    }
+
+    /// Drop the peer subscription churn state, e.g., on disconnection.
+    pub(crate) fn peer_disconnected(&mut self, peer: &PeerId) {
+        self.counters.remove(peer);
+    }
+}
@@ -1,7 +1,10 @@
 use libp2p::identity::PeerId;
+use libp2p::swarm::ConnectionId;
 
+use crate::dispatch::Consumes;
 use crate::framing::SubscriptionAction;
-use crate::subscription::Subscription;
+use crate::services::connections::ConnectionsOutEvent;
+use crate::subscription::{Subscription, SubscriptionInfo};
 use crate::topic::TopicHash;
 
 /// Events consumed by the [`SubscriptionsService`].
@@ -19,11 +22,31 @@ pub enum ServiceIn {
     PeerSubscriptionRequest {
         /// Peer that sent the subscription request.
         src: PeerId,
+        /// The connection the subscription request was received on, if the peer subscriptions are
+        /// tracked per connection (see [`Config::connection_scoped_subscriptions`]).
+        ///
+        /// [`Config::connection_scoped_subscriptions`]: crate::Config::connection_scoped_subscriptions
+        connection: Option<ConnectionId>,
         /// Subscription action.
         action: SubscriptionAction,
     },
+    /// The topics a peer is subscribed to, learned from a third party, e.g., the peer exchange of
+    /// a `Prune` control message.
+    ///
+    /// If the peer is not connected, it is tracked as a known remote peer.
+    PeerInfoLearned {
+        /// The peer the information is about.
+        peer: PeerId,
+        /// The topics the peer is subscribed to.
+        topics: Vec<TopicHash>,
+    },
     /// A peer connection event.
     PeerConnectionEvent(SubscriptionsPeerConnectionEvent),
+    /// A heartbeat tick, advancing the peers subscription churn sliding window (see
+    /// [`Config::subscription_churn_window`]).
+    ///
+    /// [`Config::subscription_churn_window`]: crate::Config::subscription_churn_window
+    Heartbeat,
 }
 
 impl ServiceIn {
@@ -40,6 +63,15 @@ pub enum SubscriptionsPeerConnectionEvent {
     NewPeerConnected(PeerId),
     /// Peer disconnected.
     PeerDisconnected(PeerId),
+    /// One of the peer connections closed, while other connections remain established.
+    ///
+    /// The connection scoped subscriptions of the closed connection are dropped.
+    ConnectionClosed {
+        /// The peer.
+        peer: PeerId,
+        /// The closed connection.
+        connection: ConnectionId,
+    },
 }
 
 /// Events emitted by the [`SubscriptionsService`].
@@ -54,7 +86,13 @@ pub enum ServiceOut {
     ///
     /// This event is emitted when the node unsubscribes from a topic. This will emit one
     /// unsubscription request to each active peer.
-    Unsubscribed(TopicHash),
+    Unsubscribed {
+        /// The unsubscribed topic.
+        topic: TopicHash,
+
+        /// The metadata of the removed subscription.
+        info: SubscriptionInfo,
+    },
     /// A peer registered a new subscription.
     ///
     /// This peer is now subscribed to the `topic`.
@@ -75,6 +113,59 @@ pub enum ServiceOut {
         /// Topic that the peer unsubscribed from.
         topic: TopicHash,
     },
+    /// A peer registered new subscriptions.
+    ///
+    /// The peer subscriptions changes processed in the same service poll are coalesced into a
+    /// single event per peer. This event is emitted instead of [`ServiceOut::PeerSubscribed`] when
+    /// the peer subscribed to more than one topic.
+    PeerSubscribedMany {
+        /// Peer that subscribed.
+        peer: PeerId,
+
+        /// Topics that the peer subscribed to.
+        topics: Vec<TopicHash>,
+    },
+    /// A peer unregistered some subscriptions.
+    ///
+    /// The peer subscriptions changes processed in the same service poll are coalesced into a
+    /// single event per peer. This event is emitted instead of [`ServiceOut::PeerUnsubscribed`]
+    /// when the peer unsubscribed from more than one topic.
+    PeerUnsubscribedMany {
+        /// Peer that unsubscribed.
+        peer: PeerId,
+
+        /// Topics that the peer unsubscribed from.
+        topics: Vec<TopicHash>,
+    },
+    /// A known remote peer is subscribed to a topic, as learned from a third party.
+    ///
+    /// The local node is not connected to this peer.
+    KnownPeerSubscribed {
+        /// The known remote peer.
+        peer: PeerId,
+
+        /// Topic that the peer is subscribed to.
+        topic: TopicHash,
+    },
+    /// A known remote peer is no longer tracked, either because it connected or because it was
+    /// evicted to make room for other known remote peers.
+    KnownPeerForgotten(PeerId),
+    /// A peer flaps its subscription to a topic, i.e., its subscription transitions rate to the
+    /// topic reached the flapping threshold (see [`Config::subscription_flapping_threshold`]).
+    ///
+    /// This event is emitted at most once per churn window and peer topic.
+    ///
+    /// [`Config::subscription_flapping_threshold`]: crate::Config::subscription_flapping_threshold
+    PeerFlapping {
+        /// The flapping peer.
+        peer: PeerId,
+
+        /// The topic the peer flaps its subscription to.
+        topic: TopicHash,
+
+        /// The peer subscription transitions rate to the topic, in transitions per minute.
+        transitions_per_min: f64,
+    },
     /// Send all the local node subscriptions to a peer.
     ///
     /// This event is emitted when a new peer connects to the node. This will send one
@@ -87,3 +178,17 @@ pub enum ServiceOut {
         topics: Vec<TopicHash>,
     },
 }
+
+impl Consumes<ConnectionsOutEvent> for ServiceIn {
+    fn consume(ev: &ConnectionsOutEvent) -> Option<Self> {
+        let ev = match ev {
+            ConnectionsOutEvent::NewPeerConnected { peer, .. } => {
+                SubscriptionsPeerConnectionEvent::NewPeerConnected(*peer)
+            }
+            ConnectionsOutEvent::PeerDisconnected(peer) => {
+                SubscriptionsPeerConnectionEvent::PeerDisconnected(*peer)
+            }
+        };
+        Some(ServiceIn::PeerConnectionEvent(ev))
+    }
+}
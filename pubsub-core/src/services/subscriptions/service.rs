@@ -1,29 +1,165 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::task::{Context, Poll};
+use std::time::Instant;
 
+use libp2p::swarm::ConnectionId;
 use libp2p::PeerId;
 
-use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+use libp2p_pubsub_common::service::{InCtx, OnEventCtx, PollCtx, Service};
 
+use crate::config::Config;
 use crate::framing::SubscriptionAction;
+use crate::message::Priority;
 use crate::services::subscriptions::SubscriptionsPeerConnectionEvent;
-use crate::topic::TopicHash;
+use crate::subscription::{PeerSubscriptionInfo, Subscription, SubscriptionInfo};
+use crate::topic::{Hasher, Sha256Hash, TopicHash};
 
+use super::churn::{ChurnParams, ChurnStats, ChurnTracker};
 use super::events::{ServiceIn, ServiceOut};
 
+/// The subscriptions changes of a peer, not emitted yet.
 #[derive(Debug, Default)]
+struct PeerSubscriptionsChanges {
+    /// The topics the peer subscribed to.
+    subscribed: Vec<TopicHash>,
+    /// The topics the peer unsubscribed from.
+    unsubscribed: Vec<TopicHash>,
+}
+
+impl PeerSubscriptionsChanges {
+    /// Records a peer subscription. An unsubscription from the same topic is cancelled out.
+    fn subscribe(&mut self, topic: TopicHash) {
+        match self.unsubscribed.iter().position(|t| *t == topic) {
+            Some(idx) => {
+                self.unsubscribed.remove(idx);
+            }
+            None => self.subscribed.push(topic),
+        }
+    }
+
+    /// Records a peer unsubscription. A subscription to the same topic is cancelled out.
+    fn unsubscribe(&mut self, topic: TopicHash) {
+        match self.subscribed.iter().position(|t| *t == topic) {
+            Some(idx) => {
+                self.subscribed.remove(idx);
+            }
+            None => self.unsubscribed.push(topic),
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct SubscriptionsService {
+    /// The maximum number of known remote peers tracked.
+    max_known_remote_peers: usize,
+
     /// The topics this node is subscribed to.
     local_subscriptions: BTreeSet<TopicHash>,
 
+    /// The metadata of the local subscriptions.
+    local_subscriptions_info: HashMap<TopicHash, SubscriptionInfo>,
+
+    /// The topic hashes each local subscription accepts as aliases. Removed with the local
+    /// subscription.
+    subscriptions_aliases: HashMap<TopicHash, Vec<TopicHash>>,
+
+    /// The topic hashes accepted as aliases of the local subscriptions, mapped to the aliased
+    /// subscription topic.
+    ///
+    /// The reverse index of `subscriptions_aliases`. If several local subscriptions accept the
+    /// same alias, the alias is mapped to only one of them.
+    local_aliases: HashMap<TopicHash, TopicHash>,
+
+    /// The SHA-256 hashes of the local subscriptions topic strings, mapped to the local
+    /// subscription topic, to detect the peers hashing the local subscriptions topic strings
+    /// differently.
+    local_sha256_hashes: HashMap<TopicHash, TopicHash>,
+
+    /// The local subscriptions marked as high priority (see
+    /// [`SubscriptionBuilder::priority`](crate::SubscriptionBuilder::priority)).
+    high_priority_topics: HashSet<TopicHash>,
+
+    /// The peers this router is connected to.
+    connected_peers: HashSet<PeerId>,
+
     /// The peers this router is connected to and the topics they are subscribed to.
     ///
     /// Peers are added to this map when they send the router a message with a topic they are
     /// subscribed to. They are removed on disconnection.
     peers_subscriptions: HashMap<PeerId, BTreeSet<TopicHash>>,
+
+    /// The topics each connection of the peers is subscribed to, for the peers whose
+    /// subscriptions are tracked per connection.
+    ///
+    /// The peer subscriptions, in `peers_subscriptions`, are the union of its connections
+    /// subscriptions. Connections are removed when closed.
+    connections_subscriptions: HashMap<PeerId, HashMap<ConnectionId, BTreeSet<TopicHash>>>,
+
+    /// The metadata of the connected peers subscriptions. Removed with the peer subscriptions.
+    peers_subscriptions_info: HashMap<PeerId, HashMap<TopicHash, PeerSubscriptionInfo>>,
+
+    /// The number of connected peers subscribed to each topic.
+    ///
+    /// The reverse index of `peers_subscriptions`. The topics without subscribed peers are
+    /// removed.
+    topics_subscribers: HashMap<TopicHash, usize>,
+
+    /// The peers this router is not connected to and the topics they are known to be subscribed
+    /// to, as learned from third parties.
+    ///
+    /// Peers are removed when they connect, or when evicted to make room for other known remote
+    /// peers (see `known_peers_order`).
+    known_peers_subscriptions: HashMap<PeerId, BTreeSet<TopicHash>>,
+
+    /// The known remote peers, from the least to the most recently learned.
+    known_peers_order: VecDeque<PeerId>,
+
+    /// The peers subscriptions changes processed in the current poll, coalesced into a single
+    /// event per peer at the end of the poll.
+    pending_changes: HashMap<PeerId, PeerSubscriptionsChanges>,
+
+    /// The peers with pending subscriptions changes, in the order of their first change.
+    pending_changes_order: Vec<PeerId>,
+
+    /// The connected peers subscription transitions, per topic, over the churn sliding window.
+    churn: ChurnTracker,
+}
+
+impl Default for SubscriptionsService {
+    fn default() -> Self {
+        Self::new(
+            Config::default().max_known_remote_peers(),
+            ChurnParams::default(),
+        )
+    }
 }
 
 /// Public API.
 impl SubscriptionsService {
+    /// Creates a new subscriptions service tracking up to `max_known_remote_peers` known remote
+    /// peers, and the peers subscription churn with the `churn_params` parameters.
+    pub fn new(max_known_remote_peers: usize, churn_params: ChurnParams) -> Self {
+        Self {
+            max_known_remote_peers,
+            local_subscriptions: Default::default(),
+            local_subscriptions_info: Default::default(),
+            subscriptions_aliases: Default::default(),
+            local_aliases: Default::default(),
+            local_sha256_hashes: Default::default(),
+            high_priority_topics: Default::default(),
+            connected_peers: Default::default(),
+            peers_subscriptions: Default::default(),
+            connections_subscriptions: Default::default(),
+            peers_subscriptions_info: Default::default(),
+            topics_subscribers: Default::default(),
+            known_peers_subscriptions: Default::default(),
+            known_peers_order: Default::default(),
+            pending_changes: Default::default(),
+            pending_changes_order: Default::default(),
+            churn: ChurnTracker::new(churn_params),
+        }
+    }
+
     /// Whether the router is subscribed to the given topic or not.
     pub fn is_subscribed(&self, topic: &TopicHash) -> bool {
         self.local_subscriptions.contains(topic)
@@ -34,6 +170,73 @@ impl SubscriptionsService {
         &self.local_subscriptions
     }
 
+    /// Returns the metadata of the local subscription to the given topic.
+    ///
+    /// If the node is not subscribed to the topic, this returns `None`.
+    pub fn subscription_info(&self, topic: &TopicHash) -> Option<&SubscriptionInfo> {
+        self.local_subscriptions_info.get(topic)
+    }
+
+    /// Returns the local subscription topic the given topic hash is an alias of (see
+    /// [`SubscriptionBuilder::also_accept_alias`](crate::SubscriptionBuilder::also_accept_alias)).
+    ///
+    /// If the topic hash is not an alias of a local subscription, this returns `None`.
+    pub fn alias_topic(&self, alias: &TopicHash) -> Option<&TopicHash> {
+        self.local_aliases.get(alias)
+    }
+
+    /// Returns the local scheduling priority of the messages forwarded on the given topic (see
+    /// [`SubscriptionBuilder::priority`](crate::SubscriptionBuilder::priority)).
+    ///
+    /// If the node is not subscribed to the topic, this returns [`Priority::Normal`].
+    pub fn topic_priority(&self, topic: &TopicHash) -> Priority {
+        if self.high_priority_topics.contains(topic) {
+            Priority::High
+        } else {
+            Priority::Normal
+        }
+    }
+
+    /// Returns the local subscription topic hashing the same topic string as the given topic hash,
+    /// with a different hash function: the local subscription topic is the SHA-256 hash of the
+    /// given topic string, or the given topic hash is the SHA-256 hash of the local subscription
+    /// topic string.
+    ///
+    /// If no local subscription matches, this returns `None`.
+    pub fn hash_counterpart(&self, topic: &TopicHash) -> Option<&TopicHash> {
+        if let Some(local) = self
+            .local_subscriptions
+            .get(&Sha256Hash::hash(topic.as_str().to_owned()))
+        {
+            return Some(local);
+        }
+
+        self.local_sha256_hashes.get(topic)
+    }
+
+    /// Returns the topic hashes accepted by more than one local subscription, either as the
+    /// subscription topic or as an alias, with the topics of the local subscriptions accepting
+    /// them.
+    pub fn duplicate_topic_hashes(&self) -> Vec<(TopicHash, BTreeSet<TopicHash>)> {
+        let mut accepted = HashMap::<&TopicHash, BTreeSet<TopicHash>>::new();
+        for topic in &self.local_subscriptions {
+            accepted.entry(topic).or_default().insert(topic.clone());
+        }
+        for (topic, aliases) in &self.subscriptions_aliases {
+            for alias in aliases {
+                accepted.entry(alias).or_default().insert(topic.clone());
+            }
+        }
+
+        let mut duplicates = accepted
+            .into_iter()
+            .filter(|(_, topics)| topics.len() > 1)
+            .map(|(hash, topics)| (hash.clone(), topics))
+            .collect::<Vec<_>>();
+        duplicates.sort();
+        duplicates
+    }
+
     /// Returns whether the given peer is subscribed to the given topic or not.
     ///
     /// If the peer is not subscribed to the topic, or not connected, this returns `false`.
@@ -44,30 +247,152 @@ impl SubscriptionsService {
             .unwrap_or(false)
     }
 
+    /// Returns the number of connected peers subscribed to the given topic.
+    pub fn topic_subscribers_count(&self, topic: &TopicHash) -> usize {
+        self.topics_subscribers.get(topic).copied().unwrap_or(0)
+    }
+
     /// Returns the topics the given peer is subscribed to.
     ///
     /// If the peer is not connected, this returns `None`.
     pub fn peer_subscriptions(&self, peer: &PeerId) -> Option<&BTreeSet<TopicHash>> {
         self.peers_subscriptions.get(peer)
     }
+
+    /// Returns the metadata of the given peer subscription to the given topic.
+    ///
+    /// If the peer is not subscribed to the topic, or not connected, this returns `None`.
+    pub fn peer_subscription_info(
+        &self,
+        peer: &PeerId,
+        topic: &TopicHash,
+    ) -> Option<&PeerSubscriptionInfo> {
+        self.peers_subscriptions_info
+            .get(peer)
+            .and_then(|topics| topics.get(topic))
+    }
+
+    /// Returns whether the given peer connection is subscribed to the given topic or not.
+    ///
+    /// If the peer subscriptions are not tracked per connection, this returns `false`.
+    pub fn is_connection_subscribed(
+        &self,
+        peer: &PeerId,
+        connection: &ConnectionId,
+        topic: &TopicHash,
+    ) -> bool {
+        self.connection_subscriptions(peer, connection)
+            .map(|topics| topics.contains(topic))
+            .unwrap_or(false)
+    }
+
+    /// Returns the topics the given peer connection is subscribed to.
+    ///
+    /// If the peer subscriptions are not tracked per connection, this returns `None`.
+    pub fn connection_subscriptions(
+        &self,
+        peer: &PeerId,
+        connection: &ConnectionId,
+    ) -> Option<&BTreeSet<TopicHash>> {
+        self.connections_subscriptions
+            .get(peer)
+            .and_then(|connections| connections.get(connection))
+    }
+
+    /// Returns the connections of the given peer subscribed to the given topic.
+    ///
+    /// If the peer subscriptions are not tracked per connection, this returns `None`.
+    pub fn subscribed_connections(
+        &self,
+        peer: &PeerId,
+        topic: &TopicHash,
+    ) -> Option<Vec<ConnectionId>> {
+        let connections = self.connections_subscriptions.get(peer)?;
+        Some(
+            connections
+                .iter()
+                .filter(|(_, topics)| topics.contains(topic))
+                .map(|(connection, _)| *connection)
+                .collect(),
+        )
+    }
+
+    /// Returns the topics the given known remote peer is subscribed to, as learned from third
+    /// parties.
+    ///
+    /// If the peer is connected, or not known, this returns `None`.
+    pub fn known_peer_subscriptions(&self, peer: &PeerId) -> Option<&BTreeSet<TopicHash>> {
+        self.known_peers_subscriptions.get(peer)
+    }
+
+    /// Returns the subscription churn statistics of the given peer over the churn sliding window.
+    ///
+    /// If the peer is not connected, or its subscriptions did not change during the window, the
+    /// statistics are empty.
+    pub fn churn(&self, peer: &PeerId) -> ChurnStats {
+        self.churn.stats(peer)
+    }
 }
 
 // Internal API.
 impl SubscriptionsService {
-    /// Adds a new local subscription.
+    /// Adds a new local subscription, and records its metadata.
     ///
     /// If the node was not already subscribed to the topic, this returns `true`. Otherwise, it
     /// returns `false`.
-    fn add_local_subscription(&mut self, topic: TopicHash) -> bool {
-        self.local_subscriptions.insert(topic)
+    fn add_local_subscription(&mut self, sub: &Subscription) -> bool {
+        if !self.local_subscriptions.insert(sub.topic.clone()) {
+            return false;
+        }
+
+        let info = SubscriptionInfo::new(sub.owner.clone(), Instant::now());
+        self.local_subscriptions_info
+            .insert(sub.topic.clone(), info);
+        for alias in &sub.aliases {
+            self.local_aliases
+                .entry(alias.clone())
+                .or_insert_with(|| sub.topic.clone());
+        }
+        if !sub.aliases.is_empty() {
+            self.subscriptions_aliases
+                .insert(sub.topic.clone(), sub.aliases.clone());
+        }
+        self.local_sha256_hashes.insert(
+            Sha256Hash::hash(sub.topic.as_str().to_owned()),
+            sub.topic.clone(),
+        );
+        if sub.priority == Priority::High {
+            self.high_priority_topics.insert(sub.topic.clone());
+        }
+        true
     }
 
-    /// Removes a local subscription.
+    /// Removes a local subscription, and its aliases.
     ///
-    /// If the node was subscribed to the topic, this returns `true`. Otherwise, it returns
-    /// `false`.
-    fn remove_local_subscription(&mut self, topic: TopicHash) -> bool {
-        self.local_subscriptions.remove(&topic)
+    /// If the node was subscribed to the topic, this returns the subscription metadata.
+    /// Otherwise, it returns `None`.
+    fn remove_local_subscription(&mut self, topic: &TopicHash) -> Option<SubscriptionInfo> {
+        if !self.local_subscriptions.remove(topic) {
+            return None;
+        }
+
+        // Map the removed aliases to the other local subscriptions accepting them, if any.
+        if let Some(aliases) = self.subscriptions_aliases.remove(topic) {
+            for alias in aliases {
+                self.local_aliases.remove(&alias);
+                let other = self
+                    .subscriptions_aliases
+                    .iter()
+                    .find(|(_, aliases)| aliases.contains(&alias))
+                    .map(|(other, _)| other.clone());
+                if let Some(other) = other {
+                    self.local_aliases.insert(alias, other);
+                }
+            }
+        }
+        self.local_sha256_hashes.retain(|_, local| local != topic);
+        self.high_priority_topics.remove(topic);
+        self.local_subscriptions_info.remove(topic)
     }
 
     /// Adds a new peer subscription.
@@ -76,68 +401,357 @@ impl SubscriptionsService {
     /// returns `false`.
     fn add_peer_subscription(&mut self, peer: PeerId, topic: TopicHash) -> bool {
         let peer_subscriptions = self.peers_subscriptions.entry(peer).or_default();
-        peer_subscriptions.insert(topic.clone())
+        if !peer_subscriptions.insert(topic.clone()) {
+            return false;
+        }
+
+        *self.topics_subscribers.entry(topic).or_default() += 1;
+        true
     }
 
     /// Removes a peer subscription.
     ///
     /// If the peer was subscribed to the topic, this returns `true`. Otherwise, it returns `false`.
     fn remove_peer_subscription(&mut self, peer: &PeerId, topic: &TopicHash) -> bool {
-        if let Some(peer_subscriptions) = self.peers_subscriptions.get_mut(peer) {
-            return peer_subscriptions.remove(topic);
+        let Some(peer_subscriptions) = self.peers_subscriptions.get_mut(peer) else {
+            return false;
+        };
+        if !peer_subscriptions.remove(topic) {
+            return false;
+        }
+
+        if let Some(topics) = self.peers_subscriptions_info.get_mut(peer) {
+            topics.remove(topic);
+        }
+        self.remove_topic_subscriber(topic);
+        true
+    }
+
+    /// Records the peer subscription announcement: the first announcement, or a refresh if the
+    /// peer is already subscribed to the topic.
+    fn record_peer_subscription_announcement(&mut self, peer: PeerId, topic: &TopicHash) {
+        if !self.is_peer_subscribed(&peer, topic) {
+            return;
+        }
+
+        let now = Instant::now();
+        self.peers_subscriptions_info
+            .entry(peer)
+            .or_default()
+            .entry(topic.clone())
+            .and_modify(|info| info.last_refreshed = now)
+            .or_insert_with(|| PeerSubscriptionInfo::new(now));
+    }
+
+    /// Adds a new peer connection subscription, and the peer subscription if none of the peer
+    /// connections was subscribed to the topic.
+    ///
+    /// If the peer was not already subscribed to the topic, this returns `true`. Otherwise, it
+    /// returns `false`.
+    fn add_connection_subscription(
+        &mut self,
+        peer: PeerId,
+        connection: ConnectionId,
+        topic: TopicHash,
+    ) -> bool {
+        let connection_subscriptions = self
+            .connections_subscriptions
+            .entry(peer)
+            .or_default()
+            .entry(connection)
+            .or_default();
+        if !connection_subscriptions.insert(topic.clone()) {
+            return false;
+        }
+
+        self.add_peer_subscription(peer, topic)
+    }
+
+    /// Removes a peer connection subscription, and the peer subscription if no other peer
+    /// connection is subscribed to the topic.
+    ///
+    /// If the peer is no longer subscribed to the topic, this returns `true`. Otherwise, it
+    /// returns `false`.
+    fn remove_connection_subscription(
+        &mut self,
+        peer: &PeerId,
+        connection: &ConnectionId,
+        topic: &TopicHash,
+    ) -> bool {
+        let Some(connections) = self.connections_subscriptions.get_mut(peer) else {
+            return false;
+        };
+        let removed = connections
+            .get_mut(connection)
+            .map(|topics| topics.remove(topic))
+            .unwrap_or(false);
+        if !removed || connections.values().any(|topics| topics.contains(topic)) {
+            return false;
         }
 
-        false
+        self.remove_peer_subscription(peer, topic)
+    }
+
+    /// Removes a closed peer connection subscriptions.
+    ///
+    /// Returns the topics the peer is no longer subscribed to.
+    fn remove_connection(&mut self, peer: &PeerId, connection: &ConnectionId) -> Vec<TopicHash> {
+        let Some(topics) = self
+            .connections_subscriptions
+            .get_mut(peer)
+            .and_then(|connections| connections.remove(connection))
+        else {
+            return Vec::new();
+        };
+
+        let mut unsubscribed = Vec::new();
+        for topic in topics {
+            let subscribed = self
+                .connections_subscriptions
+                .get(peer)
+                .map(|connections| connections.values().any(|topics| topics.contains(&topic)))
+                .unwrap_or(false);
+            if !subscribed && self.remove_peer_subscription(peer, &topic) {
+                unsubscribed.push(topic);
+            }
+        }
+        unsubscribed
     }
 
     /// Removes a peer from the peer subscriptions tracker.
     fn remove_peer(&mut self, peer: &PeerId) {
-        self.peers_subscriptions.remove(peer);
+        self.connected_peers.remove(peer);
+        self.connections_subscriptions.remove(peer);
+        self.peers_subscriptions_info.remove(peer);
+        if let Some(peer_subscriptions) = self.peers_subscriptions.remove(peer) {
+            for topic in &peer_subscriptions {
+                self.remove_topic_subscriber(topic);
+            }
+        }
+    }
+
+    /// Decrements the number of connected peers subscribed to the given topic.
+    fn remove_topic_subscriber(&mut self, topic: &TopicHash) {
+        if let Some(count) = self.topics_subscribers.get_mut(topic) {
+            *count -= 1;
+            if *count == 0 {
+                self.topics_subscribers.remove(topic);
+            }
+        }
+    }
+
+    /// Returns the pending subscriptions changes of the given peer.
+    fn peer_pending_changes(&mut self, peer: PeerId) -> &mut PeerSubscriptionsChanges {
+        self.pending_changes.entry(peer).or_insert_with(|| {
+            self.pending_changes_order.push(peer);
+            Default::default()
+        })
+    }
+
+    /// Emits the pending peers subscriptions changes, one event per peer and change kind.
+    ///
+    /// A single topic change is emitted as a [`ServiceOut::PeerSubscribed`] or a
+    /// [`ServiceOut::PeerUnsubscribed`] event.
+    fn flush_pending_changes<'a>(&mut self, svc_cx: &mut impl OnEventCtx<'a, ServiceOut>) {
+        for peer in self.pending_changes_order.drain(..) {
+            let Some(mut changes) = self.pending_changes.remove(&peer) else {
+                continue;
+            };
+
+            if changes.subscribed.len() == 1 {
+                let topic = changes.subscribed.remove(0);
+                svc_cx.emit(ServiceOut::PeerSubscribed { peer, topic });
+            } else if !changes.subscribed.is_empty() {
+                svc_cx.emit(ServiceOut::PeerSubscribedMany {
+                    peer,
+                    topics: changes.subscribed,
+                });
+            }
+
+            if changes.unsubscribed.len() == 1 {
+                let topic = changes.unsubscribed.remove(0);
+                svc_cx.emit(ServiceOut::PeerUnsubscribed { peer, topic });
+            } else if !changes.unsubscribed.is_empty() {
+                svc_cx.emit(ServiceOut::PeerUnsubscribedMany {
+                    peer,
+                    topics: changes.unsubscribed,
+                });
+            }
+        }
+    }
+
+    /// Records a peer subscription transition of the topic, and emits a
+    /// [`ServiceOut::PeerFlapping`] event if the peer flaps its subscription to the topic.
+    fn record_transition<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, ServiceOut>,
+        peer: PeerId,
+        topic: TopicHash,
+    ) {
+        if let Some(transitions_per_min) = self.churn.record(peer, topic.clone()) {
+            svc_cx.emit(ServiceOut::PeerFlapping {
+                peer,
+                topic,
+                transitions_per_min,
+            });
+        }
+    }
+
+    /// Adds the topics a known remote peer is subscribed to, and marks it as the most recently
+    /// learned known remote peer.
+    ///
+    /// Returns the topics that were not already known.
+    fn add_known_peer_subscriptions(
+        &mut self,
+        peer: PeerId,
+        topics: Vec<TopicHash>,
+    ) -> Vec<TopicHash> {
+        self.known_peers_order.retain(|p| *p != peer);
+        self.known_peers_order.push_back(peer);
+
+        let known_topics = self.known_peers_subscriptions.entry(peer).or_default();
+        topics
+            .into_iter()
+            .filter(|topic| known_topics.insert(topic.clone()))
+            .collect()
+    }
+
+    /// Evicts the least recently learned known remote peers exceeding the limit.
+    ///
+    /// Returns the evicted peers.
+    fn evict_known_peers(&mut self) -> Vec<PeerId> {
+        let mut evicted = Vec::new();
+        while self.known_peers_order.len() > self.max_known_remote_peers {
+            if let Some(peer) = self.known_peers_order.pop_front() {
+                self.known_peers_subscriptions.remove(&peer);
+                evicted.push(peer);
+            }
+        }
+        evicted
+    }
+
+    /// Removes a peer from the known remote peers tracker.
+    ///
+    /// If the peer was known, this returns `true`. Otherwise, it returns `false`.
+    fn remove_known_peer(&mut self, peer: &PeerId) -> bool {
+        if self.known_peers_subscriptions.remove(peer).is_none() {
+            return false;
+        }
+
+        self.known_peers_order.retain(|p| p != peer);
+        true
     }
 }
 
-impl EventHandler for SubscriptionsService {
+impl Service for SubscriptionsService {
     type InEvent = ServiceIn;
     type OutEvent = ServiceOut;
 
-    fn on_event<'a>(
+    fn poll<'a>(
         &mut self,
-        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
-        ev: Self::InEvent,
-    ) {
+        svc_cx: impl PollCtx<'a, Self::InEvent, Self::OutEvent>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Self::OutEvent> {
+        let (mut in_cx, mut out_cx) = svc_cx.split();
+
+        // Process the incoming events, and coalesce the peers subscriptions changes.
+        while let Some(ev) = in_cx.pop_next() {
+            self.on_event(&mut out_cx, ev);
+        }
+        self.flush_pending_changes(&mut out_cx);
+
+        Poll::Pending
+    }
+}
+
+impl SubscriptionsService {
+    fn on_event<'a>(&mut self, svc_cx: &mut impl OnEventCtx<'a, ServiceOut>, ev: ServiceIn) {
         match ev {
             ServiceIn::SubscriptionRequest(sub) => {
                 // Emit a [`SubscriptionsOutEvent::Subscribed`] event if the node was not already
                 // subscribed to the topic.
-                if self.add_local_subscription(sub.topic.clone()) {
+                if self.add_local_subscription(&sub) {
                     svc_cx.emit(ServiceOut::Subscribed(sub));
                 }
             }
             ServiceIn::UnsubscriptionRequest(topic) => {
                 // Emit a [`SubscriptionsOutEvent::Unsubscribed`] event if the node was subscribed to the
                 // topic.
-                if self.remove_local_subscription(topic.clone()) {
-                    svc_cx.emit(ServiceOut::Unsubscribed(topic));
+                if let Some(info) = self.remove_local_subscription(&topic) {
+                    svc_cx.emit(ServiceOut::Unsubscribed { topic, info });
                 }
             }
-            ServiceIn::PeerSubscriptionRequest { src: peer, action } => match action {
-                SubscriptionAction::Subscribe(topic) => {
-                    // Emit a [`SubscriptionsOutEvent::PeerSubscribed`] event if the peer was not already
-                    // subscribed to the topic.
-                    if self.add_peer_subscription(peer, topic.clone()) {
-                        svc_cx.emit(ServiceOut::PeerSubscribed { peer, topic });
-                    }
+            ServiceIn::PeerSubscriptionRequest {
+                src: peer,
+                connection,
+                action,
+            } => {
+                // A subscription request can only be received from a connected peer.
+                self.connected_peers.insert(peer);
+                if self.remove_known_peer(&peer) {
+                    svc_cx.emit(ServiceOut::KnownPeerForgotten(peer));
                 }
-                SubscriptionAction::Unsubscribe(topic) => {
-                    // Emit a [`SubscriptionsOutEvent::PeerUnsubscribed`] event if the peer was subscribed to
-                    // the topic.
-                    if self.remove_peer_subscription(&peer, &topic) {
-                        svc_cx.emit(ServiceOut::PeerUnsubscribed { peer, topic });
+
+                match action {
+                    SubscriptionAction::Subscribe(topic) => {
+                        // Record the peer subscription change if the peer was not already subscribed
+                        // to the topic.
+                        let subscribed = match connection {
+                            Some(connection) => {
+                                self.add_connection_subscription(peer, connection, topic.clone())
+                            }
+                            None => self.add_peer_subscription(peer, topic.clone()),
+                        };
+                        self.record_peer_subscription_announcement(peer, &topic);
+                        if subscribed {
+                            self.record_transition(svc_cx, peer, topic.clone());
+                            self.peer_pending_changes(peer).subscribe(topic);
+                        }
+                    }
+                    SubscriptionAction::Unsubscribe(topic) => {
+                        // Record the peer unsubscription change if the peer was subscribed to the
+                        // topic.
+                        let unsubscribed = match connection {
+                            Some(connection) => {
+                                self.remove_connection_subscription(&peer, &connection, &topic)
+                            }
+                            None => self.remove_peer_subscription(&peer, &topic),
+                        };
+                        if unsubscribed {
+                            self.record_transition(svc_cx, peer, topic.clone());
+                            self.peer_pending_changes(peer).unsubscribe(topic);
+                        }
                     }
                 }
-            },
+            }
+            ServiceIn::PeerInfoLearned { peer, topics } => {
+                // The direct knowledge of the connected peers takes precedence.
+                if self.connected_peers.contains(&peer) {
+                    return;
+                }
+
+                let topics = self.add_known_peer_subscriptions(peer, topics);
+                for topic in topics {
+                    svc_cx.emit(ServiceOut::KnownPeerSubscribed { peer, topic });
+                }
+
+                for peer in self.evict_known_peers() {
+                    svc_cx.emit(ServiceOut::KnownPeerForgotten(peer));
+                }
+            }
+            ServiceIn::Heartbeat => {
+                // Evict the peers subscription transitions older than the churn window.
+                self.churn.heartbeat();
+            }
             ServiceIn::PeerConnectionEvent(conn_ev) => match conn_ev {
                 SubscriptionsPeerConnectionEvent::NewPeerConnected(peer) => {
+                    // A connected peer is no longer a known remote peer, its subscriptions will be
+                    // announced by the peer itself.
+                    self.connected_peers.insert(peer);
+                    if self.remove_known_peer(&peer) {
+                        svc_cx.emit(ServiceOut::KnownPeerForgotten(peer));
+                    }
+
                     // Send all the local node subscriptions to a peer when it connects for the first
                     // time (only if the node is subscribed to at least one topic).
                     if self.local_subscriptions.is_empty() {
@@ -148,8 +762,18 @@ impl EventHandler for SubscriptionsService {
                     svc_cx.emit(ServiceOut::SendSubscriptions { dest: peer, topics });
                 }
                 SubscriptionsPeerConnectionEvent::PeerDisconnected(peer) => {
-                    // Remove the peer from the peer subscriptions tracker when it disconnects.
+                    // Remove the peer from the peer subscriptions tracker when it disconnects, and
+                    // drop its pending subscriptions changes and its subscription churn.
                     self.remove_peer(&peer);
+                    self.pending_changes.remove(&peer);
+                    self.churn.peer_disconnected(&peer);
+                }
+                SubscriptionsPeerConnectionEvent::ConnectionClosed { peer, connection } => {
+                    // Record the peer unsubscription change for the topics only the closed
+                    // connection was subscribed to.
+                    for topic in self.remove_connection(&peer, &connection) {
+                        self.peer_pending_changes(peer).unsubscribe(topic);
+                    }
                 }
             },
         }
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::FutureExt;
+use futures_timer::Delay;
+
+use libp2p_pubsub_common::service::{InCtx, OutCtx, PollCtx, Service};
+
+use crate::dispatch::Consumes;
+use crate::framing::SubscriptionAction;
+use crate::services::subscriptions::SubscriptionsOutEvent;
+use crate::topic::TopicHash;
+
+/// A topic subscription change pending to be announced.
+struct PendingAction {
+    /// Whether the node was announced as subscribed to the topic before the debounce window
+    /// started.
+    announced: bool,
+    /// Whether the node is currently subscribed to the topic.
+    subscribed: bool,
+    /// The debounce window timer.
+    flush_timer: Delay,
+}
+
+/// The subscriptions debounce service holds the local subscription actions destined to the
+/// remote peers for a debounce window and nets them out per topic.
+///
+/// When the debounce window elapses, only the final subscription state is announced. If the
+/// final state matches the state announced before the window started (e.g., an unsubscription
+/// followed by a subscription to the same topic), nothing is announced.
+///
+/// If no debounce window is set, the subscription actions are announced immediately.
+#[derive(Default)]
+pub struct SubscriptionsDebounceService {
+    /// The debounce window duration. If `None`, the actions are not debounced.
+    window: Option<Duration>,
+
+    /// The per-topic pending subscription actions.
+    pending: HashMap<TopicHash, PendingAction>,
+}
+
+impl SubscriptionsDebounceService {
+    /// Creates a new subscriptions debounce service.
+    pub fn new(window: Option<Duration>) -> Self {
+        Self {
+            window,
+            pending: Default::default(),
+        }
+    }
+}
+
+impl Service for SubscriptionsDebounceService {
+    type InEvent = SubscriptionAction;
+    type OutEvent = SubscriptionAction;
+
+    fn poll<'a>(
+        &mut self,
+        svc_cx: impl PollCtx<'a, Self::InEvent, Self::OutEvent>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::OutEvent> {
+        let (mut in_cx, mut out_cx) = svc_cx.split();
+
+        // Process the incoming events.
+        while let Some(action) = in_cx.pop_next() {
+            // If no debounce window is set, announce the action immediately.
+            let Some(window) = self.window else {
+                out_cx.emit(action);
+                continue;
+            };
+
+            let (topic, subscribed) = action.into_pair();
+            self.pending
+                .entry(topic)
+                .and_modify(|pending| pending.subscribed = subscribed)
+                .or_insert_with(|| PendingAction {
+                    announced: !subscribed,
+                    subscribed,
+                    flush_timer: Delay::new(window),
+                });
+        }
+
+        // Poll the pending actions flush timers and announce the final state of the topics
+        // whose subscription state changed.
+        let expired = self
+            .pending
+            .iter_mut()
+            .filter_map(|(topic, pending)| {
+                pending
+                    .flush_timer
+                    .poll_unpin(cx)
+                    .is_ready()
+                    .then(|| topic.clone())
+            })
+            .collect::<Vec<_>>();
+        for topic in expired {
+            let Some(pending) = self.pending.remove(&topic) else {
+                continue;
+            };
+
+            if pending.subscribed == pending.announced {
+                tracing::trace!(%topic, "Subscription changes cancelled out, nothing to announce");
+                continue;
+            }
+
+            out_cx.emit(if pending.subscribed {
+                SubscriptionAction::Subscribe(topic)
+            } else {
+                SubscriptionAction::Unsubscribe(topic)
+            });
+        }
+
+        Poll::Pending
+    }
+}
+
+/// The local unsubscriptions are not consumed: the unsubscriptions flushed to the peers on their
+/// own (see [`Behaviour::unsubscribe_many_and_flush`](crate::Behaviour::unsubscribe_many_and_flush))
+/// are not debounced, so the behaviour notifies them itself.
+impl Consumes<SubscriptionsOutEvent> for SubscriptionAction {
+    fn consume(ev: &SubscriptionsOutEvent) -> Option<Self> {
+        match ev {
+            SubscriptionsOutEvent::Subscribed(sub) => {
+                Some(SubscriptionAction::Subscribe(sub.topic.clone()))
+            }
+            SubscriptionsOutEvent::Unsubscribed { .. }
+            | SubscriptionsOutEvent::PeerSubscribed { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribed { .. }
+            | SubscriptionsOutEvent::PeerSubscribedMany { .. }
+            | SubscriptionsOutEvent::PeerUnsubscribedMany { .. }
+            | SubscriptionsOutEvent::KnownPeerSubscribed { .. }
+            | SubscriptionsOutEvent::KnownPeerForgotten(_)
+            | SubscriptionsOutEvent::PeerFlapping { .. }
+            | SubscriptionsOutEvent::SendSubscriptions { .. } => None,
+        }
+    }
+}
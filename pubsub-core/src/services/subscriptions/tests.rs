@@ -1,7 +1,11 @@
+use std::time::{Duration, Instant};
+
 use assert_matches::assert_matches;
 use libp2p::identity::PeerId;
+use libp2p::swarm::ConnectionId;
 use rand::Rng;
 
+use libp2p_pubsub_common::service::BufferedContext;
 use testlib;
 use testlib::service::noop_context;
 
@@ -10,7 +14,8 @@ use crate::services::subscriptions::{
     SubscriptionsInEvent, SubscriptionsOutEvent, SubscriptionsPeerConnectionEvent,
     SubscriptionsService,
 };
-use crate::topic::{Hasher, IdentityHash, Topic};
+use crate::subscription::SubscriptionBuilder;
+use crate::topic::{Hasher, IdentTopic, IdentityHash, Sha256Topic, Topic, TopicHash};
 
 /// Create a new random test topic.
 fn new_test_topic() -> Topic<IdentityHash> {
@@ -58,6 +63,7 @@ fn new_peer_subscribe_seq<H: Hasher>(
 ) -> impl IntoIterator<Item = SubscriptionsInEvent> {
     [SubscriptionsInEvent::PeerSubscriptionRequest {
         src: peer,
+        connection: None,
         action: SubscriptionAction::Subscribe(topic.hash()),
     }]
 }
@@ -69,6 +75,7 @@ fn new_peer_unsubscribe_seq<H: Hasher>(
 ) -> impl IntoIterator<Item = SubscriptionsInEvent> {
     [SubscriptionsInEvent::PeerSubscriptionRequest {
         src: peer,
+        connection: None,
         action: SubscriptionAction::Unsubscribe(topic.hash()),
     }]
 }
@@ -206,7 +213,7 @@ fn unregister_existing_topic_subscription() {
 
     // Assert events
     assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
-    assert_matches!(&output_events[0], SubscriptionsOutEvent::Unsubscribed(topic) => {
+    assert_matches!(&output_events[0], SubscriptionsOutEvent::Unsubscribed { topic, .. } => {
         assert_eq!(topic, &topic_a.hash());
     });
 }
@@ -317,16 +324,12 @@ fn register_peer_subscription_to_topic_when_not_registered() {
     // Assert the events
     assert_eq!(
         output_events.len(),
-        2,
-        "Only 2 events should be emitted (1 per topic)"
+        1,
+        "Only 1 event should be emitted (the peer subscriptions are coalesced)"
     );
-    assert_matches!(&output_events[0], SubscriptionsOutEvent::PeerSubscribed { peer, topic } => {
+    assert_matches!(&output_events[0], SubscriptionsOutEvent::PeerSubscribedMany { peer, topics } => {
         assert_eq!(peer, &remote_peer);
-        assert_eq!(topic, &topic_a.hash());
-    });
-    assert_matches!(&output_events[1], SubscriptionsOutEvent::PeerSubscribed { peer, topic } => {
-        assert_eq!(peer, &remote_peer);
-        assert_eq!(topic, &topic_b.hash());
+        assert_eq!(topics, &[topic_a.hash(), topic_b.hash()]);
     });
 }
 
@@ -430,16 +433,12 @@ fn unregister_peer_subscription_to_topic_when_registered() {
     // Assert the events
     assert_eq!(
         output_events.len(),
-        2,
-        "Only 2 events should be emitted (1 per topic)"
+        1,
+        "Only 1 event should be emitted (the peer unsubscriptions are coalesced)"
     );
-    assert_matches!(&output_events[0], SubscriptionsOutEvent::PeerUnsubscribed { peer, topic } => {
+    assert_matches!(&output_events[0], SubscriptionsOutEvent::PeerUnsubscribedMany { peer, topics } => {
         assert_eq!(peer, &remote_peer);
-        assert_eq!(topic, &topic_a.hash());
-    });
-    assert_matches!(&output_events[1], SubscriptionsOutEvent::PeerUnsubscribed { peer, topic } => {
-        assert_eq!(peer, &remote_peer);
-        assert_eq!(topic, &topic_b.hash());
+        assert_eq!(topics, &[topic_a.hash(), topic_b.hash()]);
     });
 }
 
@@ -479,3 +478,1030 @@ fn remove_all_peer_subscriptions_on_peer_disconnected() {
     // Assert the events
     assert_eq!(output_events.len(), 0, "No events should be emitted");
 }
+
+#[test]
+fn count_topic_subscribers_across_peer_subscription_changes() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let remote_peer_a = new_test_peer_id();
+    let remote_peer_b = new_test_peer_id();
+    let topic = new_test_topic();
+
+    //// When
+    let input_events = itertools::chain!(
+        new_peer_subscribe_seq(remote_peer_a, topic.clone()),
+        new_peer_subscribe_seq(remote_peer_b, topic.clone()),
+        // A repeated subscription is not counted twice.
+        new_peer_subscribe_seq(remote_peer_b, topic.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+    let subscribed_count = service.topic_subscribers_count(&topic.hash());
+
+    let input_events = new_peer_unsubscribe_seq(remote_peer_a, topic.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+    let unsubscribed_count = service.topic_subscribers_count(&topic.hash());
+
+    let input_events = new_peer_disconnected_seq(remote_peer_b);
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+    let disconnected_count = service.topic_subscribers_count(&topic.hash());
+
+    //// Then
+    assert_eq!(subscribed_count, 2);
+    assert_eq!(unsubscribed_count, 1);
+    assert_eq!(disconnected_count, 0);
+}
+
+#[test]
+fn coalesce_peer_subscription_changes_per_peer() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let remote_peer_a = new_test_peer_id();
+    let remote_peer_b = new_test_peer_id();
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+    let topic_c = new_test_topic();
+
+    // Peer A was subscribed to Topic C in a previous poll.
+    testlib::service::inject_events(
+        &mut service,
+        new_peer_subscribe_seq(remote_peer_a, topic_c.clone()),
+    );
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    // The peers subscriptions changes are interleaved.
+    let input_events = itertools::chain!(
+        new_peer_subscribe_seq(remote_peer_a, topic_a.clone()),
+        new_peer_subscribe_seq(remote_peer_b, topic_a.clone()),
+        new_peer_subscribe_seq(remote_peer_a, topic_b.clone()),
+        new_peer_unsubscribe_seq(remote_peer_a, topic_c.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events.len(), 3, "Only 3 events should be emitted");
+    assert_matches!(&output_events[0], SubscriptionsOutEvent::PeerSubscribedMany { peer, topics } => {
+        assert_eq!(peer, &remote_peer_a);
+        assert_eq!(topics, &[topic_a.hash(), topic_b.hash()]);
+    });
+    assert_matches!(&output_events[1], SubscriptionsOutEvent::PeerUnsubscribed { peer, topic } => {
+        assert_eq!(peer, &remote_peer_a);
+        assert_eq!(topic, &topic_c.hash());
+    });
+    assert_matches!(&output_events[2], SubscriptionsOutEvent::PeerSubscribed { peer, topic } => {
+        assert_eq!(peer, &remote_peer_b);
+        assert_eq!(topic, &topic_a.hash());
+    });
+}
+
+#[test]
+fn peer_subscription_changes_cancelled_out_in_the_same_poll_are_not_emitted() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let remote_peer = new_test_peer_id();
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+
+    testlib::service::inject_events(
+        &mut service,
+        new_peer_subscribe_seq(remote_peer, topic_b.clone()),
+    );
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    let input_events = itertools::chain!(
+        new_peer_subscribe_seq(remote_peer, topic_a.clone()),
+        new_peer_unsubscribe_seq(remote_peer, topic_a.clone()),
+        new_peer_unsubscribe_seq(remote_peer, topic_b.clone()),
+        new_peer_subscribe_seq(remote_peer, topic_b.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(output_events.is_empty(), "No events should be emitted");
+    assert!(!service.is_peer_subscribed(&remote_peer, &topic_a.hash()));
+    assert!(service.is_peer_subscribed(&remote_peer, &topic_b.hash()));
+}
+
+#[test]
+fn drop_pending_peer_subscription_changes_on_peer_disconnected() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let remote_peer = new_test_peer_id();
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+
+    //// When
+    let input_events = itertools::chain!(
+        new_peer_subscribe_seq(remote_peer, topic_a.clone()),
+        new_peer_subscribe_seq(remote_peer, topic_b.clone()),
+        new_peer_disconnected_seq(remote_peer),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(output_events.is_empty(), "No events should be emitted");
+    assert_eq!(service.topic_subscribers_count(&topic_a.hash()), 0);
+}
+
+#[test]
+fn coalesced_peer_subscription_changes_produce_the_same_state() {
+    //// Given
+    let mut singular_service = testlib::service::default_test_service::<SubscriptionsService>();
+    let mut batched_service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let peers = [new_test_peer_id(), new_test_peer_id()];
+    let topics = [new_test_topic(), new_test_topic(), new_test_topic()];
+
+    let input_events = itertools::chain!(
+        peers.iter().flat_map(|peer| {
+            topics
+                .iter()
+                .flat_map(|topic| new_peer_subscribe_seq(*peer, topic.clone()))
+        }),
+        new_peer_unsubscribe_seq(peers[0], topics[1].clone()),
+        new_peer_unsubscribe_seq(peers[1], topics[2].clone()),
+    )
+    .collect::<Vec<_>>();
+
+    //// When
+    // Process the events one per poll, and all of them in a single poll.
+    let mut singular_events = Vec::new();
+    for event in input_events.clone() {
+        testlib::service::inject_events(&mut singular_service, [event]);
+        singular_events.extend(testlib::service::collect_events(
+            &mut singular_service,
+            &mut noop_context(),
+        ));
+    }
+
+    testlib::service::inject_events(&mut batched_service, input_events);
+    let batched_events =
+        testlib::service::collect_events(&mut batched_service, &mut noop_context());
+
+    //// Then
+    assert_eq!(singular_events.len(), 8, "One event per change");
+    assert_eq!(batched_events.len(), 2, "One event per peer");
+    for peer in &peers {
+        assert_eq!(
+            batched_service.peer_subscriptions(peer),
+            singular_service.peer_subscriptions(peer)
+        );
+    }
+    for topic in &topics {
+        assert_eq!(
+            batched_service.topic_subscribers_count(&topic.hash()),
+            singular_service.topic_subscribers_count(&topic.hash())
+        );
+    }
+}
+
+/// Create a new connection scoped peer subscription action sequence.
+fn new_connection_subscription_seq(
+    peer: PeerId,
+    connection: ConnectionId,
+    action: SubscriptionAction,
+) -> impl IntoIterator<Item = SubscriptionsInEvent> {
+    [SubscriptionsInEvent::PeerSubscriptionRequest {
+        src: peer,
+        connection: Some(connection),
+        action,
+    }]
+}
+
+/// Create a new connection closed event sequence for the given peer connection.
+fn new_connection_closed_seq(
+    peer: PeerId,
+    connection: ConnectionId,
+) -> impl IntoIterator<Item = SubscriptionsInEvent> {
+    [SubscriptionsInEvent::PeerConnectionEvent(
+        SubscriptionsPeerConnectionEvent::ConnectionClosed { peer, connection },
+    )]
+}
+
+#[test]
+fn track_connection_scoped_subscriptions_separately() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let remote_peer = new_test_peer_id();
+    let connection_a = ConnectionId::new_unchecked(1);
+    let connection_b = ConnectionId::new_unchecked(2);
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+
+    //// When
+    let input_events = itertools::chain!(
+        new_connection_subscription_seq(
+            remote_peer,
+            connection_a,
+            SubscriptionAction::Subscribe(topic_a.hash())
+        ),
+        new_connection_subscription_seq(
+            remote_peer,
+            connection_b,
+            SubscriptionAction::Subscribe(topic_a.hash())
+        ),
+        new_connection_subscription_seq(
+            remote_peer,
+            connection_b,
+            SubscriptionAction::Subscribe(topic_b.hash())
+        ),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(service.is_connection_subscribed(&remote_peer, &connection_a, &topic_a.hash()));
+    assert!(!service.is_connection_subscribed(&remote_peer, &connection_a, &topic_b.hash()));
+    assert!(service.is_connection_subscribed(&remote_peer, &connection_b, &topic_b.hash()));
+    assert_eq!(
+        service.subscribed_connections(&remote_peer, &topic_b.hash()),
+        Some(vec![connection_b])
+    );
+    assert_eq!(
+        service
+            .peer_subscriptions(&remote_peer)
+            .map(|topics| topics.len()),
+        Some(2),
+        "The peer subscriptions should be the union of its connections subscriptions"
+    );
+
+    // Assert the events
+    assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&output_events[0], SubscriptionsOutEvent::PeerSubscribedMany { peer, topics } => {
+        assert_eq!(peer, &remote_peer);
+        assert_eq!(topics, &[topic_a.hash(), topic_b.hash()]);
+    });
+}
+
+#[test]
+fn peer_is_unsubscribed_once_no_connection_is_subscribed() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let remote_peer = new_test_peer_id();
+    let connection_a = ConnectionId::new_unchecked(1);
+    let connection_b = ConnectionId::new_unchecked(2);
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+
+    let input_events = itertools::chain!(
+        new_connection_subscription_seq(
+            remote_peer,
+            connection_a,
+            SubscriptionAction::Subscribe(topic_a.hash())
+        ),
+        new_connection_subscription_seq(
+            remote_peer,
+            connection_b,
+            SubscriptionAction::Subscribe(topic_a.hash())
+        ),
+        new_connection_subscription_seq(
+            remote_peer,
+            connection_b,
+            SubscriptionAction::Subscribe(topic_b.hash())
+        ),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    // Connection A unsubscribes from Topic A, still subscribed by connection B.
+    let input_events = new_connection_subscription_seq(
+        remote_peer,
+        connection_a,
+        SubscriptionAction::Unsubscribe(topic_a.hash()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    let unsubscribe_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    // Connection B closes.
+    let input_events = new_connection_closed_seq(remote_peer, connection_b);
+    testlib::service::inject_events(&mut service, input_events);
+    let closed_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(
+        unsubscribe_events.is_empty(),
+        "The peer should still be subscribed to Topic A"
+    );
+
+    assert_eq!(closed_events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&closed_events[0], SubscriptionsOutEvent::PeerUnsubscribedMany { peer, topics } => {
+        assert_eq!(peer, &remote_peer);
+        assert_eq!(topics.len(), 2);
+    });
+    assert!(service
+        .connection_subscriptions(&remote_peer, &connection_b)
+        .is_none());
+    assert_eq!(service.topic_subscribers_count(&topic_a.hash()), 0);
+    assert_eq!(service.topic_subscribers_count(&topic_b.hash()), 0);
+}
+
+/// Create a new peer info learned sequence for the given peer and topics.
+fn new_peer_info_learned_seq(
+    peer: PeerId,
+    topics: impl IntoIterator<Item = TopicHash>,
+) -> impl IntoIterator<Item = SubscriptionsInEvent> {
+    [SubscriptionsInEvent::PeerInfoLearned {
+        peer,
+        topics: topics.into_iter().collect(),
+    }]
+}
+
+#[test]
+fn track_learned_peer_info_as_known_remote_peer() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let remote_peer = new_test_peer_id();
+    let topic = new_test_topic();
+
+    //// When
+    let input_events = new_peer_info_learned_seq(remote_peer, [topic.hash()]);
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(
+        !service.is_peer_subscribed(&remote_peer, &topic.hash()),
+        "Known remote peer should not be tracked as connected peer"
+    );
+    assert!(service.peer_subscriptions(&remote_peer).is_none());
+    assert_matches!(service.known_peer_subscriptions(&remote_peer), Some(topics) => {
+        assert!(topics.contains(&topic.hash()));
+    });
+
+    // Assert the events
+    assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&output_events[0], SubscriptionsOutEvent::KnownPeerSubscribed { peer, topic: known_topic } => {
+        assert_eq!(peer, &remote_peer);
+        assert_eq!(known_topic, &topic.hash());
+    });
+}
+
+#[test]
+fn ignore_learned_peer_info_of_connected_peer() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let remote_peer = new_test_peer_id();
+    let topic = new_test_topic();
+
+    let input_events = new_peer_connected_seq(remote_peer);
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    let input_events = new_peer_info_learned_seq(remote_peer, [topic.hash()]);
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(service.known_peer_subscriptions(&remote_peer).is_none());
+    assert!(!service.is_peer_subscribed(&remote_peer, &topic.hash()));
+
+    // Assert the events
+    assert_eq!(output_events.len(), 0, "No events should be emitted");
+}
+
+#[test]
+fn forget_known_remote_peer_on_connection() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let remote_peer = new_test_peer_id();
+    let topic = new_test_topic();
+
+    let input_events = new_peer_info_learned_seq(remote_peer, [topic.hash()]);
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    let input_events = new_peer_connected_seq(remote_peer);
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(service.known_peer_subscriptions(&remote_peer).is_none());
+
+    // Assert the events
+    assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&output_events[0], SubscriptionsOutEvent::KnownPeerForgotten(peer) => {
+        assert_eq!(peer, &remote_peer);
+    });
+}
+
+#[test]
+fn evict_least_recently_learned_known_remote_peer() {
+    //// Given
+    let mut service = BufferedContext::new(SubscriptionsService::new(2, Default::default()));
+
+    let peer_a = new_test_peer_id();
+    let peer_b = new_test_peer_id();
+    let peer_c = new_test_peer_id();
+    let topic = new_test_topic();
+
+    let input_events = itertools::chain!(
+        new_peer_info_learned_seq(peer_a, [topic.hash()]),
+        new_peer_info_learned_seq(peer_b, [topic.hash()]),
+        // Learning about peer A again makes peer B the least recently learned peer.
+        new_peer_info_learned_seq(peer_a, [topic.hash()]),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    let input_events = new_peer_info_learned_seq(peer_c, [topic.hash()]);
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(service.known_peer_subscriptions(&peer_a).is_some());
+    assert!(
+        service.known_peer_subscriptions(&peer_b).is_none(),
+        "Peer B should be evicted"
+    );
+    assert!(service.known_peer_subscriptions(&peer_c).is_some());
+
+    // Assert the events
+    assert_eq!(output_events.len(), 2, "2 events should be emitted");
+    assert_matches!(&output_events[0], SubscriptionsOutEvent::KnownPeerSubscribed { peer, .. } => {
+        assert_eq!(peer, &peer_c);
+    });
+    assert_matches!(&output_events[1], SubscriptionsOutEvent::KnownPeerForgotten(peer) => {
+        assert_eq!(peer, &peer_b);
+    });
+}
+
+#[test]
+fn track_local_subscription_info_and_return_it_on_unsubscription() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let owned_topic = new_test_topic();
+    let plain_topic = new_test_topic();
+
+    let mut owned_sub = SubscriptionBuilder::new(owned_topic.clone());
+    owned_sub.owner("test-plugin");
+
+    let before = Instant::now();
+
+    //// When
+    let input_events = itertools::chain!(
+        [SubscriptionsInEvent::SubscriptionRequest(owned_sub.build())],
+        new_subscribe_seq(plain_topic.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// Then
+    let info = service
+        .subscription_info(&owned_topic.hash())
+        .expect("owned subscription info");
+    assert_eq!(info.owner.as_deref(), Some("test-plugin"));
+    assert!(info.created_at >= before);
+    let created_at = info.created_at;
+
+    // The subscriptions converted from a topic have no owner.
+    let info = service
+        .subscription_info(&plain_topic.hash())
+        .expect("plain subscription info");
+    assert_eq!(info.owner, None);
+
+    //// When
+    let input_events = new_unsubscribe_seq(owned_topic.clone());
+    testlib::service::inject_events(&mut service, input_events);
+
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(service.subscription_info(&owned_topic.hash()).is_none());
+    assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&output_events[0], SubscriptionsOutEvent::Unsubscribed { topic, info } => {
+        assert_eq!(topic, &owned_topic.hash());
+        assert_eq!(info.owner.as_deref(), Some("test-plugin"));
+        assert_eq!(info.created_at, created_at);
+    });
+}
+
+#[test]
+fn register_and_remove_local_subscription_aliases() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let topic = new_test_topic();
+    let alias = TopicHash::from_raw("/test/alias");
+
+    let mut sub = SubscriptionBuilder::new(topic.clone());
+    sub.also_accept_alias(alias.clone());
+
+    //// When
+    let input_events = [SubscriptionsInEvent::SubscriptionRequest(sub.build())];
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(service.alias_topic(&alias), Some(&topic.hash()));
+    assert!(
+        !service.is_subscribed(&alias),
+        "Node should not be subscribed to the alias"
+    );
+
+    //// When
+    testlib::service::inject_events(&mut service, new_unsubscribe_seq(topic.clone()));
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(service.alias_topic(&alias), None);
+}
+
+#[test]
+fn announce_only_the_primary_topic_of_aliased_subscriptions() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let peer = new_test_peer_id();
+    let topic = new_test_topic();
+
+    let mut sub = SubscriptionBuilder::new(topic.clone());
+    sub.also_accept_alias(TopicHash::from_raw("/test/alias"));
+
+    let input_events = [SubscriptionsInEvent::SubscriptionRequest(sub.build())];
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// When
+    testlib::service::inject_events(&mut service, new_peer_connected_seq(peer));
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&output_events[0], SubscriptionsOutEvent::SendSubscriptions { dest, topics } => {
+        assert_eq!(dest, &peer);
+        assert_eq!(topics, &vec![topic.hash()]);
+    });
+}
+
+#[test]
+fn find_the_local_subscription_hashing_the_same_topic_string() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let sha256_topic = Sha256Topic::new("/test/sha256-topic");
+    let ident_topic = IdentTopic::new("/test/ident-topic");
+
+    let input_events = itertools::chain!(
+        new_subscribe_seq(sha256_topic.clone()),
+        new_subscribe_seq(ident_topic.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// Then
+    // A peer announcing the raw topic string of a SHA-256 hashed local subscription.
+    assert_eq!(
+        service.hash_counterpart(&IdentTopic::new("/test/sha256-topic").hash()),
+        Some(&sha256_topic.hash())
+    );
+    // A peer announcing the SHA-256 hash of an identity hashed local subscription.
+    assert_eq!(
+        service.hash_counterpart(&Sha256Topic::new("/test/ident-topic").hash()),
+        Some(&ident_topic.hash())
+    );
+    assert_eq!(
+        service.hash_counterpart(&IdentTopic::new("/test/unknown-topic").hash()),
+        None
+    );
+
+    //// When
+    let input_events = itertools::chain!(
+        new_unsubscribe_seq(sha256_topic),
+        new_unsubscribe_seq(ident_topic),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(
+        service.hash_counterpart(&IdentTopic::new("/test/sha256-topic").hash()),
+        None
+    );
+    assert_eq!(
+        service.hash_counterpart(&Sha256Topic::new("/test/ident-topic").hash()),
+        None
+    );
+}
+
+#[test]
+fn track_peer_subscription_first_seen_and_last_refreshed() {
+    //// Given
+    let mut service = testlib::service::default_test_service::<SubscriptionsService>();
+
+    let peer = new_test_peer_id();
+    let topic = new_test_topic();
+
+    let input_events = itertools::chain!(
+        new_peer_connected_seq(peer),
+        new_peer_subscribe_seq(peer, topic.clone()),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    let first = *service
+        .peer_subscription_info(&peer, &topic.hash())
+        .expect("peer subscription info");
+
+    //// When
+    std::thread::sleep(Duration::from_millis(5));
+
+    let input_events = new_peer_subscribe_seq(peer, topic.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// Then
+    let refreshed = *service
+        .peer_subscription_info(&peer, &topic.hash())
+        .expect("peer subscription info");
+    assert_eq!(first.first_seen, first.last_refreshed);
+    assert_eq!(refreshed.first_seen, first.first_seen);
+    assert!(refreshed.last_refreshed > first.last_refreshed);
+
+    //// When
+    let input_events = new_peer_unsubscribe_seq(peer, topic.clone());
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(
+        service
+            .peer_subscription_info(&peer, &topic.hash())
+            .is_none(),
+        "The peer subscription info should be dropped on unsubscription"
+    );
+
+    //// When
+    let input_events = itertools::chain!(
+        new_peer_subscribe_seq(peer, topic.clone()),
+        new_peer_disconnected_seq(peer),
+    );
+    testlib::service::inject_events(&mut service, input_events);
+    testlib::service::poll(&mut service, &mut noop_context());
+
+    //// Then
+    assert!(
+        service
+            .peer_subscription_info(&peer, &topic.hash())
+            .is_none(),
+        "The peer subscription info should be dropped on disconnection"
+    );
+}
+
+mod debounce {
+    use crate::services::subscriptions::SubscriptionsDebounceService;
+
+    use super::*;
+
+    /// Create a test `SubscriptionsDebounceService` with the given debounce window.
+    fn new_test_service(window: Duration) -> BufferedContext<SubscriptionsDebounceService> {
+        BufferedContext::new(SubscriptionsDebounceService::new(Some(window)))
+    }
+
+    #[test]
+    fn actions_are_not_held_without_debounce_window() {
+        //// Given
+        let mut service = testlib::service::default_test_service::<SubscriptionsDebounceService>();
+
+        let topic = new_test_topic();
+
+        //// When
+        let input_events = [
+            SubscriptionAction::Unsubscribe(topic.hash()),
+            SubscriptionAction::Subscribe(topic.hash()),
+        ];
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(output_events.len(), 2, "Only 2 events should be emitted");
+        assert_eq!(
+            output_events[0],
+            SubscriptionAction::Unsubscribe(topic.hash())
+        );
+        assert_eq!(
+            output_events[1],
+            SubscriptionAction::Subscribe(topic.hash())
+        );
+    }
+
+    #[tokio::test]
+    async fn flapping_subscription_within_window_announces_nothing() {
+        //// Given
+        let mut service = new_test_service(Duration::from_millis(50));
+
+        let topic = new_test_topic();
+
+        //// When
+        let input_events = [
+            SubscriptionAction::Unsubscribe(topic.hash()),
+            SubscriptionAction::Subscribe(topic.hash()),
+        ];
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events_before = testlib::service::async_collect_events(&mut service).await;
+
+        // Wait for the debounce window to elapse
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        let output_events_after = testlib::service::async_collect_events(&mut service).await;
+
+        //// Then
+        assert!(
+            output_events_before.is_empty(),
+            "No events should be emitted before the window elapses"
+        );
+        assert!(
+            output_events_after.is_empty(),
+            "No events should be emitted after the window elapses"
+        );
+    }
+
+    #[tokio::test]
+    async fn subscription_change_within_window_announces_final_state_once() {
+        //// Given
+        let mut service = new_test_service(Duration::from_millis(50));
+
+        let topic = new_test_topic();
+
+        //// When
+        let input_events = [
+            SubscriptionAction::Subscribe(topic.hash()),
+            SubscriptionAction::Unsubscribe(topic.hash()),
+            SubscriptionAction::Subscribe(topic.hash()),
+        ];
+        testlib::service::inject_events(&mut service, input_events);
+
+        let output_events_before = testlib::service::async_collect_events(&mut service).await;
+
+        // Wait for the debounce window to elapse
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        let output_events_after = testlib::service::async_collect_events(&mut service).await;
+
+        //// Then
+        assert!(
+            output_events_before.is_empty(),
+            "No events should be emitted before the window elapses"
+        );
+        assert_eq!(
+            output_events_after.len(),
+            1,
+            "Only 1 event should be emitted"
+        );
+        assert_eq!(
+            output_events_after[0],
+            SubscriptionAction::Subscribe(topic.hash())
+        );
+    }
+}
+
+mod churn {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use crate::services::subscriptions::ChurnParams;
+
+    use super::*;
+
+    /// Create a test `SubscriptionsService` notifying the peers flapping a topic subscription 5
+    /// times, or more, over a 10 heartbeats window.
+    fn new_test_service() -> BufferedContext<SubscriptionsService> {
+        BufferedContext::new(SubscriptionsService::new(
+            2,
+            ChurnParams {
+                window: Duration::from_secs(10),
+                heartbeat_interval: Duration::from_secs(1),
+                flapping_threshold: 30.0,
+            },
+        ))
+    }
+
+    /// Create a new sequence of `count` peer subscription transitions to the given topic,
+    /// alternating subscriptions and unsubscriptions.
+    fn new_peer_flapping_seq<H: Hasher>(
+        peer: PeerId,
+        topic: Topic<H>,
+        count: usize,
+    ) -> impl IntoIterator<Item = SubscriptionsInEvent> {
+        (0..count)
+            .map(|i| {
+                let action = if i % 2 == 0 {
+                    SubscriptionAction::Subscribe(topic.hash())
+                } else {
+                    SubscriptionAction::Unsubscribe(topic.hash())
+                };
+                SubscriptionsInEvent::PeerSubscriptionRequest {
+                    src: peer,
+                    connection: None,
+                    action,
+                }
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// Create a new sequence of `count` heartbeat ticks.
+    fn new_heartbeat_seq(count: usize) -> impl IntoIterator<Item = SubscriptionsInEvent> {
+        std::iter::repeat_with(|| SubscriptionsInEvent::Heartbeat).take(count)
+    }
+
+    /// Collect the peer flapping notifications among the given output events.
+    fn flapping_events(
+        events: impl IntoIterator<Item = SubscriptionsOutEvent>,
+    ) -> Vec<(PeerId, TopicHash, f64)> {
+        events
+            .into_iter()
+            .filter_map(|ev| match ev {
+                SubscriptionsOutEvent::PeerFlapping {
+                    peer,
+                    topic,
+                    transitions_per_min,
+                } => Some((peer, topic, transitions_per_min)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flapping_peer_is_notified_once_per_window() {
+        //// Given
+        let mut service = new_test_service();
+
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        testlib::service::inject_events(&mut service, new_peer_connected_seq(remote_peer));
+        testlib::service::poll(&mut service, &mut noop_context());
+
+        //// When
+        // The threshold is crossed on the 5th transition, the following transitions within the
+        // window are not notified again.
+        let input_events = new_peer_flapping_seq(remote_peer, topic.clone(), 8);
+        testlib::service::inject_events(&mut service, input_events);
+        let first_window_events =
+            testlib::service::collect_events(&mut service, &mut noop_context());
+
+        let input_events = itertools::chain!(
+            new_heartbeat_seq(5),
+            new_peer_flapping_seq(remote_peer, topic.clone(), 2),
+        );
+        testlib::service::inject_events(&mut service, input_events);
+        let same_window_events =
+            testlib::service::collect_events(&mut service, &mut noop_context());
+
+        let input_events = itertools::chain!(
+            new_heartbeat_seq(10),
+            new_peer_flapping_seq(remote_peer, topic.clone(), 5),
+        );
+        testlib::service::inject_events(&mut service, input_events);
+        let next_window_events =
+            testlib::service::collect_events(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(
+            flapping_events(first_window_events),
+            [(remote_peer, topic.hash(), 30.0)]
+        );
+        assert!(
+            flapping_events(same_window_events).is_empty(),
+            "The flapping peer should be notified at most once per window"
+        );
+        assert_eq!(
+            flapping_events(next_window_events),
+            [(remote_peer, topic.hash(), 30.0)]
+        );
+    }
+
+    #[test]
+    fn churn_decays_on_heartbeat() {
+        //// Given
+        let mut service = new_test_service();
+
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        let input_events = itertools::chain!(
+            new_peer_connected_seq(remote_peer),
+            new_peer_flapping_seq(remote_peer, topic.clone(), 3),
+            new_heartbeat_seq(5),
+            new_peer_unsubscribe_seq(remote_peer, topic.clone()),
+            // A redundant unsubscription is not a transition.
+            new_peer_unsubscribe_seq(remote_peer, topic.clone()),
+            new_peer_subscribe_seq(remote_peer, topic.clone()),
+        );
+        testlib::service::inject_events(&mut service, input_events);
+        testlib::service::poll(&mut service, &mut noop_context());
+
+        //// When
+        let churn_within_window = service.churn(&remote_peer);
+
+        testlib::service::inject_events(&mut service, new_heartbeat_seq(5));
+        testlib::service::poll(&mut service, &mut noop_context());
+        let churn_partially_decayed = service.churn(&remote_peer);
+
+        testlib::service::inject_events(&mut service, new_heartbeat_seq(5));
+        testlib::service::poll(&mut service, &mut noop_context());
+        let churn_decayed = service.churn(&remote_peer);
+
+        //// Then
+        assert_eq!(churn_within_window.transitions, 5);
+        assert_eq!(
+            churn_within_window.topics,
+            BTreeMap::from([(topic.hash(), 5)])
+        );
+        assert_eq!(
+            churn_partially_decayed.transitions, 2,
+            "The transitions older than the window should be evicted"
+        );
+        assert_eq!(churn_decayed, Default::default());
+    }
+
+    #[test]
+    fn non_flapping_peer_stays_silent() {
+        //// Given
+        let mut service = new_test_service();
+
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        testlib::service::inject_events(&mut service, new_peer_connected_seq(remote_peer));
+        testlib::service::poll(&mut service, &mut noop_context());
+
+        //// When
+        // One transition every 3 heartbeats, i.e., at most 4 transitions per window.
+        let mut output_events = Vec::new();
+        for i in 0..30 {
+            let input_events = itertools::chain!(
+                new_peer_flapping_seq(remote_peer, topic.clone(), 2)
+                    .into_iter()
+                    .skip(i % 2)
+                    .take(1),
+                new_heartbeat_seq(3),
+            );
+            testlib::service::inject_events(&mut service, input_events);
+            output_events.extend(testlib::service::collect_events(
+                &mut service,
+                &mut noop_context(),
+            ));
+        }
+
+        //// Then
+        assert!(
+            flapping_events(output_events).is_empty(),
+            "No flapping event should be emitted"
+        );
+        assert_eq!(service.churn(&remote_peer).transitions, 3);
+    }
+
+    #[test]
+    fn peer_churn_is_dropped_on_disconnection() {
+        //// Given
+        let mut service = new_test_service();
+
+        let remote_peer = new_test_peer_id();
+        let topic = new_test_topic();
+
+        let input_events = itertools::chain!(
+            new_peer_connected_seq(remote_peer),
+            new_peer_flapping_seq(remote_peer, topic.clone(), 3),
+        );
+        testlib::service::inject_events(&mut service, input_events);
+        testlib::service::poll(&mut service, &mut noop_context());
+
+        //// When
+        testlib::service::inject_events(&mut service, new_peer_disconnected_seq(remote_peer));
+        testlib::service::poll(&mut service, &mut noop_context());
+
+        //// Then
+        assert_eq!(service.churn(&remote_peer), Default::default());
+    }
+}
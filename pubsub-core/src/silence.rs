@@ -0,0 +1,207 @@
+//! The subscribed topics silence watchdog (see
+//! [`Config::silence_watchdog`](crate::Config::silence_watchdog)).
+
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use libp2p::identity::PeerId;
+
+use crate::topic::TopicHash;
+
+/// A silence watchdog alert: the local node has been receiving no message on its subscribed
+/// topics for longer than the watchdog timeout, while remote peers are subscribed to them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SilenceAlert {
+    /// The locally subscribed topics active peers are subscribed to.
+    pub(crate) topics: Vec<TopicHash>,
+    /// The active peers subscribed to the topics.
+    pub(crate) peers: Vec<PeerId>,
+    /// The time since the last message was received on a subscribed topic, or since the watched
+    /// topics had subscribed peers, if later.
+    pub(crate) silent_for: Duration,
+}
+
+/// Watches the messages received on the locally subscribed topics, to detect the nodes that are
+/// healthy, i.e., connected to peers subscribed to the same topics, but receive no traffic.
+///
+/// The watchdog fires once per silence period: once fired, it is re-armed only by a message
+/// received on a subscribed topic.
+#[derive(Debug)]
+pub(crate) struct SilenceWatchdog {
+    /// The silence duration after which the watchdog fires.
+    timeout: Duration,
+    /// The time the last message was received, by locally subscribed topic.
+    last_received: HashMap<TopicHash, Instant>,
+    /// The time the watched topics started having subscribed peers. `None` if no locally
+    /// subscribed topic has an active subscribed peer.
+    watching_since: Option<Instant>,
+    /// Whether the watchdog fired for the current silence period.
+    fired: bool,
+}
+
+impl SilenceWatchdog {
+    /// Create a new silence watchdog firing after the given silence duration.
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last_received: Default::default(),
+            watching_since: None,
+            fired: false,
+        }
+    }
+
+    /// Stop accounting the received messages of the topic, e.g., on unsubscription.
+    pub(crate) fn untrack_topic(&mut self, topic: &TopicHash) {
+        self.last_received.remove(topic);
+    }
+
+    /// Record a message received on a locally subscribed topic, re-arming the watchdog.
+    pub(crate) fn record_received(&mut self, topic: &TopicHash, now: Instant) {
+        self.last_received.insert(topic.clone(), now);
+        self.fired = false;
+    }
+
+    /// Check the silence at `now`, given the locally subscribed topics and, by topic, the active
+    /// peers subscribed to it.
+    ///
+    /// Returns an alert if the watched topics have been silent for longer than the timeout and
+    /// the watchdog did not fire yet for this silence period.
+    pub(crate) fn check(
+        &mut self,
+        watched: Vec<(TopicHash, Vec<PeerId>)>,
+        now: Instant,
+    ) -> Option<SilenceAlert> {
+        let watched = watched
+            .into_iter()
+            .filter(|(_, peers)| !peers.is_empty())
+            .collect::<Vec<_>>();
+        if watched.is_empty() {
+            self.watching_since = None;
+            return None;
+        }
+
+        let watching_since = *self.watching_since.get_or_insert(now);
+        let last_received = watched
+            .iter()
+            .filter_map(|(topic, _)| self.last_received.get(topic))
+            .max()
+            .copied();
+        let silent_since = last_received.map_or(watching_since, |at| at.max(watching_since));
+        let silent_for = now.saturating_duration_since(silent_since);
+        if self.fired || silent_for < self.timeout {
+            return None;
+        }
+
+        self.fired = true;
+        let mut topics = Vec::with_capacity(watched.len());
+        let mut peers = BTreeSet::new();
+        for (topic, topic_peers) in watched {
+            topics.push(topic);
+            peers.extend(topic_peers);
+        }
+        Some(SilenceAlert {
+            topics,
+            peers: peers.into_iter().collect(),
+            silent_for,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_topic(name: &str) -> TopicHash {
+        TopicHash::from_raw(format!("/test/{name}"))
+    }
+
+    #[test]
+    fn silent_subscribed_topics_with_peers_fire_once() {
+        //// Given
+        let topic = new_test_topic("topic");
+        let peer = PeerId::random();
+        let start = Instant::now();
+
+        let mut watchdog = SilenceWatchdog::new(Duration::from_secs(60));
+        let watched = || vec![(topic.clone(), vec![peer])];
+
+        //// When
+        let first = watchdog.check(watched(), start);
+        let before_timeout = watchdog.check(watched(), start + Duration::from_secs(59));
+        let alert = watchdog.check(watched(), start + Duration::from_secs(61));
+        let after_alert = watchdog.check(watched(), start + Duration::from_secs(600));
+
+        //// Then
+        assert_eq!(first, None);
+        assert_eq!(before_timeout, None);
+        assert_eq!(
+            alert,
+            Some(SilenceAlert {
+                topics: vec![topic.clone()],
+                peers: vec![peer],
+                silent_for: Duration::from_secs(61),
+            })
+        );
+        assert_eq!(after_alert, None, "The watchdog should fire once");
+    }
+
+    #[test]
+    fn received_traffic_or_no_subscribed_peers_do_not_fire() {
+        //// Given
+        let topic = new_test_topic("topic");
+        let peer = PeerId::random();
+        let start = Instant::now();
+
+        let mut watchdog = SilenceWatchdog::new(Duration::from_secs(60));
+
+        //// When
+        // The topic has traffic every 30 seconds
+        let mut alerts = Vec::new();
+        for tick in 0..10 {
+            let now = start + Duration::from_secs(30 * tick);
+            watchdog.record_received(&topic, now);
+            alerts.extend(watchdog.check(vec![(topic.clone(), vec![peer])], now));
+        }
+
+        // No peer is subscribed to the silent topic
+        let lonely = new_test_topic("lonely");
+        let mut lonely_watchdog = SilenceWatchdog::new(Duration::from_secs(60));
+        lonely_watchdog.check(vec![(lonely.clone(), vec![])], start);
+        let lonely_alert =
+            lonely_watchdog.check(vec![(lonely, vec![])], start + Duration::from_secs(600));
+
+        //// Then
+        assert!(alerts.is_empty(), "A topic with traffic should not fire");
+        assert_eq!(lonely_alert, None, "A topic without peers should not fire");
+    }
+
+    #[test]
+    fn watchdog_re_arms_after_recovery_and_fires_on_relapse() {
+        //// Given
+        let topic = new_test_topic("topic");
+        let peer = PeerId::random();
+        let start = Instant::now();
+
+        let mut watchdog = SilenceWatchdog::new(Duration::from_secs(60));
+        let watched = || vec![(topic.clone(), vec![peer])];
+
+        watchdog.check(watched(), start);
+        let first = watchdog.check(watched(), start + Duration::from_secs(61));
+
+        //// When
+        // The traffic recovers, then stops again
+        watchdog.record_received(&topic, start + Duration::from_secs(100));
+        let recovered = watchdog.check(watched(), start + Duration::from_secs(100));
+        let relapse_early = watchdog.check(watched(), start + Duration::from_secs(150));
+        let relapse = watchdog.check(watched(), start + Duration::from_secs(170));
+
+        //// Then
+        assert!(first.is_some());
+        assert_eq!(recovered, None);
+        assert_eq!(relapse_early, None);
+        assert_eq!(
+            relapse.map(|alert| alert.silent_for),
+            Some(Duration::from_secs(70))
+        );
+    }
+}
@@ -1,14 +1,53 @@
+use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
+use crate::delivery::DeliveryMode;
+use crate::message::Priority;
 use crate::message_id::{MessageId, MessageIdFn};
 use crate::topic::{Hasher, Topic, TopicHash};
 
+/// Errors that can occur when updating a subscription.
+///
+/// See [`Behaviour::set_message_id_fn`](crate::Behaviour::set_message_id_fn) for more details.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum SubscriptionError {
+    /// The local node is not subscribed to the topic.
+    #[error("not subscribed to topic: {0}")]
+    NotSubscribed(TopicHash),
+}
+
 #[derive(Clone)]
 pub struct Subscription {
     /// The topic to subscribe to.
     pub topic: TopicHash,
     /// The message id function to use for this subscription.
     pub message_id_fn: Option<Rc<dyn MessageIdFn<Output = MessageId>>>,
+    /// The protocol id of the router owning the topic, if the behaviour hosts multiple protocol
+    /// routers (see [`CompositeProtocol`](crate::protocol::CompositeProtocol)).
+    pub protocol_hint: Option<String>,
+    /// Whether the messages received on the topic are delivered in their author sequence number
+    /// order (see [`SubscriptionBuilder::ordered_delivery`]).
+    pub ordered_delivery: bool,
+    /// Whether the recently cached messages of the topic are sent to the peers subscribing to it
+    /// (see [`SubscriptionBuilder::backfill_on_peer_subscribe`]).
+    pub backfill_on_peer_subscribe: bool,
+    /// Whether the messages received on the topic are forwarded to the other peers (see
+    /// [`SubscriptionBuilder::forwarding`]).
+    pub forwarding: bool,
+    /// The internal owner that requested the subscription, if any (see
+    /// [`SubscriptionBuilder::owner`]).
+    pub owner: Option<String>,
+    /// The additional topic hashes whose messages are accepted as messages of the topic (see
+    /// [`SubscriptionBuilder::also_accept_alias`]).
+    pub aliases: Vec<TopicHash>,
+    /// The local scheduling priority of the messages forwarded on the topic (see
+    /// [`SubscriptionBuilder::priority`]).
+    pub priority: Priority,
+    /// How the messages published by the local node on the topic are delivered (see
+    /// [`SubscriptionBuilder::delivery_mode`]).
+    pub delivery_mode: DeliveryMode,
 }
 
 impl std::fmt::Debug for Subscription {
@@ -22,6 +61,17 @@ impl std::fmt::Debug for Subscription {
                     Some(_) => &"MessageIdFn(<fn>)",
                 },
             )
+            .field("protocol_hint", &self.protocol_hint)
+            .field("ordered_delivery", &self.ordered_delivery)
+            .field(
+                "backfill_on_peer_subscribe",
+                &self.backfill_on_peer_subscribe,
+            )
+            .field("forwarding", &self.forwarding)
+            .field("owner", &self.owner)
+            .field("aliases", &self.aliases)
+            .field("priority", &self.priority)
+            .field("delivery_mode", &self.delivery_mode)
             .finish()
     }
 }
@@ -31,6 +81,14 @@ impl From<TopicHash> for Subscription {
         Self {
             topic,
             message_id_fn: None,
+            protocol_hint: None,
+            ordered_delivery: false,
+            backfill_on_peer_subscribe: false,
+            forwarding: true,
+            owner: None,
+            aliases: Vec::new(),
+            priority: Priority::Normal,
+            delivery_mode: DeliveryMode::Broadcast,
         }
     }
 }
@@ -45,6 +103,14 @@ impl<H: Hasher> From<Topic<H>> for Subscription {
 pub struct SubscriptionBuilder {
     topic: TopicHash,
     message_id_fn: Option<Rc<dyn MessageIdFn<Output = MessageId>>>,
+    protocol_hint: Option<String>,
+    ordered_delivery: bool,
+    backfill_on_peer_subscribe: bool,
+    forwarding: bool,
+    owner: Option<String>,
+    aliases: Vec<TopicHash>,
+    priority: Priority,
+    delivery_mode: DeliveryMode,
 }
 
 impl SubscriptionBuilder {
@@ -53,11 +119,21 @@ impl SubscriptionBuilder {
         Self {
             topic: topic.hash(),
             message_id_fn: None,
+            protocol_hint: None,
+            ordered_delivery: false,
+            backfill_on_peer_subscribe: false,
+            forwarding: true,
+            owner: None,
+            aliases: Vec::new(),
+            priority: Priority::Normal,
+            delivery_mode: DeliveryMode::Broadcast,
         }
     }
 
     /// A user-defined function allowing the user to specify the message id of a pub-sub message.
-    /// The default value is to concatenate the source peer id with a sequence number. Setting this
+    /// The default value is the configured default message id function (see
+    /// [`ConfigBuilder::default_message_id_fn`](crate::ConfigBuilder::default_message_id_fn)),
+    /// which concatenates the source peer id with a sequence number. Setting this
     /// parameter allows the user to address packets arbitrarily. One example is content based
     /// addressing, where this function may be set to `hash(message)`. This would prevent messages
     /// of the same content from being duplicated.
@@ -73,10 +149,224 @@ impl SubscriptionBuilder {
         self
     }
 
+    /// The protocol id of the router that should own the topic.
+    ///
+    /// Only relevant if the behaviour hosts multiple protocol routers (see
+    /// [`CompositeProtocol`](crate::protocol::CompositeProtocol)). The subscription is only
+    /// notified to the router whose protocol ids include the hinted protocol id. By default, the
+    /// subscription is notified to all the routers.
+    pub fn protocol_hint(&mut self, protocol: impl Into<String>) -> &mut Self {
+        self.protocol_hint = Some(protocol.into());
+        self
+    }
+
+    /// Deliver the messages received on the topic in their author sequence number order.
+    ///
+    /// A received message whose author sequence number indicates a gap relative to the last
+    /// delivered message of the same author is held back, for up to the
+    /// [maximum ordering delay](crate::Config::ordering_max_delay), until the missing messages are
+    /// received. Once the delay expires, the held back messages are delivered skipping the missing
+    /// ones, and an [`Event::MessageGap`](crate::Event::MessageGap) event is emitted. The messages
+    /// without an author or an 8-byte big-endian sequence number are delivered immediately.
+    ///
+    /// By default, the messages are delivered as they are received.
+    pub fn ordered_delivery(&mut self, ordered: bool) -> &mut Self {
+        self.ordered_delivery = ordered;
+        self
+    }
+
+    /// Send the recently cached messages of the topic to the peers subscribing to it.
+    ///
+    /// When a connected peer subscribes to the topic, the messages seen on the topic during the
+    /// last [`Config::backfill_max_age`](crate::Config::backfill_max_age), up to
+    /// [`Config::backfill_max_messages`](crate::Config::backfill_max_messages), are forwarded to
+    /// that peer only, oldest first, and an [`Event::BackfillSent`](crate::Event::BackfillSent)
+    /// event is emitted. This is useful for low-rate topics, e.g., configuration broadcasts, so
+    /// new subscribers do not wait for the next publication. The peer deduplicates the messages
+    /// it already received.
+    ///
+    /// The messages are only cached for the [message cache TTL](crate::Config::message_cache_ttl).
+    ///
+    /// By default, no message is backfilled.
+    pub fn backfill_on_peer_subscribe(&mut self, backfill: bool) -> &mut Self {
+        self.backfill_on_peer_subscribe = backfill;
+        self
+    }
+
+    /// Forward the messages received on the topic to the other peers.
+    ///
+    /// If disabled, the node is a pure consumer of the topic: the received messages are cached
+    /// and delivered locally, but they are not handed to the protocol router, so they are never
+    /// relayed. The messages published by the local node are sent as usual. This is useful for
+    /// edge nodes on bandwidth-constrained links.
+    ///
+    /// A node not forwarding a topic still counts as a topic subscriber for its peers, which may
+    /// rely on it to relay the messages. If many nodes disable the forwarding, the topic
+    /// messages may not reach all the subscribers, e.g., the peers only connected through a
+    /// non-forwarding node.
+    ///
+    /// By default, the received messages are forwarded.
+    pub fn forwarding(&mut self, forwarding: bool) -> &mut Self {
+        self.forwarding = forwarding;
+        self
+    }
+
+    /// The internal owner requesting the subscription, e.g., the name of the plugin subscribing
+    /// to the topic.
+    ///
+    /// The owner is only kept as the subscription metadata (see
+    /// [`Behaviour::subscription_info`](crate::Behaviour::subscription_info)), so the operators
+    /// can attribute the subscriptions, and clean up the orphan ones. It is not announced to the
+    /// peers.
+    ///
+    /// By default, the subscription has no owner.
+    pub fn owner(&mut self, owner: impl Into<String>) -> &mut Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    /// Accept the messages received on another topic hash as messages of the topic.
+    ///
+    /// This is a compatibility mode for the peers hashing the topic string differently, e.g., the
+    /// peers announcing and publishing on the raw topic string (see [`IdentTopic`](
+    /// crate::IdentTopic)) while the local node subscribes with the SHA-256 hash (see
+    /// [`Sha256Topic`](crate::Sha256Topic)). The messages received on the alias are delivered,
+    /// deduplicated and forwarded as messages of the subscription topic. The subscription is only
+    /// announced to the peers for the subscription topic, not for its aliases.
+    ///
+    /// The aliases are removed when unsubscribing from the topic.
+    ///
+    /// By default, the subscription has no alias.
+    pub fn also_accept_alias(&mut self, alias: impl Into<TopicHash>) -> &mut Self {
+        let alias = alias.into();
+        if alias != self.topic && !self.aliases.contains(&alias) {
+            self.aliases.push(alias);
+        }
+        self
+    }
+
+    /// The local scheduling priority of the messages forwarded on the topic.
+    ///
+    /// The messages received on a high priority topic are forwarded ahead of the normal priority
+    /// messages queued toward the same peer (see [`Message::set_priority`](
+    /// crate::Message::set_priority)). The messages published on the topic are sent with the
+    /// highest of their own priority and the topic priority.
+    ///
+    /// The priority only applies to the local node: it is neither announced nor propagated to the
+    /// peers.
+    ///
+    /// By default, the topic messages are of normal priority.
+    pub fn priority(&mut self, priority: Priority) -> &mut Self {
+        self.priority = priority;
+        self
+    }
+
+    /// How the messages published by the local node on the topic are delivered to the topic's
+    /// subscribed peers.
+    ///
+    /// In [round-robin](DeliveryMode::RoundRobinPublish) mode, every published message is sent
+    /// to exactly one connected subscribed peer, rotating through the topic's subscribed peers,
+    /// instead of to all of them. The selected peer is returned by
+    /// [`Behaviour::publish_with_receipt`](crate::Behaviour::publish_with_receipt). The received
+    /// messages are forwarded as usual, so the subscribers should disable the topic forwarding
+    /// (see [`SubscriptionBuilder::forwarding`]).
+    ///
+    /// By default, the published messages are broadcast.
+    pub fn delivery_mode(&mut self, mode: DeliveryMode) -> &mut Self {
+        self.delivery_mode = mode;
+        self
+    }
+
     pub fn build(self) -> Subscription {
         Subscription {
             topic: self.topic,
             message_id_fn: self.message_id_fn,
+            protocol_hint: self.protocol_hint,
+            ordered_delivery: self.ordered_delivery,
+            backfill_on_peer_subscribe: self.backfill_on_peer_subscribe,
+            forwarding: self.forwarding,
+            owner: self.owner,
+            aliases: self.aliases,
+            priority: self.priority,
+            delivery_mode: self.delivery_mode,
+        }
+    }
+}
+
+/// Limits the topic hash mismatch notifications to one per interval and received topic.
+#[derive(Debug)]
+pub(crate) struct TopicHashMismatchNotifications {
+    /// The minimum time between two notifications for the same received topic.
+    interval: Duration,
+    /// The time of the last notification, by received topic.
+    notified: HashMap<TopicHash, Instant>,
+}
+
+impl TopicHashMismatchNotifications {
+    /// Create a new notifications limiter with the given interval.
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            notified: Default::default(),
+        }
+    }
+
+    /// Returns `true` if the topic hash mismatch of the received topic must be notified, i.e., it
+    /// was not notified during the last interval.
+    pub(crate) fn notify(&mut self, topic: &TopicHash, now: Instant) -> bool {
+        if let Some(last) = self.notified.get(topic) {
+            if now.saturating_duration_since(*last) < self.interval {
+                return false;
+            }
+        }
+
+        // Forget the topics not notified during the last interval, so the received topics do not
+        // accumulate.
+        let interval = self.interval;
+        self.notified
+            .retain(|_, last| now.saturating_duration_since(*last) < interval);
+        self.notified.insert(topic.clone(), now);
+        true
+    }
+}
+
+/// The metadata of a local subscription.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SubscriptionInfo {
+    /// The internal owner that requested the subscription, if any (see
+    /// [`SubscriptionBuilder::owner`]).
+    pub owner: Option<String>,
+    /// The time the subscription was processed.
+    pub created_at: Instant,
+}
+
+impl SubscriptionInfo {
+    /// Create a new subscription info, created at the given time.
+    #[must_use]
+    pub fn new(owner: Option<String>, created_at: Instant) -> Self {
+        Self { owner, created_at }
+    }
+}
+
+/// The metadata of a remote peer subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PeerSubscriptionInfo {
+    /// The time the peer subscription was first received.
+    pub first_seen: Instant,
+    /// The time the peer subscription was last received, i.e., announced again while already
+    /// subscribed.
+    pub last_refreshed: Instant,
+}
+
+impl PeerSubscriptionInfo {
+    /// Create a new peer subscription info, first seen at the given time.
+    #[must_use]
+    pub fn new(first_seen: Instant) -> Self {
+        Self {
+            first_seen,
+            last_refreshed: first_seen,
         }
     }
 }
@@ -1,6 +1,10 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::convert::Infallible;
 use std::fmt;
+use std::hash::Hash;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use base64::prelude::*;
 use prost::Message as _;
@@ -24,7 +28,7 @@ pub struct IdentityHash;
 impl Hasher for IdentityHash {
     /// Creates a [`TopicHash`] as a raw string.
     fn hash(topic_string: String) -> TopicHash {
-        TopicHash { hash: topic_string }
+        TopicHash::from_raw(topic_string)
     }
 }
 
@@ -45,28 +49,57 @@ impl Hasher for Sha256Hash {
             .encode(&mut bytes)
             .expect("Encoding to succeed");
         let hash = BASE64_STANDARD.encode(Sha256::digest(&bytes));
-        TopicHash { hash }
+        TopicHash::from_raw(hash)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// The topic hash.
+///
+/// The `Hash`, `Eq` and `Ord` implementations are equivalent to the ones of the underlying hash
+/// string, so a `TopicHash` can be looked up in maps and sets by `&str` (see the [`Borrow<str>`]
+/// implementation).
+///
+/// The hash string is reference counted: cloning a `TopicHash` does not allocate, and comparing
+/// two clones of the same hash does not compare the hash strings.
+#[derive(Debug, Clone, PartialOrd, Ord)]
 pub struct TopicHash {
     /// The topic hash. Stored as a string to align with the protobuf API.
-    hash: String,
+    hash: Arc<str>,
 }
 
 impl TopicHash {
     pub fn from_raw<T: Into<String>>(raw: T) -> Self {
-        Self { hash: raw.into() }
+        Self {
+            hash: Arc::from(raw.into()),
+        }
     }
 
     pub fn into_string(self) -> String {
-        self.hash
+        self.hash.to_string()
     }
 
     pub fn as_str(&self) -> &str {
         &self.hash
     }
+
+    /// Returns `true` if both topic hashes share the same hash string allocation.
+    pub(crate) fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.hash, &other.hash)
+    }
+}
+
+impl PartialEq for TopicHash {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.hash == other.hash
+    }
+}
+
+impl Eq for TopicHash {}
+
+impl Hash for TopicHash {
+    fn hash<S: std::hash::Hasher>(&self, state: &mut S) {
+        self.as_str().hash(state)
+    }
 }
 
 impl<T: Into<String>> From<T> for TopicHash {
@@ -89,8 +122,106 @@ impl AsRef<str> for TopicHash {
     }
 }
 
+impl Borrow<str> for TopicHash {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// Errors that can occur when validating a topic string.
+///
+/// See [`TopicValidation::validate`] for more details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum TopicValidationError {
+    /// The topic is longer than the maximum length.
+    #[error("topic too long: {length} bytes (max. {max_length} bytes)")]
+    TooLong { length: usize, max_length: usize },
+
+    /// The topic contains a control character.
+    #[error("topic contains a control character")]
+    ControlCharacter,
+
+    /// The topic contains a Unicode replacement character, i.e., it was not valid UTF-8.
+    #[error("topic is not valid UTF-8")]
+    InvalidUtf8,
+
+    /// The topic was rejected by the custom validation function.
+    #[error("topic rejected by the custom validation")]
+    Rejected,
+}
+
+/// The topic strings validation rules.
+///
+/// The rules apply to the local subscriptions (see
+/// [`Behaviour::subscribe`](crate::Behaviour::subscribe)) and to the topics announced by the
+/// remote peers, whose invalid subscription actions are dropped.
+#[derive(Debug, Clone, Copy)]
+pub struct TopicValidation {
+    /// The maximum length of a topic, in bytes.
+    pub max_length: usize,
+
+    /// Whether the topics may contain control characters, e.g., a new line.
+    pub allow_control_chars: bool,
+
+    /// Whether to reject the topics containing the Unicode replacement character (`U+FFFD`),
+    /// i.e., the topics that were not valid UTF-8 before a lossy conversion.
+    pub require_utf8: bool,
+
+    /// A custom validation function, returning `false` to reject a topic.
+    pub custom: Option<fn(&str) -> bool>,
+}
+
+impl Default for TopicValidation {
+    fn default() -> Self {
+        Self {
+            max_length: 1024,
+            allow_control_chars: false,
+            require_utf8: true,
+            custom: None,
+        }
+    }
+}
+
+impl TopicValidation {
+    /// Validate the topic string against the validation rules.
+    ///
+    /// A topic is valid if:
+    /// - It is not longer than the maximum length.
+    /// - It contains no control characters, unless allowed.
+    /// - It contains no Unicode replacement character, if UTF-8 is required.
+    /// - The custom validation function, if any, accepts it.
+    pub fn validate(&self, topic: &str) -> Result<(), TopicValidationError> {
+        if topic.len() > self.max_length {
+            return Err(TopicValidationError::TooLong {
+                length: topic.len(),
+                max_length: self.max_length,
+            });
+        }
+
+        if !self.allow_control_chars && topic.chars().any(char::is_control) {
+            return Err(TopicValidationError::ControlCharacter);
+        }
+
+        if self.require_utf8 && topic.contains(char::REPLACEMENT_CHARACTER) {
+            return Err(TopicValidationError::InvalidUtf8);
+        }
+
+        if let Some(custom) = self.custom {
+            if !custom(topic) {
+                return Err(TopicValidationError::Rejected);
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// A pub-sub topic.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+///
+/// Two topics are equal if their topic names are equal. The `Hash`, `Eq` and `Ord` implementations
+/// are based on the topic name and do not depend on the topic hasher type.
+#[derive(Debug, Clone)]
 pub struct Topic<H: Hasher> {
     topic: String,
     phantom_data: std::marker::PhantomData<H>,
@@ -110,11 +241,58 @@ impl<H: Hasher> Topic<H> {
         }
     }
 
+    /// Create a new topic, validating the topic name against the topic validation rules.
+    ///
+    /// See [`TopicValidation::validate`] for more details.
+    pub fn try_new<T: Into<String>>(
+        topic: T,
+        validation: &TopicValidation,
+    ) -> Result<Self, TopicValidationError> {
+        let topic = topic.into();
+        validation.validate(&topic)?;
+        Ok(Self::new(topic))
+    }
+
     pub fn hash(&self) -> TopicHash {
         H::hash(self.topic.clone())
     }
 }
 
+impl<H: Hasher> PartialEq for Topic<H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.topic == other.topic
+    }
+}
+
+impl<H: Hasher> Eq for Topic<H> {}
+
+impl<H: Hasher> PartialOrd for Topic<H> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<H: Hasher> Ord for Topic<H> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.topic.cmp(&other.topic)
+    }
+}
+
+impl<H: Hasher> Hash for Topic<H> {
+    fn hash<S: std::hash::Hasher>(&self, state: &mut S) {
+        self.topic.hash(state)
+    }
+}
+
+impl<H: Hasher> FromStr for Topic<H> {
+    type Err = Infallible;
+
+    /// Parses a topic from its topic name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
 impl<H: Hasher> fmt::Display for Topic<H> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.topic)
@@ -126,3 +304,226 @@ impl fmt::Display for TopicHash {
         write!(f, "{}", self.hash)
     }
 }
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{Hasher, Topic, TopicHash};
+
+    impl Serialize for TopicHash {
+        /// Serializes the topic hash as a string.
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TopicHash {
+        /// Deserializes the topic hash from a string.
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            String::deserialize(deserializer).map(TopicHash::from_raw)
+        }
+    }
+
+    impl<H: Hasher> Serialize for Topic<H> {
+        /// Serializes the topic as its topic name string.
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&self.topic)
+        }
+    }
+
+    impl<'de, H: Hasher> Deserialize<'de> for Topic<H> {
+        /// Deserializes the topic from its topic name string.
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            String::deserialize(deserializer).map(Topic::new)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    use super::*;
+
+    #[test]
+    fn topic_hash_map_key_can_be_queried_by_str() {
+        //// Given
+        let mut map = HashMap::new();
+        map.insert(TopicHash::from_raw("/test/topic-a"), 1);
+        map.insert(TopicHash::from_raw("/test/topic-b"), 2);
+
+        //// Then
+        assert_eq!(map.get("/test/topic-a"), Some(&1));
+        assert_eq!(map.get("/test/topic-b"), Some(&2));
+        assert_eq!(map.get("/test/topic-c"), None);
+    }
+
+    #[test]
+    fn topics_can_be_used_as_map_keys() {
+        //// Given
+        let topic_a = IdentTopic::new("/test/topic-a");
+        let topic_b = IdentTopic::new("/test/topic-b");
+
+        //// When
+        let hash_set = HashSet::from([topic_a.clone(), topic_b.clone(), topic_a.clone()]);
+        let btree_map = BTreeMap::from([(topic_b.clone(), 2), (topic_a.clone(), 1)]);
+
+        //// Then
+        assert_eq!(hash_set.len(), 2);
+        assert!(hash_set.contains(&topic_a));
+        assert!(hash_set.contains(&topic_b));
+
+        assert_eq!(
+            btree_map.keys().collect::<Vec<_>>(),
+            vec![&topic_a, &topic_b],
+            "Topics should be ordered by name"
+        );
+    }
+
+    #[test]
+    fn topic_hash_from_str_display_round_trip() {
+        //// Given
+        let topic = Sha256Topic::new("/test/topic").hash();
+
+        //// When
+        let parsed = topic.to_string().parse::<TopicHash>().unwrap();
+
+        //// Then
+        assert_eq!(parsed, topic);
+    }
+
+    #[test]
+    fn topic_from_str_display_round_trip() {
+        //// Given
+        let topic = IdentTopic::new("/test/topic");
+
+        //// When
+        let parsed = topic.to_string().parse::<IdentTopic>().unwrap();
+
+        //// Then
+        assert_eq!(parsed, topic);
+        assert_eq!(parsed.hash(), topic.hash());
+    }
+
+    #[test]
+    fn topic_hashes_are_stable() {
+        //// Given
+        let ident_topic = IdentTopic::new("/test/topic");
+        let sha256_topic = Sha256Topic::new("/test/topic");
+
+        //// Then
+        assert_eq!(ident_topic.hash().as_str(), "/test/topic");
+        assert_eq!(
+            sha256_topic.hash().as_str(),
+            "mmFkh4m3WgNeGMfyYPEqcqDybspvteUBFO/EHoGR6/w="
+        );
+    }
+
+    #[test]
+    fn topic_longer_than_max_length_is_rejected() {
+        //// Given
+        let validation = TopicValidation {
+            max_length: 8,
+            ..Default::default()
+        };
+
+        //// Then
+        assert!(IdentTopic::try_new("/test/a", &validation).is_ok());
+        assert_eq!(
+            IdentTopic::try_new("/test/topic", &validation),
+            Err(TopicValidationError::TooLong {
+                length: 11,
+                max_length: 8
+            })
+        );
+    }
+
+    #[test]
+    fn topic_with_control_characters_is_rejected_unless_allowed() {
+        //// Given
+        let default_validation = TopicValidation::default();
+        let permissive_validation = TopicValidation {
+            allow_control_chars: true,
+            ..Default::default()
+        };
+
+        //// Then
+        for topic in ["/test/topic\n", "/test/\ttopic", "/test/topic\u{7f}"] {
+            assert_eq!(
+                default_validation.validate(topic),
+                Err(TopicValidationError::ControlCharacter),
+                "{topic:?} should be rejected"
+            );
+            assert_eq!(permissive_validation.validate(topic), Ok(()));
+        }
+    }
+
+    #[test]
+    fn topic_with_replacement_character_is_rejected_if_utf8_is_required() {
+        //// Given
+        let topic = String::from_utf8_lossy(b"/test/topic-\xff").into_owned();
+        let lossy_validation = TopicValidation {
+            require_utf8: false,
+            ..Default::default()
+        };
+
+        //// Then
+        assert_eq!(
+            TopicValidation::default().validate(&topic),
+            Err(TopicValidationError::InvalidUtf8)
+        );
+        assert_eq!(lossy_validation.validate(&topic), Ok(()));
+        assert_eq!(TopicValidation::default().validate("/test/tópico"), Ok(()));
+    }
+
+    #[test]
+    fn topic_rejected_by_the_custom_validation() {
+        //// Given
+        let validation = TopicValidation {
+            custom: Some(|topic| topic.starts_with("/test/")),
+            ..Default::default()
+        };
+
+        //// Then
+        assert_eq!(validation.validate("/test/topic"), Ok(()));
+        assert_eq!(
+            validation.validate("/other/topic"),
+            Err(TopicValidationError::Rejected)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn topic_hash_serde_round_trip() {
+        //// Given
+        let topic = Sha256Topic::new("/test/topic").hash();
+
+        //// When
+        let json = serde_json::to_string(&topic).unwrap();
+        let deserialized = serde_json::from_str::<TopicHash>(&json).unwrap();
+
+        //// Then
+        assert_eq!(json, format!("\"{}\"", topic.as_str()));
+        assert_eq!(deserialized, topic);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn topic_serde_round_trip() {
+        //// Given
+        let topics = vec![
+            Sha256Topic::new("/test/topic-a"),
+            Sha256Topic::new("/test/topic-b"),
+        ];
+
+        //// When
+        let json = serde_json::to_string(&topics).unwrap();
+        let deserialized = serde_json::from_str::<Vec<Sha256Topic>>(&json).unwrap();
+
+        //// Then
+        assert_eq!(json, r#"["/test/topic-a","/test/topic-b"]"#);
+        assert_eq!(deserialized, topics);
+        assert_eq!(deserialized[0].hash(), topics[0].hash());
+    }
+}
@@ -0,0 +1,112 @@
+//! Owned snapshots of the pubsub network topology as seen by the local node.
+//!
+//! Unlike the [`Behaviour`](crate::Behaviour) accessors, a [`TopologySnapshot`] does not borrow
+//! the behaviour, so it can be moved into async tasks or dumped to diagnostics endpoints.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use libp2p::identity::PeerId;
+
+use crate::topic::TopicHash;
+
+/// A remote peer's entry in a [`TopologySnapshot`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeerTopology {
+    /// The number of established connections with the peer.
+    pub connections: usize,
+    /// The topics the peer is subscribed to.
+    pub subscriptions: BTreeSet<TopicHash>,
+}
+
+/// A point-in-time snapshot of the local node subscriptions and its connected peers.
+///
+/// The snapshot is immutable and cheap to clone: clones share the same underlying data.
+///
+/// > NOTE: The negotiated protocols are not tracked per peer, so they are not part of the snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopologySnapshot {
+    /// The topics the local node is subscribed to.
+    subscriptions: Arc<BTreeSet<TopicHash>>,
+    /// The connected peers.
+    peers: Arc<BTreeMap<PeerId, PeerTopology>>,
+}
+
+impl TopologySnapshot {
+    /// Creates a new topology snapshot.
+    pub(crate) fn new(
+        subscriptions: BTreeSet<TopicHash>,
+        peers: BTreeMap<PeerId, PeerTopology>,
+    ) -> Self {
+        Self {
+            subscriptions: Arc::new(subscriptions),
+            peers: Arc::new(peers),
+        }
+    }
+
+    /// The topics the local node is subscribed to.
+    #[must_use]
+    pub fn subscriptions(&self) -> &BTreeSet<TopicHash> {
+        &self.subscriptions
+    }
+
+    /// The connected peers and their topology information.
+    #[must_use]
+    pub fn peers(&self) -> &BTreeMap<PeerId, PeerTopology> {
+        &self.peers
+    }
+
+    /// The topology information of the given peer, if connected.
+    #[must_use]
+    pub fn peer(&self, peer: &PeerId) -> Option<&PeerTopology> {
+        self.peers.get(peer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn topology_snapshot_serde_round_trip() {
+        //// Given
+        let topic = TopicHash::from_raw("/test/topic");
+        let peer = PeerId::random();
+        let snapshot = TopologySnapshot::new(
+            BTreeSet::from([topic.clone()]),
+            BTreeMap::from([(
+                peer,
+                PeerTopology {
+                    connections: 2,
+                    subscriptions: BTreeSet::from([topic]),
+                },
+            )]),
+        );
+
+        //// When
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let deserialized = serde_json::from_str::<TopologySnapshot>(&json).unwrap();
+
+        //// Then
+        assert_eq!(deserialized, snapshot);
+    }
+
+    #[test]
+    fn topology_snapshot_clones_share_data() {
+        //// Given
+        let snapshot = TopologySnapshot::new(
+            BTreeSet::from([TopicHash::from_raw("/test/topic")]),
+            BTreeMap::new(),
+        );
+
+        //// When
+        let clone = snapshot.clone();
+
+        //// Then
+        assert!(Arc::ptr_eq(&snapshot.subscriptions, &clone.subscriptions));
+        assert!(Arc::ptr_eq(&snapshot.peers, &clone.peers));
+    }
+}
@@ -0,0 +1,223 @@
+//! Per-topic message traffic accounting.
+//!
+//! The message sizes are measured as the encoded size of the message protobuf, i.e., the message
+//! payload plus the message metadata (topic, sequence number, author, signature, etc.).
+
+use std::collections::HashMap;
+
+use crate::topic::TopicHash;
+
+/// The inclusive upper bounds, in bytes, of the [message size histogram](TopicTraffic::size_histogram)
+/// buckets. The last histogram bucket counts the messages larger than the last bound.
+pub const MESSAGE_SIZE_BUCKETS: [usize; 6] = [64, 256, 1024, 4096, 16384, 65536];
+
+/// The message traffic counters of a topic (or of a set of topics).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopicTraffic {
+    /// The number of messages published by the local node.
+    pub published_messages: u64,
+    /// The bytes of the messages published by the local node.
+    pub published_bytes: u64,
+    /// The number of messages received from the remote peers, including duplicates.
+    pub received_messages: u64,
+    /// The bytes of the messages received from the remote peers, including duplicates.
+    pub received_bytes: u64,
+    /// The number of messages sent to the remote peers, including the locally published messages.
+    /// A message sent to `n` peers counts `n` times.
+    pub forwarded_messages: u64,
+    /// The bytes of the messages sent to the remote peers, including the locally published
+    /// messages.
+    pub forwarded_bytes: u64,
+    /// The size distribution of the published and received messages.
+    ///
+    /// The `i`-th bucket counts the messages whose size is at most [`MESSAGE_SIZE_BUCKETS`]`[i]`
+    /// bytes (and larger than the previous bucket bound). The last bucket counts the messages
+    /// larger than the last bound.
+    pub size_histogram: [u64; MESSAGE_SIZE_BUCKETS.len() + 1],
+}
+
+impl TopicTraffic {
+    /// Add a message size sample to the size histogram.
+    fn record_size(&mut self, size: usize) {
+        let bucket = MESSAGE_SIZE_BUCKETS
+            .iter()
+            .position(|bound| size <= *bound)
+            .unwrap_or(MESSAGE_SIZE_BUCKETS.len());
+        self.size_histogram[bucket] = self.size_histogram[bucket].saturating_add(1);
+    }
+
+    /// Add the other traffic counters to these counters.
+    fn merge(&mut self, other: &TopicTraffic) {
+        self.published_messages = self
+            .published_messages
+            .saturating_add(other.published_messages);
+        self.published_bytes = self.published_bytes.saturating_add(other.published_bytes);
+        self.received_messages = self
+            .received_messages
+            .saturating_add(other.received_messages);
+        self.received_bytes = self.received_bytes.saturating_add(other.received_bytes);
+        self.forwarded_messages = self
+            .forwarded_messages
+            .saturating_add(other.forwarded_messages);
+        self.forwarded_bytes = self.forwarded_bytes.saturating_add(other.forwarded_bytes);
+        for (bucket, count) in self.size_histogram.iter_mut().zip(other.size_histogram) {
+            *bucket = bucket.saturating_add(count);
+        }
+    }
+}
+
+/// The traffic direction of a message.
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Published,
+    Received,
+    Forwarded,
+}
+
+/// Keeps the per-topic message traffic counters.
+///
+/// To keep the memory bounded, only the locally subscribed topics are accounted individually. The
+/// traffic of the rest of topics is accounted in a single "other" bucket. When the local node
+/// unsubscribes from a topic, its counters are merged into the "other" bucket.
+#[derive(Debug, Default)]
+pub(crate) struct TrafficAccounting {
+    /// The locally subscribed topics counters.
+    topics: HashMap<TopicHash, TopicTraffic>,
+    /// The counters of the topics the local node is not subscribed to.
+    other: TopicTraffic,
+    /// The counters of all topics.
+    totals: TopicTraffic,
+}
+
+impl TrafficAccounting {
+    /// Start accounting the topic traffic individually.
+    pub(crate) fn track_topic(&mut self, topic: TopicHash) {
+        self.topics.entry(topic).or_default();
+    }
+
+    /// Stop accounting the topic traffic individually, merging its counters into the "other"
+    /// bucket.
+    pub(crate) fn untrack_topic(&mut self, topic: &TopicHash) {
+        if let Some(traffic) = self.topics.remove(topic) {
+            self.other.merge(&traffic);
+        }
+    }
+
+    /// Get the traffic counters of a locally subscribed topic.
+    pub(crate) fn topic(&self, topic: &TopicHash) -> Option<&TopicTraffic> {
+        self.topics.get(topic)
+    }
+
+    /// Get the traffic counters of all topics.
+    pub(crate) fn totals(&self) -> &TopicTraffic {
+        &self.totals
+    }
+
+    /// Account a message published by the local node.
+    pub(crate) fn record_published(&mut self, topic: &TopicHash, size: usize) {
+        self.record(topic, size, Direction::Published);
+    }
+
+    /// Account a message received from a remote peer.
+    pub(crate) fn record_received(&mut self, topic: &TopicHash, size: usize) {
+        self.record(topic, size, Direction::Received);
+    }
+
+    /// Account a message sent to a remote peer.
+    pub(crate) fn record_forwarded(&mut self, topic: &TopicHash, size: usize) {
+        self.record(topic, size, Direction::Forwarded);
+    }
+
+    fn record(&mut self, topic: &TopicHash, size: usize, direction: Direction) {
+        let bucket = self.topics.get_mut(topic).unwrap_or(&mut self.other);
+        for traffic in [bucket, &mut self.totals] {
+            let bytes = size as u64;
+            match direction {
+                Direction::Published => {
+                    traffic.published_messages = traffic.published_messages.saturating_add(1);
+                    traffic.published_bytes = traffic.published_bytes.saturating_add(bytes);
+                    traffic.record_size(size);
+                }
+                Direction::Received => {
+                    traffic.received_messages = traffic.received_messages.saturating_add(1);
+                    traffic.received_bytes = traffic.received_bytes.saturating_add(bytes);
+                    traffic.record_size(size);
+                }
+                Direction::Forwarded => {
+                    traffic.forwarded_messages = traffic.forwarded_messages.saturating_add(1);
+                    traffic.forwarded_bytes = traffic.forwarded_bytes.saturating_add(bytes);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untracked_topic_traffic_rolls_into_other_bucket() {
+        //// Given
+        let topic_a = TopicHash::from_raw("/test/topic-a");
+        let topic_b = TopicHash::from_raw("/test/topic-b");
+
+        let mut accounting = TrafficAccounting::default();
+        accounting.track_topic(topic_a.clone());
+
+        //// When
+        accounting.record_published(&topic_a, 10);
+        accounting.record_received(&topic_b, 100);
+        accounting.record_forwarded(&topic_a, 10);
+        accounting.record_forwarded(&topic_a, 10);
+
+        //// Then
+        assert!(
+            accounting.topic(&topic_b).is_none(),
+            "Untracked topic should not be accounted individually"
+        );
+        assert_eq!(
+            accounting.topic(&topic_a),
+            Some(&TopicTraffic {
+                published_messages: 1,
+                published_bytes: 10,
+                forwarded_messages: 2,
+                forwarded_bytes: 20,
+                size_histogram: [1, 0, 0, 0, 0, 0, 0],
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            accounting.other,
+            TopicTraffic {
+                received_messages: 1,
+                received_bytes: 100,
+                size_histogram: [0, 1, 0, 0, 0, 0, 0],
+                ..Default::default()
+            }
+        );
+        assert_eq!(accounting.totals().published_bytes, 10);
+        assert_eq!(accounting.totals().received_bytes, 100);
+        assert_eq!(accounting.totals().forwarded_bytes, 20);
+    }
+
+    #[test]
+    fn untracking_a_topic_merges_its_counters_into_other_bucket() {
+        //// Given
+        let topic = TopicHash::from_raw("/test/topic");
+
+        let mut accounting = TrafficAccounting::default();
+        accounting.track_topic(topic.clone());
+        accounting.record_received(&topic, 70_000);
+
+        //// When
+        accounting.untrack_topic(&topic);
+
+        //// Then
+        assert!(accounting.topic(&topic).is_none());
+        assert_eq!(accounting.other.received_bytes, 70_000);
+        assert_eq!(accounting.other.size_histogram, [0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(accounting.totals(), &accounting.other);
+    }
+}
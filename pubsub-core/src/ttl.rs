@@ -0,0 +1,148 @@
+//! Application-level message expiry.
+//!
+//! A message published with a time-to-live (see [`Message::set_ttl`](crate::Message::set_ttl))
+//! carries its absolute expiry time in an envelope wrapping the message payload:
+//!
+//! ```text
+//! +-------+---------+--------+---------------+
+//! | magic | version | expiry | payload bytes |
+//! +-------+---------+--------+---------------+
+//!     4        1         8        variable
+//! ```
+//!
+//!  - `magic`: The `PSTL` ASCII bytes.
+//!  - `version`: The envelope format version. Currently, `1`.
+//!  - `expiry`: The expiry time in milliseconds since the UNIX epoch, big-endian encoded.
+//!
+//! The expired messages are neither accepted nor forwarded. As the expiry time is an absolute
+//! wall-clock time, a clock skew tolerance (see
+//! [`Config::message_ttl_clock_skew`](crate::Config::message_ttl_clock_skew)) is applied when
+//! checking it.
+//!
+//! Nodes not supporting message expiry see the envelope as part of the message payload.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The envelope magic bytes.
+const TTL_MAGIC: [u8; 4] = *b"PSTL";
+
+/// The envelope format version.
+const TTL_VERSION: u8 = 1;
+
+/// The envelope header length in bytes.
+const TTL_HEADER_LEN: usize = 4 + 1 + 8;
+
+/// Wrap the payload into an envelope carrying the given expiry time.
+pub(crate) fn encode(expiry: SystemTime, payload: &[u8]) -> Vec<u8> {
+    let expiry_ms = expiry
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    let mut data = Vec::with_capacity(TTL_HEADER_LEN + payload.len());
+    data.extend_from_slice(&TTL_MAGIC);
+    data.push(TTL_VERSION);
+    data.extend_from_slice(&u64::try_from(expiry_ms).unwrap_or(u64::MAX).to_be_bytes());
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Decode an envelope into its expiry time and the wrapped payload.
+///
+/// Returns `None` if the data is not a valid envelope.
+pub(crate) fn decode(data: &[u8]) -> Option<(SystemTime, &[u8])> {
+    if data.len() < TTL_HEADER_LEN {
+        return None;
+    }
+
+    let (header, payload) = data.split_at(TTL_HEADER_LEN);
+    let (magic, header) = header.split_at(TTL_MAGIC.len());
+    let (version, expiry_ms) = header.split_at(1);
+    if magic != TTL_MAGIC || version[0] != TTL_VERSION {
+        return None;
+    }
+
+    let expiry_ms = u64::from_be_bytes(expiry_ms.try_into().ok()?);
+    let expiry = UNIX_EPOCH.checked_add(Duration::from_millis(expiry_ms))?;
+
+    Some((expiry, payload))
+}
+
+/// Check if the message data carries an expiry time that has passed, taking into account the
+/// clock skew tolerance.
+///
+/// Data without an envelope never expires.
+pub(crate) fn is_expired(data: &[u8], now: SystemTime, clock_skew: Duration) -> bool {
+    match decode(data) {
+        Some((expiry, _)) => expiry + clock_skew < now,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_round_trip() {
+        //// Given
+        let expiry = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let payload = b"test payload";
+
+        //// When
+        let data = encode(expiry, payload);
+        let decoded = decode(&data);
+
+        //// Then
+        assert_eq!(decoded, Some((expiry, &payload[..])));
+    }
+
+    #[test]
+    fn data_without_envelope_never_expires() {
+        //// Given
+        let data = b"PSTL";
+
+        //// When
+        let decoded = decode(data);
+        let expired = is_expired(data, SystemTime::now(), Duration::ZERO);
+
+        //// Then
+        assert!(decoded.is_none(), "Data should not be decoded as envelope");
+        assert!(!expired, "Data without envelope should not expire");
+    }
+
+    #[test]
+    fn accepted_message_expires_before_forward() {
+        //// Given
+        let accepted_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let data = encode(accepted_at + Duration::from_secs(30), b"command");
+
+        //// When
+        let expired_on_accept = is_expired(&data, accepted_at, Duration::ZERO);
+        let expired_on_forward =
+            is_expired(&data, accepted_at + Duration::from_secs(31), Duration::ZERO);
+
+        //// Then
+        assert!(!expired_on_accept, "Message should be accepted");
+        assert!(expired_on_forward, "Message should not be forwarded");
+    }
+
+    #[test]
+    fn clock_skew_tolerance_extends_expiry() {
+        //// Given
+        let expiry = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let data = encode(expiry, b"command");
+        let now = expiry + Duration::from_millis(500);
+
+        //// When
+        let expired_without_tolerance = is_expired(&data, now, Duration::ZERO);
+        let expired_with_tolerance = is_expired(&data, now, Duration::from_secs(1));
+
+        //// Then
+        assert!(expired_without_tolerance);
+        assert!(
+            !expired_with_tolerance,
+            "Message within the skew tolerance should not expire"
+        );
+    }
+}
@@ -1,8 +1,11 @@
+pub(crate) use composite::protocol_ids;
+pub use composite::CompositeProtocolUpgrade;
 pub use simple::SimpleProtocolUpgrade;
 pub use upgrade_trait::{
     ProtocolInboundUpgrade, ProtocolOutboundUpgrade, ProtocolUpgrade, ProtocolUpgradeInfo,
-    ProtocolUpgradeOutput, ProtocolUpgradeSend,
+    ProtocolUpgradeOutput, ProtocolUpgradeSend, SocketProtocolUpgradeSend,
 };
 
+mod composite;
 mod simple;
 mod upgrade_trait;
@@ -0,0 +1,122 @@
+use futures::future::{BoxFuture, Either};
+use futures::{FutureExt, TryFutureExt};
+use libp2p::core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
+use libp2p::swarm::handler::{InboundUpgradeSend, OutboundUpgradeSend, UpgradeInfoSend};
+use libp2p::swarm::Stream;
+
+use super::upgrade_trait::{ProtocolUpgradeOutput, ProtocolUpgradeSend};
+
+/// A [`ProtocolUpgrade`](super::upgrade_trait::ProtocolUpgrade) implementation that negotiates
+/// either of two protocol upgrades.
+///
+/// The protocol ids of the first upgrade take precedence over the protocol ids of the second
+/// upgrade. The upgrade output info is the negotiated protocol id.
+#[derive(Debug, Clone)]
+pub struct CompositeProtocolUpgrade<U1, U2> {
+    first: U1,
+    second: U2,
+}
+
+impl<U1, U2> CompositeProtocolUpgrade<U1, U2>
+where
+    U1: ProtocolUpgradeSend,
+    U2: ProtocolUpgradeSend,
+{
+    pub fn new(first: U1, second: U2) -> Self {
+        Self { first, second }
+    }
+}
+
+/// Get the protocol ids supported by the upgrade.
+pub(crate) fn protocol_ids(upgrade: &impl UpgradeInfoSend) -> Vec<String> {
+    upgrade
+        .protocol_info()
+        .map(|info| info.as_ref().to_string())
+        .collect()
+}
+
+/// Find the upgrade protocol info matching the negotiated protocol id.
+fn find_info<U: UpgradeInfoSend>(upgrade: &U, protocol: &str) -> Option<U::Info> {
+    upgrade
+        .protocol_info()
+        .find(|info| info.as_ref() == protocol)
+}
+
+impl<U1, U2> UpgradeInfo for CompositeProtocolUpgrade<U1, U2>
+where
+    U1: ProtocolUpgradeSend,
+    U2: ProtocolUpgradeSend,
+{
+    type Info = String;
+    type InfoIter = std::vec::IntoIter<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        let mut ids = protocol_ids(&self.first);
+        ids.extend(protocol_ids(&self.second));
+        ids.into_iter()
+    }
+}
+
+impl<U1, U2> InboundUpgrade<Stream> for CompositeProtocolUpgrade<U1, U2>
+where
+    U1: ProtocolUpgradeSend,
+    U2: ProtocolUpgradeSend,
+{
+    type Output = ProtocolUpgradeOutput<String>;
+    type Error = Either<<U1 as InboundUpgradeSend>::Error, <U2 as InboundUpgradeSend>::Error>;
+    type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn upgrade_inbound(self, socket: Stream, info: Self::Info) -> Self::Future {
+        if let Some(first_info) = find_info(&self.first, &info) {
+            return InboundUpgradeSend::upgrade_inbound(self.first, socket, first_info)
+                .map_ok(move |output| ProtocolUpgradeOutput {
+                    socket: output.socket,
+                    info,
+                })
+                .map_err(Either::Left)
+                .boxed();
+        }
+
+        let second_info =
+            find_info(&self.second, &info).expect("negotiated protocol id should be supported");
+        InboundUpgradeSend::upgrade_inbound(self.second, socket, second_info)
+            .map_ok(move |output| ProtocolUpgradeOutput {
+                socket: output.socket,
+                info,
+            })
+            .map_err(Either::Right)
+            .boxed()
+    }
+}
+
+impl<U1, U2> OutboundUpgrade<Stream> for CompositeProtocolUpgrade<U1, U2>
+where
+    U1: ProtocolUpgradeSend,
+    U2: ProtocolUpgradeSend,
+{
+    type Output = ProtocolUpgradeOutput<String>;
+    type Error = Either<<U1 as OutboundUpgradeSend>::Error, <U2 as OutboundUpgradeSend>::Error>;
+    type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn upgrade_outbound(self, socket: Stream, info: Self::Info) -> Self::Future {
+        if let Some(first_info) = find_info(&self.first, &info) {
+            return OutboundUpgradeSend::upgrade_outbound(self.first, socket, first_info)
+                .map_ok(move |output| ProtocolUpgradeOutput {
+                    socket: output.socket,
+                    info,
+                })
+                .map_err(Either::Left)
+                .boxed();
+        }
+
+        let second_info =
+            find_info(&self.second, &info).expect("negotiated protocol id should be supported");
+        OutboundUpgradeSend::upgrade_outbound(self.second, socket, second_info)
+            .map_ok(move |output| ProtocolUpgradeOutput {
+                socket: output.socket,
+                info,
+            })
+            .map_err(Either::Right)
+            .boxed()
+    }
+}
@@ -3,8 +3,11 @@ use libp2p::swarm::handler::{InboundUpgradeSend, OutboundUpgradeSend, UpgradeInf
 use libp2p::swarm::Stream;
 
 /// Output of the [`InboundUpgrade`] and [`OutboundUpgrade`] traits.
-pub struct ProtocolUpgradeOutput<TInfo> {
-    pub socket: Stream,
+///
+/// The socket type defaults to the libp2p [`Stream`]. Other socket types are only expected in
+/// tests, e.g., to drive the connection handler with a mock substream.
+pub struct ProtocolUpgradeOutput<TInfo, S = Stream> {
+    pub socket: S,
     pub info: TInfo,
 }
 
@@ -39,4 +42,9 @@ trait_set::trait_set! {
     pub trait ProtocolUpgradeSend = UpgradeInfoSend +
         InboundUpgradeSend<Output=ProtocolUpgradeOutput<<Self as UpgradeInfoSend>::Info>> +
         OutboundUpgradeSend<Output=ProtocolUpgradeOutput<<Self as UpgradeInfoSend>::Info>>;
+
+    /// Same as [`ProtocolUpgradeSend`] but generic over the upgrade output socket type.
+    pub trait SocketProtocolUpgradeSend<S> = UpgradeInfoSend +
+        InboundUpgradeSend<Output=ProtocolUpgradeOutput<<Self as UpgradeInfoSend>::Info, S>> +
+        OutboundUpgradeSend<Output=ProtocolUpgradeOutput<<Self as UpgradeInfoSend>::Info, S>>;
 }
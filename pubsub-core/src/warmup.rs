@@ -0,0 +1,108 @@
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::FutureExt;
+use futures_timer::Delay;
+
+/// The forwarding warm-up state.
+enum State {
+    /// The warm-up starts on the first connection.
+    Pending(Duration),
+    /// The warm-up is running until the timer fires.
+    Running(Delay),
+    /// The warm-up completed, or is disabled.
+    Completed,
+}
+
+/// Holds back the forwarding of the received messages during a warm-up period after the first
+/// connection following the startup (see [`Config::forward_warmup`](crate::Config::forward_warmup)).
+///
+/// While warming up, the connected peers are still announcing their subscriptions, and forwarding
+/// the received messages as each peer announces them results in a burst of duplicates.
+pub(crate) struct ForwardWarmup {
+    state: State,
+}
+
+impl ForwardWarmup {
+    /// Create a new forwarding warm-up of the given duration. If `None`, the warm-up is disabled.
+    pub(crate) fn new(warmup: Option<Duration>) -> Self {
+        let state = match warmup {
+            Some(warmup) => State::Pending(warmup),
+            None => State::Completed,
+        };
+        Self { state }
+    }
+
+    /// Start the warm-up timer, if not started yet.
+    pub(crate) fn start(&mut self) {
+        if let State::Pending(warmup) = self.state {
+            tracing::debug!(?warmup, "Forwarding warm-up started");
+            self.state = State::Running(Delay::new(warmup));
+        }
+    }
+
+    /// Whether the received messages forwarding is held back.
+    pub(crate) fn is_warming_up(&self) -> bool {
+        !matches!(self.state, State::Completed)
+    }
+
+    /// Poll the warm-up timer. Returns `Poll::Ready` once, when the warm-up completes.
+    pub(crate) fn poll_completed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let State::Running(timer) = &mut self.state else {
+            return Poll::Pending;
+        };
+
+        futures::ready!(timer.poll_unpin(cx));
+
+        tracing::debug!("Forwarding warm-up completed");
+        self.state = State::Completed;
+        Poll::Ready(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::task::noop_waker_ref;
+
+    use super::*;
+
+    #[test]
+    fn disabled_warmup_is_completed() {
+        //// Given
+        let mut warmup = ForwardWarmup::new(None);
+
+        //// When
+        warmup.start();
+
+        //// Then
+        assert!(!warmup.is_warming_up());
+    }
+
+    #[test]
+    fn warmup_completes_once_after_the_start() {
+        //// Given
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut warmup = ForwardWarmup::new(Some(Duration::from_millis(20)));
+
+        //// When
+        std::thread::sleep(Duration::from_millis(30));
+        let before_start = warmup.poll_completed(&mut cx);
+
+        warmup.start();
+        let after_start = warmup.poll_completed(&mut cx);
+
+        std::thread::sleep(Duration::from_millis(30));
+        let after_expiry = warmup.poll_completed(&mut cx);
+        let after_completion = warmup.poll_completed(&mut cx);
+
+        //// Then
+        assert!(before_start.is_pending(), "The warm-up starts on start");
+        assert!(after_start.is_pending());
+        assert!(after_expiry.is_ready(), "The warm-up should complete");
+        assert!(
+            after_completion.is_pending(),
+            "The completion is notified once"
+        );
+        assert!(!warmup.is_warming_up());
+    }
+}
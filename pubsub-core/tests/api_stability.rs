@@ -0,0 +1,160 @@
+//! Public API stability tests.
+//!
+//! These tests construct and match the public types the way downstream crates are expected to:
+//! through the associated constructors and with wildcard arms. They must keep compiling across
+//! minor releases; a failure here signals a breaking change of the public API.
+
+use std::rc::Rc;
+
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_core::protocol::{
+    ProtocolRouterConnectionEvent, ProtocolRouterControlEvent, ProtocolRouterInEvent,
+    ProtocolRouterMessageEvent, ProtocolRouterOutEvent, ProtocolRouterSubscriptionEvent,
+};
+use libp2p_pubsub_core::{
+    ControlMessage, Event, FrameMessage, IdentTopic, Message, MessageId, MisbehaviourReason,
+    PruneControlMessage, Subscription, TopicHash,
+};
+
+fn new_test_topic() -> TopicHash {
+    IdentTopic::new("/pubsub/2/it-api-stability").hash()
+}
+
+/// Describe the event the way an application would, ignoring the unknown variants.
+fn describe_event(event: &Event) -> Option<String> {
+    match event {
+        Event::MessageReceived { src, message, .. } => {
+            Some(format!("message from {src} on {}", message.topic))
+        }
+        Event::Subscribed { topic } => Some(format!("subscribed to {topic}")),
+        Event::Unsubscribed { topic } => Some(format!("unsubscribed from {topic}")),
+        Event::PeerMisbehaving { peer, reason, .. } => match reason {
+            MisbehaviourReason::DuplicateFlood { message_id, .. } => {
+                Some(format!("{peer} flooded {message_id}"))
+            }
+            _ => Some(format!("{peer} misbehaving")),
+        },
+        _ => None,
+    }
+}
+
+/// Route the event the way a protocol router would, ignoring the unknown variants.
+fn route_event(ev: ProtocolRouterInEvent) -> Option<&'static str> {
+    match ev {
+        ProtocolRouterInEvent::ConnectionEvent(conn_ev) => match conn_ev {
+            ProtocolRouterConnectionEvent::PeerConnected(_) => Some("peer connected"),
+            ProtocolRouterConnectionEvent::PeerDisconnected(_) => Some("peer disconnected"),
+            _ => None,
+        },
+        ProtocolRouterInEvent::SubscriptionEvent(sub_ev) => match sub_ev {
+            ProtocolRouterSubscriptionEvent::Subscribed(_) => Some("subscribed"),
+            ProtocolRouterSubscriptionEvent::Unsubscribed(_) => Some("unsubscribed"),
+            _ => None,
+        },
+        ProtocolRouterInEvent::MessageEvent(msg_ev) => match msg_ev {
+            ProtocolRouterMessageEvent::MessageReceived { .. } => Some("message received"),
+            ProtocolRouterMessageEvent::MessagePublished { .. } => Some("message published"),
+            _ => None,
+        },
+        ProtocolRouterInEvent::ControlEvent(ctrl_ev) => match ctrl_ev.message {
+            ControlMessage::Prune(_) => Some("prune"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[test]
+fn events_are_constructed_and_matched_through_the_stable_api() {
+    //// Given
+    let peer = PeerId::random();
+    let topic = new_test_topic();
+    let message_id = MessageId::new(b"test-message-id".to_vec());
+
+    let events = [
+        Event::message_received(
+            peer,
+            Message::new(topic.clone(), b"data".to_vec()),
+            message_id.clone(),
+        ),
+        Event::subscribed(topic.clone()),
+        Event::unsubscribed(topic.clone()),
+        Event::peer_misbehaving(peer, MisbehaviourReason::duplicate_flood(message_id)),
+    ];
+
+    //// When
+    let descriptions = events.iter().map(describe_event).collect::<Vec<_>>();
+
+    //// Then
+    assert!(
+        descriptions.iter().all(Option::is_some),
+        "All the known events should be described"
+    );
+}
+
+#[test]
+fn router_events_are_constructed_and_matched_through_the_stable_api() {
+    //// Given
+    let peer = PeerId::random();
+    let topic = new_test_topic();
+    let message = Rc::new(FrameMessage::new(topic.clone(), b"data".to_vec()));
+    let message_id = MessageId::new(b"test-message-id".to_vec());
+
+    let events = [
+        ProtocolRouterInEvent::ConnectionEvent(ProtocolRouterConnectionEvent::PeerConnected(peer)),
+        ProtocolRouterInEvent::SubscriptionEvent(ProtocolRouterSubscriptionEvent::Subscribed(
+            Subscription::from(IdentTopic::new("/pubsub/2/it-api-stability")),
+        )),
+        ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessageReceived {
+            src: peer,
+            message,
+            message_id,
+            exclude: Default::default(),
+        }),
+        ProtocolRouterInEvent::ControlEvent(ProtocolRouterControlEvent::new(
+            peer,
+            ControlMessage::Prune(PruneControlMessage {
+                topic_hash: topic,
+                peers: Vec::new(),
+                backoff: None,
+            }),
+        )),
+        ProtocolRouterInEvent::Heartbeat,
+    ];
+
+    //// When
+    let routed = events.into_iter().map(route_event).collect::<Vec<_>>();
+
+    //// Then
+    assert_eq!(
+        routed,
+        [
+            Some("peer connected"),
+            Some("subscribed"),
+            Some("message received"),
+            Some("prune"),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn router_out_events_are_matched_through_the_stable_api() {
+    //// Given
+    let peer = PeerId::random();
+    let message = Rc::new(FrameMessage::new(new_test_topic(), b"data".to_vec()));
+    let event = ProtocolRouterOutEvent::ForwardMessage {
+        dest: vec![peer],
+        message,
+    };
+
+    //// When
+    let dest = match event {
+        ProtocolRouterOutEvent::ForwardMessage { dest, .. } => dest,
+        _ => Vec::new(),
+    };
+
+    //// Then
+    assert_eq!(dest, vec![peer]);
+}
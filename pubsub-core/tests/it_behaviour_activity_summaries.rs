@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, ConfigBuilder, Event, IdentTopic, Message, TopicActivity,
+    TopicHash,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the first node behaviour events.
+///
+/// The period is not restarted on every event, as the summary events are emitted periodically.
+async fn poll_mesh_and_collect_events(
+    duration: Duration,
+    node: &mut Swarm<Behaviour>,
+    other: &mut Swarm<Behaviour>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            event = node.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    events.push(event);
+                }
+            },
+            _ = other.select_next_some() => {},
+        }
+    }
+
+    events
+}
+
+/// Get the topic activity summaries events.
+fn activity_summaries(events: Vec<Event>) -> Vec<Vec<(TopicHash, TopicActivity)>> {
+    events
+        .into_iter()
+        .filter_map(|ev| match ev {
+            Event::TopicActivitySummary { per_topic, .. } => Some(per_topic),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn activity_summaries_report_the_window_activity_and_reset() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let node_a_config = ConfigBuilder::new()
+        .activity_summaries(Some(Duration::from_millis(200)))
+        .build();
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// When
+    for idx in 0..3u8 {
+        node_b
+            .behaviour_mut()
+            .publish(Message::new(topic.clone(), vec![idx; 16]))
+            .expect("publish the message");
+    }
+
+    // Collect the summary of the window the messages were received in, and of the next, idle,
+    // window.
+    let node_a_events =
+        poll_mesh_and_collect_events(Duration::from_millis(450), &mut node_a, &mut node_b).await;
+
+    //// Then
+    let summaries = activity_summaries(node_a_events);
+    assert!(
+        summaries.len() >= 2,
+        "Node A should emit a summary every window"
+    );
+
+    let (summary_topic, activity) = summaries
+        .iter()
+        .find_map(|per_topic| per_topic.first())
+        .expect("a summary should report the topic activity");
+    assert_eq!(summary_topic, &topic.hash());
+    assert_eq!(activity.received, 3);
+    assert_eq!(activity.delivered, 3);
+    assert_eq!(activity.duplicates, 0);
+    assert!(activity.bytes >= 3 * 16);
+
+    assert!(
+        summaries.last().expect("summaries").is_empty(),
+        "The idle window summary should omit the topic"
+    );
+}
@@ -0,0 +1,449 @@
+use std::collections::HashSet;
+
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, NetworkBehaviour, ToSwarm};
+use prost::Message as _;
+use rand::Rng;
+
+use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+use libp2p_pubsub_core::protocol::{
+    Protocol, ProtocolRouterConnectionEvent, ProtocolRouterInEvent, ProtocolRouterMessageEvent,
+    ProtocolRouterOutEvent,
+};
+use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, ForwardFanout, Frame, IdentTopic,
+    Message, SubscriptionAction,
+};
+use libp2p_pubsub_proto::pubsub::FrameProto;
+use pubsub_testlib::{connect, disconnect, poll_settled, receive_frame};
+
+mod pubsub_testlib;
+
+/// A test protocol whose router publishes the messages to the connected peers picked by the
+/// forward fan-out.
+#[derive(Default)]
+struct FanoutProtocol;
+
+impl Protocol for FanoutProtocol {
+    type Upgrade = SimpleProtocolUpgrade<&'static str>;
+    type RouterService = FanoutRouter;
+
+    fn upgrade() -> Self::Upgrade {
+        SimpleProtocolUpgrade::new("/fanout/1.0.0")
+    }
+
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
+        Default::default()
+    }
+}
+
+/// The pubsub protocol router service for the fan-out protocol.
+#[derive(Default)]
+struct FanoutRouter {
+    peers: HashSet<PeerId>,
+    fanout: ForwardFanout,
+}
+
+impl EventHandler for FanoutRouter {
+    type InEvent = ProtocolRouterInEvent;
+    type OutEvent = ProtocolRouterOutEvent;
+
+    fn on_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
+        ev: Self::InEvent,
+    ) {
+        match ev {
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerConnected(peer),
+            ) => {
+                self.peers.insert(peer);
+            }
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerDisconnected(peer),
+            ) => {
+                self.peers.remove(&peer);
+            }
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerDeprioritized {
+                    peer,
+                    deprioritized,
+                },
+            ) => {
+                self.fanout.set_deprioritized(peer, deprioritized);
+            }
+            ProtocolRouterInEvent::ForwardFanout(fanout) => {
+                self.fanout = fanout;
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessagePublished {
+                message,
+                message_id,
+                ..
+            }) => {
+                svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage {
+                    dest: self
+                        .fanout
+                        .select_publish(&message_id, self.peers.iter().copied()),
+                    message,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+type Behaviour = PubsubBehaviour<FanoutProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<FanoutProtocol>;
+
+type HandlerEvent = pubsub_testlib::HandlerEvent<FanoutProtocol>;
+
+type HandlerCommand = pubsub_testlib::HandlerCommand<FanoutProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Simulate the given number of frame sends to the given peer, failed or not.
+fn send_frames(
+    behaviour: &mut Behaviour,
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    count: usize,
+    failed: bool,
+) {
+    for _ in 0..count {
+        let event = if failed {
+            HandlerEvent::FrameSendFailed { frame_size: 128 }
+        } else {
+            HandlerEvent::FrameSent
+        };
+        behaviour.on_connection_handler_event(peer_id, connection_id, event);
+    }
+}
+
+/// Simulate the reception of a frame from the given peer.
+fn receive_any_frame(behaviour: &mut Behaviour, peer_id: PeerId, connection_id: ConnectionId) {
+    receive_frame(
+        behaviour,
+        peer_id,
+        connection_id,
+        Frame::new_with_subscriptions([SubscriptionAction::Subscribe(new_test_topic().hash())]),
+    );
+}
+
+/// Collect the peers the published messages frames were sent to.
+fn sent_frames_destinations(events: &[BehaviourEvent]) -> Vec<PeerId> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerCommand::SendFrame { frame, .. },
+                ..
+            } => {
+                let frame = FrameProto::decode(frame.clone()).ok()?;
+                (!frame.publish.is_empty()).then_some(*peer_id)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collect the asymmetric connectivity notifications, as `(peer, fail_ratio)` pairs.
+fn asymmetric_connectivity_suspicions(events: &[BehaviourEvent]) -> Vec<(PeerId, f64)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::GenerateEvent(Event::AsymmetricConnectivitySuspected {
+                peer,
+                fail_ratio,
+                ..
+            }) => Some((*peer, *fail_ratio)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Create a test behaviour, with the given configuration, connected to the given peers.
+fn new_test_behaviour(config: Config, peers: &[PeerId]) -> Behaviour {
+    let mut behaviour = Behaviour::new(PeerId::random(), config, Default::default());
+    for (idx, peer) in peers.iter().enumerate() {
+        connect(&mut behaviour, *peer, ConnectionId::new_unchecked(idx));
+    }
+    poll_settled(&mut behaviour);
+
+    behaviour
+}
+
+fn new_test_config() -> ConfigBuilder {
+    let mut builder = ConfigBuilder::default();
+    builder
+        .asymmetric_connectivity_threshold(0.5)
+        .asymmetric_connectivity_min_sends(8);
+    builder
+}
+
+#[test]
+fn receiving_peer_failing_most_sends_is_suspected_and_notified_once() {
+    testlib::init_logger();
+
+    //// Given
+    let peer = PeerId::random();
+    let healthy_peer = PeerId::random();
+    let mut behaviour = new_test_behaviour(new_test_config().build(), &[peer, healthy_peer]);
+
+    //// When
+    receive_any_frame(&mut behaviour, peer, ConnectionId::new_unchecked(0));
+    receive_any_frame(&mut behaviour, healthy_peer, ConnectionId::new_unchecked(1));
+    send_frames(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        2,
+        false,
+    );
+    send_frames(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        12,
+        true,
+    );
+    send_frames(
+        &mut behaviour,
+        healthy_peer,
+        ConnectionId::new_unchecked(1),
+        12,
+        false,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    let suspicions = asymmetric_connectivity_suspicions(&events);
+    assert_eq!(suspicions.len(), 1, "The peer should be notified once");
+    assert_eq!(suspicions[0].0, peer);
+    assert!(suspicions[0].1 > 0.5);
+    assert!(behaviour.is_connectivity_asymmetric(&peer));
+    assert!(!behaviour.is_connectivity_asymmetric(&healthy_peer));
+}
+
+#[test]
+fn silent_peer_failing_sends_is_not_suspected() {
+    testlib::init_logger();
+
+    //// Given
+    let peer = PeerId::random();
+    let mut behaviour = new_test_behaviour(new_test_config().build(), &[peer]);
+
+    //// When
+    send_frames(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        12,
+        true,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(asymmetric_connectivity_suspicions(&events).is_empty());
+    assert!(
+        !behaviour.is_connectivity_asymmetric(&peer),
+        "A peer the local node receives no frames from is not suspected"
+    );
+}
+
+#[test]
+fn too_few_sends_are_not_suspected() {
+    testlib::init_logger();
+
+    //// Given
+    let peer = PeerId::random();
+    let mut behaviour = new_test_behaviour(new_test_config().build(), &[peer]);
+
+    //// When
+    receive_any_frame(&mut behaviour, peer, ConnectionId::new_unchecked(0));
+    send_frames(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        7,
+        true,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(asymmetric_connectivity_suspicions(&events).is_empty());
+    assert!(!behaviour.is_connectivity_asymmetric(&peer));
+}
+
+#[test]
+fn suspected_peer_recovers_once_sends_succeed_again() {
+    testlib::init_logger();
+
+    //// Given
+    let peer = PeerId::random();
+    let mut behaviour = new_test_behaviour(new_test_config().build(), &[peer]);
+
+    receive_any_frame(&mut behaviour, peer, ConnectionId::new_unchecked(0));
+    send_frames(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        8,
+        true,
+    );
+    poll_settled(&mut behaviour);
+    assert!(behaviour.is_connectivity_asymmetric(&peer));
+
+    //// When
+    send_frames(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        8,
+        false,
+    );
+    poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(
+        !behaviour.is_connectivity_asymmetric(&peer),
+        "The suspicion should be dropped once the fail ratio is below the threshold"
+    );
+}
+
+#[test]
+fn suspicion_is_dropped_on_disconnection() {
+    testlib::init_logger();
+
+    //// Given
+    let peer = PeerId::random();
+    let connection_id = ConnectionId::new_unchecked(0);
+    let mut behaviour = Behaviour::new(
+        PeerId::random(),
+        new_test_config().build(),
+        Default::default(),
+    );
+    let handler = connect(&mut behaviour, peer, connection_id);
+    poll_settled(&mut behaviour);
+
+    receive_any_frame(&mut behaviour, peer, connection_id);
+    send_frames(&mut behaviour, peer, connection_id, 8, true);
+    poll_settled(&mut behaviour);
+    assert!(behaviour.is_connectivity_asymmetric(&peer));
+
+    //// When
+    disconnect(&mut behaviour, peer, connection_id, handler);
+    poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(!behaviour.is_connectivity_asymmetric(&peer));
+}
+
+#[test]
+fn suspected_peer_is_picked_last_as_a_destination() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let peer = PeerId::random();
+    let healthy_peer = PeerId::random();
+
+    let config = new_test_config().max_publish_fanout(Some(1)).build();
+    let mut behaviour = new_test_behaviour(config, &[peer, healthy_peer]);
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    receive_any_frame(&mut behaviour, peer, ConnectionId::new_unchecked(0));
+    send_frames(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        8,
+        true,
+    );
+    poll_settled(&mut behaviour);
+
+    //// When
+    let published = (0..8u64)
+        .flat_map(|idx| {
+            behaviour
+                .publish(Message::new_with_sequence_number(
+                    topic.clone(),
+                    b"test-payload".to_vec(),
+                    idx.to_be_bytes(),
+                ))
+                .expect("publish message");
+            sent_frames_destinations(&poll_settled(&mut behaviour))
+        })
+        .collect::<Vec<_>>();
+
+    //// Then
+    assert!(behaviour.is_connectivity_asymmetric(&peer));
+    assert_eq!(
+        published,
+        vec![healthy_peer; 8],
+        "The messages should be sent to the healthy peer"
+    );
+}
+
+#[test]
+fn suspected_peer_is_not_deprioritized_if_disabled() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let peer = PeerId::random();
+    let healthy_peer = PeerId::random();
+
+    let config = new_test_config()
+        .max_publish_fanout(Some(1))
+        .deprioritize_asymmetric_peers(false)
+        .build();
+    let mut behaviour = new_test_behaviour(config, &[peer, healthy_peer]);
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    receive_any_frame(&mut behaviour, peer, ConnectionId::new_unchecked(0));
+    send_frames(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        8,
+        true,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// When
+    let published = (0..32u64)
+        .flat_map(|idx| {
+            behaviour
+                .publish(Message::new_with_sequence_number(
+                    topic.clone(),
+                    b"test-payload".to_vec(),
+                    idx.to_be_bytes(),
+                ))
+                .expect("publish message");
+            sent_frames_destinations(&poll_settled(&mut behaviour))
+        })
+        .collect::<Vec<_>>();
+
+    //// Then
+    assert_eq!(
+        asymmetric_connectivity_suspicions(&events).len(),
+        1,
+        "The peer should still be notified"
+    );
+    assert!(
+        published.contains(&peer),
+        "The suspected peer should still be picked by the fan-out"
+    );
+}
@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, ToSwarm};
+use prost::Message as _;
+use rand::Rng;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, Frame, IdentTopic, Message,
+    SubscriptionAction, SubscriptionBuilder, TopicHash,
+};
+use libp2p_pubsub_proto::pubsub::FrameProto;
+use pubsub_testlib::{connect, poll_settled, receive_frame, NoopProtocol};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<NoopProtocol>;
+
+type HandlerCommand = pubsub_testlib::HandlerCommand<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Simulate the reception of a subscription action from the given peer.
+fn receive_subscription(
+    behaviour: &mut Behaviour,
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    action: SubscriptionAction,
+) {
+    receive_frame(
+        behaviour,
+        peer_id,
+        connection_id,
+        Frame::new_with_subscriptions([action]),
+    );
+}
+
+/// Collect the payloads of the messages sent to the given peer, in order.
+fn sent_messages(events: &[BehaviourEvent], dest: PeerId) -> Vec<Bytes> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerCommand::SendFrame { frame, .. },
+                ..
+            } if *peer_id == dest => Some(frame),
+            _ => None,
+        })
+        .flat_map(|frame| {
+            FrameProto::decode(frame.clone())
+                .expect("valid frame")
+                .publish
+        })
+        .filter_map(|message| message.data)
+        .collect()
+}
+
+/// Collect the backfill notifications, as `(peer, topic, count)` tuples.
+fn backfills_sent(events: &[BehaviourEvent]) -> Vec<(PeerId, TopicHash, usize)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::GenerateEvent(Event::BackfillSent {
+                peer, topic, count, ..
+            }) => Some((*peer, topic.clone(), *count)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Create a test behaviour subscribed to the topic, with the backfill enabled or not, and
+/// connected to the given peer.
+fn new_test_behaviour(
+    config: Config,
+    topic: &IdentTopic,
+    backfill: bool,
+    peer: PeerId,
+) -> Behaviour {
+    let mut behaviour = Behaviour::new(PeerId::random(), config, Default::default());
+
+    let mut subscription = SubscriptionBuilder::new(topic.clone());
+    subscription.backfill_on_peer_subscribe(backfill);
+    behaviour
+        .subscribe(subscription.build())
+        .expect("subscribe to topic");
+
+    connect(&mut behaviour, peer, ConnectionId::new_unchecked(0));
+    poll_settled(&mut behaviour);
+
+    behaviour
+}
+
+/// Publish the given payloads, in order.
+///
+/// Each message carries its own sequence number, so they get distinct message ids.
+fn publish_all(behaviour: &mut Behaviour, topic: &IdentTopic, payloads: &[&'static [u8]]) {
+    for (seq_no, payload) in payloads.iter().enumerate() {
+        behaviour
+            .publish(Message::new_with_sequence_number(
+                topic.clone(),
+                payload.to_vec(),
+                (seq_no as u64).to_be_bytes(),
+            ))
+            .expect("publish message");
+        poll_settled(behaviour);
+    }
+}
+
+#[test]
+fn peer_subscribing_after_publish_receives_backfilled_message_once() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let peer = PeerId::random();
+
+    let mut behaviour = new_test_behaviour(Config::default(), &topic, true, peer);
+    publish_all(&mut behaviour, &topic, &[b"latest-config"]);
+
+    //// When
+    receive_subscription(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        SubscriptionAction::Subscribe(topic.hash()),
+    );
+    let events = poll_settled(&mut behaviour);
+
+    // The peer re-announces its subscription, e.g., to refresh it.
+    receive_subscription(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        SubscriptionAction::Subscribe(topic.hash()),
+    );
+    let events_after_reannouncement = poll_settled(&mut behaviour);
+
+    //// Then
+    assert_eq!(
+        sent_messages(&events, peer),
+        [Bytes::from_static(b"latest-config")]
+    );
+    assert_eq!(backfills_sent(&events), [(peer, topic.hash(), 1)]);
+
+    assert!(
+        sent_messages(&events_after_reannouncement, peer).is_empty(),
+        "The message should be backfilled once"
+    );
+    assert!(backfills_sent(&events_after_reannouncement).is_empty());
+}
+
+#[test]
+fn backfill_is_capped_to_the_most_recent_messages_oldest_first() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let peer = PeerId::random();
+    let config = ConfigBuilder::default().backfill_max_messages(2).build();
+
+    let mut behaviour = new_test_behaviour(config, &topic, true, peer);
+    publish_all(&mut behaviour, &topic, &[b"first", b"second", b"third"]);
+
+    //// When
+    receive_subscription(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        SubscriptionAction::Subscribe(topic.hash()),
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert_eq!(
+        sent_messages(&events, peer),
+        [Bytes::from_static(b"second"), Bytes::from_static(b"third")]
+    );
+    assert_eq!(backfills_sent(&events), [(peer, topic.hash(), 2)]);
+}
+
+#[test]
+fn messages_older_than_max_age_are_not_backfilled() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let peer = PeerId::random();
+    let config = ConfigBuilder::default()
+        .backfill_max_age(Duration::from_millis(50))
+        .build();
+
+    let mut behaviour = new_test_behaviour(config, &topic, true, peer);
+    publish_all(&mut behaviour, &topic, &[b"stale"]);
+
+    std::thread::sleep(Duration::from_millis(100));
+
+    //// When
+    receive_subscription(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        SubscriptionAction::Subscribe(topic.hash()),
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(sent_messages(&events, peer).is_empty());
+    assert!(backfills_sent(&events).is_empty());
+}
+
+#[test]
+fn messages_are_not_backfilled_without_the_subscription_flag() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let peer = PeerId::random();
+
+    let mut behaviour = new_test_behaviour(Config::default(), &topic, false, peer);
+    publish_all(&mut behaviour, &topic, &[b"latest-config"]);
+
+    //// When
+    receive_subscription(
+        &mut behaviour,
+        peer,
+        ConnectionId::new_unchecked(0),
+        SubscriptionAction::Subscribe(topic.hash()),
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(sent_messages(&events, peer).is_empty());
+    assert!(backfills_sent(&events).is_empty());
+}
@@ -0,0 +1,247 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::Swarm;
+use rand::Rng;
+
+use libp2p_pubsub_core::protocol::{CompositeProtocol, Protocol};
+use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, BuildError, ConfigBuilder, Identity, PreflightCode,
+    TopicValidation,
+};
+use pubsub_testlib::{new_test_swarm, new_test_topic, NoopProtocol, NoopProtocolRouter};
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+/// A protocol advertising a protocol id without a leading `/`.
+#[derive(Default)]
+struct InvalidIdProtocol;
+
+impl Protocol for InvalidIdProtocol {
+    type Upgrade = SimpleProtocolUpgrade<&'static str>;
+    type RouterService = NoopProtocolRouter;
+
+    fn upgrade() -> Self::Upgrade {
+        SimpleProtocolUpgrade::new("noop/1.0.0")
+    }
+
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
+        Default::default()
+    }
+}
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+fn new_test_identity(keypair: &Keypair) -> Identity {
+    Identity::PeerId(PeerId::from(keypair.public()))
+}
+
+fn new_test_dedup_store_path() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!(
+        "it-pubsub-dedup-test-{}",
+        rand::thread_rng().gen::<u64>()
+    ))
+}
+
+fn new_test_node(keypair: &Keypair, behaviour: Behaviour) -> Swarm<Behaviour> {
+    new_test_swarm(keypair, behaviour)
+}
+
+#[tokio::test]
+async fn build_subscribes_to_the_imported_subscriptions() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    // Node A subscribes to the topic, and its topology snapshot is taken.
+    let node_a_behaviour = Behaviour::builder(NoopProtocol)
+        .identity(new_test_identity(&node_a_key))
+        .build()
+        .await
+        .expect("build the behaviour");
+    let mut node_a = new_test_node(&node_a_key, node_a_behaviour);
+    node_a
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    testlib::swarm::poll_node(Duration::from_millis(10), &mut node_a).await;
+
+    let snapshot = node_a.behaviour().topology_snapshot();
+
+    //// When
+    let node_b_behaviour = Behaviour::builder(NoopProtocol)
+        .identity(new_test_identity(&node_b_key))
+        .import_subscriptions(&snapshot)
+        .build()
+        .await
+        .expect("build the behaviour");
+    let mut node_b = new_test_node(&node_b_key, node_b_behaviour);
+    testlib::swarm::poll_node(Duration::from_millis(10), &mut node_b).await;
+
+    //// Then
+    assert!(
+        node_b.behaviour().subscriptions().contains(&topic.hash()),
+        "Node B should be subscribed to the imported topic"
+    );
+}
+
+#[tokio::test]
+async fn build_without_identity_fails() {
+    //// When
+    let result = Behaviour::builder(NoopProtocol).build().await;
+
+    //// Then
+    assert_matches!(result.err(), Some(BuildError::MissingIdentity));
+}
+
+#[tokio::test]
+async fn build_with_invalid_imported_subscription_fails() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(
+        &node_a_key,
+        Behaviour::new(
+            PeerId::from(node_a_key.public()),
+            Default::default(),
+            Default::default(),
+        ),
+    );
+    node_a
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    testlib::swarm::poll_node(Duration::from_millis(10), &mut node_a).await;
+
+    let snapshot = node_a.behaviour().topology_snapshot();
+
+    // Node B only accepts topics shorter than the imported one.
+    let node_b_config = ConfigBuilder::new()
+        .topic_validation(TopicValidation {
+            max_length: 8,
+            ..Default::default()
+        })
+        .build();
+
+    //// When
+    let result = Behaviour::builder(NoopProtocol)
+        .config(node_b_config)
+        .identity(new_test_identity(&node_b_key))
+        .import_subscriptions(&snapshot)
+        .build()
+        .await;
+
+    //// Then
+    assert_matches!(result.err(), Some(BuildError::InvalidSubscription { topic: invalid, .. }) => {
+        assert_eq!(invalid, topic.hash());
+    });
+}
+
+#[tokio::test]
+async fn build_with_unreadable_dedup_store_fails() {
+    //// Given
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+
+    //// When
+    // A directory cannot be read as a dedup store file.
+    let result = Behaviour::builder(NoopProtocol)
+        .identity(new_test_identity(&node_key))
+        .dedup_store(std::env::temp_dir())
+        .build()
+        .await;
+
+    //// Then
+    assert_matches!(result.err(), Some(BuildError::DedupStore(_)));
+}
+
+#[tokio::test]
+async fn build_opens_the_dedup_store() {
+    //// Given
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let path = new_test_dedup_store_path();
+
+    //// When
+    let result = Behaviour::builder(NoopProtocol)
+        .identity(new_test_identity(&node_key))
+        .dedup_store(&path)
+        .build()
+        .await;
+
+    //// Then
+    assert!(result.is_ok(), "The build should succeed");
+    assert!(path.exists(), "The dedup store file should be created");
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn build_with_denied_preflight_warnings_fails() {
+    //// Given
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+
+    // The heartbeat interval is longer than the message cache TTL.
+    let config = ConfigBuilder::new()
+        .heartbeat_interval(Duration::from_secs(10))
+        .message_cache_ttl(Duration::from_secs(1))
+        .build();
+
+    //// When
+    let denied = Behaviour::builder(NoopProtocol)
+        .config(config.clone())
+        .identity(new_test_identity(&node_key))
+        .deny_preflight_warnings(true)
+        .build()
+        .await;
+    let allowed = Behaviour::builder(NoopProtocol)
+        .config(config)
+        .identity(new_test_identity(&node_key))
+        .build()
+        .await;
+
+    //// Then
+    assert_matches!(denied.err(), Some(BuildError::Preflight(warnings)) => {
+        assert!(warnings
+            .iter()
+            .any(|warning| warning.code == PreflightCode::HeartbeatExceedsCacheTtl));
+    });
+    assert!(
+        allowed.is_ok(),
+        "The preflight warnings should only be logged"
+    );
+}
+
+#[tokio::test]
+async fn build_with_invalid_protocol_ids_fails() {
+    //// Given
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+
+    //// When
+    let invalid = PubsubBehaviour::builder(InvalidIdProtocol)
+        .identity(new_test_identity(&node_key))
+        .build()
+        .await;
+    let duplicate = PubsubBehaviour::builder(CompositeProtocol::new(NoopProtocol, NoopProtocol))
+        .identity(new_test_identity(&node_key))
+        .build()
+        .await;
+
+    //// Then
+    assert_matches!(invalid.err(), Some(BuildError::InvalidProtocolId(id)) => {
+        assert_eq!(id, "noop/1.0.0");
+    });
+    assert_matches!(duplicate.err(), Some(BuildError::DuplicateProtocolId(id)) => {
+        assert_eq!(id, "/noop/1.0.0");
+    });
+}
@@ -0,0 +1,266 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, ChaosDirection, ChaosPolicy, Event, FrameMessage, IdentTopic,
+    Message,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the received messages payloads, by
+/// node.
+async fn poll_mesh3_and_collect_payloads(
+    duration: Duration,
+    node_a: &mut Swarm<Behaviour>,
+    node_b: &mut Swarm<Behaviour>,
+    node_c: &mut Swarm<Behaviour>,
+    received: &mut [Vec<Vec<u8>>; 3],
+) {
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+
+    loop {
+        let (idx, event) = tokio::select! {
+            _ = &mut deadline => break,
+            event = node_a.select_next_some() => (0, event),
+            event = node_b.select_next_some() => (1, event),
+            event = node_c.select_next_some() => (2, event),
+        };
+        if let SwarmEvent::Behaviour(Event::MessageReceived { message, .. }) = event {
+            received[idx].push(message.data);
+        }
+    }
+}
+
+/// Create three nodes, all subscribed to the topic, connected in a triangle.
+async fn new_connected_nodes(
+    topic: &IdentTopic,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let node_c_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let mut node_c = new_test_node(&node_c_key, Default::default());
+
+    let (node_a_addr, node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b, &mut node_c] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr.clone()).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    node_c.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_c, &mut node_a),
+    )
+    .await
+    .expect("Node C to connect to Node A");
+
+    node_c.dial(node_b_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_c, &mut node_b),
+    )
+    .await
+    .expect("Node C to connect to Node B");
+
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    (node_a, node_b, node_c)
+}
+
+/// Create the burst message with the given index.
+fn new_burst_message(topic: &IdentTopic, idx: u64) -> Message {
+    Message::new_with_sequence_number(
+        topic.clone(),
+        format!("burst-message-{idx}").into_bytes(),
+        idx.to_be_bytes(),
+    )
+}
+
+#[tokio::test]
+async fn burst_is_eventually_delivered_despite_the_inbound_loss() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b, mut node_c) = new_connected_nodes(&topic).await;
+
+    // Drop 20% of the frames received by every node, and duplicate 10% of them.
+    for (seed, node) in [&mut node_a, &mut node_b, &mut node_c]
+        .into_iter()
+        .enumerate()
+    {
+        node.behaviour_mut().set_chaos_policy(ChaosPolicy {
+            drop_rate: 0.2,
+            duplicate_rate: 0.1,
+            seed: seed as u64,
+            ..ChaosPolicy::new(ChaosDirection::Inbound)
+        });
+    }
+
+    //// When
+    // Node B publishes a burst of messages, each message reaching Node A and Node C both directly
+    // and through the other node. Node B then re-propagates the burst, so the messages lost on
+    // both paths are eventually delivered.
+    let mut received: [Vec<Vec<u8>>; 3] = Default::default();
+    for idx in 0..20 {
+        node_b
+            .behaviour_mut()
+            .publish(new_burst_message(&topic, idx))
+            .expect("publish the message");
+        poll_mesh3_and_collect_payloads(
+            Duration::from_millis(5),
+            &mut node_a,
+            &mut node_b,
+            &mut node_c,
+            &mut received,
+        )
+        .await;
+    }
+    for _ in 0..5 {
+        poll_mesh3_and_collect_payloads(
+            Duration::from_millis(50),
+            &mut node_a,
+            &mut node_b,
+            &mut node_c,
+            &mut received,
+        )
+        .await;
+        for idx in 0..20 {
+            node_b
+                .behaviour_mut()
+                .forward_message(FrameMessage::from(new_burst_message(&topic, idx)), &[])
+                .expect("forward the message");
+        }
+    }
+    poll_mesh3_and_collect_payloads(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+        &mut received,
+    )
+    .await;
+
+    //// Then
+    let expected = (0..20)
+        .map(|idx| format!("burst-message-{idx}").into_bytes())
+        .collect::<BTreeSet<_>>();
+    for (name, idx) in [("Node A", 0), ("Node C", 2)] {
+        let payloads = received[idx].iter().cloned().collect::<BTreeSet<_>>();
+        assert_eq!(
+            payloads, expected,
+            "{name} should eventually receive all the messages"
+        );
+        assert_eq!(
+            received[idx].len(),
+            20,
+            "{name} should receive every message once, the duplicates being deduplicated"
+        );
+    }
+
+    let stats = [&node_a, &node_c].map(|node| node.behaviour().chaos_stats().inbound);
+    assert!(
+        stats.iter().all(|stats| stats.dropped > 0),
+        "Frames should be dropped: {stats:?}"
+    );
+    assert!(
+        stats.iter().all(|stats| stats.duplicated > 0),
+        "Frames should be duplicated: {stats:?}"
+    );
+    assert_eq!(
+        node_a.behaviour().chaos_stats().outbound,
+        Default::default(),
+        "The sent frames should be out of the policy scope"
+    );
+}
+
+#[tokio::test]
+async fn delayed_frames_are_delivered_once_due() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b, mut node_c) = new_connected_nodes(&topic).await;
+    node_b.behaviour_mut().set_chaos_policy(ChaosPolicy {
+        delay: Some((Duration::from_millis(100), Duration::from_millis(150))),
+        ..ChaosPolicy::new(ChaosDirection::Outbound)
+    });
+    node_b
+        .behaviour_mut()
+        .publish(new_burst_message(&topic, 0))
+        .expect("publish the message");
+
+    //// When
+    let mut received_early: [Vec<Vec<u8>>; 3] = Default::default();
+    poll_mesh3_and_collect_payloads(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+        &mut received_early,
+    )
+    .await;
+    let mut received: [Vec<Vec<u8>>; 3] = Default::default();
+    poll_mesh3_and_collect_payloads(
+        Duration::from_millis(200),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+        &mut received,
+    )
+    .await;
+
+    //// Then
+    assert!(received_early.iter().all(Vec::is_empty));
+    assert_eq!(received[0], [b"burst-message-0".to_vec()]);
+    assert_eq!(received[2], [b"burst-message-0".to_vec()]);
+    assert_eq!(node_b.behaviour().chaos_stats().outbound.delayed, 2);
+}
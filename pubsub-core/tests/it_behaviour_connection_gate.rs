@@ -0,0 +1,132 @@
+use libp2p::core::Endpoint;
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, NetworkBehaviour, ToSwarm};
+use rand::Rng;
+
+use libp2p_pubsub_core::{
+    AllowList, Behaviour as PubsubBehaviour, ConfigBuilder, ConnectionGateDenied, Frame,
+    GateDecision, IdentTopic, SubscriptionAction,
+};
+use pubsub_testlib::{connect, new_test_endpoint, poll_settled, receive_frame, NoopProtocol};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Collect the peers the behaviour sent frames to.
+fn frame_destinations(events: &[BehaviourEvent]) -> Vec<PeerId> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::NotifyHandler { peer_id, .. } => Some(*peer_id),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn connections_denied_by_the_gate_are_refused() {
+    testlib::init_logger();
+
+    //// Given
+    let member = PeerId::random();
+    let stranger = PeerId::random();
+    let endpoint = new_test_endpoint();
+
+    let config = ConfigBuilder::default()
+        .connection_gate(Some(AllowList::new([member]).into_gate()))
+        .build();
+    let mut behaviour = Behaviour::new(PeerId::random(), config, Default::default());
+
+    //// When
+    let inbound = behaviour.handle_established_inbound_connection(
+        ConnectionId::new_unchecked(0),
+        stranger,
+        endpoint.get_remote_address(),
+        endpoint.get_remote_address(),
+    );
+    let outbound = behaviour.handle_established_outbound_connection(
+        ConnectionId::new_unchecked(1),
+        stranger,
+        endpoint.get_remote_address(),
+        Endpoint::Dialer,
+    );
+    let member_inbound = behaviour.handle_established_inbound_connection(
+        ConnectionId::new_unchecked(2),
+        member,
+        endpoint.get_remote_address(),
+        endpoint.get_remote_address(),
+    );
+
+    //// Then
+    for result in [inbound, outbound] {
+        let denied = result
+            .err()
+            .expect("connection to be denied")
+            .downcast::<ConnectionGateDenied>()
+            .expect("denied by the connection gate");
+        assert_eq!(denied.peer, stranger);
+    }
+    assert!(member_inbound.is_ok(), "The member should be accepted");
+}
+
+#[test]
+fn connection_allowed_disabled_carries_no_pubsub_traffic() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let member = PeerId::random();
+    let stranger = PeerId::random();
+
+    let mut allowlist = AllowList::new([member]);
+    allowlist.otherwise(GateDecision::AllowDisabled);
+    let config = ConfigBuilder::default()
+        .connection_gate(Some(allowlist.into_gate()))
+        .build();
+    let mut behaviour = Behaviour::new(PeerId::random(), config, Default::default());
+
+    //// When
+    connect(&mut behaviour, member, ConnectionId::new_unchecked(0));
+    connect(&mut behaviour, stranger, ConnectionId::new_unchecked(1));
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    // The stranger announces its subscription over the disabled connection.
+    receive_frame(
+        &mut behaviour,
+        stranger,
+        ConnectionId::new_unchecked(1),
+        Frame::new_with_subscriptions([SubscriptionAction::Subscribe(topic.hash())]),
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    let destinations = frame_destinations(&events);
+    assert!(
+        destinations.contains(&member),
+        "The subscription should be announced to the member"
+    );
+    assert!(
+        !destinations.contains(&stranger),
+        "No frame should be sent over the disabled connection"
+    );
+    assert!(
+        !events
+            .iter()
+            .any(|ev| matches!(ev, ToSwarm::CloseConnection { .. })),
+        "The disabled connection should not be closed"
+    );
+    assert!(behaviour.peer_subscriptions(&stranger).is_none());
+    assert!(!behaviour.connections().is_active(&stranger));
+}
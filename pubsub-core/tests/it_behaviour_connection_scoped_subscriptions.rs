@@ -0,0 +1,223 @@
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, NetworkBehaviour, NotifyHandler, ToSwarm};
+use prost::Message as _;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Frame, IdentTopic, Message,
+    SubscriptionAction, TopicHash,
+};
+use libp2p_pubsub_proto::pubsub::FrameProto;
+use pubsub_testlib::{
+    connect, connect_with_established, disconnect_with_remaining, new_test_endpoint,
+    new_test_topic, poll_all, receive_frame, BroadcastProtocol,
+};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<BroadcastProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<BroadcastProtocol>;
+
+type HandlerCommand = pubsub_testlib::HandlerCommand<BroadcastProtocol>;
+
+/// Simulate the reception of a subscription action over the given connection.
+fn receive_subscription(
+    behaviour: &mut Behaviour,
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    action: SubscriptionAction,
+) {
+    let frame = Frame::new_with_subscriptions([action]);
+    receive_frame(behaviour, peer_id, connection_id, frame);
+}
+
+/// Collect the messages topics sent, and the connection they were sent to. If sent to any of the
+/// peer connections, the connection is `None`.
+fn sent_messages(events: &[BehaviourEvent]) -> Vec<(Option<ConnectionId>, TopicHash)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::NotifyHandler {
+                handler,
+                event: HandlerCommand::SendFrame { frame, .. },
+                ..
+            } => {
+                let connection = match handler {
+                    NotifyHandler::One(connection) => Some(*connection),
+                    NotifyHandler::Any => None,
+                };
+                Some((connection, FrameProto::decode(frame.clone()).ok()?))
+            }
+            _ => None,
+        })
+        .flat_map(|(connection, frame)| {
+            frame
+                .publish
+                .into_iter()
+                .map(move |message| (connection, TopicHash::from_raw(message.topic)))
+        })
+        .collect()
+}
+
+/// Create a gateway peer with two connections to the local node, each one subscribed to one of
+/// the topics.
+fn new_test_gateway(
+    config: Config,
+    topic_a: &IdentTopic,
+    topic_b: &IdentTopic,
+) -> (Behaviour, PeerId, [ConnectionId; 2]) {
+    let local_peer_id = PeerId::random();
+    let gateway = PeerId::random();
+    let connections = [
+        ConnectionId::new_unchecked(1),
+        ConnectionId::new_unchecked(2),
+    ];
+
+    let mut behaviour = Behaviour::new(local_peer_id, config, Default::default());
+    behaviour
+        .subscribe(topic_a.clone())
+        .expect("subscribe to topic");
+    behaviour
+        .subscribe(topic_b.clone())
+        .expect("subscribe to topic");
+
+    let _handler_a = connect(&mut behaviour, gateway, connections[0]);
+    let _handler_b = connect_with_established(&mut behaviour, gateway, connections[1], 1);
+    poll_all(&mut behaviour);
+
+    receive_subscription(
+        &mut behaviour,
+        gateway,
+        connections[0],
+        SubscriptionAction::Subscribe(topic_a.hash()),
+    );
+    receive_subscription(
+        &mut behaviour,
+        gateway,
+        connections[1],
+        SubscriptionAction::Subscribe(topic_b.hash()),
+    );
+    poll_all(&mut behaviour);
+
+    (behaviour, gateway, connections)
+}
+
+#[test]
+fn gateway_connections_only_receive_their_subscribed_topics_messages() {
+    testlib::init_logger();
+
+    //// Given
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+
+    let config = ConfigBuilder::new()
+        .connection_scoped_subscriptions(true)
+        .build();
+    let (mut behaviour, gateway, [connection_a, connection_b]) =
+        new_test_gateway(config, &topic_a, &topic_b);
+
+    //// When
+    behaviour
+        .publish(Message::new(topic_a.clone(), b"test-payload-a".to_vec()))
+        .expect("publish message");
+    behaviour
+        .publish(Message::new(topic_b.clone(), b"test-payload-b".to_vec()))
+        .expect("publish message");
+    let publish_events = poll_all(&mut behaviour);
+
+    //// Then
+    assert_eq!(
+        sent_messages(&publish_events),
+        [
+            (Some(connection_a), topic_a.hash()),
+            (Some(connection_b), topic_b.hash()),
+        ],
+        "Each connection should only receive its subscribed topic messages"
+    );
+
+    let connection_a_topics = behaviour
+        .connection_subscriptions(&gateway, &connection_a)
+        .expect("connection subscriptions to be tracked");
+    assert!(connection_a_topics.contains(&topic_a.hash()));
+    assert!(!connection_a_topics.contains(&topic_b.hash()));
+
+    let peer_topics = behaviour
+        .peer_subscriptions(&gateway)
+        .expect("peer subscriptions to be tracked");
+    assert_eq!(
+        peer_topics.len(),
+        2,
+        "The gateway should be subscribed to both topics"
+    );
+}
+
+#[test]
+fn closed_gateway_connection_subscriptions_are_dropped() {
+    testlib::init_logger();
+
+    //// Given
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+
+    let config = ConfigBuilder::new()
+        .connection_scoped_subscriptions(true)
+        .build();
+    let (mut behaviour, gateway, [connection_a, connection_b]) =
+        new_test_gateway(config, &topic_a, &topic_b);
+
+    //// When
+    let endpoint = new_test_endpoint();
+    let handler = behaviour
+        .handle_established_inbound_connection(
+            connection_b,
+            gateway,
+            endpoint.get_remote_address(),
+            endpoint.get_remote_address(),
+        )
+        .expect("connection to be accepted");
+    disconnect_with_remaining(&mut behaviour, gateway, connection_b, handler, 1);
+    poll_all(&mut behaviour);
+
+    //// Then
+    assert!(behaviour
+        .connection_subscriptions(&gateway, &connection_b)
+        .is_none());
+    assert!(behaviour
+        .connection_subscriptions(&gateway, &connection_a)
+        .is_some());
+    assert!(
+        !behaviour
+            .peer_subscriptions(&gateway)
+            .expect("peer subscriptions to be tracked")
+            .contains(&topic_b.hash()),
+        "The gateway should no longer be subscribed to Topic B"
+    );
+}
+
+#[test]
+fn gateway_subscriptions_are_merged_by_default() {
+    testlib::init_logger();
+
+    //// Given
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+
+    let (mut behaviour, gateway, [connection_a, _]) =
+        new_test_gateway(Default::default(), &topic_a, &topic_b);
+
+    //// When
+    behaviour
+        .publish(Message::new(topic_a.clone(), b"test-payload-a".to_vec()))
+        .expect("publish message");
+    let publish_events = poll_all(&mut behaviour);
+
+    //// Then
+    assert_eq!(
+        sent_messages(&publish_events),
+        [(None, topic_a.hash())],
+        "The message should be sent to any of the gateway connections"
+    );
+    assert!(behaviour
+        .connection_subscriptions(&gateway, &connection_a)
+        .is_none());
+}
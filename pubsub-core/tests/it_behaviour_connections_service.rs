@@ -18,7 +18,7 @@ type Behaviour = PubsubBehaviour<NoopProtocol>;
 fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
     let peer_id = PeerId::from(keypair.public());
     let transport = testlib::test_transport(keypair);
-    let behaviour = Behaviour::new(config, Default::default());
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
     SwarmBuilder::with_executor(
         transport,
         behaviour,
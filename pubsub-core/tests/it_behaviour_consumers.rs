@@ -0,0 +1,265 @@
+use std::time::Duration;
+
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, ConsumerTag, Event, IdentTopic, Message};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Create two connected nodes: Node B, subscribed to the topic, dials Node A.
+async fn new_connected_nodes(topic: &IdentTopic) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    node_b
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    (node_a, node_b)
+}
+
+/// Publish the given payloads from Node B, and return the tags of the consumers and the payloads
+/// of the messages notified by Node A, along with the number of `MessageReceived` events.
+async fn publish_and_collect_consumer_messages(
+    topic: &IdentTopic,
+    payloads: &[&[u8]],
+    node_a: &mut Swarm<Behaviour>,
+    node_b: &mut Swarm<Behaviour>,
+) -> (Vec<(ConsumerTag, Vec<u8>)>, usize) {
+    for payload in payloads {
+        node_b
+            .behaviour_mut()
+            .publish(Message::new(topic.clone(), payload.to_vec()))
+            .expect("publish the message");
+    }
+
+    let (node_a_events, _) =
+        testlib::swarm::poll_mesh_and_collect_events(Duration::from_millis(50), node_a, node_b)
+            .await;
+
+    let mut consumer_messages = Vec::new();
+    let mut received_count = 0;
+    for event in node_a_events {
+        match event {
+            SwarmEvent::Behaviour(Event::MessageForConsumer { tag, message, .. }) => {
+                consumer_messages.push((tag, message.data));
+            }
+            SwarmEvent::Behaviour(Event::MessageReceived { .. }) => received_count += 1,
+            _ => {}
+        }
+    }
+    (consumer_messages, received_count)
+}
+
+#[tokio::test]
+async fn every_consumer_gets_every_message_once() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic).await;
+
+    let _consumer_1 = node_a
+        .behaviour_mut()
+        .register_consumer(topic.hash(), ConsumerTag::new("plugin-1"))
+        .expect("register consumer");
+    let _consumer_2 = node_a
+        .behaviour_mut()
+        .register_consumer(topic.hash(), ConsumerTag::new("plugin-2"))
+        .expect("register consumer");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// When
+    let (messages, received_count) = publish_and_collect_consumer_messages(
+        &topic,
+        &[b"message-1", b"message-2"],
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    let mut messages = messages
+        .into_iter()
+        .map(|(tag, data)| (tag.to_string(), data))
+        .collect::<Vec<_>>();
+    messages.sort();
+    assert_eq!(
+        messages,
+        [
+            ("plugin-1".to_string(), b"message-1".to_vec()),
+            ("plugin-1".to_string(), b"message-2".to_vec()),
+            ("plugin-2".to_string(), b"message-1".to_vec()),
+            ("plugin-2".to_string(), b"message-2".to_vec()),
+        ]
+    );
+    assert_eq!(
+        received_count, 0,
+        "The topic is not explicitly subscribed to"
+    );
+}
+
+#[tokio::test]
+async fn dropping_a_consumer_keeps_the_topic_subscribed() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic).await;
+    let node_a_id = *node_a.local_peer_id();
+
+    let consumer_1 = node_a
+        .behaviour_mut()
+        .register_consumer(topic.hash(), ConsumerTag::new("plugin-1"))
+        .expect("register consumer");
+    let _consumer_2 = node_a
+        .behaviour_mut()
+        .register_consumer(topic.hash(), ConsumerTag::new("plugin-2"))
+        .expect("register consumer");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// When
+    drop(consumer_1);
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    let (messages, _) =
+        publish_and_collect_consumer_messages(&topic, &[b"message"], &mut node_a, &mut node_b)
+            .await;
+
+    //// Then
+    assert!(node_a.behaviour().subscriptions().contains(&topic.hash()));
+    assert!(
+        node_b
+            .behaviour()
+            .peer_subscriptions(&node_a_id)
+            .map_or(false, |topics| topics.contains(&topic.hash())),
+        "Node B should still see Node A subscribed to the topic"
+    );
+    assert_eq!(
+        node_a
+            .behaviour()
+            .consumers(&topic.hash())
+            .collect::<Vec<_>>(),
+        [&ConsumerTag::new("plugin-2")]
+    );
+    assert_eq!(
+        messages,
+        [(ConsumerTag::new("plugin-2"), b"message".to_vec())]
+    );
+}
+
+#[tokio::test]
+async fn dropping_all_consumers_unsubscribes_from_the_topic() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic).await;
+    let node_a_id = *node_a.local_peer_id();
+
+    let consumer_1 = node_a
+        .behaviour_mut()
+        .register_consumer(topic.hash(), ConsumerTag::new("plugin-1"))
+        .expect("register consumer");
+    let consumer_2 = node_a
+        .behaviour_mut()
+        .register_consumer(topic.hash(), ConsumerTag::new("plugin-2"))
+        .expect("register consumer");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+    let subscribed_before = node_b
+        .behaviour()
+        .peer_subscriptions(&node_a_id)
+        .map_or(false, |topics| topics.contains(&topic.hash()));
+
+    //// When
+    drop(consumer_1);
+    node_a.behaviour_mut().unregister_consumer(consumer_2);
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert!(subscribed_before, "Node B should see Node A subscribed");
+    assert!(!node_a.behaviour().subscriptions().contains(&topic.hash()));
+    assert!(
+        !node_b
+            .behaviour()
+            .peer_subscriptions(&node_a_id)
+            .map_or(false, |topics| topics.contains(&topic.hash())),
+        "Node B should see Node A unsubscribed from the topic"
+    );
+}
+
+#[tokio::test]
+async fn explicit_subscription_outlives_the_consumers() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic).await;
+
+    node_a
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    let consumer = node_a
+        .behaviour_mut()
+        .register_consumer(topic.hash(), ConsumerTag::new("plugin"))
+        .expect("register consumer");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// When
+    let (messages, received_count) =
+        publish_and_collect_consumer_messages(&topic, &[b"message"], &mut node_a, &mut node_b)
+            .await;
+
+    drop(consumer);
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert_eq!(messages.len(), 1);
+    assert_eq!(received_count, 1, "The topic is explicitly subscribed to");
+    assert!(
+        node_a.behaviour().subscriptions().contains(&topic.hash()),
+        "The explicit subscription retains the topic"
+    );
+}
@@ -0,0 +1,198 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, DeadLetterStage, Event, Message,
+};
+use pubsub_testlib::{new_broadcast_test_node, new_test_topic, BroadcastProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<BroadcastProtocol>;
+
+fn new_dead_letter_config(capacity: usize, events: bool) -> Config {
+    ConfigBuilder::new()
+        .max_frame_size(1024)
+        .dead_letter(true)
+        .dead_letter_capacity(capacity)
+        .dead_letter_events(events)
+        .build()
+}
+
+fn dead_letter_stages(node: &Swarm<Behaviour>) -> Vec<DeadLetterStage> {
+    node.behaviour()
+        .dead_letters()
+        .map(|letter| letter.stage)
+        .collect()
+}
+
+#[tokio::test]
+async fn dropped_inputs_are_recorded_as_dead_letters() {
+    testlib::init_logger();
+
+    //// Given
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+    let topic_c = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_broadcast_test_node(&node_a_key, new_dead_letter_config(16, false));
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_broadcast_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    node_a
+        .behaviour_mut()
+        .subscribe(topic_a.clone())
+        .expect("subscribe to topic");
+    node_b
+        .behaviour_mut()
+        .subscribe(topic_b.clone())
+        .expect("subscribe to topic");
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    //// When
+    // A subscription request for an already subscribed topic.
+    let resubscribed = node_a
+        .behaviour_mut()
+        .subscribe(topic_a.clone())
+        .expect("subscribe to topic");
+
+    // An unsubscription request for a not subscribed topic.
+    let unsubscribed = node_a
+        .behaviour_mut()
+        .unsubscribe(&topic_c)
+        .expect("unsubscribe from topic");
+
+    // A message exceeding the maximum frame size.
+    node_a
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            topic_a.clone(),
+            vec![0; 2048],
+            1_u64.to_be_bytes(),
+        ))
+        .expect("publish the message");
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    // A message published twice.
+    for _ in 0..2 {
+        node_a
+            .behaviour_mut()
+            .publish(Message::new_with_sequence_number(
+                topic_a.clone(),
+                b"duplicate".to_vec(),
+                2_u64.to_be_bytes(),
+            ))
+            .expect("publish the message");
+        testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+    }
+
+    // A message received on a not subscribed topic.
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic_b.clone(), b"not-subscribed".to_vec()))
+        .expect("publish the message");
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert!(!resubscribed, "The subscription request should be dropped");
+    assert!(
+        !unsubscribed,
+        "The unsubscription request should be dropped"
+    );
+
+    assert_eq!(
+        dead_letter_stages(&node_a),
+        [
+            DeadLetterStage::SubscriptionFilter,
+            DeadLetterStage::SubscriptionFilter,
+            DeadLetterStage::SizeLimit,
+            DeadLetterStage::CacheDuplicate,
+            DeadLetterStage::NotSubscribed,
+        ]
+    );
+
+    let letters = node_a.behaviour().dead_letters().collect::<Vec<_>>();
+    assert_eq!(letters[0].reason, "already subscribed");
+    assert!(letters[0].summary.contains(&topic_a.hash().to_string()));
+    assert_eq!(letters[1].reason, "not subscribed");
+    assert!(letters[1].summary.contains(&topic_c.hash().to_string()));
+    assert!(letters[4]
+        .summary
+        .contains(&node_b.local_peer_id().to_string()));
+
+    assert_eq!(
+        node_b.behaviour().dead_letters().count(),
+        0,
+        "No dead letters should be recorded if disabled"
+    );
+}
+
+#[tokio::test]
+async fn dead_letters_buffer_keeps_the_most_recent_letters() {
+    testlib::init_logger();
+
+    //// Given
+    let topics = [new_test_topic(), new_test_topic(), new_test_topic()];
+
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let mut node = new_broadcast_test_node(&node_key, new_dead_letter_config(2, true));
+
+    //// When
+    for topic in &topics {
+        node.behaviour_mut()
+            .unsubscribe(topic)
+            .expect("unsubscribe from topic");
+    }
+
+    let events =
+        testlib::swarm::poll_node_and_collect_events(Duration::from_millis(10), &mut node).await;
+
+    //// Then
+    let summaries = node
+        .behaviour()
+        .dead_letters()
+        .map(|letter| letter.summary.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summaries.len(),
+        2,
+        "Only the 2 most recent letters should be kept"
+    );
+    assert!(summaries[0].contains(&topics[1].hash().to_string()));
+    assert!(summaries[1].contains(&topics[2].hash().to_string()));
+
+    assert_eq!(events.len(), 3, "All the dead letters should be emitted");
+    for (event, topic) in events.iter().zip(&topics) {
+        assert_matches!(event, SwarmEvent::Behaviour(Event::DeadLetter(letter)) => {
+            assert_eq!(letter.stage, DeadLetterStage::SubscriptionFilter);
+            assert!(letter.summary.contains(&topic.hash().to_string()));
+        });
+    }
+}
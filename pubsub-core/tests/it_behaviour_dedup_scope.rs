@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use libp2p::swarm::SwarmEvent;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    sha256_message_id_fn, ConfigBuilder, DedupScope, Event, IdentTopic, Message, TopicHash,
+};
+use pubsub_testlib::new_test_node;
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Publish the same payload to two topics from Node B, and return the topic and payload of the
+/// messages received by Node A.
+async fn publish_same_payload_on_two_topics(dedup_scope: DedupScope) -> Vec<(TopicHash, Vec<u8>)> {
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+
+    // The message id only covers the message payload.
+    let config = ConfigBuilder::new()
+        .default_message_id_fn(sha256_message_id_fn)
+        .dedup_scope(dedup_scope)
+        .build();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, config.clone());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, config);
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        for topic in [&topic_a, &topic_b] {
+            node.behaviour_mut()
+                .subscribe(topic.clone())
+                .expect("subscribe to topic");
+        }
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    let mut node_a_events = Vec::new();
+    for topic in [&topic_a, &topic_b] {
+        node_b
+            .behaviour_mut()
+            .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+            .expect("publish the message");
+
+        let (events, _) = testlib::swarm::poll_mesh_and_collect_events(
+            Duration::from_millis(50),
+            &mut node_a,
+            &mut node_b,
+        )
+        .await;
+        node_a_events.extend(events);
+    }
+
+    node_a_events
+        .into_iter()
+        .filter_map(|ev| match ev {
+            SwarmEvent::Behaviour(Event::MessageReceived { message, .. }) => {
+                Some((message.topic, message.data))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn same_payload_on_two_topics_is_delivered_once_with_global_scope() {
+    testlib::init_logger();
+
+    //// When
+    let received = publish_same_payload_on_two_topics(DedupScope::Global).await;
+
+    //// Then
+    assert_eq!(
+        received.len(),
+        1,
+        "The second message should be deduplicated"
+    );
+}
+
+#[tokio::test]
+async fn same_payload_on_two_topics_is_delivered_twice_with_per_topic_scope() {
+    testlib::init_logger();
+
+    //// When
+    let received = publish_same_payload_on_two_topics(DedupScope::PerTopic).await;
+
+    //// Then
+    assert_eq!(
+        received.len(),
+        2,
+        "The message should be delivered on both topics"
+    );
+    assert_ne!(
+        received[0].0, received[1].0,
+        "The messages should be delivered on distinct topics"
+    );
+    assert!(received.iter().all(|(_, data)| data == b"test-payload"));
+}
@@ -0,0 +1,103 @@
+use assert_matches::assert_matches;
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, NetworkBehaviour, ToSwarm};
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, ConfigBuilder, DisabledReason, Event, Message,
+};
+use pubsub_testlib::{connect, new_test_topic, poll_all, BroadcastProtocol};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<BroadcastProtocol>;
+
+type HandlerEvent = pubsub_testlib::HandlerEvent<BroadcastProtocol>;
+
+/// Simulate the connection handler of the given connection being disabled.
+fn disable(behaviour: &mut Behaviour, peer_id: PeerId, connection_id: ConnectionId) {
+    behaviour.on_connection_handler_event(
+        peer_id,
+        connection_id,
+        HandlerEvent::Disabled {
+            reason: DisabledReason::MaxSendRetriesReached,
+        },
+    );
+}
+
+#[test]
+fn disabled_peer_is_not_included_in_forward_destinations() {
+    testlib::init_logger();
+
+    //// Given
+    let local_peer_id = PeerId::random();
+    let peer_a = PeerId::random();
+    let peer_b = PeerId::random();
+    let connection_a = ConnectionId::new_unchecked(1);
+    let topic = new_test_topic();
+
+    // No peer announces its subscriptions, do not warn about the missing subscribers.
+    let config = ConfigBuilder::new().warn_on_no_subscribers(false).build();
+    let mut behaviour = Behaviour::new(local_peer_id, config, Default::default());
+
+    let _handler_a = connect(&mut behaviour, peer_a, connection_a);
+    let _handler_b = connect(&mut behaviour, peer_b, ConnectionId::new_unchecked(2));
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    poll_all(&mut behaviour);
+
+    //// When
+    disable(&mut behaviour, peer_a, connection_a);
+    let disable_events = poll_all(&mut behaviour);
+
+    behaviour
+        .publish(Message::new(topic, b"test-payload".to_vec()))
+        .expect("publish message");
+    let publish_events = poll_all(&mut behaviour);
+
+    //// Then
+    assert_matches!(
+        &disable_events[..],
+        [ToSwarm::GenerateEvent(Event::ConnectionDisabled { peer, connection_id, reason, .. })] => {
+            assert_eq!(peer, &peer_a);
+            assert_eq!(connection_id, &connection_a);
+            assert_eq!(reason, &DisabledReason::MaxSendRetriesReached);
+        }
+    );
+    assert_matches!(
+        &publish_events[..],
+        [ToSwarm::NotifyHandler { peer_id, .. }] => {
+            assert_eq!(peer_id, &peer_b, "Message should only be sent to the enabled peer");
+        }
+    );
+    assert!(!behaviour.connections().is_active(&peer_a));
+}
+
+#[test]
+fn publish_fails_when_the_only_peer_is_disabled() {
+    testlib::init_logger();
+
+    //// Given
+    let local_peer_id = PeerId::random();
+    let peer = PeerId::random();
+    let connection_id = ConnectionId::new_unchecked(1);
+    let topic = new_test_topic();
+
+    let mut behaviour = Behaviour::new(local_peer_id, Default::default(), Default::default());
+
+    let _handler = connect(&mut behaviour, peer, connection_id);
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    poll_all(&mut behaviour);
+
+    //// When
+    disable(&mut behaviour, peer, connection_id);
+    poll_all(&mut behaviour);
+
+    let result = behaviour.publish(Message::new(topic, b"test-payload".to_vec()));
+
+    //// Then
+    assert!(result.is_err(), "Publish should fail without usable peers");
+    assert_eq!(behaviour.connections().active_peers_count(), 0);
+}
@@ -0,0 +1,219 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::{SwarmBuilder, SwarmEvent};
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+use tracing_futures::Instrument;
+
+use libp2p_pubsub_common::service::{BufferedContext, EventHandler, OnEventCtx};
+use libp2p_pubsub_core::protocol::{
+    Protocol, ProtocolRouterConnectionEvent, ProtocolRouterInEvent, ProtocolRouterOutEvent,
+    ProtocolRouterSubscriptionEvent,
+};
+use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, Event, IdentTopic};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+use testlib::service::noop_context;
+
+/// A protocol disconnecting the peers as soon as they subscribe to a topic.
+#[derive(Default)]
+struct DisconnectingProtocol;
+
+impl Protocol for DisconnectingProtocol {
+    type Upgrade = SimpleProtocolUpgrade<&'static str>;
+    type RouterService = DisconnectingProtocolRouter;
+
+    fn upgrade() -> Self::Upgrade {
+        SimpleProtocolUpgrade::new("/disconnecting/1.0.0")
+    }
+
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
+        Default::default()
+    }
+}
+
+/// The pubsub protocol router service for the disconnecting protocol.
+///
+/// The router requests the disconnection of a subscribed peer twice, and requests it again once
+/// the peer disconnected, as a misbehaving router would.
+#[derive(Default)]
+struct DisconnectingProtocolRouter;
+
+impl EventHandler for DisconnectingProtocolRouter {
+    type InEvent = ProtocolRouterInEvent;
+    type OutEvent = ProtocolRouterOutEvent;
+
+    fn on_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
+        ev: Self::InEvent,
+    ) {
+        match ev {
+            ProtocolRouterInEvent::SubscriptionEvent(
+                ProtocolRouterSubscriptionEvent::PeerSubscribed { peer, .. }
+                | ProtocolRouterSubscriptionEvent::PeerSubscribedMany { peer, .. },
+            ) => {
+                svc_cx.emit(ProtocolRouterOutEvent::disconnect_peer(peer, "subscribed"));
+                svc_cx.emit(ProtocolRouterOutEvent::disconnect_peer(peer, "subscribed"));
+            }
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerDisconnected(peer),
+            ) => {
+                svc_cx.emit(ProtocolRouterOutEvent::disconnect_peer(
+                    peer,
+                    "disconnected",
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+type Behaviour = PubsubBehaviour<DisconnectingProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair);
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
+    SwarmBuilder::with_executor(
+        transport,
+        behaviour,
+        peer_id,
+        |fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+            tokio::spawn(fut.in_current_span());
+        },
+    )
+    .build()
+}
+
+#[test]
+fn router_service_emits_disconnect_peer_requests() {
+    //// Given
+    let peer = PeerId::random();
+    let topic = new_test_topic().hash();
+
+    let mut service = BufferedContext::new(DisconnectingProtocolRouter);
+
+    //// When
+    testlib::service::inject_events(
+        &mut service,
+        [ProtocolRouterInEvent::SubscriptionEvent(
+            ProtocolRouterSubscriptionEvent::PeerSubscribed { peer, topic },
+        )],
+    );
+    let output_events = testlib::service::collect_events(&mut service, &mut noop_context());
+
+    //// Then
+    assert_eq!(output_events.len(), 2, "Only 2 events should be emitted");
+    for event in &output_events {
+        assert_matches!(event, ProtocolRouterOutEvent::DisconnectPeer { peer: disconnected, reason, .. } => {
+            assert_eq!(disconnected, &peer);
+            assert_eq!(reason, "subscribed");
+        });
+    }
+}
+
+#[tokio::test]
+async fn router_disconnect_request_closes_the_peer_connections_once() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    //// When
+    // Node A router requests the disconnection of Node B once subscribed.
+    node_b
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    let (node_a_events, _) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    let disconnect_requests = node_a_events
+        .iter()
+        .filter(|ev| {
+            matches!(
+                ev,
+                SwarmEvent::Behaviour(Event::PeerDisconnectRequested { .. })
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        disconnect_requests.len(),
+        1,
+        "Only 1 disconnection should be requested"
+    );
+    assert_matches!(disconnect_requests[0], SwarmEvent::Behaviour(Event::PeerDisconnectRequested { peer, reason, .. }) => {
+        assert_eq!(peer, node_b.local_peer_id());
+        assert_eq!(reason, "subscribed");
+    });
+
+    let closed_connections = node_a_events
+        .iter()
+        .filter(|ev| matches!(ev, SwarmEvent::ConnectionClosed { .. }))
+        .count();
+    assert_eq!(closed_connections, 1, "The connection should be closed");
+
+    assert!(
+        !node_a.is_connected(node_b.local_peer_id()),
+        "Node B should be disconnected"
+    );
+    assert_eq!(
+        node_a
+            .behaviour()
+            .connections()
+            .peer_connections_count(node_b.local_peer_id()),
+        0,
+        "The connections service should observe the closed connection"
+    );
+    assert!(
+        node_a
+            .behaviour()
+            .peer_subscriptions(node_b.local_peer_id())
+            .is_none(),
+        "The subscriptions service should forget the disconnected peer"
+    );
+}
@@ -0,0 +1,70 @@
+use std::task::Poll;
+
+use assert_matches::assert_matches;
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, ToSwarm};
+use rand::Rng;
+
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, IdentTopic};
+use pubsub_testlib::{connect, disconnect, poll_all, poll_once, NoopProtocol};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+#[test]
+fn queued_frames_to_a_disconnected_peer_are_dropped() {
+    testlib::init_logger();
+
+    //// Given
+    let local_peer_id = PeerId::random();
+    let peer_a = PeerId::random();
+    let peer_b = PeerId::random();
+
+    let mut behaviour = Behaviour::new(local_peer_id, Default::default(), Default::default());
+
+    let handler_a = connect(&mut behaviour, peer_a, ConnectionId::new_unchecked(1));
+    let handler_b = connect(&mut behaviour, peer_b, ConnectionId::new_unchecked(2));
+    poll_all(&mut behaviour);
+
+    // Subscribe to a topic. A subscription frame is queued for each connected peer, and only the
+    // first one is returned by the poll.
+    behaviour
+        .subscribe(new_test_topic())
+        .expect("subscribe to topic");
+    let first_dest = assert_matches!(
+        poll_once(&mut behaviour),
+        Poll::Ready(ToSwarm::NotifyHandler { peer_id, .. }) => peer_id
+    );
+
+    //// When
+    // The peer whose frame is still queued disconnects before the frame is sent.
+    let (dest, connection_id, handler) = if first_dest == peer_a {
+        (peer_b, ConnectionId::new_unchecked(2), handler_b)
+    } else {
+        (peer_a, ConnectionId::new_unchecked(1), handler_a)
+    };
+    disconnect(&mut behaviour, dest, connection_id, handler);
+
+    let events = poll_all(&mut behaviour);
+
+    //// Then
+    assert!(
+        !events
+            .iter()
+            .any(|ev| matches!(ev, ToSwarm::NotifyHandler { peer_id, .. } if *peer_id == dest)),
+        "No frame should be sent to the disconnected peer"
+    );
+    assert_eq!(
+        behaviour.dropped_frames_disconnected(),
+        1,
+        "The queued frame should be dropped"
+    );
+}
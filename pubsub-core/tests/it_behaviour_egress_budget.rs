@@ -0,0 +1,228 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, BytesPerSecond, Config, ConfigBuilder, Event, IdentTopic,
+    Message, Priority,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the second node behaviour events.
+async fn poll_mesh_and_collect_events(
+    duration: Duration,
+    node_a: &mut Swarm<Behaviour>,
+    node_b: &mut Swarm<Behaviour>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            _ = node_a.select_next_some() => {},
+            event = node_b.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    events.push(event);
+                }
+            },
+        }
+    }
+
+    events
+}
+
+/// Create two connected nodes subscribed to the topic, Node A with the given configuration.
+async fn new_connected_nodes(
+    topic: &IdentTopic,
+    node_a_config: Config,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    (node_a, node_b)
+}
+
+/// Get the payloads of the messages received among the given events.
+fn received_payloads(events: &[Event]) -> Vec<Vec<u8>> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            Event::MessageReceived { message, .. } => Some(message.data.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Publish `count` messages of 500 bytes on the topic, with a payload starting with their index.
+fn publish_messages(node: &mut Swarm<Behaviour>, topic: &IdentTopic, count: u8) {
+    for idx in 0..count {
+        let mut payload = vec![0; 500];
+        payload[0] = idx;
+        node.behaviour_mut()
+            .publish(Message::new_with_sequence_number(
+                topic.clone(),
+                payload,
+                u64::from(idx).to_be_bytes(),
+            ))
+            .expect("publish the message");
+    }
+}
+
+#[tokio::test]
+async fn frames_exceeding_the_egress_budget_are_held_back() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let node_a_config = ConfigBuilder::new()
+        .egress_budget(Some(BytesPerSecond(2_000)))
+        .build();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, node_a_config).await;
+
+    //// When
+    publish_messages(&mut node_a, &topic, 20);
+    let node_b_events =
+        poll_mesh_and_collect_events(Duration::from_millis(200), &mut node_a, &mut node_b).await;
+
+    //// Then
+    let received = received_payloads(&node_b_events);
+    let stats = node_a
+        .behaviour()
+        .egress_stats()
+        .expect("egress budget set");
+    assert!(
+        received.len() < 10,
+        "The frames exceeding the budget should be held back, got {}",
+        received.len()
+    );
+    assert!(stats.queued_frames >= 10);
+    assert!(received.len() + stats.queued_frames <= 20);
+    assert!(stats.delayed_frames >= stats.queued_frames as u64);
+    assert!(stats.delayed_bytes >= 500 * stats.delayed_frames);
+    assert_eq!(
+        node_a.behaviour().pending_event_counts().paced_frames,
+        stats.queued_frames
+    );
+}
+
+#[tokio::test]
+async fn high_priority_frames_bypass_the_held_back_frames() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let node_a_config = ConfigBuilder::new()
+        .egress_budget(Some(BytesPerSecond(2_000)))
+        .build();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, node_a_config).await;
+    publish_messages(&mut node_a, &topic, 20);
+
+    //// When
+    let mut message = Message::new_with_sequence_number(
+        topic.clone(),
+        b"high-priority-payload",
+        20u64.to_be_bytes(),
+    );
+    message.set_priority(Priority::High);
+    node_a
+        .behaviour_mut()
+        .publish(message)
+        .expect("publish the message");
+    let node_b_events =
+        poll_mesh_and_collect_events(Duration::from_millis(500), &mut node_a, &mut node_b).await;
+
+    //// Then
+    let received = received_payloads(&node_b_events);
+    assert!(
+        received.contains(&b"high-priority-payload".to_vec()),
+        "The high priority message should be released ahead of the held back messages"
+    );
+    assert!(
+        received.len() < 21,
+        "Some messages should still be held back"
+    );
+}
+
+#[tokio::test]
+async fn removing_the_egress_budget_releases_the_held_back_frames() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let node_a_config = ConfigBuilder::new()
+        .egress_budget(Some(BytesPerSecond(2_000)))
+        .build();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, node_a_config).await;
+    publish_messages(&mut node_a, &topic, 20);
+    let node_b_events_before =
+        poll_mesh_and_collect_events(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// When
+    node_a.behaviour_mut().set_egress_budget(None);
+    let node_b_events_after =
+        poll_mesh_and_collect_events(Duration::from_millis(100), &mut node_a, &mut node_b).await;
+
+    //// Then
+    let received_before = received_payloads(&node_b_events_before);
+    let received_after = received_payloads(&node_b_events_after);
+    assert!(received_before.len() < 20);
+    assert_eq!(
+        received_before
+            .iter()
+            .chain(&received_after)
+            .map(|payload| payload[0])
+            .collect::<Vec<_>>(),
+        (0..20).collect::<Vec<_>>(),
+        "All the messages should be received, in order, once the budget is removed"
+    );
+    assert!(node_a.behaviour().egress_stats().is_none());
+    assert_eq!(node_a.behaviour().pending_event_counts().paced_frames, 0);
+}
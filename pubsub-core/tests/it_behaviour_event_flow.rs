@@ -0,0 +1,344 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::task::Poll;
+
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, ToSwarm};
+use prost::Message as _;
+
+use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+use libp2p_pubsub_core::protocol::{
+    Protocol, ProtocolRouterConnectionEvent, ProtocolRouterInEvent, ProtocolRouterMessageEvent,
+    ProtocolRouterOutEvent, ProtocolRouterSubscriptionEvent,
+};
+use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Event, Frame, FrameMessage, IdentTopic, Message,
+    SubscriptionAction,
+};
+use libp2p_pubsub_proto::pubsub::FrameProto;
+use pubsub_testlib::{connect, disconnect, poll_once, receive_frame};
+
+mod pubsub_testlib;
+
+/// The event flow log, shared by the test and the recording router.
+type EventLog = Rc<RefCell<Vec<String>>>;
+
+/// A test protocol whose router records the events it is notified of, and floods the published
+/// and received messages to all the connected peers but the message propagator.
+struct RecordingProtocol {
+    log: EventLog,
+}
+
+impl Protocol for RecordingProtocol {
+    type Upgrade = SimpleProtocolUpgrade<&'static str>;
+    type RouterService = RecordingRouter;
+
+    fn upgrade() -> Self::Upgrade {
+        SimpleProtocolUpgrade::new("/recording/1.0.0")
+    }
+
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
+        RecordingRouter {
+            log: self.log.clone(),
+            peers: Vec::new(),
+        }
+    }
+}
+
+/// The pubsub protocol router service for the recording protocol.
+struct RecordingRouter {
+    log: EventLog,
+    peers: Vec<PeerId>,
+}
+
+impl EventHandler for RecordingRouter {
+    type InEvent = ProtocolRouterInEvent;
+    type OutEvent = ProtocolRouterOutEvent;
+
+    fn on_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
+        ev: Self::InEvent,
+    ) {
+        let entry = match &ev {
+            ProtocolRouterInEvent::ConnectionEvent(ev) => match ev {
+                ProtocolRouterConnectionEvent::PeerConnected(peer) => {
+                    format!("router: peer connected {peer}")
+                }
+                ProtocolRouterConnectionEvent::PeerDisconnected(peer) => {
+                    format!("router: peer disconnected {peer}")
+                }
+                ev => format!("router: {ev:?}"),
+            },
+            ProtocolRouterInEvent::SubscriptionEvent(ev) => match ev {
+                ProtocolRouterSubscriptionEvent::Subscribed(sub) => {
+                    format!("router: subscribed {}", sub.topic)
+                }
+                ProtocolRouterSubscriptionEvent::Unsubscribed(topic) => {
+                    format!("router: unsubscribed {topic}")
+                }
+                ev => format!("router: {ev:?}"),
+            },
+            ProtocolRouterInEvent::MessageEvent(ev) => match ev {
+                ProtocolRouterMessageEvent::MessagePublished { message, .. } => {
+                    format!("router: message published {}", message.topic())
+                }
+                ProtocolRouterMessageEvent::MessageReceived { src, message, .. } => {
+                    format!("router: message received {} from {src}", message.topic())
+                }
+                ev => format!("router: {ev:?}"),
+            },
+            ProtocolRouterInEvent::ForwardFanout(_) => "router: forward fanout".to_string(),
+            ev => format!("router: {ev:?}"),
+        };
+        self.log.borrow_mut().push(entry);
+
+        match ev {
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerConnected(peer),
+            ) => {
+                self.peers.push(peer);
+            }
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerDisconnected(peer),
+            ) => {
+                self.peers.retain(|p| *p != peer);
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessagePublished {
+                message,
+                ..
+            }) => {
+                let dest = self.peers.clone();
+                svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage { dest, message });
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessageReceived {
+                src,
+                message,
+                ..
+            }) => {
+                let dest = self.peers.iter().copied().filter(|p| *p != src).collect();
+                svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage { dest, message });
+            }
+            _ => {}
+        }
+    }
+}
+
+type Behaviour = PubsubBehaviour<RecordingProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<RecordingProtocol>;
+
+type HandlerCommand = pubsub_testlib::HandlerCommand<RecordingProtocol>;
+
+/// Describe a frame sent to a peer.
+fn describe_frame(dest: &PeerId, frame: &[u8]) -> String {
+    let frame = FrameProto::decode(frame).expect("valid frame");
+    let subscriptions = frame
+        .subscriptions
+        .iter()
+        .map(|sub| {
+            let action = if sub.subscribe.unwrap_or_default() {
+                "+"
+            } else {
+                "-"
+            };
+            format!("{action}{}", sub.topic_id.as_deref().unwrap_or_default())
+        })
+        .collect::<Vec<_>>();
+    let messages = frame
+        .publish
+        .iter()
+        .map(|msg| {
+            let data = msg.data.as_deref().unwrap_or_default();
+            format!("{}:{}", msg.topic, String::from_utf8_lossy(data))
+        })
+        .collect::<Vec<_>>();
+    format!("swarm: frame to {dest} subscriptions={subscriptions:?} messages={messages:?}")
+}
+
+/// Describe an event emitted by the behaviour.
+fn describe_event(event: &BehaviourEvent) -> String {
+    match event {
+        ToSwarm::GenerateEvent(Event::MessageReceived { src, message, .. }) => format!(
+            "swarm: message received {} from {src}: {}",
+            message.topic,
+            String::from_utf8_lossy(&message.data)
+        ),
+        ToSwarm::GenerateEvent(ev) => format!("swarm: {ev:?}"),
+        ToSwarm::NotifyHandler {
+            peer_id,
+            event: HandlerCommand::SendFrame { frame, .. },
+            ..
+        } => describe_frame(peer_id, frame),
+        ToSwarm::NotifyHandler {
+            peer_id,
+            event: HandlerCommand::SendTaggedFrame { frame, tag },
+            ..
+        } => format!("{} tag={tag}", describe_frame(peer_id, frame)),
+        ToSwarm::NotifyHandler { peer_id, event, .. } => {
+            format!("swarm: {event:?} to {peer_id}")
+        }
+        ToSwarm::Dial { .. } => "swarm: dial".to_string(),
+        ToSwarm::CloseConnection { peer_id, .. } => format!("swarm: close connection {peer_id}"),
+        _ => "swarm: other".to_string(),
+    }
+}
+
+/// Poll the behaviour until it returns `Poll::Pending`, a few times, and log the emitted events.
+///
+/// The service events go through several services, some of them polled before the service
+/// feeding them. As the test waker does not reschedule the behaviour, it is polled a few times.
+fn poll_and_log(behaviour: &mut Behaviour, log: &EventLog) {
+    for _ in 0..3 {
+        while let Poll::Ready(event) = poll_once(behaviour) {
+            let entry = describe_event(&event);
+            log.borrow_mut().push(entry);
+        }
+    }
+}
+
+/// Normalize the event flow log: label the peers, and sort the consecutive frames sent to the
+/// peers, as their order depends on the peers iteration order.
+fn normalize(log: &[String], peers: &[(PeerId, &str)]) -> Vec<String> {
+    let mut flow = log
+        .iter()
+        .map(|entry| {
+            peers.iter().fold(entry.clone(), |entry, (peer, label)| {
+                entry.replace(&peer.to_string(), label)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let is_frame = |entry: &String| entry.starts_with("swarm: frame to");
+    let mut start = 0;
+    while start < flow.len() {
+        let len = flow[start..].iter().take_while(|e| is_frame(e)).count();
+        flow[start..start + len].sort();
+        start += len.max(1);
+    }
+
+    flow
+}
+
+/// Characterizes the events flowing through the behaviour services, and out of the behaviour, for
+/// a scripted scenario covering the connections, the local and remote subscriptions and the
+/// messages publication and reception.
+#[test]
+fn behaviour_event_flow_for_scripted_scenario() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = IdentTopic::new("/pubsub/2/it-event-flow");
+    let local = PeerId::random();
+    let peer_a = PeerId::random();
+    let peer_b = PeerId::random();
+    let conn_a = ConnectionId::new_unchecked(1);
+    let conn_b = ConnectionId::new_unchecked(2);
+
+    let log = EventLog::default();
+    let mut behaviour = Behaviour::new(
+        local,
+        Default::default(),
+        RecordingProtocol { log: log.clone() },
+    );
+
+    //// When
+    log.borrow_mut().push("step: subscribe".to_string());
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    poll_and_log(&mut behaviour, &log);
+
+    log.borrow_mut().push("step: connect A and B".to_string());
+    let _handler_a = connect(&mut behaviour, peer_a, conn_a);
+    let handler_b = connect(&mut behaviour, peer_b, conn_b);
+    poll_and_log(&mut behaviour, &log);
+
+    log.borrow_mut().push("step: A and B subscribe".to_string());
+    for (peer, conn) in [(peer_a, conn_a), (peer_b, conn_b)] {
+        receive_frame(
+            &mut behaviour,
+            peer,
+            conn,
+            Frame::new_with_subscriptions([SubscriptionAction::Subscribe(topic.hash())]),
+        );
+    }
+    poll_and_log(&mut behaviour, &log);
+
+    log.borrow_mut().push("step: A sends a message".to_string());
+    receive_frame(
+        &mut behaviour,
+        peer_a,
+        conn_a,
+        Frame::new_with_messages([FrameMessage::new_with_seq_no_and_from(
+            topic.hash(),
+            b"from-a".to_vec(),
+            b"1",
+            peer_a,
+        )]),
+    );
+    poll_and_log(&mut behaviour, &log);
+
+    log.borrow_mut().push("step: publish".to_string());
+    behaviour
+        .publish(Message::new(topic.clone(), b"from-local".to_vec()))
+        .expect("publish message");
+    poll_and_log(&mut behaviour, &log);
+
+    log.borrow_mut().push("step: A unsubscribes".to_string());
+    receive_frame(
+        &mut behaviour,
+        peer_a,
+        conn_a,
+        Frame::new_with_subscriptions([SubscriptionAction::Unsubscribe(topic.hash())]),
+    );
+    poll_and_log(&mut behaviour, &log);
+
+    log.borrow_mut().push("step: disconnect B".to_string());
+    disconnect(&mut behaviour, peer_b, conn_b, handler_b);
+    poll_and_log(&mut behaviour, &log);
+
+    log.borrow_mut().push("step: unsubscribe".to_string());
+    behaviour
+        .unsubscribe(&topic)
+        .expect("unsubscribe from topic");
+    poll_and_log(&mut behaviour, &log);
+
+    //// Then
+    let flow = normalize(
+        &log.borrow(),
+        &[(local, "LOCAL"), (peer_a, "A"), (peer_b, "B")],
+    );
+    let expected: &[&str] = &[
+        "step: subscribe",
+        "router: forward fanout",
+        "router: subscribed /pubsub/2/it-event-flow",
+        r#"swarm: Subscribed { topic: TopicHash { hash: "/pubsub/2/it-event-flow" } }"#,
+        "step: connect A and B",
+        "router: peer connected A",
+        "router: peer connected B",
+        r#"swarm: frame to A subscriptions=["+/pubsub/2/it-event-flow"] messages=[]"#,
+        r#"swarm: frame to B subscriptions=["+/pubsub/2/it-event-flow"] messages=[]"#,
+        "step: A and B subscribe",
+        r#"router: PeerSubscribed { peer: PeerId("A"), topic: TopicHash { hash: "/pubsub/2/it-event-flow" } }"#,
+        r#"router: PeerSubscribed { peer: PeerId("B"), topic: TopicHash { hash: "/pubsub/2/it-event-flow" } }"#,
+        "step: A sends a message",
+        "router: message received /pubsub/2/it-event-flow from A",
+        r#"swarm: frame to B subscriptions=[] messages=["/pubsub/2/it-event-flow:from-a"]"#,
+        "swarm: message received /pubsub/2/it-event-flow from A: from-a",
+        "step: publish",
+        "router: message published /pubsub/2/it-event-flow",
+        r#"swarm: frame to A subscriptions=[] messages=["/pubsub/2/it-event-flow:from-local"]"#,
+        r#"swarm: frame to B subscriptions=[] messages=["/pubsub/2/it-event-flow:from-local"]"#,
+        "step: A unsubscribes",
+        r#"router: PeerUnsubscribed { peer: PeerId("A"), topic: TopicHash { hash: "/pubsub/2/it-event-flow" } }"#,
+        "step: disconnect B",
+        "router: peer disconnected B",
+        "step: unsubscribe",
+        "router: unsubscribed /pubsub/2/it-event-flow",
+        r#"swarm: frame to A subscriptions=["-/pubsub/2/it-event-flow"] messages=[]"#,
+        r#"swarm: Unsubscribed { topic: TopicHash { hash: "/pubsub/2/it-event-flow" } }"#,
+    ];
+    assert_eq!(flow, expected, "{flow:#?}");
+}
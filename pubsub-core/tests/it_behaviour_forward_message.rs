@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use futures::StreamExt;
+use libp2p::identity::PeerId;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, ConfigBuilder, Event, ForwardError, FrameMessage, IdentTopic,
+    Message,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the first node behaviour events.
+async fn poll_mesh3_and_collect_events(
+    duration: Duration,
+    node: &mut Swarm<Behaviour>,
+    other_1: &mut Swarm<Behaviour>,
+    other_2: &mut Swarm<Behaviour>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => break,
+            event = node.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    events.push(event);
+                }
+            },
+            _ = other_1.select_next_some() => {},
+            _ = other_2.select_next_some() => {},
+        }
+    }
+
+    events
+}
+
+#[tokio::test]
+async fn forwarded_message_reaches_the_subscribed_peers_except_the_excluded_ones() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let node_c_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    // Node A holds back the received messages forwarding, so the application re-propagates them.
+    let node_a_config = ConfigBuilder::new()
+        .forward_warmup(Some(Duration::from_secs(60)))
+        .build();
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let mut node_c = new_test_node(&node_c_key, Default::default());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b, &mut node_c] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    // B -> A <- C
+    node_b.dial(node_a_addr.clone()).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    node_c.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_c, &mut node_a),
+    )
+    .await
+    .expect("Node C to connect to Node A");
+
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    // Node B publishes a message, received and cached by Node A, but not forwarded.
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish the message");
+
+    let node_a_events = poll_mesh3_and_collect_events(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    let message = node_a_events
+        .into_iter()
+        .find_map(|ev| match ev {
+            Event::MessageReceived { message, .. } => Some(message),
+            _ => None,
+        })
+        .expect("Node A to receive the message");
+    assert_eq!(node_c.behaviour().traffic_totals().received_messages, 0);
+
+    //// When
+    let queued = node_a
+        .behaviour_mut()
+        .forward_message(FrameMessage::from(message), &[*node_b.local_peer_id()])
+        .expect("forward the message");
+
+    let node_a_events = poll_mesh3_and_collect_events(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(queued, 1, "The message should be queued to Node C only");
+    assert_eq!(
+        node_a.behaviour().traffic_totals().forwarded_messages,
+        1,
+        "Node A should forward the message once"
+    );
+    assert_eq!(
+        node_b.behaviour().traffic_totals().received_messages,
+        0,
+        "The excluded Node B should not receive the message"
+    );
+    assert_eq!(
+        node_c.behaviour().traffic_totals().received_messages,
+        1,
+        "Node C should receive the message"
+    );
+    assert!(
+        !node_a_events
+            .iter()
+            .any(|ev| matches!(ev, Event::MessageReceived { .. })),
+        "The forwarded message should not be notified to Node A application"
+    );
+}
+
+#[test]
+fn forward_message_on_a_not_subscribed_topic_fails() {
+    //// Given
+    let topic = new_test_topic();
+
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let mut behaviour = Behaviour::new(
+        PeerId::from(node_key.public()),
+        Default::default(),
+        Default::default(),
+    );
+
+    //// When
+    let result = behaviour.forward_message(
+        FrameMessage::new(topic.hash(), b"test-payload".to_vec()),
+        &[],
+    );
+
+    //// Then
+    assert_matches!(result, Err(ForwardError::NotSubscribed(not_subscribed)) => {
+        assert_eq!(not_subscribed, topic.hash());
+    });
+}
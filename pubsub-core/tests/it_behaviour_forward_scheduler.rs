@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, ToSwarm};
+use prost::Message as _;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, IdentTopic, Message, Priority,
+    SubscriptionBuilder,
+};
+use libp2p_pubsub_proto::pubsub::FrameProto;
+use pubsub_testlib::{connect, new_test_topic, poll_all, BroadcastProtocol};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<BroadcastProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<BroadcastProtocol>;
+
+type HandlerCommand = pubsub_testlib::HandlerCommand<BroadcastProtocol>;
+
+/// Collect the messages payloads sent, and the peer they were sent to, in order.
+fn sent_messages(events: &[BehaviourEvent]) -> Vec<(PeerId, Bytes)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerCommand::SendFrame { frame, .. },
+                ..
+            } => Some((*peer_id, FrameProto::decode(frame.clone()).ok()?)),
+            _ => None,
+        })
+        .flat_map(|(peer, frame)| {
+            frame
+                .publish
+                .into_iter()
+                .filter_map(move |message| Some((peer, message.data?)))
+        })
+        .collect()
+}
+
+/// Collect the messages payloads sent, and their priority, in order.
+fn sent_priorities(events: &[BehaviourEvent]) -> Vec<(Bytes, Priority)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::NotifyHandler {
+                event:
+                    HandlerCommand::SendFrame {
+                        frame, priority, ..
+                    },
+                ..
+            } => Some((FrameProto::decode(frame.clone()).ok()?, *priority)),
+            _ => None,
+        })
+        .flat_map(|(frame, priority)| {
+            frame
+                .publish
+                .into_iter()
+                .filter_map(move |message| Some((message.data?, priority)))
+        })
+        .collect()
+}
+
+/// Create a test behaviour subscribed to the topic and connected to the given peers.
+fn new_test_behaviour(config: Config, topic: &IdentTopic, peers: &[PeerId]) -> Behaviour {
+    let mut behaviour = Behaviour::new(PeerId::random(), config, Default::default());
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    for (id, peer) in peers.iter().enumerate() {
+        connect(&mut behaviour, *peer, ConnectionId::new_unchecked(id));
+    }
+    poll_all(&mut behaviour);
+
+    behaviour
+}
+
+/// Publish `count` messages on the topic, with distinct sequence numbers.
+fn publish_messages(behaviour: &mut Behaviour, topic: &IdentTopic, count: u64) {
+    for seq_no in 0..count {
+        behaviour
+            .publish(Message::new_with_sequence_number(
+                topic.clone(),
+                format!("message-{seq_no}"),
+                seq_no.to_be_bytes(),
+            ))
+            .expect("publish message");
+    }
+}
+
+#[test]
+fn large_fan_out_is_spread_over_several_poll_cycles() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let peers = (0..20).map(|_| PeerId::random()).collect::<Vec<_>>();
+    let config = ConfigBuilder::default()
+        .max_forwards_per_poll(50)
+        .max_peer_forwards_per_poll(10)
+        .build();
+
+    let mut behaviour = new_test_behaviour(config, &topic, &peers);
+
+    //// When
+    // 10 messages forwarded to 20 peers: 200 forwards.
+    publish_messages(&mut behaviour, &topic, 10);
+
+    let mut cycles = Vec::new();
+    let mut pending_forwards = Vec::new();
+    loop {
+        let sent = sent_messages(&poll_all(&mut behaviour));
+        pending_forwards.push(behaviour.pending_forwards());
+        if sent.is_empty() {
+            break;
+        }
+        cycles.push(sent);
+    }
+
+    //// Then
+    assert_eq!(
+        cycles.iter().map(Vec::len).collect::<Vec<_>>(),
+        [50, 50, 50, 50],
+        "At most 50 messages should be sent per poll cycle"
+    );
+    assert_eq!(pending_forwards, [150, 100, 50, 0, 0]);
+
+    // Every peer receives all the messages, in the publishing order.
+    let mut received = HashMap::<PeerId, Vec<Bytes>>::new();
+    for (peer, data) in cycles.into_iter().flatten() {
+        received.entry(peer).or_default().push(data);
+    }
+    let expected = (0..10)
+        .map(|seq_no| Bytes::from(format!("message-{seq_no}")))
+        .collect::<Vec<_>>();
+    assert_eq!(received.len(), peers.len());
+    for peer in &peers {
+        assert_eq!(received[peer], expected, "Messages should be sent in order");
+    }
+}
+
+#[test]
+fn forwards_to_a_single_peer_are_capped_per_poll_cycle_in_order() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (peer_a, peer_b) = (PeerId::random(), PeerId::random());
+    let config = ConfigBuilder::default()
+        .max_peer_forwards_per_poll(4)
+        .build();
+
+    let mut behaviour = new_test_behaviour(config, &topic, &[peer_a, peer_b]);
+
+    //// When
+    publish_messages(&mut behaviour, &topic, 10);
+
+    let first_cycle = sent_messages(&poll_all(&mut behaviour));
+    let pending_peer_forwards = behaviour.pending_peer_forwards(&peer_a);
+    let second_cycle = sent_messages(&poll_all(&mut behaviour));
+    let third_cycle = sent_messages(&poll_all(&mut behaviour));
+
+    //// Then
+    let sent_to = |sent: &[(PeerId, Bytes)], peer: PeerId| {
+        sent.iter()
+            .filter(|(dest, _)| *dest == peer)
+            .map(|(_, data)| data.clone())
+            .collect::<Vec<_>>()
+    };
+    let messages = |range: std::ops::Range<u64>| {
+        range
+            .map(|seq_no| Bytes::from(format!("message-{seq_no}")))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(sent_to(&first_cycle, peer_a), messages(0..4));
+    assert_eq!(sent_to(&first_cycle, peer_b), messages(0..4));
+    assert_eq!(pending_peer_forwards, 6);
+    assert_eq!(sent_to(&second_cycle, peer_a), messages(4..8));
+    assert_eq!(sent_to(&third_cycle, peer_a), messages(8..10));
+    assert_eq!(behaviour.pending_forwards(), 0);
+}
+
+#[test]
+fn high_priority_message_is_forwarded_ahead_of_the_held_back_messages() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let peer = PeerId::random();
+    let config = ConfigBuilder::default()
+        .max_peer_forwards_per_poll(2)
+        .build();
+
+    let mut behaviour = new_test_behaviour(config, &topic, &[peer]);
+
+    //// When
+    // The peer is backed up: 10 messages are held back by the forwarding scheduler.
+    publish_messages(&mut behaviour, &topic, 10);
+
+    let mut message = Message::new_with_sequence_number(topic.clone(), "high", 10u64.to_be_bytes());
+    message.set_priority(Priority::High);
+    behaviour.publish(message).expect("publish message");
+
+    let first_cycle = sent_priorities(&poll_all(&mut behaviour));
+
+    //// Then
+    assert_eq!(
+        first_cycle,
+        [
+            (Bytes::from_static(b"high"), Priority::High),
+            (Bytes::from_static(b"message-0"), Priority::Normal),
+        ],
+        "The high priority message should be sent first"
+    );
+    assert_eq!(behaviour.pending_peer_forwards(&peer), 9);
+}
+
+#[test]
+fn messages_of_a_high_priority_topic_are_forwarded_with_high_priority() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let peer = PeerId::random();
+
+    let mut behaviour = Behaviour::new(PeerId::random(), Default::default(), Default::default());
+    let mut subscription = SubscriptionBuilder::new(topic.clone());
+    subscription.priority(Priority::High);
+    behaviour
+        .subscribe(subscription.build())
+        .expect("subscribe to topic");
+    connect(&mut behaviour, peer, ConnectionId::new_unchecked(0));
+    poll_all(&mut behaviour);
+
+    //// When
+    publish_messages(&mut behaviour, &topic, 1);
+    let sent = sent_priorities(&poll_all(&mut behaviour));
+
+    //// Then
+    assert_eq!(
+        sent,
+        [(Bytes::from_static(b"message-0"), Priority::High)],
+        "The message should inherit the topic priority"
+    );
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, Message};
+use pubsub_testlib::{new_test_swarm, new_test_topic, BroadcastProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<BroadcastProtocol>;
+
+/// Create a test node sending the published and received messages to all the connected peers,
+/// including the message propagation source.
+fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let behaviour = Behaviour::new(peer_id, config, BroadcastProtocol::rebroadcasting());
+    new_test_swarm(keypair, behaviour)
+}
+
+#[tokio::test]
+async fn received_messages_are_not_forwarded_while_warming_up() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let node_a_config = ConfigBuilder::new()
+        .forward_warmup(Some(Duration::from_millis(200)))
+        .build();
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    node_a
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    node_b
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    //// When
+    // Node B publishes a message while Node A is warming up.
+    let warming_up = node_a.behaviour().is_warming_up();
+    node_b
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            topic.clone(),
+            b"warming-up".to_vec(),
+            1_u64.to_be_bytes(),
+        ))
+        .expect("publish the message");
+
+    let (warmup_events, _) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+    let warmup_forwarded = node_a.behaviour().traffic_totals().forwarded_messages;
+
+    // Wait for the warm-up to complete.
+    let (completion_events, _) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(250),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    // Node B publishes a message once Node A warm-up completed.
+    node_b
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            topic.clone(),
+            b"warmed-up".to_vec(),
+            2_u64.to_be_bytes(),
+        ))
+        .expect("publish the message");
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert!(warming_up, "Node A should be warming up");
+    assert!(
+        warmup_events
+            .iter()
+            .any(|ev| matches!(ev, SwarmEvent::Behaviour(Event::MessageReceived { .. }))),
+        "The message should be delivered locally while warming up"
+    );
+    assert_eq!(
+        warmup_forwarded, 0,
+        "No message should be forwarded while warming up"
+    );
+
+    assert!(
+        completion_events
+            .iter()
+            .any(|ev| matches!(ev, SwarmEvent::Behaviour(Event::WarmupCompleted))),
+        "The warm-up completion should be notified"
+    );
+    assert!(!node_a.behaviour().is_warming_up());
+
+    assert_eq!(
+        node_a.behaviour().traffic_totals().forwarded_messages,
+        1,
+        "Only the message received after the warm-up should be forwarded"
+    );
+}
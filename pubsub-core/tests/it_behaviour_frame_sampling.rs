@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, FrameDirection, IdentTopic, Message,
+    SamplingConfig,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Create two connected nodes subscribed to the topic, Node A with the given configuration.
+async fn new_connected_nodes(
+    topic: &IdentTopic,
+    node_a_config: Config,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    (node_a, node_b)
+}
+
+#[tokio::test]
+async fn received_and_sent_frames_are_sampled() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let node_a_config = ConfigBuilder::new()
+        .frame_sampling(Some(SamplingConfig {
+            sample_sent: true,
+            ..SamplingConfig::new(1.0)
+        }))
+        .build();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, node_a_config).await;
+    node_a.behaviour_mut().clear_sampled_frames();
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish the message");
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    let node_b_id = *node_b.local_peer_id();
+    let received = node_a
+        .behaviour()
+        .sampled_frames()
+        .filter(|frame| frame.direction == FrameDirection::Received)
+        .collect::<Vec<_>>();
+    assert_eq!(received.len(), 1, "The received frame should be sampled");
+    assert_eq!(received[0].peer, node_b_id);
+    assert!(
+        received[0]
+            .decode_report()
+            .contains(&format!("message #0: topic {}", topic.hash())),
+        "The report should describe the frame message"
+    );
+
+    node_b
+        .behaviour_mut()
+        .unsubscribe(&topic)
+        .expect("unsubscribe from topic");
+    node_a.behaviour_mut().clear_sampled_frames();
+    node_a
+        .behaviour_mut()
+        .unsubscribe(&topic)
+        .expect("unsubscribe from topic");
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+    assert!(
+        node_a
+            .behaviour()
+            .sampled_frames()
+            .any(|frame| frame.direction == FrameDirection::Sent && frame.peer == node_b_id),
+        "The sent unsubscription frame should be sampled"
+    );
+}
+
+#[tokio::test]
+async fn no_frame_is_sampled_unless_enabled() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, Default::default()).await;
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish the message");
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert_eq!(node_a.behaviour().sampled_frames().count(), 0);
+}
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use libp2p::swarm::SwarmEvent;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{Config, ConfigBuilder, Event, Message};
+use pubsub_testlib::{new_test_node, new_test_topic};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+/// A config notifying any peer sending fewer than 2 messages per frame, regardless of its
+/// message rate.
+fn new_framing_stats_config() -> Config {
+    ConfigBuilder::new()
+        .inefficient_framing_threshold(2.0)
+        .inefficient_framing_min_message_rate(0.0)
+        .build()
+}
+
+#[tokio::test]
+async fn peer_sending_one_message_per_frame_is_notified() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, new_framing_stats_config());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    node_a
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    node_b
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    //// When
+    // Node B sends every message in its own frame.
+    let mut node_a_events = Vec::new();
+    for seqno in 0..3_u64 {
+        node_b
+            .behaviour_mut()
+            .publish(Message::new_with_sequence_number(
+                topic.clone(),
+                b"test-payload".to_vec(),
+                seqno.to_be_bytes(),
+            ))
+            .expect("publish the message");
+
+        let (events, _) = testlib::swarm::poll_mesh_and_collect_events(
+            Duration::from_millis(10),
+            &mut node_a,
+            &mut node_b,
+        )
+        .await;
+        node_a_events.extend(events);
+    }
+
+    let stats = node_a
+        .behaviour()
+        .peer_framing_stats(node_b.local_peer_id())
+        .expect("Node B framing stats should be tracked");
+
+    node_a
+        .disconnect_peer_id(*node_b.local_peer_id())
+        .expect("disconnect Node B");
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert_eq!(stats.frames, 3);
+    assert_eq!(stats.messages, 3);
+    assert_eq!(stats.avg_msgs_per_frame, 1.0);
+
+    let notifications = node_a_events
+        .iter()
+        .filter_map(|ev| match ev {
+            SwarmEvent::Behaviour(Event::InefficientPeerFraming {
+                peer,
+                avg_msgs_per_frame,
+                ..
+            }) => Some((*peer, *avg_msgs_per_frame)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        notifications,
+        [(*node_b.local_peer_id(), 1.0)],
+        "Node B should be notified once"
+    );
+
+    assert!(
+        node_a
+            .behaviour()
+            .peer_framing_stats(node_b.local_peer_id())
+            .is_none(),
+        "The disconnected peer framing stats should be dropped"
+    );
+}
@@ -0,0 +1,351 @@
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::{SwarmBuilder, SwarmEvent};
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+use tracing_futures::Instrument;
+
+use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+use libp2p_pubsub_core::protocol::{
+    Protocol, ProtocolRouterInEvent, ProtocolRouterMessageEvent, ProtocolRouterOutEvent,
+    ProtocolRouterSubscriptionEvent,
+};
+use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, ControlMessage, Event,
+    IHaveControlMessage, IWantControlMessage, IdentTopic, Message, MessageId, TopicHash,
+};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+/// A lazy-push protocol: the published messages are announced to the subscribed peers with an
+/// `IHave`, and the announced messages are requested with an `IWant`.
+struct LazyPushProtocol {
+    /// The number of times the announced messages are requested.
+    iwant_repeats: usize,
+}
+
+impl Default for LazyPushProtocol {
+    fn default() -> Self {
+        Self { iwant_repeats: 1 }
+    }
+}
+
+impl Protocol for LazyPushProtocol {
+    type Upgrade = SimpleProtocolUpgrade<&'static str>;
+    type RouterService = LazyPushProtocolRouter;
+
+    fn upgrade() -> Self::Upgrade {
+        SimpleProtocolUpgrade::new("/lazy-push/1.0.0")
+    }
+
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
+        LazyPushProtocolRouter {
+            peers_subscriptions: Default::default(),
+            iwant_repeats: self.iwant_repeats,
+        }
+    }
+}
+
+/// The pubsub protocol router service for the lazy-push protocol.
+///
+/// The published messages are announced to the subscribed peers, and the announced messages are
+/// requested from the announcing peer, along with an unknown message id.
+struct LazyPushProtocolRouter {
+    peers_subscriptions: HashMap<PeerId, BTreeSet<TopicHash>>,
+    iwant_repeats: usize,
+}
+
+impl LazyPushProtocolRouter {
+    fn subscribed_peers(&self, topic: &TopicHash) -> Vec<PeerId> {
+        self.peers_subscriptions
+            .iter()
+            .filter(|(_, topics)| topics.contains(topic))
+            .map(|(peer, _)| *peer)
+            .collect()
+    }
+}
+
+impl EventHandler for LazyPushProtocolRouter {
+    type InEvent = ProtocolRouterInEvent;
+    type OutEvent = ProtocolRouterOutEvent;
+
+    fn on_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
+        ev: Self::InEvent,
+    ) {
+        match ev {
+            ProtocolRouterInEvent::SubscriptionEvent(
+                ProtocolRouterSubscriptionEvent::PeerSubscribed { peer, topic },
+            ) => {
+                self.peers_subscriptions
+                    .entry(peer)
+                    .or_default()
+                    .insert(topic);
+            }
+            ProtocolRouterInEvent::SubscriptionEvent(
+                ProtocolRouterSubscriptionEvent::PeerSubscribedMany { peer, topics },
+            ) => {
+                self.peers_subscriptions
+                    .entry(peer)
+                    .or_default()
+                    .extend(topics);
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessagePublished {
+                message,
+                message_id,
+                ..
+            }) => {
+                let topic = message.topic();
+                for dest in self.subscribed_peers(&topic) {
+                    svc_cx.emit(ProtocolRouterOutEvent::SendControlMessage {
+                        dest,
+                        message: ControlMessage::IHave(IHaveControlMessage {
+                            topic_hash: topic.clone(),
+                            message_ids: vec![message_id.clone()],
+                        }),
+                    });
+                }
+            }
+            ProtocolRouterInEvent::ControlEvent(ctrl_ev) => {
+                if let ControlMessage::IHave(ihave) = ctrl_ev.message {
+                    let mut message_ids = Vec::new();
+                    for _ in 0..self.iwant_repeats {
+                        message_ids.extend(ihave.message_ids.iter().cloned());
+                    }
+                    message_ids.push(MessageId::new(b"unknown-message-id".to_vec()));
+
+                    svc_cx.emit(ProtocolRouterOutEvent::SendControlMessage {
+                        dest: ctrl_ev.src,
+                        message: ControlMessage::IWant(IWantControlMessage { message_ids }),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+type Behaviour = PubsubBehaviour<LazyPushProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+fn new_test_node(
+    keypair: &Keypair,
+    config: Config,
+    protocol: LazyPushProtocol,
+) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair);
+    let behaviour = Behaviour::new(peer_id, config, protocol);
+    SwarmBuilder::with_executor(
+        transport,
+        behaviour,
+        peer_id,
+        |fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+            tokio::spawn(fut.in_current_span());
+        },
+    )
+    .build()
+}
+
+/// Poll the nodes for a given period of time, and collect the second node behaviour events.
+async fn poll_mesh_and_collect_events(
+    duration: Duration,
+    node: &mut Swarm<Behaviour>,
+    other: &mut Swarm<Behaviour>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            _ = node.select_next_some() => {},
+            event = other.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    events.push(event);
+                }
+            },
+        }
+    }
+
+    events
+}
+
+/// Create two connected nodes subscribed to the topic, Node A serving the IWANT requests with the
+/// given quotas, and Node B requesting the announced messages the given number of times.
+async fn new_connected_nodes(
+    topic: &IdentTopic,
+    max_served_per_heartbeat: usize,
+    max_serves_per_message: usize,
+    iwant_repeats: usize,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let node_a_config = ConfigBuilder::new()
+        .heartbeat_interval(Duration::from_secs(10))
+        .message_cache_ttl(Duration::from_secs(30))
+        .max_iwant_served_per_peer_per_heartbeat(max_served_per_heartbeat)
+        .max_iwant_serves_per_message(max_serves_per_message)
+        .build();
+    let mut node_a = new_test_node(&node_a_key, node_a_config, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(
+        &node_b_key,
+        Default::default(),
+        LazyPushProtocol { iwant_repeats },
+    );
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    // Let the nodes exchange their subscriptions.
+    poll_mesh_and_collect_events(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    (node_a, node_b)
+}
+
+/// Publish the given number of messages on Node A, and collect the messages received by Node B.
+async fn publish_and_collect_received(
+    count: u8,
+    topic: &IdentTopic,
+    node_a: &mut Swarm<Behaviour>,
+    node_b: &mut Swarm<Behaviour>,
+) -> Vec<Message> {
+    for seq in 0..count {
+        node_a
+            .behaviour_mut()
+            .publish(Message::new_with_sequence_number(
+                topic.clone(),
+                b"test-payload".to_vec(),
+                [seq],
+            ))
+            .expect("publish the message");
+    }
+
+    let node_b_events =
+        poll_mesh_and_collect_events(Duration::from_millis(200), node_a, node_b).await;
+
+    node_b_events
+        .into_iter()
+        .filter_map(|ev| match ev {
+            Event::MessageReceived { message, .. } => Some(message),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn iwant_requested_messages_are_served_from_the_cache() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, 16, 3, 1).await;
+
+    //// When
+    let received = publish_and_collect_received(3, &topic, &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert_eq!(
+        received.len(),
+        3,
+        "Node B should receive the requested messages"
+    );
+    assert_eq!(
+        node_a.behaviour().drop_stats().iwant_unserved,
+        0,
+        "The unknown message ids should be ignored"
+    );
+}
+
+#[tokio::test]
+async fn iwant_requests_exceeding_the_peer_quota_are_not_served() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, 2, 3, 1).await;
+
+    //// When
+    let received = publish_and_collect_received(4, &topic, &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert_eq!(received.len(), 2, "Only the quota should be served");
+    assert_eq!(node_a.behaviour().drop_stats().iwant_unserved, 2);
+}
+
+#[tokio::test]
+async fn iwant_repeated_requests_are_served_at_most_max_times() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, 16, 3, 5).await;
+
+    //// When
+    let received = publish_and_collect_received(1, &topic, &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert!(!received.is_empty(), "Node B should receive the message");
+    assert_eq!(
+        node_a.behaviour().drop_stats().iwant_unserved,
+        2,
+        "The requests exceeding the repeat cap should not be served"
+    );
+}
+
+#[tokio::test]
+async fn iwant_requests_are_not_served_by_default() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, 0, 3, 1).await;
+
+    //// When
+    let received = publish_and_collect_received(1, &topic, &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert!(
+        received.is_empty(),
+        "The IWANT requests should not be served"
+    );
+    assert_eq!(node_a.behaviour().drop_stats().iwant_unserved, 0);
+}
@@ -0,0 +1,157 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::SwarmBuilder;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+use tracing_futures::Instrument;
+
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, IdentTopic};
+use pubsub_testlib::NoopProtocol;
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+pub type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair);
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
+    SwarmBuilder::with_executor(
+        transport,
+        behaviour,
+        peer_id,
+        |fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+            tokio::spawn(fut.in_current_span());
+        },
+    )
+    .build()
+}
+
+#[tokio::test]
+async fn known_peer_is_dialed_and_subscriptions_propagate() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (_node_a_addr, node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    node_a
+        .behaviour_mut()
+        .subscribe(pubsub_topic.clone())
+        .expect("subscribe to topic");
+    node_b
+        .behaviour_mut()
+        .subscribe(pubsub_topic.clone())
+        .expect("subscribe to topic");
+
+    //// When
+    // Node A learns Node B address. No explicit dial.
+    let node_b_peer_id = *node_b.local_peer_id();
+    node_a
+        .behaviour_mut()
+        .add_known_peer(node_b_peer_id, vec![node_b_addr.clone()]);
+
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("Node A to connect to Node B");
+
+    // Poll the network for a short period of time to allow the subscriptions to be exchanged.
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    //// Then
+    let topic = pubsub_topic.hash();
+    let node_a_peer_id = node_a.local_peer_id();
+
+    assert_eq!(
+        node_a.behaviour().known_peers().collect::<Vec<_>>(),
+        vec![(&node_b_peer_id, [node_b_addr].as_slice())]
+    );
+    assert!(
+        node_a.is_connected(&node_b_peer_id),
+        "Node A should be connected to Node B"
+    );
+
+    assert!(
+        node_a
+            .behaviour()
+            .peer_subscriptions(&node_b_peer_id)
+            .is_some_and(|subs| subs.contains(&topic)),
+        "Node A should know Node B subscription"
+    );
+    assert!(
+        node_b
+            .behaviour()
+            .peer_subscriptions(node_a_peer_id)
+            .is_some_and(|subs| subs.contains(&topic)),
+        "Node B should know Node A subscription"
+    );
+}
+
+#[tokio::test]
+async fn known_peer_is_not_dialed_without_local_subscriptions() {
+    testlib::init_logger();
+
+    //// Given
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (_node_a_addr, node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    //// When
+    let node_b_peer_id = *node_b.local_peer_id();
+    node_a
+        .behaviour_mut()
+        .add_known_peer(node_b_peer_id, vec![node_b_addr]);
+
+    // Poll the network for a short period of time to allow a (wrong) dial to happen.
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert!(
+        !node_a.is_connected(&node_b_peer_id),
+        "Node A should not dial Node B without local subscriptions"
+    );
+    assert_eq!(node_a.behaviour().known_peers().count(), 1);
+}
@@ -0,0 +1,295 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use futures::StreamExt;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Event, IdentTopic, Message, PublishError};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the first node behaviour events.
+async fn poll_mesh3_and_collect_events(
+    duration: Duration,
+    node: &mut Swarm<Behaviour>,
+    other_1: &mut Swarm<Behaviour>,
+    other_2: &mut Swarm<Behaviour>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            event = node.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    events.push(event);
+                }
+            },
+            _ = other_1.select_next_some() => {},
+            _ = other_2.select_next_some() => {},
+        }
+    }
+
+    events
+}
+
+/// Create three nodes, all subscribed to the topic, connected in a line: B -> A <- C.
+async fn new_connected_nodes(
+    topic: &IdentTopic,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let node_c_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let mut node_c = new_test_node(&node_c_key, Default::default());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b, &mut node_c] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr.clone()).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    node_c.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_c, &mut node_a),
+    )
+    .await
+    .expect("Node C to connect to Node A");
+
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    (node_a, node_b, node_c)
+}
+
+/// Get the payloads of the messages received among the given events.
+fn received_payloads(events: &[Event]) -> Vec<&[u8]> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            Event::MessageReceived { message, .. } => Some(message.data.as_slice()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Get the listen-only mode changes among the given events.
+fn listen_only_changes(events: &[Event]) -> Vec<bool> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            Event::ListenOnlyChanged(enabled) => Some(*enabled),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn listen_only_node_receives_but_neither_forwards_nor_publishes() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b, mut node_c) = new_connected_nodes(&topic).await;
+
+    node_a.behaviour_mut().set_listen_only(true);
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish the message");
+    let publish_result = node_a
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"listen-only-payload".to_vec()));
+
+    let node_a_events = poll_mesh3_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// Then
+    assert_matches!(publish_result, Err(PublishError::ListenOnly));
+    assert!(node_a.behaviour().is_listen_only());
+    assert_eq!(
+        received_payloads(&node_a_events),
+        [b"test-payload".as_slice()],
+        "The listen-only node should still receive the messages"
+    );
+    assert_eq!(
+        node_c.behaviour().traffic_totals().received_messages,
+        0,
+        "No message should be forwarded in listen-only mode"
+    );
+    assert_eq!(
+        node_a.behaviour().drop_stats().listen_only_forwards,
+        1,
+        "The forward to Node C should be dropped and counted"
+    );
+    assert!(
+        node_b
+            .behaviour()
+            .peer_subscriptions(node_a.local_peer_id())
+            .map_or(false, |topics| topics.contains(&topic.hash())),
+        "The subscription should still be announced in listen-only mode"
+    );
+}
+
+#[tokio::test]
+async fn disabling_listen_only_resumes_without_sending_the_listen_only_messages() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b, mut node_c) = new_connected_nodes(&topic).await;
+
+    // Node A receives a message, and fails to publish one, while in listen-only mode.
+    node_a.behaviour_mut().set_listen_only(true);
+    node_b
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            topic.clone(),
+            b"listen-only-payload".to_vec(),
+            [1],
+        ))
+        .expect("publish the message");
+    let _ = node_a
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"unpublished-payload".to_vec()));
+    poll_mesh3_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// When
+    node_a.behaviour_mut().set_listen_only(false);
+    let node_c_received_after_disabling = poll_mesh3_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_c,
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    node_b
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            topic.clone(),
+            b"forwarded-payload".to_vec(),
+            [2],
+        ))
+        .expect("publish the message");
+    node_a
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            topic.clone(),
+            b"published-payload".to_vec(),
+            [3],
+        ))
+        .expect("publish the message");
+    let node_c_received_once_resumed = poll_mesh3_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_c,
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    assert!(
+        received_payloads(&node_c_received_after_disabling).is_empty(),
+        "No message received, or published, in listen-only mode should be sent once disabled"
+    );
+    let mut resumed_payloads = received_payloads(&node_c_received_once_resumed);
+    resumed_payloads.sort();
+    assert_eq!(
+        resumed_payloads,
+        [
+            b"forwarded-payload".as_slice(),
+            b"published-payload".as_slice()
+        ],
+        "The forwarding and publishing should resume without re-subscribing"
+    );
+}
+
+#[tokio::test]
+async fn listen_only_changes_are_notified() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b, mut node_c) = new_connected_nodes(&topic).await;
+
+    //// When
+    node_a.behaviour_mut().set_listen_only(true);
+    node_a.behaviour_mut().set_listen_only(true);
+    node_a.behaviour_mut().set_listen_only(false);
+    node_a.behaviour_mut().set_listen_only(false);
+
+    let node_a_events = poll_mesh3_and_collect_events(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        listen_only_changes(&node_a_events),
+        [true, false],
+        "Only the mode changes should be notified"
+    );
+    assert!(!node_a.behaviour().is_listen_only());
+}
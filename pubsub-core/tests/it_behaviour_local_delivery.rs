@@ -0,0 +1,272 @@
+use std::any::Any;
+use std::sync::Arc;
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use futures::StreamExt;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, FrameMessage, IdentTopic, Message,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect both nodes behaviour events.
+async fn poll_mesh_and_collect_events(
+    duration: Duration,
+    node_a: &mut Swarm<Behaviour>,
+    node_b: &mut Swarm<Behaviour>,
+) -> (Vec<Event>, Vec<Event>) {
+    let mut node_a_events = Vec::new();
+    let mut node_b_events = Vec::new();
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            event = node_a.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    node_a_events.push(event);
+                }
+            },
+            event = node_b.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    node_b_events.push(event);
+                }
+            },
+        }
+    }
+
+    (node_a_events, node_b_events)
+}
+
+/// Create two connected nodes subscribed to the topic, Node A with the given configuration.
+async fn new_connected_nodes(
+    topic: &IdentTopic,
+    node_a_config: Config,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    (node_a, node_b)
+}
+
+/// The application data derived from a published message payload.
+#[derive(Debug, PartialEq, Eq)]
+struct ParsedPayload {
+    greeting: String,
+}
+
+/// Get the received messages among the given events, as `(payload, is_local)` pairs.
+fn received_messages(events: &[Event]) -> Vec<(&[u8], bool)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            Event::MessageReceived {
+                message, is_local, ..
+            } => Some((message.data.as_slice(), *is_local)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Get the local metadata of the received messages among the given events.
+fn received_metadata(events: &[Event]) -> Vec<Option<&Arc<dyn Any + Send + Sync>>> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            Event::MessageReceived { local_metadata, .. } => Some(local_metadata.as_ref()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn published_message_is_delivered_locally_with_its_metadata() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let node_a_config = ConfigBuilder::new().deliver_own_messages(true).build();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, node_a_config).await;
+
+    //// When
+    let metadata = Arc::new(ParsedPayload {
+        greeting: "hello".to_string(),
+    });
+    node_a
+        .behaviour_mut()
+        .publish_with_metadata(
+            Message::new_with_sequence_number(topic.clone(), b"hello", 1u64.to_be_bytes()),
+            metadata,
+        )
+        .expect("publish the message");
+    node_a
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            topic.clone(),
+            b"world",
+            2u64.to_be_bytes(),
+        ))
+        .expect("publish the message");
+    let (node_a_events, node_b_events) =
+        poll_mesh_and_collect_events(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    let node_a_id = *node_a.local_peer_id();
+    assert_eq!(
+        received_messages(&node_a_events),
+        [(b"hello".as_slice(), true), (b"world".as_slice(), true)],
+        "Both messages should be delivered locally"
+    );
+    let metadata = received_metadata(&node_a_events);
+    assert_matches!(metadata[..], [Some(metadata), None] => {
+        assert_eq!(
+            metadata.downcast_ref::<ParsedPayload>(),
+            Some(&ParsedPayload { greeting: "hello".to_string() })
+        );
+    });
+    assert!(node_a_events
+        .iter()
+        .any(|ev| matches!(ev, Event::MessageReceived { src, .. } if src == &node_a_id)));
+
+    assert_eq!(
+        received_messages(&node_b_events),
+        [(b"hello".as_slice(), false), (b"world".as_slice(), false)]
+    );
+    assert!(
+        received_metadata(&node_b_events)
+            .iter()
+            .all(Option::is_none),
+        "The metadata should never be sent on the wire"
+    );
+}
+
+#[tokio::test]
+async fn echoed_message_is_not_delivered_again() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let node_a_config = ConfigBuilder::new()
+        .deliver_own_messages(true)
+        .detect_echo(true)
+        .build();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, node_a_config).await;
+
+    node_a
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            topic.clone(),
+            b"echo",
+            1u64.to_be_bytes(),
+        ))
+        .expect("publish the message");
+    let (node_a_events_before, node_b_events) =
+        poll_mesh_and_collect_events(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// When
+    // Node B re-propagates the received message back to Node A.
+    let message = node_b_events
+        .into_iter()
+        .find_map(|ev| match ev {
+            Event::MessageReceived { message, .. } => Some(message),
+            _ => None,
+        })
+        .expect("Node B to receive the message");
+    node_b
+        .behaviour_mut()
+        .forward_message(FrameMessage::from(message), &[])
+        .expect("forward the message");
+    let (node_a_events_after, _) =
+        poll_mesh_and_collect_events(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert_eq!(
+        received_messages(&node_a_events_before),
+        [(b"echo".as_slice(), true)]
+    );
+    assert!(
+        node_a_events_after
+            .iter()
+            .any(|ev| matches!(ev, Event::PublishedMessageEchoed { .. })),
+        "The message should be echoed back to Node A"
+    );
+    assert!(
+        received_messages(&node_a_events_after).is_empty(),
+        "The echoed message should not be delivered again"
+    );
+}
+
+#[tokio::test]
+async fn published_messages_are_not_delivered_locally_by_default() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, Default::default()).await;
+
+    //// When
+    node_a
+        .behaviour_mut()
+        .publish_with_metadata(
+            Message::new_with_sequence_number(topic.clone(), b"hello", 1u64.to_be_bytes()),
+            Arc::new(()),
+        )
+        .expect("publish the message");
+    let (node_a_events, node_b_events) =
+        poll_mesh_and_collect_events(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert!(received_messages(&node_a_events).is_empty());
+    assert_eq!(
+        received_messages(&node_b_events),
+        [(b"hello".as_slice(), false)]
+    );
+}
@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    default_message_id_fn, Behaviour as PubsubBehaviour, Event, FrameMessage, IdentTopic, Identity,
+    Message, MessageId, MessageIdFnError, MessageRef, PublishError, Subscription,
+    SubscriptionBuilder,
+};
+use pubsub_testlib::{new_test_swarm, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// A message id function panicking on every message.
+fn panicking_message_id_fn(_src: Option<&PeerId>, _msg: &MessageRef) -> MessageId {
+    panic!("message id field not found");
+}
+
+/// A message id function returning an empty message id for every message.
+fn empty_message_id_fn(_src: Option<&PeerId>, _msg: &MessageRef) -> MessageId {
+    MessageId::new(Vec::new())
+}
+
+fn new_test_subscription(
+    topic: &IdentTopic,
+    id_fn: fn(Option<&PeerId>, &MessageRef) -> MessageId,
+) -> Subscription {
+    let mut builder = SubscriptionBuilder::new(topic.clone());
+    builder.message_id_fn(id_fn);
+    builder.build()
+}
+
+fn new_test_node(keypair: &Keypair) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    // The messages are authored, so the default message ids are not empty.
+    let behaviour = Behaviour::new_with_identity(
+        Default::default(),
+        Default::default(),
+        Identity::PeerId(peer_id),
+    );
+    new_test_swarm(keypair, behaviour)
+}
+
+/// Create two connected nodes, subscribed to the given subscriptions: Node B dials Node A.
+async fn new_connected_nodes(
+    node_a_sub: Subscription,
+    node_b_sub: Subscription,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key);
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    node_a
+        .behaviour_mut()
+        .subscribe(node_a_sub)
+        .expect("subscribe to topic");
+    node_b
+        .behaviour_mut()
+        .subscribe(node_b_sub)
+        .expect("subscribe to topic");
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    (node_a, node_b)
+}
+
+/// Publish a message from Node B, with a failing message id function, and return the publish
+/// result.
+async fn publish_with_message_id_fn(
+    id_fn: fn(Option<&PeerId>, &MessageRef) -> MessageId,
+) -> Result<(), PublishError> {
+    let topic = new_test_topic();
+    let (_node_a, mut node_b) =
+        new_connected_nodes(topic.clone().into(), new_test_subscription(&topic, id_fn)).await;
+
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic, b"test-payload".to_vec()))
+}
+
+/// Publish a message from Node B, received by Node A with a failing message id function, and
+/// return the message ids of the received messages, along with the number of Node A message id
+/// fallbacks.
+async fn receive_with_message_id_fn(
+    id_fn: fn(Option<&PeerId>, &MessageRef) -> MessageId,
+) -> (Vec<(Message, MessageId)>, u64) {
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) =
+        new_connected_nodes(new_test_subscription(&topic, id_fn), topic.clone().into()).await;
+
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic, b"test-payload".to_vec()))
+        .expect("publish the message");
+
+    let (node_a_events, _) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    let received = node_a_events
+        .into_iter()
+        .filter_map(|event| match event {
+            SwarmEvent::Behaviour(Event::MessageReceived {
+                message,
+                message_id,
+                ..
+            }) => Some((message, message_id)),
+            _ => None,
+        })
+        .collect();
+    (received, node_a.behaviour().message_id_fallbacks())
+}
+
+#[tokio::test]
+async fn publishing_with_panicking_message_id_fn_fails() {
+    testlib::init_logger();
+
+    //// When
+    let result = publish_with_message_id_fn(panicking_message_id_fn).await;
+
+    //// Then
+    assert_eq!(
+        result,
+        Err(PublishError::MessageIdComputation(
+            MessageIdFnError::Panicked
+        ))
+    );
+}
+
+#[tokio::test]
+async fn publishing_with_empty_message_id_fn_fails() {
+    testlib::init_logger();
+
+    //// When
+    let result = publish_with_message_id_fn(empty_message_id_fn).await;
+
+    //// Then
+    assert_eq!(
+        result,
+        Err(PublishError::MessageIdComputation(MessageIdFnError::Empty))
+    );
+}
+
+#[tokio::test]
+async fn received_message_with_panicking_message_id_fn_uses_default_message_id_fn() {
+    testlib::init_logger();
+
+    //// When
+    let (received, fallbacks) = receive_with_message_id_fn(panicking_message_id_fn).await;
+
+    //// Then
+    assert_eq!(received.len(), 1, "The message should not be dropped");
+    let (message, message_id) = &received[0];
+    assert!(!message_id.is_empty());
+    assert_eq!(
+        message_id,
+        &default_message_id_fn(None, &(&FrameMessage::from(message.clone())).into()),
+        "The message id should be computed with the default message id function"
+    );
+    assert_eq!(fallbacks, 1);
+}
+
+#[tokio::test]
+async fn received_message_with_empty_message_id_fn_uses_default_message_id_fn() {
+    testlib::init_logger();
+
+    //// When
+    let (received, fallbacks) = receive_with_message_id_fn(empty_message_id_fn).await;
+
+    //// Then
+    assert_eq!(received.len(), 1, "The message should not be dropped");
+    let (message, message_id) = &received[0];
+    assert!(!message_id.is_empty());
+    assert_eq!(
+        message_id,
+        &default_message_id_fn(None, &(&FrameMessage::from(message.clone())).into()),
+        "The message id should be computed with the default message id function"
+    );
+    assert_eq!(fallbacks, 1);
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, IdentTopic, Message,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the messages received by the first node.
+async fn poll_mesh3_and_collect_messages(
+    duration: Duration,
+    node: &mut Swarm<Behaviour>,
+    other_1: &mut Swarm<Behaviour>,
+    other_2: &mut Swarm<Behaviour>,
+) -> Vec<Message> {
+    let mut messages = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => break,
+            event = node.select_next_some() => {
+                if let SwarmEvent::Behaviour(Event::MessageReceived { message, .. }) = event {
+                    messages.push(message);
+                }
+            },
+            _ = other_1.select_next_some() => {},
+            _ = other_2.select_next_some() => {},
+        }
+    }
+
+    messages
+}
+
+/// Create three nodes, all subscribed to the topic, connected in a line: B -> A <- C.
+async fn new_connected_nodes(
+    topic: &IdentTopic,
+    node_a_config: Config,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let node_c_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let mut node_c = new_test_node(&node_c_key, Default::default());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b, &mut node_c] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr.clone()).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    node_c.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_c, &mut node_a),
+    )
+    .await
+    .expect("Node C to connect to Node A");
+
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    (node_a, node_b, node_c)
+}
+
+#[tokio::test]
+async fn message_key_survives_the_forward_path_unmodified() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b, mut node_c) =
+        new_connected_nodes(&topic, Default::default()).await;
+
+    let key = (0..=255).cycle().take(300).collect::<Vec<u8>>();
+    let mut message = Message::new(topic.clone(), b"test-payload".to_vec());
+    message.set_key(key.clone());
+
+    //// When
+    // Node B publishes the message, and Node A forwards it to Node C.
+    node_b
+        .behaviour_mut()
+        .publish(message)
+        .expect("publish the message");
+
+    let node_c_messages = poll_mesh3_and_collect_messages(
+        Duration::from_millis(100),
+        &mut node_c,
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        node_a.behaviour().traffic_totals().forwarded_messages,
+        1,
+        "Node A should forward the message"
+    );
+    assert_eq!(
+        node_c_messages.len(),
+        1,
+        "Node C should receive the message"
+    );
+    assert_eq!(
+        node_c_messages[0].key.as_deref(),
+        Some(key.as_slice()),
+        "The message key should be forwarded unmodified"
+    );
+}
+
+#[tokio::test]
+async fn message_with_a_key_larger_than_the_max_key_size_is_dropped() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let node_a_config = ConfigBuilder::new().max_key_size(64).build();
+    let (mut node_a, mut node_b, mut node_c) = new_connected_nodes(&topic, node_a_config).await;
+
+    let mut message = Message::new(topic.clone(), b"test-payload".to_vec());
+    message.set_key(vec![0xab; 65]);
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .publish(message)
+        .expect("publish the message");
+
+    let node_a_messages = poll_mesh3_and_collect_messages(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// Then
+    assert!(node_a_messages.is_empty(), "Node A should drop the message");
+    assert_eq!(
+        node_a.behaviour().traffic_totals().forwarded_messages,
+        0,
+        "Node A should not forward the message"
+    );
+    assert_eq!(node_c.behaviour().traffic_totals().received_messages, 0);
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use libp2p::swarm::SwarmEvent;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{Event, Message};
+use pubsub_testlib::{new_broadcast_test_node, new_test_topic};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+#[tokio::test]
+async fn message_lifecycle_latencies_are_recorded() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_broadcast_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_broadcast_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish the message");
+
+    let (node_a_events, _) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    assert!(
+        node_a_events
+            .iter()
+            .any(|ev| matches!(ev, SwarmEvent::Behaviour(Event::MessageReceived { .. }))),
+        "Node A should receive the message"
+    );
+
+    assert!(
+        node_a.behaviour().last_delivery_latency().is_some(),
+        "Node A should record the message delivery latency"
+    );
+    assert!(
+        node_a.behaviour().last_forward_latency().is_none(),
+        "Node A did not forward any message"
+    );
+
+    assert!(
+        node_b.behaviour().last_forward_latency().is_some(),
+        "Node B should record the published message forward latency"
+    );
+    assert!(
+        node_b.behaviour().last_delivery_latency().is_none(),
+        "Node B did not receive any message"
+    );
+}
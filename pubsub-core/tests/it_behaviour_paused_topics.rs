@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use futures::StreamExt;
+use libp2p::identity::PeerId;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, Event, IdentTopic, Message, PublishError,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the messages received by the first node.
+async fn poll_mesh3_and_collect_messages(
+    duration: Duration,
+    node: &mut Swarm<Behaviour>,
+    other_1: &mut Swarm<Behaviour>,
+    other_2: &mut Swarm<Behaviour>,
+) -> Vec<Message> {
+    let mut messages = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => break,
+            event = node.select_next_some() => {
+                if let SwarmEvent::Behaviour(Event::MessageReceived { message, .. }) = event {
+                    messages.push(message);
+                }
+            },
+            _ = other_1.select_next_some() => {},
+            _ = other_2.select_next_some() => {},
+        }
+    }
+
+    messages
+}
+
+/// Create three nodes, all subscribed to the topic, connected in a line: B -> A <- C.
+async fn new_connected_nodes(
+    topic: &IdentTopic,
+    node_a_config: Config,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let node_c_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let mut node_c = new_test_node(&node_c_key, Default::default());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b, &mut node_c] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr.clone()).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    node_c.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_c, &mut node_a),
+    )
+    .await
+    .expect("Node C to connect to Node A");
+
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    (node_a, node_b, node_c)
+}
+
+/// Create a message authored by the given peer, with the given sequence number.
+fn new_test_message(topic: &IdentTopic, author: PeerId, seqno: u64, payload: &[u8]) -> Message {
+    let mut message = Message::new_with_sequence_number(
+        topic.clone(),
+        payload.to_vec(),
+        seqno.to_be_bytes().to_vec(),
+    );
+    message.from = Some(author);
+    message
+}
+
+#[tokio::test]
+async fn paused_topic_messages_are_not_delivered_nor_forwarded_until_resumed() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b, mut node_c) =
+        new_connected_nodes(&topic, Default::default()).await;
+    let node_b_id = *node_b.local_peer_id();
+
+    let paused = node_a.behaviour_mut().pause_topic(&topic.hash());
+
+    //// When
+    // Node B publishes a message while Node A has the topic paused.
+    node_b
+        .behaviour_mut()
+        .publish(new_test_message(&topic, node_b_id, 1, b"paused-payload"))
+        .expect("publish the message");
+
+    let paused_messages = poll_mesh3_and_collect_messages(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+    let node_c_received_while_paused = node_c.behaviour().traffic_totals().received_messages;
+
+    let resumed = node_a.behaviour_mut().resume_topic(&topic.hash());
+
+    // Node C publishes a copy of the message received by Node A while paused, and Node B a new
+    // message.
+    node_c
+        .behaviour_mut()
+        .publish(new_test_message(&topic, node_b_id, 1, b"paused-payload"))
+        .expect("publish the message");
+    node_b
+        .behaviour_mut()
+        .publish(new_test_message(&topic, node_b_id, 2, b"resumed-payload"))
+        .expect("publish the message");
+
+    let resumed_messages = poll_mesh3_and_collect_messages(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// Then
+    assert!(paused);
+    assert!(resumed);
+    assert!(
+        paused_messages.is_empty(),
+        "No message should be delivered while paused"
+    );
+    assert_eq!(
+        node_c_received_while_paused, 0,
+        "No message should be forwarded while paused"
+    );
+    assert_eq!(
+        resumed_messages
+            .iter()
+            .map(|message| message.data.as_slice())
+            .collect::<Vec<_>>(),
+        [b"resumed-payload".as_slice()],
+        "Only the new message should be delivered, the message seen while paused is a duplicate"
+    );
+    assert!(node_a.behaviour().paused_topics().is_empty());
+}
+
+#[tokio::test]
+async fn publish_to_a_paused_topic_fails() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b, mut node_c) =
+        new_connected_nodes(&topic, Default::default()).await;
+
+    node_a.behaviour_mut().pause_topic(&topic.hash());
+
+    //// When
+    let result = node_a
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()));
+
+    poll_mesh3_and_collect_messages(
+        Duration::from_millis(50),
+        &mut node_b,
+        &mut node_a,
+        &mut node_c,
+    )
+    .await;
+
+    //// Then
+    assert_matches!(result, Err(PublishError::TopicPaused(paused)) => {
+        assert_eq!(paused, topic.hash());
+    });
+    assert_eq!(
+        node_a
+            .behaviour()
+            .paused_topics()
+            .iter()
+            .collect::<Vec<_>>(),
+        [&topic.hash()]
+    );
+    assert!(
+        node_b
+            .behaviour()
+            .peer_subscriptions(node_a.local_peer_id())
+            .map_or(false, |topics| topics.contains(&topic.hash())),
+        "The paused topic subscription should still be announced"
+    );
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, IdentTopic, Identity, Message,
+};
+use pubsub_testlib::{new_test_swarm, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let behaviour =
+        Behaviour::new_with_identity(config, Default::default(), Identity::PeerId(peer_id));
+    new_test_swarm(keypair, behaviour)
+}
+
+#[tokio::test]
+async fn frames_are_clamped_to_the_peer_suspected_frame_limit() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+
+    // Node A batches the published messages into frames larger than Node B maximum frame size.
+    let node_a_config = ConfigBuilder::new()
+        .publish_batch_window(Some(Duration::from_millis(20)))
+        .build();
+    let node_b_config = ConfigBuilder::new().max_frame_size(1024).build();
+
+    let mut node_a = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_A), node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_B), node_b_config);
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    node_a
+        .behaviour_mut()
+        .subscribe(pubsub_topic.clone())
+        .expect("subscribe to topic");
+    node_b
+        .behaviour_mut()
+        .subscribe(pubsub_topic.clone())
+        .expect("subscribe to topic");
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    let node_b_peer_id = *node_b.local_peer_id();
+    let initial_limit = node_a.behaviour().peer_frame_limit(&node_b_peer_id);
+
+    //// When
+    // Publish rounds of messages, until a round is fully delivered.
+    let mut delivered = None;
+    for round in 0..8_u8 {
+        let payloads = (0..20_u8)
+            .map(|i| vec![round * 20 + i; 100])
+            .collect::<Vec<_>>();
+        for payload in &payloads {
+            node_a
+                .behaviour_mut()
+                .publish(Message::new(pubsub_topic.clone(), payload.clone()))
+                .expect("publish message");
+        }
+
+        let (_, node_b_events) = testlib::swarm::poll_mesh_and_collect_events(
+            Duration::from_millis(200),
+            &mut node_a,
+            &mut node_b,
+        )
+        .await;
+
+        let received = node_b_events
+            .into_iter()
+            .filter_map(|event| match event {
+                SwarmEvent::Behaviour(Event::MessageReceived { message, .. }) => Some(message.data),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if received == payloads {
+            delivered = Some(round);
+            break;
+        }
+    }
+
+    //// Then
+    assert_eq!(
+        initial_limit, None,
+        "No limit should be suspected initially"
+    );
+    assert!(
+        delivered.map_or(false, |round| round > 0),
+        "The messages should eventually be delivered, after the first round failed"
+    );
+
+    let limit = node_a
+        .behaviour()
+        .peer_frame_limit(&node_b_peer_id)
+        .expect("Node B frame limit to be suspected");
+    assert!(
+        limit <= 1024,
+        "The suspected limit should be below Node B maximum frame size"
+    );
+}
@@ -0,0 +1,91 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+use futures::future::poll_fn;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::SwarmBuilder;
+use libp2p::Swarm;
+use rand::Rng;
+use tracing_futures::Instrument;
+
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, Event, IdentTopic};
+use pubsub_testlib::NoopProtocol;
+use testlib::keys::TEST_KEYPAIR_A;
+
+mod pubsub_testlib;
+
+pub type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair);
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
+    SwarmBuilder::with_executor(
+        transport,
+        behaviour,
+        peer_id,
+        |fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+            tokio::spawn(fut.in_current_span());
+        },
+    )
+    .build()
+}
+
+#[tokio::test]
+async fn queued_subscriptions_are_reported_until_processed() {
+    testlib::init_logger();
+
+    //// Given
+    let topic_a = new_test_topic();
+    let topic_b = new_test_topic();
+
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let mut node = new_test_node(&node_key, Default::default());
+
+    //// When
+    for topic in [&topic_a, &topic_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    let pending_before = node.behaviour().pending_event_counts();
+
+    let drained = poll_fn(|cx| Poll::Ready(node.behaviour_mut().drain_to_completion(cx, 8))).await;
+    let pending_drained = node.behaviour().pending_event_counts();
+
+    let mut subscribed = Vec::new();
+    for _ in 0..2 {
+        let event = testlib::swarm::wait_for_message(
+            &mut node,
+            |ev| matches!(ev, Event::Subscribed { .. }),
+            Duration::from_secs(1),
+        )
+        .await;
+        subscribed.extend(event);
+    }
+    let pending_after = node.behaviour().pending_event_counts();
+
+    //// Then
+    assert_eq!(pending_before.subscriptions, 2);
+    assert!(!pending_before.is_empty());
+
+    assert!(drained, "Node queued events should be drained");
+    assert_eq!(pending_drained.services(), 0);
+    assert_eq!(
+        pending_drained.behaviour_output_mailbox, 2,
+        "The subscribed events should be left for the swarm"
+    );
+
+    assert_eq!(subscribed.len(), 2, "No subscribed event should be lost");
+    assert!(pending_after.is_empty(), "No event should be queued");
+}
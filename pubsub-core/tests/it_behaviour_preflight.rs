@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, IdentTopic, PreflightCode, PreflightWarning, Sha256Topic,
+    Subscription, SubscriptionBuilder,
+};
+use pubsub_testlib::{poll_all, NoopProtocol};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+/// Create a test behaviour, with the given configuration, subscribed to the given subscriptions.
+fn new_test_behaviour<S>(config: Config, subscriptions: impl IntoIterator<Item = S>) -> Behaviour
+where
+    S: Into<Subscription>,
+{
+    let mut behaviour = Behaviour::new(PeerId::random(), config, Default::default());
+    for sub in subscriptions {
+        behaviour.subscribe(sub).expect("subscription to succeed");
+    }
+    poll_all(&mut behaviour);
+
+    behaviour
+}
+
+/// Collect the preflight warnings codes.
+fn codes(warnings: &[PreflightWarning]) -> Vec<PreflightCode> {
+    warnings.iter().map(|warning| warning.code).collect()
+}
+
+/// Create `count` distinct test topics.
+fn new_test_topics(count: usize) -> Vec<IdentTopic> {
+    (0..count)
+        .map(|idx| IdentTopic::new(format!("/pubsub/2/it-preflight-test-topic-{idx}")))
+        .collect()
+}
+
+#[test]
+fn sane_configuration_has_no_warning() {
+    //// Given
+    let behaviour = new_test_behaviour(Default::default(), new_test_topics(10));
+
+    //// When
+    let warnings = behaviour.preflight();
+
+    //// Then
+    assert!(warnings.is_empty(), "Unexpected warnings: {warnings:?}");
+}
+
+#[test]
+fn no_subscription_is_flagged() {
+    //// Given
+    let behaviour = new_test_behaviour::<IdentTopic>(Default::default(), []);
+
+    //// When
+    let warnings = behaviour.preflight();
+
+    //// Then
+    assert_eq!(codes(&warnings), [PreflightCode::NoSubscriptions]);
+}
+
+#[test]
+fn announcement_larger_than_the_max_frame_size_is_flagged() {
+    //// Given
+    let config = Config::builder().max_frame_size(4096).build();
+    let behaviour = new_test_behaviour(config, new_test_topics(1000));
+
+    //// When
+    let warnings = behaviour.preflight();
+
+    //// Then
+    assert_eq!(
+        codes(&warnings),
+        [PreflightCode::AnnouncementExceedsMaxFrameSize]
+    );
+    assert!(warnings[0]
+        .values
+        .contains(&("max_frame_size", "4096".to_string())));
+    assert!(warnings[0]
+        .values
+        .contains(&("subscriptions", "1000".to_string())));
+}
+
+#[test]
+fn batched_announcement_fitting_the_max_frame_size_is_not_flagged() {
+    //// Given
+    let config = Config::builder()
+        .max_frame_size(4096)
+        .subscription_announce_batch(Some(50))
+        .build();
+    let behaviour = new_test_behaviour(config, new_test_topics(1000));
+
+    //// When
+    let warnings = behaviour.preflight();
+
+    //// Then
+    assert!(warnings.is_empty(), "Unexpected warnings: {warnings:?}");
+}
+
+#[test]
+fn heartbeat_longer_than_the_cache_ttl_is_flagged() {
+    //// Given
+    let config = Config::builder()
+        .heartbeat_interval(Duration::from_secs(10))
+        .message_cache_ttl(Duration::from_secs(5))
+        .build();
+    let behaviour = new_test_behaviour(config, new_test_topics(1));
+
+    //// When
+    let warnings = behaviour.preflight();
+
+    //// Then
+    assert_eq!(codes(&warnings), [PreflightCode::HeartbeatExceedsCacheTtl]);
+}
+
+#[test]
+fn backfill_max_age_longer_than_the_cache_ttl_is_flagged_only_if_enabled() {
+    //// Given
+    let config = Config::builder()
+        .message_cache_ttl(Duration::from_secs(30))
+        .backfill_max_age(Duration::from_secs(60))
+        .build();
+    let [plain_topic, backfill_topic]: [IdentTopic; 2] = new_test_topics(2).try_into().unwrap();
+
+    let plain_behaviour = new_test_behaviour(config.clone(), [plain_topic]);
+
+    let mut sub = SubscriptionBuilder::new(backfill_topic);
+    sub.backfill_on_peer_subscribe(true);
+    let backfill_behaviour = new_test_behaviour(config, [sub.build()]);
+
+    //// When
+    let plain_warnings = plain_behaviour.preflight();
+    let backfill_warnings = backfill_behaviour.preflight();
+
+    //// Then
+    assert!(
+        plain_warnings.is_empty(),
+        "Unexpected warnings: {plain_warnings:?}"
+    );
+    assert_eq!(
+        codes(&backfill_warnings),
+        [PreflightCode::BackfillMaxAgeExceedsCacheTtl]
+    );
+}
+
+#[test]
+fn same_topic_string_hashed_differently_is_flagged() {
+    //// Given
+    let mut behaviour = new_test_behaviour::<IdentTopic>(Default::default(), []);
+    behaviour.subscribe(IdentTopic::new("/test/mixed")).unwrap();
+    behaviour
+        .subscribe(Sha256Topic::new("/test/mixed"))
+        .unwrap();
+    poll_all(&mut behaviour);
+
+    //// When
+    let warnings = behaviour.preflight();
+
+    //// Then
+    assert_eq!(codes(&warnings), [PreflightCode::MixedTopicHashing]);
+    assert_eq!(
+        warnings[0].values,
+        [
+            ("identity_topic", "/test/mixed".to_string()),
+            (
+                "sha256_topic",
+                Sha256Topic::new("/test/mixed").hash().to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn topic_hash_accepted_by_several_subscriptions_is_flagged() {
+    //// Given
+    let [topic_a, topic_b]: [IdentTopic; 2] = new_test_topics(2).try_into().unwrap();
+
+    // Subscription B accepts the subscription A topic as an alias.
+    let mut sub_b = SubscriptionBuilder::new(topic_b.clone());
+    sub_b.also_accept_alias(topic_a.hash());
+
+    let behaviour = new_test_behaviour(Default::default(), [topic_a.clone().into(), sub_b.build()]);
+
+    //// When
+    let warnings = behaviour.preflight();
+
+    //// Then
+    assert_eq!(codes(&warnings), [PreflightCode::DuplicateTopicHash]);
+    assert_eq!(
+        warnings[0].values,
+        [
+            ("topic_hash", topic_a.hash().to_string()),
+            ("subscriptions", format!("{}, {}", topic_a, topic_b)),
+        ]
+    );
+}
@@ -0,0 +1,250 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Event, IdentTopic, Identity, Message, Provenance,
+};
+use pubsub_testlib::{new_test_swarm, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Create a test node, authoring the published messages if not anonymous.
+fn new_test_node(keypair: &Keypair, anonymous: bool) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let identity = if anonymous {
+        Identity::Anonymous(peer_id)
+    } else {
+        Identity::PeerId(peer_id)
+    };
+    let behaviour = Behaviour::new_with_identity(Default::default(), Default::default(), identity);
+    new_test_swarm(keypair, behaviour)
+}
+
+/// Poll the nodes for a given period of time, and collect the first node behaviour events.
+async fn poll_mesh3_and_collect_events(
+    duration: Duration,
+    node: &mut Swarm<Behaviour>,
+    other_1: &mut Swarm<Behaviour>,
+    other_2: &mut Swarm<Behaviour>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => break,
+            event = node.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    events.push(event);
+                }
+            },
+            _ = other_1.select_next_some() => {},
+            _ = other_2.select_next_some() => {},
+        }
+    }
+
+    events
+}
+
+/// Connect the three nodes in a line, i.e., B -> A <- C, and subscribe them to the topic.
+async fn connect_line3(
+    topic: &IdentTopic,
+    node_a: &mut Swarm<Behaviour>,
+    node_b: &mut Swarm<Behaviour>,
+    node_c: &mut Swarm<Behaviour>,
+) {
+    testlib::swarm::should_listen_on_address(node_a, any_memory_addr());
+    testlib::swarm::should_listen_on_address(node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(node_a, node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut *node_a, &mut *node_b, &mut *node_c] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr.clone()).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(node_b, node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    node_c.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(node_c, node_a),
+    )
+    .await
+    .expect("Node C to connect to Node A");
+
+    testlib::swarm::poll_mesh3(Duration::from_millis(50), node_a, node_b, node_c).await;
+}
+
+/// Get the provenance of the received messages events.
+fn received_provenances(events: &[Event]) -> Vec<Provenance> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            Event::MessageReceived { provenance, .. } => Some(*provenance),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn message_received_from_its_author_is_direct() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let mut node_a = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_A), false);
+    let mut node_b = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_B), false);
+    let mut node_c = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_C), false);
+
+    connect_line3(&topic, &mut node_a, &mut node_b, &mut node_c).await;
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish the message");
+
+    let node_a_events = poll_mesh3_and_collect_events(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        received_provenances(&node_a_events),
+        [Provenance::DirectFromAuthor],
+        "Node A should receive the message from its author"
+    );
+
+    let stats = node_a
+        .behaviour()
+        .topic_provenance_stats(&topic.hash())
+        .expect("Node A should be subscribed to the topic");
+    assert_eq!((stats.direct, stats.relayed, stats.unknown), (1, 0, 0));
+    assert_eq!(stats.direct_ratio(), Some(1.0));
+}
+
+#[tokio::test]
+async fn message_relayed_by_another_peer_is_indirect() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let mut node_a = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_A), false);
+    let mut node_b = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_B), false);
+    let mut node_c = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_C), false);
+
+    connect_line3(&topic, &mut node_a, &mut node_b, &mut node_c).await;
+
+    //// When
+    // Node B publishes a message, relayed by Node A to Node C.
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish the message");
+
+    let node_c_events = poll_mesh3_and_collect_events(
+        Duration::from_millis(50),
+        &mut node_c,
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        received_provenances(&node_c_events),
+        [Provenance::Relayed],
+        "Node C should receive the message relayed by Node A"
+    );
+
+    let stats = node_c
+        .behaviour()
+        .topic_provenance_stats(&topic.hash())
+        .expect("Node C should be subscribed to the topic");
+    assert_eq!((stats.direct, stats.relayed, stats.unknown), (0, 1, 0));
+    assert_eq!(stats.direct_ratio(), Some(0.0));
+}
+
+#[tokio::test]
+async fn anonymous_message_provenance_is_unknown() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let mut node_a = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_A), true);
+    let mut node_b = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_B), true);
+    let mut node_c = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_C), true);
+
+    connect_line3(&topic, &mut node_a, &mut node_b, &mut node_c).await;
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish the message");
+
+    let node_a_events = poll_mesh3_and_collect_events(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        received_provenances(&node_a_events),
+        [Provenance::Unknown],
+        "The anonymous message provenance should be unknown"
+    );
+
+    let stats = node_a
+        .behaviour()
+        .topic_provenance_stats(&topic.hash())
+        .expect("Node A should be subscribed to the topic");
+    assert_eq!((stats.direct, stats.relayed, stats.unknown), (0, 0, 1));
+    assert_eq!(stats.direct_ratio(), None);
+    assert_eq!(
+        node_a
+            .behaviour()
+            .topic_provenance_stats(&new_test_topic().hash()),
+        None,
+        "The not subscribed topics should have no stats"
+    );
+}
@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use futures::StreamExt;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, ConfigBuilder, Event, FrameMessage, IdentTopic, Message,
+    PublishError,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the first node behaviour events.
+async fn poll_mesh3_and_collect_events(
+    duration: Duration,
+    node: &mut Swarm<Behaviour>,
+    other_1: &mut Swarm<Behaviour>,
+    other_2: &mut Swarm<Behaviour>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => break,
+            event = node.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    events.push(event);
+                }
+            },
+            _ = other_1.select_next_some() => {},
+            _ = other_2.select_next_some() => {},
+        }
+    }
+
+    events
+}
+
+#[tokio::test]
+async fn publish_a_message_over_the_publish_size_once_encoded_fails() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let node_a_config = ConfigBuilder::new().max_publish_size(Some(1024)).build();
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    // The payload alone is under the publish size limit, but not with the message key.
+    let payload = vec![0xAB; 900];
+    let mut message = Message::new(topic.clone(), payload.clone());
+    message.set_key(vec![0xCD; 200]);
+
+    //// When
+    let unkeyed_result = node_a
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), payload));
+    let keyed_result = node_a.behaviour_mut().publish(message);
+
+    //// Then
+    assert_matches!(
+        unkeyed_result,
+        Ok(()),
+        "The unkeyed message should be published"
+    );
+    assert_matches!(keyed_result, Err(PublishError::MessageTooLarge { size, max_size }) => {
+        assert_eq!(max_size, 1024);
+        assert!(size > 900 + 200, "The size should be the message encoded size");
+    });
+}
+
+#[tokio::test]
+async fn received_message_over_the_publish_size_is_forwarded() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let node_c_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    // Node A publishing policy is stricter than the frame size limits.
+    let node_a_config = ConfigBuilder::new().max_publish_size(Some(1024)).build();
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let mut node_c = new_test_node(&node_c_key, Default::default());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b, &mut node_c] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    // B -> A <- C
+    node_b.dial(node_a_addr.clone()).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    node_c.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_c, &mut node_a),
+    )
+    .await
+    .expect("Node C to connect to Node A");
+
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), vec![0xAB; 4096]))
+        .expect("publish the message");
+
+    let node_c_events = poll_mesh3_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_c,
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        node_a.behaviour().traffic_totals().forwarded_messages,
+        1,
+        "Node A should forward the message"
+    );
+    let message = node_c_events
+        .into_iter()
+        .find_map(|ev| match ev {
+            Event::MessageReceived { message, .. } => Some(message),
+            _ => None,
+        })
+        .expect("Node C to receive the message");
+    assert_eq!(message.data.len(), 4096);
+    assert!(FrameMessage::from(message).encoded_len() > 1024);
+}
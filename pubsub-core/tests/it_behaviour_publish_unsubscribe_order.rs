@@ -0,0 +1,263 @@
+use std::collections::{BTreeSet, HashMap};
+use std::task::Poll;
+use std::time::Duration;
+
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, ToSwarm};
+use prost::Message as _;
+use rand::Rng;
+
+use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+use libp2p_pubsub_core::protocol::{
+    Protocol, ProtocolRouterInEvent, ProtocolRouterMessageEvent, ProtocolRouterOutEvent,
+    ProtocolRouterSubscriptionEvent,
+};
+use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Frame, IdentTopic, Message,
+    SubscriptionAction, TopicHash,
+};
+use libp2p_pubsub_proto::pubsub::FrameProto;
+use pubsub_testlib::{connect, poll_all, poll_once, receive_frame};
+
+mod pubsub_testlib;
+
+/// A test protocol whose router sends the published messages to the peers subscribed to their
+/// topic, as known by the router when it processes the publication.
+#[derive(Default)]
+struct SubscribersProtocol;
+
+impl Protocol for SubscribersProtocol {
+    type Upgrade = SimpleProtocolUpgrade<&'static str>;
+    type RouterService = SubscribersRouter;
+
+    fn upgrade() -> Self::Upgrade {
+        SimpleProtocolUpgrade::new("/subscribers/1.0.0")
+    }
+
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
+        Default::default()
+    }
+}
+
+/// The pubsub protocol router service for the subscribers protocol.
+#[derive(Default)]
+struct SubscribersRouter {
+    peers_subscriptions: HashMap<PeerId, BTreeSet<TopicHash>>,
+}
+
+impl EventHandler for SubscribersRouter {
+    type InEvent = ProtocolRouterInEvent;
+    type OutEvent = ProtocolRouterOutEvent;
+
+    fn on_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
+        ev: Self::InEvent,
+    ) {
+        match ev {
+            ProtocolRouterInEvent::SubscriptionEvent(
+                ProtocolRouterSubscriptionEvent::PeerSubscribed { peer, topic },
+            ) => {
+                self.peers_subscriptions
+                    .entry(peer)
+                    .or_default()
+                    .insert(topic);
+            }
+            ProtocolRouterInEvent::SubscriptionEvent(
+                ProtocolRouterSubscriptionEvent::PeerUnsubscribed { peer, topic },
+            ) => {
+                if let Some(topics) = self.peers_subscriptions.get_mut(&peer) {
+                    topics.remove(&topic);
+                }
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessagePublished {
+                message,
+                ..
+            }) => {
+                let topic = message.topic();
+                let dest = self
+                    .peers_subscriptions
+                    .iter()
+                    .filter(|(_, topics)| topics.contains(&topic))
+                    .map(|(peer, _)| *peer)
+                    .collect();
+                svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage { dest, message });
+            }
+            _ => {}
+        }
+    }
+}
+
+type Behaviour = PubsubBehaviour<SubscribersProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<SubscribersProtocol>;
+
+type HandlerCommand = pubsub_testlib::HandlerCommand<SubscribersProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Simulate the reception of a subscription action over the given connection.
+fn receive_subscription(
+    behaviour: &mut Behaviour,
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    action: SubscriptionAction,
+) {
+    let frame = Frame::new_with_subscriptions([action]);
+    receive_frame(behaviour, peer_id, connection_id, frame);
+}
+
+/// Collect the payloads of the messages sent to the peers.
+fn sent_payloads(events: &[BehaviourEvent]) -> Vec<Vec<u8>> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::NotifyHandler {
+                event: HandlerCommand::SendFrame { frame, .. },
+                ..
+            } => FrameProto::decode(frame.clone()).ok(),
+            _ => None,
+        })
+        .flat_map(|frame| frame.publish.into_iter().filter_map(|message| message.data))
+        .map(|data| data.to_vec())
+        .collect()
+}
+
+/// Create a behaviour subscribed to the topic, connected to a peer subscribed to the topic.
+fn new_test_behaviour(config: Config, topic: &IdentTopic) -> (Behaviour, PeerId, ConnectionId) {
+    let peer = PeerId::random();
+    let connection = ConnectionId::new_unchecked(1);
+
+    let mut behaviour = Behaviour::new(PeerId::random(), config, Default::default());
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    let _handler = connect(&mut behaviour, peer, connection);
+    poll_all(&mut behaviour);
+
+    receive_subscription(
+        &mut behaviour,
+        peer,
+        connection,
+        SubscriptionAction::Subscribe(topic.hash()),
+    );
+    poll_all(&mut behaviour);
+
+    (behaviour, peer, connection)
+}
+
+#[test]
+fn message_published_before_peer_unsubscription_is_processed_is_sent_to_the_peer() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut behaviour, peer, connection) = new_test_behaviour(Default::default(), &topic);
+
+    // The peer unsubscription frame is decoded, but not yet processed by the subscriptions state.
+    receive_subscription(
+        &mut behaviour,
+        peer,
+        connection,
+        SubscriptionAction::Unsubscribe(topic.hash()),
+    );
+    let mut events = Vec::new();
+    if let Poll::Ready(event) = poll_once(&mut behaviour) {
+        events.push(event);
+    }
+    let subscribed_on_publish = behaviour
+        .peer_subscriptions(&peer)
+        .map_or(false, |topics| topics.contains(&topic.hash()));
+
+    //// When
+    behaviour
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish message");
+    events.extend(poll_all(&mut behaviour));
+
+    //// Then
+    assert!(
+        subscribed_on_publish,
+        "The peer should be subscribed when the message is accepted"
+    );
+    assert_eq!(sent_payloads(&events), [b"test-payload".to_vec()]);
+    assert!(!behaviour
+        .peer_subscriptions(&peer)
+        .map_or(false, |topics| topics.contains(&topic.hash())));
+}
+
+#[test]
+fn message_published_after_peer_unsubscription_is_processed_is_not_sent_to_the_peer() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut behaviour, peer, connection) = new_test_behaviour(Default::default(), &topic);
+
+    receive_subscription(
+        &mut behaviour,
+        peer,
+        connection,
+        SubscriptionAction::Unsubscribe(topic.hash()),
+    );
+    // The decoded subscription request is processed on the next polling round.
+    let mut events = poll_all(&mut behaviour);
+    events.extend(poll_all(&mut behaviour));
+    let subscribed_on_publish = behaviour
+        .peer_subscriptions(&peer)
+        .map_or(false, |topics| topics.contains(&topic.hash()));
+
+    //// When
+    behaviour
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish message");
+    events.extend(poll_all(&mut behaviour));
+
+    //// Then
+    assert!(
+        !subscribed_on_publish,
+        "The peer should be unsubscribed when the message is accepted"
+    );
+    assert!(sent_payloads(&events).is_empty());
+}
+
+#[test]
+fn message_batched_before_peer_unsubscription_is_not_purged() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let config = ConfigBuilder::new()
+        .publish_batch_window(Some(Duration::from_millis(20)))
+        .build();
+    let (mut behaviour, peer, connection) = new_test_behaviour(config, &topic);
+
+    behaviour
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish message");
+    let mut events = poll_all(&mut behaviour);
+
+    //// When
+    receive_subscription(
+        &mut behaviour,
+        peer,
+        connection,
+        SubscriptionAction::Unsubscribe(topic.hash()),
+    );
+    events.extend(poll_all(&mut behaviour));
+    events.extend(poll_all(&mut behaviour));
+
+    // Wait for the publish batch window to elapse
+    std::thread::sleep(Duration::from_millis(30));
+    events.extend(poll_all(&mut behaviour));
+
+    //// Then
+    assert_eq!(sent_payloads(&events), [b"test-payload".to_vec()]);
+}
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::identity::PeerId;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::reqres::{RequestEnvelope, RequestId};
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Event, IdentTopic, PublishError};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Create two connected nodes, both subscribed to the topic.
+async fn new_connected_nodes(topic: &IdentTopic) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    (node_a, node_b)
+}
+
+/// Collect the request envelopes received by a node.
+fn received_requests(events: &[SwarmEvent<Event, impl std::fmt::Debug>]) -> Vec<RequestEnvelope> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            SwarmEvent::Behaviour(Event::MessageReceived { message, .. }) => {
+                RequestEnvelope::decode(&message.data)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collect the responses received by a node.
+fn received_responses(
+    events: &[SwarmEvent<Event, impl std::fmt::Debug>],
+) -> Vec<(RequestId, PeerId, Vec<u8>)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            SwarmEvent::Behaviour(Event::ResponseReceived {
+                request_id,
+                from,
+                payload,
+                ..
+            }) => Some((*request_id, *from, payload.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn response_is_received_by_the_requester() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic).await;
+
+    //// When
+    let request_id = node_a
+        .behaviour_mut()
+        .send_request(topic.hash(), b"ping", Duration::from_secs(5))
+        .expect("send the request");
+
+    let (_, node_b_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    let requests = received_requests(&node_b_events);
+    assert_eq!(requests.len(), 1, "Node B should receive the request");
+    assert_eq!(requests[0].request_id, request_id);
+    assert_eq!(requests[0].payload, b"ping");
+
+    node_b
+        .behaviour_mut()
+        .send_response(&requests[0], b"pong")
+        .expect("send the response");
+
+    let (node_a_events, _) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        received_responses(&node_a_events),
+        [(request_id, *node_b.local_peer_id(), b"pong".to_vec())]
+    );
+    assert!(
+        !node_a_events
+            .iter()
+            .any(|ev| matches!(ev, SwarmEvent::Behaviour(Event::MessageReceived { .. }))),
+        "The response should not be notified as a message"
+    );
+}
+
+#[tokio::test]
+async fn request_without_response_times_out() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic).await;
+
+    let request_id = node_a
+        .behaviour_mut()
+        .send_request(topic.hash(), b"ping", Duration::from_millis(100))
+        .expect("send the request");
+
+    //// When
+    // The timeouts are checked on every heartbeat, once per second by default.
+    let (node_a_events, node_b_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(2500),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    let requests = received_requests(&node_b_events);
+    assert_eq!(requests.len(), 1, "Node B should receive the request");
+
+    // Node B responds after the request timed out.
+    node_b
+        .behaviour_mut()
+        .send_response(&requests[0], b"pong")
+        .expect("send the response");
+
+    let (late_node_a_events, _) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    let timed_out = node_a_events
+        .iter()
+        .filter_map(|ev| match ev {
+            SwarmEvent::Behaviour(Event::RequestTimedOut { request_id }) => Some(*request_id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(timed_out, [request_id], "The request should time out once");
+    assert!(
+        received_responses(&late_node_a_events).is_empty(),
+        "The late response should be dropped"
+    );
+    assert!(
+        !late_node_a_events
+            .iter()
+            .any(|ev| matches!(ev, SwarmEvent::Behaviour(Event::MessageReceived { .. }))),
+        "The late response should not be notified as a message"
+    );
+}
+
+#[tokio::test]
+async fn concurrent_requests_are_correlated_with_their_responses() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic).await;
+
+    let request_ids = ["one", "two", "three"].map(|payload| {
+        let request_id = node_a
+            .behaviour_mut()
+            .send_request(topic.hash(), payload, Duration::from_secs(5))
+            .expect("send the request");
+        (request_id, payload)
+    });
+
+    let (_, node_b_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    let requests = received_requests(&node_b_events);
+    assert_eq!(requests.len(), 3, "Node B should receive all the requests");
+
+    //// When
+    // Node B responds in the reverse order, echoing the request payload.
+    for request in requests.iter().rev() {
+        let response = [b"re: ".as_slice(), &request.payload].concat();
+        node_b
+            .behaviour_mut()
+            .send_response(request, response)
+            .expect("send the response");
+    }
+
+    let (node_a_events, _) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    let responses = received_responses(&node_a_events)
+        .into_iter()
+        .map(|(request_id, _, payload)| (request_id, payload))
+        .collect::<HashMap<_, _>>();
+    assert_eq!(responses.len(), 3, "All the responses should be received");
+    for (request_id, payload) in request_ids {
+        assert_eq!(
+            responses.get(&request_id),
+            Some(&format!("re: {payload}").into_bytes()),
+            "The response should be correlated with request {request_id}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn request_to_an_unsubscribed_topic_is_rejected() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let other_topic = new_test_topic();
+    let (mut node_a, _node_b) = new_connected_nodes(&topic).await;
+
+    //// When
+    let result =
+        node_a
+            .behaviour_mut()
+            .send_request(other_topic.hash(), b"ping", Duration::from_secs(5));
+
+    //// Then
+    assert_matches!(
+        result,
+        Err(PublishError::NotSubscribed(topic)) if topic == other_topic.hash(),
+        "The request should be rejected"
+    );
+}
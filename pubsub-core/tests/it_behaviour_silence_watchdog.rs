@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::identity::PeerId;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, ConfigBuilder, Event, IdentTopic, Message, TopicHash,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the first node behaviour events.
+async fn poll_mesh_and_collect_events(
+    duration: Duration,
+    node: &mut Swarm<Behaviour>,
+    other: &mut Swarm<Behaviour>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            event = node.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    events.push(event);
+                }
+            },
+            _ = other.select_next_some() => {},
+        }
+    }
+
+    events
+}
+
+/// Create two connected nodes subscribed to the topic, Node A watching the topic silence.
+async fn new_connected_nodes(topic: &IdentTopic) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let node_a_config = ConfigBuilder::new()
+        .heartbeat_interval(Duration::from_millis(20))
+        .silence_watchdog(Some(Duration::from_millis(200)))
+        .build();
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    (node_a, node_b)
+}
+
+/// Get the silence events among the given events.
+fn silence_events(events: &[Event]) -> Vec<(&Vec<TopicHash>, &Vec<PeerId>)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            Event::SubscribedButSilent { topics, peers, .. } => Some((topics, peers)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn silent_subscribed_topic_is_notified_once() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic).await;
+
+    //// When
+    let node_a_events =
+        poll_mesh_and_collect_events(Duration::from_millis(600), &mut node_a, &mut node_b).await;
+
+    //// Then
+    let silences = silence_events(&node_a_events);
+    assert_eq!(silences.len(), 1, "The silence should be notified once");
+    assert_eq!(silences[0].0, &vec![topic.hash()]);
+    assert_eq!(silences[0].1, &vec![*node_b.local_peer_id()]);
+}
+
+#[tokio::test]
+async fn subscribed_topic_with_traffic_is_not_notified() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic).await;
+
+    //// When
+    let mut node_a_events = Vec::new();
+    for seq in 0..6u8 {
+        if let Err(err) = node_b
+            .behaviour_mut()
+            .publish(Message::new_with_sequence_number(
+                topic.clone(),
+                b"test-payload".to_vec(),
+                [seq],
+            ))
+        {
+            // The first publish may precede the connection processing.
+            tracing::debug!("Message not published: {err}");
+        }
+        node_a_events.extend(
+            poll_mesh_and_collect_events(Duration::from_millis(100), &mut node_a, &mut node_b)
+                .await,
+        );
+    }
+
+    //// Then
+    assert!(
+        node_a_events
+            .iter()
+            .any(|ev| matches!(ev, Event::MessageReceived { .. })),
+        "Node A should receive messages"
+    );
+    assert!(
+        silence_events(&node_a_events).is_empty(),
+        "The silence should not be notified"
+    );
+}
+
+#[tokio::test]
+async fn silence_is_notified_again_after_recovery_and_relapse() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic).await;
+
+    let first_events =
+        poll_mesh_and_collect_events(Duration::from_millis(400), &mut node_a, &mut node_b).await;
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .publish(Message::new(topic.clone(), b"test-payload".to_vec()))
+        .expect("publish the message");
+    let relapse_events =
+        poll_mesh_and_collect_events(Duration::from_millis(400), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert_eq!(silence_events(&first_events).len(), 1);
+    assert!(relapse_events
+        .iter()
+        .any(|ev| matches!(ev, Event::MessageReceived { .. })));
+    assert_eq!(
+        silence_events(&relapse_events).len(),
+        1,
+        "The silence should be notified again after the relapse"
+    );
+}
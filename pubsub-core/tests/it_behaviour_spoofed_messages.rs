@@ -0,0 +1,235 @@
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, ToSwarm};
+use rand::Rng;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, Frame, FrameMessage, IdentTopic,
+};
+use pubsub_testlib::{connect, poll_settled, receive_frame, NoopProtocol};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Create a test config flagging, and optionally dropping, the suspected spoofed messages.
+fn new_test_config(drop_suspected_spoofed: bool) -> Config {
+    ConfigBuilder::default()
+        .verify_from_matches_signature(true)
+        .drop_suspected_spoofed(drop_suspected_spoofed)
+        .build()
+}
+
+/// Simulate the reception of a frame carrying the given message from the given peer.
+fn receive_message(
+    behaviour: &mut Behaviour,
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    message: FrameMessage,
+) {
+    receive_frame(
+        behaviour,
+        peer_id,
+        connection_id,
+        Frame::new_with_messages([message]),
+    );
+}
+
+/// Collect the suspected spoofed messages notifications, as `(claimed_from, propagated_by)`
+/// pairs.
+fn suspected_spoofed(events: &[BehaviourEvent]) -> Vec<(PeerId, PeerId)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::GenerateEvent(Event::SuspectedSpoofedMessage {
+                claimed_from,
+                propagated_by,
+                ..
+            }) => Some((*claimed_from, *propagated_by)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collect the received messages propagation sources.
+fn received_from(events: &[BehaviourEvent]) -> Vec<PeerId> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::GenerateEvent(Event::MessageReceived { src, .. }) => Some(*src),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Create a test behaviour subscribed to the topic, and connected to the given peers.
+fn new_test_behaviour(config: Config, topic: &IdentTopic, peers: &[PeerId]) -> Behaviour {
+    let mut behaviour = Behaviour::new(PeerId::random(), config, Default::default());
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    for (idx, peer) in peers.iter().enumerate() {
+        connect(&mut behaviour, *peer, ConnectionId::new_unchecked(idx));
+    }
+    poll_settled(&mut behaviour);
+
+    behaviour
+}
+
+#[test]
+fn unsigned_message_from_connected_author_relayed_by_another_peer_is_flagged() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let author = PeerId::random();
+    let relay = PeerId::random();
+
+    let mut behaviour = new_test_behaviour(new_test_config(false), &topic, &[author, relay]);
+
+    let message =
+        FrameMessage::new_with_seq_no_and_from(topic.hash(), b"data".to_vec(), b"1", author);
+
+    //// When
+    receive_message(
+        &mut behaviour,
+        relay,
+        ConnectionId::new_unchecked(1),
+        message,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert_eq!(suspected_spoofed(&events), [(author, relay)]);
+    assert_eq!(
+        received_from(&events),
+        [relay],
+        "The flagged message should be delivered"
+    );
+}
+
+#[test]
+fn suspected_spoofed_message_is_dropped_if_enabled() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let author = PeerId::random();
+    let relay = PeerId::random();
+
+    let mut behaviour = new_test_behaviour(new_test_config(true), &topic, &[author, relay]);
+
+    let message =
+        FrameMessage::new_with_seq_no_and_from(topic.hash(), b"data".to_vec(), b"1", author);
+
+    //// When
+    receive_message(
+        &mut behaviour,
+        relay,
+        ConnectionId::new_unchecked(1),
+        message,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert_eq!(suspected_spoofed(&events), [(author, relay)]);
+    assert!(
+        received_from(&events).is_empty(),
+        "The flagged message should be dropped"
+    );
+}
+
+#[test]
+fn message_relayed_from_not_connected_author_is_not_flagged() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let author = PeerId::random();
+    let relay = PeerId::random();
+
+    // The local node is not directly connected to the author.
+    let mut behaviour = new_test_behaviour(new_test_config(true), &topic, &[relay]);
+
+    let message =
+        FrameMessage::new_with_seq_no_and_from(topic.hash(), b"data".to_vec(), b"1", author);
+
+    //// When
+    receive_message(
+        &mut behaviour,
+        relay,
+        ConnectionId::new_unchecked(0),
+        message,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(suspected_spoofed(&events).is_empty());
+    assert_eq!(received_from(&events), [relay]);
+}
+
+#[test]
+fn message_sent_by_its_author_is_not_flagged() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let author = PeerId::random();
+    let relay = PeerId::random();
+
+    let mut behaviour = new_test_behaviour(new_test_config(true), &topic, &[author, relay]);
+
+    let message =
+        FrameMessage::new_with_seq_no_and_from(topic.hash(), b"data".to_vec(), b"1", author);
+
+    //// When
+    receive_message(
+        &mut behaviour,
+        author,
+        ConnectionId::new_unchecked(0),
+        message,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(suspected_spoofed(&events).is_empty());
+    assert_eq!(received_from(&events), [author]);
+}
+
+#[test]
+fn signed_message_is_not_flagged() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let author = PeerId::random();
+    let relay = PeerId::random();
+
+    let mut behaviour = new_test_behaviour(new_test_config(true), &topic, &[author, relay]);
+
+    let mut message =
+        FrameMessage::new_with_seq_no_and_from(topic.hash(), b"data".to_vec(), b"1", author);
+    message.set_signature(Some(b"signature".to_vec()));
+
+    //// When
+    receive_message(
+        &mut behaviour,
+        relay,
+        ConnectionId::new_unchecked(1),
+        message,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(suspected_spoofed(&events).is_empty());
+    assert_eq!(received_from(&events), [relay]);
+}
@@ -0,0 +1,243 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::identity::PeerId;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, ConfigBuilder, Event, IdentTopic, Message, TopicHash,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the first node behaviour events.
+async fn poll_mesh_and_collect_events(
+    duration: Duration,
+    node: &mut Swarm<Behaviour>,
+    other: &mut Swarm<Behaviour>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            event = node.select_next_some() => {
+                if let SwarmEvent::Behaviour(event) = event {
+                    events.push(event);
+                }
+            },
+            _ = other.select_next_some() => {},
+        }
+    }
+
+    events
+}
+
+/// Create two connected nodes subscribed to the topic, emitting the subscription confirmation
+/// events if `events` is set.
+async fn new_connected_nodes(
+    topic: &IdentTopic,
+    events: bool,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let config = ConfigBuilder::new()
+        .subscription_confirmation_events(events)
+        .build();
+
+    let mut node_a = new_test_node(&node_a_key, config.clone());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, config);
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for node in [&mut node_a, &mut node_b] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    // Let the nodes exchange their subscriptions.
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    (node_a, node_b)
+}
+
+/// Publish the given number of messages on Node B, and collect Node A events.
+async fn publish_and_collect_events(
+    count: u8,
+    topic: &IdentTopic,
+    node_a: &mut Swarm<Behaviour>,
+    node_b: &mut Swarm<Behaviour>,
+) -> Vec<Event> {
+    for seq in 0..count {
+        node_b
+            .behaviour_mut()
+            .publish(Message::new_with_sequence_number(
+                topic.clone(),
+                b"test-payload".to_vec(),
+                [seq],
+            ))
+            .expect("publish the message");
+    }
+
+    poll_mesh_and_collect_events(Duration::from_millis(100), node_a, node_b).await
+}
+
+/// Get the subscription confirmation events among the given events.
+fn confirmation_events(events: &[Event]) -> Vec<(&PeerId, &TopicHash)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            Event::PeerConfirmedSubscription { peer, topic, .. } => Some((peer, topic)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn forwarded_message_confirms_the_subscription_once() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, true).await;
+    let node_b_peer_id = *node_b.local_peer_id();
+
+    assert!(node_a
+        .behaviour()
+        .peer_confirmed_topics(&node_b_peer_id)
+        .is_empty());
+
+    //// When
+    let node_a_events = publish_and_collect_events(3, &topic, &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert_eq!(
+        confirmation_events(&node_a_events),
+        vec![(&node_b_peer_id, &topic.hash())],
+        "The subscription should be confirmed once"
+    );
+    assert_eq!(
+        node_a.behaviour().peer_confirmed_topics(&node_b_peer_id),
+        BTreeSet::from([topic.hash()])
+    );
+}
+
+#[tokio::test]
+async fn confirmation_events_are_disabled_by_default() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, false).await;
+    let node_b_peer_id = *node_b.local_peer_id();
+
+    //// When
+    let node_a_events = publish_and_collect_events(3, &topic, &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert!(
+        confirmation_events(&node_a_events).is_empty(),
+        "No subscription confirmation event should be emitted"
+    );
+    assert_eq!(
+        node_a.behaviour().peer_confirmed_topics(&node_b_peer_id),
+        BTreeSet::from([topic.hash()]),
+        "The subscription confirmation should be recorded"
+    );
+}
+
+#[tokio::test]
+async fn confirmations_are_reset_on_disconnect() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, true).await;
+    let node_b_peer_id = *node_b.local_peer_id();
+
+    publish_and_collect_events(1, &topic, &mut node_a, &mut node_b).await;
+    assert!(!node_a
+        .behaviour()
+        .peer_confirmed_topics(&node_b_peer_id)
+        .is_empty());
+
+    //// When
+    node_a
+        .disconnect_peer_id(node_b_peer_id)
+        .expect("disconnect Node B");
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert!(
+        node_a
+            .behaviour()
+            .peer_confirmed_topics(&node_b_peer_id)
+            .is_empty(),
+        "The confirmations should be reset on disconnect"
+    );
+}
+
+#[tokio::test]
+async fn confirmations_are_reset_on_unsubscribe() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b) = new_connected_nodes(&topic, true).await;
+    let node_b_peer_id = *node_b.local_peer_id();
+
+    publish_and_collect_events(1, &topic, &mut node_a, &mut node_b).await;
+
+    //// When
+    node_a
+        .behaviour_mut()
+        .unsubscribe(&topic)
+        .expect("unsubscribe from topic");
+    testlib::swarm::poll_mesh(Duration::from_millis(50), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert!(
+        node_a
+            .behaviour()
+            .peer_confirmed_topics(&node_b_peer_id)
+            .is_empty(),
+        "The confirmations should be reset on unsubscribe"
+    );
+}
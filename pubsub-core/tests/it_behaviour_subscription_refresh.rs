@@ -0,0 +1,194 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::{SwarmBuilder, SwarmEvent};
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+use tracing_futures::Instrument;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, IdentTopic, TopicHash,
+};
+use pubsub_testlib::NoopProtocol;
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair);
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
+    SwarmBuilder::with_executor(
+        transport,
+        behaviour,
+        peer_id,
+        |fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+            tokio::spawn(fut.in_current_span());
+        },
+    )
+    .build()
+}
+
+/// Create two connected nodes, both subscribed to the given topic. Node A holds its subscription
+/// changes back for a long time, so Node B misses them until refreshed.
+async fn new_connected_nodes(topic: &IdentTopic) -> (Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_config = ConfigBuilder::new()
+        .subscription_debounce(Some(Duration::from_secs(60)))
+        .build();
+    let node_b_config = ConfigBuilder::new()
+        .subscription_refresh_window(Duration::from_millis(200))
+        .build();
+
+    let mut node_a = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_A), node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&testlib::secp256k1_keypair(TEST_KEYPAIR_B), node_b_config);
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    node_a
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+    node_b
+        .behaviour_mut()
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    (node_a, node_b)
+}
+
+/// Poll both nodes until Node B notifies a completed subscription refresh.
+async fn wait_for_refresh_completed(
+    node_a: &mut Swarm<Behaviour>,
+    node_b: &mut Swarm<Behaviour>,
+) -> (PeerId, Vec<TopicHash>) {
+    loop {
+        tokio::select! {
+            _ = node_a.select_next_some() => {},
+            event = node_b.select_next_some() => {
+                if let SwarmEvent::Behaviour(Event::SubscriptionRefreshCompleted {
+                    peer,
+                    topics_learned,
+                    ..
+                }) = event
+                {
+                    return (peer, topics_learned);
+                }
+            },
+        }
+    }
+}
+
+#[tokio::test]
+async fn subscription_refresh_learns_the_topics_missed_by_the_peer() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic_a = new_test_topic();
+    let pubsub_topic_b = new_test_topic();
+
+    let (mut node_a, mut node_b) = new_connected_nodes(&pubsub_topic_a).await;
+    let node_a_peer_id = *node_a.local_peer_id();
+
+    // Node A subscription to Topic B is held back by the subscription debounce window.
+    node_a
+        .behaviour_mut()
+        .subscribe(pubsub_topic_b.clone())
+        .expect("subscribe to topic");
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    let missed = !node_b
+        .behaviour()
+        .peer_subscriptions(&node_a_peer_id)
+        .expect("Node A subscriptions to be known")
+        .contains(&pubsub_topic_b.hash());
+
+    //// When
+    let requested = node_b
+        .behaviour_mut()
+        .request_subscription_refresh(node_a_peer_id);
+
+    let (peer, topics_learned) = timeout(
+        Duration::from_secs(5),
+        wait_for_refresh_completed(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("subscription refresh to complete");
+
+    //// Then
+    assert!(missed, "Node B should have missed Node A subscription");
+    assert!(requested, "The subscription refresh should be sent");
+    assert_eq!(peer, node_a_peer_id);
+    assert_eq!(topics_learned, vec![pubsub_topic_b.hash()]);
+    assert!(node_b
+        .behaviour()
+        .peer_subscriptions(&node_a_peer_id)
+        .expect("Node A subscriptions to be known")
+        .contains(&pubsub_topic_b.hash()));
+}
+
+#[tokio::test]
+async fn subscription_refresh_is_not_repeated_within_cooldown() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+
+    let (mut node_a, mut node_b) = new_connected_nodes(&pubsub_topic).await;
+    let node_a_peer_id = *node_a.local_peer_id();
+
+    //// When
+    let first = node_b
+        .behaviour_mut()
+        .request_subscription_refresh(node_a_peer_id);
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    let second = node_b
+        .behaviour_mut()
+        .request_subscription_refresh(node_a_peer_id);
+    let refreshed_by_b = node_b.behaviour_mut().refresh_all_peers();
+
+    // Node A answered Node B re-announcement, so its own refresh is in cooldown too.
+    let refreshed_by_a = node_a.behaviour_mut().refresh_all_peers();
+
+    //// Then
+    assert!(first, "The first subscription refresh should be sent");
+    assert!(
+        !second,
+        "The second subscription refresh should be in cooldown"
+    );
+    assert_eq!(refreshed_by_b, 0);
+    assert_eq!(refreshed_by_a, 0);
+}
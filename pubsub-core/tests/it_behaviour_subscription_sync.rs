@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, NotifyHandler, ToSwarm};
+use rand::Rng;
+
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, IdentTopic};
+use pubsub_testlib::{connect, connect_with_established, poll_all, NoopProtocol};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Create a test config with a maximum frame size too small to fit the subscriptions of many
+/// topics.
+fn new_test_config(max_attempts: usize) -> Config {
+    ConfigBuilder::new()
+        .max_frame_size(256)
+        .subscription_sync_max_attempts(max_attempts)
+        .subscription_sync_retry_interval(Duration::from_millis(50))
+        .build()
+}
+
+/// Subscribe to many topics, so the subscriptions frame exceeds the test maximum frame size.
+fn subscribe_many(behaviour: &mut Behaviour) {
+    for _ in 0..32 {
+        behaviour
+            .subscribe(new_test_topic())
+            .expect("subscribe to topic");
+    }
+    poll_all(behaviour);
+}
+
+/// Get the peers the behaviour sent frames to.
+fn frame_destinations(events: &[BehaviourEvent]) -> Vec<PeerId> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                handler: NotifyHandler::Any,
+                ..
+            } => Some(*peer_id),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn subscriptions_sync_succeeds_after_the_max_frame_size_is_corrected() {
+    testlib::init_logger();
+
+    //// Given
+    let local_peer_id = PeerId::random();
+    let peer = PeerId::random();
+
+    let mut behaviour = Behaviour::new(local_peer_id, new_test_config(3), Default::default());
+    subscribe_many(&mut behaviour);
+
+    let _handler = connect(&mut behaviour, peer, ConnectionId::new_unchecked(1));
+    let connect_events = poll_all(&mut behaviour);
+
+    //// When
+    behaviour.set_max_frame_size(65536);
+
+    // Wait for the subscription sync retry interval to elapse
+    std::thread::sleep(Duration::from_millis(60));
+    let retry_events = poll_all(&mut behaviour);
+
+    //// Then
+    assert!(
+        frame_destinations(&connect_events).is_empty(),
+        "Subscriptions frame should exceed the maximum frame size"
+    );
+    assert_eq!(frame_destinations(&retry_events), [peer]);
+    assert!(!retry_events.iter().any(|ev| matches!(
+        ev,
+        ToSwarm::GenerateEvent(Event::SubscriptionSyncFailed { .. })
+    )));
+}
+
+#[test]
+fn subscriptions_sync_is_retried_over_a_new_connection() {
+    testlib::init_logger();
+
+    //// Given
+    let local_peer_id = PeerId::random();
+    let peer = PeerId::random();
+
+    let mut behaviour = Behaviour::new(local_peer_id, new_test_config(3), Default::default());
+    subscribe_many(&mut behaviour);
+
+    let _handler_a = connect(&mut behaviour, peer, ConnectionId::new_unchecked(1));
+    poll_all(&mut behaviour);
+
+    //// When
+    behaviour.set_max_frame_size(65536);
+    let _handler_b =
+        connect_with_established(&mut behaviour, peer, ConnectionId::new_unchecked(2), 1);
+    let events = poll_all(&mut behaviour);
+
+    //// Then
+    assert_eq!(
+        frame_destinations(&events),
+        [peer],
+        "Subscriptions should be re-sent without waiting for the retry interval"
+    );
+}
+
+#[test]
+fn subscriptions_sync_failure_is_notified_after_the_maximum_attempts() {
+    testlib::init_logger();
+
+    //// Given
+    let local_peer_id = PeerId::random();
+    let peer = PeerId::random();
+
+    let mut behaviour = Behaviour::new(local_peer_id, new_test_config(2), Default::default());
+    subscribe_many(&mut behaviour);
+
+    //// When
+    let _handler = connect(&mut behaviour, peer, ConnectionId::new_unchecked(1));
+    let mut events = poll_all(&mut behaviour);
+
+    // Wait for the subscription sync retry interval to elapse
+    std::thread::sleep(Duration::from_millis(60));
+    events.extend(poll_all(&mut behaviour));
+
+    //// Then
+    assert!(frame_destinations(&events).is_empty());
+    assert_matches!(
+        &events[..],
+        [ToSwarm::GenerateEvent(Event::SubscriptionSyncFailed { peer: failed_peer })] => {
+            assert_eq!(failed_peer, &peer);
+        }
+    );
+}
@@ -1,16 +1,21 @@
 use std::future::Future;
 use std::pin::Pin;
+use std::task::Poll;
 use std::time::Duration;
 
 use assert_matches::assert_matches;
+use futures::future::poll_fn;
 use libp2p::identity::{Keypair, PeerId};
-use libp2p::swarm::SwarmBuilder;
+use libp2p::swarm::{SwarmBuilder, SwarmEvent};
 use libp2p::Swarm;
 use rand::Rng;
 use tokio::time::timeout;
 use tracing_futures::Instrument;
 
-use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, IdentTopic};
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, IdentTopic, Message,
+    SubscriptionBuilder,
+};
 use pubsub_testlib::NoopProtocol;
 use testlib::any_memory_addr;
 use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
@@ -29,7 +34,7 @@ fn new_test_topic() -> IdentTopic {
 fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
     let peer_id = PeerId::from(keypair.public());
     let transport = testlib::test_transport(keypair);
-    let behaviour = Behaviour::new(config, Default::default());
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
     SwarmBuilder::with_executor(
         transport,
         behaviour,
@@ -61,8 +66,9 @@ async fn node_should_subscribe_to_topic() {
         .subscribe(pubsub_topic_b.clone())
         .expect("subscribe to topic");
 
-    // Poll the node for a short period of time to allow the subscriptions to be processed.
-    testlib::swarm::poll_node(Duration::from_micros(10), &mut node).await;
+    // Process the subscriptions, instead of polling the node for a fixed period of time.
+    let drained = poll_fn(|cx| Poll::Ready(node.behaviour_mut().drain_to_completion(cx, 8))).await;
+    assert!(drained, "Node queued events should be drained");
 
     //// Then
     let topic_a = pubsub_topic_a.hash();
@@ -108,16 +114,18 @@ async fn node_should_unsubscribe_from_topic() {
         .subscribe(pubsub_topic_c.clone())
         .expect("subscribe to topic");
 
-    // Poll the node for a short period of time to allow the subscriptions to be processed.
-    testlib::swarm::poll_node(Duration::from_micros(10), &mut node).await;
+    // Process the subscriptions, instead of polling the node for a fixed period of time.
+    let drained = poll_fn(|cx| Poll::Ready(node.behaviour_mut().drain_to_completion(cx, 8))).await;
+    assert!(drained, "Node queued events should be drained");
 
     //// When
     node.behaviour_mut()
         .unsubscribe(&pubsub_topic_b)
         .expect("unsubscribe from topic");
 
-    // Poll the node for a short period of time to allow the subscriptions to be processed.
-    testlib::swarm::poll_node(Duration::from_micros(10), &mut node).await;
+    // Process the subscriptions, instead of polling the node for a fixed period of time.
+    let drained = poll_fn(|cx| Poll::Ready(node.behaviour_mut().drain_to_completion(cx, 8))).await;
+    assert!(drained, "Node queued events should be drained");
 
     //// Then
     let topic_a = pubsub_topic_a.hash();
@@ -227,6 +235,80 @@ async fn send_subscriptions_on_connection_established() {
     );
 }
 
+#[tokio::test]
+async fn paced_subscriptions_announcement_is_sent_in_batches() {
+    testlib::init_logger();
+
+    //// Given
+    let topics = (0..5).map(|_| new_test_topic()).collect::<Vec<_>>();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let node_a_config = ConfigBuilder::new()
+        .subscription_announce_batch(Some(2))
+        .subscription_announce_interval(Duration::from_millis(50))
+        .build();
+    let mut node_a = new_test_node(&node_a_key, node_a_config);
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for topic in &topics {
+        node_a
+            .behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    //// When
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node A to connect to Node B");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+    let first_progress = node_a
+        .behaviour()
+        .announcement_progress(node_b.local_peer_id());
+    let first_known = node_b
+        .behaviour()
+        .peer_subscriptions_owned(node_a.local_peer_id())
+        .unwrap_or_default();
+
+    testlib::swarm::poll_mesh(Duration::from_millis(200), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert_eq!(first_progress, Some((2, 5)), "Only the first batch is sent");
+    assert_eq!(first_known.len(), 2);
+    assert_eq!(
+        node_a
+            .behaviour()
+            .announcement_progress(node_b.local_peer_id()),
+        None,
+        "The announcement should be complete"
+    );
+    assert_matches!(
+        node_b.behaviour().peer_subscriptions(node_a.local_peer_id()),
+        Some(subscriptions) => {
+            assert!(topics.iter().all(|topic| subscriptions.contains(&topic.hash())));
+            assert_eq!(subscriptions.len(), 5);
+        },
+        "Node B should be aware of all Node A's topic subscriptions"
+    );
+}
+
 #[tokio::test]
 async fn send_subscriptions_on_subscribe() {
     testlib::init_logger();
@@ -377,3 +459,302 @@ async fn send_subscriptions_on_unsubscribe() {
         "Node B should be aware of Node A's topic subscriptions"
     );
 }
+
+#[tokio::test]
+async fn node_should_emit_subscription_lifecycle_events() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+
+    let mut node = new_test_node(&node_key, Default::default());
+
+    //// When
+    node.behaviour_mut()
+        .subscribe(pubsub_topic.clone())
+        .expect("subscribe to topic");
+
+    let subscribed_before_poll = node
+        .behaviour()
+        .subscriptions()
+        .contains(&pubsub_topic.hash());
+
+    let subscribe_events =
+        testlib::swarm::poll_node_and_collect_events(Duration::from_millis(10), &mut node).await;
+
+    node.behaviour_mut()
+        .unsubscribe(&pubsub_topic)
+        .expect("unsubscribe from topic");
+
+    let unsubscribe_events =
+        testlib::swarm::poll_node_and_collect_events(Duration::from_millis(10), &mut node).await;
+
+    //// Then
+    let topic = pubsub_topic.hash();
+
+    assert!(
+        !subscribed_before_poll,
+        "Subscription should be processed during a later poll"
+    );
+
+    assert_eq!(subscribe_events.len(), 1, "Only 1 event should be emitted");
+    assert_matches!(&subscribe_events[0], SwarmEvent::Behaviour(Event::Subscribed { topic: event_topic }) => {
+        assert_eq!(event_topic, &topic);
+    });
+
+    assert_eq!(
+        unsubscribe_events.len(),
+        1,
+        "Only 1 event should be emitted"
+    );
+    assert_matches!(&unsubscribe_events[0], SwarmEvent::Behaviour(Event::Unsubscribed { topic: event_topic }) => {
+        assert_eq!(event_topic, &topic);
+    });
+}
+
+#[tokio::test]
+async fn node_should_expose_and_return_subscription_info() {
+    testlib::init_logger();
+
+    //// Given
+    let owned_topic = new_test_topic();
+    let plain_topic = new_test_topic();
+
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+
+    let mut node = new_test_node(&node_key, Default::default());
+
+    let mut owned_sub = SubscriptionBuilder::new(owned_topic.clone());
+    owned_sub.owner("test-plugin");
+
+    //// When
+    node.behaviour_mut()
+        .subscribe(owned_sub.build())
+        .expect("subscribe to topic");
+    node.behaviour_mut()
+        .subscribe(plain_topic.clone())
+        .expect("subscribe to topic");
+
+    testlib::swarm::poll_node_and_collect_events(Duration::from_millis(10), &mut node).await;
+
+    let owned_info = node
+        .behaviour()
+        .subscription_info(&owned_topic.hash())
+        .cloned();
+    let plain_info = node
+        .behaviour()
+        .subscription_info(&plain_topic.hash())
+        .cloned();
+
+    let unsubscribed_info = node
+        .behaviour_mut()
+        .unsubscribe_with_info(&owned_topic)
+        .expect("unsubscribe from topic");
+
+    testlib::swarm::poll_node_and_collect_events(Duration::from_millis(10), &mut node).await;
+
+    //// Then
+    assert_matches!(&owned_info, Some(info) => {
+        assert_eq!(info.owner.as_deref(), Some("test-plugin"));
+    });
+    assert_matches!(&plain_info, Some(info) => {
+        assert_eq!(info.owner, None, "The topic subscriptions should have no owner");
+    });
+
+    assert_eq!(
+        unsubscribed_info, owned_info,
+        "The unsubscription should return the subscription info"
+    );
+    assert!(node
+        .behaviour()
+        .subscription_info(&owned_topic.hash())
+        .is_none());
+    assert_matches!(
+        node.behaviour_mut().unsubscribe_with_info(&owned_topic),
+        Ok(None)
+    );
+}
+
+#[tokio::test]
+async fn publish_should_succeed_after_subscribed_event() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    // Node B dial Node A
+    testlib::swarm::should_dial_address(&mut node_b, node_a_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .subscribe(pubsub_topic.clone())
+        .expect("subscribe to topic");
+
+    // Publishing before the subscription is processed fails.
+    let publish_before_result = node_b
+        .behaviour_mut()
+        .publish(Message::new(pubsub_topic.clone(), b"test-payload".to_vec()));
+
+    let (_, node_b_events) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(10),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    // Publishing after the subscribed event is emitted succeeds.
+    let publish_after_result = node_b
+        .behaviour_mut()
+        .publish(Message::new(pubsub_topic.clone(), b"test-payload".to_vec()));
+
+    //// Then
+    let topic = pubsub_topic.hash();
+
+    assert!(
+        publish_before_result.is_err(),
+        "Publish should fail before the subscription is processed"
+    );
+    assert!(
+        node_b_events.iter().any(|event| matches!(
+            event,
+            SwarmEvent::Behaviour(Event::Subscribed { topic: event_topic }) if event_topic == &topic
+        )),
+        "Node B should emit the subscribed event"
+    );
+    assert!(
+        publish_after_result.is_ok(),
+        "Publish should succeed after the subscribed event"
+    );
+}
+
+#[tokio::test]
+async fn publish_without_subscribed_peers_should_emit_warning_event() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    // Node B dial Node A
+    testlib::swarm::should_dial_address(&mut node_b, node_a_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    node_b
+        .behaviour_mut()
+        .subscribe(pubsub_topic.clone())
+        .expect("subscribe to topic");
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    //// When
+    // Publish before any peer announces the topic.
+    node_b
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            pubsub_topic.clone(),
+            b"test-payload".to_vec(),
+            1_u64.to_be_bytes(),
+        ))
+        .expect("publish to succeed");
+
+    let (_, events_before) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(10),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    // Publish after Node A subscribes to the topic.
+    node_a
+        .behaviour_mut()
+        .subscribe(pubsub_topic.clone())
+        .expect("subscribe to topic");
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    node_b
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            pubsub_topic.clone(),
+            b"test-payload".to_vec(),
+            2_u64.to_be_bytes(),
+        ))
+        .expect("publish to succeed");
+
+    let (_, events_after) = testlib::swarm::poll_mesh_and_collect_events(
+        Duration::from_millis(10),
+        &mut node_a,
+        &mut node_b,
+    )
+    .await;
+
+    //// Then
+    let topic = pubsub_topic.hash();
+
+    let warnings_before = events_before
+        .iter()
+        .filter(|event| {
+            matches!(
+                event,
+                SwarmEvent::Behaviour(Event::PublishedWithoutSubscribers { topic: event_topic, .. }) if event_topic == &topic
+            )
+        })
+        .count();
+    assert_eq!(
+        warnings_before, 1,
+        "Node B should warn about the message published without subscribers"
+    );
+
+    assert!(
+        !events_after.iter().any(|event| matches!(
+            event,
+            SwarmEvent::Behaviour(Event::PublishedWithoutSubscribers { .. })
+        )),
+        "Node B should not warn once Node A is subscribed"
+    );
+}
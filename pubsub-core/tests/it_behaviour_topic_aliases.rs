@@ -0,0 +1,367 @@
+use std::collections::HashSet;
+
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, ToSwarm};
+use prost::Message as _;
+
+use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+use libp2p_pubsub_core::protocol::{
+    Protocol, ProtocolRouterConnectionEvent, ProtocolRouterInEvent, ProtocolRouterMessageEvent,
+    ProtocolRouterOutEvent,
+};
+use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, Event, Frame, FrameMessage, IdentTopic,
+    Sha256Topic, SubscriptionBuilder, TopicHash,
+};
+use libp2p_pubsub_proto::pubsub::FrameProto;
+use pubsub_testlib::{connect, poll_settled, receive_frame};
+
+mod pubsub_testlib;
+
+/// A test protocol whose router forwards the received messages to all the other connected peers.
+#[derive(Default)]
+struct ForwardReceivedProtocol;
+
+impl Protocol for ForwardReceivedProtocol {
+    type Upgrade = SimpleProtocolUpgrade<&'static str>;
+    type RouterService = ForwardReceivedRouter;
+
+    fn upgrade() -> Self::Upgrade {
+        SimpleProtocolUpgrade::new("/forward-received/1.0.0")
+    }
+
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
+        Default::default()
+    }
+}
+
+/// The pubsub protocol router service for the forward-received protocol.
+#[derive(Default)]
+struct ForwardReceivedRouter {
+    peers: HashSet<PeerId>,
+}
+
+impl EventHandler for ForwardReceivedRouter {
+    type InEvent = ProtocolRouterInEvent;
+    type OutEvent = ProtocolRouterOutEvent;
+
+    fn on_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
+        ev: Self::InEvent,
+    ) {
+        match ev {
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerConnected(peer),
+            ) => {
+                self.peers.insert(peer);
+            }
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerDisconnected(peer),
+            ) => {
+                self.peers.remove(&peer);
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessageReceived {
+                src,
+                message,
+                ..
+            }) => {
+                svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage {
+                    dest: self.peers.iter().copied().filter(|p| *p != src).collect(),
+                    message,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+type Behaviour = PubsubBehaviour<ForwardReceivedProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<ForwardReceivedProtocol>;
+
+type HandlerCommand = pubsub_testlib::HandlerCommand<ForwardReceivedProtocol>;
+
+/// Collect the topics of the messages delivered to the application.
+fn delivered_topics(events: &[BehaviourEvent]) -> Vec<TopicHash> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::GenerateEvent(Event::MessageReceived { message, .. }) => {
+                Some(message.topic.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collect the topic hash mismatch notifications, as `(received_topic, matching_local_topic)`
+/// pairs.
+fn topic_hash_mismatches(events: &[BehaviourEvent]) -> Vec<(TopicHash, TopicHash)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::GenerateEvent(Event::TopicHashMismatchSuspected {
+                received_topic,
+                matching_local_topic,
+                ..
+            }) => Some((received_topic.clone(), matching_local_topic.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collect the frames sent to the peers, decoded, as `(peer, frame)` pairs.
+fn sent_frames(events: &[BehaviourEvent]) -> Vec<(PeerId, FrameProto)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerCommand::SendFrame { frame, .. },
+                ..
+            } => Some((*peer_id, FrameProto::decode(frame.clone()).ok()?)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collect the topics of the messages forwarded to the given peer.
+fn forwarded_topics(events: &[BehaviourEvent], peer: PeerId) -> Vec<String> {
+    sent_frames(events)
+        .into_iter()
+        .filter(|(dest, _)| *dest == peer)
+        .flat_map(|(_, frame)| frame.publish)
+        .map(|message| message.topic)
+        .collect()
+}
+
+/// Collect the topics announced in the subscription frames sent to the given peer.
+fn announced_topics(events: &[BehaviourEvent], peer: PeerId) -> Vec<String> {
+    sent_frames(events)
+        .into_iter()
+        .filter(|(dest, _)| *dest == peer)
+        .flat_map(|(_, frame)| frame.subscriptions)
+        .filter_map(|sub| sub.topic_id)
+        .collect()
+}
+
+/// Create a test behaviour, with the given configuration, connected to the given peers.
+fn new_test_behaviour(config: Config, peers: &[PeerId]) -> Behaviour {
+    let mut behaviour = Behaviour::new(PeerId::random(), config, Default::default());
+    for (idx, peer) in peers.iter().enumerate() {
+        connect(&mut behaviour, *peer, ConnectionId::new_unchecked(idx));
+    }
+    poll_settled(&mut behaviour);
+
+    behaviour
+}
+
+/// Create a message on the given topic, with a distinct sequence number.
+fn new_test_message(topic: impl Into<TopicHash>, idx: u64) -> FrameMessage {
+    FrameMessage::new_with_sequence_number(topic, b"test-data".to_vec(), idx.to_be_bytes())
+}
+
+#[test]
+fn message_on_the_raw_topic_string_of_a_sha256_subscription_is_notified_once() {
+    testlib::init_logger();
+
+    //// Given
+    let peer = PeerId::random();
+    let sha256_topic = Sha256Topic::new("/test/mismatch");
+    let raw_topic = IdentTopic::new("/test/mismatch").hash();
+
+    let mut behaviour = new_test_behaviour(Default::default(), &[peer]);
+    behaviour.subscribe(sha256_topic.clone()).unwrap();
+    poll_settled(&mut behaviour);
+
+    //// When
+    let events = (0..3)
+        .flat_map(|idx| {
+            let frame = Frame::new_with_messages([new_test_message(raw_topic.clone(), idx)]);
+            receive_frame(&mut behaviour, peer, ConnectionId::new_unchecked(0), frame);
+            poll_settled(&mut behaviour)
+        })
+        .collect::<Vec<_>>();
+
+    //// Then
+    assert!(delivered_topics(&events).is_empty(), "No message delivered");
+    assert_eq!(
+        topic_hash_mismatches(&events),
+        vec![(raw_topic, sha256_topic.hash())],
+        "The mismatch should be notified once per interval"
+    );
+}
+
+#[test]
+fn message_on_the_sha256_hash_of_an_identity_subscription_is_notified() {
+    testlib::init_logger();
+
+    //// Given
+    let peer = PeerId::random();
+    let ident_topic = IdentTopic::new("/test/mismatch");
+    let sha256_topic = Sha256Topic::new("/test/mismatch").hash();
+
+    let mut behaviour = new_test_behaviour(Default::default(), &[peer]);
+    behaviour.subscribe(ident_topic.clone()).unwrap();
+    poll_settled(&mut behaviour);
+
+    //// When
+    let frame = Frame::new_with_messages([new_test_message(sha256_topic.clone(), 0)]);
+    receive_frame(&mut behaviour, peer, ConnectionId::new_unchecked(0), frame);
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert_eq!(
+        topic_hash_mismatches(&events),
+        vec![(sha256_topic, ident_topic.hash())]
+    );
+}
+
+#[test]
+fn message_on_an_unrelated_topic_is_not_notified() {
+    testlib::init_logger();
+
+    //// Given
+    let peer = PeerId::random();
+
+    let mut behaviour = new_test_behaviour(Default::default(), &[peer]);
+    behaviour
+        .subscribe(Sha256Topic::new("/test/subscribed"))
+        .unwrap();
+    poll_settled(&mut behaviour);
+
+    //// When
+    let frame = Frame::new_with_messages([new_test_message("/test/unrelated", 0)]);
+    receive_frame(&mut behaviour, peer, ConnectionId::new_unchecked(0), frame);
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(topic_hash_mismatches(&events).is_empty());
+}
+
+#[test]
+fn mismatch_is_notified_again_once_the_interval_elapsed() {
+    testlib::init_logger();
+
+    //// Given
+    let peer = PeerId::random();
+    let sha256_topic = Sha256Topic::new("/test/mismatch");
+    let raw_topic = IdentTopic::new("/test/mismatch").hash();
+
+    let mut config = ConfigBuilder::default();
+    config.topic_hash_mismatch_event_interval(std::time::Duration::ZERO);
+    let mut behaviour = new_test_behaviour(config.build(), &[peer]);
+    behaviour.subscribe(sha256_topic).unwrap();
+    poll_settled(&mut behaviour);
+
+    //// When
+    let events = (0..2)
+        .flat_map(|idx| {
+            let frame = Frame::new_with_messages([new_test_message(raw_topic.clone(), idx)]);
+            receive_frame(&mut behaviour, peer, ConnectionId::new_unchecked(0), frame);
+            poll_settled(&mut behaviour)
+        })
+        .collect::<Vec<_>>();
+
+    //// Then
+    assert_eq!(topic_hash_mismatches(&events).len(), 2);
+}
+
+#[test]
+fn aliased_messages_are_delivered_and_forwarded_as_the_subscription_topic() {
+    testlib::init_logger();
+
+    //// Given
+    let ident_peer = PeerId::random();
+    let sha256_peer = PeerId::random();
+    let sha256_topic = Sha256Topic::new("/test/alias");
+    let raw_topic = IdentTopic::new("/test/alias").hash();
+
+    let mut behaviour = new_test_behaviour(Default::default(), &[ident_peer, sha256_peer]);
+
+    let mut sub = SubscriptionBuilder::new(sha256_topic.clone());
+    sub.also_accept_alias(raw_topic.clone());
+    behaviour.subscribe(sub.build()).unwrap();
+    let events = poll_settled(&mut behaviour);
+
+    // The subscription is only announced for the subscription topic.
+    assert_eq!(
+        announced_topics(&events, ident_peer),
+        vec![sha256_topic.hash().into_string()]
+    );
+
+    //// When
+    let author = PeerId::random();
+    let message =
+        FrameMessage::new_with_seq_no_and_from(raw_topic, b"test-data".to_vec(), [0], author);
+    let frame = Frame::new_with_messages([message]);
+    receive_frame(
+        &mut behaviour,
+        ident_peer,
+        ConnectionId::new_unchecked(0),
+        frame,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert_eq!(delivered_topics(&events), vec![sha256_topic.hash()]);
+    assert_eq!(
+        forwarded_topics(&events, sha256_peer),
+        vec![sha256_topic.hash().into_string()]
+    );
+    assert!(forwarded_topics(&events, ident_peer).is_empty());
+    assert!(
+        topic_hash_mismatches(&events).is_empty(),
+        "Aliased messages are not notified as a mismatch"
+    );
+
+    //// When
+    // The same message, received on the subscription topic, is deduplicated.
+    let duplicate = FrameMessage::new_with_seq_no_and_from(
+        sha256_topic.hash(),
+        b"test-data".to_vec(),
+        [0],
+        author,
+    );
+    let frame = Frame::new_with_messages([duplicate]);
+    receive_frame(
+        &mut behaviour,
+        sha256_peer,
+        ConnectionId::new_unchecked(1),
+        frame,
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(delivered_topics(&events).is_empty(), "Duplicate delivered");
+}
+
+#[test]
+fn aliases_are_removed_on_unsubscription() {
+    testlib::init_logger();
+
+    //// Given
+    let peer = PeerId::random();
+    let sha256_topic = Sha256Topic::new("/test/alias");
+    let raw_topic = IdentTopic::new("/test/alias").hash();
+
+    let mut behaviour = new_test_behaviour(Default::default(), &[peer]);
+
+    let mut sub = SubscriptionBuilder::new(sha256_topic.clone());
+    sub.also_accept_alias(raw_topic.clone());
+    behaviour.subscribe(sub.build()).unwrap();
+    poll_settled(&mut behaviour);
+
+    //// When
+    behaviour.unsubscribe(&sha256_topic).unwrap();
+    poll_settled(&mut behaviour);
+
+    let frame = Frame::new_with_messages([new_test_message(raw_topic, 0)]);
+    receive_frame(&mut behaviour, peer, ConnectionId::new_unchecked(0), frame);
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(delivered_topics(&events).is_empty(), "No message delivered");
+}
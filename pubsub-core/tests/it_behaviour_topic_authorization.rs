@@ -0,0 +1,194 @@
+use bytes::Bytes;
+use libp2p::identity::PeerId;
+use libp2p::swarm::{ConnectionId, ToSwarm};
+use rand::Rng;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, ConfigBuilder, Event, Frame, FrameMessage, IdentTopic,
+    StaticAllowlist, SubscriptionAction, TopicAction, TopicHash,
+};
+use pubsub_testlib::{connect, poll_settled, receive_frame, NoopProtocol};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+type BehaviourEvent = pubsub_testlib::BehaviourEvent<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Collect the received messages payloads.
+fn received_messages(events: &[BehaviourEvent]) -> Vec<Bytes> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::GenerateEvent(Event::MessageReceived { message, .. }) => {
+                Some(Bytes::from(message.data.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collect the unauthorized messages notifications, as `(peer, topic)` pairs.
+fn unauthorized_messages(events: &[BehaviourEvent]) -> Vec<(PeerId, TopicHash)> {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::GenerateEvent(Event::UnauthorizedMessage { peer, topic, .. }) => {
+                Some((*peer, topic.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Create a test behaviour, with the given allowlist, subscribed to the topic and connected to
+/// the given peers.
+fn new_test_behaviour(
+    allowlist: StaticAllowlist,
+    topic: &IdentTopic,
+    peers: &[PeerId],
+) -> Behaviour {
+    let config = ConfigBuilder::default()
+        .topic_authorizer(Some(allowlist.into_authorizer()))
+        .build();
+
+    let mut behaviour = Behaviour::new(PeerId::random(), config, Default::default());
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    for (idx, peer) in peers.iter().enumerate() {
+        connect(&mut behaviour, *peer, ConnectionId::new_unchecked(idx));
+    }
+    poll_settled(&mut behaviour);
+
+    behaviour
+}
+
+#[test]
+fn unauthorized_peer_subscription_is_ignored() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let member = PeerId::random();
+    let stranger = PeerId::random();
+
+    let mut allowlist = StaticAllowlist::new();
+    allowlist.allow(topic.hash(), TopicAction::Subscribe, [member]);
+
+    let mut behaviour = new_test_behaviour(allowlist, &topic, &[member, stranger]);
+
+    //// When
+    for (idx, peer) in [member, stranger].into_iter().enumerate() {
+        receive_frame(
+            &mut behaviour,
+            peer,
+            ConnectionId::new_unchecked(idx),
+            Frame::new_with_subscriptions([SubscriptionAction::Subscribe(topic.hash())]),
+        );
+    }
+    poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(behaviour
+        .peer_subscriptions(&member)
+        .map_or(false, |topics| topics.contains(&topic.hash())));
+    assert!(
+        !behaviour
+            .peer_subscriptions(&stranger)
+            .map_or(false, |topics| topics.contains(&topic.hash())),
+        "The unauthorized peer subscription should be ignored"
+    );
+    assert_eq!(behaviour.drop_stats().unauthorized_subscriptions, 1);
+}
+
+#[test]
+fn messages_from_unauthorized_peer_are_dropped_and_notified_once() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let member = PeerId::random();
+    let stranger = PeerId::random();
+
+    let mut allowlist = StaticAllowlist::new();
+    allowlist.allow(topic.hash(), TopicAction::Publish, [member]);
+
+    let mut behaviour = new_test_behaviour(allowlist, &topic, &[member, stranger]);
+
+    //// When
+    receive_frame(
+        &mut behaviour,
+        stranger,
+        ConnectionId::new_unchecked(1),
+        Frame::new_with_messages([
+            FrameMessage::new_with_sequence_number(topic.hash(), b"spam-1".to_vec(), b"1"),
+            FrameMessage::new_with_sequence_number(topic.hash(), b"spam-2".to_vec(), b"2"),
+        ]),
+    );
+    receive_frame(
+        &mut behaviour,
+        member,
+        ConnectionId::new_unchecked(0),
+        Frame::new_with_messages([FrameMessage::new_with_sequence_number(
+            topic.hash(),
+            b"announcement".to_vec(),
+            b"3",
+        )]),
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert_eq!(
+        received_messages(&events),
+        [Bytes::from_static(b"announcement")]
+    );
+    assert_eq!(behaviour.drop_stats().unauthorized_messages, 2);
+    assert_eq!(
+        unauthorized_messages(&events),
+        [(stranger, topic.hash())],
+        "The unauthorized messages should be notified once per interval"
+    );
+}
+
+#[test]
+fn message_from_unauthorized_author_propagated_by_authorized_peer_is_dropped() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let member = PeerId::random();
+    let stranger = PeerId::random();
+
+    let mut allowlist = StaticAllowlist::new();
+    allowlist.allow(topic.hash(), TopicAction::Publish, [member]);
+
+    let mut behaviour = new_test_behaviour(allowlist, &topic, &[member]);
+
+    //// When
+    receive_frame(
+        &mut behaviour,
+        member,
+        ConnectionId::new_unchecked(0),
+        Frame::new_with_messages([FrameMessage::new_with_seq_no_and_from(
+            topic.hash(),
+            b"relayed-spam".to_vec(),
+            b"1",
+            stranger,
+        )]),
+    );
+    let events = poll_settled(&mut behaviour);
+
+    //// Then
+    assert!(received_messages(&events).is_empty());
+    assert_eq!(behaviour.drop_stats().unauthorized_messages, 1);
+    assert_eq!(unauthorized_messages(&events), [(stranger, topic.hash())]);
+}
@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::swarm::SwarmEvent;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Event, IdentTopic, Message, SubscriptionBuilder,
+};
+use pubsub_testlib::{new_test_node, FloodProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B, TEST_KEYPAIR_C};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<FloodProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+/// Poll the nodes for a given period of time, and collect the messages payloads received by each
+/// node.
+async fn poll_mesh3_and_collect_messages(
+    duration: Duration,
+    node_a: &mut Swarm<Behaviour>,
+    node_b: &mut Swarm<Behaviour>,
+    node_c: &mut Swarm<Behaviour>,
+) -> [Vec<Vec<u8>>; 3] {
+    let mut messages: [Vec<Vec<u8>>; 3] = Default::default();
+
+    loop {
+        let (idx, event) = tokio::select! {
+            _ = tokio::time::sleep(duration) => break,
+            event = node_a.select_next_some() => (0, event),
+            event = node_b.select_next_some() => (1, event),
+            event = node_c.select_next_some() => (2, event),
+        };
+        if let SwarmEvent::Behaviour(Event::MessageReceived { message, .. }) = event {
+            messages[idx].push(message.data);
+        }
+    }
+
+    messages
+}
+
+/// Create three nodes, all subscribed to the topic, connected in a chain: A -> B <- C.
+///
+/// Node B subscribes to the topic with the forwarding disabled.
+async fn new_chained_nodes(
+    topic: &IdentTopic,
+) -> (Swarm<Behaviour>, Swarm<Behaviour>, Swarm<Behaviour>) {
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+    let node_c_key = testlib::secp256k1_keypair(TEST_KEYPAIR_C);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let mut node_c = new_test_node(&node_c_key, Default::default());
+
+    let (_node_a_addr, node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    let mut subscription = SubscriptionBuilder::new(topic.clone());
+    subscription.forwarding(false);
+    node_b
+        .behaviour_mut()
+        .subscribe(subscription.build())
+        .expect("subscribe to topic");
+    for node in [&mut node_a, &mut node_c] {
+        node.behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_a.dial(node_b_addr.clone()).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("Node A to connect to Node B");
+
+    node_c.dial(node_b_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_c, &mut node_b),
+    )
+    .await
+    .expect("Node C to connect to Node B");
+
+    testlib::swarm::poll_mesh3(
+        Duration::from_millis(50),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    (node_a, node_b, node_c)
+}
+
+#[tokio::test]
+async fn non_forwarding_node_delivers_but_does_not_relay_received_messages() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b, mut node_c) = new_chained_nodes(&topic).await;
+
+    //// When
+    node_a
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            topic.clone(),
+            b"from-a".to_vec(),
+            1_u64.to_be_bytes(),
+        ))
+        .expect("publish the message");
+
+    let [_, node_b_messages, node_c_messages] = poll_mesh3_and_collect_messages(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(
+        node_b_messages,
+        [b"from-a".to_vec()],
+        "The message should be delivered locally"
+    );
+    assert!(
+        node_c_messages.is_empty(),
+        "The message should not be relayed by Node B"
+    );
+    assert_eq!(node_b.behaviour().traffic_totals().forwarded_messages, 0);
+}
+
+#[tokio::test]
+async fn non_forwarding_node_publishes_messages_as_usual() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let (mut node_a, mut node_b, mut node_c) = new_chained_nodes(&topic).await;
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .publish(Message::new_with_sequence_number(
+            topic.clone(),
+            b"from-b".to_vec(),
+            1_u64.to_be_bytes(),
+        ))
+        .expect("publish the message");
+
+    let [node_a_messages, _, node_c_messages] = poll_mesh3_and_collect_messages(
+        Duration::from_millis(100),
+        &mut node_a,
+        &mut node_b,
+        &mut node_c,
+    )
+    .await;
+
+    //// Then
+    assert_eq!(node_a_messages, [b"from-b".to_vec()]);
+    assert_eq!(node_c_messages, [b"from-b".to_vec()]);
+}
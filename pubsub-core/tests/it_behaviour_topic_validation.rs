@@ -0,0 +1,125 @@
+use libp2p::identity::PeerId;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, ConfigBuilder, ConsumerTag, IdentTopic, TopicValidation,
+    TopicValidationError,
+};
+use pubsub_testlib::NoopProtocol;
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+fn new_test_behaviour(config: Config) -> Behaviour {
+    Behaviour::new(PeerId::random(), config, Default::default())
+}
+
+fn new_test_config(topic_validation: TopicValidation) -> Config {
+    ConfigBuilder::default()
+        .topic_validation(topic_validation)
+        .build()
+}
+
+/// Subscribe to the topic, and return the topic validation error, if any.
+fn subscribe_validation_error(
+    behaviour: &mut Behaviour,
+    topic: &str,
+) -> Option<TopicValidationError> {
+    behaviour
+        .subscribe(IdentTopic::new(topic))
+        .err()
+        .map(|err| {
+            *err.downcast_ref::<TopicValidationError>()
+                .expect("topic validation error")
+        })
+}
+
+#[test]
+fn subscription_to_topic_longer_than_max_length_is_rejected() {
+    //// Given
+    let mut behaviour = new_test_behaviour(new_test_config(TopicValidation {
+        max_length: 16,
+        ..Default::default()
+    }));
+
+    //// When
+    let valid = subscribe_validation_error(&mut behaviour, "/test/topic");
+    let invalid = subscribe_validation_error(&mut behaviour, "/test/too-long-topic");
+
+    //// Then
+    assert_eq!(valid, None);
+    assert_eq!(
+        invalid,
+        Some(TopicValidationError::TooLong {
+            length: 20,
+            max_length: 16
+        })
+    );
+}
+
+#[test]
+fn subscription_to_topic_with_control_characters_is_rejected_by_default() {
+    //// Given
+    let mut behaviour = new_test_behaviour(Default::default());
+    let mut permissive_behaviour = new_test_behaviour(new_test_config(TopicValidation {
+        allow_control_chars: true,
+        ..Default::default()
+    }));
+
+    //// When
+    let rejected = subscribe_validation_error(&mut behaviour, "/test/topic\n");
+    let accepted = subscribe_validation_error(&mut permissive_behaviour, "/test/topic\n");
+
+    //// Then
+    assert_eq!(rejected, Some(TopicValidationError::ControlCharacter));
+    assert_eq!(accepted, None);
+}
+
+#[test]
+fn subscription_to_topic_with_replacement_character_is_rejected() {
+    //// Given
+    let mut behaviour = new_test_behaviour(Default::default());
+    let topic = String::from_utf8_lossy(b"/test/topic-\xff").into_owned();
+
+    //// When
+    let error = subscribe_validation_error(&mut behaviour, &topic);
+
+    //// Then
+    assert_eq!(error, Some(TopicValidationError::InvalidUtf8));
+}
+
+#[test]
+fn subscription_to_topic_rejected_by_custom_validation() {
+    //// Given
+    let mut behaviour = new_test_behaviour(new_test_config(TopicValidation {
+        custom: Some(|topic| topic.starts_with("/test/")),
+        ..Default::default()
+    }));
+
+    //// When
+    let valid = subscribe_validation_error(&mut behaviour, "/test/topic");
+    let invalid = subscribe_validation_error(&mut behaviour, "/other/topic");
+
+    //// Then
+    assert_eq!(valid, None);
+    assert_eq!(invalid, Some(TopicValidationError::Rejected));
+}
+
+#[test]
+fn consumer_registration_on_invalid_topic_is_rejected() {
+    //// Given
+    let mut behaviour = new_test_behaviour(Default::default());
+
+    //// When
+    let result = behaviour.register_consumer(
+        IdentTopic::new("/test/topic\r\n").hash(),
+        ConsumerTag::new("plugin"),
+    );
+
+    //// Then
+    let err = result.expect_err("the consumer registration should be rejected");
+    assert_eq!(
+        err.downcast_ref::<TopicValidationError>(),
+        Some(&TopicValidationError::ControlCharacter)
+    );
+}
@@ -0,0 +1,201 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use futures::future::poll_fn;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::SwarmBuilder;
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+use tracing_futures::Instrument;
+
+use libp2p_pubsub_core::{Behaviour as PubsubBehaviour, Config, IdentTopic};
+use pubsub_testlib::NoopProtocol;
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+pub type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair);
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
+    SwarmBuilder::with_executor(
+        transport,
+        behaviour,
+        peer_id,
+        |fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+            tokio::spawn(fut.in_current_span());
+        },
+    )
+    .build()
+}
+
+#[tokio::test]
+async fn local_subscriptions_snapshot_is_not_affected_by_later_changes() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic_a = new_test_topic();
+    let pubsub_topic_b = new_test_topic();
+
+    let node_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+
+    let mut node = new_test_node(&node_key, Default::default());
+
+    node.behaviour_mut()
+        .subscribe(pubsub_topic_a.clone())
+        .expect("subscribe to topic");
+    node.behaviour_mut()
+        .subscribe(pubsub_topic_b.clone())
+        .expect("subscribe to topic");
+
+    // Process the subscriptions, instead of polling the node for a fixed period of time.
+    let drained = poll_fn(|cx| Poll::Ready(node.behaviour_mut().drain_to_completion(cx, 8))).await;
+    assert!(drained, "Node queued events should be drained");
+
+    let subscriptions_before = node.behaviour().subscriptions_owned();
+    let snapshot_before = node.behaviour().topology_snapshot();
+
+    //// When
+    node.behaviour_mut()
+        .unsubscribe(&pubsub_topic_a)
+        .expect("unsubscribe from topic");
+
+    // Process the subscriptions, instead of polling the node for a fixed period of time.
+    let drained = poll_fn(|cx| Poll::Ready(node.behaviour_mut().drain_to_completion(cx, 8))).await;
+    assert!(drained, "Node queued events should be drained");
+
+    let snapshot_after = node.behaviour().topology_snapshot();
+
+    //// Then
+    let topic_a = pubsub_topic_a.hash();
+    let topic_b = pubsub_topic_b.hash();
+
+    assert_eq!(subscriptions_before.len(), 2);
+    assert_eq!(snapshot_before.subscriptions(), &subscriptions_before);
+    assert!(
+        snapshot_before.subscriptions().contains(&topic_a),
+        "Previous snapshot should still contain Topic A"
+    );
+
+    assert_eq!(snapshot_after.subscriptions().len(), 1);
+    assert!(
+        !snapshot_after.subscriptions().contains(&topic_a),
+        "New snapshot should not contain Topic A"
+    );
+    assert!(
+        snapshot_after.subscriptions().contains(&topic_b),
+        "New snapshot should contain Topic B"
+    );
+    assert_eq!(
+        snapshot_after.subscriptions(),
+        &node.behaviour().subscriptions_owned()
+    );
+    assert!(
+        snapshot_after.peers().is_empty(),
+        "No peers should be connected"
+    );
+}
+
+#[tokio::test]
+async fn topology_snapshot_tracks_peers_and_their_subscriptions() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic_a = new_test_topic();
+    let pubsub_topic_b = new_test_topic();
+
+    let node_a_key = testlib::secp256k1_keypair(TEST_KEYPAIR_A);
+    let node_b_key = testlib::secp256k1_keypair(TEST_KEYPAIR_B);
+
+    let mut node_a = new_test_node(&node_a_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(&node_b_key, Default::default());
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    node_b
+        .behaviour_mut()
+        .subscribe(pubsub_topic_a.clone())
+        .expect("subscribe to topic");
+    node_b
+        .behaviour_mut()
+        .subscribe(pubsub_topic_b.clone())
+        .expect("subscribe to topic");
+
+    // Node B dial Node A
+    testlib::swarm::should_dial_address(&mut node_b, node_a_addr);
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    // Poll the network for a short period of time to allow the subscriptions to be exchanged.
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    let snapshot_before = node_a.behaviour().topology_snapshot();
+
+    //// When
+    node_b
+        .behaviour_mut()
+        .unsubscribe(&pubsub_topic_a)
+        .expect("unsubscribe from topic");
+
+    // Poll the network for a short period of time to allow the subscriptions to be exchanged.
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    let snapshot_after = node_a.behaviour().topology_snapshot();
+
+    //// Then
+    let topic_a = pubsub_topic_a.hash();
+    let topic_b = pubsub_topic_b.hash();
+    let node_b_peer_id = node_b.local_peer_id();
+
+    assert_eq!(
+        snapshot_before.peers().len(),
+        1,
+        "Only Node B should be connected"
+    );
+    assert_matches!(snapshot_before.peer(node_b_peer_id), Some(peer) => {
+        assert_eq!(peer.connections, 1);
+        assert!(peer.subscriptions.contains(&topic_a), "Node B should be subscribed to Topic A");
+        assert!(peer.subscriptions.contains(&topic_b), "Node B should be subscribed to Topic B");
+    });
+
+    assert_matches!(snapshot_after.peer(node_b_peer_id), Some(peer) => {
+        assert_eq!(peer.connections, 1);
+        assert!(!peer.subscriptions.contains(&topic_a), "Node B should not be subscribed to Topic A");
+        assert!(peer.subscriptions.contains(&topic_b), "Node B should be subscribed to Topic B");
+    });
+    assert_eq!(
+        snapshot_after
+            .peer(node_b_peer_id)
+            .map(|peer| &peer.subscriptions),
+        node_a
+            .behaviour()
+            .peer_subscriptions_owned(node_b_peer_id)
+            .as_ref(),
+    );
+}
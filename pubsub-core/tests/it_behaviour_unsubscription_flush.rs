@@ -0,0 +1,231 @@
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::{ConnectionId, NetworkBehaviour, SwarmBuilder, SwarmEvent, ToSwarm};
+use libp2p::Swarm;
+use rand::Rng;
+use tokio::time::timeout;
+use tracing_futures::Instrument;
+
+use libp2p_pubsub_core::{
+    Behaviour as PubsubBehaviour, Config, Event, FlushId, FlushReport, IdentTopic,
+};
+use pubsub_testlib::{connect, poll_all, NoopProtocol};
+use testlib::any_memory_addr;
+use testlib::keys::{TEST_KEYPAIR_A, TEST_KEYPAIR_B};
+
+mod pubsub_testlib;
+
+type Behaviour = PubsubBehaviour<NoopProtocol>;
+
+type HandlerEvent = pubsub_testlib::HandlerEvent<NoopProtocol>;
+
+type HandlerCommand = pubsub_testlib::HandlerCommand<NoopProtocol>;
+
+fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
+
+fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair);
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
+    SwarmBuilder::with_executor(
+        transport,
+        behaviour,
+        peer_id,
+        |fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+            tokio::spawn(fut.in_current_span());
+        },
+    )
+    .build()
+}
+
+/// Poll both nodes until Node A notifies the flushed unsubscription completion.
+async fn wait_for_unsubscription_flushed(
+    node_a: &mut Swarm<Behaviour>,
+    node_b: &mut Swarm<Behaviour>,
+) -> (FlushId, FlushReport) {
+    loop {
+        tokio::select! {
+            event = node_a.select_next_some() => {
+                if let SwarmEvent::Behaviour(Event::UnsubscriptionFlushed {
+                    flush_id,
+                    report,
+                    ..
+                }) = event
+                {
+                    return (flush_id, report);
+                }
+            },
+            _ = node_b.select_next_some() => {},
+        }
+    }
+}
+
+#[tokio::test]
+async fn unsubscription_is_flushed_to_all_healthy_peers_before_deadline() {
+    testlib::init_logger();
+
+    //// Given
+    let pubsub_topic_a = new_test_topic();
+    let pubsub_topic_b = new_test_topic();
+
+    let mut node_a = new_test_node(
+        &testlib::secp256k1_keypair(TEST_KEYPAIR_A),
+        Default::default(),
+    );
+    testlib::swarm::should_listen_on_address(&mut node_a, any_memory_addr());
+
+    let mut node_b = new_test_node(
+        &testlib::secp256k1_keypair(TEST_KEYPAIR_B),
+        Default::default(),
+    );
+    testlib::swarm::should_listen_on_address(&mut node_b, any_memory_addr());
+
+    let (node_a_addr, _node_b_addr) = timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_start_listening(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("listening to start");
+
+    for topic in [&pubsub_topic_a, &pubsub_topic_b] {
+        node_a
+            .behaviour_mut()
+            .subscribe(topic.clone())
+            .expect("subscribe to topic");
+    }
+
+    node_b.dial(node_a_addr).expect("dial to succeed");
+    timeout(
+        Duration::from_secs(5),
+        testlib::swarm::wait_for_connection_establishment(&mut node_b, &mut node_a),
+    )
+    .await
+    .expect("Node B to connect to Node A");
+
+    // Wait for pub-sub network to establish
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    let node_a_peer_id = *node_a.local_peer_id();
+    let node_b_peer_id = *node_b.local_peer_id();
+
+    //// When
+    let requested_flush_id = node_a.behaviour_mut().unsubscribe_many_and_flush(
+        [pubsub_topic_a.hash(), pubsub_topic_b.hash()],
+        Duration::from_secs(5),
+    );
+
+    let (flush_id, report) = timeout(
+        Duration::from_secs(2),
+        wait_for_unsubscription_flushed(&mut node_a, &mut node_b),
+    )
+    .await
+    .expect("unsubscription flush to complete before the deadline");
+
+    // Let Node B process the unsubscription frame.
+    testlib::swarm::poll_mesh(Duration::from_millis(10), &mut node_a, &mut node_b).await;
+
+    //// Then
+    assert_eq!(flush_id, requested_flush_id);
+    assert_eq!(
+        report,
+        FlushReport::new(BTreeSet::from([node_b_peer_id]), BTreeSet::new())
+    );
+    assert!(node_a.behaviour().subscriptions().is_empty());
+    assert!(node_b
+        .behaviour()
+        .peer_subscriptions(&node_a_peer_id)
+        .map_or(true, |topics| topics.is_empty()));
+}
+
+#[test]
+fn peer_with_stuck_sink_is_reported_as_failed_at_deadline() {
+    testlib::init_logger();
+
+    //// Given
+    let topic = new_test_topic();
+    let healthy_peer = PeerId::random();
+    let stuck_peer = PeerId::random();
+
+    let mut behaviour = Behaviour::new(PeerId::random(), Default::default(), Default::default());
+    behaviour
+        .subscribe(topic.clone())
+        .expect("subscribe to topic");
+
+    let _healthy_handler = connect(&mut behaviour, healthy_peer, ConnectionId::new_unchecked(1));
+    let _stuck_handler = connect(&mut behaviour, stuck_peer, ConnectionId::new_unchecked(2));
+    poll_all(&mut behaviour);
+
+    //// When
+    let flush_id = behaviour.unsubscribe_many_and_flush([topic.hash()], Duration::from_millis(100));
+    let sent_events = poll_all(&mut behaviour);
+
+    // Only the healthy peer connection handler reports the tagged frame flushed. The stuck peer
+    // connection handler sink never completes the send.
+    let tagged_frames = sent_events
+        .iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerCommand::SendTaggedFrame { tag, .. },
+                ..
+            } => Some((*peer_id, *tag)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    behaviour.on_connection_handler_event(
+        healthy_peer,
+        ConnectionId::new_unchecked(1),
+        HandlerEvent::TaggedFrameSent {
+            tag: flush_id.as_u64(),
+        },
+    );
+    let events_before_deadline = poll_all(&mut behaviour);
+
+    std::thread::sleep(Duration::from_millis(150));
+    let events_after_deadline = poll_all(&mut behaviour);
+
+    //// Then
+    assert_eq!(
+        tagged_frames.len(),
+        2,
+        "A tagged frame should be sent per peer"
+    );
+    assert!(tagged_frames
+        .iter()
+        .all(|(_, tag)| *tag == flush_id.as_u64()));
+
+    assert!(
+        !events_before_deadline.iter().any(|ev| matches!(
+            ev,
+            ToSwarm::GenerateEvent(Event::UnsubscriptionFlushed { .. })
+        )),
+        "The flush should not complete before the deadline"
+    );
+
+    let reports = events_after_deadline
+        .into_iter()
+        .filter_map(|ev| match ev {
+            ToSwarm::GenerateEvent(Event::UnsubscriptionFlushed {
+                flush_id, report, ..
+            }) => Some((flush_id, report)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reports,
+        [(
+            flush_id,
+            FlushReport::new(BTreeSet::from([healthy_peer]), BTreeSet::from([stuck_peer]))
+        )]
+    );
+}
@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::Swarm;
+
+use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+use libp2p_pubsub_core::protocol::{
+    Protocol, ProtocolRouterConnectionEvent, ProtocolRouterInEvent, ProtocolRouterMessageEvent,
+    ProtocolRouterOutEvent,
+};
+use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
+use libp2p_pubsub_core::{Behaviour, Config};
+
+use super::swarm::new_test_swarm;
+
+/// The protocol ID for the broadcast protocol.
+pub const BROADCAST_PROTOCOL_ID: &str = "/broadcast/1.0.0";
+
+/// A protocol sending the published messages to all the connected peers, regardless of their
+/// subscriptions.
+///
+/// If rebroadcasting, the received messages are sent to all the connected peers too, including
+/// the message propagation source.
+#[derive(Default)]
+pub struct BroadcastProtocol {
+    rebroadcast: bool,
+}
+
+impl BroadcastProtocol {
+    /// Create a broadcast protocol sending the received messages to all the connected peers too.
+    pub fn rebroadcasting() -> Self {
+        Self { rebroadcast: true }
+    }
+}
+
+impl Protocol for BroadcastProtocol {
+    type Upgrade = SimpleProtocolUpgrade<&'static str>;
+    type RouterService = BroadcastProtocolRouter;
+
+    fn upgrade() -> Self::Upgrade {
+        SimpleProtocolUpgrade::new(BROADCAST_PROTOCOL_ID)
+    }
+
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
+        BroadcastProtocolRouter {
+            rebroadcast: self.rebroadcast,
+            peers: Default::default(),
+        }
+    }
+}
+
+/// The pubsub protocol router service for the broadcast protocol.
+pub struct BroadcastProtocolRouter {
+    rebroadcast: bool,
+    peers: HashSet<PeerId>,
+}
+
+impl EventHandler for BroadcastProtocolRouter {
+    type InEvent = ProtocolRouterInEvent;
+    type OutEvent = ProtocolRouterOutEvent;
+
+    fn on_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
+        ev: Self::InEvent,
+    ) {
+        match ev {
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerConnected(peer),
+            ) => {
+                self.peers.insert(peer);
+            }
+            ProtocolRouterInEvent::ConnectionEvent(
+                ProtocolRouterConnectionEvent::PeerDisconnected(peer),
+            ) => {
+                self.peers.remove(&peer);
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessagePublished {
+                message,
+                ..
+            }) => {
+                svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage {
+                    dest: self.peers.iter().copied().collect(),
+                    message,
+                });
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessageReceived {
+                message,
+                ..
+            }) if self.rebroadcast => {
+                svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage {
+                    dest: self.peers.iter().copied().collect(),
+                    message,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Create a broadcast protocol test node, with the given configuration.
+pub fn new_broadcast_test_node(
+    keypair: &Keypair,
+    config: Config,
+) -> Swarm<Behaviour<BroadcastProtocol>> {
+    let peer_id = PeerId::from(keypair.public());
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
+    new_test_swarm(keypair, behaviour)
+}
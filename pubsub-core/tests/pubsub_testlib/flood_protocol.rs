@@ -0,0 +1,129 @@
+use std::collections::{BTreeSet, HashMap};
+
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::Swarm;
+
+use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
+use libp2p_pubsub_core::protocol::{
+    Protocol, ProtocolRouterInEvent, ProtocolRouterMessageEvent, ProtocolRouterOutEvent,
+    ProtocolRouterSubscriptionEvent,
+};
+use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
+use libp2p_pubsub_core::{Behaviour, Config, TopicHash};
+
+use super::swarm::new_test_swarm;
+
+/// The protocol ID for the flood protocol.
+pub const FLOOD_PROTOCOL_ID: &str = "/flood/1.0.0";
+
+/// A protocol flooding the published and received messages to the subscribed peers.
+#[derive(Default)]
+pub struct FloodProtocol;
+
+impl Protocol for FloodProtocol {
+    type Upgrade = SimpleProtocolUpgrade<&'static str>;
+    type RouterService = FloodProtocolRouter;
+
+    fn upgrade() -> Self::Upgrade {
+        SimpleProtocolUpgrade::new(FLOOD_PROTOCOL_ID)
+    }
+
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
+        Default::default()
+    }
+}
+
+/// The pubsub protocol router service for the flood protocol.
+///
+/// The received messages are forwarded to the subscribed peers, except the message propagator
+/// and the excluded peers.
+#[derive(Default)]
+pub struct FloodProtocolRouter {
+    peers_subscriptions: HashMap<PeerId, BTreeSet<TopicHash>>,
+}
+
+impl FloodProtocolRouter {
+    fn subscribed_peers(&self, topic: &TopicHash) -> Vec<PeerId> {
+        self.peers_subscriptions
+            .iter()
+            .filter(|(_, topics)| topics.contains(topic))
+            .map(|(peer, _)| *peer)
+            .collect()
+    }
+}
+
+impl EventHandler for FloodProtocolRouter {
+    type InEvent = ProtocolRouterInEvent;
+    type OutEvent = ProtocolRouterOutEvent;
+
+    fn on_event<'a>(
+        &mut self,
+        svc_cx: &mut impl OnEventCtx<'a, Self::OutEvent>,
+        ev: Self::InEvent,
+    ) {
+        match ev {
+            ProtocolRouterInEvent::SubscriptionEvent(
+                ProtocolRouterSubscriptionEvent::PeerSubscribed { peer, topic },
+            ) => {
+                self.peers_subscriptions
+                    .entry(peer)
+                    .or_default()
+                    .insert(topic);
+            }
+            ProtocolRouterInEvent::SubscriptionEvent(
+                ProtocolRouterSubscriptionEvent::PeerSubscribedMany { peer, topics },
+            ) => {
+                self.peers_subscriptions
+                    .entry(peer)
+                    .or_default()
+                    .extend(topics);
+            }
+            ProtocolRouterInEvent::SubscriptionEvent(
+                ProtocolRouterSubscriptionEvent::PeerUnsubscribed { peer, topic },
+            ) => {
+                if let Some(topics) = self.peers_subscriptions.get_mut(&peer) {
+                    topics.remove(&topic);
+                }
+            }
+            ProtocolRouterInEvent::SubscriptionEvent(
+                ProtocolRouterSubscriptionEvent::PeerUnsubscribedMany { peer, topics },
+            ) => {
+                if let Some(subscriptions) = self.peers_subscriptions.get_mut(&peer) {
+                    for topic in &topics {
+                        subscriptions.remove(topic);
+                    }
+                }
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessagePublished {
+                message,
+                ..
+            }) => {
+                svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage {
+                    dest: self.subscribed_peers(&message.topic()),
+                    message,
+                });
+            }
+            ProtocolRouterInEvent::MessageEvent(ProtocolRouterMessageEvent::MessageReceived {
+                src,
+                message,
+                exclude,
+                ..
+            }) => {
+                let dest = self
+                    .subscribed_peers(&message.topic())
+                    .into_iter()
+                    .filter(|peer| peer != &src && !exclude.contains(peer))
+                    .collect();
+                svc_cx.emit(ProtocolRouterOutEvent::ForwardMessage { dest, message });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Create a flood protocol test node, with the given configuration.
+pub fn new_test_node(keypair: &Keypair, config: Config) -> Swarm<Behaviour<FloodProtocol>> {
+    let peer_id = PeerId::from(keypair.public());
+    let behaviour = Behaviour::new(peer_id, config, Default::default());
+    new_test_swarm(keypair, behaviour)
+}
@@ -1,3 +1,16 @@
+// Each test crate uses a subset of the shared test helpers.
+#![allow(dead_code, unused_imports)]
+
+pub use broadcast_protocol::*;
+pub use flood_protocol::*;
 pub use noop_protocol::*;
+pub use poll_harness::*;
+pub use swarm::*;
+pub use topic::*;
 
+mod broadcast_protocol;
+mod flood_protocol;
 mod noop_protocol;
+mod poll_harness;
+mod swarm;
+mod topic;
@@ -1,3 +1,5 @@
+use libp2p::identity::PeerId;
+
 use libp2p_pubsub_common::service::{EventHandler, OnEventCtx};
 use libp2p_pubsub_core::protocol::{Protocol, ProtocolRouterInEvent, ProtocolRouterOutEvent};
 use libp2p_pubsub_core::upgrade::SimpleProtocolUpgrade;
@@ -17,7 +19,7 @@ impl Protocol for NoopProtocol {
         SimpleProtocolUpgrade::new(NOOP_PROTOCOL_ID)
     }
 
-    fn router(&self) -> Self::RouterService {
+    fn router(&self, _local_peer_id: PeerId) -> Self::RouterService {
         Default::default()
     }
 }
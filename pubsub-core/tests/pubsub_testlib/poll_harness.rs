@@ -0,0 +1,152 @@
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::task::noop_waker_ref;
+use libp2p::core::ConnectedPoint;
+use libp2p::identity::PeerId;
+use libp2p::swarm::behaviour::{ConnectionClosed, ConnectionEstablished};
+use libp2p::swarm::{
+    ConnectionId, FromSwarm, NetworkBehaviour, PollParameters, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p::Multiaddr;
+use prost::Message as _;
+
+use libp2p_pubsub_core::protocol::Protocol;
+use libp2p_pubsub_core::{Behaviour, Frame};
+use libp2p_pubsub_proto::pubsub::FrameProto;
+
+/// The events emitted by the behaviour to the swarm.
+pub type BehaviourEvent<P> =
+    ToSwarm<<Behaviour<P> as NetworkBehaviour>::ToSwarm, THandlerInEvent<Behaviour<P>>>;
+
+/// The events emitted by the behaviour connection handlers.
+pub type HandlerEvent<P> = THandlerOutEvent<Behaviour<P>>;
+
+/// The commands sent by the behaviour to its connection handlers.
+pub type HandlerCommand<P> = THandlerInEvent<Behaviour<P>>;
+
+/// A [`PollParameters`] implementation to poll the behaviour outside of a swarm.
+pub struct TestPollParameters;
+
+impl PollParameters for TestPollParameters {
+    type SupportedProtocolsIter = std::iter::Empty<Vec<u8>>;
+
+    fn supported_protocols(&self) -> Self::SupportedProtocolsIter {
+        std::iter::empty()
+    }
+}
+
+/// The endpoint of the simulated inbound connections.
+pub fn new_test_endpoint() -> ConnectedPoint {
+    ConnectedPoint::Listener {
+        local_addr: "/memory/1".parse::<Multiaddr>().unwrap(),
+        send_back_addr: "/memory/2".parse::<Multiaddr>().unwrap(),
+    }
+}
+
+/// Simulate the establishment of the first inbound connection with the given peer.
+pub fn connect<P: Protocol + 'static>(
+    behaviour: &mut Behaviour<P>,
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+) -> THandler<Behaviour<P>> {
+    connect_with_established(behaviour, peer_id, connection_id, 0)
+}
+
+/// Simulate the establishment of an inbound connection with the given peer, with
+/// `other_established` connections already established with it.
+pub fn connect_with_established<P: Protocol + 'static>(
+    behaviour: &mut Behaviour<P>,
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    other_established: usize,
+) -> THandler<Behaviour<P>> {
+    let endpoint = new_test_endpoint();
+    let handler = behaviour
+        .handle_established_inbound_connection(
+            connection_id,
+            peer_id,
+            endpoint.get_remote_address(),
+            endpoint.get_remote_address(),
+        )
+        .expect("connection to be accepted");
+
+    behaviour.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+        peer_id,
+        connection_id,
+        endpoint: &endpoint,
+        failed_addresses: &[],
+        other_established,
+    }));
+
+    handler
+}
+
+/// Simulate the closing of the last connection with the given peer.
+pub fn disconnect<P: Protocol + 'static>(
+    behaviour: &mut Behaviour<P>,
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    handler: THandler<Behaviour<P>>,
+) {
+    disconnect_with_remaining(behaviour, peer_id, connection_id, handler, 0);
+}
+
+/// Simulate the closing of a connection with the given peer, with `remaining_established`
+/// connections still established with it.
+pub fn disconnect_with_remaining<P: Protocol + 'static>(
+    behaviour: &mut Behaviour<P>,
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    handler: THandler<Behaviour<P>>,
+    remaining_established: usize,
+) {
+    let endpoint = new_test_endpoint();
+    behaviour.on_swarm_event(FromSwarm::ConnectionClosed(ConnectionClosed {
+        peer_id,
+        connection_id,
+        endpoint: &endpoint,
+        handler,
+        remaining_established,
+    }));
+}
+
+/// Simulate the reception of the given frame over the given connection.
+pub fn receive_frame<P: Protocol + 'static>(
+    behaviour: &mut Behaviour<P>,
+    peer_id: PeerId,
+    connection_id: ConnectionId,
+    frame: Frame,
+) {
+    let frame = FrameProto::from(frame);
+    behaviour.on_connection_handler_event(
+        peer_id,
+        connection_id,
+        HandlerEvent::<P>::FrameReceived(Bytes::from(frame.encode_to_vec())),
+    );
+}
+
+/// Poll the behaviour once.
+pub fn poll_once<P: Protocol + 'static>(behaviour: &mut Behaviour<P>) -> Poll<BehaviourEvent<P>> {
+    let mut cx = Context::from_waker(noop_waker_ref());
+    behaviour.poll(&mut cx, &mut TestPollParameters)
+}
+
+/// Poll the behaviour until it returns `Poll::Pending` and collect the emitted events.
+pub fn poll_all<P: Protocol + 'static>(behaviour: &mut Behaviour<P>) -> Vec<BehaviourEvent<P>> {
+    let mut events = Vec::new();
+    while let Poll::Ready(event) = poll_once(behaviour) {
+        events.push(event);
+    }
+    events
+}
+
+/// Poll the behaviour until it returns `Poll::Pending`, a few times, and collect the emitted
+/// events.
+///
+/// The received frames go through several services, some of them polled before the service
+/// feeding them. As the test waker does not reschedule the behaviour, it is polled a few times.
+pub fn poll_settled<P: Protocol + 'static>(behaviour: &mut Behaviour<P>) -> Vec<BehaviourEvent<P>> {
+    (0..3).flat_map(|_| poll_all(behaviour)).collect()
+}
@@ -0,0 +1,22 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use libp2p::identity::{Keypair, PeerId};
+use libp2p::swarm::{self, Swarm};
+use tracing_futures::Instrument;
+
+use libp2p_pubsub_core::protocol::Protocol;
+use libp2p_pubsub_core::Behaviour;
+
+/// Create a test node, running the given behaviour.
+pub fn new_test_swarm<P: Protocol + 'static>(
+    keypair: &Keypair,
+    behaviour: Behaviour<P>,
+) -> Swarm<Behaviour<P>> {
+    let peer_id = PeerId::from(keypair.public());
+    let transport = testlib::test_transport(keypair);
+    let config = swarm::Config::with_executor(|fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+        tokio::spawn(fut.in_current_span());
+    });
+    Swarm::new(transport, behaviour, peer_id, config)
+}
@@ -0,0 +1,11 @@
+use rand::Rng;
+
+use libp2p_pubsub_core::IdentTopic;
+
+/// Create a new test topic, with a random suffix.
+pub fn new_test_topic() -> IdentTopic {
+    IdentTopic::new(format!(
+        "/pubsub/2/it-pubsub-test-{}",
+        rand::thread_rng().gen::<u32>()
+    ))
+}
@@ -0,0 +1,277 @@
+//! Helpers to drive [`ConnectionHandler`] implementations in unit tests, without a swarm.
+//!
+//! The [`MockSubstream`] implements [`AsyncRead`] and [`AsyncWrite`] with a scriptable behavior.
+//! Handlers generic over their substream type can be fed mock substreams via the
+//! [`fully_negotiated_inbound`] and [`fully_negotiated_outbound`] connection events, and polled
+//! with [`drive`].
+
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use futures::{AsyncRead, AsyncWrite};
+use libp2p::swarm::handler::{
+    ConnectionEvent, FullyNegotiatedInbound, FullyNegotiatedOutbound, InboundUpgradeSend,
+    OutboundUpgradeSend,
+};
+use libp2p::swarm::{ConnectionHandler, ConnectionHandlerEvent};
+
+use crate::service::noop_context;
+
+/// The events emitted by a [`ConnectionHandler`].
+pub type HandlerEvent<H> = ConnectionHandlerEvent<
+    <H as ConnectionHandler>::OutboundProtocol,
+    <H as ConnectionHandler>::OutboundOpenInfo,
+    <H as ConnectionHandler>::ToBehaviour,
+    <H as ConnectionHandler>::Error,
+>;
+
+/// The connection events consumed by a [`ConnectionHandler`].
+pub type HandlerConnectionEvent<'a, H> = ConnectionEvent<
+    'a,
+    <H as ConnectionHandler>::InboundProtocol,
+    <H as ConnectionHandler>::OutboundProtocol,
+    <H as ConnectionHandler>::InboundOpenInfo,
+    <H as ConnectionHandler>::OutboundOpenInfo,
+>;
+
+/// The scripted behavior of one direction (read or write) of a [`MockSubstream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MockBehavior {
+    /// IO operations complete immediately. Reads return `Pending` if no data is available.
+    #[default]
+    Ready,
+    /// IO operations never complete.
+    Pending,
+    /// IO operations complete immediately until `n` bytes were transferred. After that, they fail
+    /// with an IO error.
+    ErrorAfter(usize),
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    /// The read direction behavior.
+    read_behavior: MockBehavior,
+    /// The bytes available to be read.
+    read_buf: VecDeque<u8>,
+    /// The number of bytes read so far.
+    read_count: usize,
+    /// Whether the remote closed its write side (i.e., reads return EOF once the buffer is empty).
+    read_eof: bool,
+    /// The read task waker, if a read returned `Pending`.
+    read_waker: Option<Waker>,
+
+    /// The write direction behavior.
+    write_behavior: MockBehavior,
+    /// The bytes written so far.
+    written: Vec<u8>,
+    /// The number of flush calls that completed successfully.
+    flush_count: usize,
+    /// Whether the substream was closed.
+    closed: bool,
+}
+
+/// A mock substream implementing [`AsyncRead`] and [`AsyncWrite`] with a scriptable behavior.
+///
+/// Clones share the same state, so a test can keep a handle to inject inbound data and inspect the
+/// written bytes after handing the substream over to the connection handler.
+#[derive(Debug, Clone, Default)]
+pub struct MockSubstream {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockSubstream {
+    /// Create a new mock substream whose read and write directions are [`MockBehavior::Ready`].
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock substream lock poisoned")
+    }
+
+    /// Set the read direction behavior.
+    pub fn with_read_behavior(self, behavior: MockBehavior) -> Self {
+        self.set_read_behavior(behavior);
+        self
+    }
+
+    /// Set the write direction behavior.
+    pub fn with_write_behavior(self, behavior: MockBehavior) -> Self {
+        self.set_write_behavior(behavior);
+        self
+    }
+
+    /// Set the read direction behavior.
+    pub fn set_read_behavior(&self, behavior: MockBehavior) {
+        let mut state = self.state();
+        state.read_behavior = behavior;
+        if let Some(waker) = state.read_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Set the write direction behavior.
+    pub fn set_write_behavior(&self, behavior: MockBehavior) {
+        self.state().write_behavior = behavior;
+    }
+
+    /// Make the given bytes available to be read from the substream.
+    pub fn push_read(&self, bytes: impl AsRef<[u8]>) {
+        let mut state = self.state();
+        state.read_buf.extend(bytes.as_ref());
+        if let Some(waker) = state.read_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Signal the end of the inbound data. Once the buffered bytes are consumed, reads return EOF.
+    pub fn close_read(&self) {
+        let mut state = self.state();
+        state.read_eof = true;
+        if let Some(waker) = state.read_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// The bytes written to the substream so far.
+    pub fn written(&self) -> Vec<u8> {
+        self.state().written.clone()
+    }
+
+    /// The number of completed flush calls.
+    pub fn flush_count(&self) -> usize {
+        self.state().flush_count
+    }
+
+    /// Whether the substream was closed.
+    pub fn is_closed(&self) -> bool {
+        self.state().closed
+    }
+}
+
+/// Build the error returned by the mock substream IO operations.
+fn mock_error() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionReset, "mock substream error")
+}
+
+impl AsyncRead for MockSubstream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut state = self.state();
+
+        let max_len = match state.read_behavior {
+            MockBehavior::Pending => {
+                state.read_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            MockBehavior::ErrorAfter(n) if state.read_count >= n => {
+                return Poll::Ready(Err(mock_error()));
+            }
+            MockBehavior::ErrorAfter(n) => buf.len().min(n - state.read_count),
+            MockBehavior::Ready => buf.len(),
+        };
+
+        if state.read_buf.is_empty() {
+            if state.read_eof {
+                return Poll::Ready(Ok(0));
+            }
+
+            state.read_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let len = max_len.min(state.read_buf.len());
+        for (dst, src) in buf.iter_mut().zip(state.read_buf.drain(..len)) {
+            *dst = src;
+        }
+        state.read_count += len;
+
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for MockSubstream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut state = self.state();
+
+        let len = match state.write_behavior {
+            MockBehavior::Pending => return Poll::Pending,
+            MockBehavior::ErrorAfter(n) if state.written.len() >= n => {
+                return Poll::Ready(Err(mock_error()));
+            }
+            MockBehavior::ErrorAfter(n) => buf.len().min(n - state.written.len()),
+            MockBehavior::Ready => buf.len(),
+        };
+
+        state.written.extend_from_slice(&buf[..len]);
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut state = self.state();
+
+        match state.write_behavior {
+            MockBehavior::Pending => Poll::Pending,
+            MockBehavior::ErrorAfter(n) if state.written.len() >= n => {
+                Poll::Ready(Err(mock_error()))
+            }
+            _ => {
+                state.flush_count += 1;
+                Poll::Ready(Ok(()))
+            }
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut state = self.state();
+
+        state.closed = true;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Build a [`ConnectionEvent::FullyNegotiatedInbound`] event with the given upgrade output.
+pub fn fully_negotiated_inbound<'a, H>(
+    protocol: <H::InboundProtocol as InboundUpgradeSend>::Output,
+    info: H::InboundOpenInfo,
+) -> HandlerConnectionEvent<'a, H>
+where
+    H: ConnectionHandler,
+{
+    ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound { protocol, info })
+}
+
+/// Build a [`ConnectionEvent::FullyNegotiatedOutbound`] event with the given upgrade output.
+pub fn fully_negotiated_outbound<'a, H>(
+    protocol: <H::OutboundProtocol as OutboundUpgradeSend>::Output,
+    info: H::OutboundOpenInfo,
+) -> HandlerConnectionEvent<'a, H>
+where
+    H: ConnectionHandler,
+{
+    ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound { protocol, info })
+}
+
+/// Poll the connection handler `n_polls` times and collect the emitted events.
+pub fn drive<H>(handler: &mut H, n_polls: usize) -> Vec<HandlerEvent<H>>
+where
+    H: ConnectionHandler,
+{
+    let mut cx = noop_context();
+    (0..n_polls)
+        .filter_map(|_| match handler.poll(&mut cx) {
+            Poll::Ready(event) => Some(event),
+            Poll::Pending => None,
+        })
+        .collect()
+}
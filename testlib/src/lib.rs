@@ -4,6 +4,7 @@ use tracing_subscriber::EnvFilter;
 pub use keys::secp256k1_keypair;
 pub use transport::*;
 
+pub mod handler;
 pub mod keys;
 pub mod service;
 pub mod swarm;
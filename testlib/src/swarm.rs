@@ -1,7 +1,9 @@
 use std::fmt::Debug;
+use std::task::Poll;
 use std::time::Duration;
 
 use assert_matches::assert_matches;
+use futures::future::poll_fn;
 use futures::StreamExt;
 use libp2p::core::transport::ListenerId;
 use libp2p::swarm::{ConnectionHandler, NetworkBehaviour, SwarmEvent};
@@ -64,6 +66,56 @@ pub async fn poll_mesh<B1, E1, B2, E2>(
     }
 }
 
+/// Poll three different swarms for a given period of time.
+#[tracing::instrument(skip_all)]
+pub async fn poll_mesh3<B1, E1, B2, E2, B3, E3>(
+    duration: Duration,
+    swarm1: &mut Swarm<B1>,
+    swarm2: &mut Swarm<B2>,
+    swarm3: &mut Swarm<B3>,
+) where
+    B1: NetworkBehaviour<ToSwarm = E1>,
+    E1: Debug,
+    B2: NetworkBehaviour<ToSwarm = E2>,
+    E2: Debug,
+    B3: NetworkBehaviour<ToSwarm = E3>,
+    E3: Debug,
+{
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => break,
+            _ = poll(swarm1) => {},
+            _ = poll(swarm2) => {},
+            _ = poll(swarm3) => {},
+        }
+    }
+}
+
+/// Poll the node (swarm) for events for a given duration and collect them.
+#[tracing::instrument(skip_all, fields(swarm = % swarm.local_peer_id()))]
+pub async fn poll_node_and_collect_events<B, E>(
+    duration: Duration,
+    swarm: &mut Swarm<B>,
+) -> Vec<NetworkBehaviourSwarmEvent<B>>
+where
+    B: NetworkBehaviour<ToSwarm = E>,
+    E: Debug,
+{
+    let mut events = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => break,
+            event = swarm.select_next_some() => {
+                tracing::trace!(event = ?event);
+                events.push(event);
+            },
+        }
+    }
+
+    events
+}
+
 /// Poll the mesh's swarms for events for a given duration and collect them.
 #[tracing::instrument(skip_all)]
 pub async fn poll_mesh_and_collect_events<B1, E1, B2, E2>(
@@ -105,6 +157,94 @@ where
     (swarm1_events, swarm2_events)
 }
 
+/// Poll the mesh's swarms until the `predicate` holds or the `timeout` elapses.
+///
+/// The predicate is checked every time the swarms are polled. Returns `true` if the predicate
+/// holds, and `false` if the timeout elapsed.
+#[tracing::instrument(skip_all)]
+pub async fn poll_mesh_until<B, E>(
+    swarms: &mut [&mut Swarm<B>],
+    mut predicate: impl FnMut(&[&mut Swarm<B>]) -> bool,
+    timeout: Duration,
+) -> bool
+where
+    B: NetworkBehaviour<ToSwarm = E>,
+    E: Debug,
+{
+    let until = poll_fn(|cx| loop {
+        let mut progress = false;
+        for swarm in swarms.iter_mut() {
+            if let Poll::Ready(Some(event)) = swarm.poll_next_unpin(cx) {
+                tracing::trace!(swarm = %swarm.local_peer_id(), event = ?event);
+                progress = true;
+            }
+        }
+
+        // The behaviours state can change without emitting a swarm event, so the predicate is
+        // checked after every polling round.
+        if predicate(swarms) {
+            return Poll::Ready(());
+        }
+
+        if !progress {
+            return Poll::Pending;
+        }
+    });
+
+    tokio::time::timeout(timeout, until).await.is_ok()
+}
+
+/// Poll the swarm until the `is_subscribed` check on its behaviour holds or the `timeout` elapses,
+/// e.g., until the behaviour's peer subscriptions show a remote peer's topic subscription.
+///
+/// Returns `true` if the check holds, and `false` if the timeout elapsed.
+#[tracing::instrument(skip_all, fields(swarm = % swarm.local_peer_id()))]
+pub async fn wait_for_peer_subscription<B, E>(
+    swarm: &mut Swarm<B>,
+    mut is_subscribed: impl FnMut(&B) -> bool,
+    timeout: Duration,
+) -> bool
+where
+    B: NetworkBehaviour<ToSwarm = E>,
+    E: Debug,
+{
+    poll_mesh_until(
+        &mut [swarm],
+        |swarms| is_subscribed(swarms[0].behaviour()),
+        timeout,
+    )
+    .await
+}
+
+/// Poll the swarm until it emits a behaviour event matching the `predicate`, e.g., a message
+/// received event, or the `timeout` elapses.
+///
+/// Returns the matching behaviour event, or `None` if the timeout elapsed.
+#[tracing::instrument(skip_all, fields(swarm = % swarm.local_peer_id()))]
+pub async fn wait_for_message<B, E>(
+    swarm: &mut Swarm<B>,
+    mut predicate: impl FnMut(&E) -> bool,
+    timeout: Duration,
+) -> Option<E>
+where
+    B: NetworkBehaviour<ToSwarm = E>,
+    E: Debug,
+{
+    let wait = async {
+        loop {
+            let event = swarm.select_next_some().await;
+            tracing::trace!(event = ?event);
+            if let SwarmEvent::Behaviour(event) = event {
+                if predicate(&event) {
+                    return event;
+                }
+            }
+        }
+    };
+
+    tokio::time::timeout(timeout, wait).await.ok()
+}
+
 /// Listen on the given address and assert that the listen is successful.
 #[tracing::instrument(skip_all, fields(swarm = % swarm.local_peer_id()))]
 pub fn should_listen_on_address<B>(swarm: &mut Swarm<B>, addr: Multiaddr) -> ListenerId